@@ -17,6 +17,7 @@ User's model path:
 """
 DEFAULT_MODEL_PATH = r"C:\Users\hirog\OneDrive\Desktop\4. Models\Llama-3.1-8B-Lexi-Uncensored-V2-Q8_0.gguf"
 
+import json
 import logging
 import os
 from typing import Optional, Dict
@@ -187,7 +188,12 @@ def generate(
     mirostat_eta: float = 0.1,
     # Prefix caching
     cache_prompt: bool = False,
-    cache_key: Optional[str] = None
+    cache_key: Optional[str] = None,
+    # Reproducibility
+    seed: Optional[int] = None,
+    # Structured output
+    json_schema: Optional[Dict] = None,
+    logit_bias: Optional[Dict[int, float]] = None
 ) -> Optional[str]:
     """
     Generate text using in-process model with advanced sampling and prefix caching
@@ -219,6 +225,10 @@ def generate(
         Prefix Caching:
         cache_prompt: Whether to cache this prompt for reuse
         cache_key: Key for caching (e.g., "narrator_system_prompt")
+        
+        seed: Sampling seed; the same seed and settings give the same text
+        json_schema: JSON Schema the output must match (sampled with a grammar)
+        logit_bias: Bias added to token logits, by token id
     
     Returns:
         Generated text or None if model not loaded
@@ -283,6 +293,13 @@ def generate(
             params["mirostat_mode"] = mirostat_mode
             params["mirostat_tau"] = mirostat_tau
             params["mirostat_eta"] = mirostat_eta
+        if seed is not None:
+            params["seed"] = seed
+        if json_schema is not None:
+            from llama_cpp import LlamaGrammar
+            params["grammar"] = LlamaGrammar.from_json_schema(json.dumps(json_schema))
+        if logit_bias:
+            params["logit_bias"] = {int(token): bias for token, bias in logit_bias.items()}
         
         # Generate using in-process model
         result = _llm_instance(**params)
@@ -630,8 +647,8 @@ def generate_with_context(
         "\n\n\n"  # Stop on excessive newlines
     ]
     
-    # Override kwargs to include stop sequences
-    kwargs['stop'] = stop_sequences
+    # Add the caller's stop sequences to the built-in ones
+    kwargs['stop'] = stop_sequences + list(kwargs.get('stop') or [])
     
     # Generate with provided sampling parameters
     response = generate(
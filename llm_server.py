@@ -25,7 +25,14 @@ def generate():
         'top_p': data.get('top_p', 0.95),
         'top_k': data.get('top_k', 40),
         'max_tokens': data.get('max_tokens', 512),
+        'stop': data.get('stop') or [],
     }
+    if data.get('seed') is not None:
+        kwargs['seed'] = data['seed']
+    if data.get('json_schema') is not None:
+        kwargs['json_schema'] = data['json_schema']
+    if data.get('logit_bias'):
+        kwargs['logit_bias'] = data['logit_bias']
     
     # Generate response
     try:
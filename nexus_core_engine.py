@@ -326,6 +326,29 @@ class NexusCoreEngine:
             return content
 
 
+def delete_user_data(user_id: str, base_path: str = "./nexus_data") -> int:
+    """
+    Delete everything The Nexus Core stored for a user.
+
+    Logs aren't split by user (one install, one local user), so this clears
+    the conversation logs, sessions and search index under base_path.
+
+    Returns:
+        Number of files removed
+    """
+    import shutil
+
+    base = Path(base_path)
+    removed = 0
+    for folder in ("conversations", "sessions", "indices"):
+        path = base / folder
+        if not path.exists():
+            continue
+        removed += sum(1 for item in path.rglob("*") if item.is_file())
+        shutil.rmtree(path)
+    return removed
+
+
 if __name__ == "__main__":
     # Demo usage
     engine = NexusCoreEngine("./demo_nexus_data")
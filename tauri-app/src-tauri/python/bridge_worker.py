@@ -0,0 +1,154 @@
+"""
+AuraNexus Python bridge worker.
+
+Run by the Rust side (python_bridge.rs) as a child process. Speaks JSON-RPC
+2.0 over stdio, one JSON message per line:
+
+    initialize {"paths": [...], "operations": [{"name", "target", "params"}]}
+        -> {"available": [names], "failures": [{"operation", "error"}]}
+    call {"operation", "args", "kwargs", "stream"}
+        -> the function's return value; with "stream" and an iterator
+           result, each item is sent first as a "token" notification
+           {"id", "token"} and the joined text is returned
+    ping -> "pong"
+    shutdown -> null, then the worker exits
+
+A "cancel" notification {"id"} stops a streaming call after the current
+token. Requests run one at a time. Closing stdin (the app exiting) ends the
+worker. Everything the backend prints goes to stderr, so only protocol
+messages reach stdout.
+"""
+
+import importlib
+import inspect
+import json
+import os
+import queue
+import sys
+import threading
+import traceback
+
+# Keep the real stdout for the protocol and point fd 1 at stderr, so prints
+# from Python code and C extensions alike can't corrupt it
+PROTOCOL = os.fdopen(os.dup(1), "w", encoding="utf-8")
+os.dup2(2, 1)
+sys.stdout = sys.stderr
+
+_write_lock = threading.Lock()
+_requests = queue.Queue()
+_cancelled = set()
+_functions = {}
+
+
+def send(message):
+    line = json.dumps(message, default=str)
+    with _write_lock:
+        PROTOCOL.write(line + "\n")
+        PROTOCOL.flush()
+
+
+def read_stdin():
+    for line in sys.stdin:
+        line = line.strip()
+        if not line:
+            continue
+        try:
+            message = json.loads(line)
+        except ValueError as e:
+            send({"jsonrpc": "2.0", "id": None, "error": {"code": -32700, "message": str(e)}})
+            continue
+        if message.get("method") == "cancel":
+            _cancelled.add((message.get("params") or {}).get("id"))
+            continue
+        _requests.put(message)
+    # stdin closed: the app is gone
+    _requests.put(None)
+
+
+def missing_params(func, names):
+    try:
+        params = list(inspect.signature(func).parameters.values())
+    except (TypeError, ValueError):
+        return []  # No introspectable signature (e.g. C extensions)
+    if any(p.kind == p.VAR_KEYWORD for p in params):
+        return []
+    accepted = {p.name for p in params if p.kind != p.VAR_POSITIONAL}
+    return [name for name in names if name not in accepted]
+
+
+def initialize(params):
+    for path in reversed(params.get("paths", [])):
+        if path not in sys.path:
+            sys.path.insert(0, path)
+    available, failures = [], []
+    for operation in params.get("operations", []):
+        name, target = operation["name"], operation["target"]
+        try:
+            module, _, function = target.rpartition(".")
+            func = getattr(importlib.import_module(module), function)
+            if not callable(func):
+                raise TypeError(f"{target} is not callable")
+            missing = missing_params(func, operation.get("params", []))
+            if missing:
+                raise TypeError("does not accept parameter(s): " + ", ".join(missing))
+        except Exception as e:
+            failures.append({"operation": name, "error": f"{type(e).__name__}: {e}"})
+            continue
+        _functions[name] = func
+        available.append(name)
+    return {"available": available, "failures": failures}
+
+
+def call(request_id, params):
+    name = params["operation"]
+    func = _functions.get(name)
+    if func is None:
+        raise RuntimeError(f"Bridge operation '{name}' is not available")
+    result = func(*params.get("args", []), **params.get("kwargs", {}))
+    streamed = params.get("stream") and hasattr(result, "__iter__")
+    if not streamed or isinstance(result, (str, bytes, dict, list)):
+        return result
+    text = []
+    for token in result:
+        if request_id in _cancelled:
+            break
+        token = str(token)
+        send({"jsonrpc": "2.0", "method": "token", "params": {"id": request_id, "token": token}})
+        text.append(token)
+    return "".join(text)
+
+
+def main():
+    threading.Thread(target=read_stdin, daemon=True).start()
+    while True:
+        message = _requests.get()
+        if message is None:
+            break
+        request_id = message.get("id")
+        method = message.get("method")
+        params = message.get("params") or {}
+        if method == "shutdown":
+            send({"jsonrpc": "2.0", "id": request_id, "result": None})
+            break
+        try:
+            if method == "ping":
+                result = "pong"
+            elif method == "initialize":
+                result = initialize(params)
+            elif method == "call":
+                result = call(request_id, params)
+            else:
+                send({"jsonrpc": "2.0", "id": request_id,
+                      "error": {"code": -32601, "message": f"Unknown method {method}"}})
+                continue
+            send({"jsonrpc": "2.0", "id": request_id, "result": result})
+        except Exception as e:
+            traceback.print_exc()
+            send({"jsonrpc": "2.0", "id": request_id,
+                  "error": {"code": -32000, "message": f"{type(e).__name__}: {e}"}})
+        finally:
+            _cancelled.discard(request_id)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,702 @@
+// Backend Module - One interface over every way of running the LLM
+//
+// `LlmBackend` is implemented by the llm_server.py HTTP client, the embedded
+// Python bridge, the native llama.cpp `LlmManager` and the OpenAI-compatible
+// remote client. With the default `auto` setting `select_backend` picks the
+// first local one that works, in that order, so the app still answers when
+// the Python side is missing or its modules fail to import. `set_backend`
+// pins one instead (e.g. a remote API for machines without a GPU).
+// `load_model_by_path` swaps the GGUF the local backends run without a
+// restart. The backend sits behind an async lock (`SharedBackend`): work on
+// it runs in `spawn_blocking` with `blocking_lock`, and commands that only
+// need it briefly await it, so waiting never ties up the async runtime.
+// It is started in the background at launch; when nothing can start, the UI
+// opens in a setup-required state (`backend-status` events) and
+// `retry_backend_init` tries again.
+
+use crate::capabilities::{self, Feature, ModelCapabilities};
+use crate::errors::AppError;
+use crate::generation::CancellationToken;
+use crate::http_backend::{BackendTimeouts, Completion, GenerationStats, HttpBackend};
+use crate::llm::LlmManager;
+use crate::logit_bias;
+use crate::openai_backend::{OpenAiBackend, RemoteSettings};
+use crate::prompt_budget;
+use crate::python_bridge::{self, BridgeStatus, PythonBridge};
+use crate::settings::AppSettings;
+use crate::tokenizer::count_tokens;
+use crate::{AppState, ConversationEntry, LlmConfig};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::Manager;
+use tracing::{error, info, warn};
+
+/// Guards against overlapping model loads
+static LOADING_MODEL: AtomicBool = AtomicBool::new(false);
+
+/// The chat backend, shared by commands (`None` until first needed)
+pub type SharedBackend = Arc<tokio::sync::Mutex<Option<Box<dyn LlmBackend>>>>;
+
+/// Everything a backend needs to produce one reply
+pub struct GenerationRequest<'a> {
+    pub prompt: &'a str,
+    pub system_prompt: &'a str,
+    pub history: &'a [ConversationEntry],
+    pub config: &'a LlmConfig,
+}
+
+/// A way of generating text
+///
+/// Implementations stream tokens to `on_token` (which returns `false` to stop)
+/// and return early with the text so far once `cancel` is cancelled.
+pub trait LlmBackend: Send {
+    /// Short name reported in `GenerationStats::backend`
+    fn name(&self) -> &'static str;
+
+    fn generate(
+        &mut self,
+        request: &GenerationRequest,
+        cancel: &CancellationToken,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion>;
+
+    /// Switch to the GGUF at `path` in place; `false` if this backend has to
+    /// be torn down and replaced instead
+    fn switch_model(&mut self, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Delete whatever the backend itself keeps for `user_id` (the Python
+    /// backend's Nexus Core log); `None` if it keeps nothing
+    fn delete_user_data(&mut self, _user_id: &str) -> Result<Option<usize>> {
+        Ok(None)
+    }
+}
+
+/// Which backend chat uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// First that works: llm_server.py, Python subprocess, native
+    #[default]
+    Auto,
+    LlmServer,
+    Python,
+    Native,
+    /// OpenAI-compatible API at `remote.base_url`
+    Remote,
+}
+
+/// `[backend]` in settings.toml
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub kind: BackendKind,
+    pub remote: RemoteSettings,
+    /// GGUF the local backends load (the first one found when unset)
+    pub model_path: Option<PathBuf>,
+}
+
+impl BackendSettings {
+    pub fn load() -> Self {
+        AppSettings::load().backend
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::update(|settings| settings.backend = self.clone()).map(|_| ())
+    }
+}
+
+/// `backend` in settings.toml: the table, or only the kind as in files
+/// written before the rest moved there
+pub fn deserialize_settings<'de, D>(deserializer: D) -> std::result::Result<BackendSettings, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Kind(BackendKind),
+        Settings(BackendSettings),
+    }
+    Ok(match Entry::deserialize(deserializer)? {
+        Entry::Kind(kind) => BackendSettings {
+            kind,
+            ..Default::default()
+        },
+        Entry::Settings(settings) => settings,
+    })
+}
+
+/// Backend settings as shown to the frontend (the API key is never sent back)
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendInfo {
+    pub kind: BackendKind,
+    pub base_url: String,
+    pub model: Option<String>,
+    pub has_api_key: bool,
+    /// GGUF picked with `load_model_by_path`
+    pub model_path: Option<String>,
+    /// Backend answering chat right now, if one has been picked
+    pub active: Option<String>,
+}
+
+/// The configured backend, or the first usable one with `auto`
+///
+/// A pinned backend that fails to load falls back to `auto` rather than
+/// leaving chat without one. If nothing loads, the HTTP client is used so
+/// llm_server.py can still be started later.
+pub fn select_backend(on_python_status: &dyn Fn(&BridgeStatus)) -> Box<dyn LlmBackend> {
+    match try_select_backend(on_python_status) {
+        Ok(backend) => {
+            set_status(BackendStatus::Ready {
+                backend: backend.name().to_string(),
+            });
+            backend
+        }
+        Err(e) => {
+            error!("{:#}; waiting for llm_server.py", e);
+            set_status(BackendStatus::SetupRequired { error: startup_error(&e) });
+            Box::new(HttpBackend::new(crate::http_backend::DEFAULT_SERVER_URL).expect("HTTP client"))
+        }
+    }
+}
+
+/// Like `select_backend`, but fails when no backend can be started
+pub fn try_select_backend(on_python_status: &dyn Fn(&BridgeStatus)) -> Result<Box<dyn LlmBackend>> {
+    let settings = BackendSettings::load();
+    let pinned = match settings.kind {
+        BackendKind::Auto => return auto_backend(on_python_status),
+        BackendKind::LlmServer => HttpBackend::local().map(boxed),
+        BackendKind::Python => python_bridge::start(on_python_status).map(boxed),
+        BackendKind::Native => LlmManager::new().map(boxed),
+        BackendKind::Remote => OpenAiBackend::new(settings.remote, BackendTimeouts::load()).map(boxed),
+    };
+    match pinned {
+        Ok(backend) => {
+            info!("Using {} backend", backend.name());
+            Ok(backend)
+        }
+        Err(e) => {
+            warn!(
+                "Configured backend {:?} unavailable ({:#}), picking automatically",
+                settings.kind, e
+            );
+            auto_backend(on_python_status)
+        }
+    }
+}
+
+fn boxed(backend: impl LlmBackend + 'static) -> Box<dyn LlmBackend> {
+    Box::new(backend)
+}
+
+/// Pick the first usable backend: llm_server.py, Python subprocess, native
+///
+/// Python is only started here, when the server can't take chat;
+/// `on_python_status` follows its progress.
+fn auto_backend(on_python_status: &dyn Fn(&BridgeStatus)) -> Result<Box<dyn LlmBackend>> {
+    match HttpBackend::local() {
+        Ok(http) if http.health() => {
+            info!("Using LLM server backend");
+            return Ok(Box::new(http));
+        }
+        _ => warn!("LLM server not reachable, trying Python subprocess"),
+    }
+
+    match python_bridge::start(on_python_status) {
+        Ok(bridge) => {
+            info!("Using Python subprocess backend");
+            return Ok(Box::new(bridge));
+        }
+        Err(e) => warn!("Python backend unavailable ({}), trying native llama.cpp", e),
+    }
+
+    // The native backend's failure (usually a missing model) is the one to
+    // act on; the others were logged above
+    let native = LlmManager::new()
+        .context("No backend could be started: the LLM server isn't running and Python didn't load")?;
+    info!("Using native llama.cpp backend");
+    Ok(Box::new(native))
+}
+
+/// Whether chat has a backend, sent to the frontend as `backend-status`
+/// events
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum BackendStatus {
+    /// Still being started in the background after launch
+    Starting,
+    Ready { backend: String },
+    /// Nothing could be started; chat waits for the user to fix `error`
+    SetupRequired { error: AppError },
+}
+
+static STATUS: parking_lot::Mutex<BackendStatus> = parking_lot::const_mutex(BackendStatus::Starting);
+
+fn set_status(status: BackendStatus) {
+    *STATUS.lock() = status;
+}
+
+fn startup_error(error: &anyhow::Error) -> AppError {
+    AppError::classify(error).unwrap_or_else(|| AppError::BackendUnavailable(format!("{:#}", error)))
+}
+
+/// Start the chat backend now, replacing any current one, and announce the
+/// outcome as a `backend-status` event
+fn init_backend(llm: &SharedBackend, app: &tauri::AppHandle) -> BackendStatus {
+    let mut llm = llm.blocking_lock();
+    *llm = None;
+    let status = match try_select_backend(&|status| {
+        let _ = app.emit_all("python-bridge-status", status);
+    }) {
+        Ok(backend) => {
+            let status = BackendStatus::Ready {
+                backend: backend.name().to_string(),
+            };
+            *llm = Some(backend);
+            status
+        }
+        Err(e) => {
+            warn!("Starting in setup-required mode: {:#}", e);
+            BackendStatus::SetupRequired { error: startup_error(&e) }
+        }
+    };
+    set_status(status.clone());
+    let _ = app.emit_all("backend-status", &status);
+    status
+}
+
+/// Start the backend in the background once the window is up, so a missing
+/// model or Python setup shows in the UI instead of stopping the launch
+pub fn spawn_startup_init(app: tauri::AppHandle) {
+    let llm = app.state::<AppState>().llm.clone();
+    tauri::async_runtime::spawn_blocking(move || init_backend(&llm, &app));
+}
+
+/// Whether chat has a backend (`starting` until the startup attempt ends)
+#[tauri::command]
+pub async fn get_backend_status() -> Result<BackendStatus, String> {
+    Ok(STATUS.lock().clone())
+}
+
+/// Try starting the backend again, e.g. after installing a model or
+/// starting llm_server.py
+#[tauri::command]
+pub async fn retry_backend_init(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<BackendStatus, AppError> {
+    if state.generation.is_active() {
+        return Err(AppError::Busy("Can't restart the backend while a reply is being generated".to_string()));
+    }
+    info!("Retrying backend start");
+    let llm = state.llm.clone();
+    set_status(BackendStatus::Starting);
+    let _ = app.emit_all("backend-status", &BackendStatus::Starting);
+    tauri::async_runtime::spawn_blocking(move || init_backend(&llm, &app))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Request body for llm_server.py's `/generate`
+pub fn request_body(request: &GenerationRequest) -> serde_json::Value {
+    serde_json::json!({
+        "prompt": request.prompt,
+        "system_prompt": request.system_prompt,
+        "conversation_history": request.history.iter().map(|entry| {
+            serde_json::json!({
+                "role": entry.role,
+                "content": entry.content,
+                "timestamp": entry.timestamp
+            })
+        }).collect::<Vec<_>>(),
+        "temperature": request.config.temperature,
+        "top_p": request.config.top_p,
+        "top_k": request.config.top_k,
+        "max_tokens": request.config.max_tokens,
+        "stop": request.config.stop,
+        "seed": request.config.seed,
+        "json_schema": request.config.json_schema,
+        "logit_bias": logit_bias::token_ids(&request.config.logit_bias),
+    })
+}
+
+/// Run `generate`, timing streamed tokens into stats
+///
+/// `generate` fills in the token counts it knows; otherwise each streamed
+/// piece counts as one completion token.
+fn timed(
+    backend: &'static str,
+    on_token: &mut dyn FnMut(&str) -> bool,
+    generate: impl FnOnce(&mut dyn FnMut(&str) -> bool, &mut GenerationStats) -> Result<String>,
+) -> Result<Completion> {
+    let started = Instant::now();
+    let mut first_token = None;
+    let mut tokens = 0u32;
+    let mut stats = GenerationStats {
+        backend: backend.to_string(),
+        ..Default::default()
+    };
+
+    let text = generate(
+        &mut |token| {
+            first_token.get_or_insert_with(Instant::now);
+            tokens += 1;
+            on_token(token)
+        },
+        &mut stats,
+    )?;
+
+    stats.completion_tokens.get_or_insert(tokens);
+    stats.finish(started, first_token);
+    Ok(Completion {
+        text,
+        stats,
+        tool_calls: Vec::new(),
+    })
+}
+
+impl LlmBackend for HttpBackend {
+    fn name(&self) -> &'static str {
+        "llm_server"
+    }
+
+    fn generate(
+        &mut self,
+        request: &GenerationRequest,
+        cancel: &CancellationToken,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        // Pick up timeout changes without restarting
+        *self = HttpBackend::with_timeouts(self.base_url(), BackendTimeouts::load())?;
+        self.generate_streaming_until(&request_body(request), || cancel.is_cancelled(), on_token)
+    }
+}
+
+impl LlmBackend for PythonBridge {
+    fn name(&self) -> &'static str {
+        "python"
+    }
+
+    fn generate(
+        &mut self,
+        request: &GenerationRequest,
+        cancel: &CancellationToken,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        timed(self.name(), on_token, |on_token, stats| {
+            let text = self.generate_streaming(
+                request.prompt.to_string(),
+                Some(request.system_prompt.to_string()),
+                request.history,
+                request.config.clone(),
+                cancel,
+                on_token,
+            )?;
+            // Python doesn't report usage; count with the chat model's tokenizer
+            let completion_tokens = count_tokens(&text) as u32;
+            stats.prompt_tokens =
+                Some(prompt_budget::request_tokens(request.system_prompt, request.history, request.prompt) as u32);
+            stats.completion_tokens = Some(completion_tokens);
+            stats.truncated = completion_tokens >= request.config.max_tokens.max(1) as u32;
+            Ok(text)
+        })
+    }
+
+    fn switch_model(&mut self, path: &Path) -> Result<bool> {
+        PythonBridge::load_model(self, Some(path.to_path_buf()))?;
+        Ok(true)
+    }
+
+    fn delete_user_data(&mut self, user_id: &str) -> Result<Option<usize>> {
+        PythonBridge::delete_user_data(self, user_id)
+    }
+}
+
+impl LlmBackend for LlmManager {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn generate(
+        &mut self,
+        request: &GenerationRequest,
+        cancel: &CancellationToken,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        let (prompt, n_keep) = self.fit_request(request)?;
+        timed(self.name(), on_token, |on_token, stats| {
+            let (text, usage) = self.generate_streaming(&prompt, n_keep, request.config, cancel, on_token)?;
+            stats.model = Some(self.model_name());
+            stats.prompt_tokens = Some(usage.prompt_tokens);
+            stats.completion_tokens = Some(usage.completion_tokens);
+            stats.truncated = usage.hit_limit;
+            stats.finish_reason = Some(if usage.hit_limit { "length" } else { "stop" }.to_string());
+            Ok(text)
+        })
+    }
+}
+
+/// Current backend settings (without the API key)
+#[tauri::command]
+pub async fn get_backend(state: tauri::State<'_, AppState>) -> Result<BackendInfo, String> {
+    let settings = BackendSettings::load();
+    // Don't wait on a generation that holds the backend
+    let active = state
+        .llm
+        .try_lock()
+        .ok()
+        .and_then(|llm| llm.as_ref().map(|backend| backend.name().to_string()));
+    Ok(BackendInfo {
+        kind: settings.kind,
+        base_url: settings.remote.base_url,
+        model: settings.remote.model,
+        has_api_key: settings.remote.api_key.is_some_and(|key| !key.is_empty()),
+        model_path: settings.model_path.map(|path| path.to_string_lossy().into_owned()),
+        active,
+    })
+}
+
+/// Choose the chat backend; takes effect with the next message
+///
+/// `base_url`, `api_key` and `model` configure the remote backend; omitted
+/// ones keep their saved values (an empty `api_key` clears it).
+#[tauri::command]
+pub async fn set_backend(
+    kind: BackendKind,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<BackendInfo, String> {
+    if state.generation.is_active() {
+        return Err("Can't change the backend while a reply is being generated".to_string());
+    }
+
+    let mut settings = BackendSettings::load();
+    settings.kind = kind;
+    if let Some(base_url) = base_url {
+        settings.remote.base_url = base_url.trim().to_string();
+    }
+    if let Some(api_key) = api_key {
+        settings.remote.api_key = Some(api_key).filter(|key| !key.is_empty());
+    }
+    if let Some(model) = model {
+        settings.remote.model = Some(model.trim().to_string()).filter(|model| !model.is_empty());
+    }
+    if kind == BackendKind::Remote {
+        // Fail now on a bad URL rather than on the next message
+        OpenAiBackend::new(settings.remote.clone(), BackendTimeouts::load()).map_err(|e| e.to_string())?;
+    }
+    settings.save().map_err(|e| e.to_string())?;
+
+    *state.llm.lock().await = None;
+    info!("Backend set to {:?}", kind);
+    get_backend(state).await
+}
+
+/// Stage of a model load, sent as `model-load-progress` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ModelLoadStage {
+    /// Releasing the current model
+    Unloading,
+    Loading,
+    Ready { backend: String },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelLoadProgress {
+    pub model_path: String,
+    #[serde(flatten)]
+    pub stage: ModelLoadStage,
+}
+
+/// Load `path` into the chat backend, replacing the current model
+///
+/// The Python subprocess backend loads it in place; any other backend is
+/// dropped first (freeing its memory) and replaced by native llama.cpp.
+/// Holds the backend lock throughout, so a message sent meanwhile waits for
+/// the new model.
+fn switch_model(
+    llm: &tokio::sync::Mutex<Option<Box<dyn LlmBackend>>>,
+    path: &Path,
+    on_stage: &dyn Fn(ModelLoadStage),
+) -> Result<&'static str> {
+    // Fail on a bad file, or one that can't chat, before giving up the
+    // current model
+    let probed = ModelCapabilities::probe(path)?;
+    probed.require(Feature::Chat)?;
+
+    let mut llm = llm.blocking_lock();
+    if let Some(backend) = llm.as_mut() {
+        on_stage(ModelLoadStage::Loading);
+        if backend.switch_model(path)? {
+            capabilities::set(probed);
+            return Ok(backend.name());
+        }
+    }
+
+    if llm.is_some() {
+        on_stage(ModelLoadStage::Unloading);
+        *llm = None;
+    }
+    on_stage(ModelLoadStage::Loading);
+    let native = LlmManager::load(path)?;
+    let name = native.name();
+    *llm = Some(Box::new(native));
+    Ok(name)
+}
+
+/// Load `path` for chat and save it as the model to use from now on
+///
+/// Returns the backend that loaded it. Blocks; call it off the async runtime.
+pub fn select_model(
+    llm: &tokio::sync::Mutex<Option<Box<dyn LlmBackend>>>,
+    path: &Path,
+    on_stage: &dyn Fn(ModelLoadStage),
+) -> Result<&'static str> {
+    if LOADING_MODEL.swap(true, Ordering::SeqCst) {
+        return Err(anyhow!("A model is already loading"));
+    }
+    let result = switch_model(llm, path, on_stage);
+    LOADING_MODEL.store(false, Ordering::SeqCst);
+    let backend = result?;
+
+    let mut settings = BackendSettings::load();
+    settings.model_path = Some(path.to_path_buf());
+    if backend != "python" {
+        settings.kind = BackendKind::Native;
+    }
+    settings.save()?;
+    crate::tokenizer::preload(path.to_path_buf());
+    info!("Switched to {} ({} backend)", path.display(), backend);
+    Ok(backend)
+}
+
+/// Switch chat to another GGUF (e.g. one from `get_available_models`)
+///
+/// Emits `model-load-progress` events while the current model is released
+/// and the new one loaded. The choice is saved, so it is also used after a
+/// restart; unless the Python backend is in use, native llama.cpp is pinned.
+#[tauri::command]
+pub async fn load_model_by_path(
+    model_path: String,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<BackendInfo, AppError> {
+    if state.generation.is_active() {
+        return Err(AppError::Busy("Can't switch models while a reply is being generated".to_string()));
+    }
+
+    let path = PathBuf::from(&model_path);
+    let llm = state.llm.clone();
+    let app = window.app_handle();
+    let emit = {
+        let model_path = model_path.clone();
+        move |stage: ModelLoadStage| {
+            let _ = window.emit(
+                "model-load-progress",
+                ModelLoadProgress {
+                    model_path: model_path.clone(),
+                    stage,
+                },
+            );
+        }
+    };
+    let result = tauri::async_runtime::spawn_blocking({
+        let path = path.clone();
+        let emit = emit.clone();
+        move || select_model(&llm, &path, &emit)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+    .and_then(|result| result.map_err(AppError::from));
+
+    let backend = match result {
+        Ok(backend) => backend,
+        Err(error) => {
+            error!("Failed to load {}: {}", path.display(), error);
+            emit(ModelLoadStage::Failed { error: error.to_string() });
+            return Err(error);
+        }
+    };
+
+    emit(ModelLoadStage::Ready {
+        backend: backend.to_string(),
+    });
+    let status = BackendStatus::Ready {
+        backend: backend.to_string(),
+    };
+    set_status(status.clone());
+    let _ = app.emit_all("backend-status", &status);
+    get_backend(state).await.map_err(AppError::Internal)
+}
+
+/// Release the chat model and its memory
+///
+/// The next message loads a backend again from the saved settings.
+#[tauri::command]
+pub async fn unload_model(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    if state.generation.is_active() {
+        return Err(AppError::Busy("Can't unload the model while a reply is being generated".to_string()));
+    }
+    if LOADING_MODEL.load(Ordering::SeqCst) {
+        return Err(AppError::Busy("A model is loading".to_string()));
+    }
+    if let Some(backend) = state.llm.lock().await.take() {
+        info!("Unloaded the {} backend", backend.name());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_builder::ChatTemplate;
+    use crate::EntryStatus;
+
+    #[test]
+    fn test_chatml_prompt_includes_history() {
+        let history = vec![ConversationEntry {
+            role: "user".to_string(),
+            content: "Hi".to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }];
+        let config = LlmConfig::default();
+        let request = GenerationRequest {
+            prompt: "How are you?",
+            system_prompt: "Be kind.",
+            history: &history,
+            config: &config,
+        };
+
+        let prompt = ChatTemplate::ChatMl.render(&request);
+        assert!(prompt.starts_with("<|im_start|>system\nBe kind.<|im_end|>"));
+        assert!(prompt.contains("<|im_start|>user\nHi<|im_end|>"));
+        assert!(prompt.ends_with("How are you?<|im_end|>\n<|im_start|>assistant\n"));
+        assert_eq!(request_body(&request)["conversation_history"][0]["content"], "Hi");
+    }
+
+    #[test]
+    fn test_setup_required_status_carries_error() {
+        let error = anyhow!("No model found in models/ directory").context("No backend could be started");
+        let status = BackendStatus::SetupRequired { error: startup_error(&error) };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "setup_required");
+        assert_eq!(json["error"]["code"], "model_not_found");
+        assert!(json["error"]["hint"].is_string());
+        let unknown = startup_error(&anyhow!("Python didn't load"));
+        assert_eq!(unknown.code(), "backend_unavailable");
+    }
+}
@@ -0,0 +1,259 @@
+// Bridge Manifest Module - Maps logical Python bridge operations to callables
+//
+// The Python backend's module and function names live in `bridge.toml` next to
+// the backend instead of being compiled into the Rust side. Any operation the
+// file leaves out falls back to the built-in mapping below.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// File name looked up in the Python backend directory
+pub const MANIFEST_FILE: &str = "bridge.toml";
+
+/// Mapping used when the backend ships no manifest (or omits operations)
+const DEFAULT_MANIFEST: &str = r#"
+[operations.find_model]
+target = "llm_manager.find_available_model"
+
+[operations.download_starter_model]
+target = "llm_manager.download_starter_model"
+# Unused since the Rust downloader took over (it reports progress)
+required = false
+
+[operations.load_model]
+target = "llm_manager.load_model"
+params = ["model_path"]
+
+[operations.generate]
+target = "llm_manager.generate_with_context"
+params = ["prompt", "conversation_history", "temperature", "top_p", "top_k", "max_tokens"]
+
+[operations.log_conversation]
+target = "nexus_core_engine.log_conversation_turn"
+params = ["user_message", "assistant_response", "mode"]
+required = false
+
+[operations.search_memory]
+target = "nexus_core_indexing.intelligent_search"
+params = ["query", "top_k"]
+required = false
+
+[operations.recent_history]
+target = "hierarchical_memory.get_recent_history"
+params = ["limit"]
+required = false
+
+[operations.delete_user_data]
+target = "nexus_core_engine.delete_user_data"
+params = ["user_id"]
+required = false
+"#;
+
+/// Logical operations the bridge performs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operation {
+    FindModel,
+    DownloadStarterModel,
+    LoadModel,
+    Generate,
+    LogConversation,
+    SearchMemory,
+    RecentHistory,
+    DeleteUserData,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 8] = [
+        Operation::FindModel,
+        Operation::DownloadStarterModel,
+        Operation::LoadModel,
+        Operation::Generate,
+        Operation::LogConversation,
+        Operation::SearchMemory,
+        Operation::RecentHistory,
+        Operation::DeleteUserData,
+    ];
+
+    /// Key used in `bridge.toml`
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::FindModel => "find_model",
+            Operation::DownloadStarterModel => "download_starter_model",
+            Operation::LoadModel => "load_model",
+            Operation::Generate => "generate",
+            Operation::LogConversation => "log_conversation",
+            Operation::SearchMemory => "search_memory",
+            Operation::RecentHistory => "recent_history",
+            Operation::DeleteUserData => "delete_user_data",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.name() == name)
+    }
+}
+
+/// Where an operation lives and what it must accept
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct OperationSpec {
+    /// `module.function` (the module may be dotted: `pkg.mod.function`)
+    pub target: String,
+    /// Parameter names the function must accept
+    #[serde(default)]
+    pub params: Vec<String>,
+    /// Whether initialization fails if this operation can't be resolved
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl OperationSpec {
+    /// Split `target` into (module, function)
+    pub fn module_and_function(&self) -> (&str, &str) {
+        // Validated at load time to contain a dot
+        self.target.rsplit_once('.').unwrap_or(("", self.target.as_str()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    #[serde(default)]
+    operations: HashMap<String, OperationSpec>,
+}
+
+/// Resolved mapping for every operation
+#[derive(Debug, Clone)]
+pub struct BridgeManifest {
+    operations: HashMap<Operation, OperationSpec>,
+}
+
+impl BridgeManifest {
+    /// The built-in mapping
+    pub fn builtin() -> Self {
+        Self::parse(DEFAULT_MANIFEST, None).expect("Built-in bridge manifest is valid")
+    }
+
+    /// Parse a manifest, filling unspecified operations from the built-in one
+    pub fn from_toml(text: &str) -> Result<Self> {
+        Self::parse(text, Some(Self::builtin()))
+    }
+
+    /// Load `bridge.toml` from the backend directory, or the built-in mapping
+    pub fn load(backend_dir: &Path) -> Result<Self> {
+        let path = backend_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::builtin());
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("Invalid bridge manifest {}", path.display()))
+    }
+
+    fn parse(text: &str, defaults: Option<Self>) -> Result<Self> {
+        let file: ManifestFile = toml::from_str(text).context("Failed to parse manifest TOML")?;
+
+        let mut operations = defaults.map(|d| d.operations).unwrap_or_default();
+        for (name, spec) in file.operations {
+            let operation = Operation::from_name(&name)
+                .ok_or_else(|| anyhow!("Unknown bridge operation '{}'", name))?;
+            validate_spec(&name, &spec)?;
+            operations.insert(operation, spec);
+        }
+
+        if let Some(missing) = Operation::ALL.iter().find(|op| !operations.contains_key(*op)) {
+            return Err(anyhow!("Bridge operation '{}' is not mapped", missing.name()));
+        }
+
+        Ok(Self { operations })
+    }
+
+    pub fn get(&self, operation: Operation) -> &OperationSpec {
+        &self.operations[&operation]
+    }
+
+    /// Every Python module the manifest refers to
+    pub fn modules(&self) -> BTreeSet<&str> {
+        self.operations
+            .values()
+            .map(|spec| spec.module_and_function().0)
+            .collect()
+    }
+}
+
+fn validate_spec(name: &str, spec: &OperationSpec) -> Result<()> {
+    let is_identifier = |s: &str| {
+        let mut chars = s.chars();
+        matches!(chars.next(), Some(c) if c == '_' || c.is_alphabetic())
+            && chars.all(|c| c == '_' || c.is_alphanumeric())
+    };
+
+    let valid_target = spec.target.contains('.') && spec.target.split('.').all(is_identifier);
+    if !valid_target {
+        return Err(anyhow!(
+            "Operation '{}': target '{}' must be 'module.function'",
+            name,
+            spec.target
+        ));
+    }
+
+    let mut seen = BTreeSet::new();
+    for param in &spec.params {
+        if !is_identifier(param) || !seen.insert(param.as_str()) {
+            return Err(anyhow!("Operation '{}': invalid or duplicate parameter '{}'", name, param));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_maps_every_operation() {
+        let manifest = BridgeManifest::builtin();
+        for op in Operation::ALL {
+            assert!(!manifest.get(op).target.is_empty());
+        }
+        assert_eq!(
+            manifest.get(Operation::Generate).module_and_function(),
+            ("llm_manager", "generate_with_context")
+        );
+        assert!(manifest.modules().contains("hierarchical_memory"));
+    }
+
+    #[test]
+    fn test_override_keeps_other_defaults() {
+        let manifest = BridgeManifest::from_toml(
+            r#"
+            [operations.generate]
+            target = "backend.llm.generate"
+            params = ["prompt"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.get(Operation::Generate).module_and_function(),
+            ("backend.llm", "generate")
+        );
+        assert_eq!(manifest.get(Operation::LoadModel).target, "llm_manager.load_model");
+    }
+
+    #[test]
+    fn test_invalid_manifests_are_rejected() {
+        assert!(BridgeManifest::from_toml("[operations.summon]\ntarget = \"a.b\"").is_err());
+        assert!(BridgeManifest::from_toml("[operations.generate]\ntarget = \"nodot\"").is_err());
+        assert!(BridgeManifest::from_toml(
+            "[operations.generate]\ntarget = \"a.b\"\nparams = [\"x\", \"x\"]"
+        )
+        .is_err());
+    }
+}
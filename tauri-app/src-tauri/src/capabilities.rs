@@ -0,0 +1,216 @@
+// Capabilities Module - What the loaded model can do, read from its GGUF header
+//
+// After a model loads, its header is probed for a chat template, the context
+// length it was trained for, whether it is an embedding model and which
+// tool-call format its template speaks. Model switching refuses embedding-only
+// models for chat, the native backend caps its context at the trained length,
+// the embedder refuses chat models, and tool use checks `require` first, so
+// an unsupported feature fails with a clear error instead of garbage output.
+
+use crate::models::{read_gguf_metadata, GgufMetadata};
+use crate::prompt_builder::ChatTemplate;
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::Path;
+use tracing::info;
+
+/// Architectures that only produce embeddings
+const EMBEDDING_ARCHITECTURES: &[&str] = &["bert", "nomic-bert", "jina-bert-v2", "modern-bert", "t5encoder"];
+
+/// Capabilities of the model chat is using
+static CURRENT: Mutex<Option<ModelCapabilities>> = parking_lot::const_mutex(None);
+
+/// How a model's chat template asks for tool calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallFormat {
+    /// `<tool_call>{...}</tool_call>` (Hermes, Qwen 2.5+)
+    Hermes,
+    /// `<|python_tag|>` / ipython role (Llama 3.1+)
+    Llama3,
+    /// `[TOOL_CALLS][...]` (Mistral)
+    Mistral,
+    /// The template takes a tool list but uses no known markers
+    Generic,
+}
+
+impl ToolCallFormat {
+    /// Tool-call format of a GGUF Jinja chat template, if it has one
+    pub fn detect(jinja: &str) -> Option<Self> {
+        if jinja.contains("<tool_call>") {
+            Some(Self::Hermes)
+        } else if jinja.contains("<|python_tag|>") || jinja.contains("ipython") {
+            Some(Self::Llama3)
+        } else if jinja.contains("[TOOL_CALLS]") {
+            Some(Self::Mistral)
+        } else if jinja.contains("tools") {
+            Some(Self::Generic)
+        } else {
+            None
+        }
+    }
+}
+
+/// Something a caller needs from the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Chat,
+    Embeddings,
+    ToolCalls,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCapabilities {
+    /// File name of the model
+    pub model: String,
+    pub architecture: Option<String>,
+    /// The header carries a chat template
+    pub chat_template: bool,
+    /// Prompt format recognised in that template
+    pub template_family: Option<ChatTemplate>,
+    /// Context length the model was trained for
+    pub context_length: Option<u64>,
+    /// Can write replies
+    pub chat: bool,
+    /// Produces sentence embeddings
+    pub embeddings: bool,
+    pub tool_calls: Option<ToolCallFormat>,
+}
+
+impl ModelCapabilities {
+    pub fn from_metadata(model: &str, metadata: &GgufMetadata) -> Self {
+        let template = metadata.chat_template.as_deref();
+        let architecture = metadata.architecture.as_deref().unwrap_or_default();
+        let embeddings = metadata.pooling_type.is_some_and(|pooling| pooling > 0)
+            || metadata.causal_attention == Some(false)
+            || EMBEDDING_ARCHITECTURES.contains(&architecture)
+            || model.to_lowercase().contains("embed");
+        Self {
+            model: model.to_string(),
+            architecture: metadata.architecture.clone(),
+            chat_template: template.is_some(),
+            template_family: template.and_then(ChatTemplate::detect),
+            context_length: metadata.context_length,
+            // An embedding model with a chat template was tuned for both
+            chat: !embeddings || template.is_some(),
+            embeddings,
+            tool_calls: template.and_then(ToolCallFormat::detect),
+        }
+    }
+
+    /// Read the capabilities of the GGUF at `path`
+    pub fn probe(path: &Path) -> Result<Self> {
+        let model = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::from_metadata(&model, &read_gguf_metadata(path)?))
+    }
+
+    /// Fail unless the model supports `feature`
+    pub fn require(&self, feature: Feature) -> Result<()> {
+        match feature {
+            Feature::Chat if !self.chat => Err(anyhow!(
+                "{} is an embedding model and can't chat; pick a chat model instead",
+                self.model
+            )),
+            Feature::Embeddings if !self.embeddings => Err(anyhow!(
+                "{} is not an embedding model; pick one such as nomic-embed-text or bge",
+                self.model
+            )),
+            Feature::ToolCalls if self.tool_calls.is_none() => Err(anyhow!(
+                "{} has no tool-call format in its chat template, so it can't use tools",
+                self.model
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// `requested` context tokens, capped at the trained context length
+    pub fn usable_context(&self, requested: u32) -> u32 {
+        match self.context_length {
+            Some(trained) if trained > 0 => requested.min(trained.min(u32::MAX as u64) as u32),
+            _ => requested,
+        }
+    }
+}
+
+/// Capabilities of the model chat is using, once one has loaded
+pub fn current() -> Option<ModelCapabilities> {
+    CURRENT.lock().clone()
+}
+
+/// Record the model chat now uses
+pub fn set(capabilities: ModelCapabilities) {
+    info!(
+        "{}: chat={} embeddings={} tools={:?} context={:?}",
+        capabilities.model,
+        capabilities.chat,
+        capabilities.embeddings,
+        capabilities.tool_calls,
+        capabilities.context_length
+    );
+    *CURRENT.lock() = Some(capabilities);
+}
+
+/// Fail unless the chat model supports `feature`; models that couldn't be
+/// probed (e.g. behind a server) are given the benefit of the doubt
+pub fn require_current(feature: Feature) -> Result<()> {
+    match current() {
+        Some(capabilities) => capabilities.require(feature),
+        None => Ok(()),
+    }
+}
+
+/// Capabilities of the GGUF at `model_path`, or of the model chat is using
+#[tauri::command]
+pub async fn get_model_capabilities(model_path: Option<String>) -> Result<ModelCapabilities, String> {
+    match model_path {
+        Some(path) => ModelCapabilities::probe(Path::new(&path)).map_err(|e| format!("{:#}", e)),
+        None => current().ok_or_else(|| "No model is loaded".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_from_metadata() {
+        let chat = GgufMetadata {
+            architecture: Some("qwen2".to_string()),
+            context_length: Some(32768),
+            chat_template: Some(
+                "{% if tools %}<tools>{% endif %}<|im_start|>assistant\n<tool_call>{{ call }}</tool_call>".to_string(),
+            ),
+            ..Default::default()
+        };
+        let capabilities = ModelCapabilities::from_metadata("qwen2.5-7b-instruct.gguf", &chat);
+        assert_eq!(capabilities.template_family, Some(ChatTemplate::ChatMl));
+        assert_eq!(capabilities.tool_calls, Some(ToolCallFormat::Hermes));
+        assert!(capabilities.require(Feature::Chat).is_ok());
+        assert!(capabilities.require(Feature::ToolCalls).is_ok());
+        assert!(capabilities.require(Feature::Embeddings).is_err());
+        assert_eq!(capabilities.usable_context(8192), 8192);
+        assert_eq!(capabilities.usable_context(65536), 32768);
+
+        let embedder = GgufMetadata {
+            architecture: Some("nomic-bert".to_string()),
+            context_length: Some(2048),
+            pooling_type: Some(1),
+            ..Default::default()
+        };
+        let capabilities = ModelCapabilities::from_metadata("nomic-embed-text-v1.5.Q8_0.gguf", &embedder);
+        assert!(capabilities.embeddings);
+        let error = capabilities.require(Feature::Chat).unwrap_err().to_string();
+        assert!(error.contains("embedding model"));
+        assert!(capabilities.require(Feature::ToolCalls).is_err());
+
+        // No template at all: chat still works (ChatML fallback), tools don't
+        let bare = ModelCapabilities::from_metadata("tinyllama.gguf", &GgufMetadata::default());
+        assert!(bare.chat && !bare.chat_template);
+        assert_eq!(bare.tool_calls, None);
+        assert_eq!(bare.usable_context(4096), 4096);
+    }
+}
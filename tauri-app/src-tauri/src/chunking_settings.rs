@@ -0,0 +1,318 @@
+// Chunking Settings Module - How documents are cut, and re-cutting them
+//
+// Chunk size, overlap and unit are saved in `chunking.json` and read by the
+// ingest worker for each batch. Every stored chunk carries a hash of the
+// settings it was cut with; when the settings change, documents whose chunks
+// carry another hash are stale and can be re-chunked and re-embedded in the
+// background. A file still on disk is read again; text ingested directly is
+// pieced back together from its chunks.
+
+use crate::embeddings::fnv1a;
+use crate::ingest::{document_filters, DocumentSource, IngestJob};
+use crate::memory_policy::{Sensitivity, SENSITIVITY_KEY};
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use crate::text_chunker::{ChunkingConfig, SizeUnit};
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Chunk metadata key holding the settings hash
+pub const CHUNKING_KEY: &str = "chunking";
+
+/// Shortest overlap trusted when joining chunks back together
+const MIN_REJOIN_OVERLAP: usize = 8;
+
+/// Chunking settings, saved in `chunking.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingSettings {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    /// Smaller chunks are merged into their neighbours
+    pub min_chunk_size: usize,
+    pub size_unit: SizeUnit,
+}
+
+impl Default for ChunkingSettings {
+    fn default() -> Self {
+        Self {
+            chunk_size: 512,
+            chunk_overlap: 50,
+            min_chunk_size: 100,
+            size_unit: SizeUnit::Chars,
+        }
+    }
+}
+
+impl ChunkingSettings {
+    const FILE: &'static str = "chunking.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(anyhow!("Chunk size must be at least 1"));
+        }
+        if self.chunk_overlap >= self.chunk_size {
+            return Err(anyhow!("Chunk overlap must be smaller than the chunk size"));
+        }
+        Ok(())
+    }
+
+    pub fn config(&self) -> ChunkingConfig {
+        ChunkingConfig {
+            chunk_size: self.chunk_size,
+            chunk_overlap: self.chunk_overlap,
+            min_chunk_size: self.min_chunk_size,
+            size_unit: self.size_unit,
+            ..Default::default()
+        }
+    }
+
+    /// Identifies these settings in chunk metadata
+    pub fn hash(&self) -> String {
+        let key = format!(
+            "{}:{}:{}:{:?}",
+            self.chunk_size, self.chunk_overlap, self.min_chunk_size, self.size_unit
+        );
+        format!("{:016x}", fnv1a(key.as_bytes()))
+    }
+}
+
+/// A document cut with other settings than the current ones
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleDocument {
+    pub doc_id: String,
+    pub chunks: usize,
+    /// Still readable from disk (otherwise rebuilt from its chunks)
+    pub has_file: bool,
+}
+
+/// Settings hash of a chunk; chunks from before hashes were recorded were
+/// cut with the defaults
+fn chunk_hash(chunk: &MemoryItem) -> String {
+    chunk
+        .metadata
+        .get(CHUNKING_KEY)
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| ChunkingSettings::default().hash())
+}
+
+/// Chunks of every ingested document, by doc id, in chunk order
+fn documents(store: &MemoryStore) -> BTreeMap<String, Vec<MemoryItem>> {
+    let mut filters = MemoryFilters::default();
+    filters
+        .metadata
+        .insert("kind".to_string(), serde_json::json!("document_chunk"));
+
+    let mut documents: BTreeMap<String, Vec<MemoryItem>> = BTreeMap::new();
+    for chunk in store.get_all(&filters, usize::MAX) {
+        if let Some(doc_id) = chunk.metadata.get("doc_id").and_then(|v| v.as_str()) {
+            documents.entry(doc_id.to_string()).or_default().push(chunk);
+        }
+    }
+    for chunks in documents.values_mut() {
+        chunks.sort_by_key(|chunk| chunk.metadata.get("chunk_index").and_then(|v| v.as_u64()));
+    }
+    documents
+}
+
+/// Documents with chunks cut under settings other than `current_hash`
+pub fn stale_documents(store: &MemoryStore, current_hash: &str) -> Vec<StaleDocument> {
+    documents(store)
+        .into_iter()
+        .filter(|(_, chunks)| chunks.iter().any(|chunk| chunk_hash(chunk) != current_hash))
+        .map(|(doc_id, chunks)| StaleDocument {
+            has_file: Path::new(&doc_id).is_file(),
+            chunks: chunks.len(),
+            doc_id,
+        })
+        .collect()
+}
+
+/// Piece a document back together from its chunks, dropping the text
+/// consecutive chunks overlap on
+pub fn rejoin(chunks: &[&str]) -> String {
+    let mut text = String::new();
+    for chunk in chunks {
+        let max = text.len().min(chunk.len());
+        let overlap = (MIN_REJOIN_OVERLAP..=max)
+            .rev()
+            .filter(|&n| chunk.is_char_boundary(n) && text.is_char_boundary(text.len() - n))
+            .find(|&n| text.ends_with(&chunk[..n]));
+        match overlap {
+            Some(n) => text.push_str(&chunk[n..]),
+            None if text.is_empty() => text.push_str(chunk),
+            None => {
+                text.push(' ');
+                text.push_str(chunk);
+            }
+        }
+    }
+    text
+}
+
+/// Queue documents for re-chunking; the ingest worker replaces their chunks
+///
+/// # Returns
+/// How many were queued
+pub fn rechunk(state: &AppState, doc_ids: &[String]) -> Result<usize> {
+    let mut jobs = Vec::new();
+    {
+        let store = state.memory_store.lock();
+        for doc_id in doc_ids {
+            let chunks = store.get_all(&document_filters(doc_id), usize::MAX);
+            if chunks.is_empty() {
+                continue;
+            }
+            let sensitivity = chunks
+                .iter()
+                .filter_map(|chunk| chunk.metadata.get(SENSITIVITY_KEY))
+                .filter_map(|v| serde_json::from_value::<Sensitivity>(v.clone()).ok())
+                .find(|s| *s == Sensitivity::Private)
+                .unwrap_or_default();
+
+            let source = if Path::new(doc_id).is_file() {
+                DocumentSource::File(PathBuf::from(doc_id))
+            } else {
+                let mut chunks = chunks;
+                chunks.sort_by_key(|chunk| chunk.metadata.get("chunk_index").and_then(|v| v.as_u64()));
+                let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+                DocumentSource::Text(rejoin(&texts))
+            };
+            jobs.push(IngestJob {
+                doc_id: doc_id.clone(),
+                source,
+                sensitivity,
+                replace: true,
+            });
+        }
+    }
+
+    let queued = jobs.len();
+    for job in jobs {
+        state.ingest.enqueue(job)?;
+    }
+    Ok(queued)
+}
+
+/// Stale documents found after a settings change, and how many were queued
+#[derive(Debug, Clone, Serialize)]
+pub struct RechunkReport {
+    pub stale: Vec<StaleDocument>,
+    pub queued: usize,
+}
+
+#[tauri::command]
+pub async fn get_chunking_settings() -> Result<ChunkingSettings, String> {
+    Ok(ChunkingSettings::load())
+}
+
+/// Save chunking settings; with `rechunk` the documents they make stale are
+/// re-chunked in the background, otherwise they are only reported
+#[tauri::command]
+pub async fn set_chunking_settings(
+    settings: ChunkingSettings,
+    rechunk: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<RechunkReport, String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    settings.save().map_err(|e| e.to_string())?;
+
+    let stale = stale_documents(&state.memory_store.lock(), &settings.hash());
+    let queued = if rechunk.unwrap_or(false) {
+        let doc_ids: Vec<String> = stale.iter().map(|doc| doc.doc_id.clone()).collect();
+        self::rechunk(&state, &doc_ids).map_err(|e| e.to_string())?
+    } else {
+        0
+    };
+    info!(
+        "Chunking set to {} {:?} (overlap {}); {} stale document(s), {} queued",
+        settings.chunk_size,
+        settings.size_unit,
+        settings.chunk_overlap,
+        stale.len(),
+        queued
+    );
+    Ok(RechunkReport { stale, queued })
+}
+
+/// Documents whose chunks don't match the current chunking settings
+#[tauri::command]
+pub async fn get_stale_documents(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<StaleDocument>, String> {
+    let hash = ChunkingSettings::load().hash();
+    Ok(stale_documents(&state.memory_store.lock(), &hash))
+}
+
+/// Re-chunk and re-embed documents (every stale one if `doc_ids` is omitted)
+#[tauri::command]
+pub async fn rechunk_documents(
+    doc_ids: Option<Vec<String>>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let doc_ids = match doc_ids {
+        Some(doc_ids) => doc_ids,
+        None => {
+            let hash = ChunkingSettings::load().hash();
+            stale_documents(&state.memory_store.lock(), &hash)
+                .into_iter()
+                .map(|doc| doc.doc_id)
+                .collect()
+        }
+    };
+    rechunk(&state, &doc_ids).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::HashingEmbedder;
+    use crate::ingest::{prepare_documents, store_chunks};
+    use crate::text_chunker::TextChunker;
+
+    #[test]
+    fn test_stale_detection_and_rejoin() {
+        let settings = ChunkingSettings {
+            chunk_size: 80,
+            chunk_overlap: 30,
+            min_chunk_size: 0,
+            ..Default::default()
+        };
+        let text = "The first sentence is here. The second one follows it. A third comes next.";
+        let pieces = [
+            "The first sentence is here. The second one follows it.",
+            "The second one follows it. A third comes next.",
+        ];
+        assert_eq!(rejoin(&pieces), text);
+        // No overlap: joined with a space
+        assert_eq!(rejoin(&["One part.", "Another part."]), "One part. Another part.");
+
+        let chunker = TextChunker::with_config(settings.config());
+        let documents = vec![("notes".to_string(), text.to_string())];
+        let chunks = prepare_documents(&documents, &chunker, &HashingEmbedder::default());
+        let mut store = MemoryStore::new();
+        store_chunks(&mut store, chunks, &settings.hash());
+
+        assert!(stale_documents(&store, &settings.hash()).is_empty());
+        let changed = ChunkingSettings {
+            chunk_size: 120,
+            ..settings
+        };
+        let stale = stale_documents(&store, &changed.hash());
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].doc_id, "notes");
+        assert!(!stale[0].has_file);
+    }
+}
@@ -0,0 +1,184 @@
+// Clock Module - The current time, the user's timezone and relative times
+//
+// Timestamps are stored as RFC3339 in UTC and taken from `now()`. They are
+// shown in the timezone set in settings.toml (`[time] timezone`, an IANA name
+// such as "Europe/Berlin"), or the system's when unset. Query results carry
+// the local time and a relative form ("2 hours ago") next to the stored one.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The current time
+pub fn now() -> DateTime<Utc> {
+    SystemClock.now()
+}
+
+/// The current time as stored in history and metadata (RFC3339, UTC)
+pub fn timestamp() -> String {
+    now().to_rfc3339()
+}
+
+/// A stored RFC3339 timestamp
+pub fn parse(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Timezone times are shown in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserTimezone {
+    /// The operating system's
+    System,
+    Named(Tz),
+}
+
+impl UserTimezone {
+    /// From the settings value; empty or unset means the system's
+    pub fn parse(name: Option<&str>) -> Result<Self> {
+        match name.map(str::trim).filter(|name| !name.is_empty()) {
+            None => Ok(UserTimezone::System),
+            Some(name) => name
+                .parse::<Tz>()
+                .map(UserTimezone::Named)
+                .map_err(|_| anyhow!("Unknown timezone: {}", name)),
+        }
+    }
+
+    /// `time` in this timezone
+    pub fn localize(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            UserTimezone::System => time.with_timezone(&Local).fixed_offset(),
+            UserTimezone::Named(tz) => time.with_timezone(tz).fixed_offset(),
+        }
+    }
+}
+
+/// The user's timezone (the system's if the setting is invalid)
+pub fn timezone() -> UserTimezone {
+    let settings = crate::settings::AppSettings::load();
+    UserTimezone::parse(settings.time.timezone.as_deref()).unwrap_or(UserTimezone::System)
+}
+
+/// `time` in the user's timezone
+pub fn to_local(time: DateTime<Utc>) -> DateTime<FixedOffset> {
+    timezone().localize(time)
+}
+
+/// The current time in the user's timezone
+pub fn local_now() -> DateTime<FixedOffset> {
+    to_local(now())
+}
+
+/// How long before (or after) `now` `time` is, e.g. "3 days ago"
+pub fn relative(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+
+    let seconds = (now - time).num_seconds();
+    let elapsed = seconds.abs();
+    if elapsed < 45 {
+        return "just now".to_string();
+    }
+    // Rounded to the nearest unit
+    let (count, unit) = match elapsed {
+        s if s < 45 * MINUTE => ((s + MINUTE / 2) / MINUTE, "minute"),
+        s if s < 22 * HOUR => ((s + HOUR / 2) / HOUR, "hour"),
+        s if s < 26 * DAY => ((s + DAY / 2) / DAY, "day"),
+        s if s < 320 * DAY => ((s + 15 * DAY) / (30 * DAY), "month"),
+        s => ((s + 182 * DAY) / (365 * DAY), "year"),
+    };
+    let count = count.max(1);
+    let span = format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" });
+    if seconds < 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
+/// A query result with its time shown in the user's timezone and relative
+/// to now
+#[derive(Debug, Clone, Serialize)]
+pub struct Dated<T> {
+    #[serde(flatten)]
+    pub item: T,
+    /// RFC3339 with the user's UTC offset
+    pub local_time: Option<String>,
+    pub relative_time: Option<String>,
+}
+
+/// Attach local and relative times to `items`, using `time_of` each
+pub fn dated<T>(
+    items: Vec<T>,
+    clock: &dyn Clock,
+    time_of: impl Fn(&T) -> Option<DateTime<Utc>>,
+) -> Vec<Dated<T>> {
+    let timezone = timezone();
+    let now = clock.now();
+    items
+        .into_iter()
+        .map(|item| {
+            let time = time_of(&item);
+            Dated {
+                local_time: time.map(|time| timezone.localize(time).to_rfc3339()),
+                relative_time: time.map(|time| relative(time, now)),
+                item,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_relative_times_and_timezones() {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+        assert_eq!(relative(now - Duration::seconds(10), now), "just now");
+        assert_eq!(relative(now - Duration::minutes(1), now), "1 minute ago");
+        assert_eq!(relative(now - Duration::minutes(125), now), "2 hours ago");
+        assert_eq!(relative(now - Duration::days(3), now), "3 days ago");
+        assert_eq!(relative(now - Duration::days(65), now), "2 months ago");
+        assert_eq!(relative(now - Duration::days(800), now), "2 years ago");
+        assert_eq!(relative(now + Duration::hours(5), now), "in 5 hours");
+
+        let berlin = UserTimezone::parse(Some("Europe/Berlin")).unwrap();
+        // Summer time: UTC+2
+        assert_eq!(berlin.localize(now).to_rfc3339(), "2024-07-01T14:00:00+02:00");
+        assert_eq!(UserTimezone::parse(Some(" ")).unwrap(), UserTimezone::System);
+        assert!(UserTimezone::parse(Some("Mars/Olympus_Mons")).is_err());
+
+        let items = vec!["2024-07-01T10:00:00Z".to_string(), "not a time".to_string()];
+        let dated = dated(items, &FixedClock(now), |ts| parse(ts));
+        assert_eq!(dated[0].relative_time.as_deref(), Some("2 hours ago"));
+        assert!(dated[1].local_time.is_none());
+    }
+}
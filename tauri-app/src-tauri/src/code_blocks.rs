@@ -0,0 +1,324 @@
+// Code Blocks Module - Fenced code in assistant replies, for copy/save buttons
+//
+// Blocks are parsed from the stored message with their language tag and, when
+// the reply names one, the file they belong to: either in the info string
+// (```rust title="src/main.rs"`, ```python:app.py`) or on the line just above
+// the fence ("Create `config.toml`:"). Saving writes each block under a
+// directory the user picked, never outside it and never over an existing file.
+
+use crate::AppState;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use tracing::info;
+
+/// One fenced block from a message
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CodeBlock {
+    /// Position among the message's blocks, from 0
+    pub index: usize,
+    /// Language tag, or the one implied by the file name
+    pub language: Option<String>,
+    /// File the reply says this block belongs to
+    pub filename: Option<String>,
+    pub code: String,
+    /// Where the block was written, when saving was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_to: Option<String>,
+}
+
+/// Parse the fenced code blocks in `content`
+///
+/// Fences are ``` or ~~~ (three or more); a block closes on the same
+/// character at least as long. An unterminated block runs to the end.
+pub fn parse_code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mention = Regex::new(r"`([^`\s]+\.[A-Za-z0-9]+)`").unwrap();
+    let mut blocks = Vec::new();
+    let mut previous_line = "";
+    let mut open: Option<(char, usize, Option<String>, Option<String>, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        match open.as_mut() {
+            Some((fence, len, _, _, lines)) => {
+                if is_closing_fence(trimmed, *fence, *len) {
+                    let (_, _, language, filename, lines) = open.take().unwrap();
+                    blocks.push(new_block(blocks.len(), language, filename, lines));
+                    previous_line = "";
+                } else {
+                    lines.push(line);
+                }
+            }
+            None => {
+                if let Some((fence, len, info)) = opening_fence(trimmed) {
+                    let (language, mut filename) = parse_info(info);
+                    if filename.is_none() {
+                        filename = mention
+                            .captures_iter(previous_line)
+                            .last()
+                            .map(|c| c[1].to_string());
+                    }
+                    open = Some((fence, len, language, filename, Vec::new()));
+                } else if !line.trim().is_empty() {
+                    previous_line = line;
+                }
+            }
+        }
+    }
+
+    if let Some((_, _, language, filename, lines)) = open {
+        blocks.push(new_block(blocks.len(), language, filename, lines));
+    }
+    blocks
+}
+
+fn new_block(
+    index: usize,
+    language: Option<String>,
+    filename: Option<String>,
+    lines: Vec<&str>,
+) -> CodeBlock {
+    let language = language.or_else(|| filename.as_deref().and_then(language_for_file));
+    CodeBlock {
+        index,
+        language,
+        filename,
+        code: lines.join("\n"),
+        saved_to: None,
+    }
+}
+
+/// Fence character, length and info string of an opening fence line
+fn opening_fence(line: &str) -> Option<(char, usize, &str)> {
+    let fence = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == fence).count();
+    if len < 3 {
+        return None;
+    }
+    let info = line[len..].trim();
+    // Backtick info strings can't contain backticks (that's inline code)
+    if fence == '`' && info.contains('`') {
+        return None;
+    }
+    Some((fence, len, info))
+}
+
+fn is_closing_fence(line: &str, fence: char, len: usize) -> bool {
+    let run = line.chars().take_while(|c| *c == fence).count();
+    run >= len && line[run..].trim().is_empty()
+}
+
+/// Language and file name from an info string
+///
+/// Handles `rust`, `rust title="src/main.rs"`, `python:app.py`, `file=x.js`
+/// and a bare path such as `src/main.rs`.
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut words = info.split_whitespace();
+    let Some(first) = words.next() else {
+        return (None, None);
+    };
+
+    let attribute = words.find_map(|word| {
+        let (key, value) = word.split_once('=')?;
+        matches!(key, "title" | "file" | "filename" | "path")
+            .then(|| value.trim_matches(|c| c == '"' || c == '\'').to_string())
+    });
+
+    let (language, filename) = match first.split_once(':') {
+        Some((language, filename)) => (Some(language), Some(filename.to_string())),
+        None if first.contains('/') || (first.contains('.') && language_for_file(first).is_some()) => {
+            (None, Some(first.to_string()))
+        }
+        None => (Some(first), None),
+    };
+
+    let language = language
+        .filter(|language| !language.is_empty() && !language.contains('='))
+        .map(|language| language.trim_start_matches('{').trim_start_matches('.').to_lowercase());
+    let filename = attribute.or(filename).filter(|name| !name.is_empty());
+    (language, filename)
+}
+
+/// Language tag for a file name, from its extension
+fn language_for_file(name: &str) -> Option<String> {
+    let extension = Path::new(name).extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        "sh" | "bash" => "bash",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "go" => "go",
+        "java" => "java",
+        "rb" => "ruby",
+        "xml" => "xml",
+        _ => return None,
+    };
+    Some(language.to_string())
+}
+
+/// File extension for a language tag, for blocks without a file name
+fn extension_for_language(language: &str) -> &'static str {
+    match language {
+        "rust" | "rs" => "rs",
+        "python" | "py" => "py",
+        "javascript" | "js" => "js",
+        "typescript" | "ts" => "ts",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "html" => "html",
+        "css" => "css",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "markdown" | "md" => "md",
+        "bash" | "sh" | "shell" | "zsh" => "sh",
+        "powershell" | "ps1" => "ps1",
+        "sql" => "sql",
+        "c" => "c",
+        "cpp" | "c++" => "cpp",
+        "go" => "go",
+        "java" => "java",
+        "ruby" | "rb" => "rb",
+        "xml" => "xml",
+        _ => "txt",
+    }
+}
+
+/// Relative path to save `block` under: its file name if it's a plain
+/// relative path, otherwise `snippet-N.<ext>`
+fn relative_target(block: &CodeBlock) -> PathBuf {
+    let named = block.filename.as_deref().map(PathBuf::from).filter(|path| {
+        path.components().all(|c| matches!(c, Component::Normal(_)))
+            && path.components().next().is_some()
+    });
+    named.unwrap_or_else(|| {
+        let extension = extension_for_language(block.language.as_deref().unwrap_or_default());
+        PathBuf::from(format!("snippet-{}.{}", block.index + 1, extension))
+    })
+}
+
+/// Write `block` under `dir`, adding `-2`, `-3`... rather than overwriting
+pub fn save_block(block: &CodeBlock, dir: &Path) -> Result<PathBuf> {
+    let relative = relative_target(block);
+    let mut target = dir.join(&relative);
+    let stem = relative
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = relative.extension().map(|e| e.to_string_lossy().into_owned());
+    let mut copy = 1;
+    while target.exists() {
+        copy += 1;
+        let name = match &extension {
+            Some(extension) => format!("{}-{}.{}", stem, copy, extension),
+            None => format!("{}-{}", stem, copy),
+        };
+        target.set_file_name(name);
+    }
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut code = block.code.clone();
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+    std::fs::write(&target, code)
+        .with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(target)
+}
+
+/// Code blocks of a stored message, optionally saved under `save_dir`
+pub fn extract(state: &AppState, message_id: &str, save_dir: Option<&Path>) -> Result<Vec<CodeBlock>> {
+    let message = state
+        .memory_store
+        .lock()
+        .get(message_id)
+        .ok_or_else(|| anyhow!("Message {} not found", message_id))?;
+
+    let mut blocks = parse_code_blocks(&message.content);
+    if let Some(dir) = save_dir {
+        for block in &mut blocks {
+            let path = save_block(block, dir)?;
+            info!("Saved code block {} to {}", block.index, path.display());
+            block.saved_to = Some(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(blocks)
+}
+
+/// Fenced code blocks of a message (`ChatResponse::message_id` or a memory
+/// id); with `save_dir`, each is also written to a file there
+#[tauri::command]
+pub async fn extract_code_blocks(
+    message_id: String,
+    save_dir: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<CodeBlock>, String> {
+    extract(&state, &message_id, save_dir.as_deref().map(Path::new)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_languages_and_filenames() {
+        let reply = "Create `src/config.toml`:\n\n```\nname = \"aura\"\n```\n\nThen:\n\n```rust title=\"src/main.rs\"\nfn main() {}\n```\n\n````markdown\n```js\nnested\n```\n````\n\n```python:app.py\nprint(1)";
+        let blocks = parse_code_blocks(reply);
+        assert_eq!(blocks.len(), 4);
+
+        assert_eq!(blocks[0].filename.as_deref(), Some("src/config.toml"));
+        assert_eq!(blocks[0].language.as_deref(), Some("toml"));
+        assert_eq!(blocks[0].code, "name = \"aura\"");
+
+        assert_eq!(blocks[1].language.as_deref(), Some("rust"));
+        assert_eq!(blocks[1].filename.as_deref(), Some("src/main.rs"));
+
+        assert_eq!(blocks[2].language.as_deref(), Some("markdown"));
+        assert_eq!(blocks[2].code, "```js\nnested\n```");
+        assert_eq!(blocks[2].filename, None);
+
+        // Unterminated fence runs to the end
+        assert_eq!(blocks[3].language.as_deref(), Some("python"));
+        assert_eq!(blocks[3].filename.as_deref(), Some("app.py"));
+        assert_eq!(blocks[3].code, "print(1)");
+    }
+
+    #[test]
+    fn test_save_stays_inside_dir() {
+        let dir = std::env::temp_dir().join(format!("code_blocks_{}", uuid::Uuid::new_v4()));
+        let block = |filename: Option<&str>| CodeBlock {
+            index: 0,
+            language: Some("rust".to_string()),
+            filename: filename.map(str::to_string),
+            code: "fn main() {}".to_string(),
+            saved_to: None,
+        };
+
+        let first = save_block(&block(Some("src/main.rs")), &dir).unwrap();
+        assert_eq!(first, dir.join("src/main.rs"));
+        let second = save_block(&block(Some("src/main.rs")), &dir).unwrap();
+        assert_eq!(second, dir.join("src/main-2.rs"));
+        let escaped = save_block(&block(Some("../evil.rs")), &dir).unwrap();
+        assert_eq!(escaped, dir.join("snippet-1.rs"));
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "fn main() {}\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
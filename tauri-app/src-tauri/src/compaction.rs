@@ -0,0 +1,560 @@
+// Context Compaction Module - Topic-preserving history compression
+//
+// Instead of dropping the oldest messages when a session grows very long, the
+// old part of the history is clustered by embedding similarity and each
+// cluster is summarized into one entry. Prompts carry the summaries in place
+// of the originals, which stay in the visible history and the saved
+// transcript; the summaries are also stored as memories, so every topic
+// discussed keeps a foothold in the prompt.
+//
+// A session's summaries (`compactions.json`) remember the last entry they
+// cover; if that entry is no longer in the history (trimmed, edited), they
+// are ignored until the next pass rebuilds them.
+
+use crate::backend::{GenerationRequest, LlmBackend, SharedBackend};
+use crate::embeddings::{centroid, cosine_similarity, Embedder, HashingEmbedder};
+use crate::generation::{CancellationToken, GenerationTracker};
+use crate::memory_store::MemoryStore;
+use crate::redaction;
+use crate::rolling_summary::EntryKey;
+use crate::session::SessionIds;
+use crate::task_presets::{self, Task};
+use crate::{encryption, paths, ConversationEntry, EntryStatus};
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Role used for compaction summaries in the working history
+pub const SUMMARY_ROLE: &str = "system";
+
+/// Prefix marking a compaction summary in the working history
+pub const SUMMARY_PREFIX: &str = "[Earlier conversation] ";
+
+/// Upper bound on an extractive summary
+const MAX_SUMMARY_CHARS: usize = 600;
+
+/// Guards against overlapping compaction passes
+static COMPACTING: AtomicBool = AtomicBool::new(false);
+
+/// When and how aggressively to compact
+#[derive(Debug, Clone)]
+pub struct CompactionConfig {
+    /// Compact once the history holds more than this many entries
+    pub trigger_messages: usize,
+    /// Number of most recent entries that are never compacted
+    pub keep_recent: usize,
+    /// Minimum cosine similarity for a message to join an existing cluster
+    pub similarity_threshold: f32,
+    /// Maximum number of summaries produced per pass
+    pub max_clusters: usize,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            trigger_messages: 100,
+            keep_recent: 40,
+            similarity_threshold: 0.25,
+            max_clusters: 6,
+        }
+    }
+}
+
+/// Summary of one topical cluster of messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterSummary {
+    pub summary: String,
+    pub message_count: usize,
+    pub first_timestamp: String,
+    pub last_timestamp: String,
+}
+
+impl ClusterSummary {
+    /// Prompt entry standing in for the summarized messages
+    pub fn to_entry(&self) -> ConversationEntry {
+        ConversationEntry {
+            role: SUMMARY_ROLE.to_string(),
+            content: format!("{}{}", SUMMARY_PREFIX, self.summary),
+            timestamp: self.last_timestamp.clone(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+}
+
+/// A session's topic summaries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Compaction {
+    pub summaries: Vec<ClusterSummary>,
+    /// Last entry the summaries cover
+    pub covered_through: EntryKey,
+}
+
+impl Compaction {
+    /// Index of the first history entry after the compacted ones, if they
+    /// still line up with `history`
+    pub fn uncovered_start(&self, history: &[ConversationEntry]) -> Option<usize> {
+        history
+            .iter()
+            .rposition(|entry| EntryKey::of(entry) == self.covered_through)
+            .map(|i| i + 1)
+    }
+}
+
+/// Compactions by run id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CompactionFile {
+    sessions: HashMap<String, Compaction>,
+}
+
+impl CompactionFile {
+    fn path() -> PathBuf {
+        paths::app_data_dir().join("compactions.json")
+    }
+
+    fn load() -> Self {
+        encryption::read_to_string(&Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        encryption::write(&path, serde_json::to_string_pretty(self)?.as_bytes())
+            .with_context(|| format!("Failed to save topic summaries to {}", path.display()))
+    }
+}
+
+/// The topic summaries of session `run_id`, if it has been compacted
+pub fn load(run_id: &str) -> Option<Compaction> {
+    CompactionFile::load().sessions.remove(run_id)
+}
+
+/// Drop the topic summaries of `run_ids`; returns how many sessions had some
+pub fn forget(run_ids: &[String]) -> Result<usize> {
+    let mut file = CompactionFile::load();
+    let before = file.sessions.len();
+    file.sessions.retain(|run_id, _| !run_ids.contains(run_id));
+    let removed = before - file.sessions.len();
+    if removed > 0 {
+        file.save()?;
+    }
+    Ok(removed)
+}
+
+/// What to send for `history`: the topic summaries in place of the entries
+/// they cover (all of `history` if they no longer line up with it)
+pub fn apply(compaction: Option<&Compaction>, history: &[ConversationEntry]) -> Vec<ConversationEntry> {
+    match compaction.and_then(|compaction| Some((compaction, compaction.uncovered_start(history)?))) {
+        Some((compaction, start)) => compaction
+            .summaries
+            .iter()
+            .map(ClusterSummary::to_entry)
+            .chain(history[start..].iter().cloned())
+            .collect(),
+        None => history.to_vec(),
+    }
+}
+
+/// Number of leading entries to compact, if the history is long enough
+pub fn compaction_split(history_len: usize, config: &CompactionConfig) -> Option<usize> {
+    if history_len <= config.trigger_messages || history_len <= config.keep_recent {
+        return None;
+    }
+    Some(history_len - config.keep_recent)
+}
+
+/// Group vectors into topical clusters
+///
+/// Greedy single pass: each vector joins the most similar cluster centroid
+/// above `threshold` or starts a new cluster. If more than `max_clusters`
+/// result, the most similar pair of clusters is merged until within bounds.
+/// Clusters are returned ordered by their first member.
+pub fn cluster(vectors: &[Vec<f32>], threshold: f32, max_clusters: usize) -> Vec<Vec<usize>> {
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut centroids: Vec<Vec<f32>> = Vec::new();
+
+    for (i, vector) in vectors.iter().enumerate() {
+        let best = centroids
+            .iter()
+            .enumerate()
+            .map(|(c, center)| (c, cosine_similarity(vector, center)))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((c, _)) => {
+                clusters[c].push(i);
+                centroids[c] = members_centroid(vectors, &clusters[c]);
+            }
+            None => {
+                clusters.push(vec![i]);
+                centroids.push(vector.clone());
+            }
+        }
+    }
+
+    let max_clusters = max_clusters.max(1);
+    while clusters.len() > max_clusters {
+        let mut best_pair = (0, 1);
+        let mut best_similarity = f32::MIN;
+        for a in 0..centroids.len() {
+            for b in (a + 1)..centroids.len() {
+                let similarity = cosine_similarity(&centroids[a], &centroids[b]);
+                if similarity > best_similarity {
+                    best_similarity = similarity;
+                    best_pair = (a, b);
+                }
+            }
+        }
+
+        let (a, b) = best_pair;
+        let merged = clusters.remove(b);
+        centroids.remove(b);
+        clusters[a].extend(merged);
+        clusters[a].sort_unstable();
+        centroids[a] = members_centroid(vectors, &clusters[a]);
+    }
+
+    clusters.sort_by_key(|members| members[0]);
+    clusters
+}
+
+fn members_centroid(vectors: &[Vec<f32>], members: &[usize]) -> Vec<f32> {
+    let refs: Vec<&[f32]> = members.iter().map(|&i| vectors[i].as_slice()).collect();
+    centroid(&refs)
+}
+
+/// Pick the sentences closest to the cluster's topic, in original order
+pub fn extractive_summary(entries: &[&ConversationEntry], embedder: &dyn Embedder) -> String {
+    let sentences: Vec<&str> = entries
+        .iter()
+        .flat_map(|entry| split_sentences(&entry.content))
+        .collect();
+    if sentences.is_empty() {
+        return String::new();
+    }
+
+    let vectors = embedder.embed_batch(&sentences);
+    let refs: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+    let center = centroid(&refs);
+
+    let mut ranked: Vec<(usize, f32)> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i, cosine_similarity(v, &center)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut chosen: Vec<usize> = Vec::new();
+    let mut length = 0;
+    for (i, _) in ranked {
+        if chosen.len() >= 3 || length + sentences[i].len() > MAX_SUMMARY_CHARS {
+            break;
+        }
+        length += sentences[i].len();
+        chosen.push(i);
+    }
+    if chosen.is_empty() {
+        // Every sentence is too long: fall back to a truncated first sentence
+        return sentences[0].chars().take(MAX_SUMMARY_CHARS).collect();
+    }
+    chosen.sort_unstable();
+
+    chosen
+        .into_iter()
+        .map(|i| sentences[i])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn split_sentences(text: &str) -> Vec<&str> {
+    text.split_inclusive(['.', '!', '?', '\n'])
+        .map(|s| s.trim())
+        .filter(|s| s.len() > 3)
+        .collect()
+}
+
+/// Cluster `entries` by topic and summarize each cluster
+///
+/// `summarize` is tried first for each cluster (e.g. an LLM call); when it
+/// returns `None` an extractive summary is used instead.
+pub fn compact(
+    entries: &[ConversationEntry],
+    config: &CompactionConfig,
+    embedder: &dyn Embedder,
+    mut summarize: impl FnMut(&[&ConversationEntry]) -> Option<String>,
+) -> Vec<ClusterSummary> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+
+    let texts: Vec<&str> = entries.iter().map(|e| e.content.as_str()).collect();
+    let vectors = embedder.embed_batch(&texts);
+    let clusters = cluster(&vectors, config.similarity_threshold, config.max_clusters);
+
+    clusters
+        .into_iter()
+        .map(|members| {
+            let cluster_entries: Vec<&ConversationEntry> =
+                members.iter().map(|&i| &entries[i]).collect();
+
+            let summary = summarize(&cluster_entries)
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| extractive_summary(&cluster_entries, embedder));
+
+            ClusterSummary {
+                summary,
+                message_count: cluster_entries.len(),
+                first_timestamp: cluster_entries[0].timestamp.clone(),
+                last_timestamp: cluster_entries[cluster_entries.len() - 1].timestamp.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Ask the chat backend for a short summary of a cluster, with the
+/// summarization preset
+pub fn summarize_with_llm(backend: &mut dyn LlmBackend, entries: &[&ConversationEntry]) -> Option<String> {
+    let transcript = entries
+        .iter()
+        .map(|entry| format!("{}: {}", entry.role, entry.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = format!(
+        "Summarize the following conversation excerpt in at most three sentences. \
+        Keep names, facts, and decisions.\n\n{}",
+        transcript
+    );
+    let config = task_presets::config_for(Task::Summarization);
+    let request = GenerationRequest {
+        prompt: &prompt,
+        system_prompt: "You write concise, factual summaries.",
+        history: &[],
+        config: &config,
+    };
+    backend
+        .generate(&request, &CancellationToken::new(), &mut |_| true)
+        .ok()
+        .map(|completion| completion.text)
+}
+
+/// Compact the history in the background if the part not yet compacted has
+/// grown past the trigger
+///
+/// The history itself is left alone; the new summaries join the session's
+/// earlier ones for `apply` to use. Summaries come from the chat backend,
+/// which is locked for one cluster at a time; while a reply is being
+/// generated the remaining clusters are summarized extractively instead, so a
+/// message sent meanwhile waits for at most one summary.
+pub fn spawn_compaction(
+    history: Arc<Mutex<Vec<ConversationEntry>>>,
+    memory_store: Arc<Mutex<MemoryStore>>,
+    llm: SharedBackend,
+    generation: Arc<GenerationTracker>,
+    session: SessionIds,
+) {
+    let config = CompactionConfig::default();
+
+    let existing = load(&session.run_id);
+    let (previous, snapshot) = {
+        let history = history.lock();
+        // Summaries that no longer line up are rebuilt from the start
+        let (previous, start) = match existing.and_then(|c| Some((c.uncovered_start(&history)?, c))) {
+            Some((start, compaction)) => (Some(compaction), start),
+            None => (None, 0),
+        };
+        match compaction_split(history.len() - start, &config) {
+            Some(split) => (previous, history[start..start + split].to_vec()),
+            None => return,
+        }
+    };
+
+    if COMPACTING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        info!("Compacting {} old messages...", snapshot.len());
+
+        let embedder = HashingEmbedder::default();
+        let summaries = compact(&snapshot, &config, &embedder, |cluster| {
+            if generation.is_active() {
+                return None;
+            }
+            let mut backend = llm.blocking_lock();
+            backend.as_mut().and_then(|b| summarize_with_llm(b.as_mut(), cluster))
+        });
+
+        let compaction = Compaction {
+            summaries: previous
+                .map(|previous| previous.summaries)
+                .unwrap_or_default()
+                .into_iter()
+                .chain(summaries.iter().cloned())
+                .collect(),
+            covered_through: EntryKey::of(&snapshot[snapshot.len() - 1]),
+        };
+        let mut file = CompactionFile::load();
+        file.sessions.insert(session.run_id.clone(), compaction);
+        if let Err(e) = file.save() {
+            warn!("Failed to persist topic summaries: {}", e);
+        }
+
+        // Summaries quote the raw history, so they're redacted like messages
+        let stored: Vec<String> = summaries
+            .iter()
+            .map(|summary| redaction::scrub_with(&llm, &summary.summary))
+            .collect();
+        let mut memories = memory_store.lock();
+        for (summary, content) in summaries.iter().zip(stored) {
+            let mut metadata = HashMap::new();
+            metadata.insert("kind".to_string(), serde_json::json!("conversation_summary"));
+            metadata.insert("message_count".to_string(), serde_json::json!(summary.message_count));
+            metadata.insert("first_timestamp".to_string(), serde_json::json!(summary.first_timestamp));
+            metadata.insert("last_timestamp".to_string(), serde_json::json!(summary.last_timestamp));
+            let (user_id, agent_id, run_id) = session.memory_ids();
+            if let Err(e) = memories.add(content, user_id, agent_id, run_id, metadata) {
+                warn!("Failed to store topic summary: {}", e);
+            }
+        }
+
+        info!(
+            "Compacted {} messages into {} topic summaries",
+            snapshot.len(),
+            summaries.len()
+        );
+        COMPACTING.store(false, Ordering::SeqCst);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(role: &str, content: &str, ts: usize) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: format!("2024-01-01T00:00:{:02}Z", ts),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compaction_split() {
+        let config = CompactionConfig::default();
+        assert_eq!(compaction_split(10, &config), None);
+        assert_eq!(compaction_split(config.trigger_messages, &config), None);
+        assert_eq!(
+            compaction_split(120, &config),
+            Some(120 - config.keep_recent)
+        );
+    }
+
+    #[test]
+    fn test_apply_leaves_history_alone() {
+        let history = vec![entry("user", "a", 1), entry("assistant", "b", 2), entry("user", "c", 3)];
+        let summary = ClusterSummary {
+            summary: "Talked about a and b.".to_string(),
+            message_count: 2,
+            first_timestamp: history[0].timestamp.clone(),
+            last_timestamp: history[1].timestamp.clone(),
+        };
+        let compaction = Compaction {
+            summaries: vec![summary],
+            covered_through: EntryKey::of(&history[1]),
+        };
+
+        let sent = apply(Some(&compaction), &history);
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].content.starts_with(SUMMARY_PREFIX));
+        assert_eq!(sent[1].content, "c");
+        // Once the covered entry is gone the summaries no longer apply
+        assert_eq!(apply(Some(&compaction), &history[2..]).len(), 1);
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn test_cluster_separates_topics() {
+        let embedder = HashingEmbedder::default();
+        let texts = [
+            "My garden tomatoes are ripening early this year",
+            "Filing quarterly taxes with my accountant tomorrow",
+            "Should I prune the garden tomatoes before ripening",
+            "The accountant says quarterly taxes are due soon",
+        ];
+        let vectors = embedder.embed_batch(&texts);
+        let clusters = cluster(&vectors, 0.2, 10);
+
+        assert_eq!(clusters, vec![vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_cluster_respects_max_clusters() {
+        let embedder = HashingEmbedder::default();
+        let texts = ["apples", "bicycles", "volcanoes", "symphonies", "glaciers"];
+        let vectors = embedder.embed_batch(&texts);
+
+        let clusters = cluster(&vectors, 0.9, 2);
+        assert_eq!(clusters.len(), 2);
+        let total: usize = clusters.iter().map(|c| c.len()).sum();
+        assert_eq!(total, texts.len());
+    }
+
+    #[test]
+    fn test_compact_falls_back_to_extractive() {
+        let entries = vec![
+            entry("user", "My garden tomatoes are ripening early this year.", 1),
+            entry("assistant", "Ripening tomatoes in the garden need steady watering.", 2),
+            entry("user", "I need to file quarterly taxes with my accountant.", 3),
+            entry("assistant", "Your accountant can help with quarterly taxes.", 4),
+        ];
+
+        let summaries = compact(
+            &entries,
+            &CompactionConfig {
+                similarity_threshold: 0.2,
+                ..Default::default()
+            },
+            &HashingEmbedder::default(),
+            |_| None,
+        );
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].message_count, 2);
+        assert!(summaries[0].summary.contains("tomatoes"));
+        assert!(summaries[1].summary.contains("taxes"));
+        assert_eq!(summaries[1].first_timestamp, entries[2].timestamp);
+    }
+
+    #[test]
+    fn test_summary_entry_is_marked() {
+        let summary = ClusterSummary {
+            summary: "Talked about tomatoes.".to_string(),
+            message_count: 2,
+            first_timestamp: "a".to_string(),
+            last_timestamp: "b".to_string(),
+        };
+        let entry = summary.to_entry();
+        assert_eq!(entry.role, SUMMARY_ROLE);
+        assert!(entry.content.starts_with(SUMMARY_PREFIX));
+    }
+}
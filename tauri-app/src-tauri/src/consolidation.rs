@@ -0,0 +1,334 @@
+// Consolidation Module - Merge duplicate facts, supersede contradicted ones
+//
+// Fact extraction checks each new fact against the few closest stored ones,
+// so duplicates ("User has a dog named Rex" / "User's dog is Rex") and stale
+// facts ("User lives in Leeds" after moving to York) still pile up.
+// `consolidate_memories` clusters each persona's facts by embedding
+// similarity and asks the model (extraction preset) whether each cluster
+// holds duplicates, a contradiction or distinct facts. Duplicates are merged
+// and contradictions resolved in favour of what the model judges current:
+// the most recently updated memory is kept with the resulting text, the
+// others are deleted (their content stays in the version history), and the
+// kept memory records where it came from in `consolidated_from`. Clusters
+// whose facts say the same thing word for word are merged without asking.
+// Only facts are consolidated - messages and documents are records. It runs
+// on demand and every `interval_hours` while the app is open.
+
+use crate::backend::{LlmBackend, SharedBackend};
+use crate::compaction::cluster;
+use crate::fact_extraction::{normalize, FACT_KIND};
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use crate::session::LOCAL_USER_ID;
+use crate::settings::AppSettings;
+use crate::task_presets::{self, Task};
+use crate::{structured, AppState};
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Manager;
+use tracing::{info, warn};
+
+/// Facts at least this similar are judged together
+const SIMILARITY_THRESHOLD: f32 = 0.85;
+
+/// Largest cluster shown to the model; the rest wait for the next run
+const MAX_GROUP: usize = 8;
+
+const JUDGE_PROMPT: &str = "You tidy a memory of facts about the user. Decide how the numbered facts relate: \
+\"duplicate\" when they say the same thing, \"contradiction\" when a newer one replaces an older one, or \
+\"distinct\" when each says something different and all should be kept. Unless distinct, give in \"text\" the \
+single fact that should be kept, as a short standalone sentence - the merged wording for duplicates, the current \
+truth for contradictions (newer facts win unless they say otherwise).";
+
+/// Guards against overlapping runs
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// `[consolidation]` in settings.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsolidationSettings {
+    /// Consolidate in the background every `interval_hours`
+    pub scheduled: bool,
+    pub interval_hours: u64,
+}
+
+impl Default for ConsolidationSettings {
+    fn default() -> Self {
+        Self {
+            scheduled: true,
+            interval_hours: 24,
+        }
+    }
+}
+
+/// How the facts of a cluster relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    Duplicate,
+    Contradiction,
+    Distinct,
+}
+
+/// What to do with one cluster
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub relation: Relation,
+    /// Memory kept, with `text` as its content
+    pub keep: String,
+    pub text: String,
+    /// Memories folded into `keep`
+    pub remove: Vec<String>,
+}
+
+/// What a run changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ConsolidationReport {
+    /// Clusters of similar facts looked at
+    pub groups: usize,
+    pub merged: usize,
+    pub superseded: usize,
+    /// Memories deleted by merging or superseding
+    pub removed: usize,
+}
+
+fn judgment_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "relation": { "enum": ["duplicate", "contradiction", "distinct"] },
+            "text": { "type": "string" }
+        },
+        "required": ["relation", "text"]
+    })
+}
+
+/// Clusters of at least two similar facts, each from one persona
+pub fn find_groups(memories: &[MemoryItem]) -> Vec<Vec<MemoryItem>> {
+    let mut by_agent: BTreeMap<Option<&str>, Vec<&MemoryItem>> = BTreeMap::new();
+    for memory in memories.iter().filter(|memory| memory.embedding.is_some()) {
+        by_agent.entry(memory.agent_id.as_deref()).or_default().push(memory);
+    }
+
+    let mut groups = Vec::new();
+    for facts in by_agent.into_values() {
+        // Vectors from an older embedder can't be compared with the rest
+        let dimensions = facts[0].embedding.as_ref().map_or(0, Vec::len);
+        let facts: Vec<&MemoryItem> = facts
+            .into_iter()
+            .filter(|memory| memory.embedding.as_ref().map(Vec::len) == Some(dimensions))
+            .collect();
+        let vectors: Vec<Vec<f32>> = facts.iter().filter_map(|memory| memory.embedding.clone()).collect();
+        for members in cluster(&vectors, SIMILARITY_THRESHOLD, vectors.len()) {
+            if members.len() > 1 {
+                groups.push(members.into_iter().take(MAX_GROUP).map(|i| facts[i].clone()).collect());
+            }
+        }
+    }
+    groups
+}
+
+/// The resolution for `group` given the model's (or the exact-match) verdict
+pub fn resolve(group: &[MemoryItem], relation: Relation, text: &str) -> Option<Resolution> {
+    let text = text.trim();
+    if relation == Relation::Distinct || text.is_empty() {
+        return None;
+    }
+    let keep = group.iter().max_by_key(|memory| memory.updated_at)?;
+    Some(Resolution {
+        relation,
+        keep: keep.id.clone(),
+        text: text.to_string(),
+        remove: group
+            .iter()
+            .filter(|memory| memory.id != keep.id)
+            .map(|memory| memory.id.clone())
+            .collect(),
+    })
+}
+
+/// Facts that differ only in case and punctuation need no judgment
+fn exact_duplicate(group: &[MemoryItem]) -> Option<Resolution> {
+    let first = normalize(&group[0].content);
+    if !group.iter().all(|memory| normalize(&memory.content) == first) {
+        return None;
+    }
+    let newest = group.iter().max_by_key(|memory| memory.updated_at)?;
+    resolve(group, Relation::Duplicate, &newest.content)
+}
+
+/// Ask the model how the facts of `group` relate
+fn judge(backend: &mut dyn LlmBackend, group: &[MemoryItem]) -> Result<Option<Resolution>> {
+    let facts: Vec<String> = group
+        .iter()
+        .enumerate()
+        .map(|(i, memory)| {
+            let updated = chrono::DateTime::<chrono::Utc>::from(memory.updated_at).format("%Y-%m-%d");
+            format!("{}. ({}) {}", i + 1, updated, memory.content)
+        })
+        .collect();
+    let config = task_presets::config_for(Task::Extraction);
+    let reply = structured::generate(backend, &facts.join("\n"), JUDGE_PROMPT, &judgment_schema(), &config)?;
+    let relation: Relation =
+        serde_json::from_value(reply.value["relation"].clone()).context("Unexpected relation")?;
+    Ok(resolve(group, relation, reply.value["text"].as_str().unwrap_or_default()))
+}
+
+/// Carry out `resolution`; false if the cluster changed since it was read
+pub fn apply(store: &mut MemoryStore, group: &[MemoryItem], resolution: &Resolution) -> Result<bool> {
+    let unchanged = group.iter().all(|memory| {
+        store
+            .get(&memory.id)
+            .is_some_and(|current| current.updated_at == memory.updated_at)
+    });
+    if !unchanged {
+        return Ok(false);
+    }
+
+    let kept = store.get(&resolution.keep).ok_or_else(|| anyhow!("Memory {} is gone", resolution.keep))?;
+    let mut sources: Vec<Value> = kept
+        .metadata
+        .get("consolidated_from")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    sources.extend(resolution.remove.iter().map(|id| json!(id)));
+    let metadata = HashMap::from([
+        ("consolidated_from".to_string(), Value::Array(sources)),
+        ("consolidation".to_string(), json!(resolution.relation)),
+        ("consolidated_at".to_string(), json!(crate::clock::timestamp())),
+    ]);
+    let content = (kept.content != resolution.text).then(|| resolution.text.clone());
+    store.update(&resolution.keep, content, Some(metadata))?;
+    store.delete_batch(&resolution.remove);
+    Ok(true)
+}
+
+/// Consolidate `user_id`'s facts; the model is only locked while judging
+pub fn consolidate(llm: &SharedBackend, store: &Mutex<MemoryStore>, user_id: &str) -> Result<ConsolidationReport> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(anyhow!("Consolidation is already running"));
+    }
+    let filters = MemoryFilters {
+        user_id: Some(user_id.to_string()),
+        metadata: HashMap::from([("kind".to_string(), json!(FACT_KIND))]),
+        ..Default::default()
+    };
+    let groups = find_groups(&store.lock().get_all(&filters, usize::MAX));
+
+    let mut report = ConsolidationReport {
+        groups: groups.len(),
+        ..Default::default()
+    };
+    for group in &groups {
+        let resolution = match exact_duplicate(group) {
+            Some(resolution) => Some(resolution),
+            None => {
+                let mut llm = llm.blocking_lock();
+                let Some(backend) = llm.as_mut() else {
+                    warn!("No model loaded; only exact duplicates were consolidated");
+                    break;
+                };
+                judge(backend.as_mut(), group).unwrap_or_else(|e| {
+                    warn!("Couldn't judge a cluster of {} facts: {:#}", group.len(), e);
+                    None
+                })
+            }
+        };
+        let Some(resolution) = resolution else { continue };
+        match apply(&mut store.lock(), group, &resolution) {
+            Ok(true) => {
+                match resolution.relation {
+                    Relation::Contradiction => report.superseded += 1,
+                    _ => report.merged += 1,
+                }
+                report.removed += resolution.remove.len();
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to consolidate a cluster: {}", e),
+        }
+    }
+    RUNNING.store(false, Ordering::SeqCst);
+    Ok(report)
+}
+
+fn log_report(report: &ConsolidationReport) {
+    info!(
+        "Consolidated memories: {} clusters, {} merged, {} superseded, {} removed",
+        report.groups, report.merged, report.superseded, report.removed
+    );
+}
+
+/// Consolidate every `interval_hours` while scheduling is on
+pub fn spawn_scheduler(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let settings = AppSettings::load().consolidation;
+        std::thread::sleep(Duration::from_secs(settings.interval_hours.max(1) * 3600));
+        if !AppSettings::load().consolidation.scheduled {
+            continue;
+        }
+        let state = app.state::<AppState>();
+        match consolidate(&state.llm, &state.memory_store, LOCAL_USER_ID) {
+            Ok(report) => log_report(&report),
+            Err(e) => warn!("Scheduled consolidation failed: {:#}", e),
+        }
+    });
+}
+
+/// Merge duplicate facts and resolve contradictions now
+#[tauri::command]
+pub async fn consolidate_memories(state: tauri::State<'_, AppState>) -> Result<ConsolidationReport, String> {
+    let llm = state.llm.clone();
+    let store = state.memory_store.clone();
+    let user_id = state.session.lock().user_id.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || consolidate(&llm, &store, &user_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+    log_report(&report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_and_merge() {
+        let mut store = MemoryStore::new();
+        let fact = |store: &mut MemoryStore, text: &str, agent: &str, embedding: Vec<f32>| {
+            let metadata = HashMap::from([("kind".to_string(), json!(FACT_KIND))]);
+            let id = store
+                .add(text, Some(LOCAL_USER_ID.to_string()), Some(agent.to_string()), None, metadata)
+                .unwrap();
+            store.set_embedding(&id, embedding);
+            std::thread::sleep(Duration::from_millis(2));
+            id
+        };
+        let old = fact(&mut store, "User's dog is named Rex", "companion", vec![1.0, 0.0]);
+        let new = fact(&mut store, "user's dog is named Rex!", "companion", vec![0.99, 0.05]);
+        fact(&mut store, "User's dog is named Rex", "youniverse", vec![1.0, 0.0]);
+        fact(&mut store, "User plays chess", "companion", vec![0.0, 1.0]);
+
+        let groups = find_groups(&store.get_all(&MemoryFilters::default(), usize::MAX));
+        assert_eq!(groups.len(), 1);
+        let resolution = exact_duplicate(&groups[0]).unwrap();
+        assert_eq!(resolution.keep, new);
+        assert_eq!(resolution.remove, [old.clone()]);
+        assert!(resolve(&groups[0], Relation::Distinct, "whatever").is_none());
+
+        assert!(apply(&mut store, &groups[0], &resolution).unwrap());
+        assert!(store.get(&old).is_none());
+        let kept = store.get(&new).unwrap();
+        assert_eq!(kept.metadata["consolidated_from"], json!([old]));
+        assert_eq!(kept.metadata["consolidation"], "duplicate");
+        // The deleted fact stays in the version history
+        assert_eq!(store.history(&old)[0].memory.content, "User's dog is named Rex");
+        // A second pass over the stale cluster changes nothing
+        assert!(!apply(&mut store, &groups[0], &resolution).unwrap());
+    }
+}
@@ -0,0 +1,149 @@
+// Content Module - Typed message content
+//
+// A message's `parts` say what it holds beyond plain text: images, tool
+// calls and their results, system notes and attached files. They serialize
+// as `{"type": "...", ...}` objects. `content` stays the plain-text rendering
+// that prompts, search and exports read, so entries without parts (all
+// history written before this) keep working unchanged.
+
+use crate::ConversationEntry;
+use serde::{Deserialize, Serialize};
+
+/// One piece of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    /// An image stored on disk
+    Image {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alt: Option<String>,
+    },
+    /// The model asking for a tool to run
+    ToolCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        tool: String,
+        #[serde(default)]
+        arguments: serde_json::Value,
+    },
+    /// What a tool returned
+    ToolResult {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        tool: String,
+        output: String,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// App-generated notice (mode switch, summary, ...), not said by anyone
+    SystemNote {
+        text: String,
+    },
+    /// A file attached to the message
+    File {
+        path: String,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size_bytes: Option<u64>,
+    },
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    /// How the part reads in a plain-text transcript
+    pub fn to_plain_text(&self) -> String {
+        match self {
+            Self::Text { text } => text.clone(),
+            Self::Image { alt: Some(alt), .. } => format!("[image: {}]", alt),
+            Self::Image { path, .. } => format!("[image: {}]", path),
+            Self::ToolCall { tool, arguments, .. } => format!("[tool call: {} {}]", tool, arguments),
+            Self::ToolResult { tool, output, is_error, .. } => {
+                let label = if *is_error { "tool error" } else { "tool result" };
+                format!("[{}: {}] {}", label, tool, output)
+            }
+            Self::SystemNote { text } => format!("[note] {}", text),
+            Self::File { name, .. } => format!("[file: {}]", name),
+        }
+    }
+}
+
+/// Plain-text rendering of `parts`, one part per paragraph
+pub fn to_plain_text(parts: &[ContentPart]) -> String {
+    parts
+        .iter()
+        .map(ContentPart::to_plain_text)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+impl ConversationEntry {
+    /// The entry's parts, or its text as a single part if it has none
+    pub fn content_parts(&self) -> Vec<ContentPart> {
+        if self.parts.is_empty() {
+            vec![ContentPart::text(self.content.clone())]
+        } else {
+            self.parts.clone()
+        }
+    }
+
+    /// Replace the entry's content with `parts` (and their text rendering)
+    pub fn set_parts(&mut self, parts: Vec<ContentPart>) {
+        self.content = to_plain_text(&parts);
+        self.parts = parts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+
+    #[test]
+    fn test_tagged_parts_and_plain_text() {
+        let json = r#"[
+            {"type": "text", "text": "Here is the chart"},
+            {"type": "image", "path": "charts/sales.png", "alt": "sales chart"},
+            {"type": "tool_result", "tool": "calculator", "output": "42"}
+        ]"#;
+        let parts: Vec<ContentPart> = serde_json::from_str(json).unwrap();
+        assert_eq!(parts[2], ContentPart::ToolResult {
+            call_id: None,
+            tool: "calculator".to_string(),
+            output: "42".to_string(),
+            is_error: false,
+        });
+        assert_eq!(
+            serde_json::to_value(&parts[0]).unwrap(),
+            serde_json::json!({"type": "text", "text": "Here is the chart"})
+        );
+
+        let mut entry = ConversationEntry {
+            role: "assistant".to_string(),
+            content: "old".to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        };
+        assert_eq!(entry.content_parts(), vec![ContentPart::text("old")]);
+
+        entry.set_parts(parts);
+        assert_eq!(
+            entry.content,
+            "Here is the chart\n\n[image: sales chart]\n\n[tool result: calculator] 42"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// Context Window Module - Keeping long chats inside the native context
+//
+// The native backend runs with a fixed context (4096 tokens). Before a reply,
+// the oldest history turns are dropped until the prompt fits with room left
+// to answer; the system prompt is always kept. If the reply itself reaches
+// the end of the context, half of what follows the system prompt is evicted
+// from the KV cache and the rest shifted down (llama.cpp's context shift), so
+// generation carries on instead of failing.
+
+/// Template tokens around each message (role header, end marker), roughly
+pub const TURN_OVERHEAD: usize = 8;
+
+/// Index of the first history turn to keep so that `fixed` tokens (system
+/// prompt and new message) plus the kept turns fit in `budget`
+pub fn first_kept_turn(fixed: usize, turn_tokens: &[usize], budget: usize) -> usize {
+    let mut total = fixed + turn_tokens.iter().sum::<usize>();
+    let mut start = 0;
+    while total > budget && start < turn_tokens.len() {
+        total -= turn_tokens[start];
+        start += 1;
+    }
+    start
+}
+
+/// Cut `tokens` down to `budget` by dropping the ones right after the first
+/// `keep` (the system prompt), so the most recent text survives
+pub fn truncate_middle<T>(tokens: &mut Vec<T>, keep: usize, budget: usize) {
+    if tokens.len() <= budget {
+        return;
+    }
+    let keep = keep.min(budget);
+    let excess = tokens.len() - budget;
+    tokens.drain(keep..keep + excess);
+}
+
+/// Tokens to evict once `n_past` has filled an `n_ctx` context, keeping the
+/// first `n_keep`; `None` while there is still room (or nothing to evict)
+pub fn shift_amount(n_past: usize, n_ctx: usize, n_keep: usize) -> Option<usize> {
+    if n_past < n_ctx {
+        return None;
+    }
+    let discard = n_past.saturating_sub(n_keep) / 2;
+    (discard > 0).then_some(discard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fitting_keeps_system_prompt_and_recent_turns() {
+        // Fits as is
+        assert_eq!(first_kept_turn(100, &[50, 50], 300), 0);
+        // Oldest turns go first
+        assert_eq!(first_kept_turn(100, &[500, 80, 60, 40], 300), 1);
+        assert_eq!(first_kept_turn(100, &[500, 80, 60, 40], 250), 2);
+        // Even the fixed part alone is too long: drop all history
+        assert_eq!(first_kept_turn(400, &[10, 10], 300), 2);
+
+        let mut tokens: Vec<u32> = (0..10).collect();
+        truncate_middle(&mut tokens, 3, 6);
+        assert_eq!(tokens, vec![0, 1, 2, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_shift_when_context_is_full() {
+        assert_eq!(shift_amount(4000, 4096, 200), None);
+        assert_eq!(shift_amount(4096, 4096, 200), Some(1948));
+        assert_eq!(shift_amount(4096, 4096, 4096), None);
+    }
+}
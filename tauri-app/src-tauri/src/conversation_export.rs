@@ -0,0 +1,202 @@
+// Conversation Export Module - Markdown and JSON transcripts
+//
+// `export_conversation` writes a session's full history, with timestamps and
+// the mode it ran in, as clean Markdown (for reading and sharing) or JSON (for
+// archiving and other tools); "html" goes through the HTML export. The
+// transcript comes from `html_export::load_transcript`, so the current,
+// archived and older sessions can all be exported.
+
+use crate::html_export::{self, display_mode, display_timestamp, Transcript};
+use crate::tool_calls::ToolInvocation;
+use crate::{AppState, ConversationEntry, EntryStatus};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Layout of an exported transcript
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            other => Err(anyhow!("Unknown export format '{}' (use markdown, json or html)", other)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// JSON export layout
+#[derive(Debug, Serialize)]
+struct ExportedConversation<'a> {
+    session_id: &'a str,
+    mode: &'a str,
+    exported_at: String,
+    message_count: usize,
+    entries: &'a [ConversationEntry],
+}
+
+fn speaker(entry: &ConversationEntry, mode: &str) -> String {
+    match entry.role.as_str() {
+        "user" => "You".to_string(),
+        "system" => "Summary".to_string(),
+        _ => mode.to_string(),
+    }
+}
+
+/// One line per tool the assistant ran, as a blockquote
+fn render_tool_calls(calls: &[ToolInvocation]) -> String {
+    calls
+        .iter()
+        .map(|call| match &call.error {
+            Some(error) => format!("> Tool `{}` failed after {} ms: {}\n", call.tool, call.duration_ms, error),
+            None => format!("> Tool `{}` ({} ms) with `{}`\n", call.tool, call.duration_ms, call.arguments),
+        })
+        .collect()
+}
+
+/// Render a transcript as a Markdown document
+pub fn render_markdown(transcript: &Transcript) -> String {
+    let mode = display_mode(&transcript.mode);
+    let started = transcript
+        .entries
+        .first()
+        .map(|entry| display_timestamp(&entry.timestamp))
+        .unwrap_or_default();
+
+    let mut markdown = format!(
+        "# {} conversation\n\n- Session: `{}`\n- Mode: {}\n- Started: {}\n- Messages: {}\n",
+        mode,
+        transcript.session_id,
+        mode,
+        started,
+        transcript.entries.len()
+    );
+    for entry in &transcript.entries {
+        let status = match entry.status {
+            EntryStatus::Complete => "",
+            EntryStatus::Interrupted => " _(interrupted)_",
+            EntryStatus::Incomplete => " _(incomplete)_",
+        };
+        markdown.push_str(&format!(
+            "\n---\n\n### {} · {}{}\n\n",
+            speaker(entry, &mode),
+            display_timestamp(&entry.timestamp),
+            status
+        ));
+        let tools = render_tool_calls(&entry.tool_calls);
+        if !tools.is_empty() {
+            markdown.push_str(&tools);
+            markdown.push('\n');
+        }
+        markdown.push_str(entry.content.trim());
+        markdown.push('\n');
+    }
+    markdown.push_str(&format!(
+        "\n---\n\n_Exported from AuraNexus on {}_\n",
+        crate::clock::local_now().format("%Y-%m-%d %H:%M")
+    ));
+    markdown
+}
+
+/// Render a transcript as pretty-printed JSON
+pub fn render_json(transcript: &Transcript) -> Result<String> {
+    let exported = ExportedConversation {
+        session_id: &transcript.session_id,
+        mode: &transcript.mode,
+        exported_at: crate::clock::timestamp(),
+        message_count: transcript.entries.len(),
+        entries: &transcript.entries,
+    };
+    Ok(serde_json::to_string_pretty(&exported)?)
+}
+
+/// Export a session as "markdown", "json" or "html"; returns the file path
+#[tauri::command]
+pub async fn export_conversation(
+    session_id: String,
+    format: String,
+    path: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let format = ExportFormat::parse(&format).map_err(|e| e.to_string())?;
+    let transcript = html_export::load_transcript(&state, &session_id).map_err(|e| e.to_string())?;
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| html_export::default_export_path(&session_id, format.extension()));
+
+    let write = || -> Result<()> {
+        let contents = match format {
+            ExportFormat::Markdown => render_markdown(&transcript),
+            ExportFormat::Json => render_json(&transcript)?,
+            ExportFormat::Html => html_export::render_html(&transcript),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    };
+    write().map_err(|e| e.to_string())?;
+
+    info!("Exported conversation as {} to {}", format.extension(), path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_and_json() {
+        let entry = |role: &str, content: &str, status: EntryStatus| ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: "2024-05-01T10:00:00+00:00".to_string(),
+            quality_score: None,
+            status,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        };
+        let mut reply = entry("assistant", "It's sunny.\n", EntryStatus::Interrupted);
+        reply.tool_calls.push(ToolInvocation {
+            tool: "web_search".to_string(),
+            arguments: serde_json::json!({"q": "weather"}),
+            duration_ms: 120,
+            ..Default::default()
+        });
+        let transcript = Transcript {
+            session_id: "abc".to_string(),
+            mode: "companion".to_string(),
+            entries: vec![entry("user", "Weather?", EntryStatus::Complete), reply],
+        };
+
+        let markdown = render_markdown(&transcript);
+        assert!(markdown.starts_with("# Companion conversation\n"));
+        assert!(markdown.contains("- Messages: 2\n"));
+        assert!(markdown.contains("### You · "));
+        assert!(markdown.contains(" _(interrupted)_\n\n> Tool `web_search` (120 ms) with `{\"q\":\"weather\"}`\n\nIt's sunny.\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&render_json(&transcript).unwrap()).unwrap();
+        assert_eq!(json["mode"], "companion");
+        assert_eq!(json["message_count"], 2);
+        assert_eq!(json["entries"][1]["status"], serde_json::to_value(EntryStatus::Interrupted).unwrap());
+
+        assert_eq!(ExportFormat::parse("MD").unwrap(), ExportFormat::Markdown);
+        assert!(ExportFormat::parse("pdf").is_err());
+    }
+}
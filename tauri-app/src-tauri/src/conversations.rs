@@ -0,0 +1,614 @@
+// Conversations Module - Several independent chats, one active at a time
+//
+// The active conversation lives in `AppState` (history, mode, session ids) and
+// is persisted by `HistoryStore` as before. The others are parked in
+// `conversations/<id>.json` with their own history and session ids; switching
+// parks the active one and loads the target. `index.json` keeps the list with
+// each conversation's title, mode and sampling overrides.
+
+use crate::clock::{self, dated, Dated, SystemClock};
+use crate::encryption;
+use crate::sampling_presets;
+use crate::session::SessionIds;
+use crate::{AppMode, AppState, ConversationEntry, LlmConfig};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+const DEFAULT_TITLE: &str = "New conversation";
+
+/// Listing details of one conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMeta {
+    pub id: String,
+    pub title: String,
+    /// Mode/persona the conversation runs in (e.g. "companion")
+    pub mode: String,
+    /// Sampling settings; `None` uses the preset, else the mode's defaults
+    pub config: Option<LlmConfig>,
+    /// Sampling preset selected with `set_sampling_preset`
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Conversation this one branched from by editing a message
+    #[serde(default)]
+    pub branch_of: Option<String>,
+    /// Messages shared with `branch_of` (the edited message was the next)
+    #[serde(default)]
+    pub branch_point: Option<usize>,
+    /// Privacy mode: turns are kept in the history only (see `privacy`)
+    #[serde(default)]
+    pub private: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    pub message_count: usize,
+    /// Start of the first user message
+    pub preview: String,
+}
+
+/// `ConversationMeta` plus whether it is the active conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationInfo {
+    #[serde(flatten)]
+    pub meta: ConversationMeta,
+    pub active: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ConversationIndex {
+    active: String,
+    conversations: Vec<ConversationMeta>,
+}
+
+/// History and session ids of a parked conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredConversation {
+    pub session: SessionIds,
+    pub entries: Vec<ConversationEntry>,
+}
+
+/// Index of conversations plus storage for the inactive ones
+pub struct SessionManager {
+    dir: PathBuf,
+    index: ConversationIndex,
+}
+
+impl SessionManager {
+    /// Open (creating if needed) the conversations in `dir`
+    ///
+    /// The first launch adopts the current history as the active conversation.
+    pub fn open(
+        dir: impl Into<PathBuf>,
+        session: &SessionIds,
+        history: &[ConversationEntry],
+    ) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create conversations directory {}", dir.display()))?;
+
+        let index = encryption::read_to_string(&dir.join("index.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let mut manager = Self { dir, index };
+
+        if manager.find(&manager.index.active).is_none() {
+            let meta = new_meta(DEFAULT_TITLE, &session.agent_id, None);
+            manager.index.active = meta.id.clone();
+            manager.index.conversations.push(meta);
+        }
+        manager.refresh_active(history);
+        manager.save()?;
+        Ok(manager)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn conversation_path(&self, id: &str) -> Result<PathBuf> {
+        // Ids are UUIDs; anything else could escape the directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(anyhow!("Invalid conversation id: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.index)?;
+        encryption::write(&self.index_path(), json.as_bytes())
+    }
+
+    fn find(&self, id: &str) -> Option<usize> {
+        self.index.conversations.iter().position(|meta| meta.id == id)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut ConversationMeta> {
+        let position = self.find(id).ok_or_else(|| anyhow!("No conversation {}", id))?;
+        Ok(&mut self.index.conversations[position])
+    }
+
+    pub fn active_id(&self) -> &str {
+        &self.index.active
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ConversationMeta> {
+        self.find(id).map(|position| &self.index.conversations[position])
+    }
+
+    /// Sampling settings of the active conversation, if it overrides the
+    /// mode's (its own, else its preset's)
+    pub fn active_config(&self) -> Option<LlmConfig> {
+        let meta = self.get(&self.index.active)?;
+        meta.config
+            .clone()
+            .or_else(|| meta.preset.as_deref().and_then(sampling_presets::config))
+    }
+
+    /// All conversations, most recently updated first
+    pub fn list(&self) -> Vec<ConversationInfo> {
+        let mut list: Vec<ConversationInfo> = self
+            .index
+            .conversations
+            .iter()
+            .map(|meta| ConversationInfo {
+                meta: meta.clone(),
+                active: meta.id == self.index.active,
+            })
+            .collect();
+        list.sort_by(|a, b| b.meta.updated_at.cmp(&a.meta.updated_at));
+        list
+    }
+
+    /// Update the active conversation's count, preview and time from `history`
+    pub fn refresh_active(&mut self, history: &[ConversationEntry]) {
+        let active = self.index.active.clone();
+        if let Ok(meta) = self.get_mut(&active) {
+            describe(meta, history);
+        }
+    }
+
+    /// Drop the active conversation's preview after its history is wiped
+    pub fn clear_active(&mut self) -> Result<()> {
+        self.refresh_active(&[]);
+        self.save()
+    }
+
+    /// Record a mode switch in the active conversation
+    pub fn set_active_mode(&mut self, mode: &str) -> Result<()> {
+        let active = self.index.active.clone();
+        self.get_mut(&active)?.mode = mode.to_string();
+        self.save()
+    }
+
+    /// Add an empty conversation (not yet active)
+    pub fn create(&mut self, title: &str, mode: &str, config: Option<LlmConfig>) -> Result<ConversationMeta> {
+        let meta = new_meta(title, mode, config);
+        self.park(
+            &meta.id,
+            &StoredConversation {
+                session: SessionIds::new(mode),
+                entries: Vec::new(),
+            },
+        )?;
+        self.index.conversations.push(meta.clone());
+        self.save()?;
+        Ok(meta)
+    }
+
+    /// Select a sampling preset (`None` for the mode's sampling), dropping
+    /// the conversation's own sampling settings
+    pub fn set_preset(&mut self, id: &str, preset: Option<String>) -> Result<ConversationMeta> {
+        let meta = self.get_mut(id)?;
+        meta.preset = preset;
+        meta.config = None;
+        let meta = meta.clone();
+        self.save()?;
+        Ok(meta)
+    }
+
+    /// Turn privacy mode on or off for a conversation
+    pub fn set_private(&mut self, id: &str, private: bool) -> Result<ConversationMeta> {
+        let meta = self.get_mut(id)?;
+        meta.private = private;
+        let meta = meta.clone();
+        self.save()?;
+        Ok(meta)
+    }
+
+    /// Whether the active conversation is in privacy mode
+    pub fn active_private(&self) -> bool {
+        self.get(&self.index.active).map_or(false, |meta| meta.private)
+    }
+
+    /// Add a conversation (not yet active) continuing from the first
+    /// `entries` of `from`, with its mode and sampling
+    pub fn branch(&mut self, from: &str, entries: Vec<ConversationEntry>) -> Result<ConversationMeta> {
+        let source = self.get(from).ok_or_else(|| anyhow!("No conversation {}", from))?;
+        let mut meta = new_meta(&format!("{} (branch)", source.title), &source.mode, source.config.clone());
+        meta.preset = source.preset.clone();
+        meta.private = source.private;
+        meta.branch_of = Some(from.to_string());
+        meta.branch_point = Some(entries.len());
+        self.park(
+            &meta.id,
+            &StoredConversation {
+                session: SessionIds::new(meta.mode.clone()),
+                entries,
+            },
+        )?;
+        self.index.conversations.push(meta.clone());
+        self.save()?;
+        Ok(meta)
+    }
+
+    /// Add a conversation (not yet active) brought in from elsewhere, dated
+    /// `created_at` when known
+    pub fn import(
+        &mut self,
+        title: &str,
+        created_at: Option<&str>,
+        conversation: &StoredConversation,
+    ) -> Result<ConversationMeta> {
+        let mut meta = new_meta(title, &conversation.session.agent_id, None);
+        // Sorted by when they were last used, not when they were imported
+        if let Some(created_at) = created_at {
+            meta.created_at = created_at.to_string();
+            meta.updated_at = created_at.to_string();
+        }
+        describe(&mut meta, &conversation.entries);
+        self.park(&meta.id, conversation)?;
+        self.index.conversations.push(meta.clone());
+        self.save()?;
+        Ok(meta)
+    }
+
+    pub fn rename(&mut self, id: &str, title: &str) -> Result<ConversationMeta> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(anyhow!("Conversation title can't be empty"));
+        }
+        let meta = self.get_mut(id)?;
+        meta.title = title.to_string();
+        let meta = meta.clone();
+        self.save()?;
+        Ok(meta)
+    }
+
+    /// Delete an inactive conversation and its history
+    pub fn remove(&mut self, id: &str) -> Result<()> {
+        if id == self.index.active {
+            return Err(anyhow!("Can't remove the active conversation"));
+        }
+        let position = self.find(id).ok_or_else(|| anyhow!("No conversation {}", id))?;
+        let path = self.conversation_path(id)?;
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+        self.index.conversations.remove(position);
+        self.save()
+    }
+
+    /// Delete the inactive conversations of `user_id`, returning their run ids
+    pub fn remove_user(&mut self, user_id: &str) -> Result<Vec<String>> {
+        let inactive: Vec<String> = self
+            .index
+            .conversations
+            .iter()
+            .map(|meta| meta.id.clone())
+            .filter(|id| *id != self.index.active)
+            .collect();
+        let mut removed = Vec::new();
+        for id in inactive {
+            let conversation = self.load(&id)?;
+            if conversation.session.user_id == user_id {
+                self.remove(&id)?;
+                removed.push(conversation.session.run_id);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Write a conversation's history and session ids to its file
+    pub fn park(&self, id: &str, conversation: &StoredConversation) -> Result<()> {
+        let json = serde_json::to_string_pretty(conversation)?;
+        encryption::write(&self.conversation_path(id)?, json.as_bytes())
+    }
+
+    /// Read a parked conversation (empty if its file is missing)
+    pub fn load(&self, id: &str) -> Result<StoredConversation> {
+        let meta = self.get(id).ok_or_else(|| anyhow!("No conversation {}", id))?;
+        let path = self.conversation_path(id)?;
+        if !path.exists() {
+            return Ok(StoredConversation {
+                session: SessionIds::new(meta.mode.clone()),
+                entries: Vec::new(),
+            });
+        }
+        let json = encryption::read_to_string(&path)
+            .with_context(|| format!("Failed to read conversation {}", id))?;
+        serde_json::from_str(&json).context("Failed to parse conversation")
+    }
+
+    /// Make `id` active; its parked file is dropped since `HistoryStore` now
+    /// holds its history
+    pub fn set_active(&mut self, id: &str) -> Result<()> {
+        self.find(id).ok_or_else(|| anyhow!("No conversation {}", id))?;
+        self.index.active = id.to_string();
+        self.save()?;
+        let path = self.conversation_path(id)?;
+        if path.exists() {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    /// Most recently updated conversation other than `id`
+    fn next_after(&self, id: &str) -> Option<String> {
+        self.list()
+            .into_iter()
+            .map(|info| info.meta.id)
+            .find(|other| other != id)
+    }
+}
+
+fn new_meta(title: &str, mode: &str, config: Option<LlmConfig>) -> ConversationMeta {
+    let now = crate::clock::timestamp();
+    let title = title.trim();
+    ConversationMeta {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: if title.is_empty() { DEFAULT_TITLE } else { title }.to_string(),
+        mode: mode.to_string(),
+        config,
+        preset: None,
+        branch_of: None,
+        branch_point: None,
+        private: false,
+        created_at: now.clone(),
+        updated_at: now,
+        message_count: 0,
+        preview: String::new(),
+    }
+}
+
+fn describe(meta: &mut ConversationMeta, history: &[ConversationEntry]) {
+    meta.message_count = history.len();
+    meta.preview = history
+        .iter()
+        .find(|entry| entry.role == "user")
+        .map(|entry| entry.content.chars().take(80).collect())
+        .unwrap_or_default();
+    if let Some(last) = history.last() {
+        if last.timestamp > meta.updated_at {
+            meta.updated_at = last.timestamp.clone();
+        }
+    }
+}
+
+/// Park the active conversation (unless it is being deleted) and load `id`
+/// into the app state
+pub fn activate(state: &AppState, id: &str, park_current: bool) -> Result<()> {
+    if state.generation.is_active() {
+        return Err(anyhow!("Wait for the current response to finish before switching conversations"));
+    }
+    if state.session.lock().incognito {
+        return Err(anyhow!("End the incognito session before switching conversations"));
+    }
+
+    let mut manager = state.conversations.lock();
+    let target = manager.load(id)?;
+    let mode = manager
+        .get(id)
+        .and_then(|meta| AppMode::from_name(&meta.mode))
+        .unwrap_or(AppMode::Companion);
+
+    let mut history = state.conversation_history.lock();
+    let mut session = state.session.lock();
+    if park_current {
+        let active = manager.active_id().to_string();
+        manager.park(
+            &active,
+            &StoredConversation {
+                session: session.clone(),
+                entries: history.clone(),
+            },
+        )?;
+        manager.refresh_active(&history);
+    }
+    manager.set_active(id)?;
+
+    *history = target.entries;
+    *session = target.session;
+    *state.current_mode.lock() = mode;
+    if let Err(e) = state.history_store.save(&history) {
+        warn!("Failed to persist conversation history: {}", e);
+    }
+    if let Err(e) = state.history_store.save_session(&session) {
+        warn!("Failed to persist session ids: {}", e);
+    }
+
+    info!("Switched to conversation {} ({} messages)", id, history.len());
+    Ok(())
+}
+
+pub fn info(manager: &SessionManager, id: &str) -> Result<ConversationInfo> {
+    let meta = manager.get(id).ok_or_else(|| anyhow!("No conversation {}", id))?;
+    Ok(ConversationInfo {
+        meta: meta.clone(),
+        active: manager.active_id() == id,
+    })
+}
+
+/// Start a new conversation and switch to it
+///
+/// `mode` defaults to the current one; `config` overrides its sampling.
+#[tauri::command]
+pub async fn create_conversation(
+    title: Option<String>,
+    mode: Option<String>,
+    config: Option<LlmConfig>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let mode = match mode {
+        Some(name) => AppMode::from_name(&name).ok_or_else(|| format!("Unknown mode: {}", name))?,
+        None => state.current_mode.lock().clone(),
+    };
+    let meta = state
+        .conversations
+        .lock()
+        .create(title.as_deref().unwrap_or_default(), &mode.to_string(), config)
+        .map_err(|e| e.to_string())?;
+    activate(&state, &meta.id, true).map_err(|e| e.to_string())?;
+    info(&state.conversations.lock(), &meta.id).map_err(|e| e.to_string())
+}
+
+/// All conversations, most recently updated first
+#[tauri::command]
+pub async fn list_conversations(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Dated<ConversationInfo>>, String> {
+    let mut manager = state.conversations.lock();
+    // The active conversation is set aside while incognito
+    if !state.session.lock().incognito {
+        manager.refresh_active(&state.conversation_history.lock());
+    }
+    Ok(dated(manager.list(), &SystemClock, |info| clock::parse(&info.meta.updated_at)))
+}
+
+/// Make another conversation active (its history, mode and session ids)
+#[tauri::command]
+pub async fn switch_conversation(
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    if state.conversations.lock().active_id() != id {
+        activate(&state, &id, true).map_err(|e| e.to_string())?;
+    }
+    info(&state.conversations.lock(), &id).map_err(|e| e.to_string())
+}
+
+/// Delete a conversation's history; deleting the active one switches to the
+/// most recent other conversation (or a new empty one)
+///
+/// Memories recorded during the conversation are kept.
+#[tauri::command]
+pub async fn delete_conversation(id: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let is_active = state.conversations.lock().active_id() == id;
+    if is_active {
+        let next = state.conversations.lock().next_after(&id);
+        let next = match next {
+            Some(next) => next,
+            None => {
+                let mode = state.current_mode.lock().to_string();
+                let meta = state
+                    .conversations
+                    .lock()
+                    .create(DEFAULT_TITLE, &mode, None)
+                    .map_err(|e| e.to_string())?;
+                meta.id
+            }
+        };
+        activate(&state, &next, false).map_err(|e| e.to_string())?;
+    }
+
+    state.conversations.lock().remove(&id).map_err(|e| e.to_string())?;
+    info!("Deleted conversation {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn rename_conversation(
+    id: String,
+    title: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let mut manager = state.conversations.lock();
+    manager.rename(&id, &title).map_err(|e| e.to_string())?;
+    info(&manager, &id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+
+    fn entry(role: &str, content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: crate::clock::timestamp(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_park_and_reopen() {
+        let dir = std::env::temp_dir().join(format!("auranexus_conversations_{}", uuid::Uuid::new_v4()));
+        let session = SessionIds::new("companion");
+        let history = vec![entry("user", "What's a closure?"), entry("assistant", "A function...")];
+
+        let mut manager = SessionManager::open(&dir, &session, &history).unwrap();
+        let first = manager.active_id().to_string();
+        assert_eq!(manager.list()[0].meta.preview, "What's a closure?");
+
+        let story = manager.create("Dragon story", "youniverse", None).unwrap();
+        assert!(manager.load(&story.id).unwrap().entries.is_empty());
+        manager
+            .park(&first, &StoredConversation { session: session.clone(), entries: history })
+            .unwrap();
+        manager.set_active(&story.id).unwrap();
+        manager.rename(&first, "Rust questions").unwrap();
+
+        let mut reopened = SessionManager::open(&dir, &SessionIds::new("youniverse"), &[]).unwrap();
+        assert_eq!(reopened.active_id(), story.id);
+        assert_eq!(reopened.get(&first).unwrap().title, "Rust questions");
+        assert_eq!(reopened.get(&story.id).unwrap().mode, "youniverse");
+        let parked = reopened.load(&first).unwrap();
+        assert_eq!(parked.session.run_id, session.run_id);
+        assert_eq!(parked.entries.len(), 2);
+
+        assert!(reopened.remove(&story.id).is_err());
+        reopened.remove(&first).unwrap();
+        assert_eq!(reopened.list().len(), 1);
+        assert!(reopened.load("../index").is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_branch_keeps_the_original() {
+        let dir = std::env::temp_dir().join(format!("auranexus_conversations_{}", uuid::Uuid::new_v4()));
+        let session = SessionIds::new("youniverse");
+        let history = vec![
+            entry("user", "Begin the story"),
+            entry("assistant", "Once upon a time..."),
+            entry("user", "The dragon attacks"),
+            entry("assistant", "Fire everywhere."),
+        ];
+        let mut manager = SessionManager::open(&dir, &session, &history).unwrap();
+        let original = manager.active_id().to_string();
+        manager.set_preset(&original, Some("storyteller".to_string())).unwrap();
+        manager.set_private(&original, true).unwrap();
+        assert!(manager.active_private());
+
+        let branch = manager.branch(&original, history[..2].to_vec()).unwrap();
+        assert!(branch.private);
+        assert_eq!(branch.branch_of.as_deref(), Some(original.as_str()));
+        assert_eq!(branch.branch_point, Some(2));
+        assert_eq!(branch.mode, "youniverse");
+        assert_eq!(branch.preset.as_deref(), Some("storyteller"));
+        let parked = manager.load(&branch.id).unwrap();
+        assert_eq!(parked.entries.len(), 2);
+        assert_ne!(parked.session.run_id, session.run_id);
+        // Still the active conversation, untouched
+        assert_eq!(manager.active_id(), original);
+        assert!(manager.branch("missing", Vec::new()).is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,189 @@
+// Custom Instructions Module - User-pinned additions to every system prompt
+//
+// The user keeps one or more named profiles (e.g. "default", "work") saying
+// how to address them, what response style they prefer and what to always
+// take into account. The active profile is appended to every persona's
+// system prompt.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Profile used until the user creates another
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// What the user wants every persona to know
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CustomInstructions {
+    pub enabled: bool,
+    /// How to address the user (name, nickname, pronouns)
+    pub address_as: String,
+    /// Preferred response style (length, tone, formatting)
+    pub response_style: String,
+    /// Things to always keep in mind
+    pub always_consider: Vec<String>,
+}
+
+impl Default for CustomInstructions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            address_as: String::new(),
+            response_style: String::new(),
+            always_consider: Vec::new(),
+        }
+    }
+}
+
+impl CustomInstructions {
+    fn is_empty(&self) -> bool {
+        self.address_as.trim().is_empty()
+            && self.response_style.trim().is_empty()
+            && self.always_consider.iter().all(|item| item.trim().is_empty())
+    }
+
+    /// Append these instructions to a persona's system prompt
+    pub fn apply(&self, system_prompt: &str) -> String {
+        if !self.enabled || self.is_empty() {
+            return system_prompt.to_string();
+        }
+
+        let mut prompt = format!("{}\n\nCustom instructions from the user:", system_prompt);
+        if !self.address_as.trim().is_empty() {
+            prompt.push_str(&format!("\n- Address the user as: {}", self.address_as.trim()));
+        }
+        if !self.response_style.trim().is_empty() {
+            prompt.push_str(&format!("\n- Response style: {}", self.response_style.trim()));
+        }
+        for item in self.always_consider.iter().filter(|item| !item.trim().is_empty()) {
+            prompt.push_str(&format!("\n- Always consider: {}", item.trim()));
+        }
+        prompt
+    }
+}
+
+/// All profiles plus which one is active
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstructionProfiles {
+    pub active: String,
+    pub profiles: BTreeMap<String, CustomInstructions>,
+}
+
+impl Default for InstructionProfiles {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl InstructionProfiles {
+    const FILE: &'static str = "custom_instructions.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// Instructions of the active profile (empty if it was never edited)
+    pub fn active(&self) -> CustomInstructions {
+        self.profiles.get(&self.active).cloned().unwrap_or_default()
+    }
+
+    pub fn set_active(&mut self, profile: &str) -> Result<()> {
+        if profile != DEFAULT_PROFILE && !self.profiles.contains_key(profile) {
+            return Err(anyhow!("Unknown instruction profile: {}", profile));
+        }
+        self.active = profile.to_string();
+        Ok(())
+    }
+}
+
+/// `system_prompt` with the active custom instructions merged in
+pub fn apply_to(system_prompt: &str) -> String {
+    InstructionProfiles::load().active().apply(system_prompt)
+}
+
+/// All profiles and the active one
+#[tauri::command]
+pub async fn get_instruction_profiles() -> Result<InstructionProfiles, String> {
+    Ok(InstructionProfiles::load())
+}
+
+/// Create or replace a profile's instructions (defaults to the active profile)
+#[tauri::command]
+pub async fn set_custom_instructions(
+    profile: Option<String>,
+    instructions: CustomInstructions,
+) -> Result<(), String> {
+    let mut profiles = InstructionProfiles::load();
+    let profile = profile.unwrap_or_else(|| profiles.active.clone());
+    if profile.trim().is_empty() {
+        return Err("Profile name must not be empty".to_string());
+    }
+    profiles.profiles.insert(profile, instructions);
+    profiles.save().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_active_instruction_profile(profile: String) -> Result<(), String> {
+    let mut profiles = InstructionProfiles::load();
+    profiles.set_active(&profile).map_err(|e| e.to_string())?;
+    profiles.save().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_instruction_profile(profile: String) -> Result<(), String> {
+    let mut profiles = InstructionProfiles::load();
+    profiles.profiles.remove(&profile);
+    if profiles.active == profile {
+        profiles.active = DEFAULT_PROFILE.to_string();
+    }
+    profiles.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_appends_instructions() {
+        let instructions = CustomInstructions {
+            enabled: true,
+            address_as: "Sam".to_string(),
+            response_style: "Short and warm".to_string(),
+            always_consider: vec!["I'm vegetarian".to_string(), "  ".to_string()],
+        };
+
+        let prompt = instructions.apply("You are Aura.");
+        assert!(prompt.starts_with("You are Aura.\n\nCustom instructions"));
+        assert!(prompt.contains("- Address the user as: Sam"));
+        assert!(prompt.contains("- Always consider: I'm vegetarian"));
+        assert_eq!(prompt.matches("Always consider").count(), 1);
+
+        let disabled = CustomInstructions { enabled: false, ..instructions };
+        assert_eq!(disabled.apply("You are Aura."), "You are Aura.");
+    }
+
+    #[test]
+    fn test_profiles_switch_active() {
+        let mut profiles = InstructionProfiles::default();
+        assert_eq!(profiles.active(), CustomInstructions::default());
+        assert!(profiles.set_active("work").is_err());
+
+        let work = CustomInstructions {
+            enabled: true,
+            response_style: "Formal".to_string(),
+            ..Default::default()
+        };
+        profiles.profiles.insert("work".to_string(), work.clone());
+        profiles.set_active("work").unwrap();
+        assert_eq!(profiles.active(), work);
+    }
+}
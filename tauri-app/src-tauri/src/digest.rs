@@ -0,0 +1,413 @@
+// Digest Module - Scheduled weekly conversation digest
+//
+// Once a week (configurable day/time) the past seven days of messages are
+// grouped by mode, clustered into topics with the compaction pipeline, and
+// written out as one `weekly_digest` memory plus a Markdown note (sealed when
+// encryption at rest is on). The user is told via a desktop notification and
+// a `digest-ready` event.
+
+use crate::clock::{self, UserTimezone};
+use crate::compaction::{compact, summarize_with_llm, CompactionConfig};
+use crate::embeddings::HashingEmbedder;
+use crate::memory_store::MemoryFilters;
+use crate::session::LOCAL_USER_ID;
+use crate::{encryption, paths, AppState, ConversationEntry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tauri::Manager;
+use tracing::{info, warn};
+
+/// How often the scheduler checks whether a digest is due
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Most topics listed per mode
+const MAX_TOPICS_PER_MODE: usize = 5;
+
+/// When the weekly digest runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    pub weekday: Weekday,
+    /// Time of day in the user's timezone (24h)
+    pub hour: u32,
+    pub minute: u32,
+    /// When the last digest was produced
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weekday: Weekday::Sun,
+            hour: 18,
+            minute: 0,
+            last_run: None,
+        }
+    }
+}
+
+impl DigestSettings {
+    const FILE: &'static str = "digest.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// Most recent scheduled time at or before `now`, in `now`'s timezone
+    pub fn last_scheduled<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let mut date = now.date_naive();
+        for _ in 0..8 {
+            if date.weekday() == self.weekday {
+                let scheduled = date
+                    .and_hms_opt(self.hour, self.minute, 0)
+                    .and_then(|naive| now.timezone().from_local_datetime(&naive).earliest());
+                if let Some(scheduled) = scheduled.filter(|s| *s <= now) {
+                    return Some(scheduled);
+                }
+            }
+            date = date.pred_opt()?;
+        }
+        None
+    }
+
+    /// Whether a scheduled run has passed since the last digest
+    pub fn is_due<Tz: TimeZone>(&self, now: DateTime<Tz>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match (self.last_scheduled(now), self.last_run) {
+            (Some(scheduled), Some(last_run)) => last_run < scheduled.with_timezone(&Utc),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Digest of one mode's week
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestSection {
+    pub mode: String,
+    pub message_count: usize,
+    pub topics: Vec<String>,
+}
+
+/// A produced weekly digest
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub week_start: DateTime<Utc>,
+    pub week_end: DateTime<Utc>,
+    pub sections: Vec<DigestSection>,
+    /// Where the Markdown note was written
+    pub note_path: Option<String>,
+}
+
+impl Digest {
+    pub fn to_markdown(&self) -> String {
+        let mut note = format!(
+            "# Weekly digest: {} – {}\n",
+            clock::to_local(self.week_start).format("%b %e"),
+            clock::to_local(self.week_end).format("%b %e, %Y")
+        );
+        for section in &self.sections {
+            note.push_str(&format!(
+                "\n## {} ({} messages)\n\n",
+                section.mode, section.message_count
+            ));
+            for topic in &section.topics {
+                note.push_str(&format!("- {}\n", topic));
+            }
+        }
+        note
+    }
+}
+
+/// Messages from the week ending at `week_end`, grouped by mode
+fn weekly_messages(
+    state: &AppState,
+    week_start: DateTime<Utc>,
+    week_end: DateTime<Utc>,
+) -> BTreeMap<String, Vec<ConversationEntry>> {
+    let mut filters = MemoryFilters::default();
+    filters
+        .metadata
+        .insert("kind".to_string(), serde_json::json!("message"));
+
+    let store = state.memory_store.lock();
+    let mut by_mode: BTreeMap<String, Vec<(DateTime<Utc>, ConversationEntry)>> = BTreeMap::new();
+    for item in store.get_all(&filters, usize::MAX) {
+        let timestamp = item.metadata.get("timestamp").and_then(|v| v.as_str());
+        let Some(time) = timestamp.and_then(|t| DateTime::parse_from_rfc3339(t).ok()) else {
+            continue;
+        };
+        let time = time.with_timezone(&Utc);
+        if time < week_start || time > week_end {
+            continue;
+        }
+
+        let entry = ConversationEntry {
+            role: item
+                .metadata
+                .get("role")
+                .and_then(|v| v.as_str())
+                .unwrap_or("user")
+                .to_string(),
+            content: item.content.clone(),
+            timestamp: time.to_rfc3339(),
+            quality_score: None,
+            status: Default::default(),
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        };
+        let mode = item.agent_id.clone().unwrap_or_else(|| "unknown".to_string());
+        by_mode.entry(mode).or_default().push((time, entry));
+    }
+
+    by_mode
+        .into_iter()
+        .map(|(mode, mut entries)| {
+            entries.sort_by_key(|(time, _)| *time);
+            (mode, entries.into_iter().map(|(_, entry)| entry).collect())
+        })
+        .collect()
+}
+
+/// Summarize the week ending at `now`; `None` if there were no conversations
+///
+/// Topics are summarized by the chat backend, so this blocks until a reply
+/// still generating is done.
+pub fn build_digest(state: &AppState, now: DateTime<Utc>) -> Option<Digest> {
+    let week_start = now - Duration::days(7);
+    let by_mode = weekly_messages(state, week_start, now);
+    if by_mode.is_empty() {
+        return None;
+    }
+
+    let config = CompactionConfig {
+        max_clusters: MAX_TOPICS_PER_MODE,
+        ..Default::default()
+    };
+    let embedder = HashingEmbedder::default();
+    let mut backend = state.llm.blocking_lock();
+
+    let sections = by_mode
+        .into_iter()
+        .map(|(mode, entries)| {
+            let summaries = compact(&entries, &config, &embedder, |cluster| {
+                backend.as_mut().and_then(|b| summarize_with_llm(b.as_mut(), cluster))
+            });
+            DigestSection {
+                mode,
+                message_count: entries.len(),
+                topics: summaries.into_iter().map(|s| s.summary).collect(),
+            }
+        })
+        .collect();
+
+    Some(Digest {
+        week_start,
+        week_end: now,
+        sections,
+        note_path: None,
+    })
+}
+
+fn notes_dir() -> PathBuf {
+    paths::app_data_dir().join("digests")
+}
+
+/// Delete every Markdown digest note; returns how many
+pub fn delete_notes() -> Result<usize> {
+    let Ok(entries) = std::fs::read_dir(notes_dir()) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.is_file() {
+            std::fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Store the digest as a memory and write the Markdown note
+fn save_digest(state: &AppState, digest: &mut Digest) -> Result<()> {
+    let note = digest.to_markdown();
+
+    let dir = notes_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "weekly-{}.md",
+        clock::to_local(digest.week_end).format("%Y-%m-%d")
+    ));
+    encryption::write(&path, note.as_bytes()).with_context(|| format!("Failed to write {}", path.display()))?;
+    digest.note_path = Some(path.to_string_lossy().to_string());
+
+    let mut metadata = HashMap::new();
+    metadata.insert("kind".to_string(), serde_json::json!("weekly_digest"));
+    metadata.insert("week_start".to_string(), serde_json::json!(digest.week_start.to_rfc3339()));
+    metadata.insert("week_end".to_string(), serde_json::json!(digest.week_end.to_rfc3339()));
+    metadata.insert(
+        "modes".to_string(),
+        serde_json::json!(digest.sections.iter().map(|s| &s.mode).collect::<Vec<_>>()),
+    );
+    state
+        .memory_store
+        .lock()
+        .add(note, Some(LOCAL_USER_ID.to_string()), None, None, metadata)?;
+
+    Ok(())
+}
+
+/// Build, save and announce a digest, then record the run
+fn run_digest(app: &tauri::AppHandle) -> Result<Option<Digest>> {
+    let state = app.state::<AppState>();
+    let now = clock::now();
+
+    let digest = match build_digest(&state, now) {
+        Some(mut digest) => {
+            save_digest(&state, &mut digest)?;
+            notify(app, &digest);
+            Some(digest)
+        }
+        None => {
+            info!("No conversations this week - skipping digest");
+            None
+        }
+    };
+
+    let mut settings = DigestSettings::load();
+    settings.last_run = Some(now);
+    settings.save()?;
+
+    Ok(digest)
+}
+
+fn notify(app: &tauri::AppHandle, digest: &Digest) {
+    let messages: usize = digest.sections.iter().map(|s| s.message_count).sum();
+    let body = format!(
+        "{} messages across {} mode(s) this week",
+        messages,
+        digest.sections.len()
+    );
+
+    let identifier = app.config().tauri.bundle.identifier.clone();
+    if let Err(e) = tauri::api::notification::Notification::new(identifier)
+        .title("Your weekly digest is ready")
+        .body(body)
+        .show()
+    {
+        warn!("Failed to show digest notification: {}", e);
+    }
+    if let Err(e) = app.emit_all("digest-ready", digest) {
+        warn!("Failed to emit digest event: {}", e);
+    }
+}
+
+/// Whether the digest is due by the clock in the user's timezone
+fn is_due(settings: &DigestSettings) -> bool {
+    match clock::timezone() {
+        UserTimezone::System => settings.is_due(clock::now().with_timezone(&Local)),
+        UserTimezone::Named(tz) => settings.is_due(clock::now().with_timezone(&tz)),
+    }
+}
+
+/// Check once a minute whether the weekly digest is due
+pub fn spawn_scheduler(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        if is_due(&DigestSettings::load()) {
+            info!("Building weekly digest...");
+            match run_digest(&app) {
+                Ok(Some(digest)) => info!("Weekly digest saved ({} modes)", digest.sections.len()),
+                Ok(None) => {}
+                Err(e) => warn!("Weekly digest failed: {}", e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_digest_settings() -> Result<DigestSettings, String> {
+    Ok(DigestSettings::load())
+}
+
+/// Change the digest schedule (the last-run time is kept)
+#[tauri::command]
+pub async fn set_digest_settings(settings: DigestSettings) -> Result<DigestSettings, String> {
+    if settings.hour > 23 || settings.minute > 59 {
+        return Err(format!("Invalid time {}:{:02}", settings.hour, settings.minute));
+    }
+
+    let mut saved = DigestSettings::load();
+    saved.enabled = settings.enabled;
+    saved.weekday = settings.weekday;
+    saved.hour = settings.hour;
+    saved.minute = settings.minute;
+    saved.save().map_err(|e| e.to_string())?;
+
+    Ok(saved)
+}
+
+/// Produce a digest for the past seven days right away
+#[tauri::command]
+pub async fn run_digest_now(app: tauri::AppHandle) -> Result<Option<Digest>, String> {
+    tauri::async_runtime::spawn_blocking(move || run_digest(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_last_scheduled_finds_previous_occurrence() {
+        let settings = DigestSettings {
+            weekday: Weekday::Sun,
+            hour: 18,
+            minute: 0,
+            ..Default::default()
+        };
+
+        // Wednesday 2024-05-08 -> Sunday 2024-05-05 18:00
+        assert_eq!(
+            settings.last_scheduled(local(2024, 5, 8, 9, 0)),
+            Some(local(2024, 5, 5, 18, 0))
+        );
+        // Sunday before 18:00 -> the previous Sunday
+        assert_eq!(
+            settings.last_scheduled(local(2024, 5, 12, 17, 59)),
+            Some(local(2024, 5, 5, 18, 0))
+        );
+    }
+
+    #[test]
+    fn test_is_due_once_per_week() {
+        let mut settings = DigestSettings::default();
+        let now = local(2024, 5, 12, 18, 5);
+        assert!(settings.is_due(now));
+
+        settings.last_run = Some(local(2024, 5, 12, 18, 1).with_timezone(&Utc));
+        assert!(!settings.is_due(now));
+        assert!(settings.is_due(local(2024, 5, 19, 18, 0)));
+
+        settings.enabled = false;
+        assert!(!settings.is_due(local(2024, 5, 19, 18, 0)));
+    }
+}
@@ -0,0 +1,290 @@
+// Downloader Module - Streamed HTTP downloads with progress reporting
+//
+// Interrupted downloads resume from their `.part` file, and finished ones can
+// be checked against an expected SHA-256 before they are moved into place.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Minimum time between progress reports
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Weight of the newest sample in the smoothed transfer rate
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// Progress snapshot reported while downloading
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub downloaded_bytes: u64,
+    /// Total size if the server reported it
+    pub total_bytes: Option<u64>,
+    /// Recent transfer rate, smoothed across reports
+    pub bytes_per_second: f64,
+    /// Estimated time left, if the total is known and data is arriving
+    pub eta_seconds: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// Fraction complete in [0, 1], if the total is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total_bytes
+            .filter(|total| *total > 0)
+            .map(|total| (self.downloaded_bytes as f64 / total as f64) as f32)
+    }
+
+    /// Update the rate and ETA after `bytes` arrived over `elapsed`
+    fn record(&mut self, bytes: u64, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            let rate = bytes as f64 / seconds;
+            self.bytes_per_second = if self.bytes_per_second > 0.0 {
+                RATE_SMOOTHING * rate + (1.0 - RATE_SMOOTHING) * self.bytes_per_second
+            } else {
+                rate
+            };
+        }
+
+        let rate = self.bytes_per_second;
+        self.eta_seconds = self
+            .total_bytes
+            .filter(|_| rate > 0.0)
+            .map(|total| (total.saturating_sub(self.downloaded_bytes) as f64 / rate).ceil() as u64);
+    }
+}
+
+/// Download `url` to `dest`, reporting progress (with rate and ETA) a few
+/// times a second
+///
+/// The file is written to `<dest>.part` and renamed when complete, so a
+/// partially downloaded model is never mistaken for a usable one. A `.part`
+/// left by an interrupted download is resumed with an HTTP range request when
+/// the server supports it. With `sha256`, the finished file must match that
+/// digest (hex) or it is deleted and an error returned.
+pub fn download_file(
+    url: &str,
+    dest: &Path,
+    sha256: Option<&str>,
+    mut on_progress: impl FnMut(&DownloadProgress),
+) -> Result<u64> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(None::<std::time::Duration>)
+        .build()
+        .context("Failed to build HTTP client")?;
+
+    let part_path = part_path(dest);
+    let partial = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    let (mut response, resumed_from) = match resume(&client, url, partial)? {
+        Some(response) => {
+            info!("Resuming download at {} bytes", partial);
+            (response, partial)
+        }
+        None => (start(&client, url)?, 0),
+    };
+
+    let mut file = if resumed_from > 0 {
+        std::fs::OpenOptions::new().append(true).open(&part_path)
+    } else {
+        std::fs::File::create(&part_path)
+    }
+    .with_context(|| format!("Failed to open {}", part_path.display()))?;
+
+    let mut hasher = match sha256 {
+        Some(_) => {
+            let mut hasher = Sha256::new();
+            if resumed_from > 0 {
+                let mut existing = std::fs::File::open(&part_path)
+                    .with_context(|| format!("Failed to read {}", part_path.display()))?;
+                std::io::copy(&mut (&mut existing).take(resumed_from), &mut hasher)
+                    .context("Failed to hash partial download")?;
+            }
+            Some(hasher)
+        }
+        None => None,
+    };
+
+    let mut progress = DownloadProgress {
+        downloaded_bytes: resumed_from,
+        total_bytes: response.content_length().map(|len| len + resumed_from),
+        bytes_per_second: 0.0,
+        eta_seconds: None,
+    };
+    on_progress(&progress);
+
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut last_report = Instant::now();
+    let mut reported_bytes = progress.downloaded_bytes;
+    loop {
+        let read = response.read(&mut buffer).context("Download interrupted")?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read]).context("Failed to write download")?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..read]);
+        }
+
+        progress.downloaded_bytes += read as u64;
+        let elapsed = last_report.elapsed();
+        if elapsed >= REPORT_INTERVAL {
+            progress.record(progress.downloaded_bytes - reported_bytes, elapsed);
+            last_report = Instant::now();
+            reported_bytes = progress.downloaded_bytes;
+            on_progress(&progress);
+        }
+    }
+
+    file.flush()?;
+    drop(file);
+
+    if let Some(total) = progress.total_bytes {
+        if progress.downloaded_bytes != total {
+            return Err(anyhow!(
+                "Download incomplete: got {} of {} bytes",
+                progress.downloaded_bytes,
+                total
+            ));
+        }
+    }
+
+    if let (Some(hasher), Some(expected)) = (hasher, sha256) {
+        let actual = to_hex(&hasher.finalize());
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            let _ = std::fs::remove_file(&part_path);
+            return Err(anyhow!(
+                "Checksum mismatch: expected sha256 {}, got {}",
+                expected.trim(),
+                actual
+            ));
+        }
+    }
+
+    std::fs::rename(&part_path, dest)
+        .with_context(|| format!("Failed to move download into {}", dest.display()))?;
+    progress.eta_seconds = Some(0);
+    on_progress(&progress);
+
+    Ok(progress.downloaded_bytes)
+}
+
+fn start(client: &reqwest::blocking::Client, url: &str) -> Result<reqwest::blocking::Response> {
+    let response = client
+        .get(url)
+        .send()
+        .with_context(|| format!("Failed to start download from {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Download failed: server returned {}", response.status()));
+    }
+    Ok(response)
+}
+
+/// Continue a download from byte `offset`
+///
+/// `None` if there is nothing to resume or the server won't serve the rest
+/// of the file (the download then starts over).
+fn resume(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    offset: u64,
+) -> Result<Option<reqwest::blocking::Response>> {
+    if offset == 0 {
+        return Ok(None);
+    }
+    let response = client
+        .get(url)
+        .header(reqwest::header::RANGE, format!("bytes={}-", offset))
+        .send()
+        .with_context(|| format!("Failed to resume download from {}", url))?;
+
+    let content_range = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let continues = response.status() == reqwest::StatusCode::PARTIAL_CONTENT
+        && content_range_start(content_range) == Some(offset);
+    Ok(continues.then_some(response))
+}
+
+/// First byte of a `Content-Range: bytes <start>-<end>/<size>` header
+fn content_range_start(header: &str) -> Option<u64> {
+    header
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// SHA-256 of a file on disk, as lowercase hex
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Temporary path used while `dest` is downloading (`model.gguf.part`)
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_and_eta() {
+        let mut progress = DownloadProgress {
+            downloaded_bytes: 1_000_000,
+            total_bytes: Some(5_000_000),
+            bytes_per_second: 0.0,
+            eta_seconds: None,
+        };
+        progress.record(1_000_000, Duration::from_secs(1));
+        assert_eq!(progress.bytes_per_second, 1_000_000.0);
+        assert_eq!(progress.eta_seconds, Some(4));
+
+        // A slower sample only partly pulls the rate down
+        progress.downloaded_bytes = 1_500_000;
+        progress.record(500_000, Duration::from_secs(1));
+        assert!(progress.bytes_per_second > 500_000.0 && progress.bytes_per_second < 1_000_000.0);
+        assert_eq!(progress.fraction(), Some(0.3));
+
+        progress.total_bytes = None;
+        progress.record(0, Duration::from_secs(1));
+        assert_eq!(progress.eta_seconds, None);
+    }
+
+    #[test]
+    fn test_content_range_and_sha256() {
+        assert_eq!(content_range_start("bytes 1024-2047/2048"), Some(1024));
+        assert_eq!(content_range_start("bytes */2048"), None);
+
+        let path = std::env::temp_dir().join(format!("downloader_{}.txt", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,431 @@
+// Embeddings Module - Text → vector encoders for similarity search
+//
+// `HashingEmbedder` is a dependency-free feature-hashing encoder (bag of
+// words + bigrams). It has no notion of synonyms, but it is deterministic,
+// instant, and good enough to group messages by topic.
+//
+// `LlamaEmbedder` runs a GGUF embedding model (nomic-embed-text, bge, ...)
+// through llama.cpp for real semantic similarity. `load_embedder` picks it
+// when such a model is configured or found, and falls back to hashing.
+//
+// `CachedEmbedder` wraps whichever is loaded so repeated texts (queries, the
+// `/v1/embeddings` endpoint, re-ingested chunks) aren't encoded twice.
+
+use crate::capabilities::{Feature, ModelCapabilities};
+use anyhow::{anyhow, Context, Result};
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaModel};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Anything that can turn text into a fixed-size vector
+pub trait Embedder: Send + Sync {
+    /// Embed a single text into a unit-length vector
+    fn embed(&self, text: &str) -> Vec<f32>;
+
+    /// Dimensionality of the vectors produced
+    fn dimensions(&self) -> usize;
+
+    /// Model name reported to API clients
+    fn name(&self) -> String {
+        "hashing".to_string()
+    }
+
+    /// Embed several texts (override for batched backends)
+    fn embed_batch(&self, texts: &[&str]) -> Vec<Vec<f32>> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+}
+
+/// Words too common to carry topical signal
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "do", "for", "from", "has", "have",
+    "i", "if", "in", "is", "it", "its", "me", "my", "of", "on", "or", "so", "that", "the",
+    "this", "to", "was", "we", "what", "with", "you", "your",
+];
+
+/// Feature-hashing embedder over word unigrams and bigrams
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        assert!(dimensions > 0, "Embedding dimensions must be positive");
+        Self { dimensions }
+    }
+
+    fn add_feature(&self, vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let index = (hash % self.dimensions as u64) as usize;
+        // Use a separate hash bit for the sign to reduce collision bias
+        let sign = if (hash >> 63) & 1 == 0 { 1.0 } else { -1.0 };
+        vector[index] += sign * weight;
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+
+        let words: Vec<String> = tokenize(text)
+            .into_iter()
+            .filter(|word| !STOPWORDS.contains(&word.as_str()))
+            .collect();
+
+        for word in &words {
+            self.add_feature(&mut vector, word, 1.0);
+        }
+        for pair in words.windows(2) {
+            self.add_feature(&mut vector, &format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+
+        normalize(&mut vector);
+        vector
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
+
+/// Longest input (in tokens) fed to an embedding model; the rest is cut off
+const MAX_EMBEDDING_TOKENS: u32 = 512;
+
+/// GGUF embedding model run through llama.cpp
+pub struct LlamaEmbedder {
+    backend: &'static LlamaBackend,
+    model: LlamaModel,
+    dimensions: usize,
+    name: String,
+}
+
+impl LlamaEmbedder {
+    pub fn load(model_path: &Path) -> Result<Self> {
+        ModelCapabilities::probe(model_path)?.require(Feature::Embeddings)?;
+        let backend = crate::llm::llama_backend()?;
+        let model = LlamaModel::load_from_file(backend, model_path, &LlamaModelParams::default())
+            .with_context(|| format!("Failed to load embedding model {}", model_path.display()))?;
+        let dimensions = model.n_embd() as usize;
+        info!("Embedding model loaded: {} ({} dims)", model_path.display(), dimensions);
+        let name = model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "embedding".to_string());
+        Ok(Self {
+            backend,
+            model,
+            dimensions,
+            name,
+        })
+    }
+
+    /// Embed texts with one llama.cpp context, clearing it between texts
+    fn try_embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let n_ctx = NonZeroU32::new(MAX_EMBEDDING_TOKENS);
+        let params = LlamaContextParams::default()
+            .with_n_ctx(n_ctx)
+            .with_n_batch(MAX_EMBEDDING_TOKENS)
+            .with_embeddings(true);
+        let mut context = self
+            .model
+            .new_context(self.backend, params)
+            .context("Failed to create embedding context")?;
+        let mut batch = LlamaBatch::new(MAX_EMBEDDING_TOKENS as usize, 1);
+
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            let mut tokens = self.model.str_to_token(text, AddBos::Always)?;
+            tokens.truncate(MAX_EMBEDDING_TOKENS as usize);
+            if tokens.is_empty() {
+                vectors.push(vec![0.0; self.dimensions]);
+                continue;
+            }
+
+            batch.clear();
+            batch.add_sequence(&tokens, 0, false)?;
+            context.clear_kv_cache();
+            context.decode(&mut batch).context("Failed to decode embedding batch")?;
+
+            let mut vector = context.embeddings_seq_ith(0)?.to_vec();
+            normalize(&mut vector);
+            vectors.push(vector);
+        }
+        Ok(vectors)
+    }
+}
+
+impl Embedder for LlamaEmbedder {
+    fn embed(&self, text: &str) -> Vec<f32> {
+        self.embed_batch(&[text]).pop().unwrap_or_default()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Vec<Vec<f32>> {
+        self.try_embed_batch(texts).unwrap_or_else(|e| {
+            // Zero vectors never match, so a failure only hides these texts
+            warn!("Embedding failed: {:#}", e);
+            vec![vec![0.0; self.dimensions]; texts.len()]
+        })
+    }
+}
+
+/// Vectors kept by `CachedEmbedder` before the oldest are dropped
+const EMBEDDING_CACHE_SIZE: usize = 10_000;
+
+/// Remembers recent embeddings by text hash in front of another embedder
+pub struct CachedEmbedder {
+    inner: Arc<dyn Embedder>,
+    capacity: usize,
+    cache: Mutex<EmbeddingCache>,
+}
+
+#[derive(Default)]
+struct EmbeddingCache {
+    vectors: HashMap<u64, Vec<f32>>,
+    /// Insertion order, oldest first
+    order: VecDeque<u64>,
+}
+
+impl CachedEmbedder {
+    pub fn new(inner: Arc<dyn Embedder>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity: capacity.max(1),
+            cache: Mutex::new(EmbeddingCache::default()),
+        }
+    }
+
+    /// Number of cached vectors
+    pub fn len(&self) -> usize {
+        self.cache.lock().vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Embedder for CachedEmbedder {
+    fn embed(&self, text: &str) -> Vec<f32> {
+        self.embed_batch(&[text]).pop().unwrap_or_default()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.inner.dimensions()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Vec<Vec<f32>> {
+        let keys: Vec<u64> = texts.iter().map(|text| fnv1a(text.as_bytes())).collect();
+        let mut vectors: Vec<Option<Vec<f32>>> = {
+            let cache = self.cache.lock();
+            keys.iter().map(|key| cache.vectors.get(key).cloned()).collect()
+        };
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|i| vectors[*i].is_none()).collect();
+        if !missing.is_empty() {
+            // Encoded without holding the lock, so lookups aren't blocked
+            let inputs: Vec<&str> = missing.iter().map(|i| texts[*i]).collect();
+            let encoded = self.inner.embed_batch(&inputs);
+
+            let mut cache = self.cache.lock();
+            for (i, vector) in missing.into_iter().zip(encoded) {
+                // Zero vectors are failed or empty inputs; don't pin them
+                if vector.iter().any(|v| *v != 0.0) && !cache.vectors.contains_key(&keys[i]) {
+                    cache.vectors.insert(keys[i], vector.clone());
+                    cache.order.push_back(keys[i]);
+                    while cache.order.len() > self.capacity {
+                        if let Some(oldest) = cache.order.pop_front() {
+                            cache.vectors.remove(&oldest);
+                        }
+                    }
+                }
+                vectors[i] = Some(vector);
+            }
+        }
+
+        vectors.into_iter().map(Option::unwrap_or_default).collect()
+    }
+}
+
+/// Which embedding model to use
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingSettings {
+    /// GGUF embedding model; if unset, the first downloaded model with
+    /// "embed" in its name is used
+    pub model_path: Option<String>,
+    /// Port of the local `/v1/embeddings` endpoint (default 8766, 0 disables it)
+    pub server_port: Option<u16>,
+}
+
+impl EmbeddingSettings {
+    const FILE: &'static str = "embeddings.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// The configured model, or an embedding model found among the local ones
+    fn resolve_model(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.model_path {
+            return Some(PathBuf::from(path));
+        }
+        crate::models::scan_all_model_locations()
+            .ok()?
+            .into_iter()
+            .find(|model| model.name.to_lowercase().contains("embed"))
+            .map(|model| PathBuf::from(model.path))
+    }
+}
+
+/// The best available embedder: a GGUF embedding model, else hashing,
+/// behind a shared cache
+pub fn load_embedder() -> Arc<dyn Embedder> {
+    let loaded = EmbeddingSettings::load()
+        .resolve_model()
+        .ok_or_else(|| anyhow!("no embedding model found"))
+        .and_then(|path| LlamaEmbedder::load(&path));
+    let embedder: Arc<dyn Embedder> = match loaded {
+        Ok(embedder) => Arc::new(embedder),
+        Err(e) => {
+            warn!("Using hashing embeddings ({:#})", e);
+            Arc::new(HashingEmbedder::default())
+        }
+    };
+    Arc::new(CachedEmbedder::new(embedder, EMBEDDING_CACHE_SIZE))
+}
+
+/// Lowercase alphanumeric words of at least two characters
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Scale a vector to unit length (no-op for the zero vector)
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in vector.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+/// Cosine similarity between two vectors (0.0 if either is zero)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Mean of a set of vectors, normalized to unit length
+pub fn centroid(vectors: &[&[f32]]) -> Vec<f32> {
+    let Some(first) = vectors.first() else {
+        return Vec::new();
+    };
+
+    let mut center = vec![0.0; first.len()];
+    for vector in vectors {
+        for (c, v) in center.iter_mut().zip(vector.iter()) {
+            *c += v;
+        }
+    }
+    normalize(&mut center);
+    center
+}
+
+/// 64-bit FNV-1a hash (stable across Rust versions, unlike `DefaultHasher`)
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_is_normalized() {
+        let embedder = HashingEmbedder::default();
+        let vector = embedder.embed("The quick brown fox jumps over the lazy dog");
+
+        assert_eq!(vector.len(), 256);
+        let norm: f32 = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_similar_texts_score_higher() {
+        let embedder = HashingEmbedder::default();
+        let garden = embedder.embed("I planted tomatoes and basil in the garden");
+        let garden2 = embedder.embed("The tomatoes in my garden need more basil nearby");
+        let taxes = embedder.embed("Filing quarterly taxes with the accountant");
+
+        assert!(cosine_similarity(&garden, &garden2) > cosine_similarity(&garden, &taxes));
+    }
+
+    #[test]
+    fn test_empty_text() {
+        let embedder = HashingEmbedder::default();
+        let vector = embedder.embed("");
+        assert!(vector.iter().all(|v| *v == 0.0));
+        assert_eq!(cosine_similarity(&vector, &vector), 0.0);
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest() {
+        let cached = CachedEmbedder::new(Arc::new(HashingEmbedder::default()), 2);
+        let first = cached.embed("garden tomatoes");
+        assert_eq!(cached.embed_batch(&["garden tomatoes", "quarterly taxes"])[0], first);
+        assert_eq!(cached.len(), 2);
+
+        cached.embed("basil seedlings");
+        assert_eq!(cached.len(), 2);
+        assert!(!cached.cache.lock().vectors.contains_key(&fnv1a(b"garden tomatoes")));
+
+        // Empty input isn't cached
+        cached.embed("");
+        assert_eq!(cached.len(), 2);
+    }
+}
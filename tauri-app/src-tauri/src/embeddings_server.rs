@@ -0,0 +1,316 @@
+// Embeddings Server Module - OpenAI-compatible `/v1/embeddings` on localhost
+//
+// Lets other local apps reuse AuraNexus's embedding model. The server shares
+// the memory store's embedder, and with it the embedding cache, so texts the
+// app has already embedded are answered without running the model again.
+//
+//   POST /v1/embeddings {"input": "text" | ["a", "b"], "stream": false}
+//
+// With `"stream": true` each embedding is sent as a server-sent event as soon
+// as its batch is done, ending with `data: [DONE]`. Only 127.0.0.1 is bound.
+
+use crate::embeddings::{tokenize, Embedder};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Port used when the settings don't pick one
+pub const DEFAULT_PORT: u16 = 8766;
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Most inputs accepted in one request
+const MAX_INPUTS: usize = 2048;
+
+/// Inputs embedded together before a streamed response is flushed
+const STREAM_BATCH: usize = 16;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EmbeddingInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingRequest {
+    /// `model` and other OpenAI fields are ignored; the loaded model is used
+    input: EmbeddingInput,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingData {
+    object: &'static str,
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct Usage {
+    prompt_tokens: usize,
+    total_tokens: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingResponse {
+    object: &'static str,
+    data: Vec<EmbeddingData>,
+    model: String,
+    usage: Usage,
+}
+
+/// A parsed HTTP request
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Serve `/v1/embeddings` on 127.0.0.1:`port` in the background
+pub fn spawn(embedder: Arc<dyn Embedder>, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("Failed to bind embeddings server to port {}", port))?;
+    info!("Embeddings endpoint: http://127.0.0.1:{}/v1/embeddings", port);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let embedder = embedder.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, embedder.as_ref()) {
+                    warn!("Embeddings request failed: {:#}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, embedder: &dyn Embedder) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err(e) => return write_error(&mut stream, 400, &e.to_string()),
+    };
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/v1/embeddings") => {}
+        (_, "/v1/embeddings") => return write_error(&mut stream, 405, "Use POST"),
+        _ => return write_error(&mut stream, 404, "Not found"),
+    }
+
+    let request: EmbeddingRequest = match serde_json::from_slice(&request.body) {
+        Ok(request) => request,
+        Err(e) => return write_error(&mut stream, 400, &format!("Invalid request: {}", e)),
+    };
+    let inputs = match request.input {
+        EmbeddingInput::One(text) => vec![text],
+        EmbeddingInput::Many(texts) => texts,
+    };
+    if inputs.is_empty() || inputs.len() > MAX_INPUTS {
+        let message = format!("Expected between 1 and {} inputs", MAX_INPUTS);
+        return write_error(&mut stream, 400, &message);
+    }
+
+    if request.stream {
+        stream_embeddings(&mut stream, embedder, &inputs)
+    } else {
+        let response = embed_all(embedder, &inputs);
+        write_response(&mut stream, 200, "application/json", &serde_json::to_vec(&response)?)
+    }
+}
+
+/// Embed every input in one call (cache hits skip the model)
+fn embed_all(embedder: &dyn Embedder, inputs: &[String]) -> EmbeddingResponse {
+    let texts: Vec<&str> = inputs.iter().map(String::as_str).collect();
+    let data = embedder
+        .embed_batch(&texts)
+        .into_iter()
+        .enumerate()
+        .map(|(index, embedding)| EmbeddingData {
+            object: "embedding",
+            index,
+            embedding,
+        })
+        .collect();
+    let tokens = approximate_tokens(inputs);
+    EmbeddingResponse {
+        object: "list",
+        data,
+        model: embedder.name(),
+        usage: Usage {
+            prompt_tokens: tokens,
+            total_tokens: tokens,
+        },
+    }
+}
+
+/// Word count standing in for a token count (the embedder doesn't report one)
+fn approximate_tokens(inputs: &[String]) -> usize {
+    inputs.iter().map(|text| tokenize(text).len()).sum()
+}
+
+/// Send embeddings as server-sent events, a batch at a time
+fn stream_embeddings(stream: &mut TcpStream, embedder: &dyn Embedder, inputs: &[String]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+    )?;
+
+    for (batch_index, batch) in inputs.chunks(STREAM_BATCH).enumerate() {
+        let texts: Vec<&str> = batch.iter().map(String::as_str).collect();
+        let mut events = String::new();
+        for (offset, embedding) in embedder.embed_batch(&texts).into_iter().enumerate() {
+            let data = EmbeddingData {
+                object: "embedding",
+                index: batch_index * STREAM_BATCH + offset,
+                embedding,
+            };
+            events.push_str(&format!("data: {}\n\n", serde_json::to_string(&data)?));
+        }
+        write_chunk(stream, events.as_bytes())?;
+    }
+
+    write_chunk(stream, b"data: [DONE]\n\n")?;
+    stream.write_all(b"0\r\n\r\n")?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn write_chunk(stream: &mut TcpStream, data: &[u8]) -> Result<()> {
+    write!(stream, "{:x}\r\n", data.len())?;
+    stream.write_all(data)?;
+    stream.write_all(b"\r\n")?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Read the request line, headers and `Content-Length` body
+pub fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(|| anyhow!("Empty request"))?.to_string();
+    let path = parts.next().ok_or_else(|| anyhow!("Missing request path"))?;
+    let path = path.split('?').next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let content_length: usize = match headers.get("content-length") {
+        Some(length) => length.parse().context("Invalid Content-Length")?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_BYTES {
+        return Err(anyhow!("Request body over {} bytes", MAX_BODY_BYTES));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).context("Request body cut short")?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+pub fn write_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// OpenAI-style `{"error": {"message", "type"}}` response
+fn write_error(stream: &mut TcpStream, status: u16, message: &str) -> Result<()> {
+    let body = serde_json::json!({
+        "error": { "message": message, "type": "invalid_request_error" }
+    });
+    write_response(stream, status, "application/json", body.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::HashingEmbedder;
+
+    fn post(port: u16, body: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "POST /v1/embeddings HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_embeddings_endpoint() {
+        // Bind to a free port first so the test doesn't collide with a running app
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        spawn(Arc::new(HashingEmbedder::default()), port).unwrap();
+
+        let response = post(port, r#"{"input": ["garden tomatoes", "quarterly taxes"], "model": "x"}"#);
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body: serde_json::Value =
+            serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["object"], "list");
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"][1]["index"], 1);
+        assert_eq!(body["data"][0]["embedding"].as_array().unwrap().len(), 256);
+        assert_eq!(body["model"], "hashing");
+
+        let streamed = post(port, r#"{"input": "garden tomatoes", "stream": true}"#);
+        assert!(streamed.contains("text/event-stream"));
+        assert!(streamed.contains("\"index\":0"));
+        assert!(streamed.contains("data: [DONE]"));
+
+        assert!(post(port, r#"{"input": []}"#).starts_with("HTTP/1.1 400"));
+        assert!(post(port, "not json").starts_with("HTTP/1.1 400"));
+    }
+}
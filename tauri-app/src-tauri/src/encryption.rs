@@ -0,0 +1,336 @@
+// Encryption Module - Optional encryption at rest for memories and history
+//
+// With encryption on, the files under `history/`, `conversations/`, `trash/`
+// and `digests/`, the entity index, the running and topic summaries and the
+// content and metadata columns of memories.db are sealed with AES-256-GCM.
+// The key is derived (Argon2id, salt in the `[encryption]` section of
+// settings.toml) from a random secret kept in the OS keychain, so copying the
+// app data dir alone gives nothing away. Readers go through
+// `read`/`read_to_string`/`open_text`, which pass plaintext through
+// unchanged; that keeps data written before encryption was enabled readable
+// and lets `enable_encryption` convert it in place. Embeddings and the vector
+// index stay unencrypted. Memory text search is done in Rust while encryption
+// is on, since SQLite only sees ciphertext.
+//
+// Sealed layout: "AURAENC" | version (1 byte) | nonce (12) | ciphertext
+
+use crate::history_store::write_atomic;
+use crate::settings::{self, AppSettings};
+use crate::{paths, AppState};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+const MAGIC: &[u8; 7] = b"AURAENC";
+const FORMAT_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + NONCE_LEN;
+const SECRET_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+/// Prefix of sealed text stored in SQLite columns
+const TEXT_PREFIX: &str = "enc1:";
+
+const KEYCHAIN_SERVICE: &str = "AuraNexus";
+const KEYCHAIN_ACCOUNT: &str = "data-key";
+
+/// Directories (under the app data dir) whose files are sealed
+const ENCRYPTED_DIRS: &[&str] = &["history", "conversations", "trash", "digests"];
+/// Files directly in the app data dir that are sealed
+const ENCRYPTED_FILES: &[&str] = &["entities.json", "rolling_summaries.json", "compactions.json"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CIPHER: RwLock<Option<Aes256Gcm>> = parking_lot::const_rwlock(None);
+
+/// `[encryption]` in settings.toml; holds nothing secret
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    enabled: bool,
+    /// Salt for deriving the key from the keychain secret, base64
+    salt: String,
+}
+
+impl EncryptionSettings {
+    fn load() -> Self {
+        AppSettings::load().encryption
+    }
+
+    fn save(&self) -> Result<()> {
+        settings::update(|settings| settings.encryption = self.clone()).map(|_| ())
+    }
+}
+
+fn keychain() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).context("OS keychain unavailable")
+}
+
+fn derive_cipher(secret: &[u8], salt: &[u8]) -> Result<Aes256Gcm> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key: {}", e))?;
+    Ok(Aes256Gcm::new(&key))
+}
+
+/// The key for `settings`, from the secret in the keychain
+fn load_cipher(settings: &EncryptionSettings) -> Result<Aes256Gcm> {
+    let secret = keychain()?
+        .get_password()
+        .context("The encryption secret is missing from the OS keychain")?;
+    let secret = BASE64.decode(secret).context("The encryption secret is damaged")?;
+    let salt = BASE64.decode(&settings.salt).context("The encryption salt in settings.toml is damaged")?;
+    derive_cipher(&secret, &salt)
+}
+
+/// Load the key if encryption is on; call once, before anything is read
+///
+/// If the keychain secret can't be read, encrypted data stays unreadable and
+/// nothing is written over it.
+pub fn init() {
+    let settings = EncryptionSettings::load();
+    if !settings.enabled {
+        return;
+    }
+    ENABLED.store(true, Ordering::SeqCst);
+    match load_cipher(&settings) {
+        Ok(cipher) => {
+            *CIPHER.write() = Some(cipher);
+            info!("Encryption at rest enabled");
+        }
+        Err(e) => warn!("Encrypted data can't be opened: {:#}", e),
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+fn seal_with(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.push(FORMAT_VERSION);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LEN && &bytes[..MAGIC.len()] == MAGIC
+}
+
+fn open_with(cipher: Option<&Aes256Gcm>, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_sealed(&bytes) {
+        return Ok(bytes);
+    }
+    let version = bytes[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(anyhow!("Unsupported encryption version {}", version));
+    }
+    let cipher = cipher.ok_or_else(|| anyhow!("Data is encrypted and the key isn't available"))?;
+    cipher
+        .decrypt(Nonce::from_slice(&bytes[MAGIC.len() + 1..HEADER_LEN]), &bytes[HEADER_LEN..])
+        .map_err(|_| anyhow!("Encrypted data is damaged or was sealed with another key"))
+}
+
+/// `plaintext` as it should be stored: sealed when encryption is on
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>> {
+    if !is_enabled() {
+        return Ok(plaintext.to_vec());
+    }
+    let cipher = CIPHER.read();
+    let cipher = cipher.as_ref().ok_or_else(|| anyhow!("Encryption is on but the key isn't available"))?;
+    seal_with(cipher, plaintext)
+}
+
+/// Stored bytes back to plaintext; unsealed bytes are returned as they are
+pub fn open(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    open_with(CIPHER.read().as_ref(), bytes)
+}
+
+/// `std::fs::read` for files that may be sealed
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    open(bytes).with_context(|| format!("Failed to decrypt {}", path.display()))
+}
+
+/// `std::fs::read_to_string` for files that may be sealed
+pub fn read_to_string(path: &Path) -> Result<String> {
+    String::from_utf8(read(path)?).with_context(|| format!("{} is not valid UTF-8", path.display()))
+}
+
+/// `write_atomic`, sealing `contents` when encryption is on
+pub fn write(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic(path, &seal(contents)?)
+}
+
+/// Text for a database column: sealed and base64'd when encryption is on
+pub fn seal_text(text: &str) -> Result<String> {
+    if !is_enabled() {
+        return Ok(text.to_string());
+    }
+    Ok(format!("{}{}", TEXT_PREFIX, BASE64.encode(seal(text.as_bytes())?)))
+}
+
+/// A database column back to text; unsealed text is returned as it is
+pub fn open_text(stored: String) -> Result<String> {
+    let Some(encoded) = stored.strip_prefix(TEXT_PREFIX) else {
+        return Ok(stored);
+    };
+    let sealed = BASE64.decode(encoded).context("Encrypted text is damaged")?;
+    String::from_utf8(open(sealed)?).context("Encrypted text is not valid UTF-8")
+}
+
+/// Re-write every encrypted file and memory in the current mode (sealed or
+/// plain); returns how many were rewritten
+fn rewrite_all(state: &AppState) -> Result<usize> {
+    let mut rewritten = 0;
+    for dir in ENCRYPTED_DIRS {
+        let files = WalkDir::new(paths::app_data_dir().join(dir))
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) != Some("tmp"));
+        for entry in files {
+            let contents = read(entry.path())?;
+            write(entry.path(), &contents)?;
+            rewritten += 1;
+        }
+    }
+    for file in ENCRYPTED_FILES {
+        let path = paths::app_data_dir().join(file);
+        if path.exists() {
+            let contents = read(&path)?;
+            write(&path, &contents)?;
+            rewritten += 1;
+        }
+    }
+
+    rewritten += state.memory_store.lock().rewrite_all();
+    Ok(rewritten)
+}
+
+/// Whether encryption at rest is on and its key is loaded
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    /// False when encryption is on but the keychain secret couldn't be read
+    pub unlocked: bool,
+}
+
+fn status() -> EncryptionStatus {
+    EncryptionStatus {
+        enabled: is_enabled(),
+        unlocked: CIPHER.read().is_some(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_encryption_status() -> Result<EncryptionStatus, String> {
+    Ok(status())
+}
+
+/// Turn on encryption at rest, sealing the existing history, conversations
+/// and memories
+#[tauri::command]
+pub async fn enable_encryption(state: tauri::State<'_, AppState>) -> Result<EncryptionStatus, String> {
+    if is_enabled() {
+        return Ok(status());
+    }
+    if state.generation.is_active() {
+        return Err("Wait for the current response to finish".to_string());
+    }
+
+    let enable = || -> Result<usize> {
+        let mut secret = [0u8; SECRET_LEN];
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut secret);
+        OsRng.fill_bytes(&mut salt);
+        keychain()?
+            .set_password(&BASE64.encode(secret))
+            .context("Failed to store the encryption secret in the OS keychain")?;
+        *CIPHER.write() = Some(derive_cipher(&secret, &salt)?);
+        EncryptionSettings {
+            enabled: true,
+            salt: BASE64.encode(salt),
+        }
+        .save()?;
+        ENABLED.store(true, Ordering::SeqCst);
+        rewrite_all(&state)
+    };
+    let sealed = enable().map_err(|e| format!("{:#}", e))?;
+    info!("Encryption at rest enabled ({} files and memories sealed)", sealed);
+    Ok(status())
+}
+
+/// Turn off encryption at rest, writing everything back as plaintext and
+/// removing the secret from the keychain
+#[tauri::command]
+pub async fn disable_encryption(state: tauri::State<'_, AppState>) -> Result<EncryptionStatus, String> {
+    if !is_enabled() {
+        return Ok(status());
+    }
+    if CIPHER.read().is_none() {
+        return Err("The encryption key isn't available, so the data can't be decrypted".to_string());
+    }
+    if state.generation.is_active() {
+        return Err("Wait for the current response to finish".to_string());
+    }
+
+    // The key stays loaded until everything has been rewritten in the clear
+    let disable = || -> Result<usize> {
+        ENABLED.store(false, Ordering::SeqCst);
+        let rewritten = rewrite_all(&state)?;
+        EncryptionSettings::default().save()?;
+        *CIPHER.write() = None;
+        if let Err(e) = keychain().and_then(|entry| entry.delete_password().map_err(anyhow::Error::from)) {
+            warn!("Failed to remove the encryption secret from the keychain: {:#}", e);
+        }
+        Ok(rewritten)
+    };
+    let result = disable();
+    if result.is_err() {
+        // Anything not yet rewritten is still sealed
+        ENABLED.store(true, Ordering::SeqCst);
+    }
+    let opened = result.map_err(|e| format!("{:#}", e))?;
+    info!("Encryption at rest disabled ({} files and memories decrypted)", opened);
+    Ok(status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = derive_cipher(&[7u8; SECRET_LEN], &[1u8; SALT_LEN]).unwrap();
+        let sealed = seal_with(&cipher, b"Takes 50 mg metformin").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(9).any(|w| w == b"metformin"));
+        assert_eq!(open_with(Some(&cipher), sealed.clone()).unwrap(), b"Takes 50 mg metformin");
+
+        // Plaintext written before encryption passes through
+        assert_eq!(open_with(None, b"[]".to_vec()).unwrap(), b"[]");
+        assert!(open_with(None, sealed.clone()).is_err());
+        let other = derive_cipher(&[8u8; SECRET_LEN], &[1u8; SALT_LEN]).unwrap();
+        assert!(open_with(Some(&other), sealed).is_err());
+
+        assert_eq!(open_text("plain memory".to_string()).unwrap(), "plain memory");
+    }
+}
@@ -0,0 +1,465 @@
+// Entities Module - Index of the people, places and projects conversations mention
+//
+// Every recorded turn is scanned for names: runs of capitalized words, typed
+// by the words around them ("with Anna" is a person, "in Lisbon" a place,
+// "Project Phoenix" a project). `entities.json` keeps each one's mention
+// count, when it first and last came up and the message it last came up in.
+// A lone capitalized word at the start of a sentence is only counted once the
+// name is known from elsewhere. When the user asks when something was last
+// talked about, the indexed answer goes into the system prompt so the model
+// doesn't have to guess it from retrieved memories.
+
+use crate::clock::{self, Dated, SystemClock};
+use crate::history_store::HistoryStore;
+use crate::{encryption, paths, AppState, ConversationEntry};
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// Longest name kept, in words
+const MAX_NAME_WORDS: usize = 4;
+
+/// Characters of the last message mentioning an entity that are kept
+const EXCERPT_CHARS: usize = 160;
+
+/// Serializes load-change-save of `entities.json`
+static LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+/// Capitalized words that aren't names
+const STOPWORDS: &[&str] = &[
+    "i", "i'm", "i've", "i'll", "i'd", "a", "an", "the", "this", "that", "these", "those", "it", "it's",
+    "he", "she", "we", "they", "you", "me", "my", "our", "your", "his", "her", "their", "what", "when",
+    "where", "why", "how", "who", "which", "yes", "no", "ok", "okay", "hi", "hello", "hey", "thanks",
+    "thank", "please", "and", "but", "or", "so", "if", "then", "also", "just", "well", "oh", "maybe",
+    "did", "do", "does", "can", "could", "would", "should", "will", "is", "are", "was", "were", "let's",
+    "there", "here", "not", "now", "sure", "sorry", "in", "on", "at", "for", "with", "to", "from", "as",
+    "all", "some", "any", "every", "one", "today", "tonight", "tomorrow", "yesterday", "monday",
+    "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday", "january", "february", "march",
+    "april", "june", "july", "august", "september", "october", "november", "december",
+];
+
+/// Titles starting a person's name
+const HONORIFICS: &[&str] = &["mr", "mrs", "ms", "miss", "dr", "prof", "aunt", "uncle", "grandma", "grandpa"];
+
+/// Words before a person's name
+const PERSON_CUES: &[&str] = &[
+    "with", "met", "meet", "meeting", "told", "tell", "asked", "ask", "called", "call", "named", "texted",
+    "friend", "sister", "brother", "mom", "dad", "mother", "father", "wife", "husband", "partner",
+    "boss", "colleague", "son", "daughter", "cousin",
+];
+
+/// Words after a person's name
+const PERSON_VERBS: &[&str] = &["said", "says", "told", "asked", "thinks", "thought", "wants", "texted", "called"];
+
+/// Words before a place name
+const PLACE_CUES: &[&str] = &["in", "at", "from", "visit", "visited", "visiting", "near", "around", "moved"];
+
+/// What an entity is, as far as the words around it tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Person,
+    Place,
+    Project,
+    Other,
+}
+
+/// A name found in one message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mention {
+    pub name: String,
+    pub kind: EntityKind,
+    /// False for a lone capitalized word opening a sentence, which may just
+    /// be an ordinary word
+    pub confident: bool,
+}
+
+/// An indexed entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    pub kind: EntityKind,
+    pub mentions: u32,
+    /// RFC3339 timestamps of the first and latest message mentioning it
+    pub first_seen: String,
+    pub last_seen: String,
+    pub last_run_id: Option<String>,
+    /// Start of the latest message mentioning it
+    pub last_excerpt: String,
+}
+
+struct Token {
+    word: String,
+    sentence_start: bool,
+    /// Followed by punctuation (or a possessive), so no name continues past it
+    ends_clause: bool,
+}
+
+fn tokens(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut sentence_start = true;
+    for raw in text.split_whitespace() {
+        let word = raw.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+        let word = word.trim_matches('\'');
+        let possessive = word.strip_suffix("'s");
+        let trailing = &raw[raw.trim_end_matches(|c: char| !c.is_alphanumeric()).len()..];
+        let honorific = HONORIFICS.contains(&word.to_lowercase().as_str());
+        let ends_sentence = !honorific && trailing.contains(['.', '!', '?']);
+        tokens.push(Token {
+            word: possessive.unwrap_or(word).to_string(),
+            sentence_start,
+            ends_clause: possessive.is_some() || (!honorific && !trailing.is_empty()),
+        });
+        sentence_start = ends_sentence;
+    }
+    tokens
+}
+
+fn is_name_word(token: &Token) -> bool {
+    token.word.chars().next().is_some_and(char::is_uppercase)
+        && !STOPWORDS.contains(&token.word.to_lowercase().as_str())
+}
+
+/// Names mentioned in `text`
+pub fn extract(text: &str) -> Vec<Mention> {
+    let tokens = tokens(text);
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_name_word(&tokens[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < tokens.len() && is_name_word(&tokens[i]) && i - start < MAX_NAME_WORDS {
+            // "Project" always opens a new name
+            if i > start && tokens[i].word.eq_ignore_ascii_case("project") {
+                break;
+            }
+            i += 1;
+            if tokens[i - 1].ends_clause {
+                break;
+            }
+        }
+        let words: Vec<&str> = tokens[start..i].iter().map(|token| token.word.as_str()).collect();
+        let previous = start
+            .checked_sub(1)
+            .filter(|&p| !tokens[p].ends_clause)
+            .map(|p| tokens[p].word.to_lowercase());
+        let next = tokens
+            .get(i)
+            .filter(|_| !tokens[i - 1].ends_clause)
+            .map(|token| token.word.to_lowercase());
+        if let Some(mention) = classify(&words, previous.as_deref(), next.as_deref(), tokens[start].sentence_start) {
+            mentions.push(mention);
+        }
+    }
+    mentions
+}
+
+fn classify(words: &[&str], previous: Option<&str>, next: Option<&str>, sentence_start: bool) -> Option<Mention> {
+    let first = words[0].to_lowercase();
+    let is = |word: Option<&str>, list: &[&str]| word.is_some_and(|word| list.contains(&word));
+    let (words, kind) = if first == "project" {
+        (&words[1..], EntityKind::Project)
+    } else if previous == Some("project") {
+        (words, EntityKind::Project)
+    } else if HONORIFICS.contains(&first.as_str()) {
+        (if words.len() > 1 { words } else { &words[1..] }, EntityKind::Person)
+    } else if is(previous, PERSON_CUES) || is(next, PERSON_VERBS) {
+        (words, EntityKind::Person)
+    } else if is(previous, PLACE_CUES) {
+        (words, EntityKind::Place)
+    } else {
+        (words, EntityKind::Other)
+    };
+    if words.is_empty() {
+        return None;
+    }
+    Some(Mention {
+        name: words.join(" "),
+        kind,
+        confident: !(sentence_start && words.len() == 1 && kind == EntityKind::Other),
+    })
+}
+
+/// Index key: lowercase, without a leading "project"
+fn key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let name = name.strip_prefix("project ").unwrap_or(&name);
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `text` lowercased with punctuation and possessives removed, padded with
+/// spaces so keys match whole words
+fn normalized(text: &str) -> String {
+    let words: Vec<String> = tokens(text).into_iter().map(|token| token.word.to_lowercase()).collect();
+    format!(" {} ", words.join(" "))
+}
+
+fn is_later(a: &str, b: &str) -> bool {
+    match (clock::parse(a), clock::parse(b)) {
+        (Some(a), Some(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+/// Entities by key, persisted in `entities.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EntityIndex {
+    entities: HashMap<String, Entity>,
+}
+
+impl EntityIndex {
+    fn path() -> PathBuf {
+        paths::app_data_dir().join("entities.json")
+    }
+
+    pub fn exists() -> bool {
+        Self::path().exists()
+    }
+
+    pub fn load() -> Self {
+        encryption::read_to_string(&Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        encryption::write(&path, serde_json::to_string(self)?.as_bytes()).context("Failed to save entity index")
+    }
+
+    /// Count the names in a message sent at `timestamp`
+    pub fn record(&mut self, text: &str, timestamp: &str, run_id: Option<&str>) {
+        for mention in extract(text) {
+            let key = key(&mention.name);
+            if key.is_empty() || (!mention.confident && !self.entities.contains_key(&key)) {
+                continue;
+            }
+            let entity = self.entities.entry(key).or_insert_with(|| Entity {
+                name: mention.name.clone(),
+                kind: mention.kind,
+                mentions: 0,
+                first_seen: timestamp.to_string(),
+                last_seen: timestamp.to_string(),
+                last_run_id: None,
+                last_excerpt: String::new(),
+            });
+            entity.mentions += 1;
+            if entity.kind == EntityKind::Other {
+                entity.kind = mention.kind;
+            }
+            if is_later(&entity.first_seen, timestamp) {
+                entity.first_seen = timestamp.to_string();
+            }
+            if !is_later(&entity.last_seen, timestamp) {
+                entity.last_seen = timestamp.to_string();
+                entity.last_run_id = run_id.map(str::to_string);
+                entity.last_excerpt = text.chars().take(EXCERPT_CHARS).collect();
+            }
+        }
+    }
+
+    pub fn record_all(&mut self, entries: &[ConversationEntry], run_id: Option<&str>) {
+        for entry in entries {
+            self.record(&entry.content, &entry.timestamp, run_id);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entity> {
+        self.entities.get(&key(name))
+    }
+
+    /// The indexed entity `text` names (the longest name if several)
+    pub fn find_in(&self, text: &str) -> Option<&Entity> {
+        let text = normalized(text);
+        self.entities
+            .iter()
+            .filter(|(key, _)| text.contains(&format!(" {} ", key)))
+            .max_by_key(|(key, entity)| (key.len(), entity.mentions))
+            .map(|(_, entity)| entity)
+    }
+
+    /// Entities, most mentioned first (of one kind if given)
+    pub fn list(&self, kind: Option<EntityKind>) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .entities
+            .values()
+            .filter(|entity| kind.is_none() || kind == Some(entity.kind))
+            .cloned()
+            .collect();
+        entities.sort_by(|a, b| b.mentions.cmp(&a.mentions).then_with(|| b.last_seen.cmp(&a.last_seen)));
+        entities
+    }
+
+    pub fn count(&self) -> usize {
+        self.entities.len()
+    }
+}
+
+/// Index the names in a recorded turn
+pub fn record_turn(entries: &[ConversationEntry], run_id: &str) {
+    let _guard = LOCK.lock();
+    let mut index = EntityIndex::load();
+    index.record_all(entries, Some(run_id));
+    if let Err(e) = index.save() {
+        warn!("Failed to update entity index: {:#}", e);
+    }
+}
+
+/// Rebuild the index from every archived session plus the current history
+pub fn rebuild(history_store: &HistoryStore, history: &[ConversationEntry], run_id: &str) -> Result<usize> {
+    let _guard = LOCK.lock();
+    let mut index = EntityIndex::default();
+    for summary in history_store.list_archived(true) {
+        match history_store.load_archived(&summary.run_id) {
+            Ok(archived) => index.record_all(&archived.entries, Some(&archived.session.run_id)),
+            Err(e) => warn!("Skipping session {} in entity index: {:#}", summary.run_id, e),
+        }
+    }
+    index.record_all(history, Some(run_id));
+    index.save()?;
+    Ok(index.count())
+}
+
+/// Build the index from past conversations on first run
+pub fn spawn_backfill(history_store: std::sync::Arc<HistoryStore>, history: Vec<ConversationEntry>, run_id: String) {
+    if EntityIndex::exists() {
+        return;
+    }
+    std::thread::spawn(move || match rebuild(&history_store, &history, &run_id) {
+        Ok(count) => info!("Indexed {} entities from past conversations", count),
+        Err(e) => warn!("Failed to build entity index: {:#}", e),
+    });
+}
+
+/// Whether `message` asks when something last came up
+pub fn asks_last_mention(message: &str) -> bool {
+    let message = message.to_lowercase();
+    let asks_when = ["when did", "last time", "how long since", "how long ago"]
+        .iter()
+        .any(|phrase| message.contains(phrase));
+    let about_talking = ["talk", "spoke", "speak", "chat", "mention", "discuss", "brought up", "came up"]
+        .iter()
+        .any(|word| message.contains(word));
+    asks_when && about_talking
+}
+
+/// What the index says about `entity`, for the model
+fn describe(entity: &Entity, now: chrono::DateTime<chrono::Utc>) -> String {
+    let when = |timestamp: &str| {
+        clock::parse(timestamp).map(|time| {
+            (
+                clock::to_local(time).format("%A %-d %B %Y at %H:%M").to_string(),
+                clock::relative(time, now),
+            )
+        })
+    };
+    let Some((last, ago)) = when(&entity.last_seen) else {
+        return String::new();
+    };
+    let first = when(&entity.first_seen).map_or_else(String::new, |(first, _)| format!(" since {}", first));
+    format!(
+        "From the conversation records: {} last came up on {} ({}), {} mention(s){}. That message began: \"{}\"",
+        entity.name, last, ago, entity.mentions, first, entity.last_excerpt
+    )
+}
+
+/// `system_prompt` plus when the entity the message asks about last came up,
+/// if it asks that and the entity is indexed
+pub fn with_last_mention(system_prompt: &str, message: &str) -> String {
+    if !asks_last_mention(message) {
+        return system_prompt.to_string();
+    }
+    let index = EntityIndex::load();
+    match index.find_in(message).map(|entity| describe(entity, clock::now())) {
+        Some(fact) if !fact.is_empty() => format!("{}\n\n{}", system_prompt, fact),
+        _ => system_prompt.to_string(),
+    }
+}
+
+fn dated(entities: Vec<Entity>) -> Vec<Dated<Entity>> {
+    clock::dated(entities, &SystemClock, |entity| clock::parse(&entity.last_seen))
+}
+
+/// Indexed entities, most mentioned first
+#[tauri::command]
+pub async fn list_entities(kind: Option<EntityKind>, limit: Option<usize>) -> Result<Vec<Dated<Entity>>, String> {
+    let mut entities = EntityIndex::load().list(kind);
+    entities.truncate(limit.unwrap_or(usize::MAX));
+    Ok(dated(entities))
+}
+
+/// When `name` was last mentioned (none if it never was)
+#[tauri::command]
+pub async fn entity_last_mentioned(name: String) -> Result<Option<Dated<Entity>>, String> {
+    let entity = EntityIndex::load().get(&name).cloned();
+    Ok(dated(entity.into_iter().collect()).pop())
+}
+
+/// Re-scan every conversation into a fresh index; returns how many entities
+#[tauri::command]
+pub async fn rebuild_entity_index(state: tauri::State<'_, AppState>) -> Result<usize, String> {
+    let history = state.conversation_history.lock().clone();
+    let run_id = state.session.lock().run_id.clone();
+    let history_store = state.history_store.clone();
+    let count = tauri::async_runtime::spawn_blocking(move || rebuild(&history_store, &history, &run_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    info!("Rebuilt entity index ({} entities)", count);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extraction_and_last_mention() {
+        let mentions = extract("Yesterday I had lunch with Anna Berg in Lisbon. We planned Project Phoenix, and Dr. Ruiz said hi.");
+        let found: Vec<(&str, EntityKind)> = mentions.iter().map(|m| (m.name.as_str(), m.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Anna Berg", EntityKind::Person),
+                ("Lisbon", EntityKind::Place),
+                ("Phoenix", EntityKind::Project),
+                ("Dr Ruiz", EntityKind::Person),
+            ]
+        );
+        // A sentence-opening word is only a maybe
+        assert!(!extract("Sounds good.")[0].confident);
+
+        let mut index = EntityIndex::default();
+        index.record("Sounds good.", "2024-05-01T09:00:00+00:00", None);
+        assert_eq!(index.count(), 0);
+        index.record("How is project Phoenix going?", "2024-05-02T09:00:00+00:00", Some("run-1"));
+        index.record("Phoenix shipped! Anna's team did it.", "2024-06-10T18:30:00+00:00", Some("run-2"));
+        // Out of order, as when rebuilding
+        index.record("Planning Project Phoenix", "2024-04-20T08:00:00+00:00", Some("run-0"));
+
+        let phoenix = index.get("Project Phoenix").unwrap();
+        assert_eq!(phoenix.kind, EntityKind::Project);
+        assert_eq!(phoenix.mentions, 3);
+        assert_eq!(phoenix.first_seen, "2024-04-20T08:00:00+00:00");
+        assert_eq!(phoenix.last_seen, "2024-06-10T18:30:00+00:00");
+        assert_eq!(phoenix.last_run_id.as_deref(), Some("run-2"));
+        // Opens its sentence and wasn't seen before
+        assert!(index.get("Anna").is_none());
+
+        let question = "When did we last talk about Project Phoenix?";
+        assert!(asks_last_mention(question));
+        assert!(!asks_last_mention("Tell me about Project Phoenix"));
+        assert_eq!(index.find_in(question).unwrap().name, "Phoenix");
+        assert!(index.find_in("When did we last talk about Lisbon?").is_none());
+    }
+}
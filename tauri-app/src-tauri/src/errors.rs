@@ -0,0 +1,160 @@
+// Errors Module - Errors the frontend can act on
+//
+// Commands returning `AppError` reach the frontend as
+// `{"code": "model_not_found", "message": "...", "hint": "..."}` rather than
+// a bare string, so it can show what to do next (or act on the code, e.g.
+// open the model picker). Errors from the backends arrive as `anyhow` chains;
+// `classify` recognises the typed ones (`BackendError::Timeout`) and the
+// usual wording of llama.cpp, Python and HTTP failures. Anything else from
+// `anyhow` is `internal`; a plain `String` error is the caller's mistake,
+// `invalid_request`.
+
+use crate::http_backend;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    ModelNotFound(String),
+    #[error("{0}")]
+    BackendUnavailable(String),
+    #[error("{0}")]
+    GenerationTimeout(String),
+    #[error("{0}")]
+    OutOfMemory(String),
+    #[error("{0}")]
+    PythonImportError(String),
+    /// Something else is in progress (a reply, a model load)
+    #[error("{0}")]
+    Busy(String),
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    /// Stable identifier for the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ModelNotFound(_) => "model_not_found",
+            AppError::BackendUnavailable(_) => "backend_unavailable",
+            AppError::GenerationTimeout(_) => "generation_timeout",
+            AppError::OutOfMemory(_) => "out_of_memory",
+            AppError::PythonImportError(_) => "python_import_error",
+            AppError::Busy(_) => "busy",
+            AppError::InvalidRequest(_) => "invalid_request",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// What the user can do about it
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AppError::ModelNotFound(_) => Some("Download a model or pick one in Settings → Models."),
+            AppError::BackendUnavailable(_) => {
+                Some("Start llm_server.py, or choose another backend in Settings → Backend.")
+            }
+            AppError::GenerationTimeout(_) => {
+                Some("Try a shorter reply or a smaller model, or raise the timeout in Settings → Backend.")
+            }
+            AppError::OutOfMemory(_) => {
+                Some("Use a smaller or more quantized model, or lower the context size in Settings → Inference.")
+            }
+            AppError::PythonImportError(_) => {
+                Some("Install the backend's requirements (pip install -r requirements.txt) or use the native backend.")
+            }
+            AppError::Busy(_) => Some("Wait for it to finish, or stop the current reply."),
+            AppError::InvalidRequest(_) => None,
+            AppError::Internal(_) => Some("See the logs for details."),
+        }
+    }
+
+    /// The specific kind of a backend error, if it is a known one
+    pub fn classify(error: &anyhow::Error) -> Option<AppError> {
+        let message = format!("{:#}", error);
+        if http_backend::is_timeout(error) {
+            return Some(AppError::GenerationTimeout(message));
+        }
+        let lower = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        let kind = if has(&["out of memory", "failed to allocate", "cannot allocate", "memoryerror"]) {
+            AppError::OutOfMemory
+        } else if has(&["modulenotfounderror", "importerror", "no module named", "no python interpreter"]) {
+            AppError::PythonImportError
+        } else if has(&["no model found", "model not found", "no such file", "cannot find the file"]) {
+            AppError::ModelNotFound
+        } else if has(&[
+            "failed to connect",
+            "connection refused",
+            "is llm_server.py running",
+            "python worker exited",
+            "not restarting",
+        ]) {
+            AppError::BackendUnavailable
+        } else {
+            return None;
+        };
+        Some(kind(message))
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("hint", &self.hint())?;
+        error.end()
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        AppError::classify(&error).unwrap_or_else(|| AppError::Internal(format!("{:#}", error)))
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::InvalidRequest(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::InvalidRequest(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_backend::BackendError;
+    use anyhow::{anyhow, Context};
+    use std::time::Duration;
+
+    #[test]
+    fn test_classify_and_serialize() {
+        let timeout = anyhow::Error::new(BackendError::Timeout {
+            operation: "generation",
+            limit: Duration::from_secs(5),
+        });
+        assert_eq!(AppError::from(timeout).code(), "generation_timeout");
+        let oom = Err::<(), _>(anyhow!("ggml_cuda: failed to allocate 2.1 GB")).context("Failed to load model");
+        assert_eq!(AppError::from(oom.unwrap_err()).code(), "out_of_memory");
+        let import = anyhow!("ModuleNotFoundError: No module named 'llama_cpp'");
+        assert_eq!(AppError::from(import).code(), "python_import_error");
+        assert_eq!(AppError::from(anyhow!("No model found")).code(), "model_not_found");
+        assert_eq!(AppError::from(anyhow!("Failed to connect to LLM server")).code(), "backend_unavailable");
+        assert_eq!(AppError::from(anyhow!("disk on fire")).code(), "internal");
+        assert_eq!(AppError::from("No message 4".to_string()).code(), "invalid_request");
+
+        let json = serde_json::to_value(AppError::ModelNotFound("No model found".to_string())).unwrap();
+        assert_eq!(json["code"], "model_not_found");
+        assert_eq!(json["message"], "No model found");
+        assert!(json["hint"].as_str().unwrap().contains("Settings"));
+        assert!(serde_json::to_value(AppError::from("bad")).unwrap()["hint"].is_null());
+    }
+}
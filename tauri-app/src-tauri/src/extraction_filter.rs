@@ -0,0 +1,126 @@
+// Extraction Filter Module - Cheap gate in front of LLM memory extraction
+//
+// Running fact extraction through the LLM after every turn is too slow on
+// small hardware, and most turns ("ok", "thanks!", "what's 2+2?") hold
+// nothing worth remembering. Each user message is first checked on cheap
+// signals, cheapest first: enough words, a first-person statement (not just
+// a question), and novelty against what is already stored (similarity to the
+// nearest memory). The verdict is recorded on the message's memory as
+// `extraction`; only "extract" turns are worth an extraction pass.
+
+use crate::memory_store::{MemoryFilters, MemoryStore};
+use serde::{Deserialize, Serialize};
+
+/// Fewer words than this rarely state anything lasting
+const MIN_WORDS: usize = 4;
+
+/// Similarity to an existing memory above which a turn adds nothing new
+const NOVELTY_THRESHOLD: f32 = 0.9;
+
+/// Words that mark a statement about the user
+const FIRST_PERSON: [&str; 16] = [
+    "i", "i'm", "im", "i've", "ive", "i'd", "i'll", "me", "my", "mine", "myself", "we", "we're",
+    "our", "ours", "us",
+];
+
+/// Whether a turn should go through extraction, and if not, why
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Extract,
+    TooShort,
+    /// No first-person statement (questions, commands, small talk)
+    NoPersonalStatement,
+    /// Close to something already remembered
+    AlreadyKnown,
+}
+
+impl Verdict {
+    pub fn should_extract(self) -> bool {
+        self == Self::Extract
+    }
+}
+
+/// Whether any sentence of `text` that isn't a question speaks in the first
+/// person
+pub fn has_first_person_statement(text: &str) -> bool {
+    let text = text.replace('\u{2019}', "'").to_lowercase();
+    text.split_inclusive(['.', '!', '?', '\n'])
+        .filter(|sentence| !sentence.trim_end().ends_with('?'))
+        .any(|sentence| {
+            sentence
+                .split(|c: char| !c.is_alphanumeric() && c != '\'')
+                .any(|word| FIRST_PERSON.contains(&word))
+        })
+}
+
+/// Verdict from the cheap text checks alone; `None` means the turn passed
+/// them and only novelty is left to check
+fn text_verdict(text: &str) -> Option<Verdict> {
+    if text.split_whitespace().count() < MIN_WORDS {
+        Some(Verdict::TooShort)
+    } else if !has_first_person_statement(text) {
+        Some(Verdict::NoPersonalStatement)
+    } else {
+        None
+    }
+}
+
+/// Decide whether `text` is worth extraction, given the similarity of the
+/// nearest stored memory (if any)
+pub fn evaluate(text: &str, nearest_similarity: Option<f32>) -> Verdict {
+    if let Some(verdict) = text_verdict(text) {
+        return verdict;
+    }
+    match nearest_similarity {
+        Some(similarity) if similarity >= NOVELTY_THRESHOLD => Verdict::AlreadyKnown,
+        _ => Verdict::Extract,
+    }
+}
+
+/// Check a user message against `store`, embedding it only if the cheap
+/// checks pass
+///
+/// Call before the message itself is stored, or it will match itself.
+pub fn check(store: &MemoryStore, text: &str, filters: &MemoryFilters) -> Verdict {
+    if let Some(verdict) = text_verdict(text) {
+        return verdict;
+    }
+    let nearest = store
+        .search_scored(text, Some(filters), 1)
+        .first()
+        .map(|(_, score)| *score);
+    evaluate(text, nearest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cheap_signals() {
+        assert_eq!(evaluate("ok thanks", None), Verdict::TooShort);
+        assert_eq!(
+            evaluate("What is the capital of France?", None),
+            Verdict::NoPersonalStatement
+        );
+        assert_eq!(
+            evaluate("Can you remind me what I said yesterday?", None),
+            Verdict::NoPersonalStatement
+        );
+        assert_eq!(
+            evaluate("I’m allergic to peanuts, so keep that in mind.", None),
+            Verdict::Extract
+        );
+        assert_eq!(
+            evaluate("My sister moved to Lisbon. Any tips for visiting?", Some(0.4)),
+            Verdict::Extract
+        );
+        assert_eq!(
+            evaluate("My sister moved to Lisbon last month.", Some(0.95)),
+            Verdict::AlreadyKnown
+        );
+        // "mine" is a word, not part of "minecraft"
+        assert!(!has_first_person_statement("Minecraft servers are down again."));
+    }
+}
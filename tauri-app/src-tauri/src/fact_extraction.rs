@@ -0,0 +1,353 @@
+// Fact Extraction Module - mem0-style add pipeline for lasting facts
+//
+// Messages are stored verbatim; this pulls what is worth remembering out of
+// them. After an exchange whose user message passed the extraction filter
+// (see `extraction_filter`), a background pass asks the model - extraction
+// preset, JSON-constrained through `structured` - for the durable facts and
+// preferences it states ("User's dog is named Rex"). Stored facts similar to
+// them are looked up, and a second call decides for each, as mem0 does,
+// whether to ADD it, UPDATE a fact it refines, DELETE one it contradicts or
+// do nothing. Facts are memories with kind "fact", tagged as part of the user
+// profile, so updates and deletes land in their version history and only
+// personas allowed to write the profile run the pass. Passes run one at a time on the chat
+// backend; a message sent meanwhile waits for the pass to finish.
+
+use crate::backend::{LlmBackend, SharedBackend};
+use crate::memory_policy::{self, current_scope};
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use crate::session::SessionIds;
+use crate::settings::AppSettings;
+use crate::task_presets::{self, Task};
+use crate::{structured, AppState};
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Memory kind of extracted facts
+pub const FACT_KIND: &str = "fact";
+
+/// Similar stored facts looked up per new fact
+const CANDIDATES_PER_FACT: usize = 5;
+
+/// Most stored facts shown to the model at once
+const MAX_CANDIDATES: usize = 20;
+
+const EXTRACT_PROMPT: &str = "You pull lasting facts about the user out of a conversation: preferences, plans, \
+relationships, health details, habits, and other personal details worth remembering later. Write each fact as a \
+short standalone sentence about the user, e.g. \"User's dog is named Rex\". Ignore small talk, questions, and \
+anything the assistant said that the user did not confirm. Return an empty list when nothing qualifies.";
+
+const DECIDE_PROMPT: &str = "You keep a memory of facts about the user up to date. For each new fact, compare it with \
+the existing memories and decide: ADD it when nothing covers it (id \"new\"); UPDATE an existing memory it refines \
+or changes, giving that memory's id and the combined text; DELETE an existing memory it contradicts, giving its id; \
+or NONE when it is already known. Never invent ids.";
+
+/// Serializes passes so each sees the facts the previous one stored
+static PASS: Mutex<()> = parking_lot::const_mutex(());
+
+/// `[extraction]` in settings.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractionSettings {
+    /// Run the fact extraction pass after exchanges
+    pub enabled: bool,
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// What the model decided for a fact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FactEvent {
+    Add,
+    Update,
+    Delete,
+    None,
+}
+
+/// One entry of the model's decision
+#[derive(Debug, Clone, Deserialize)]
+pub struct Decision {
+    /// Index of an existing memory as shown to the model, or "new"
+    pub id: String,
+    pub text: String,
+    pub event: FactEvent,
+}
+
+/// A change to make to the store
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactAction {
+    Add(String),
+    Update { memory_id: String, text: String },
+    Delete(String),
+}
+
+/// What a pass changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ExtractionSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+fn facts_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "facts": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["facts"]
+    })
+}
+
+fn decisions_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "memory": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "text": { "type": "string" },
+                        "event": { "enum": ["ADD", "UPDATE", "DELETE", "NONE"] }
+                    },
+                    "required": ["id", "text", "event"]
+                }
+            }
+        },
+        "required": ["memory"]
+    })
+}
+
+/// Lowercased words only, so trivial rewordings compare equal
+pub fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Changes for `decisions`, whose ids index `existing`
+///
+/// Unknown ids, repeated changes to one memory, no-op updates and facts
+/// already stored word for word are dropped.
+pub fn plan(decisions: Vec<Decision>, existing: &[MemoryItem]) -> Vec<FactAction> {
+    let mut known: HashSet<String> = existing.iter().map(|memory| normalize(&memory.content)).collect();
+    let mut touched = HashSet::new();
+    let mut actions = Vec::new();
+    for decision in decisions {
+        let text = decision.text.trim().to_string();
+        let target = decision
+            .id
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| existing.get(index));
+        match (decision.event, target) {
+            (FactEvent::Add, _) => {
+                if !text.is_empty() && known.insert(normalize(&text)) {
+                    actions.push(FactAction::Add(text));
+                }
+            }
+            (FactEvent::Update, Some(memory)) => {
+                if !text.is_empty() && normalize(&text) != normalize(&memory.content) && touched.insert(&memory.id) {
+                    known.insert(normalize(&text));
+                    actions.push(FactAction::Update {
+                        memory_id: memory.id.clone(),
+                        text,
+                    });
+                }
+            }
+            (FactEvent::Delete, Some(memory)) => {
+                if touched.insert(&memory.id) {
+                    actions.push(FactAction::Delete(memory.id.clone()));
+                }
+            }
+            (FactEvent::Update | FactEvent::Delete, None) => {
+                warn!("Ignoring a fact {:?} for unknown memory '{}'", decision.event, decision.id);
+            }
+            (FactEvent::None, _) => {}
+        }
+    }
+    actions
+}
+
+/// Make `actions` on `store`, tagging new facts with `session`'s ids
+pub fn apply(store: &mut MemoryStore, actions: Vec<FactAction>, session: &SessionIds) -> ExtractionSummary {
+    let mut summary = ExtractionSummary::default();
+    for action in actions {
+        match action {
+            FactAction::Add(text) => {
+                let mut metadata = HashMap::from([
+                    ("kind".to_string(), json!(FACT_KIND)),
+                    ("extracted_at".to_string(), json!(crate::clock::timestamp())),
+                ]);
+                memory_policy::tag_profile(&mut metadata);
+                let (user_id, agent_id, run_id) = session.memory_ids();
+                match store.add(text, user_id, agent_id, run_id, metadata) {
+                    Ok(_) => summary.added += 1,
+                    Err(e) => warn!("Failed to store an extracted fact: {}", e),
+                }
+            }
+            FactAction::Update { memory_id, text } => match store.update(&memory_id, Some(text), None) {
+                Ok(true) => summary.updated += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to update fact {}: {}", memory_id, e),
+            },
+            FactAction::Delete(memory_id) => summary.deleted += store.delete(&memory_id) as usize,
+        }
+    }
+    summary
+}
+
+/// Stored facts similar to any of `facts`, at most `MAX_CANDIDATES`
+fn candidates(store: &MemoryStore, facts: &[String], filters: &MemoryFilters) -> Vec<MemoryItem> {
+    let mut seen = HashSet::new();
+    facts
+        .iter()
+        .flat_map(|fact| store.search(fact, Some(filters), CANDIDATES_PER_FACT))
+        .filter(|memory| seen.insert(memory.id.clone()))
+        .take(MAX_CANDIDATES)
+        .collect()
+}
+
+/// Run the whole pass over `exchange` and update the store
+pub fn extract(
+    backend: &mut dyn LlmBackend,
+    store: &Mutex<MemoryStore>,
+    session: &SessionIds,
+    filters: &MemoryFilters,
+    exchange: &str,
+) -> Result<ExtractionSummary> {
+    let config = task_presets::config_for(Task::Extraction);
+    let reply = structured::generate(backend, exchange, EXTRACT_PROMPT, &facts_schema(), &config)?;
+    let facts: Vec<String> = serde_json::from_value::<Vec<String>>(reply.value["facts"].clone())
+        .context("Unexpected fact list")?
+        .into_iter()
+        .map(|fact| fact.trim().to_string())
+        .filter(|fact| !fact.is_empty())
+        .collect();
+    if facts.is_empty() {
+        return Ok(ExtractionSummary::default());
+    }
+
+    let existing = candidates(&store.lock(), &facts, filters);
+    let shown: Vec<Value> = existing
+        .iter()
+        .enumerate()
+        .map(|(index, memory)| json!({ "id": index.to_string(), "text": memory.content }))
+        .collect();
+    let prompt = format!(
+        "Existing memories:\n{}\n\nNew facts:\n{}",
+        serde_json::to_string_pretty(&shown)?,
+        serde_json::to_string_pretty(&facts)?
+    );
+    let reply = structured::generate(backend, &prompt, DECIDE_PROMPT, &decisions_schema(), &config)?;
+    let decisions: Vec<Decision> =
+        serde_json::from_value(reply.value["memory"].clone()).context("Unexpected memory decisions")?;
+
+    let actions = plan(decisions, &existing);
+    Ok(apply(&mut store.lock(), actions, session))
+}
+
+/// Extract facts from an exchange in the background, if enabled
+///
+/// `exchange` is the (redacted) text of the turn, one "Role: text" line per
+/// message.
+pub fn spawn(state: &AppState, exchange: String) {
+    if !AppSettings::load().extraction.enabled {
+        return;
+    }
+    let scope = current_scope(state);
+    if !scope.can_write_profile() {
+        return;
+    }
+    let session = state.session.lock().clone();
+    let filters = MemoryFilters {
+        user_id: Some(session.user_id.clone()),
+        metadata: HashMap::from([("kind".to_string(), json!(FACT_KIND))]),
+        access: Some(scope),
+        ..Default::default()
+    };
+    let llm: SharedBackend = state.llm.clone();
+    let store: Arc<Mutex<MemoryStore>> = state.memory_store.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _pass = PASS.lock();
+        let mut llm = llm.blocking_lock();
+        let Some(backend) = llm.as_mut() else {
+            return;
+        };
+        match extract(backend.as_mut(), &store, &session, &filters, &exchange) {
+            Ok(summary) if summary != ExtractionSummary::default() => info!(
+                "Fact extraction: {} added, {} updated, {} deleted",
+                summary.added, summary.updated, summary.deleted
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Fact extraction failed: {:#}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_and_apply() {
+        let mut store = MemoryStore::new();
+        let session = SessionIds::new("companion");
+        let (user_id, agent_id, run_id) = session.memory_ids();
+        let metadata = HashMap::from([("kind".to_string(), json!(FACT_KIND))]);
+        let dog = store.add("User's dog is named Rex", user_id.clone(), agent_id.clone(), run_id.clone(), metadata.clone()).unwrap();
+        let city = store.add("User lives in Leeds", user_id, agent_id, run_id, metadata).unwrap();
+        let existing = vec![store.get(&dog).unwrap(), store.get(&city).unwrap()];
+
+        let decision = |id: &str, text: &str, event| Decision {
+            id: id.to_string(),
+            text: text.to_string(),
+            event,
+        };
+        let decisions = vec![
+            decision("new", "User is allergic to peanuts", FactEvent::Add),
+            decision("new", "user's dog is named rex.", FactEvent::Add),
+            decision("0", "User's dog Rex is a beagle", FactEvent::Update),
+            decision("0", "User has no dog", FactEvent::Delete),
+            decision("1", "User lives in York", FactEvent::Update),
+            decision("7", "Made up", FactEvent::Delete),
+            decision("new", "User likes tea", FactEvent::None),
+        ];
+        let actions = plan(decisions, &existing);
+        assert_eq!(
+            actions,
+            [
+                FactAction::Add("User is allergic to peanuts".to_string()),
+                FactAction::Update { memory_id: dog.clone(), text: "User's dog Rex is a beagle".to_string() },
+                FactAction::Update { memory_id: city.clone(), text: "User lives in York".to_string() },
+            ]
+        );
+
+        let summary = apply(&mut store, actions, &session);
+        assert_eq!(summary, ExtractionSummary { added: 1, updated: 2, deleted: 0 });
+        assert_eq!(store.get(&dog).unwrap().content, "User's dog Rex is a beagle");
+        let added = store.search("allergic to peanuts", None, 1).remove(0);
+        assert_eq!(added.metadata["tags"], json!([memory_policy::PROFILE_KIND]));
+        assert_eq!(store.history(&city)[0].memory.content, "User lives in Leeds");
+        let facts = MemoryFilters {
+            metadata: HashMap::from([("kind".to_string(), json!(FACT_KIND))]),
+            ..Default::default()
+        };
+        assert_eq!(store.count_filtered(&facts), 3);
+    }
+}
@@ -0,0 +1,208 @@
+// File Read Module - Let the model read a local file or folder the user allows
+//
+// A `Tool` for requests like "summarize ~/notes/todo.md". Only paths inside
+// one of the allowed folders in the tool settings can be read: the path is
+// resolved (`~` expanded, symlinks followed) before the check, so `..` or a
+// link can't reach outside them. Every read also asks the user first with a
+// native confirmation dialog naming the exact path; if they decline, or
+// there is no window to ask in, nothing is opened. A file's text is returned
+// up to `max_bytes`; a folder gives its listing. Binary files are refused.
+
+use crate::tools::{Tool, ToolContext};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Entries listed for a folder
+const MAX_ENTRIES: usize = 200;
+/// Largest `max_bytes` the settings accept
+const MAX_READ_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileReadSettings {
+    pub enabled: bool,
+    /// Folders (or single files) the tool may read from; `~` is the home folder
+    pub allowed_paths: Vec<String>,
+    /// Most of a file given to the model
+    pub max_bytes: usize,
+}
+
+impl Default for FileReadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_paths: Vec::new(),
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl FileReadSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_READ_BYTES).contains(&self.max_bytes) {
+            return Err(anyhow!("File reads are 1 to {} bytes", MAX_READ_BYTES));
+        }
+        for path in &self.allowed_paths {
+            if !expand_home(path).is_absolute() {
+                return Err(anyhow!("Allowed paths must be absolute: {}", path));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `path` with a leading `~` replaced by the home folder
+pub fn expand_home(path: &str) -> PathBuf {
+    let path = path.trim();
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(std::path::is_separator) => {
+            home.join(rest.trim_start_matches(std::path::is_separator))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The real location of `requested` if it is inside one of `allowed`
+pub fn resolve(requested: &str, allowed: &[String]) -> Result<PathBuf> {
+    let path = expand_home(requested);
+    if !path.is_absolute() {
+        return Err(anyhow!("Give the full path (or start it with ~): {}", requested));
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Can't open {}", path.display()))?;
+    let permitted = allowed
+        .iter()
+        .filter_map(|root| expand_home(root).canonicalize().ok())
+        .any(|root| path.starts_with(root));
+    if !permitted {
+        return Err(anyhow!("{} is not in an allowed folder", path.display()));
+    }
+    Ok(path)
+}
+
+/// Listing of a folder, folders first
+fn list_dir(path: &Path) -> Result<String> {
+    let mut entries: Vec<(bool, String, u64)> = std::fs::read_dir(path)
+        .with_context(|| format!("Can't list {}", path.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.is_dir(), entry.file_name().to_string_lossy().into_owned(), metadata.len()))
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase())));
+    let total = entries.len();
+    let mut listing: Vec<String> = entries
+        .into_iter()
+        .take(MAX_ENTRIES)
+        .map(|(is_dir, name, size)| if is_dir { format!("{}/", name) } else { format!("{} ({} bytes)", name, size) })
+        .collect();
+    if total > MAX_ENTRIES {
+        listing.push(format!("... and {} more", total - MAX_ENTRIES));
+    }
+    Ok(format!("Folder {} ({} entries):\n{}", path.display(), total, listing.join("\n")))
+}
+
+/// Text of a file, up to `max_bytes`
+fn read_text(path: &Path, max_bytes: usize) -> Result<String> {
+    let file = std::fs::File::open(path).with_context(|| format!("Can't open {}", path.display()))?;
+    let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let mut bytes = Vec::new();
+    file.take(max_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.contains(&0) {
+        return Err(anyhow!("{} is not a text file", path.display()));
+    }
+    let mut text = String::from_utf8_lossy(&bytes).into_owned();
+    if size > max_bytes as u64 {
+        text.push_str(&format!("\n[... truncated, {} of {} bytes shown]", max_bytes, size));
+    }
+    Ok(format!("File {}:\n{}", path.display(), text))
+}
+
+/// The `read_file` tool
+pub struct FileRead {
+    settings: FileReadSettings,
+}
+
+impl FileRead {
+    pub fn new(settings: FileReadSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Tool for FileRead {
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a local text file, or list a folder, when the user asks about one by path"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "path": { "type": "string", "description": "Full path, may start with ~" } },
+            "required": ["path"]
+        })
+    }
+
+    fn run(&self, arguments: &Value, context: &ToolContext) -> Result<String> {
+        let requested = arguments["path"]
+            .as_str()
+            .filter(|path| !path.trim().is_empty())
+            .ok_or_else(|| anyhow!("read_file needs a path"))?;
+        let path = resolve(requested, &self.settings.allowed_paths)?;
+        let window = context
+            .window
+            .as_ref()
+            .ok_or_else(|| anyhow!("No window to confirm reading {}", path.display()))?;
+        let approved = tauri::api::dialog::blocking::ask(
+            Some(window),
+            "Allow file access?",
+            format!("Aura wants to read:\n{}\n\nAllow this once?", path.display()),
+        );
+        if !approved {
+            info!("Declined reading {}", path.display());
+            return Err(anyhow!("The user declined access to {}", path.display()));
+        }
+        info!("Reading {}", path.display());
+        if path.is_dir() {
+            list_dir(&path)
+        } else {
+            read_text(&path, self.settings.max_bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_stays_in_allowed_folders() {
+        let root = std::env::temp_dir().join(format!("auranexus-file-read-{}", uuid::Uuid::new_v4()));
+        let notes = root.join("notes");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(notes.join("todo.md"), "- water plants").unwrap();
+        std::fs::write(root.join("secret.txt"), "no").unwrap();
+        let allowed = vec![notes.to_string_lossy().into_owned()];
+
+        let todo = resolve(&notes.join("todo.md").to_string_lossy(), &allowed).unwrap();
+        assert!(read_text(&todo, 1024).unwrap().ends_with("- water plants"));
+        assert!(read_text(&todo, 5).unwrap().contains("truncated, 5 of 14 bytes"));
+        assert!(list_dir(&notes).unwrap().contains("todo.md (14 bytes)"));
+
+        let escape = notes.join("..").join("secret.txt");
+        assert!(resolve(&escape.to_string_lossy(), &allowed).is_err());
+        assert!(resolve("notes/todo.md", &allowed).is_err());
+        assert!(resolve(&notes.join("missing.md").to_string_lossy(), &allowed).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
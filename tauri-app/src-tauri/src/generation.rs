@@ -0,0 +1,236 @@
+// Generation Tracking Module - In-flight generation state
+//
+// Tracks the single generation that is currently streaming so a newer user
+// message can "barge in": the running decode is cancelled and whatever text
+// it produced so far is handed to the new turn. `cancel` stops it without a
+// new message; the generation then records its own partial turn.
+
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Shared flag asking a running decode to stop at the next token
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A generation that is currently in flight
+pub struct ActiveGeneration {
+    id: String,
+    user_message: String,
+    cancelled: CancellationToken,
+    /// Whether a cancelled generation's partial text goes into history
+    keep_partial: AtomicBool,
+    partial: Mutex<String>,
+}
+
+impl ActiveGeneration {
+    fn new(user_message: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            user_message,
+            cancelled: CancellationToken::new(),
+            keep_partial: AtomicBool::new(true),
+            partial: Mutex::new(String::new()),
+        }
+    }
+
+    /// Unique id of this generation
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The user message this generation is answering
+    pub fn user_message(&self) -> &str {
+        &self.user_message
+    }
+
+    /// Request that the generation stops at the next token boundary
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// Token to hand to a backend's decode loop
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Whether partial text should be recorded if this generation is cancelled
+    pub fn keeps_partial(&self) -> bool {
+        self.keep_partial.load(Ordering::SeqCst)
+    }
+
+    /// Append a streamed token to the partial output
+    pub fn push_token(&self, token: &str) {
+        self.partial.lock().push_str(token);
+    }
+
+    /// Snapshot of the text generated so far
+    pub fn partial_text(&self) -> String {
+        self.partial.lock().clone()
+    }
+}
+
+/// A turn that was cut short by a newer message
+#[derive(Debug, Clone)]
+pub struct InterruptedTurn {
+    pub generation_id: String,
+    pub user_message: String,
+    pub partial: String,
+}
+
+/// Owner of the current in-flight generation
+///
+/// Exactly one party records a turn in history: either the generation itself
+/// when `finish` reports it still owns the slot, or the caller of `interrupt`.
+#[derive(Default)]
+pub struct GenerationTracker {
+    active: Mutex<Option<Arc<ActiveGeneration>>>,
+}
+
+impl GenerationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new generation, cancelling any leftover one
+    pub fn begin(&self, user_message: impl Into<String>) -> Arc<ActiveGeneration> {
+        let handle = Arc::new(ActiveGeneration::new(user_message.into()));
+        let previous = self.active.lock().replace(handle.clone());
+        if let Some(previous) = previous {
+            previous.cancel();
+        }
+        handle
+    }
+
+    /// Cancel the in-flight generation and take ownership of its partial turn
+    ///
+    /// Returns `None` if nothing is generating.
+    pub fn interrupt(&self) -> Option<InterruptedTurn> {
+        let handle = self.active.lock().take()?;
+        handle.cancel();
+
+        Some(InterruptedTurn {
+            generation_id: handle.id.clone(),
+            user_message: handle.user_message.clone(),
+            partial: handle.partial_text().trim().to_string(),
+        })
+    }
+
+    /// Cancel the in-flight generation, leaving it to record its own turn
+    ///
+    /// Returns the generation's id, or `None` if nothing is generating.
+    pub fn cancel(&self, keep_partial: bool) -> Option<String> {
+        let active = self.active.lock();
+        let handle = active.as_ref()?;
+        handle.keep_partial.store(keep_partial, Ordering::SeqCst);
+        handle.cancel();
+        Some(handle.id.clone())
+    }
+
+    /// Mark a generation as finished
+    ///
+    /// Returns `true` if the generation still owned the slot (and should record
+    /// its own turn), `false` if it was interrupted in the meantime.
+    pub fn finish(&self, handle: &Arc<ActiveGeneration>) -> bool {
+        let mut active = self.active.lock();
+        match active.as_ref() {
+            Some(current) if Arc::ptr_eq(current, handle) => {
+                *active = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a generation is currently streaming
+    pub fn is_active(&self) -> bool {
+        self.active.lock().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_without_interrupt() {
+        let tracker = GenerationTracker::new();
+        let handle = tracker.begin("Hello");
+        handle.push_token("Hi ");
+        handle.push_token("there");
+
+        assert!(tracker.is_active());
+        assert!(tracker.finish(&handle));
+        assert!(!tracker.is_active());
+        assert!(!handle.is_cancelled());
+    }
+
+    #[test]
+    fn test_interrupt_takes_partial() {
+        let tracker = GenerationTracker::new();
+        let handle = tracker.begin("Tell me a story");
+        handle.push_token("Once upon ");
+        handle.push_token("a time ");
+
+        let interrupted = tracker.interrupt().unwrap();
+        assert_eq!(interrupted.user_message, "Tell me a story");
+        assert_eq!(interrupted.partial, "Once upon a time");
+        assert!(handle.is_cancelled());
+
+        // The interrupted generation no longer owns its turn
+        assert!(!tracker.finish(&handle));
+    }
+
+    #[test]
+    fn test_interrupt_when_idle() {
+        let tracker = GenerationTracker::new();
+        assert!(tracker.interrupt().is_none());
+    }
+
+    #[test]
+    fn test_begin_cancels_leftover() {
+        let tracker = GenerationTracker::new();
+        let first = tracker.begin("first");
+        let second = tracker.begin("second");
+
+        assert!(first.is_cancelled());
+        assert!(!tracker.finish(&first));
+        assert!(tracker.finish(&second));
+    }
+
+    #[test]
+    fn test_cancel_keeps_ownership() {
+        let tracker = GenerationTracker::new();
+        let handle = tracker.begin("Tell me a story");
+        handle.push_token("Once upon");
+
+        assert_eq!(tracker.cancel(false).as_deref(), Some(handle.id()));
+        assert!(handle.is_cancelled());
+        assert!(handle.cancellation_token().is_cancelled());
+        assert!(!handle.keeps_partial());
+
+        // Unlike interrupt, the generation still records its own turn
+        assert!(tracker.finish(&handle));
+        assert!(tracker.cancel(true).is_none());
+    }
+}
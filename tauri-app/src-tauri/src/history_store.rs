@@ -0,0 +1,584 @@
+// History Store Module - Persists conversation history to disk
+//
+// The finished history lives in `history.json`. While a response is
+// streaming, its partial text is journaled to `inflight/<generation_id>.json`
+// so a crash mid-response loses at most the last flush interval. On the next
+// launch leftover journals are folded back into the history marked incomplete.
+// Switching modes archives the finished session to `archive/<run_id>.json`.
+// Archives untouched for `cold_after_months` are zstd-compressed into
+// `archive/cold/<run_id>.json.zst`: left out of the default listing but still
+// loadable (their memories stay in the search index) until unarchived.
+
+use crate::encryption;
+use crate::session::SessionIds;
+use crate::{ConversationEntry, EntryStatus};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+/// How often a streaming response is flushed to its journal
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// zstd level for cold archives (written once, rarely read)
+const COLD_COMPRESSION_LEVEL: i32 = 19;
+
+const DAYS_PER_MONTH: u64 = 30;
+
+/// Journal record for a response that is still being generated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InflightRecord {
+    pub generation_id: String,
+    pub user_message: String,
+    pub partial: String,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+/// A session archived when the user switched modes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSession {
+    pub session: SessionIds,
+    pub archived_at: String,
+    pub entries: Vec<ConversationEntry>,
+}
+
+/// Listing entry for an archived session
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivedSessionSummary {
+    pub run_id: String,
+    pub agent_id: String,
+    pub archived_at: String,
+    pub message_count: usize,
+    /// Start of the first user message
+    pub preview: String,
+    /// Compressed into cold storage
+    pub cold: bool,
+}
+
+/// When archived sessions move to cold storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchivalSettings {
+    /// Months without changes before an archive is compressed (0 = never)
+    pub cold_after_months: u32,
+}
+
+impl Default for ArchivalSettings {
+    fn default() -> Self {
+        Self { cold_after_months: 3 }
+    }
+}
+
+impl ArchivalSettings {
+    const FILE: &'static str = "archival.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    /// Age at which an archive goes cold, if ever
+    pub fn cold_after(&self) -> Option<Duration> {
+        (self.cold_after_months > 0)
+            .then(|| Duration::from_secs(self.cold_after_months as u64 * DAYS_PER_MONTH * 24 * 60 * 60))
+    }
+}
+
+/// File-backed conversation history
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+impl HistoryStore {
+    /// Open (creating if needed) a history store rooted at `dir`
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        for subdir in ["inflight", "archive", "archive/cold"] {
+            std::fs::create_dir_all(dir.join(subdir))
+                .with_context(|| format!("Failed to create history directory {}", dir.display()))?;
+        }
+        Ok(Self { dir })
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.dir.join("history.json")
+    }
+
+    fn session_path(&self) -> PathBuf {
+        self.dir.join("session.json")
+    }
+
+    fn inflight_path(&self, generation_id: &str) -> PathBuf {
+        self.dir.join("inflight").join(format!("{}.json", generation_id))
+    }
+
+    /// Load the saved history (empty if none has been saved yet)
+    pub fn load(&self) -> Result<Vec<ConversationEntry>> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let json = encryption::read_to_string(&path).context("Failed to read history")?;
+        serde_json::from_str(&json).context("Failed to parse history")
+    }
+
+    /// Save the full history, replacing the previous file atomically
+    pub fn save(&self, history: &[ConversationEntry]) -> Result<()> {
+        let json = serde_json::to_string_pretty(history)?;
+        encryption::write(&self.history_path(), json.as_bytes())
+    }
+
+    /// Load the ids of the session the saved history belongs to
+    pub fn load_session(&self) -> Option<SessionIds> {
+        encryption::read_to_string(&self.session_path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+    }
+
+    /// Save the ids of the current session
+    pub fn save_session(&self, session: &SessionIds) -> Result<()> {
+        let json = serde_json::to_string_pretty(session)?;
+        encryption::write(&self.session_path(), json.as_bytes())
+    }
+
+    fn archive_path(&self, run_id: &str) -> Result<PathBuf> {
+        // Run ids are UUIDs; anything else could escape the archive directory
+        if run_id.is_empty() || !run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(anyhow::anyhow!("Invalid run id: {}", run_id));
+        }
+        Ok(self.dir.join("archive").join(format!("{}.json", run_id)))
+    }
+
+    fn cold_path(&self, run_id: &str) -> Result<PathBuf> {
+        // Validates the run id
+        self.archive_path(run_id)?;
+        Ok(self.dir.join("archive").join("cold").join(format!("{}.json.zst", run_id)))
+    }
+
+    /// Archive a finished session (no-op for an empty history)
+    pub fn archive(&self, session: &SessionIds, history: &[ConversationEntry]) -> Result<()> {
+        if history.is_empty() {
+            return Ok(());
+        }
+
+        let archived = ArchivedSession {
+            session: session.clone(),
+            archived_at: crate::clock::timestamp(),
+            entries: history.to_vec(),
+        };
+        let json = serde_json::to_string_pretty(&archived)?;
+        encryption::write(&self.archive_path(&session.run_id)?, json.as_bytes())
+    }
+
+    /// Load an archived session by run id (cold or not)
+    pub fn load_archived(&self, run_id: &str) -> Result<ArchivedSession> {
+        let path = self.archive_path(run_id)?;
+        let json = match encryption::read_to_string(&path) {
+            Ok(json) => json,
+            Err(_) => read_cold(&self.cold_path(run_id)?)
+                .with_context(|| format!("No archived session {}", run_id))?,
+        };
+        serde_json::from_str(&json).context("Failed to parse archived session")
+    }
+
+    /// Summaries of archived sessions, newest first; cold ones only if asked
+    pub fn list_archived(&self, include_cold: bool) -> Vec<ArchivedSessionSummary> {
+        let archive = self.dir.join("archive");
+        let hot = list_files(&archive, "json").filter_map(|path| encryption::read_to_string(&path).ok());
+        let mut summaries: Vec<ArchivedSessionSummary> = hot
+            .filter_map(|json| serde_json::from_str::<ArchivedSession>(&json).ok())
+            .map(|archived| summarize(archived, false))
+            .collect();
+
+        if include_cold {
+            summaries.extend(
+                list_files(&archive.join("cold"), "zst")
+                    .filter_map(|path| read_cold(&path).ok())
+                    .filter_map(|json| serde_json::from_str::<ArchivedSession>(&json).ok())
+                    .map(|archived| summarize(archived, true)),
+            );
+        }
+
+        summaries.sort_by(|a, b| b.archived_at.cmp(&a.archived_at));
+        summaries
+    }
+
+    /// Compress archives not modified for `max_age` into cold storage
+    ///
+    /// Returns the number of sessions moved.
+    pub fn compress_stale(&self, max_age: Duration) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut moved = 0;
+        for path in list_files(&self.dir.join("archive"), "json").collect::<Vec<_>>() {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified());
+            let stale = modified
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age >= max_age);
+            let Some(run_id) = path.file_stem().and_then(|stem| stem.to_str()).filter(|_| stale) else {
+                continue;
+            };
+
+            let json = encryption::read(&path)?;
+            let compressed = zstd::encode_all(json.as_slice(), COLD_COMPRESSION_LEVEL)
+                .context("Failed to compress archived session")?;
+            encryption::write(&self.cold_path(run_id)?, &compressed)?;
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    /// Bring a cold session back into the regular archive
+    pub fn unarchive(&self, run_id: &str) -> Result<ArchivedSession> {
+        let cold = self.cold_path(run_id)?;
+        let json = read_cold(&cold).with_context(|| format!("No cold session {}", run_id))?;
+        let archived: ArchivedSession =
+            serde_json::from_str(&json).context("Failed to parse archived session")?;
+
+        encryption::write(&self.archive_path(run_id)?, json.as_bytes())?;
+        std::fs::remove_file(&cold)
+            .with_context(|| format!("Failed to remove {}", cold.display()))?;
+        Ok(archived)
+    }
+
+    /// Remove an archived session (cold or not), returning what was removed
+    pub fn remove_archived(&self, run_id: &str) -> Result<ArchivedSession> {
+        let archived = self.load_archived(run_id)?;
+        for path in [self.archive_path(run_id)?, self.cold_path(run_id)?] {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(archived)
+    }
+
+    /// Remove the archived sessions (cold or not) of `user_id`, returning
+    /// their run ids
+    pub fn remove_user_archives(&self, user_id: &str) -> Result<Vec<String>> {
+        let mut removed = Vec::new();
+        for summary in self.list_archived(true) {
+            if self.load_archived(&summary.run_id)?.session.user_id == user_id {
+                self.remove_archived(&summary.run_id)?;
+                removed.push(summary.run_id);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Put back a session taken out with `remove_archived`
+    pub fn restore_archived(&self, archived: &ArchivedSession) -> Result<()> {
+        let json = serde_json::to_string_pretty(archived)?;
+        encryption::write(&self.archive_path(&archived.session.run_id)?, json.as_bytes())
+    }
+
+    /// Write (or overwrite) the journal for a streaming response
+    pub fn write_inflight(&self, record: &InflightRecord) -> Result<()> {
+        let json = serde_json::to_string(record)?;
+        encryption::write(&self.inflight_path(&record.generation_id), json.as_bytes())
+    }
+
+    /// Remove the journal once the response has been recorded in history
+    pub fn clear_inflight(&self, generation_id: &str) {
+        let path = self.inflight_path(generation_id);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to remove in-flight journal {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Take all journals left behind by a crash, oldest first
+    ///
+    /// The journal files are removed; the caller is responsible for folding
+    /// the records into history.
+    pub fn recover_inflight(&self) -> Vec<InflightRecord> {
+        let mut records = Vec::new();
+
+        let Ok(entries) = std::fs::read_dir(self.dir.join("inflight")) else {
+            return records;
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            match encryption::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<InflightRecord>(&json).ok())
+            {
+                Some(record) => records.push(record),
+                None => warn!("Discarding unreadable in-flight journal {}", path.display()),
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+
+        records.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        records
+    }
+
+    /// Fold recovered journals into `history` as incomplete turns
+    ///
+    /// Returns the number of turns recovered.
+    pub fn restore_incomplete(&self, history: &mut Vec<ConversationEntry>) -> usize {
+        let records = self.recover_inflight();
+        for record in &records {
+            history.push(ConversationEntry {
+                role: "user".to_string(),
+                content: record.user_message.clone(),
+                timestamp: record.started_at.clone(),
+                quality_score: None,
+                status: EntryStatus::Complete,
+                tool_calls: Vec::new(),
+                parts: Vec::new(),
+            });
+            if !record.partial.trim().is_empty() {
+                history.push(ConversationEntry {
+                    role: "assistant".to_string(),
+                    content: record.partial.trim().to_string(),
+                    timestamp: record.updated_at.clone(),
+                    quality_score: None,
+                    status: EntryStatus::Incomplete,
+                    tool_calls: Vec::new(),
+                    parts: Vec::new(),
+                });
+            }
+        }
+        records.len()
+    }
+}
+
+/// Throttled journal writer for one streaming response
+pub struct InflightWriter<'a> {
+    store: &'a HistoryStore,
+    record: InflightRecord,
+    last_flush: Option<Instant>,
+}
+
+impl<'a> InflightWriter<'a> {
+    pub fn new(store: &'a HistoryStore, generation_id: &str, user_message: &str) -> Self {
+        let now = crate::clock::timestamp();
+        Self {
+            store,
+            record: InflightRecord {
+                generation_id: generation_id.to_string(),
+                user_message: user_message.to_string(),
+                partial: String::new(),
+                started_at: now.clone(),
+                updated_at: now,
+            },
+            last_flush: None,
+        }
+    }
+
+    /// Append a streamed token, flushing if the interval has elapsed
+    pub fn push_token(&mut self, token: &str) {
+        self.record.partial.push_str(token);
+
+        let due = self
+            .last_flush
+            .map(|at| at.elapsed() >= FLUSH_INTERVAL)
+            .unwrap_or(true);
+        if due {
+            self.flush();
+        }
+    }
+
+    /// Write the partial text to the journal immediately
+    pub fn flush(&mut self) {
+        self.record.updated_at = crate::clock::timestamp();
+        self.last_flush = Some(Instant::now());
+
+        if let Err(e) = self.store.write_inflight(&self.record) {
+            warn!("Failed to journal partial response: {}", e);
+        }
+    }
+}
+
+/// Move stale archives to cold storage in the background, per the settings
+pub fn spawn_cold_storage(store: Arc<HistoryStore>) {
+    let Some(max_age) = ArchivalSettings::load().cold_after() else {
+        return;
+    };
+    std::thread::spawn(move || match store.compress_stale(max_age) {
+        Ok(0) => {}
+        Ok(moved) => info!("Moved {} archived session(s) to cold storage", moved),
+        Err(e) => warn!("Cold storage pass failed: {}", e),
+    });
+}
+
+fn summarize(archived: ArchivedSession, cold: bool) -> ArchivedSessionSummary {
+    ArchivedSessionSummary {
+        preview: archived
+            .entries
+            .iter()
+            .find(|entry| entry.role == "user")
+            .map(|entry| entry.content.chars().take(80).collect())
+            .unwrap_or_default(),
+        run_id: archived.session.run_id,
+        agent_id: archived.session.agent_id,
+        archived_at: archived.archived_at,
+        message_count: archived.entries.len(),
+        cold,
+    }
+}
+
+/// Files in `dir` with the given extension
+fn list_files(dir: &Path, extension: &'static str) -> impl Iterator<Item = PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(move |path| path.extension().and_then(|ext| ext.to_str()) == Some(extension))
+}
+
+fn read_cold(path: &Path) -> Result<String> {
+    let compressed = encryption::read(path)?;
+    let json = zstd::decode_all(compressed.as_slice()).context("Failed to decompress archive")?;
+    String::from_utf8(json).context("Archive is not valid UTF-8")
+}
+
+/// Write a file via a temporary sibling and rename, so readers never see a
+/// half-written file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> (HistoryStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("auranexus_history_{}", uuid::Uuid::new_v4()));
+        (HistoryStore::open(&dir).unwrap(), dir)
+    }
+
+    fn entry(role: &str, content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: crate::clock::timestamp(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (store, dir) = temp_store();
+        assert!(store.load().unwrap().is_empty());
+
+        store.save(&[entry("user", "Hi"), entry("assistant", "Hello!")]).unwrap();
+        let history = store.load().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].content, "Hello!");
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_recover_incomplete_response() {
+        let (store, dir) = temp_store();
+
+        // Simulate a crash mid-stream: journal written, never cleared
+        let mut writer = InflightWriter::new(&store, "gen-1", "Tell me a story");
+        writer.push_token("Once upon ");
+        writer.push_token("a time");
+        writer.flush();
+
+        let mut history = vec![entry("user", "Hi"), entry("assistant", "Hello!")];
+        assert_eq!(store.restore_incomplete(&mut history), 1);
+
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[2].content, "Tell me a story");
+        assert_eq!(history[3].content, "Once upon a time");
+        assert_eq!(history[3].status, EntryStatus::Incomplete);
+
+        // Journals are consumed by recovery
+        assert!(store.recover_inflight().is_empty());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_cleared_journal_is_not_recovered() {
+        let (store, dir) = temp_store();
+
+        let mut writer = InflightWriter::new(&store, "gen-2", "Question");
+        writer.push_token("Partial answer");
+        store.clear_inflight("gen-2");
+
+        assert!(store.recover_inflight().is_empty());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_archive_and_list() {
+        let (store, dir) = temp_store();
+        let session = SessionIds::new("companion");
+
+        // Nothing to archive
+        store.archive(&session, &[]).unwrap();
+        assert!(store.list_archived(false).is_empty());
+
+        store.archive(&session, &[entry("user", "Plan my week"), entry("assistant", "Sure")]).unwrap();
+        let summaries = store.list_archived(false);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].run_id, session.run_id);
+        assert_eq!(summaries[0].message_count, 2);
+        assert_eq!(summaries[0].preview, "Plan my week");
+
+        let archived = store.load_archived(&session.run_id).unwrap();
+        assert_eq!(archived.entries[1].content, "Sure");
+        assert!(store.load_archived("../history").is_err());
+
+        let mut other = SessionIds::new("companion");
+        other.user_id = "someone_else".to_string();
+        store.archive(&other, &[entry("user", "Not mine")]).unwrap();
+        assert_eq!(store.remove_user_archives(&session.user_id).unwrap(), [session.run_id.clone()]);
+        assert_eq!(store.list_archived(true)[0].run_id, other.run_id);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_cold_storage_round_trip() {
+        let (store, dir) = temp_store();
+        let session = SessionIds::new("companion");
+        store.archive(&session, &[entry("user", "Old chat"), entry("assistant", "Indeed")]).unwrap();
+
+        // Nothing is old enough yet
+        assert_eq!(store.compress_stale(Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(store.compress_stale(Duration::ZERO).unwrap(), 1);
+
+        // Hidden from the default listing, still loadable
+        assert!(store.list_archived(false).is_empty());
+        let cold = store.list_archived(true);
+        assert_eq!(cold.len(), 1);
+        assert!(cold[0].cold);
+        assert_eq!(store.load_archived(&session.run_id).unwrap().entries[0].content, "Old chat");
+
+        let restored = store.unarchive(&session.run_id).unwrap();
+        assert_eq!(restored.entries.len(), 2);
+        assert!(!store.list_archived(false)[0].cold);
+        assert!(store.unarchive(&session.run_id).is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,491 @@
+// HTML Export Module - Standalone, shareable conversation transcripts
+//
+// The export is a single HTML file with inline CSS. Images in the app's
+// attachments folder referenced as `![alt](path)` are embedded as data URIs
+// so the file still renders after it has been moved or shared; other local
+// paths are left as they are, so an export never carries files from
+// elsewhere on disk. Links are listed again under "Sources".
+
+use crate::memory_store::{MemoryFilters, MemoryItem};
+use crate::tool_calls::ToolInvocation;
+use crate::{paths, AppState, ConversationEntry, EntryStatus};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Images larger than this are linked instead of embedded
+const MAX_EMBEDDED_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+const STYLE: &str = r#"
+:root { --bg: #f6f5f2; --card: #ffffff; --text: #1f1f24; --muted: #6b6b76; --user: #e8effd; --accent: #6b5bd2; }
+@media (prefers-color-scheme: dark) {
+  :root { --bg: #16161b; --card: #202028; --text: #e9e9ef; --muted: #9a9aa8; --user: #26304a; --accent: #a99bff; }
+}
+body { margin: 0; background: var(--bg); color: var(--text); font: 16px/1.55 system-ui, -apple-system, "Segoe UI", sans-serif; }
+main { max-width: 760px; margin: 0 auto; padding: 32px 20px 64px; }
+header h1 { margin: 0 0 4px; font-size: 1.5rem; }
+header p { margin: 0 0 24px; color: var(--muted); font-size: 0.9rem; }
+.message { background: var(--card); border-radius: 12px; padding: 12px 16px; margin: 12px 0; box-shadow: 0 1px 2px rgba(0,0,0,.08); }
+.message.user { background: var(--user); }
+.message.system { background: transparent; box-shadow: none; border: 1px dashed var(--muted); color: var(--muted); }
+.meta { display: flex; gap: 8px; align-items: baseline; font-size: 0.8rem; color: var(--muted); margin-bottom: 4px; }
+.meta strong { color: var(--accent); font-size: 0.9rem; }
+.badge { border: 1px solid var(--muted); border-radius: 6px; padding: 0 6px; }
+.tool { font-size: 0.85rem; color: var(--muted); margin: 4px 0; }
+.tool summary { cursor: pointer; }
+.content p { margin: 6px 0; }
+.content img { max-width: 100%; border-radius: 8px; }
+pre { background: rgba(127,127,127,.12); padding: 10px 12px; border-radius: 8px; overflow-x: auto; }
+a { color: var(--accent); }
+footer { margin-top: 32px; font-size: 0.85rem; color: var(--muted); }
+"#;
+
+/// Transcript of one session, ready to render
+pub struct Transcript {
+    pub session_id: String,
+    /// Mode/persona the session ran in (e.g. "companion")
+    pub mode: String,
+    pub entries: Vec<ConversationEntry>,
+}
+
+/// Collect the transcript for `session_id`
+///
+/// The current session comes from the live history and archived sessions
+/// from the archive; others are rebuilt from the messages recorded in the
+/// memory store.
+pub fn load_transcript(state: &AppState, session_id: &str) -> Result<Transcript> {
+    let session = state.session.lock().clone();
+    if session.run_id == session_id {
+        return Ok(Transcript {
+            session_id: session.run_id,
+            mode: session.agent_id,
+            entries: state.conversation_history.lock().clone(),
+        });
+    }
+    if let Ok(archived) = state.history_store.load_archived(session_id) {
+        return Ok(Transcript {
+            session_id: archived.session.run_id,
+            mode: archived.session.agent_id,
+            entries: archived.entries,
+        });
+    }
+
+    let mut filters = MemoryFilters {
+        run_id: Some(session_id.to_string()),
+        ..Default::default()
+    };
+    filters
+        .metadata
+        .insert("kind".to_string(), serde_json::json!("message"));
+
+    let store = state.memory_store.lock();
+    let mut messages = store.get_all(&filters, usize::MAX);
+    if messages.is_empty() {
+        return Err(anyhow!("No conversation found for session {}", session_id));
+    }
+
+    messages.sort_by_key(|item| (metadata_str(item, "timestamp"), item.created_at));
+
+    let mode = messages[0].agent_id.clone().unwrap_or_default();
+    let entries = messages
+        .iter()
+        .map(|item| ConversationEntry {
+            role: metadata_str(item, "role"),
+            content: item.content.clone(),
+            timestamp: metadata_str(item, "timestamp"),
+            quality_score: None,
+            status: item
+                .metadata
+                .get("status")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default(),
+            tool_calls: item
+                .metadata
+                .get("tool_calls")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default(),
+            parts: Vec::new(),
+        })
+        .collect();
+
+    Ok(Transcript {
+        session_id: session_id.to_string(),
+        mode,
+        entries,
+    })
+}
+
+fn metadata_str(item: &MemoryItem, key: &str) -> String {
+    item.metadata
+        .get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Render a transcript as a standalone HTML document
+pub fn render_html(transcript: &Transcript) -> String {
+    let renderer = ContentRenderer::new();
+    let mode = display_mode(&transcript.mode);
+    let mut sources: Vec<(String, String)> = Vec::new();
+
+    let mut body = String::new();
+    for entry in &transcript.entries {
+        let (class, speaker) = match entry.role.as_str() {
+            "user" => ("user", "You".to_string()),
+            "system" => ("system", "Summary".to_string()),
+            _ => ("assistant", mode.clone()),
+        };
+        let badge = match entry.status {
+            EntryStatus::Complete => "",
+            EntryStatus::Interrupted => r#"<span class="badge">interrupted</span>"#,
+            EntryStatus::Incomplete => r#"<span class="badge">incomplete</span>"#,
+        };
+
+        body.push_str(&format!(
+            "<section class=\"message {}\"><div class=\"meta\"><strong>{}</strong><time datetime=\"{}\">{}</time>{}</div>{}<div class=\"content\">{}</div></section>\n",
+            class,
+            escape_html(&speaker),
+            escape_html(&entry.timestamp),
+            escape_html(&display_timestamp(&entry.timestamp)),
+            badge,
+            render_tool_calls(&entry.tool_calls),
+            renderer.render(&entry.content, &mut sources),
+        ));
+    }
+
+    let mut footer = String::new();
+    if !sources.is_empty() {
+        footer.push_str("<h2>Sources</h2><ol>");
+        for (text, url) in &sources {
+            footer.push_str(&format!("<li><a href=\"{}\">{}</a></li>", url, text));
+        }
+        footer.push_str("</ol>");
+    }
+    footer.push_str(&format!(
+        "<p>Exported from AuraNexus on {}</p>",
+        crate::clock::local_now().format("%Y-%m-%d %H:%M")
+    ));
+
+    let started = transcript
+        .entries
+        .first()
+        .map(|e| display_timestamp(&e.timestamp))
+        .unwrap_or_default();
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>AuraNexus – {mode} conversation</title>\n<style>{style}</style>\n</head>\n<body>\n<main>\n<header><h1>{mode} conversation</h1><p>{count} messages · started {started} · session {session}</p></header>\n{body}<footer>{footer}</footer>\n</main>\n</body>\n</html>\n",
+        mode = escape_html(&mode),
+        style = STYLE,
+        count = transcript.entries.len(),
+        started = escape_html(&started),
+        session = escape_html(&transcript.session_id),
+        body = body,
+        footer = footer,
+    )
+}
+
+/// Collapsible record of each tool the assistant ran before replying
+fn render_tool_calls(calls: &[ToolInvocation]) -> String {
+    calls
+        .iter()
+        .map(|call| {
+            let result = match &call.error {
+                Some(error) => format!("Error: {}", error),
+                None if call.truncated => {
+                    format!("{}\n[{} characters in total]", call.output, call.output_chars)
+                }
+                None => call.output.clone(),
+            };
+            format!(
+                "<details class=\"tool\"><summary>🔧 {} · {} ms</summary><pre><code>{}</code></pre><pre><code>{}</code></pre></details>",
+                escape_html(&call.tool),
+                call.duration_ms,
+                escape_html(&call.arguments.to_string()),
+                escape_html(&result),
+            )
+        })
+        .collect()
+}
+
+/// Markdown-lite renderer for message content
+struct ContentRenderer {
+    /// `![alt](src)` or `[text](http...)`
+    inline: Regex,
+}
+
+impl ContentRenderer {
+    fn new() -> Self {
+        Self {
+            inline: Regex::new(r"!\[([^\]]*)\]\(([^)\s]+)\)|\[([^\]]+)\]\((https?://[^)\s]+)\)")
+                .unwrap(),
+        }
+    }
+
+    /// Render code fences, paragraphs, images and links
+    fn render(&self, content: &str, sources: &mut Vec<(String, String)>) -> String {
+        let mut html = String::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut code: Option<Vec<&str>> = None;
+
+        for line in content.lines() {
+            if line.trim_start().starts_with("```") {
+                match code.take() {
+                    Some(lines) => {
+                        html.push_str(&format!(
+                            "<pre><code>{}</code></pre>",
+                            escape_html(&lines.join("\n"))
+                        ));
+                    }
+                    None => {
+                        self.flush_paragraph(&mut paragraph, &mut html, sources);
+                        code = Some(Vec::new());
+                    }
+                }
+                continue;
+            }
+
+            match code.as_mut() {
+                Some(lines) => lines.push(line),
+                None if line.trim().is_empty() => {
+                    self.flush_paragraph(&mut paragraph, &mut html, sources)
+                }
+                None => paragraph.push(line),
+            }
+        }
+
+        // Unterminated fence: still show it as code
+        if let Some(lines) = code {
+            html.push_str(&format!("<pre><code>{}</code></pre>", escape_html(&lines.join("\n"))));
+        }
+        self.flush_paragraph(&mut paragraph, &mut html, sources);
+
+        html
+    }
+
+    fn flush_paragraph(
+        &self,
+        lines: &mut Vec<&str>,
+        html: &mut String,
+        sources: &mut Vec<(String, String)>,
+    ) {
+        if lines.is_empty() {
+            return;
+        }
+        let rendered: Vec<String> = lines.iter().map(|l| self.render_inline(l, sources)).collect();
+        html.push_str(&format!("<p>{}</p>", rendered.join("<br>")));
+        lines.clear();
+    }
+
+    fn render_inline(&self, text: &str, sources: &mut Vec<(String, String)>) -> String {
+        let mut html = String::new();
+        let mut last = 0;
+
+        for caps in self.inline.captures_iter(text) {
+            let whole = caps.get(0).unwrap();
+            html.push_str(&escape_html(&text[last..whole.start()]));
+            last = whole.end();
+
+            if let (Some(alt), Some(src)) = (caps.get(1), caps.get(2)) {
+                html.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">",
+                    escape_html(&image_src(src.as_str())),
+                    escape_html(alt.as_str())
+                ));
+            } else if let (Some(label), Some(url)) = (caps.get(3), caps.get(4)) {
+                let label = escape_html(label.as_str());
+                let url = escape_html(url.as_str());
+                let number = match sources.iter().position(|(_, u)| *u == url) {
+                    Some(index) => index + 1,
+                    None => {
+                        sources.push((label.clone(), url.clone()));
+                        sources.len()
+                    }
+                };
+                html.push_str(&format!(
+                    "<a href=\"{}\">{}</a><sup>[{}]</sup>",
+                    url, label, number
+                ));
+            }
+        }
+        html.push_str(&escape_html(&text[last..]));
+
+        html
+    }
+}
+
+/// Embed attached images as data URIs; leave remote/data URLs and any other
+/// path alone
+fn image_src(src: &str) -> String {
+    if src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:") {
+        return src.to_string();
+    }
+
+    let Some(path) = paths::resolve_attachment(Path::new(src.strip_prefix("file://").unwrap_or(src))) else {
+        return src.to_string();
+    };
+    let Some(mime) = image_mime(&path) else {
+        return src.to_string();
+    };
+    match std::fs::metadata(&path) {
+        Ok(meta) if meta.len() <= MAX_EMBEDDED_IMAGE_BYTES => match std::fs::read(&path) {
+            Ok(bytes) => format!("data:{};base64,{}", mime, BASE64.encode(&bytes)),
+            Err(_) => src.to_string(),
+        },
+        _ => src.to_string(),
+    }
+}
+
+fn image_mime(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn display_mode(mode: &str) -> String {
+    let mut chars = mode.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Assistant".to_string(),
+    }
+}
+
+pub fn display_timestamp(timestamp: &str) -> String {
+    crate::clock::parse(timestamp)
+        .map(|t| crate::clock::to_local(t).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Default location for an exported session, e.g. `conversation-1a2b3c4d.html`
+pub fn default_export_path(session_id: &str, extension: &str) -> PathBuf {
+    let short_id: String = session_id.chars().take(8).collect();
+    crate::paths::app_data_dir()
+        .join("exports")
+        .join(format!("conversation-{}.{}", short_id, extension))
+}
+
+/// Export a session's transcript as standalone HTML; returns the file path
+#[tauri::command]
+pub async fn export_conversation_html(
+    session_id: String,
+    path: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let transcript = load_transcript(&state, &session_id).map_err(|e| e.to_string())?;
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_export_path(&session_id, "html"));
+
+    let html = render_html(&transcript);
+    let write = || -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))
+    };
+    write().map_err(|e| e.to_string())?;
+
+    info!("Exported conversation to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(role: &str, content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: "2024-05-01T10:00:00+00:00".to_string(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_content_is_escaped() {
+        let transcript = Transcript {
+            session_id: "abc".to_string(),
+            mode: "companion".to_string(),
+            entries: vec![entry("user", "<script>alert('hi')</script>")],
+        };
+        let html = render_html(&transcript);
+
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("Companion conversation"));
+    }
+
+    #[test]
+    fn test_tool_calls_are_rendered() {
+        let mut reply = entry("assistant", "It's sunny.");
+        reply.tool_calls.push(ToolInvocation {
+            tool: "web_search".to_string(),
+            arguments: serde_json::json!({"q": "<weather>"}),
+            duration_ms: 120,
+            output: "Sunny".to_string(),
+            output_chars: 5,
+            ..Default::default()
+        });
+        let transcript = Transcript {
+            session_id: "abc".to_string(),
+            mode: "companion".to_string(),
+            entries: vec![reply],
+        };
+        let html = render_html(&transcript);
+
+        assert!(html.contains("🔧 web_search · 120 ms"));
+        assert!(html.contains("&lt;weather&gt;"));
+    }
+
+    #[test]
+    fn test_links_become_sources_and_code_is_preserved() {
+        let renderer = ContentRenderer::new();
+        let mut sources = Vec::new();
+        let html = renderer.render(
+            "See [the docs](https://example.com/a?b=1&c=2).\n\n```\nlet x = 1 < 2;\n```",
+            &mut sources,
+        );
+
+        assert!(html.contains("<a href=\"https://example.com/a?b=1&amp;c=2\">the docs</a><sup>[1]</sup>"));
+        assert!(html.contains("<pre><code>let x = 1 &lt; 2;</code></pre>"));
+        assert_eq!(sources.len(), 1);
+    }
+
+    #[test]
+    fn test_only_attachments_are_embedded() {
+        let outside = std::env::temp_dir().join(format!("auranexus-image-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&outside, b"not really a png").unwrap();
+        let src = outside.to_string_lossy().to_string();
+        assert_eq!(image_src(&src), src);
+        assert_eq!(image_src("https://example.com/a.png"), "https://example.com/a.png");
+        std::fs::remove_file(outside).unwrap();
+    }
+}
@@ -0,0 +1,531 @@
+// HTTP Backend Module - Client for the local Python LLM server (llm_server.py)
+//
+// Requests are sent with `"stream": true`. Servers that support streaming
+// answer with newline-delimited JSON (`{"token": "..."}` lines followed by a
+// `{"done": true}` line); older servers answer with a single JSON object
+// containing `response`, which is delivered as one token. The final line (or
+// the single object) may also carry `usage`, `model`, `finish_reason` and
+// `context_truncated`, which end up in `GenerationStats`. Tools the server
+// runs are reported as `{"tool_call": {...}}` lines (or a `tool_calls` array
+// on the final object) and returned in `Completion::tool_calls`.
+//
+// Every call is bounded by the limits in `BackendTimeouts`. A stalled stream
+// is abandoned with `BackendError::Timeout`; the connection is dropped so the
+// server stops decoding and the next request starts clean.
+
+use crate::tool_calls::ToolInvocation;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Default address of llm_server.py
+pub const DEFAULT_SERVER_URL: &str = "http://localhost:5555";
+
+/// How often a stalled stream re-checks for cancellation
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Errors callers may want to tell apart from generic failures
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("Timed out waiting for {operation} after {}s", .limit.as_secs_f32())]
+    Timeout {
+        operation: &'static str,
+        limit: Duration,
+    },
+}
+
+/// Whether `error` is (or wraps) a backend timeout
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<BackendError>(), Some(BackendError::Timeout { .. }))
+}
+
+/// Telemetry for one generation, shown in the message details popover
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationStats {
+    /// Which backend produced the text (e.g. "llm_server")
+    pub backend: String,
+    pub model: Option<String>,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub time_to_first_token_ms: Option<u64>,
+    pub total_ms: u64,
+    pub tokens_per_second: Option<f32>,
+    pub finish_reason: Option<String>,
+    /// Generation stopped at `max_tokens`
+    pub truncated: bool,
+    /// The server dropped older history to fit the context window
+    pub context_truncated: bool,
+}
+
+impl GenerationStats {
+    /// Pick up whatever metadata the server reported
+    fn apply_server_metadata(&mut self, event: &serde_json::Value) {
+        let usage = &event["usage"];
+        if let Some(tokens) = usage["prompt_tokens"].as_u64() {
+            self.prompt_tokens = Some(tokens as u32);
+        }
+        if let Some(tokens) = usage["completion_tokens"].as_u64() {
+            self.completion_tokens = Some(tokens as u32);
+        }
+        if let Some(model) = event["model"].as_str() {
+            self.model = Some(model.to_string());
+        }
+        if let Some(reason) = event["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+            self.truncated = reason == "length";
+        }
+        if let Some(truncated) = event["context_truncated"].as_bool() {
+            self.context_truncated = truncated;
+        }
+    }
+
+    /// Fill in timing-derived fields once generation has ended
+    pub fn finish(&mut self, started: Instant, first_token: Option<Instant>) {
+        self.total_ms = started.elapsed().as_millis() as u64;
+        self.time_to_first_token_ms = first_token.map(|at| (at - started).as_millis() as u64);
+
+        // Decode speed excludes prompt processing
+        let decode_secs = first_token.map(|at| at.elapsed().as_secs_f32()).unwrap_or(0.0);
+        self.tokens_per_second = self
+            .completion_tokens
+            .filter(|_| decode_secs > 0.0)
+            .map(|tokens| tokens as f32 / decode_secs);
+    }
+}
+
+/// Generated text plus telemetry
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub stats: GenerationStats,
+    /// Tools run while generating, in order
+    pub tool_calls: Vec<ToolInvocation>,
+}
+
+/// Tool calls listed in a server response's `tool_calls` array
+fn server_tool_calls(event: &serde_json::Value) -> Vec<ToolInvocation> {
+    event["tool_calls"]
+        .as_array()
+        .map(|calls| calls.iter().filter_map(ToolInvocation::from_server).collect())
+        .unwrap_or_default()
+}
+
+/// Per-operation time limits, in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendTimeouts {
+    pub connect_secs: u64,
+    pub health_secs: u64,
+    /// Until the first token (includes prompt processing)
+    pub first_token_secs: u64,
+    /// Longest gap between two tokens
+    pub idle_secs: u64,
+    /// Whole generation, start to finish
+    pub generate_secs: u64,
+}
+
+impl Default for BackendTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_secs: 5,
+            health_secs: 5,
+            first_token_secs: 120,
+            idle_secs: 60,
+            generate_secs: 600,
+        }
+    }
+}
+
+impl BackendTimeouts {
+    const FILE: &'static str = "backend_timeouts.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    pub(crate) fn secs(value: u64) -> Duration {
+        Duration::from_secs(value.max(1))
+    }
+}
+
+/// Blocking client for the local LLM server
+///
+/// Must be used from a blocking context (e.g. `spawn_blocking`).
+pub struct HttpBackend {
+    base_url: String,
+    client: reqwest::blocking::Client,
+    timeouts: BackendTimeouts,
+}
+
+impl HttpBackend {
+    /// Create a client for the server at `base_url` with default timeouts
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::with_timeouts(base_url, BackendTimeouts::default())
+    }
+
+    /// Create a client for the server at `base_url`
+    pub fn with_timeouts(base_url: impl Into<String>, timeouts: BackendTimeouts) -> Result<Self> {
+        // Per-request limits are applied in each call
+        let client = reqwest::blocking::Client::builder()
+            .timeout(None::<Duration>)
+            .connect_timeout(BackendTimeouts::secs(timeouts.connect_secs))
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client,
+            timeouts,
+        })
+    }
+
+    /// Create a client for the default local server using the saved timeouts
+    pub fn local() -> Result<Self> {
+        Self::with_timeouts(DEFAULT_SERVER_URL, BackendTimeouts::load())
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Check whether the server is reachable and healthy
+    pub fn health(&self) -> bool {
+        self.client
+            .get(format!("{}/health", self.base_url))
+            .timeout(BackendTimeouts::secs(self.timeouts.health_secs))
+            .send()
+            .map(|response| response.status().is_success())
+            .unwrap_or(false)
+    }
+
+    /// Generate a response, delivering text to `on_token` as it arrives
+    ///
+    /// `on_token` returns `false` to stop reading (e.g. on cancellation); the
+    /// text received up to that point is returned.
+    pub fn generate_streaming(
+        &self,
+        request_body: &serde_json::Value,
+        on_token: impl FnMut(&str) -> bool,
+    ) -> Result<String> {
+        self.generate_streaming_until(request_body, || false, on_token)
+            .map(|completion| completion.text)
+    }
+
+    /// Like `generate_streaming`, but also stops while waiting for a token
+    /// once `is_cancelled` returns true, and reports telemetry
+    pub fn generate_streaming_until(
+        &self,
+        request_body: &serde_json::Value,
+        is_cancelled: impl Fn() -> bool,
+        mut on_token: impl FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        let mut body = request_body.clone();
+        body["stream"] = serde_json::json!(true);
+
+        let total_limit = BackendTimeouts::secs(self.timeouts.generate_secs);
+        let started = Instant::now();
+        let response = self
+            .client
+            .post(format!("{}/generate", self.base_url))
+            .json(&body)
+            // Also bounds the reader thread below after we stop waiting on it
+            .timeout(total_limit)
+            .send()
+            .map_err(|e| {
+                if e.is_timeout() && !e.is_connect() {
+                    anyhow::Error::new(BackendError::Timeout {
+                        operation: "generation",
+                        limit: total_limit,
+                    })
+                } else {
+                    anyhow!("Failed to connect to LLM server: {}. Is llm_server.py running?", e)
+                }
+            })?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("LLM server returned error: {}", response.status()));
+        }
+
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.contains("ndjson"))
+            .unwrap_or(false);
+
+        if !is_stream {
+            // Non-streaming server: the whole response arrives at once
+            let result: serde_json::Value = response.json().map_err(|e| {
+                if e.is_timeout() {
+                    anyhow::Error::new(BackendError::Timeout {
+                        operation: "generation",
+                        limit: total_limit,
+                    })
+                } else {
+                    anyhow::Error::new(e).context("Failed to parse LLM response")
+                }
+            })?;
+            let text = result["response"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Missing response field in LLM output"))?
+                .to_string();
+            on_token(&text);
+
+            let mut stats = GenerationStats {
+                backend: "llm_server".to_string(),
+                ..Default::default()
+            };
+            stats.apply_server_metadata(&result);
+            stats.finish(started, None);
+            return Ok(Completion {
+                text,
+                stats,
+                tool_calls: server_tool_calls(&result),
+            });
+        }
+
+        // Read on a separate thread so a stalled stream can't block us past
+        // the deadline; it exits once the request timeout closes the body
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(response).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let first_token_limit = BackendTimeouts::secs(self.timeouts.first_token_secs);
+        let idle_limit = BackendTimeouts::secs(self.timeouts.idle_secs);
+        let mut output = String::new();
+        let mut stats = GenerationStats {
+            backend: "llm_server".to_string(),
+            ..Default::default()
+        };
+        let mut tool_calls = Vec::new();
+        let mut streamed_tokens = 0u32;
+        let mut first_token = None;
+        let mut received_token = false;
+        let mut waiting_since = Instant::now();
+        loop {
+            let (operation, wait_limit) = if received_token {
+                ("next token", idle_limit)
+            } else {
+                ("first token", first_token_limit)
+            };
+            let token_deadline = waiting_since + wait_limit;
+            let total_deadline = started + total_limit;
+
+            let now = Instant::now();
+            if now >= total_deadline {
+                return Err(BackendError::Timeout {
+                    operation: "generation",
+                    limit: total_limit,
+                }
+                .into());
+            }
+            if now >= token_deadline {
+                return Err(BackendError::Timeout {
+                    operation,
+                    limit: wait_limit,
+                }
+                .into());
+            }
+
+            let wait = token_deadline.min(total_deadline) - now;
+            let line = match lines.recv_timeout(wait.min(CANCEL_POLL_INTERVAL)) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) if is_cancelled() => break,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let line = line.map_err(|e| {
+                if e.kind() == std::io::ErrorKind::TimedOut {
+                    anyhow::Error::new(BackendError::Timeout {
+                        operation: "generation",
+                        limit: total_limit,
+                    })
+                } else {
+                    anyhow::Error::new(e).context("Failed to read LLM stream")
+                }
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let event: serde_json::Value = serde_json::from_str(&line)
+                .with_context(|| format!("Malformed stream line: {}", line))?;
+
+            if let Some(error) = event["error"].as_str() {
+                return Err(anyhow!("LLM server error: {}", error));
+            }
+            if let Some(call) = event.get("tool_call").and_then(ToolInvocation::from_server) {
+                // Tool output counts as progress for the idle timeout
+                waiting_since = Instant::now();
+                tool_calls.push(call);
+            }
+            if let Some(token) = event["token"].as_str() {
+                received_token = true;
+                waiting_since = Instant::now();
+                first_token.get_or_insert(waiting_since);
+                streamed_tokens += 1;
+                output.push_str(token);
+                if !on_token(token) {
+                    break;
+                }
+            }
+            if event["done"].as_bool().unwrap_or(false) {
+                stats.apply_server_metadata(&event);
+                tool_calls.extend(server_tool_calls(&event));
+                break;
+            }
+        }
+
+        // Each stream line is one decoded token unless the server says otherwise
+        stats.completion_tokens.get_or_insert(streamed_tokens);
+        stats.finish(started, first_token);
+
+        Ok(Completion {
+            text: output,
+            stats,
+            tool_calls,
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn get_backend_timeouts() -> Result<BackendTimeouts, String> {
+    Ok(BackendTimeouts::load())
+}
+
+#[tauri::command]
+pub async fn set_backend_timeouts(timeouts: BackendTimeouts) -> Result<(), String> {
+    timeouts.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve one streaming response that sends `body` and then stalls
+    fn stalling_server(body: &'static str) -> String {
+        serve(body, Duration::from_secs(10))
+    }
+
+    /// Serve one streaming response, holding the connection open for `stall`
+    fn serve(body: &'static str, stall: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\n\r\n{}",
+                body
+            );
+            let _ = stream.flush();
+            std::thread::sleep(stall);
+        });
+        format!("http://{}", addr)
+    }
+
+    fn quick_timeouts() -> BackendTimeouts {
+        BackendTimeouts {
+            first_token_secs: 1,
+            idle_secs: 1,
+            generate_secs: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stalled_stream_times_out() {
+        let url = stalling_server("{\"token\": \"Hello\"}\n");
+        let backend = HttpBackend::with_timeouts(url, quick_timeouts()).unwrap();
+
+        let mut received = String::new();
+        let error = backend
+            .generate_streaming(&serde_json::json!({}), |token| {
+                received.push_str(token);
+                true
+            })
+            .unwrap_err();
+
+        assert!(is_timeout(&error));
+        assert!(error.to_string().contains("next token"));
+        assert_eq!(received, "Hello");
+    }
+
+    #[test]
+    fn test_stream_reports_stats() {
+        let url = serve(
+            concat!(
+                "{\"token\": \"Hi\"}\n",
+                "{\"token\": \" there\"}\n",
+                "{\"done\": true, \"model\": \"qwen\", \"finish_reason\": \"length\", ",
+                "\"usage\": {\"prompt_tokens\": 12, \"completion_tokens\": 2}}\n",
+            ),
+            Duration::ZERO,
+        );
+        let backend = HttpBackend::with_timeouts(url, quick_timeouts()).unwrap();
+
+        let completion = backend
+            .generate_streaming_until(&serde_json::json!({}), || false, |_| true)
+            .unwrap();
+
+        assert_eq!(completion.text, "Hi there");
+        let stats = completion.stats;
+        assert_eq!(stats.model.as_deref(), Some("qwen"));
+        assert_eq!(stats.prompt_tokens, Some(12));
+        assert_eq!(stats.completion_tokens, Some(2));
+        assert!(stats.truncated);
+        assert!(!stats.context_truncated);
+        assert!(stats.time_to_first_token_ms.is_some());
+    }
+
+    #[test]
+    fn test_stream_collects_tool_calls() {
+        let url = serve(
+            concat!(
+                "{\"tool_call\": {\"tool\": \"web_search\", \"arguments\": {\"q\": \"rust\"}, ",
+                "\"duration_ms\": 40, \"output\": \"3 results\"}}\n",
+                "{\"token\": \"Found it\"}\n",
+                "{\"done\": true}\n",
+            ),
+            Duration::ZERO,
+        );
+        let backend = HttpBackend::with_timeouts(url, quick_timeouts()).unwrap();
+
+        let completion = backend
+            .generate_streaming_until(&serde_json::json!({}), || false, |_| true)
+            .unwrap();
+        assert_eq!(completion.text, "Found it");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].tool, "web_search");
+        assert_eq!(completion.tool_calls[0].duration_ms, 40);
+    }
+
+    #[test]
+    fn test_cancel_while_waiting() {
+        let url = stalling_server("");
+        let backend = HttpBackend::with_timeouts(url, quick_timeouts()).unwrap();
+
+        let started = Instant::now();
+        let completion = backend
+            .generate_streaming_until(&serde_json::json!({}), || true, |_| true)
+            .unwrap();
+
+        assert!(completion.text.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,466 @@
+// Importer Module - Bring in conversation history from ChatGPT and Claude
+//
+// Both services export a zip with a `conversations.json`; `import_conversations`
+// takes either the zip or the JSON file. ChatGPT stores each conversation as a
+// tree of message nodes (edits and regenerations branch it), so the branch
+// ending at `current_node` - the one the user last saw - is the one kept.
+// Claude stores a flat `chat_messages` list. System, tool and hidden messages
+// are dropped. Every conversation becomes an inactive AuraNexus conversation
+// with its original title and dates, and each message is added to the memory
+// store (redacted like live turns) so it is recalled from then on. The Nexus
+// Core log is fed from live turns only and is left alone.
+
+use crate::conversations::{SessionManager, StoredConversation};
+use crate::memory_store::{MemoryStore, NewMemory};
+use crate::redaction;
+use crate::session::SessionIds;
+use crate::settings::AppSettings;
+use crate::{clock, AppMode, AppState, ConversationEntry, EntryStatus};
+use anyhow::{anyhow, Context, Result};
+use chrono::{TimeZone, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+/// Where an export came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Decided from the file's contents
+    #[default]
+    Auto,
+    ChatGpt,
+    Claude,
+}
+
+impl ImportFormat {
+    fn name(self) -> &'static str {
+        match self {
+            ImportFormat::Auto => "auto",
+            ImportFormat::ChatGpt => "chatgpt",
+            ImportFormat::Claude => "claude",
+        }
+    }
+}
+
+/// One conversation read from an export
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub title: String,
+    pub created_at: Option<String>,
+    pub entries: Vec<ConversationEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptConversation {
+    #[serde(default)]
+    title: Option<String>,
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, ChatGptNode>,
+    current_node: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptNode {
+    message: Option<ChatGptMessage>,
+    parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    content: ChatGptContent,
+    create_time: Option<f64>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<Value>,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeConversation {
+    #[serde(default)]
+    name: String,
+    created_at: Option<String>,
+    #[serde(default)]
+    chat_messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeMessage {
+    sender: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    content: Vec<ClaudeContent>,
+    created_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeContent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Seconds since the epoch (ChatGPT's timestamps) as a stored timestamp
+fn from_epoch(seconds: f64) -> Option<String> {
+    Utc.timestamp_opt(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32)
+        .single()
+        .map(|time| time.to_rfc3339())
+}
+
+/// An ISO 8601 timestamp (Claude's) in the stored form
+fn normalize_timestamp(timestamp: &str) -> String {
+    clock::parse(timestamp)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn entry(role: &str, content: String, timestamp: Option<String>) -> ConversationEntry {
+    ConversationEntry {
+        role: role.to_string(),
+        content,
+        timestamp: timestamp.unwrap_or_default(),
+        quality_score: None,
+        status: EntryStatus::Complete,
+        tool_calls: Vec::new(),
+        parts: Vec::new(),
+    }
+}
+
+/// The text of a ChatGPT message, if it is one a user would have seen
+fn chatgpt_text(message: &ChatGptMessage) -> Option<String> {
+    if !matches!(message.author.role.as_str(), "user" | "assistant")
+        || message.metadata["is_visually_hidden_from_conversation"] == Value::Bool(true)
+    {
+        return None;
+    }
+    let text = match message.content.content_type.as_str() {
+        // Images and files in `parts` are objects; only the text is kept
+        "text" | "multimodal_text" => message
+            .content
+            .parts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "code" => message.content.text.clone().unwrap_or_default(),
+        _ => return None,
+    };
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn parse_chatgpt_conversation(conversation: ChatGptConversation) -> ImportedConversation {
+    // Walk up from the last node seen (stopping at a node seen before, should
+    // the parents loop); without one, order every message by time
+    let mut messages: Vec<&ChatGptMessage> = Vec::new();
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut node = conversation.current_node.as_deref();
+    while let Some(id) = node {
+        let Some(current) = conversation.mapping.get(id) else {
+            break;
+        };
+        if !visited.insert(id) {
+            break;
+        }
+        messages.extend(current.message.as_ref());
+        node = current.parent.as_deref();
+    }
+    if messages.is_empty() {
+        messages = conversation.mapping.values().filter_map(|node| node.message.as_ref()).collect();
+        messages.sort_by(|a, b| b.create_time.unwrap_or(0.0).total_cmp(&a.create_time.unwrap_or(0.0)));
+    }
+    messages.reverse();
+
+    let created_at = conversation.create_time.and_then(from_epoch);
+    let entries = messages
+        .into_iter()
+        .filter_map(|message| {
+            let text = chatgpt_text(message)?;
+            // Messages without a time take the conversation's
+            let timestamp = message.create_time.and_then(from_epoch).or(created_at.clone());
+            Some(entry(&message.author.role, text, timestamp))
+        })
+        .collect();
+    ImportedConversation {
+        title: conversation.title.unwrap_or_default(),
+        created_at,
+        entries,
+    }
+}
+
+fn parse_claude_conversation(conversation: ClaudeConversation) -> ImportedConversation {
+    let created_at = conversation.created_at.as_deref().map(normalize_timestamp);
+    let entries = conversation
+        .chat_messages
+        .into_iter()
+        .filter_map(|message| {
+            let role = match message.sender.as_str() {
+                "human" => "user",
+                "assistant" => "assistant",
+                _ => return None,
+            };
+            let text = if message.text.trim().is_empty() {
+                message
+                    .content
+                    .iter()
+                    .filter(|part| part.kind == "text")
+                    .map(|part| part.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                message.text
+            };
+            let text = text.trim();
+            // Messages without a time take the conversation's
+            let timestamp = message.created_at.as_deref().map(normalize_timestamp).or(created_at.clone());
+            (!text.is_empty()).then(|| entry(role, text.to_string(), timestamp))
+        })
+        .collect();
+    ImportedConversation {
+        title: conversation.name,
+        created_at,
+        entries,
+    }
+}
+
+/// Which service `conversations` came from
+fn detect(conversations: &[Value]) -> Result<ImportFormat> {
+    let first = conversations
+        .first()
+        .ok_or_else(|| anyhow!("The export has no conversations"))?;
+    if first.get("mapping").is_some() {
+        Ok(ImportFormat::ChatGpt)
+    } else if first.get("chat_messages").is_some() {
+        Ok(ImportFormat::Claude)
+    } else {
+        Err(anyhow!("Not a ChatGPT or Claude conversations export"))
+    }
+}
+
+/// Parse a `conversations.json`, returning the format it turned out to be;
+/// conversations with no messages left are dropped
+pub fn parse(json: &str, format: ImportFormat) -> Result<(ImportFormat, Vec<ImportedConversation>)> {
+    let conversations: Vec<Value> =
+        serde_json::from_str(json).context("conversations.json should be a list of conversations")?;
+    let format = match format {
+        ImportFormat::Auto => detect(&conversations)?,
+        format => format,
+    };
+    let mut imported = Vec::new();
+    for conversation in conversations {
+        let conversation = match format {
+            ImportFormat::ChatGpt => parse_chatgpt_conversation(
+                serde_json::from_value(conversation).context("Unexpected ChatGPT conversation")?,
+            ),
+            _ => parse_claude_conversation(
+                serde_json::from_value(conversation).context("Unexpected Claude conversation")?,
+            ),
+        };
+        if !conversation.entries.is_empty() {
+            imported.push(conversation);
+        }
+    }
+    Ok((format, imported))
+}
+
+/// `conversations.json` from an export zip, or the file itself
+fn read_export(path: &Path) -> Result<String> {
+    let is_zip = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("zip"));
+    if !is_zip {
+        return std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()));
+    }
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file).context("Not a valid zip file")?;
+    let name = zip
+        .file_names()
+        .find(|name| name.rsplit('/').next() == Some("conversations.json"))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("No conversations.json in {}", path.display()))?;
+    let mut json = String::new();
+    zip.by_name(&name)?.read_to_string(&mut json)?;
+    Ok(json)
+}
+
+/// What an import brought in
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSummary {
+    pub format: ImportFormat,
+    pub conversations: usize,
+    pub messages: usize,
+    /// Ids of the new conversations
+    pub conversation_ids: Vec<String>,
+}
+
+/// Add `conversations` as inactive conversations and their messages to memory
+pub fn import(
+    sessions: &Mutex<SessionManager>,
+    memory_store: &Mutex<MemoryStore>,
+    conversations: Vec<ImportedConversation>,
+    format: ImportFormat,
+) -> Result<ImportSummary> {
+    let redaction = AppSettings::load().redaction;
+    let mut summary = ImportSummary {
+        format,
+        conversations: 0,
+        messages: 0,
+        conversation_ids: Vec::new(),
+    };
+    for conversation in conversations {
+        let stored = StoredConversation {
+            session: SessionIds::new(AppMode::Companion.to_string()),
+            entries: conversation.entries,
+        };
+        let meta = sessions
+            .lock()
+            .import(&conversation.title, conversation.created_at.as_deref(), &stored)?;
+
+        let (user_id, agent_id, run_id) = stored.session.memory_ids();
+        let batch = stored
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut metadata = HashMap::new();
+                metadata.insert("kind".to_string(), serde_json::json!("message"));
+                metadata.insert("role".to_string(), serde_json::json!(entry.role));
+                metadata.insert("timestamp".to_string(), serde_json::json!(entry.timestamp));
+                metadata.insert("status".to_string(), serde_json::json!(entry.status));
+                metadata.insert("imported_from".to_string(), serde_json::json!(format.name()));
+                NewMemory {
+                    content: redaction::redact(&entry.content, &redaction),
+                    user_id: user_id.clone(),
+                    agent_id: agent_id.clone(),
+                    run_id: run_id.clone(),
+                    metadata,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        // One batch per conversation, so its messages are embedded together
+        for result in memory_store.lock().add_batch(batch) {
+            result?;
+        }
+        summary.conversations += 1;
+        summary.messages += stored.entries.len();
+        summary.conversation_ids.push(meta.id);
+    }
+    Ok(summary)
+}
+
+/// Import a ChatGPT or Claude export (the zip or its conversations.json);
+/// `format` defaults to detecting it
+#[tauri::command]
+pub async fn import_conversations(
+    path: String,
+    format: Option<ImportFormat>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ImportSummary, String> {
+    let path = std::path::PathBuf::from(path);
+    let format = format.unwrap_or_default();
+    let (format, conversations) = tauri::async_runtime::spawn_blocking(move || parse(&read_export(&path)?, format))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+
+    let sessions = state.conversations.clone();
+    let memory_store = state.memory_store.clone();
+    let summary = tauri::async_runtime::spawn_blocking(move || import(&sessions, &memory_store, conversations, format))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+    info!(
+        "Imported {} conversation(s) ({} messages) from a {} export",
+        summary.conversations,
+        summary.messages,
+        format.name()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exports() {
+        // An edited first message leaves two branches; the current one wins
+        let chatgpt = r#"[{
+            "title": "Sleep tips",
+            "create_time": 1700000000.5,
+            "current_node": "c",
+            "mapping": {
+                "root": {"message": null, "parent": null},
+                "s": {"message": {"author": {"role": "system"}, "content": {"content_type": "text", "parts": [""]},
+                      "create_time": null, "metadata": {"is_visually_hidden_from_conversation": true}}, "parent": "root"},
+                "old": {"message": {"author": {"role": "user"}, "content": {"content_type": "text", "parts": ["first try"]},
+                        "create_time": 1700000001}, "parent": "s"},
+                "u": {"message": {"author": {"role": "user"}, "content": {"content_type": "multimodal_text",
+                      "parts": [{"asset_pointer": "file"}, "How do I sleep better?"]}, "create_time": 1700000002}, "parent": "s"},
+                "c": {"message": {"author": {"role": "assistant"}, "content": {"content_type": "text", "parts": ["Keep a schedule."]},
+                      "create_time": 1700000003}, "parent": "u"}
+            }
+        }]"#;
+        let (format, imported) = parse(chatgpt, ImportFormat::Auto).unwrap();
+        assert_eq!(format, ImportFormat::ChatGpt);
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].title, "Sleep tips");
+        let texts: Vec<(&str, &str)> = imported[0]
+            .entries
+            .iter()
+            .map(|entry| (entry.role.as_str(), entry.content.as_str()))
+            .collect();
+        assert_eq!(texts, [("user", "How do I sleep better?"), ("assistant", "Keep a schedule.")]);
+        assert_eq!(imported[0].entries[0].timestamp, "2023-11-14T22:13:22+00:00");
+
+        let claude = r#"[
+            {"uuid": "1", "name": "Recipes", "created_at": "2024-03-01T12:00:00.000000Z", "chat_messages": [
+                {"sender": "human", "text": "Something with lentils?", "created_at": "2024-03-01T12:00:01Z"},
+                {"sender": "assistant", "text": "", "content": [{"type": "text", "text": "Try dal."}, {"type": "tool_use"}]}
+            ]},
+            {"uuid": "2", "name": "Empty", "chat_messages": []}
+        ]"#;
+        let (_, imported) = parse(claude, ImportFormat::Auto).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].created_at.as_deref(), Some("2024-03-01T12:00:00+00:00"));
+        assert_eq!(imported[0].entries[1].content, "Try dal.");
+        assert_eq!(imported[0].entries[1].timestamp, "2024-03-01T12:00:00+00:00");
+
+        // Parents that loop through message-less nodes end the walk
+        let cycle = r#"[{
+            "title": "Loop",
+            "current_node": "a",
+            "mapping": {
+                "a": {"message": {"author": {"role": "user"}, "content": {"content_type": "text", "parts": ["hi"]}}, "parent": "b"},
+                "b": {"message": null, "parent": "c"},
+                "c": {"message": null, "parent": "b"}
+            }
+        }]"#;
+        let (_, imported) = parse(cycle, ImportFormat::ChatGpt).unwrap();
+        assert_eq!(imported[0].entries.len(), 1);
+
+        assert!(parse(r#"[{"id": 1}]"#, ImportFormat::Auto).is_err());
+        assert!(parse("{}", ImportFormat::Claude).is_err());
+    }
+}
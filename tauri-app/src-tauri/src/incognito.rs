@@ -0,0 +1,112 @@
+// Incognito Module - Conversations that leave nothing behind
+//
+// Starting incognito sets the current history and session aside in memory
+// and begins an empty session flagged `incognito`. While it lasts, turns are
+// kept in the working history only: nothing goes to the history file, the
+// in-flight journal, the memory store (so there's no memory extraction), the
+// entity index, the rolling summary or compaction. Existing memories are
+// still retrieved. Ending incognito, or quitting, discards its turns and
+// brings the previous conversation back. Each `ChatResponse` says whether it
+// was incognito.
+
+use crate::session::SessionIds;
+use crate::{AppMode, AppState, ConversationEntry};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use tauri::Manager;
+use tracing::info;
+
+/// What incognito set aside
+struct Stashed {
+    session: SessionIds,
+    history: Vec<ConversationEntry>,
+    mode: AppMode,
+}
+
+static STASH: Mutex<Option<Stashed>> = parking_lot::const_mutex(None);
+
+/// Whether the current session is incognito
+pub fn is_active(state: &AppState) -> bool {
+    state.session.lock().incognito
+}
+
+/// A new incognito session for `agent_id`
+pub fn session(agent_id: impl Into<String>) -> SessionIds {
+    SessionIds {
+        incognito: true,
+        ..SessionIds::new(agent_id)
+    }
+}
+
+/// Set the current conversation aside and start an incognito session
+pub fn start(state: &AppState) -> Result<()> {
+    if state.generation.is_active() {
+        return Err(anyhow!("Wait for the current response to finish before going incognito"));
+    }
+    let mut stash = STASH.lock();
+    if stash.is_some() {
+        return Err(anyhow!("Already incognito"));
+    }
+    let mode = state.current_mode.lock().clone();
+    let previous = std::mem::replace(&mut *state.session.lock(), session(mode.to_string()));
+    *stash = Some(Stashed {
+        session: previous,
+        history: std::mem::take(&mut *state.conversation_history.lock()),
+        mode,
+    });
+    Ok(())
+}
+
+/// Discard the incognito turns and restore the conversation set aside;
+/// returns how many turns were discarded
+pub fn end(state: &AppState) -> Result<usize> {
+    if state.generation.is_active() {
+        return Err(anyhow!("Wait for the current response to finish before leaving incognito"));
+    }
+    let stashed = STASH.lock().take().ok_or_else(|| anyhow!("Not incognito"))?;
+    let discarded = std::mem::replace(&mut *state.conversation_history.lock(), stashed.history).len();
+    *state.session.lock() = stashed.session;
+    *state.current_mode.lock() = stashed.mode;
+    Ok(discarded)
+}
+
+/// Start an incognito conversation; emits `incognito-changed` (true)
+#[tauri::command]
+pub async fn start_incognito(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    start(&state).map_err(|e| e.to_string())?;
+    info!("Incognito session started");
+    let _ = app.emit_all("incognito-changed", true);
+    Ok(())
+}
+
+/// End the incognito conversation, discarding it; emits `incognito-changed`
+/// (false)
+#[tauri::command]
+pub async fn end_incognito(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<usize, String> {
+    let discarded = end(&state).map_err(|e| e.to_string())?;
+    info!("Incognito session ended ({} entries discarded)", discarded);
+    let _ = app.emit_all("incognito-changed", false);
+    Ok(discarded)
+}
+
+#[tauri::command]
+pub async fn is_incognito(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(is_active(&state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incognito_flag_is_never_saved() {
+        let ids = session("companion");
+        assert!(ids.incognito);
+        // A session file written by mistake would load as a normal session
+        let json = serde_json::to_string(&ids).unwrap();
+        assert!(!json.contains("incognito"));
+        let loaded: SessionIds = serde_json::from_str(&json).unwrap();
+        assert!(!loaded.incognito);
+        assert_eq!(loaded.run_id, ids.run_id);
+    }
+}
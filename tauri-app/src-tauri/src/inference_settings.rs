@@ -0,0 +1,141 @@
+// Inference Settings Module - llama.cpp context and loading options
+//
+// Context size, threads, batch size, mmap/mlock and flash attention for the
+// native backend, saved in `inference.json`. The context size also sets the
+// token budget prompts are fitted to on every backend. Loading options only
+// take effect when the model is loaded, so changing settings releases the
+// native model and the next message loads it again with them.
+
+use crate::prompt_budget::DEFAULT_CONTEXT_TOKENS;
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Smallest context that still leaves room for a system prompt and a reply
+const MIN_CONTEXT_TOKENS: u32 = 512;
+
+/// Native inference settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceSettings {
+    /// Context window, in tokens
+    pub n_ctx: u32,
+    /// CPU threads for generation (llama.cpp's default when unset)
+    pub n_threads: Option<i32>,
+    /// Prompt tokens decoded per batch
+    pub n_batch: u32,
+    /// Map the model file instead of reading it into memory
+    pub use_mmap: bool,
+    /// Lock the model in RAM so it is never swapped out
+    pub use_mlock: bool,
+    pub flash_attention: bool,
+}
+
+impl Default for InferenceSettings {
+    fn default() -> Self {
+        Self {
+            n_ctx: DEFAULT_CONTEXT_TOKENS as u32,
+            n_threads: None,
+            n_batch: 512,
+            use_mmap: true,
+            use_mlock: false,
+            flash_attention: false,
+        }
+    }
+}
+
+impl InferenceSettings {
+    const FILE: &'static str = "inference.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.n_ctx < MIN_CONTEXT_TOKENS {
+            return Err(anyhow!("Context size must be at least {} tokens", MIN_CONTEXT_TOKENS));
+        }
+        if self.n_batch == 0 {
+            return Err(anyhow!("Batch size must be at least 1"));
+        }
+        if self.n_threads.is_some_and(|threads| threads < 1) {
+            return Err(anyhow!("Thread count must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Prompt tokens decoded at once (never more than the context)
+    pub fn batch_size(&self) -> u32 {
+        self.n_batch.clamp(1, self.n_ctx)
+    }
+}
+
+/// Context window prompts are fitted to
+pub fn context_tokens() -> usize {
+    InferenceSettings::load().n_ctx as usize
+}
+
+#[tauri::command]
+pub async fn get_inference_settings() -> Result<InferenceSettings, String> {
+    Ok(InferenceSettings::load())
+}
+
+/// Save inference settings; a loaded native model is released and loaded
+/// again with them on the next message
+#[tauri::command]
+pub async fn set_inference_settings(
+    settings: InferenceSettings,
+    state: tauri::State<'_, AppState>,
+) -> Result<InferenceSettings, String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    if state.generation.is_active() {
+        return Err("Can't change inference settings while a reply is being generated".to_string());
+    }
+    settings.save().map_err(|e| e.to_string())?;
+
+    let mut llm = state.llm.lock().await;
+    if llm.as_ref().is_some_and(|backend| backend.name() == "native") {
+        *llm = None;
+        info!("Released the native model to apply new inference settings");
+    }
+    info!(
+        "Inference settings: context {}, batch {}, threads {:?}, mmap {}, mlock {}, flash attention {}",
+        settings.n_ctx,
+        settings.n_batch,
+        settings.n_threads,
+        settings.use_mmap,
+        settings.use_mlock,
+        settings.flash_attention
+    );
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_settings_and_validation() {
+        let settings: InferenceSettings = serde_json::from_str(r#"{"n_ctx": 8192, "n_batch": 16384}"#).unwrap();
+        assert_eq!(settings.n_ctx, 8192);
+        assert!(settings.use_mmap);
+        assert_eq!(settings.batch_size(), 8192);
+        assert!(settings.validate().is_ok());
+
+        let tiny = InferenceSettings {
+            n_ctx: 128,
+            ..Default::default()
+        };
+        assert!(tiny.validate().is_err());
+        let no_threads = InferenceSettings {
+            n_threads: Some(0),
+            ..Default::default()
+        };
+        assert!(no_threads.validate().is_err());
+    }
+}
@@ -0,0 +1,595 @@
+// Ingest Module - Parallel document ingestion into the memory store
+//
+// Documents are queued from the UI and processed by a background worker.
+// Each batch is read, chunked, hashed and embedded in parallel with rayon;
+// only the final insert into `MemoryStore` takes the lock. Files too large
+// to hold in memory are streamed through `StreamingChunker` instead. Chunks of
+// documents ingested as private are tagged so retrieval hides them until the
+// session is unlocked. Every chunk records the hash of the chunking settings
+// it was cut with, so documents can be re-chunked when those change. Chunks
+// whose text is already in the store are skipped.
+
+use crate::chunking_settings::{ChunkingSettings, CHUNKING_KEY};
+use crate::embeddings::{fnv1a, Embedder, HashingEmbedder};
+use crate::memory_policy::{Sensitivity, SENSITIVITY_KEY};
+use crate::memory_store::{MemoryFilters, MemoryStore, NewMemory};
+use crate::session::LOCAL_USER_ID;
+use crate::text_chunker::{ChunkingConfig, StreamingChunker, TextChunker};
+use crate::AppState;
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Chunks embedded per `embed_batch` call
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Most documents processed in one batch (bounds memory for large queues)
+const MAX_BATCH_DOCUMENTS: usize = 64;
+
+/// Files larger than this are streamed instead of read whole
+const STREAM_THRESHOLD_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Chunks hashed, embedded and stored together when streaming a file
+const STREAM_BATCH_CHUNKS: usize = 256;
+
+/// Where a queued document's text comes from
+#[derive(Debug, Clone)]
+pub enum DocumentSource {
+    Text(String),
+    File(PathBuf),
+}
+
+/// A document waiting to be ingested
+#[derive(Debug, Clone)]
+pub struct IngestJob {
+    pub doc_id: String,
+    pub source: DocumentSource,
+    pub sensitivity: Sensitivity,
+    /// Drop the document's existing chunks first (re-chunking)
+    pub replace: bool,
+}
+
+/// A chunk that has been hashed and embedded, ready to store
+#[derive(Debug, Clone)]
+pub struct PreparedChunk {
+    pub doc_id: String,
+    pub index: usize,
+    /// Total chunks in the document (unknown while streaming)
+    pub count: Option<usize>,
+    pub text: String,
+    pub content_hash: u64,
+    pub embedding: Vec<f32>,
+    pub sensitivity: Sensitivity,
+}
+
+impl PreparedChunk {
+    fn new(doc_id: &str, index: usize, count: Option<usize>, text: String) -> Self {
+        Self {
+            doc_id: doc_id.to_string(),
+            index,
+            count,
+            content_hash: fnv1a(text.as_bytes()),
+            text,
+            embedding: Vec::new(),
+            sensitivity: Sensitivity::Normal,
+        }
+    }
+}
+
+/// Running ingestion totals and throughput
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestStats {
+    pub documents: usize,
+    pub failed_documents: usize,
+    pub chunks: usize,
+    /// Chunks skipped because identical text was already ingested
+    pub duplicate_chunks: usize,
+    pub bytes: u64,
+    /// Time spent processing batches (excludes idle time)
+    pub busy_seconds: f64,
+    pub bytes_per_second: f64,
+    pub chunks_per_second: f64,
+    /// Documents queued but not yet processed
+    pub pending: usize,
+}
+
+impl IngestStats {
+    fn record_batch(&mut self, batch: &BatchResult, seconds: f64) {
+        self.documents += batch.documents;
+        self.failed_documents += batch.failed;
+        self.chunks += batch.stored;
+        self.duplicate_chunks += batch.duplicates;
+        self.bytes += batch.bytes;
+        self.busy_seconds += seconds;
+        self.pending = self.pending.saturating_sub(batch.documents + batch.failed);
+
+        if self.busy_seconds > 0.0 {
+            self.bytes_per_second = self.bytes as f64 / self.busy_seconds;
+            self.chunks_per_second = self.chunks as f64 / self.busy_seconds;
+        }
+    }
+}
+
+/// Outcome of processing one batch of jobs
+struct BatchResult {
+    documents: usize,
+    failed: usize,
+    stored: usize,
+    duplicates: usize,
+    bytes: u64,
+}
+
+/// Chunk, hash and embed documents in parallel
+///
+/// `documents` are `(doc_id, text)` pairs. Chunks keep document order.
+pub fn prepare_documents(
+    documents: &[(String, String)],
+    chunker: &TextChunker,
+    embedder: &dyn Embedder,
+) -> Vec<PreparedChunk> {
+    let mut chunks: Vec<PreparedChunk> = documents
+        .par_iter()
+        .flat_map_iter(|(doc_id, text)| {
+            let pieces = chunker.chunk_text(text);
+            let count = pieces.len();
+            pieces
+                .into_iter()
+                .enumerate()
+                .map(move |(index, text)| PreparedChunk::new(doc_id, index, Some(count), text))
+        })
+        .collect();
+
+    embed_chunks(&mut chunks, embedder);
+    chunks
+}
+
+/// Fill in embeddings, batching calls across rayon workers
+fn embed_chunks(chunks: &mut [PreparedChunk], embedder: &dyn Embedder) {
+    chunks.par_chunks_mut(EMBED_BATCH_SIZE).for_each(|batch| {
+        let texts: Vec<&str> = batch.iter().map(|chunk| chunk.text.as_str()).collect();
+        let vectors = embedder.embed_batch(&texts);
+        for (chunk, vector) in batch.iter_mut().zip(vectors) {
+            chunk.embedding = vector;
+        }
+    });
+}
+
+/// Stream a large file into the store without reading it whole
+///
+/// # Returns
+/// (stored, duplicates, bytes)
+fn ingest_stream(
+    doc_id: &str,
+    sensitivity: Sensitivity,
+    path: &Path,
+    config: &ChunkingConfig,
+    chunking_hash: &str,
+    embedder: &dyn Embedder,
+    memory_store: &Mutex<MemoryStore>,
+) -> Result<(usize, usize, u64)> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut chunks = StreamingChunker::new(BufReader::new(file), config.clone());
+
+    let mut stored = 0;
+    let mut duplicates = 0;
+    let mut index = 0;
+    loop {
+        let mut batch = Vec::with_capacity(STREAM_BATCH_CHUNKS);
+        for chunk in chunks.by_ref().take(STREAM_BATCH_CHUNKS) {
+            let text = chunk.with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut chunk = PreparedChunk::new(doc_id, index, None, text);
+            chunk.sensitivity = sensitivity;
+            batch.push(chunk);
+            index += 1;
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        embed_chunks(&mut batch, embedder);
+        let (batch_stored, batch_duplicates) =
+            store_chunks(&mut memory_store.lock(), batch, chunking_hash);
+        stored += batch_stored;
+        duplicates += batch_duplicates;
+    }
+
+    Ok((stored, duplicates, bytes))
+}
+
+/// Text hashes of the document chunks currently in the store
+fn stored_hashes(store: &MemoryStore) -> HashSet<u64> {
+    let mut filters = MemoryFilters::default();
+    filters
+        .metadata
+        .insert("kind".to_string(), serde_json::json!("document_chunk"));
+    store
+        .get_all(&filters, usize::MAX)
+        .iter()
+        .filter_map(|chunk| chunk.metadata.get("content_hash")?.as_str())
+        .filter_map(|hex| u64::from_str_radix(hex, 16).ok())
+        .collect()
+}
+
+/// Insert prepared chunks, skipping any whose text is already stored
+///
+/// `chunking_hash` identifies the settings the chunks were cut with.
+///
+/// # Returns
+/// (stored, duplicates)
+pub fn store_chunks(
+    store: &mut MemoryStore,
+    chunks: Vec<PreparedChunk>,
+    chunking_hash: &str,
+) -> (usize, usize) {
+    // Checked against the store itself, so deleted text can come back
+    let mut seen = stored_hashes(store);
+    let mut duplicates = 0;
+    let mut batch = Vec::with_capacity(chunks.len());
+    let mut labels = Vec::with_capacity(chunks.len());
+
+    for chunk in chunks {
+        if !seen.insert(chunk.content_hash) {
+            duplicates += 1;
+            continue;
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("kind".to_string(), serde_json::json!("document_chunk"));
+        metadata.insert("doc_id".to_string(), serde_json::json!(chunk.doc_id));
+        metadata.insert("chunk_index".to_string(), serde_json::json!(chunk.index));
+        if let Some(count) = chunk.count {
+            metadata.insert("chunk_count".to_string(), serde_json::json!(count));
+        }
+        metadata.insert(
+            "content_hash".to_string(),
+            serde_json::json!(format!("{:016x}", chunk.content_hash)),
+        );
+        metadata.insert(CHUNKING_KEY.to_string(), serde_json::json!(chunking_hash));
+        if chunk.sensitivity == Sensitivity::Private {
+            metadata.insert(SENSITIVITY_KEY.to_string(), serde_json::json!(chunk.sensitivity));
+        }
+
+        labels.push((chunk.index, chunk.doc_id));
+        batch.push(NewMemory {
+            content: chunk.text,
+            user_id: Some(LOCAL_USER_ID.to_string()),
+            metadata,
+            embedding: Some(chunk.embedding),
+            ..Default::default()
+        });
+    }
+
+    let mut stored = 0;
+    for (result, (index, doc_id)) in store.add_batch(batch).into_iter().zip(labels) {
+        match result {
+            Ok(_) => stored += 1,
+            Err(e) => warn!("Skipping chunk {} of {}: {}", index, doc_id, e),
+        }
+    }
+
+    (stored, duplicates)
+}
+
+/// Filters matching the chunks of document `doc_id`
+pub fn document_filters(doc_id: &str) -> MemoryFilters {
+    let mut filters = MemoryFilters::default();
+    filters
+        .metadata
+        .insert("kind".to_string(), serde_json::json!("document_chunk"));
+    filters
+        .metadata
+        .insert("doc_id".to_string(), serde_json::json!(doc_id));
+    filters
+}
+
+/// Background ingestion queue
+pub struct IngestQueue {
+    sender: Mutex<Sender<IngestJob>>,
+    stats: Arc<Mutex<IngestStats>>,
+}
+
+impl IngestQueue {
+    /// Start the background worker that feeds `memory_store`
+    pub fn start(memory_store: Arc<Mutex<MemoryStore>>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(IngestStats::default()));
+
+        let worker_stats = stats.clone();
+        std::thread::Builder::new()
+            .name("ingest".to_string())
+            .spawn(move || run_worker(receiver, memory_store, worker_stats))
+            .expect("Failed to start ingestion worker");
+
+        Self {
+            sender: Mutex::new(sender),
+            stats,
+        }
+    }
+
+    /// Queue a document for ingestion
+    pub fn enqueue(&self, job: IngestJob) -> Result<()> {
+        self.stats.lock().pending += 1;
+        self.sender.lock().send(job).map_err(|_| {
+            self.stats.lock().pending -= 1;
+            anyhow!("Ingestion worker has stopped")
+        })
+    }
+
+    /// Snapshot of the ingestion totals
+    pub fn stats(&self) -> IngestStats {
+        self.stats.lock().clone()
+    }
+}
+
+fn run_worker(
+    receiver: Receiver<IngestJob>,
+    memory_store: Arc<Mutex<MemoryStore>>,
+    stats: Arc<Mutex<IngestStats>>,
+) {
+    // Same vectors as the store uses for queries
+    let embedder = memory_store
+        .lock()
+        .embedder()
+        .unwrap_or_else(|| Arc::new(HashingEmbedder::default()));
+    let embedder = embedder.as_ref();
+
+    // Block for the first job, then take whatever else is already queued
+    while let Ok(first) = receiver.recv() {
+        let mut jobs = vec![first];
+        jobs.extend(receiver.try_iter().take(MAX_BATCH_DOCUMENTS - 1));
+
+        // Settings changes apply from the next batch
+        let settings = ChunkingSettings::load();
+        let config = settings.config();
+        let chunker = TextChunker::with_config(config.clone());
+        let chunking_hash = settings.hash();
+
+        let started = Instant::now();
+        for job in jobs.iter().filter(|job| job.replace) {
+            let removed = memory_store.lock().delete_all(&document_filters(&job.doc_id));
+            info!("Re-chunking {} (replacing {} chunks)", job.doc_id, removed);
+        }
+        let sensitivity: HashMap<String, Sensitivity> = jobs
+            .iter()
+            .map(|job| (job.doc_id.clone(), job.sensitivity))
+            .collect();
+        let (documents, large_files, failed) = load_documents(jobs);
+        let bytes = documents.iter().map(|(_, text)| text.len() as u64).sum();
+
+        let mut chunks = prepare_documents(&documents, &chunker, embedder);
+        for chunk in &mut chunks {
+            chunk.sensitivity = sensitivity[&chunk.doc_id];
+        }
+        let (stored, duplicates) = store_chunks(&mut memory_store.lock(), chunks, &chunking_hash);
+
+        let mut batch = BatchResult {
+            documents: documents.len(),
+            failed,
+            stored,
+            duplicates,
+            bytes,
+        };
+
+        for (doc_id, path) in large_files {
+            info!("Streaming large file {}", path.display());
+            match ingest_stream(
+                &doc_id,
+                sensitivity[&doc_id],
+                &path,
+                &config,
+                &chunking_hash,
+                embedder,
+                &memory_store,
+            ) {
+                Ok((stored, duplicates, bytes)) => {
+                    batch.documents += 1;
+                    batch.stored += stored;
+                    batch.duplicates += duplicates;
+                    batch.bytes += bytes;
+                }
+                Err(e) => {
+                    warn!("Skipping document: {:#}", e);
+                    batch.failed += 1;
+                }
+            }
+        }
+
+        let seconds = started.elapsed().as_secs_f64();
+
+        let mut stats = stats.lock();
+        stats.record_batch(&batch, seconds);
+        info!(
+            "Ingested {} document(s), {} chunk(s) in {:.2}s ({:.0} KB/s overall)",
+            batch.documents,
+            batch.stored,
+            seconds,
+            stats.bytes_per_second / 1024.0
+        );
+    }
+}
+
+/// A job's text, or a file too large to read whole
+enum Loaded {
+    Text(String, String),
+    Stream(String, PathBuf),
+}
+
+/// Read the text of each job in parallel, logging any that fail
+///
+/// # Returns
+/// (documents, large files to stream, failed count)
+fn load_documents(jobs: Vec<IngestJob>) -> (Vec<(String, String)>, Vec<(String, PathBuf)>, usize) {
+    let results: Vec<Result<Loaded>> = jobs
+        .into_par_iter()
+        .map(|job| match job.source {
+            DocumentSource::Text(text) => Ok(Loaded::Text(job.doc_id, text)),
+            DocumentSource::File(path) => {
+                let size = std::fs::metadata(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+                    .len();
+                if size > STREAM_THRESHOLD_BYTES {
+                    return Ok(Loaded::Stream(job.doc_id, path));
+                }
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+                    .map(|text| Loaded::Text(job.doc_id, text))
+            }
+        })
+        .collect();
+
+    let mut documents = Vec::with_capacity(results.len());
+    let mut large_files = Vec::new();
+    let mut failed = 0;
+    for result in results {
+        match result {
+            Ok(Loaded::Text(doc_id, text)) => documents.push((doc_id, text)),
+            Ok(Loaded::Stream(doc_id, path)) => large_files.push((doc_id, path)),
+            Err(e) => {
+                warn!("Skipping document: {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    (documents, large_files, failed)
+}
+
+/// Queue files for ingestion; returns how many were queued
+///
+/// `sensitivity: "private"` keeps them out of retrieval until the session is
+/// unlocked.
+#[tauri::command]
+pub async fn ingest_files(
+    paths: Vec<String>,
+    sensitivity: Option<Sensitivity>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    for path in &paths {
+        state
+            .ingest
+            .enqueue(IngestJob {
+                doc_id: path.clone(),
+                source: DocumentSource::File(PathBuf::from(path)),
+                sensitivity: sensitivity.unwrap_or_default(),
+                replace: false,
+            })
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(paths.len())
+}
+
+/// Queue raw text for ingestion under `doc_id`
+#[tauri::command]
+pub async fn ingest_text(
+    doc_id: String,
+    text: String,
+    sensitivity: Option<Sensitivity>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .ingest
+        .enqueue(IngestJob {
+            doc_id,
+            source: DocumentSource::Text(text),
+            sensitivity: sensitivity.unwrap_or_default(),
+            replace: false,
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Mark an ingested document normal or private; returns the chunks updated
+#[tauri::command]
+pub async fn set_document_sensitivity(
+    doc_id: String,
+    sensitivity: Sensitivity,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let filters = document_filters(&doc_id);
+    let mut store = state.memory_store.lock();
+    let ids: Vec<String> = store
+        .get_all(&filters, usize::MAX)
+        .into_iter()
+        .map(|chunk| chunk.id)
+        .collect();
+    if ids.is_empty() {
+        return Err(format!("No ingested document {}", doc_id));
+    }
+
+    let mut metadata = HashMap::new();
+    metadata.insert(SENSITIVITY_KEY.to_string(), serde_json::json!(sensitivity));
+    for id in &ids {
+        store
+            .update(id, None, Some(metadata.clone()))
+            .map_err(|e| e.to_string())?;
+    }
+    info!("Marked {} ({} chunks) as {:?}", doc_id, ids.len(), sensitivity);
+    Ok(ids.len())
+}
+
+/// Ingestion totals and throughput
+#[tauri::command]
+pub async fn get_ingest_stats(state: tauri::State<'_, AppState>) -> Result<IngestStats, String> {
+    Ok(state.ingest.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunker() -> TextChunker {
+        TextChunker::with_config(ChunkingConfig {
+            chunk_size: 60,
+            chunk_overlap: 0,
+            min_chunk_size: 0,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_prepare_keeps_document_order() {
+        let documents = vec![
+            ("a".to_string(), "First document. It has two sentences that are long.".repeat(3)),
+            ("b".to_string(), "Second document.".to_string()),
+        ];
+        let embedder = HashingEmbedder::default();
+        let chunks = prepare_documents(&documents, &chunker(), &embedder);
+
+        let a: Vec<&PreparedChunk> = chunks.iter().filter(|c| c.doc_id == "a").collect();
+        assert!(a.len() > 1);
+        for (i, chunk) in a.iter().enumerate() {
+            assert_eq!(chunk.index, i);
+            assert_eq!(chunk.count, Some(a.len()));
+        }
+        assert_eq!(chunks.last().unwrap().doc_id, "b");
+        assert!(chunks.iter().all(|c| c.embedding.len() == embedder.dimensions()));
+    }
+
+    #[test]
+    fn test_store_skips_duplicate_chunks() {
+        let documents = vec![
+            ("a".to_string(), "The same text.".to_string()),
+            ("b".to_string(), "The same text.".to_string()),
+        ];
+        let chunks = prepare_documents(&documents, &chunker(), &HashingEmbedder::default());
+
+        let mut store = MemoryStore::new();
+        assert_eq!(store_chunks(&mut store, chunks.clone(), "test"), (1, 1));
+        assert_eq!(store.count(), 1);
+
+        // Already stored
+        assert_eq!(store_chunks(&mut store, chunks.clone(), "test"), (0, 2));
+        // Deleted text can be ingested again
+        store.delete_all(&document_filters("a"));
+        assert_eq!(store_chunks(&mut store, chunks, "test"), (1, 1));
+    }
+}
@@ -0,0 +1,168 @@
+// Intent Module - Detects messages that belong to the other mode
+//
+// A keyword/phrase scorer decides whether a message clearly asks for
+// storytelling (Youniverse) or everyday help (Companion). When it points away
+// from the current mode the app either suggests a switch (`mode-suggestion`
+// event) or, with `auto_switch` enabled, switches before generating.
+
+use crate::AppMode;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Minimum score lead the other mode needs to count as a clear intent
+const MIN_LEAD: u32 = 2;
+
+/// Phrases hinting at storytelling / roleplay, with weights
+const YOUNIVERSE_CUES: &[(&str, u32)] = &[
+    ("once upon a time", 3),
+    ("tell me a story", 3),
+    ("write a story", 3),
+    ("continue the story", 3),
+    ("let's roleplay", 3),
+    ("lets roleplay", 3),
+    ("roleplay", 2),
+    ("role-play", 2),
+    ("in character", 2),
+    ("narrate", 2),
+    ("plot twist", 2),
+    ("adventure", 1),
+    ("quest", 1),
+    ("dungeon", 1),
+    ("chapter", 1),
+    ("fantasy", 1),
+];
+
+/// Phrases hinting at a plain assistant request, with weights
+const COMPANION_CUES: &[(&str, u32)] = &[
+    ("out of character", 3),
+    ("(ooc", 3),
+    ("ooc:", 3),
+    ("break character", 3),
+    ("stop the story", 3),
+    ("in real life", 2),
+    ("help me with", 2),
+    ("can you explain", 2),
+    ("how do i", 2),
+    ("remind me", 2),
+    ("what is the weather", 2),
+    ("summarize", 1),
+    ("translate", 1),
+    ("advice", 1),
+];
+
+/// A suggested switch to another mode
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModeSuggestion {
+    pub mode: String,
+    /// 0.0 - 1.0, how clearly the message belongs to `mode`
+    pub confidence: f32,
+    /// Phrases that triggered the suggestion
+    pub cues: Vec<String>,
+}
+
+/// Whether suggestions are acted on automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeSwitchSettings {
+    /// Suggest switches at all
+    pub enabled: bool,
+    /// Switch without asking
+    pub auto_switch: bool,
+    /// Keep the conversation history when switching automatically
+    pub include_history: bool,
+}
+
+impl Default for ModeSwitchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auto_switch: false,
+            include_history: true,
+        }
+    }
+}
+
+impl ModeSwitchSettings {
+    const FILE: &'static str = "mode_switch.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+}
+
+fn score(message: &str, cues: &[(&str, u32)]) -> (u32, Vec<String>) {
+    let mut total = 0;
+    let mut matched = Vec::new();
+    for (cue, weight) in cues {
+        if message.contains(cue) {
+            total += weight;
+            matched.push(cue.to_string());
+        }
+    }
+    (total, matched)
+}
+
+/// Suggest the other mode if `message` clearly belongs to it
+pub fn detect(message: &str, current: &AppMode) -> Option<ModeSuggestion> {
+    let message = message.to_lowercase();
+    let (story, story_cues) = score(&message, YOUNIVERSE_CUES);
+    let (assistant, assistant_cues) = score(&message, COMPANION_CUES);
+
+    let (target, lead, total, cues) = match current {
+        AppMode::Companion if story >= assistant + MIN_LEAD => {
+            (AppMode::Youniverse, story - assistant, story, story_cues)
+        }
+        AppMode::Youniverse if assistant >= story + MIN_LEAD => {
+            (AppMode::Companion, assistant - story, assistant, assistant_cues)
+        }
+        _ => return None,
+    };
+
+    Some(ModeSuggestion {
+        mode: target.to_string(),
+        confidence: (lead as f32 / (total + MIN_LEAD) as f32).min(1.0),
+        cues,
+    })
+}
+
+#[tauri::command]
+pub async fn get_mode_switch_settings() -> Result<ModeSwitchSettings, String> {
+    Ok(ModeSwitchSettings::load())
+}
+
+#[tauri::command]
+pub async fn set_mode_switch_settings(settings: ModeSwitchSettings) -> Result<(), String> {
+    settings.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_story_request_suggests_youniverse() {
+        let suggestion = detect("Tell me a story about a dragon's quest", &AppMode::Companion).unwrap();
+        assert_eq!(suggestion.mode, "youniverse");
+        assert!(suggestion.cues.contains(&"tell me a story".to_string()));
+        assert!(suggestion.confidence > 0.0);
+
+        // Already in the right mode
+        assert!(detect("Tell me a story", &AppMode::Youniverse).is_none());
+    }
+
+    #[test]
+    fn test_out_of_character_suggests_companion() {
+        let suggestion = detect("OOC: how do I export my notes?", &AppMode::Youniverse).unwrap();
+        assert_eq!(suggestion.mode, "companion");
+    }
+
+    #[test]
+    fn test_ambiguous_messages_stay_put() {
+        assert!(detect("What a nice adventure today", &AppMode::Companion).is_none());
+        assert!(detect("Thanks!", &AppMode::Youniverse).is_none());
+    }
+}
@@ -1,52 +1,188 @@
+use crate::backend::GenerationRequest;
+use crate::capabilities::{self, Feature, ModelCapabilities};
+use crate::context_window::{self, TURN_OVERHEAD};
+use crate::generation::CancellationToken;
+use crate::logit_bias::{BannedWords, TokenBias};
+use crate::inference_settings::InferenceSettings;
+use crate::prompt_builder::{self, ChatTemplate};
+use crate::stop_sequences::StopFilter;
+use crate::{sampling, LlmConfig};
 use anyhow::{Context, Result};
 use llama_cpp_2::context::params::LlamaContextParams;
 use llama_cpp_2::llama_backend::LlamaBackend;
 use llama_cpp_2::llama_batch::LlamaBatch;
-use llama_cpp_2::model::{LlamaModel, params::LlamaModelParams, Special};
+use llama_cpp_2::model::{AddBos, LlamaModel, params::LlamaModelParams, Special};
 use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::sampling::LlamaSampler;
-use std::path::PathBuf;
+use llama_cpp_2::token::LlamaToken;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{debug, info, warn};
+
+static BACKEND: OnceLock<LlamaBackend> = OnceLock::new();
+static BACKEND_INIT: Mutex<()> = parking_lot::const_mutex(());
+
+/// The process-wide llama.cpp backend (it can only be initialized once)
+pub fn llama_backend() -> Result<&'static LlamaBackend> {
+    let _guard = BACKEND_INIT.lock();
+    if let Some(backend) = BACKEND.get() {
+        return Ok(backend);
+    }
+    let backend = LlamaBackend::init().context("Failed to initialize llama backend")?;
+    Ok(BACKEND.get_or_init(|| backend))
+}
+
+/// Tokens read and written by one generation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    /// Prompt tokens decoded (after any cut to fit the context)
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Generation stopped at `max_tokens`
+    pub hit_limit: bool,
+}
 
 pub struct LlmManager {
-    backend: LlamaBackend,
+    backend: &'static LlamaBackend,
     model: LlamaModel,
+    model_path: PathBuf,
+    /// `tokenizer.chat_template` from the GGUF metadata, if present
+    chat_template: Option<String>,
     n_ctx: u32,
+    settings: InferenceSettings,
 }
 
 impl LlmManager {
+    /// Load the chosen model (the first one found if none was chosen)
     pub fn new() -> Result<Self> {
+        let model_path = Self::default_model()
+            .context("No model found in models/ directory")?;
+        Self::load(&model_path)
+    }
+    
+    /// Load the GGUF at `model_path`
+    pub fn load(model_path: &Path) -> Result<Self> {
         // Initialize llama.cpp backend
-        let backend = LlamaBackend::init()
-            .context("Failed to initialize llama backend")?;
+        let backend = llama_backend()?;
         
-        // Find model file
-        let model_path = Self::find_model()
-            .context("No model found in models/ directory")?;
+        info!("Loading model: {}", model_path.display());
         
-        println!("📦 Loading model: {}", model_path.display());
+        let settings = InferenceSettings::load();
+        let probed = ModelCapabilities::probe(model_path)?;
+        probed.require(Feature::Chat)?;
+        // Past the trained length the model only produces noise
+        let n_ctx = probed.usable_context(settings.n_ctx);
+        if n_ctx < settings.n_ctx {
+            warn!("Context capped at {} tokens, the length the model was trained for", n_ctx);
+        }
         
-        // Load model with GPU support
-        let mut model_params = LlamaModelParams::default();
-        // Try to offload all layers to GPU if available (will fall back to CPU if no GPU)
-        model_params = model_params.with_n_gpu_layers(999);
+        // Offload as many layers as fit in free VRAM
+        let gpu_layers = crate::system_probe::gpu_layers_for(model_path, n_ctx);
+        let model_params = LlamaModelParams::default()
+            .with_n_gpu_layers(gpu_layers)
+            .with_use_mmap(settings.use_mmap)
+            .with_use_mlock(settings.use_mlock);
         
-        let model = LlamaModel::load_from_file(&backend, &model_path, &model_params)
+        let model = LlamaModel::load_from_file(backend, model_path, &model_params)
             .context("Failed to load model")?;
         
-        let n_ctx = 4096; // Context window size
+        let chat_template = model.meta_val_str("tokenizer.chat_template").ok();
         
-        println!("✅ Model loaded (context: {} tokens)", n_ctx);
+        info!("Model loaded (context: {} tokens)", n_ctx);
+        capabilities::set(probed);
         
         Ok(Self {
             backend,
             model,
+            model_path: model_path.to_path_buf(),
+            chat_template,
             n_ctx,
+            settings,
         })
     }
     
-    fn find_model() -> Option<PathBuf> {
+    /// File stem of the loaded model, e.g. "qwen2.5-7b-instruct-q4_k_m"
+    pub fn model_name(&self) -> String {
+        self.model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+    
+    /// Prompt format for the loaded model (a saved override wins over the
+    /// template in its metadata)
+    pub fn chat_template(&self) -> ChatTemplate {
+        let name = self
+            .model_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        prompt_builder::resolve(&name, self.chat_template.as_deref())
+    }
+    
+    /// Prompt tokens allowed, leaving room for (up to a quarter of the
+    /// context of) the reply
+    fn prompt_budget(&self, config: &LlmConfig) -> usize {
+        let n_ctx = self.n_ctx as usize;
+        n_ctx - (config.max_tokens.max(1) as usize).min(n_ctx / 4)
+    }
+    
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        let tokens = self.model
+            .str_to_token(text, AddBos::Always)
+            .context("Failed to tokenize prompt")?;
+        Ok(tokens.len())
+    }
+    
+    /// Render `request` in the model's chat template, dropping the oldest
+    /// history turns that don't fit in the context
+    ///
+    /// Also returns how many leading tokens (the system prompt) must survive
+    /// a context shift.
+    pub fn fit_request(&self, request: &GenerationRequest) -> Result<(String, usize)> {
+        let template = self.chat_template();
+        let fixed = self.count_tokens(&template.render(&GenerationRequest {
+            history: &[],
+            ..*request
+        }))?;
+        let system = self.count_tokens(&template.render(&GenerationRequest {
+            prompt: "",
+            history: &[],
+            ..*request
+        }))?;
+        let turn_tokens = request
+            .history
+            .iter()
+            .map(|entry| self.count_tokens(&entry.content).map(|n| n + TURN_OVERHEAD))
+            .collect::<Result<Vec<_>>>()?;
+        
+        let start = context_window::first_kept_turn(fixed, &turn_tokens, self.prompt_budget(request.config));
+        if start > 0 {
+            info!("Dropped {} oldest turn(s) to fit the {}-token context", start, self.n_ctx);
+        }
+        let prompt = template.render(&GenerationRequest {
+            history: &request.history[start..],
+            ..*request
+        });
+        Ok((prompt, system.min(self.n_ctx as usize / 2)))
+    }
+    
+    /// The model picked with `load_model_by_path`, else the first one found
+    pub fn default_model() -> Option<PathBuf> {
+        crate::backend::BackendSettings::load()
+            .model_path
+            .filter(|path| path.exists())
+            .or_else(Self::find_model)
+    }
+    
+    /// First GGUF in the models directories
+    pub fn find_model() -> Option<PathBuf> {
         // Try multiple locations for models directory
-        let search_paths = vec![
+        let mut search_paths = vec![
+            // Downloaded by the app (setup wizard, model downloads)
+            crate::paths::models_dir(),
             // Development: from src-tauri, go up to workspace root
             std::env::current_dir().ok()?.parent()?.parent()?.join("models"),
             // Production: models next to exe
@@ -54,6 +190,8 @@ impl LlmManager {
             // Alternative: models in workspace root (when running from tauri-app/src-tauri/target/release)
             std::env::current_exe().ok()?.parent()?.parent()?.parent()?.parent()?.parent()?.join("models"),
         ];
+        // Folders added in settings.toml
+        search_paths.extend(crate::settings::AppSettings::load().models.extra_dirs);
         
         for models_dir in search_paths {
             if !models_dir.exists() {
@@ -80,88 +218,193 @@ impl LlmManager {
         None
     }
     
-    pub fn generate(&mut self, prompt: &str) -> Result<String> {
+    /// Generate a response; cancelling `cancel` stops at the next token and
+    /// returns the text so far
+    pub fn generate(&mut self, prompt: &str, config: &LlmConfig, cancel: &CancellationToken) -> Result<String> {
+        self.generate_streaming(prompt, 0, config, cancel, |_| true)
+            .map(|(text, _)| text)
+    }
+    
+    /// Generate, handing each decoded piece to `on_token` as it is produced
+    ///
+    /// Tokens are sampled with the chain `config` describes (see `sampling`).
+    /// `on_token` returns `false` to stop early; the text so far is returned.
+    /// The first `n_keep` prompt tokens are never evicted when the prompt is
+    /// cut to fit or the context shifts (see `context_window`).
+    pub fn generate_streaming(
+        &mut self,
+        prompt: &str,
+        n_keep: usize,
+        config: &LlmConfig,
+        cancel: &CancellationToken,
+        mut on_token: impl FnMut(&str) -> bool,
+    ) -> Result<(String, TokenUsage)> {
         // Create context for this generation
-        let context_params = LlamaContextParams::default()
-            .with_n_ctx(Some(std::num::NonZeroU32::new(self.n_ctx).unwrap()));
+        let n_batch = self.settings.batch_size();
+        let mut context_params = LlamaContextParams::default()
+            .with_n_ctx(Some(std::num::NonZeroU32::new(self.n_ctx).unwrap()))
+            .with_n_batch(n_batch)
+            .with_flash_attention(self.settings.flash_attention);
+        if let Some(threads) = self.settings.n_threads {
+            context_params = context_params
+                .with_n_threads(threads)
+                .with_n_threads_batch(threads);
+        }
         
-        let mut context = self.model.new_context(&self.backend, context_params)
+        let mut context = self.model.new_context(self.backend, context_params)
             .context("Failed to create context")?;
         
         // Tokenize prompt
-        let tokens = self.model
-            .str_to_token(prompt, llama_cpp_2::model::AddBos::Always)
+        let mut tokens = self.model
+            .str_to_token(prompt, AddBos::Always)
             .context("Failed to tokenize prompt")?;
         
         if tokens.is_empty() {
             return Err(anyhow::anyhow!("Tokenization produced no tokens"));
         }
         
-        println!("🔢 Prompt tokenized: {} tokens", tokens.len());
-        
-        // Create batch with size to fit all prompt tokens + some for generation
-        let batch_size = (tokens.len() + 512).max(1024);
-        let mut batch = LlamaBatch::new(batch_size, 1);
+        debug!("Prompt tokenized: {} tokens", tokens.len());
         
-        // Add tokens to batch
-        for (i, token) in tokens.iter().enumerate() {
-            // Mark last token as logits-generating
-            let is_last = i == tokens.len() - 1;
-            batch.add(*token, i as i32, &[0], is_last)
-                .context("Failed to add token to batch")?;
+        // Still too long (e.g. one huge message): cut from just after the
+        // system prompt
+        let budget = self.prompt_budget(config);
+        if tokens.len() > budget {
+            info!("Prompt cut from {} to {} tokens", tokens.len(), budget);
+            context_window::truncate_middle(&mut tokens, n_keep, budget);
         }
         
-        // Decode (process the prompt)
-        context.decode(&mut batch)
-            .context("Failed to decode batch")?;
+        // Decode (process the prompt) `n_batch` tokens at a time
+        let mut batch = LlamaBatch::new(n_batch as usize, 1);
+        for (chunk_index, chunk) in tokens.chunks(n_batch as usize).enumerate() {
+            batch.clear();
+            for (i, token) in chunk.iter().enumerate() {
+                let pos = chunk_index * n_batch as usize + i;
+                // Mark last token as logits-generating
+                let is_last = pos == tokens.len() - 1;
+                batch.add(*token, pos as i32, &[0], is_last)
+                    .context("Failed to add token to batch")?;
+            }
+            context.decode(&mut batch)
+                .context("Failed to decode batch")?;
+        }
         
-        println!("✅ Prompt decoded, starting generation...");
+        debug!("Prompt decoded, starting generation...");
         
         // Generate response
         let mut output = String::new();
-        let max_tokens = 512;
+        let max_tokens = config.max_tokens.max(1);
         let mut generated = 0;
+        let mut n_past = tokens.len();
         
-        // Create a greedy sampler for deterministic token selection
-        let mut sampler = LlamaSampler::greedy();
+        let seed = config.seed.unwrap_or_else(sampling::random_seed);
+        let mut sampler = sampling::build_sampler(config, &self.model, seed);
+        // Text that may begin a stop sequence is held back until it can't
+        let mut stops = StopFilter::new(&config.stop);
+        let mut caller_stopped = false;
+        // Logit bias and banned phrases, in this model's tokens
+        let bias = TokenBias::new(&config.logit_bias, &BannedWords::load().words, |text| {
+            self.model
+                .str_to_token(text, AddBos::Never)
+                .map(|tokens| tokens.into_iter().map(|token| token.0).collect())
+                .unwrap_or_default()
+        });
+        let mut output_tokens: Vec<i32> = Vec::new();
         
         while generated < max_tokens {
+            if cancel.is_cancelled() {
+                info!("Generation cancelled after {} tokens", generated);
+                break;
+            }
+            
             // Sample next token using the sampler
             // idx -1 means use the last token in the context
-            let new_token_id = sampler.sample(&context, -1);
+            let new_token_id = if bias.is_empty() {
+                sampler.sample(&context, -1)
+            } else {
+                sample_biased(&context, &mut sampler, &bias.next(&output_tokens))?
+            };
             
             // Check for EOS
             if self.model.is_eog_token(new_token_id) {
-                println!("🏁 Reached end of generation");
+                info!("Reached end of generation");
                 break;
             }
+            output_tokens.push(new_token_id.0);
             
             // Convert token to text
             if let Ok(piece) = self.model.token_to_str(new_token_id, Special::Tokenize) {
-                output.push_str(&piece);
+                let text = stops.push(&piece);
+                output.push_str(&text);
+                if !text.is_empty() && !on_token(&text) {
+                    info!("Generation stopped by caller");
+                    caller_stopped = true;
+                    break;
+                }
+                if stops.is_stopped() {
+                    info!("Reached a stop sequence");
+                    break;
+                }
             }
             
             // Progress logging every 50 tokens
             if generated % 50 == 0 {
-                println!("⏳ Generated {}/{} tokens...", generated, max_tokens);
+                debug!("Generated {}/{} tokens...", generated, max_tokens);
+            }
+            
+            // Context full: evict the older half after the system prompt and
+            // shift the rest down
+            if let Some(discard) = context_window::shift_amount(n_past, self.n_ctx as usize, n_keep) {
+                let (keep, end) = (n_keep as u32, (n_keep + discard) as u32);
+                context.clear_kv_cache_seq(Some(0), Some(keep), Some(end))
+                    .context("Failed to evict tokens from context")?;
+                context.kv_cache_seq_add(0, Some(end), Some(n_past as u32), -(discard as i32))
+                    .context("Failed to shift context")?;
+                n_past -= discard;
+                info!("Context shifted: evicted {} tokens", discard);
             }
             
             // Add token to context for next iteration
             batch.clear();
-            batch.add(new_token_id, generated as i32 + tokens.len() as i32, &[0], true)
+            batch.add(new_token_id, n_past as i32, &[0], true)
                 .context("Failed to add generated token")?;
             
             context.decode(&mut batch)
                 .context("Failed to decode generated token")?;
             
             generated += 1;
+            n_past += 1;
         }
         
-        println!("✅ Generated {} tokens ({} chars)", generated, output.len());
-        Ok(output.trim().to_string())
+        let held = stops.finish();
+        if !held.is_empty() && !caller_stopped {
+            output.push_str(&held);
+            on_token(&held);
+        }
+        
+        info!("Generated {} tokens ({} chars)", generated, output.len());
+        let usage = TokenUsage {
+            prompt_tokens: tokens.len() as u32,
+            completion_tokens: generated as u32,
+            hit_limit: generated >= max_tokens,
+        };
+        Ok((output.trim().to_string(), usage))
     }
     
     pub fn is_ready(&self) -> bool {
         true // If we got here, model is loaded
     }
 }
+
+/// Sample the next token with `biases` added to the logits first
+fn sample_biased(context: &LlamaContext, sampler: &mut LlamaSampler, biases: &HashMap<i32, f32>) -> Result<LlamaToken> {
+    let mut candidates = context.token_data_array_ith(-1);
+    for candidate in candidates.data.iter_mut() {
+        if let Some(bias) = biases.get(&candidate.id().0) {
+            candidate.set_logit(candidate.logit() + bias);
+        }
+    }
+    candidates.apply_sampler(sampler);
+    let token = candidates.selected_token().context("The sampler picked no token")?;
+    sampler.accept(token);
+    Ok(token)
+}
@@ -0,0 +1,181 @@
+// Logging Module - tracing setup, the log file and the in-app log viewer
+//
+// Everything logs through `tracing`. Events go to the console as before and,
+// as JSON lines, to `logs/auranexus.log.YYYY-MM-DD` in the app data dir; a
+// new file is started each day and the last week of files is kept.
+// `get_recent_logs` reads the newest entries back so users can look at (and
+// attach) diagnostics when reporting a problem. `RUST_LOG` overrides the
+// default `info` level, e.g. `RUST_LOG=auranexus=debug`.
+
+use crate::paths;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::Level;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+const LOG_FILE_PREFIX: &str = "auranexus.log";
+/// Daily files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+/// Most entries `get_recent_logs` returns
+const MAX_RECENT: usize = 5000;
+
+pub fn log_dir() -> PathBuf {
+    paths::app_data_dir().join("logs")
+}
+
+/// Install the global subscriber; call once, after `paths::init`
+///
+/// If the log directory can't be created, logging carries on to the console.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let console = fmt::layer().with_target(false);
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir());
+    let registry = tracing_subscriber::registry().with(filter).with(console);
+    match file {
+        Ok(file) => {
+            let json = fmt::layer()
+                .json()
+                .with_current_span(false)
+                .with_span_list(false)
+                .with_ansi(false)
+                .with_writer(file);
+            registry.with(json).init();
+            tracing::info!("Logging to {}", log_dir().display());
+        }
+        Err(e) => {
+            registry.init();
+            tracing::warn!("Log file unavailable, logging to the console only: {}", e);
+        }
+    }
+}
+
+/// One line of the log file
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    /// Module that logged it, e.g. `auranexus::backend`
+    pub target: String,
+    pub message: String,
+    /// Any other fields recorded with the event
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let mut value: Value = serde_json::from_str(line).ok()?;
+    let mut fields = match value.get_mut("fields").map(Value::take) {
+        Some(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    let message = match fields.remove("message") {
+        Some(Value::String(message)) => message,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let text = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+    Some(LogEntry {
+        timestamp: text("timestamp"),
+        level: text("level"),
+        target: text("target"),
+        message,
+        fields,
+    })
+}
+
+/// The last `limit` entries at `min_level` or more severe in `dir`'s log
+/// files, oldest first
+pub fn recent_in(dir: &Path, min_level: Level, limit: usize) -> Result<Vec<LogEntry>> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(LOG_FILE_PREFIX))
+            .map(|entry| entry.path())
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Can't list {}", dir.display())),
+    };
+    // The date suffix sorts oldest to newest
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files.iter().rev() {
+        let text = std::fs::read_to_string(file).with_context(|| format!("Can't read {}", file.display()))?;
+        let matching = text
+            .lines()
+            .rev()
+            .filter_map(parse_line)
+            .filter(|entry| Level::from_str(&entry.level).map_or(true, |level| level <= min_level));
+        for entry in matching {
+            entries.push(entry);
+            if entries.len() == limit {
+                entries.reverse();
+                return Ok(entries);
+            }
+        }
+    }
+    entries.reverse();
+    Ok(entries)
+}
+
+/// The newest log entries for the diagnostics view
+///
+/// `level` ("error", "warn", "info", "debug" or "trace") is the least severe
+/// level included, default "info"; `limit` defaults to 200.
+#[tauri::command]
+pub async fn get_recent_logs(level: Option<String>, limit: Option<usize>) -> Result<Vec<LogEntry>, String> {
+    let min_level = match level.as_deref() {
+        Some(level) => Level::from_str(level).map_err(|_| format!("Unknown log level {:?}", level))?,
+        None => Level::INFO,
+    };
+    let limit = limit.unwrap_or(200).clamp(1, MAX_RECENT);
+    tauri::async_runtime::spawn_blocking(move || recent_in(&log_dir(), min_level, limit))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_in_filters_and_orders() {
+        let dir = std::env::temp_dir().join(format!("auranexus-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let line = |time: &str, level: &str, message: &str| {
+            format!(
+                r#"{{"timestamp":"{}","level":"{}","fields":{{"message":"{}","n":1}},"target":"auranexus::test"}}"#,
+                time, level, message
+            )
+        };
+        let older = [line("1", "INFO", "started"), line("2", "WARN", "slow disk")].join("\n");
+        let newer = [line("3", "DEBUG", "detail"), "not json".to_string(), line("4", "ERROR", "crashed")].join("\n");
+        std::fs::write(dir.join("auranexus.log.2026-01-01"), older).unwrap();
+        std::fs::write(dir.join("auranexus.log.2026-01-02"), newer).unwrap();
+
+        let all = recent_in(&dir, Level::TRACE, 10).unwrap();
+        let messages: Vec<&str> = all.iter().map(|entry| entry.message.as_str()).collect();
+        assert_eq!(messages, ["started", "slow disk", "detail", "crashed"]);
+        assert_eq!(all[0].fields["n"], 1);
+        assert_eq!(all[0].target, "auranexus::test");
+
+        let warnings = recent_in(&dir, Level::WARN, 10).unwrap();
+        assert_eq!(warnings.iter().map(|entry| entry.level.as_str()).collect::<Vec<_>>(), ["WARN", "ERROR"]);
+        let last_two = recent_in(&dir, Level::INFO, 2).unwrap();
+        assert_eq!(last_two.iter().map(|entry| entry.timestamp.as_str()).collect::<Vec<_>>(), ["2", "4"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(recent_in(&dir, Level::INFO, 10).unwrap().is_empty());
+    }
+}
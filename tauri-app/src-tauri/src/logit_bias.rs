@@ -0,0 +1,185 @@
+// Logit Bias Module - Encourage, discourage or ban tokens and phrases
+//
+// `LlmConfig::logit_bias` maps a token id ("15043") or a piece of text to a
+// bias added to its logit before sampling; text is tokenized with the chat
+// model and each of its tokens gets the bias. A bias of -100 or less bans the
+// token outright. The banned phrases in `banned_words.json` apply to every
+// reply: a phrase that is a single token is never sampled, and for a longer
+// one its last token is ruled out right after the tokens before it, so the
+// model has to word it differently. Both are applied in the native sampling
+// loop; other backends have their own vocabularies and only get the entries
+// given as token ids.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+/// Largest bias either way; at or below -MAX_BIAS a token is banned
+pub const MAX_BIAS: f32 = 100.0;
+
+/// Limits on the banned phrases list
+const MAX_BANNED_WORDS: usize = 200;
+const MAX_BANNED_CHARS: usize = 100;
+
+/// Phrases never to be generated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BannedWords {
+    pub words: Vec<String>,
+}
+
+impl BannedWords {
+    const FILE: &'static str = "banned_words.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+}
+
+/// The token-id entries of a bias map, for backends with their own tokenizer
+pub fn token_ids(bias: &HashMap<String, f32>) -> HashMap<i32, f32> {
+    bias.iter()
+        .filter_map(|(key, bias)| Some((key.trim().parse().ok()?, bias.clamp(-MAX_BIAS, MAX_BIAS))))
+        .collect()
+}
+
+/// Per-step logit adjustments for one generation
+#[derive(Debug, Default)]
+pub struct TokenBias {
+    /// Added at every step
+    fixed: HashMap<i32, f32>,
+    /// Multi-token banned phrases; the last token is banned after the rest
+    sequences: Vec<Vec<i32>>,
+}
+
+impl TokenBias {
+    /// Resolve `bias` and `banned` with the model's `tokenize`
+    pub fn new(bias: &HashMap<String, f32>, banned: &[String], tokenize: impl Fn(&str) -> Vec<i32>) -> Self {
+        let mut token_bias = Self::default();
+        for (key, &value) in bias {
+            let value = if value <= -MAX_BIAS { f32::NEG_INFINITY } else { value.min(MAX_BIAS) };
+            let tokens = match key.trim().parse::<i32>() {
+                Ok(id) => vec![id],
+                Err(_) => tokenize(key),
+            };
+            for token in tokens {
+                token_bias.fixed.insert(token, value);
+            }
+        }
+        for phrase in banned.iter().map(|phrase| phrase.trim()).filter(|phrase| !phrase.is_empty()) {
+            // Mid-sentence the phrase follows a space, which most vocabularies
+            // fold into its first token
+            for variant in [phrase.to_string(), format!(" {}", phrase)] {
+                match tokenize(&variant).as_slice() {
+                    [] => {}
+                    [single] => {
+                        token_bias.fixed.insert(*single, f32::NEG_INFINITY);
+                    }
+                    tokens => token_bias.sequences.push(tokens.to_vec()),
+                }
+            }
+        }
+        token_bias
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.sequences.is_empty()
+    }
+
+    /// Biases for the next token, given the tokens generated so far
+    pub fn next(&self, generated: &[i32]) -> HashMap<i32, f32> {
+        let mut biases = self.fixed.clone();
+        for sequence in &self.sequences {
+            let (last, prefix) = sequence.split_last().expect("sequences have at least two tokens");
+            if generated.ends_with(prefix) {
+                biases.insert(*last, f32::NEG_INFINITY);
+            }
+        }
+        biases
+    }
+}
+
+/// Current banned phrases
+#[tauri::command]
+pub async fn get_banned_words() -> Result<Vec<String>, String> {
+    Ok(BannedWords::load().words)
+}
+
+/// Replace the banned phrases
+#[tauri::command]
+pub async fn set_banned_words(words: Vec<String>) -> Result<Vec<String>, String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for word in words.iter().map(|word| word.trim()).filter(|word| !word.is_empty()) {
+        if word.chars().count() > MAX_BANNED_CHARS {
+            return Err(format!("Banned phrases are at most {} characters", MAX_BANNED_CHARS));
+        }
+        if !cleaned.iter().any(|existing| existing == word) {
+            cleaned.push(word.to_string());
+        }
+    }
+    if cleaned.len() > MAX_BANNED_WORDS {
+        return Err(format!("At most {} banned phrases", MAX_BANNED_WORDS));
+    }
+    BannedWords { words: cleaned.clone() }
+        .save()
+        .map_err(|e| e.to_string())?;
+    info!("{} banned phrase(s)", cleaned.len());
+    Ok(cleaned)
+}
+
+/// Check a bias map from the frontend
+pub fn validate(bias: &HashMap<String, f32>) -> Result<()> {
+    if let Some(key) = bias.keys().find(|key| key.trim().is_empty()) {
+        return Err(anyhow!("Empty logit bias key {:?}", key));
+    }
+    if let Some((key, _)) = bias.iter().find(|(_, value)| !value.is_finite() || value.abs() > MAX_BIAS) {
+        return Err(anyhow!("Logit bias for {:?} must be between -{} and {}", key, MAX_BIAS, MAX_BIAS));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(text: &str) -> Vec<i32> {
+        match text {
+            "As an AI" => vec![0, 2, 3],
+            " As an AI" => vec![1, 2, 3],
+            "Hello" => vec![6],
+            " hello" => vec![7],
+            "hello" => vec![8, 9],
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_bias_and_banned_phrases() {
+        let bias = HashMap::from([("42".to_string(), 5.0), ("Hello".to_string(), -150.0)]);
+        let banned = vec!["As an AI".to_string(), "hello".to_string()];
+        let token_bias = TokenBias::new(&bias, &banned, tokenize);
+
+        let start = token_bias.next(&[]);
+        assert_eq!(start[&42], 5.0);
+        assert_eq!(start[&6], f32::NEG_INFINITY);
+        // " hello" is one token, so it is banned everywhere
+        assert_eq!(start[&7], f32::NEG_INFINITY);
+        // "As" and " an" are fine on their own...
+        assert!(!start.contains_key(&0) && !start.contains_key(&2));
+        // ...but " AI" can't follow "As an"
+        assert_eq!(token_bias.next(&[9, 0, 2])[&3], f32::NEG_INFINITY);
+        assert_eq!(token_bias.next(&[1, 2])[&3], f32::NEG_INFINITY);
+        assert!(!token_bias.next(&[2]).contains_key(&3));
+        assert_eq!(token_bias.next(&[8])[&9], f32::NEG_INFINITY);
+
+        assert_eq!(token_ids(&bias), HashMap::from([(42, 5.0)]));
+        assert!(validate(&bias).is_err());
+        assert!(validate(&HashMap::from([("42".to_string(), -100.0)])).is_ok());
+        assert!(TokenBias::new(&HashMap::new(), &[], tokenize).is_empty());
+    }
+}
@@ -5,15 +5,118 @@ mod llm;
 mod memory;
 mod models;
 mod memory_store;  // Translated from mem0
+mod memory_sqlite; // SQLite MemoryBackend (memories.db)
+mod memory_history; // Versions of updated/deleted memories, restorable
+mod metadata_filter; // mem0-style filter expressions (ranges, in, AND/OR/NOT)
 mod text_chunker;  // Translated from llama_index
+mod sentence_segmenter; // Sentence boundaries for chunking
 mod rag_example;   // Example usage of translated modules
-// mod python_bridge;  // Not needed - using HTTP instead
+mod presets;       // Shareable persona/sampling presets
+mod sampling_presets; // Built-in and saved sampling presets per conversation
+mod generation;    // In-flight generation tracking (barge-in)
+mod http_backend;  // Streaming client for llm_server.py
+mod history_store; // Persisted history + in-flight response journal
+mod paths;         // App data directory
+mod migration;     // One-time move of data from legacy locations
+mod settings;      // App-wide settings (settings.toml)
+mod clock;         // Current time, user timezone, relative times
+mod embeddings;    // Text embedders for similarity search
+mod embeddings_server;  // Local OpenAI-compatible /v1/embeddings endpoint
+mod mcp_server;       // Optional local MCP server over the memory store
+mod compaction;    // Topic-clustered history compaction
+mod downloader;    // Streamed HTTP downloads
+mod setup_wizard;  // First-run onboarding flow
+mod session;       // user/agent/run ids for memory scoping
+mod memory_policy; // Per-persona memory isolation
+mod ingest;        // Parallel document ingestion queue
+mod chunking_settings; // Chunking settings and re-chunking stale documents
+mod trash;         // Soft deletes with restore and timed purge
+mod entities;      // People, places and projects mentioned in conversations
+mod persona_stats; // Per-persona ratings, regenerations and abandonment
+mod html_export;   // Shareable HTML transcripts
+mod conversation_export; // Markdown/JSON transcripts
+mod digest;        // Scheduled weekly digest
+mod custom_instructions; // User-pinned system prompt additions
+mod intent;        // Companion/Youniverse intent detection
+mod modes;         // User-defined modes (settings.toml)
+mod bridge_manifest;  // Python bridge operation mapping (bridge.toml)
+mod python_bridge;    // Python backend in a supervised JSON-RPC worker process
+mod python_env;       // App-managed venv with the Python backend's requirements
+mod vector_index;     // HNSW index for memory search
+mod backend;          // LlmBackend trait + backend selection
+mod openai_backend;   // OpenAI-compatible remote API client
+mod prompt_builder;   // Chat-template prompt formatting for the native backend
+mod tokenizer;        // Vocab-only GGUF loading for token counts
+mod capabilities;     // Chat/embedding/tool support probed from the GGUF header
+mod system_probe;     // RAM/VRAM detection and GPU layer auto-tuning
+mod inference_settings; // Context size, threads, batch, mmap/mlock, flash attention
+mod sampling;         // Native sampler chain from LlmConfig
+mod stop_sequences;   // Stop strings matched across streamed tokens
+mod slash_commands;   // /mode, /model, /forget, /roll, /ingest typed in chat
+mod structured;       // JSON replies constrained and validated against a schema
+mod logit_bias;       // Per-token logit bias and the banned phrases list
+mod tools;            // Tools the model can call before replying
+mod web_search;       // SearxNG / DuckDuckGo / Brave web search tool
+mod file_read;        // Confirmed, allow-listed local file reading tool
+mod content;          // Typed message content (text, images, tool results, files)
+mod context_window;   // Prompt fitting and context shift for the native context
+mod prompt_budget;    // Token-budgeted history for every backend
+mod task_presets;     // Sampling presets for titles, summaries, extraction, scoring
+mod rolling_summary;  // Running summary of older turns for the prompt
+mod share;            // Encrypted .aurachat conversation sharing
+mod incognito;        // Sessions that persist nothing
+mod privacy;          // Conversations kept out of memory and the Nexus Core log
+mod redaction;        // PII scrubbed from messages before they are stored
+mod encryption;       // Optional AES-GCM encryption at rest (keychain-held key)
+mod user_data;        // Full data export (zip) and per-user data wipe
+mod importer;         // ChatGPT/Claude export import into conversations and memory
+mod prompt_trace;     // Retrieved context and per-block prompt token trace
+mod self_test;        // End-to-end subsystem checks with toy data
+mod query_fanout;     // Reworded queries and rank fusion for retrieval
+mod extraction_filter; // Cheap gate deciding which turns get memory extraction
+mod fact_extraction;  // Background LLM fact extraction (ADD/UPDATE/DELETE)
+mod consolidation;    // Merging duplicate and superseding contradicted facts
+mod tts;              // Read-aloud while responses stream
+mod tool_calls;       // Tool invocation records for history/transcripts
+mod code_blocks;      // Fenced code extraction for copy/save buttons
+mod conversations;    // Multiple conversations (SessionManager)
+mod saved_searches;   // Named (and watched) memory searches
+mod model_download;   // Resumable, checksum-verified model downloads
+mod errors;           // AppError: error codes and recovery hints for the frontend
+mod logging;          // tracing setup, rotating log file, get_recent_logs
 
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 use std::sync::Arc;
 use parking_lot::Mutex;
-// use python_bridge::{PythonBridge, LlmConfig, ConversationEntry, SearchResult};
+use backend::GenerationRequest;
+use conversations::SessionManager;
+use errors::AppError;
+use generation::GenerationTracker;
+use http_backend::{GenerationStats, HttpBackend};
+use history_store::{HistoryStore, InflightWriter};
+use ingest::IngestQueue;
+use memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use prompt_budget::PromptBudget;
+use session::SessionIds;
+use content::ContentPart;
+use tool_calls::ToolInvocation;
+use tts::TtsQueue;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Completion state of a history entry
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum EntryStatus {
+    #[default]
+    Complete,
+    /// Cut short by a newer user message (barge-in)
+    Interrupted,
+    /// Recovered from the in-flight journal after a crash, or cut off by a
+    /// backend timeout
+    Incomplete,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct ConversationEntry {
@@ -21,20 +124,42 @@ struct ConversationEntry {
     content: String,
     timestamp: String,
     quality_score: Option<f32>,
+    #[serde(default)]
+    status: EntryStatus,
+    /// Tools the assistant ran to produce this entry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolInvocation>,
+    /// Typed content (images, tool results, attachments...); `content` is
+    /// its plain-text rendering. Empty for plain text messages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parts: Vec<ContentPart>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 struct LlmConfig {
     temperature: f32,
     top_p: f32,
     top_k: i32,
     min_p: Option<f32>,
+    /// Multiplicative penalty on recently used tokens (1.0 = off)
+    repeat_penalty: Option<f32>,
     frequency_penalty: Option<f32>,
     presence_penalty: Option<f32>,
     dry_multiplier: Option<f32>,
     xtc_probability: Option<f32>,
     dynatemp_range: Option<f32>,
     max_tokens: i32,
+    /// Generation ends before any of these strings (which are left out)
+    stop: Vec<String>,
+    /// Fixed sampling seed: the same prompt, settings and seed give the same
+    /// reply (a random one when unset)
+    seed: Option<u32>,
+    /// JSON Schema the reply must match (see `structured`); unset for free text
+    json_schema: Option<serde_json::Value>,
+    /// Bias added to tokens' logits, by token id ("15043") or text; -100
+    /// bans (see `logit_bias`)
+    logit_bias: HashMap<String, f32>,
 }
 
 impl Default for LlmConfig {
@@ -44,12 +169,17 @@ impl Default for LlmConfig {
             top_p: 0.95,
             top_k: 40,
             min_p: Some(0.05),
+            repeat_penalty: None,
             frequency_penalty: Some(0.2),
             presence_penalty: Some(0.1),
             dry_multiplier: Some(0.7),
             xtc_probability: None,
             dynatemp_range: None,
             max_tokens: 512,
+            stop: Vec::new(),
+            seed: None,
+            json_schema: None,
+            logit_bias: HashMap::new(),
         }
     }
 }
@@ -60,20 +190,51 @@ struct ChatMessage {
     content: String,
     timestamp: String,
     quality_score: Option<f32>,
+    #[serde(default)]
+    status: EntryStatus,
+    #[serde(default)]
+    parts: Vec<ContentPart>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ChatResponse {
+    /// Matches the `generation_id` of this reply's `chat-token` events
+    generation_id: String,
     agent: String,
     message: String,
     timestamp: String,
     mode: String,
+    /// True if a newer message cut this response short
+    interrupted: bool,
+    /// Token counts, timing, model and truncation details
+    stats: GenerationStats,
+    /// Tools run while generating, with their (truncated) output
+    tool_calls: Vec<ToolInvocation>,
+    /// Memory id of the stored reply (for `extract_code_blocks`)
+    message_id: Option<String>,
+    /// Sent in an incognito session: nothing about it was saved
+    incognito: bool,
+    /// Privacy mode: the turn was kept out of memory and the Nexus Core log
+    private: bool,
+    /// Sampling seed used; pass it back as `config.seed` to replay the reply
+    seed: u32,
+    /// Set when this is the answer to a slash command, which isn't recorded
+    command: Option<String>,
+}
+
+/// Payload of `chat-token` events
+#[derive(Debug, Clone, Serialize)]
+struct ChatToken {
+    generation_id: String,
+    token: String,
 }
 
 #[derive(Debug, Clone)]
 enum AppMode {
     Companion,
     Youniverse,
+    /// A user-defined mode, by id (see `modes`)
+    Custom(String),
 }
 
 impl AppMode {
@@ -81,10 +242,42 @@ impl AppMode {
         match self {
             AppMode::Companion => "companion".to_string(),
             AppMode::Youniverse => "youniverse".to_string(),
+            AppMode::Custom(id) => id.clone(),
         }
     }
     
+    /// A built-in mode, or a custom one that still exists
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "companion" => Some(AppMode::Companion),
+            "youniverse" => Some(AppMode::Youniverse),
+            _ => modes::ModeRegistry::load()
+                .get(name)
+                .map(|mode| AppMode::Custom(mode.id.clone())),
+        }
+    }
+    
+    fn custom(&self) -> Option<modes::CustomMode> {
+        match self {
+            AppMode::Custom(id) => modes::ModeRegistry::load().get(id).cloned(),
+            _ => None,
+        }
+    }
+    
+    /// The mode's system prompt, as edited by the user
     fn system_prompt(&self) -> String {
+        match self.custom() {
+            Some(mode) => mode.system_prompt,
+            None => settings::AppSettings::load()
+                .prompts
+                .for_mode(self)
+                .map(str::to_string)
+                .unwrap_or_else(|| self.default_system_prompt()),
+        }
+    }
+    
+    /// The system prompt the mode ships with
+    fn default_system_prompt(&self) -> String {
         match self {
             AppMode::Companion => {
                 "You are Aura, a helpful AI assistant. Keep responses clear, concise, and helpful. \
@@ -95,184 +288,779 @@ impl AppMode {
                 Stay consistent with the story and respond in the same language as the user. \
                 Keep responses focused and creative.".to_string()
             }
+            // A deleted custom mode behaves like Companion
+            AppMode::Custom(_) => AppMode::Companion.default_system_prompt(),
+        }
+    }
+    
+    /// Sampling for the mode's replies: a custom mode's own, else from
+    /// settings.toml
+    fn sampling_config(&self) -> LlmConfig {
+        match self.custom() {
+            Some(mode) => mode.sampling,
+            None => settings::AppSettings::load().sampling.for_mode(self).clone(),
+        }
+    }
+    
+    /// Built-in sampling, used when settings.toml doesn't set it
+    fn default_sampling(&self) -> LlmConfig {
+        match self {
+            AppMode::Companion => LlmConfig {
+                temperature: 0.7,
+                top_p: 0.9,
+                top_k: 50,
+                min_p: Some(0.05),
+                frequency_penalty: Some(0.3),
+                presence_penalty: Some(0.1),
+                dry_multiplier: Some(0.8),
+                max_tokens: 256,  // Keep responses concise
+                ..Default::default()
+            },
+            AppMode::Youniverse => LlmConfig {
+                temperature: 0.75,  // Creative but not too wild
+                top_p: 0.9,
+                top_k: 60,
+                min_p: Some(0.03),
+                frequency_penalty: Some(0.2),
+                presence_penalty: Some(0.15),
+                dry_multiplier: Some(0.6),
+                max_tokens: 384,  // Longer for storytelling
+                // Role-play models otherwise go on to write the user's turn
+                stop: vec!["\nUser:".to_string(), "\nYou:".to_string()],
+                ..Default::default()
+            },
+            AppMode::Custom(_) => AppMode::Companion.default_sampling(),
         }
     }
 }
 
-// Application state (no Python bridge needed - using HTTP)
+// Application state
 struct AppState {
     conversation_history: Arc<Mutex<Vec<ConversationEntry>>>,
     current_mode: Arc<Mutex<AppMode>>,
+    generation: Arc<GenerationTracker>,
+    history_store: Arc<HistoryStore>,
+    memory_store: Arc<Mutex<MemoryStore>>,
+    session: Arc<Mutex<SessionIds>>,
+    /// All conversations; the active one's history/mode/session are above
+    conversations: Arc<Mutex<SessionManager>>,
+    ingest: Arc<IngestQueue>,
+    /// Backend used for chat, selected on the first message
+    llm: backend::SharedBackend,
+    tts: Arc<TtsQueue>,
 }
 
 // Send message using Python backend with advanced sampling
+//
+// With `stream: true` each token is also emitted as a `chat-token` event as it
+// arrives, followed by a `chat-complete` event carrying the final response.
+// `max_tokens` caps this reply's length in place of the sampling settings'.
+// A message starting with a slash command (`/help` lists them) runs it first;
+// see `slash_commands`.
 #[tauri::command]
 async fn send_chat_message(
     message: String,
+    stream: Option<bool>,
+    max_tokens: Option<i32>,
+    window: tauri::Window,
     state: tauri::State<'_, AppState>,
-) -> Result<ChatResponse, String> {
-    println!("📩 Received message");
+) -> Result<ChatResponse, AppError> {
+    let stream = stream.unwrap_or(false);
+    info!("Received message");
+    check_max_tokens(max_tokens)?;
     
-    // Get current mode and its system prompt
+    // Slash commands answer by themselves or rewrite the message to generate
+    // a reply to
+    let command = slash_commands::parse(&message).map(|invocation| invocation.name.to_lowercase());
+    let message = match slash_commands::dispatch(&state, &message) {
+        None => slash_commands::unescape(&message).to_string(),
+        Some(Ok(slash_commands::Outcome::Generate(rewritten))) => rewritten,
+        Some(Ok(slash_commands::Outcome::Reply(reply))) => {
+            return Ok(command_reply(command, reply, stream, &window, &state));
+        }
+        Some(Err(e)) => {
+            return Err(AppError::classify(&e).unwrap_or_else(|| AppError::InvalidRequest(format!("{:#}", e))))
+        }
+    };
+    
+    // Barge-in: a new message cancels any generation still streaming and
+    // keeps its partial output (marked interrupted) in the context
+    if let Some(interrupted) = state.generation.interrupt() {
+        info!("Interrupted in-flight generation ({} chars kept)", interrupted.partial.len());
+        let partial = Some(interrupted.partial)
+            .filter(|text| !text.is_empty())
+            .map(|text| (text, EntryStatus::Interrupted));
+        record_turn(&state, interrupted.user_message, partial, Vec::new(), false);
+        state.history_store.clear_inflight(&interrupted.generation_id);
+    }
+    
+    // The message may clearly belong to the other mode: suggest a switch, or
+    // make it before generating if the user opted in
+    let switch_settings = intent::ModeSwitchSettings::load();
+    if switch_settings.enabled {
+        let current = state.current_mode.lock().clone();
+        if let Some(suggestion) = intent::detect(&message, &current) {
+            let target = AppMode::from_name(&suggestion.mode).unwrap_or(current);
+            if switch_settings.auto_switch {
+                apply_mode_switch(&state, target, switch_settings.include_history);
+                let _ = window.emit("mode-switched", &suggestion);
+            } else {
+                let _ = window.emit("mode-suggestion", &suggestion);
+            }
+        }
+    }
+    
+    respond(message, stream, None, max_tokens, false, window, &state).await
+}
+
+/// The answer to a slash command, delivered like a reply but kept out of
+/// the history
+fn command_reply(
+    command: Option<String>,
+    reply: String,
+    stream: bool,
+    window: &tauri::Window,
+    state: &AppState,
+) -> ChatResponse {
+    let response = ChatResponse {
+        generation_id: uuid::Uuid::new_v4().to_string(),
+        agent: "aura".to_string(),
+        message: reply,
+        timestamp: clock::timestamp(),
+        mode: state.current_mode.lock().to_string(),
+        interrupted: false,
+        stats: GenerationStats {
+            backend: "command".to_string(),
+            ..Default::default()
+        },
+        tool_calls: Vec::new(),
+        message_id: None,
+        incognito: incognito::is_active(state),
+        private: privacy::is_private(state),
+        seed: 0,
+        command,
+    };
+    if stream {
+        let _ = window.emit("chat-complete", &response);
+    }
+    response
+}
+
+/// A reply length the frontend asked for must be one the backends accept
+fn check_max_tokens(max_tokens: Option<i32>) -> Result<(), AppError> {
+    match max_tokens {
+        Some(n) if !(1..=32768).contains(&n) => Err("max_tokens must be between 1 and 32768".into()),
+        _ => Ok(()),
+    }
+}
+
+/// Generate and record the reply to `message`, with `config` in place of the
+/// conversation's sampling if given and `max_tokens` in place of its length
+///
+/// `rerun` is set when `message` was answered before (regeneration), so it
+/// isn't indexed or mined for facts a second time.
+async fn respond(
+    message: String,
+    stream: bool,
+    config: Option<LlmConfig>,
+    max_tokens: Option<i32>,
+    rerun: bool,
+    window: tauri::Window,
+    state: &AppState,
+) -> Result<ChatResponse, AppError> {
+    // Incognito turns are kept in memory only; private ones stay out of the
+    // memory store
+    let incognito = incognito::is_active(state);
+    let private = privacy::is_private(state);
+    
+    // Get current mode and its system prompt (plus the user's custom instructions)
     let (mode, system_prompt) = {
         let current_mode = state.current_mode.lock();
-        (current_mode.clone(), current_mode.system_prompt())
+        (current_mode.clone(), custom_instructions::apply_to(&current_mode.system_prompt()))
     };
     
-    // Get conversation history
-    let history = {
-        state.conversation_history.lock().clone()
+    // The conversation's own sampling settings, else the mode's
+    let mut config = config.unwrap_or_else(|| {
+        state
+            .conversations
+            .lock()
+            .active_config()
+            .unwrap_or_else(|| mode.sampling_config())
+    });
+    if let Some(max_tokens) = max_tokens {
+        config.max_tokens = max_tokens;
+    }
+    // Every backend gets an explicit seed so the reply can be replayed
+    let seed = *config.seed.get_or_insert_with(sampling::random_seed);
+    
+    // Relevant memories and document chunks join the system prompt; the
+    // model first rewords the message so differently phrased notes are found
+    let variants = settings::AppSettings::load().retrieval.variant_count();
+    let queries = {
+        let query = message.clone();
+        let llm = state.llm.clone();
+        tauri::async_runtime::spawn_blocking(move || query_fanout::queries(&llm, &query, variants))
+            .await
+            .unwrap_or_else(|_| vec![message.clone()])
     };
+    let retrieved = prompt_trace::retrieve(&state, &queries);
+    // "When did we last talk about X?" is answered from the entity index
+    let base_prompt = entities::with_last_mention(&system_prompt, &message);
+    // A tool the model asks for (e.g. a web search) runs first; its output
+    // is given as sources to cite
+    let tool_run = tools::run_before_reply(state, &window, &mode, &message).await;
     
-    // Get appropriate LLM config for mode
-    let config = match mode {
-        AppMode::Companion => LlmConfig {
-            temperature: 0.7,
-            top_p: 0.9,
-            top_k: 50,
-            min_p: Some(0.05),
-            frequency_penalty: Some(0.3),
-            presence_penalty: Some(0.1),
-            dry_multiplier: Some(0.8),
-            max_tokens: 256,  // Keep responses concise
-            ..Default::default()
-        },
-        AppMode::Youniverse => LlmConfig {
-            temperature: 0.75,  // Creative but not too wild
-            top_p: 0.9,
-            top_k: 60,
-            min_p: Some(0.03),
-            frequency_penalty: Some(0.2),
-            presence_penalty: Some(0.15),
-            dry_multiplier: Some(0.6),
-            max_tokens: 384,  // Longer for storytelling
-            ..Default::default()
-        },
+    // The running summary stands in for older turns and topic summaries for
+    // the oldest of the rest; of what's left, as much recent history as fits
+    // beside the system prompt, the message and the reply, by token count
+    let run_id = state.session.lock().run_id.clone();
+    let summary = rolling_summary::load(&run_id);
+    let compacted = compaction::load(&run_id);
+    let (system_prompt, history, trace) = {
+        let history = state.conversation_history.lock();
+        let (summary, recent) = rolling_summary::apply(summary.as_ref(), &history);
+        let recent = compaction::apply(compacted.as_ref(), recent);
+        let system_prompt = prompt_trace::with_retrieved(&base_prompt, &retrieved);
+        let system_prompt = tools::with_output(&system_prompt, tool_run.as_ref());
+        let system_prompt = rolling_summary::with_summary(&system_prompt, summary);
+        let sent = PromptBudget::new(inference_settings::context_tokens(), &config)
+            .fit_history(&recent, &system_prompt, &message)
+            .to_vec();
+        let trace = prompt_trace::PromptTrace::build(&prompt_trace::PromptParts {
+            system_prompt: &base_prompt,
+            summary,
+            retrieved: &retrieved,
+            history: &recent,
+            history_sent: sent.len(),
+            message: &message,
+        });
+        (system_prompt, sent, trace)
     };
     
-    // Generate response using HTTP call to Python LLM server
-    let response_text = {
-        // Prepare request body
-        let request_body = serde_json::json!({
-            "prompt": message,
-            "system_prompt": system_prompt,
-            "conversation_history": history.iter().map(|entry| {
-                serde_json::json!({
-                    "role": entry.role,
-                    "content": entry.content,
-                    "timestamp": entry.timestamp
+    // Stream the response from the active backend, accumulating the partial
+    // text so a barge-in can pick it up and journaling it to disk so a crash
+    // mid-response doesn't lose it
+    let handle = state.generation.begin(message.clone());
+    prompt_trace::record(handle.id(), trace);
+    // With read-aloud on, sentences are spoken while later ones generate
+    let mut speaker = state.tts.begin(window.clone(), handle.id());
+    let result = {
+        let handle = handle.clone();
+        let history_store = state.history_store.clone();
+        let llm = state.llm.clone();
+        let window = window.clone();
+        let prompt = message.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut journal = (!incognito)
+                .then(|| InflightWriter::new(&history_store, handle.id(), handle.user_message()));
+            if let Some(journal) = journal.as_mut() {
+                journal.flush();
+            }
+            let request = GenerationRequest {
+                prompt: &prompt,
+                system_prompt: &system_prompt,
+                history: &history,
+                config: &config,
+            };
+            
+            // Chosen on first use from the backend settings (`auto`: LLM
+            // server, Python subprocess, then native)
+            let mut llm = llm.blocking_lock();
+            let active = llm.get_or_insert_with(|| {
+                backend::select_backend(&|status| {
+                    let _ = window.emit("python-bridge-status", status);
                 })
-            }).collect::<Vec<_>>(),
-            "temperature": config.temperature,
-            "top_p": config.top_p,
-            "top_k": config.top_k,
-            "max_tokens": config.max_tokens,
-        });
-        
-        // Call LLM server
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post("http://localhost:5555/generate")
-            .json(&request_body)
-            .send()
-            .map_err(|e| format!("Failed to connect to LLM server: {}. Is llm_server.py running?", e))?;
-        
-        if !response.status().is_success() {
-            return Err(format!("LLM server returned error: {}", response.status()).into());
+            });
+            let result = active.generate(&request, &handle.cancellation_token(), &mut |token| {
+                handle.push_token(token);
+                if let Some(journal) = journal.as_mut() {
+                    journal.push_token(token);
+                }
+                if let Some(speaker) = speaker.as_mut() {
+                    speaker.push_token(token);
+                }
+                if stream {
+                    let _ = window.emit("chat-token", ChatToken {
+                        generation_id: handle.id().to_string(),
+                        token: token.to_string(),
+                    });
+                }
+                !handle.is_cancelled()
+            });
+            if let Some(speaker) = speaker.filter(|_| result.is_ok() && !handle.is_cancelled()) {
+                speaker.finish();
+            }
+            result
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Generation task failed: {}", e)))?
+    };
+    let result = result.map(|mut completion| {
+        if let Some(run) = tool_run {
+            completion.tool_calls.insert(0, run.invocation);
         }
+        completion
+    });
+    
+    let timestamp = clock::timestamp();
+    let mut stats = result
+        .as_ref()
+        .map(|completion| completion.stats.clone())
+        .unwrap_or_default();
+    if stats.model.is_none() {
+        // Older servers don't report the model; fall back to the one set up
+        stats.model = backend::BackendSettings::load()
+            .model_path
+            .and_then(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
+    }
+    let owns_turn = state.generation.finish(&handle);
+    state.history_store.clear_inflight(handle.id());
+    
+    let (response_text, interrupted, tool_calls, message_id) = if !owns_turn {
+        // A newer message barged in and already recorded this turn
+        info!("Generation interrupted by a newer message");
+        let tool_calls = result.map(|completion| completion.tool_calls).unwrap_or_default();
+        (handle.partial_text().trim().to_string(), true, tool_calls, None)
+    } else {
+        let (response_text, tool_calls) = match result {
+            Ok(completion) => (completion.text.trim().to_string(), completion.tool_calls),
+            Err(e) if http_backend::is_timeout(&e) => {
+                // Keep whatever arrived; the dropped connection leaves the server
+                // free for the next request
+                info!("{}", e);
+                let partial = handle.partial_text().trim().to_string();
+                let reply = Some(partial)
+                    .filter(|text| !text.is_empty())
+                    .map(|text| (text, EntryStatus::Incomplete));
+                record_turn(&state, message, reply, Vec::new(), rerun);
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
         
-        let result: serde_json::Value = response.json()
-            .map_err(|e| format!("Failed to parse LLM response: {}", e))?;
-        
-        result["response"].as_str()
-            .ok_or("Missing response field in LLM output")?
-            .to_string()
+        if handle.is_cancelled() {
+            // Stopped via cancel_generation - the partial text is the reply
+            info!("Generation cancelled ({} chars)", response_text.len());
+            let reply = Some(response_text.clone())
+                .filter(|text| !text.is_empty() && handle.keeps_partial())
+                .map(|text| (text, EntryStatus::Interrupted));
+            let message_id = record_turn(&state, message, reply, tool_calls.clone(), rerun);
+            (response_text, true, tool_calls, message_id)
+        } else {
+            // Add to conversation history
+            let message_id = record_turn(
+                &state,
+                message,
+                Some((response_text.clone(), EntryStatus::Complete)),
+                tool_calls.clone(),
+                rerun,
+            );
+            
+            if !incognito {
+                // Keep the running summary up to date as the history grows
+                rolling_summary::spawn_update(
+                    state.conversation_history.clone(),
+                    state.llm.clone(),
+                    state.generation.clone(),
+                    state.session.lock().run_id.clone(),
+                );
+                
+                // Fold old messages into topic summaries for the prompt once
+                // the session gets very long (they are stored as memories too)
+                if !private {
+                    compaction::spawn_compaction(
+                        state.conversation_history.clone(),
+                        state.memory_store.clone(),
+                        state.llm.clone(),
+                        state.generation.clone(),
+                        state.session.lock().clone(),
+                    );
+                }
+            }
+            
+            info!("Generated response ({} chars)", response_text.len());
+            (response_text, false, tool_calls, message_id)
+        }
     };
     
-    let timestamp = chrono::Utc::now().to_rfc3339();
+    let response = ChatResponse {
+        generation_id: handle.id().to_string(),
+        agent: "aura".to_string(),
+        message: response_text,
+        timestamp,
+        mode: mode.to_string(),
+        interrupted,
+        stats,
+        tool_calls,
+        message_id,
+        incognito,
+        private,
+        seed,
+        command: None,
+    };
+    if stream {
+        let _ = window.emit("chat-complete", &response);
+    }
+    Ok(response)
+}
+
+/// Replace the last reply with a new one, optionally with different sampling
+///
+/// The last exchange leaves the history and the memory store, and the user
+/// message is answered again and recorded with the new reply. If generation
+/// fails the old exchange is put back.
+#[tauri::command]
+async fn regenerate_response(
+    config: Option<LlmConfig>,
+    stream: Option<bool>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<ChatResponse, AppError> {
+    if state.generation.is_active() {
+        return Err(AppError::Busy("Wait for the current response to finish before regenerating".to_string()));
+    }
+    let (turn, memories) = take_last_turn(&state)?;
+    let history_len = state.conversation_history.lock().len();
+    info!("Regenerating the last response");
     
-    // Add to conversation history
-    {
+    let message = turn[0].content.clone();
+    let result = respond(message, stream.unwrap_or(false), config, None, true, window, &state).await;
+    if result.is_ok() && !incognito::is_active(&state) {
+        persona_stats::record_regeneration(&state.current_mode.lock().to_string());
+    }
+    // A timed-out reply records its partial text; other failures record nothing
+    if result.is_err() && state.conversation_history.lock().len() == history_len {
+        {
+            let mut store = state.memory_store.lock();
+            for memory in memories {
+                store.restore(memory);
+            }
+        }
+        let history = push_history(&state, turn);
+        if !incognito::is_active(&state) {
+            if let Err(e) = state.history_store.save(&history) {
+                warn!("Failed to persist conversation history: {}", e);
+            }
+        }
+    }
+    result
+}
+
+/// A reply in the branch an edited message started
+#[derive(Debug, Serialize)]
+struct EditedMessage {
+    /// The new branch, now the active conversation
+    conversation: conversations::ConversationInfo,
+    response: ChatResponse,
+}
+
+/// Edit the user message at `index` of the history and answer it again
+///
+/// The messages before it are copied into a new conversation (a branch of
+/// this one) which becomes active, and the edited message is sent there. The
+/// original conversation is parked unchanged and can be switched back to.
+#[tauri::command]
+async fn edit_message(
+    index: usize,
+    new_content: String,
+    stream: Option<bool>,
+    max_tokens: Option<i32>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<EditedMessage, AppError> {
+    if new_content.trim().is_empty() {
+        return Err("The edited message is empty".into());
+    }
+    check_max_tokens(max_tokens)?;
+    if state.generation.is_active() {
+        return Err(AppError::Busy("Wait for the current response to finish before editing".to_string()));
+    }
+    let earlier = {
+        let history = state.conversation_history.lock();
+        match history.get(index) {
+            Some(entry) if entry.role == "user" => history[..index].to_vec(),
+            Some(_) => return Err(format!("Message {} isn't one of yours", index).into()),
+            None => return Err(format!("No message {}", index).into()),
+        }
+    };
+    if state.session.lock().incognito {
+        return Err("Incognito conversations can't be branched".into());
+    }
+    
+    let branch = {
+        let mut manager = state.conversations.lock();
+        let active = manager.active_id().to_string();
+        manager.branch(&active, earlier)?
+    };
+    conversations::activate(&state, &branch.id, true)?;
+    info!("Branched conversation {} at message {}", branch.id, index);
+    
+    let response = respond(new_content, stream.unwrap_or(false), None, max_tokens, false, window, &state).await?;
+    let conversation = conversations::info(&state.conversations.lock(), &branch.id)?;
+    Ok(EditedMessage { conversation, response })
+}
+
+/// Take the last exchange (user message and reply) out of the history, and
+/// its messages out of the memory store
+fn take_last_turn(state: &AppState) -> Result<(Vec<ConversationEntry>, Vec<MemoryItem>), AppError> {
+    let session = state.session.lock().clone();
+    let turn = {
         let mut history = state.conversation_history.lock();
-        history.push(ConversationEntry {
-            role: "user".to_string(),
-            content: message.clone(),
-            timestamp: timestamp.clone(),
-            quality_score: None,
-        });
-        history.push(ConversationEntry {
+        let len = history.len();
+        let has_turn = len >= 2 && history[len - 2].role == "user" && history[len - 1].role == "assistant";
+        if !has_turn {
+            return Err("There's no reply to regenerate".into());
+        }
+        let turn = history.split_off(len - 2);
+        if !session.incognito {
+            if let Err(e) = state.history_store.save(&history) {
+                warn!("Failed to persist conversation history: {}", e);
+            }
+        }
+        turn
+    };
+    
+    // The messages were logged with their role and timestamp (their content
+    // may have been redacted)
+    let mut store = state.memory_store.lock();
+    let logged: Vec<MemoryItem> = store
+        .get_all(&session.run_filters(), usize::MAX)
+        .into_iter()
+        .filter(|memory| {
+            turn.iter().any(|entry| {
+                memory.metadata.get("role") == Some(&serde_json::json!(entry.role))
+                    && memory.metadata.get("timestamp") == Some(&serde_json::json!(entry.timestamp))
+            })
+        })
+        .collect();
+    for memory in &logged {
+        store.delete(&memory.id);
+    }
+    Ok((turn, logged))
+}
+
+/// Append a user turn (and the assistant reply, if any) to the history
+///
+/// Each entry is also written to the memory store, with personal details
+/// redacted, tagged with the session's user/agent/run ids, and the names in
+/// it go into the entity index. `tool_calls` are attached to the reply, and
+/// the user message carries the extraction filter's verdict; when it says
+/// "extract", the exchange goes through fact extraction in the background.
+/// Returns the reply's memory id. In an incognito session the entries only
+/// join the working history; in privacy mode they are saved with it but not
+/// to memory or the entity index. With `rerun` the user message was recorded
+/// before, so only the reply is indexed and nothing is extracted.
+fn record_turn(
+    state: &AppState,
+    user_message: String,
+    reply: Option<(String, EntryStatus)>,
+    tool_calls: Vec<ToolInvocation>,
+    rerun: bool,
+) -> Option<String> {
+    let timestamp = clock::timestamp();
+    
+    let mut entries = vec![ConversationEntry {
+        role: "user".to_string(),
+        content: user_message,
+        timestamp: timestamp.clone(),
+        quality_score: None,
+        status: EntryStatus::Complete,
+        tool_calls: Vec::new(),
+        parts: Vec::new(),
+    }];
+    if let Some((content, status)) = reply {
+        entries.push(ConversationEntry {
             role: "assistant".to_string(),
-            content: response_text.clone(),
-            timestamp: timestamp.clone(),
+            content,
+            timestamp,
             quality_score: None,
+            status,
+            tool_calls,
+            parts: Vec::new(),
         });
-        
-        // Keep only last 20 messages in memory
-        let history_len = history.len();
-        if history_len > 20 {
-            history.drain(0..history_len - 20);
+    }
+    
+    let session = state.session.lock().clone();
+    if session.incognito {
+        push_history(state, entries);
+        return None;
+    }
+    if privacy::is_private(state) {
+        let history = push_history(state, entries);
+        if let Err(e) = state.history_store.save(&history) {
+            warn!("Failed to persist conversation history: {}", e);
+        }
+        return None;
+    }
+    
+    // Redacted before the store is locked; the model pass can take a while
+    let stored: Vec<String> = entries
+        .iter()
+        .map(|entry| redaction::scrub(state, &entry.content))
+        .collect();
+    let mut reply_id = None;
+    let mut extract = false;
+    {
+        let mut store = state.memory_store.lock();
+        for (entry, content) in entries.iter().zip(stored.iter().cloned()) {
+            let mut metadata = HashMap::new();
+            metadata.insert("kind".to_string(), serde_json::json!("message"));
+            metadata.insert("role".to_string(), serde_json::json!(entry.role));
+            metadata.insert("timestamp".to_string(), serde_json::json!(entry.timestamp));
+            metadata.insert("status".to_string(), serde_json::json!(entry.status));
+            if !entry.tool_calls.is_empty() {
+                metadata.insert("tool_calls".to_string(), serde_json::json!(entry.tool_calls));
+            }
+            if entry.role == "user" {
+                let filters = MemoryFilters {
+                    user_id: Some(session.user_id.clone()),
+                    ..Default::default()
+                };
+                let verdict = extraction_filter::check(&store, &content, &filters);
+                extract = verdict.should_extract() && !rerun;
+                metadata.insert("extraction".to_string(), serde_json::json!(verdict));
+            }
+            
+            let (user_id, agent_id, run_id) = session.memory_ids();
+            match store.add(content, user_id, agent_id, run_id, metadata) {
+                Ok(id) if entry.role == "assistant" => reply_id = Some(id),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to record {} message in memory: {}", entry.role, e),
+            }
         }
     }
     
-    // Log to hierarchical storage (The Nexus Core) - Disabled in mock mode
-    // TODO: Re-enable when real LLM and persistence is set up
-    // {
-    //     let bridge = state.python_bridge.lock();
-    //     bridge.log_conversation(
-    //         message,
-    //         response_text.clone(),
-    //         mode.to_string(),
-    //     ).map_err(|e| format!("Failed to log conversation: {}", e))?;
-    // }
+    if extract {
+        let exchange: Vec<String> = entries
+            .iter()
+            .zip(&stored)
+            .map(|(entry, content)| format!("{}: {}", if entry.role == "user" { "User" } else { "Assistant" }, content))
+            .collect();
+        fact_extraction::spawn(state, exchange.join("\n"));
+    }
     
-    println!("✅ Generated response ({} chars)", response_text.len());
+    // Indexed from the redacted text, so excerpts never keep what was removed;
+    // a rerun's user message is in the index already
+    let redacted: Vec<ConversationEntry> = entries
+        .iter()
+        .zip(&stored)
+        .skip(usize::from(rerun))
+        .map(|(entry, content)| ConversationEntry {
+            content: content.clone(),
+            ..entry.clone()
+        })
+        .collect();
+    entities::record_turn(&redacted, &session.run_id);
     
-    Ok(ChatResponse {
-        agent: "aura".to_string(),
-        message: response_text,
-        timestamp,
-        mode: mode.to_string(),
-    })
+    let history = push_history(state, entries);
+    if let Err(e) = state.history_store.save(&history) {
+        warn!("Failed to persist conversation history: {}", e);
+    }
+    reply_id
+}
+
+/// Append entries to the working history, dropping the oldest past the cap
+fn push_history(
+    state: &AppState,
+    entries: Vec<ConversationEntry>,
+) -> parking_lot::MutexGuard<'_, Vec<ConversationEntry>> {
+    let mut history = state.conversation_history.lock();
+    history.extend(entries);
+    
+    // Safety cap - compaction normally keeps history well below this
+    let history_len = history.len();
+    let max_entries = settings::AppSettings::load().history.max_entries;
+    if history_len > max_entries {
+        history.drain(0..history_len - max_entries);
+    }
+    history
+}
+
+// Stop the in-flight generation; send_chat_message returns its partial text
+// (recorded in history unless `keep_partial` is false)
+#[tauri::command]
+async fn cancel_generation(
+    keep_partial: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let cancelled = state.generation.cancel(keep_partial.unwrap_or(true));
+    state.tts.stop();
+    if let Some(generation_id) = &cancelled {
+        info!("Cancelling generation {}", generation_id);
+    }
+    Ok(cancelled)
 }
 
-// Check if LLM is ready (HTTP health check)
+// Check if LLM is ready (HTTP health check); never waits on the chat
+// backend, so it answers while a reply is generating
 #[tauri::command]
 async fn check_backend(_state: tauri::State<'_, AppState>) -> Result<bool, String> {
     // Check if LLM server is reachable
-    match reqwest::blocking::Client::new()
-        .get("http://localhost:5555/health")
-        .send() 
-    {
-        Ok(response) => Ok(response.status().is_success()),
-        Err(_) => Ok(false), // Server not running
-    }
+    tauri::async_runtime::spawn_blocking(|| {
+        HttpBackend::local().map(|backend| backend.health()).unwrap_or(false)
+    })
+    .await
+    .map_err(|e| e.to_string())
 }
 
-// Switch between Companion/Youniverse modes
+// Switch to a built-in or custom mode (the previous session is archived)
 #[tauri::command]
 async fn switch_mode(
     new_mode: String,
+    include_history: Option<bool>,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
-    let mode = match new_mode.as_str() {
-        "companion" => AppMode::Companion,
-        "youniverse" => AppMode::Youniverse,
-        _ => return Err(format!("Unknown mode: {}", new_mode)),
-    };
-    
+    let mode = AppMode::from_name(&new_mode).ok_or_else(|| format!("Unknown mode: {}", new_mode))?;
+    apply_mode_switch(&state, mode, include_history.unwrap_or(false));
+    Ok(new_mode)
+}
+
+/// Make `mode` current, archiving the finished session and starting a new
+/// one (with the old history carried over if `include_history` is set)
+pub(crate) fn apply_mode_switch(state: &AppState, mode: AppMode, include_history: bool) {
     {
         let mut current_mode = state.current_mode.lock();
         *current_mode = mode.clone();
     }
     
-    // Clear conversation history when switching modes
+    // Incognito stays incognito and unsaved in the new mode
+    if incognito::is_active(state) {
+        if !include_history {
+            state.conversation_history.lock().clear();
+        }
+        *state.session.lock() = incognito::session(mode.to_string());
+        info!("Switched to {} mode (incognito)", mode.to_string());
+        return;
+    }
+    
+    let previous_session = state.session.lock().clone();
     {
         let mut history = state.conversation_history.lock();
-        history.clear();
+        if let Err(e) = state.history_store.archive(&previous_session, &history) {
+            warn!("Failed to archive session {}: {}", previous_session.run_id, e);
+        }
+        
+        if !include_history {
+            history.clear();
+            if let Err(e) = state.history_store.save(&history) {
+                warn!("Failed to persist conversation history: {}", e);
+            }
+        }
     }
     
-    println!("🔄 Switched to {} mode", new_mode);
-    Ok(new_mode)
+    // A new mode starts a new run for memory scoping
+    {
+        let mut session = state.session.lock();
+        *session = SessionIds::new(mode.to_string());
+        if let Err(e) = state.history_store.save_session(&session) {
+            warn!("Failed to persist session ids: {}", e);
+        }
+    }
+    if let Err(e) = state.conversations.lock().set_active_mode(&mode.to_string()) {
+        warn!("Failed to persist conversation mode: {}", e);
+    }
+    
+    info!("Switched to {} mode", mode.to_string());
 }
 
 // Search past conversations using The Nexus Core (Disabled - HTTP mode)
@@ -304,6 +1092,8 @@ async fn get_conversation_history(
             content: entry.content.clone(),
             timestamp: entry.timestamp.clone(),
             quality_score: entry.quality_score,
+            status: entry.status,
+            parts: entry.content_parts(),
         })
         .collect();
     
@@ -325,40 +1115,281 @@ async fn get_current_mode(state: tauri::State<'_, AppState>) -> Result<String, S
 // }
 
 fn main() {
-    println!("🚀 Starting AuraNexus with HTTP LLM Server...");
+    // Everything is kept in Tauri's app data directory; bring over what
+    // older versions left elsewhere before anything is read
+    let context = tauri::generate_context!();
+    paths::init(context.config());
+    logging::init();
+    info!("Starting AuraNexus with HTTP LLM Server...");
+    migration::run();
+    encryption::init();
     
     // Note: Python LLM server should be running separately on localhost:5555
     // Start it with: python llm_server.py
     
-    // Create application state (no Python bridge needed - using HTTP instead)
+    // Restore persisted history, folding in any response a crash cut short
+    let history_store = HistoryStore::open(paths::app_data_dir().join("history"))
+        .or_else(|e| {
+            warn!("History directory unavailable ({}), using temp dir", e);
+            HistoryStore::open(std::env::temp_dir().join("AuraNexus").join("history"))
+        })
+        .expect("Failed to open history store");
+    let mut history = history_store.load().unwrap_or_else(|e| {
+        warn!("Failed to load conversation history: {}", e);
+        Vec::new()
+    });
+    let recovered = history_store.restore_incomplete(&mut history);
+    if recovered > 0 {
+        info!("Recovered {} incomplete response(s) from last session", recovered);
+        if let Err(e) = history_store.save(&history) {
+            warn!("Failed to persist recovered history: {}", e);
+        }
+    }
+    
+    // Resume the session the saved history belongs to (or start a new one)
+    let session = history_store.load_session().unwrap_or_else(|| {
+        let session = SessionIds::new(AppMode::Companion.to_string());
+        if let Err(e) = history_store.save_session(&session) {
+            warn!("Failed to persist session ids: {}", e);
+        }
+        session
+    });
+    let mode = AppMode::from_name(&session.agent_id).unwrap_or(AppMode::Companion);
+    info!("Session run id: {}", session.run_id);
+    
+    let conversations = SessionManager::open(paths::app_data_dir().join("conversations"), &session, &history)
+        .or_else(|e| {
+            warn!("Conversations directory unavailable ({}), using temp dir", e);
+            SessionManager::open(
+                std::env::temp_dir().join("AuraNexus").join("conversations"),
+                &session,
+                &history,
+            )
+        })
+        .expect("Failed to open conversations");
+    
+    // Shared with the local /v1/embeddings endpoint, cache included
+    let embedder = embeddings::load_embedder();
+    let port = embeddings::EmbeddingSettings::load()
+        .server_port
+        .unwrap_or(embeddings_server::DEFAULT_PORT);
+    if port != 0 {
+        if let Err(e) = embeddings_server::spawn(embedder.clone(), port) {
+            warn!("Embeddings endpoint unavailable: {:#}", e);
+        }
+    }
+    
+    let memory_store = match MemoryStore::open_sqlite(paths::app_data_dir().join("memories.db")) {
+        Ok(store) => store,
+        Err(e) => {
+            warn!("Memory database unavailable ({}), memories won't persist", e);
+            MemoryStore::new()
+        }
+    };
+    let mut memory_store = memory_store
+        .with_index_file(paths::app_data_dir().join("memories.hnsw"))
+        .with_embedder(embedder.clone());
+    memory_store.set_recency_decay(settings::AppSettings::load().retrieval.recency_decay());
+    let memory_store = Arc::new(Mutex::new(memory_store));
+    
+    // Other MCP-aware apps can search and add memories when enabled
+    let mcp_settings = mcp_server::McpServerSettings::load();
+    if mcp_settings.enabled {
+        if let Err(e) = mcp_server::spawn(memory_store.clone(), mcp_settings) {
+            warn!("MCP server unavailable: {:#}", e);
+        }
+    }
+    memory_store::spawn_embedding_backfill(memory_store.clone());
+    memory_store::spawn_expiry_pruning(memory_store.clone());
+    let ingest = IngestQueue::start(memory_store.clone());
+    
+    let history_store = Arc::new(history_store);
+    history_store::spawn_cold_storage(history_store.clone());
+    entities::spawn_backfill(history_store.clone(), history.clone(), session.run_id.clone());
+    trash::spawn_purge();
+    
+    // Create application state (the LLM backend is started once the window is up)
     let app_state = AppState {
-        conversation_history: Arc::new(Mutex::new(Vec::new())),
-        current_mode: Arc::new(Mutex::new(AppMode::Companion)),  // Start in Companion mode
+        conversation_history: Arc::new(Mutex::new(history)),
+        current_mode: Arc::new(Mutex::new(mode)),  // Companion unless resuming a Youniverse session
+        generation: Arc::new(GenerationTracker::new()),
+        history_store,
+        memory_store,
+        session: Arc::new(Mutex::new(session)),
+        conversations: Arc::new(Mutex::new(conversations)),
+        ingest: Arc::new(ingest),
+        llm: Arc::new(tokio::sync::Mutex::new(None)),
+        tts: TtsQueue::start(),
     };
     
     tauri::Builder::default()
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             send_chat_message,
+            regenerate_response,
+            edit_message,
+            cancel_generation,
             check_backend,
+            python_bridge::get_python_status,
+            python_env::get_python_env_status,
+            python_env::setup_python_env,
+            backend::get_backend,
+            backend::set_backend,
+            backend::load_model_by_path,
+            capabilities::get_model_capabilities,
+            slash_commands::list_slash_commands,
+            structured::generate_structured,
+            logit_bias::get_banned_words,
+            logit_bias::set_banned_words,
+            tools::get_tool_settings,
+            tools::set_tool_settings,
+            mcp_server::get_mcp_server_settings,
+            mcp_server::set_mcp_server_settings,
+            backend::unload_model,
+            backend::get_backend_status,
+            backend::retry_backend_init,
+            prompt_builder::set_chat_template,
+            tokenizer::get_token_count,
+            http_backend::get_backend_timeouts,
+            http_backend::set_backend_timeouts,
             switch_mode,
+            conversations::create_conversation,
+            conversations::list_conversations,
+            conversations::switch_conversation,
+            conversations::delete_conversation,
+            conversations::rename_conversation,
+            sampling_presets::list_sampling_presets,
+            sampling_presets::save_sampling_preset,
+            sampling_presets::delete_sampling_preset,
+            sampling_presets::set_sampling_preset,
+            incognito::start_incognito,
+            incognito::end_incognito,
+            incognito::is_incognito,
+            privacy::get_privacy_mode,
+            privacy::set_privacy_mode,
+            encryption::get_encryption_status,
+            encryption::enable_encryption,
+            encryption::disable_encryption,
+            user_data::export_all_data,
+            user_data::delete_all_user_data,
+            importer::import_conversations,
+            self_test::run_self_test,
+            logging::get_recent_logs,
+            tts::get_tts_settings,
+            tts::set_tts_settings,
+            custom_instructions::get_instruction_profiles,
+            custom_instructions::set_custom_instructions,
+            custom_instructions::set_active_instruction_profile,
+            custom_instructions::delete_instruction_profile,
+            intent::get_mode_switch_settings,
+            intent::set_mode_switch_settings,
             get_conversation_history,
             get_current_mode,
+            modes::list_modes,
+            modes::create_mode,
+            modes::update_mode,
+            modes::delete_mode,
+            modes::get_system_prompt,
+            modes::set_system_prompt,
             models::get_available_models,
-            models::get_model_info
+            models::get_model_info,
+            models::get_gguf_metadata,
+            system_probe::get_system_info,
+            inference_settings::get_inference_settings,
+            inference_settings::set_inference_settings,
+            presets::export_preset,
+            presets::import_preset,
+            setup_wizard::get_setup_state,
+            setup_wizard::run_hardware_scan,
+            setup_wizard::select_setup_model,
+            setup_wizard::download_setup_model,
+            model_download::download_model,
+            setup_wizard::detect_setup_backend,
+            setup_wizard::select_setup_persona,
+            setup_wizard::complete_setup,
+            session::get_session_info,
+            session::get_session_memories,
+            session::search_session_memories,
+            session::browse_memories,
+            session::set_memory_expiry,
+            memory_history::get_memory_history,
+            memory_history::restore_memory_version,
+            consolidation::consolidate_memories,
+            session::list_archived_sessions,
+            session::get_archived_session,
+            session::unarchive_session,
+            session::merge_sessions,
+            task_presets::get_task_presets,
+            task_presets::set_task_preset,
+            rolling_summary::get_conversation_summary,
+            prompt_trace::get_prompt_trace,
+            ingest::ingest_files,
+            ingest::ingest_text,
+            ingest::get_ingest_stats,
+            ingest::set_document_sensitivity,
+            chunking_settings::get_chunking_settings,
+            chunking_settings::set_chunking_settings,
+            chunking_settings::get_stale_documents,
+            chunking_settings::rechunk_documents,
+            settings::get_settings,
+            settings::update_settings,
+            trash::delete_memory,
+            trash::delete_document,
+            trash::delete_session,
+            trash::list_trash,
+            trash::restore_from_trash,
+            trash::empty_trash,
+            entities::list_entities,
+            entities::entity_last_mentioned,
+            entities::rebuild_entity_index,
+            html_export::export_conversation_html,
+            conversation_export::export_conversation,
+            share::share_conversation,
+            share::import_shared_conversation,
+            code_blocks::extract_code_blocks,
+            digest::get_digest_settings,
+            digest::set_digest_settings,
+            digest::run_digest_now,
+            saved_searches::save_search,
+            saved_searches::list_saved_searches,
+            saved_searches::run_saved_search,
+            saved_searches::delete_saved_search,
+            memory_policy::get_memory_policies,
+            memory_policy::set_memory_policy,
+            memory_policy::unlock_private_documents,
+            memory_policy::lock_private_documents,
+            persona_stats::get_persona_report,
+            persona_stats::rate_response,
+            migration::get_data_migration,
+            migration::rollback_data_migration
         ])
         .setup(|app| {
-            println!("✅ Tauri setup complete");
+            info!("Tauri setup complete");
             let window = app.get_window("main").unwrap();
-            println!("🪟 Window created: {:?}", window.label());
+            info!("Window created: {:?}", window.label());
+            
+            // First run: the frontend drives the setup wizard (hardware scan,
+            // model download, backend detection, persona) instead of any
+            // automatic model download here
+            let setup = setup_wizard::SetupState::load();
+            if setup.completed {
+                info!("Start Python server: python llm_server.py");
+            } else {
+                info!("First run - setup wizard pending (step: {:?})", setup.step);
+            }
             
-            // Note: Model initialization happens in Python server (llm_server.py)
-            println!("💡 Start Python server: python llm_server.py");
+            // A backend that can't start leaves the UI in a setup-required
+            // state (see `backend-status`) rather than failing the launch
+            backend::spawn_startup_init(app.handle());
+            tokenizer::preload_default();
+            digest::spawn_scheduler(app.handle());
+            consolidation::spawn_scheduler(app.handle());
+            saved_searches::spawn_watcher(app.handle());
             
             Ok(())
         })
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
     
-    println!("👋 AuraNexus closed.");
+    info!("AuraNexus closed.");
 }
@@ -0,0 +1,495 @@
+// MCP Server Module - The memory store as a local Model Context Protocol server
+//
+// Lets MCP-aware apps (desktop assistants, editors) search and add to
+// AuraNexus's memory. Off by default; when enabled in the `[mcp_server]`
+// section of settings.toml it speaks MCP's Streamable HTTP transport on
+// 127.0.0.1 only:
+//
+//   POST /mcp  one JSON-RPC message, answered with application/json
+//
+// Tools: `search_memory`, `get_memory` and `add_memory` (left out when
+// `allow_writes` is off). Resources: each memory as `memory://{id}`, the most
+// recent ones listed. Outside apps see memories the way a share-all persona
+// in a locked session does, so private documents never leave the app. What
+// they add is tagged with agent id "mcp"; writes are off until turned on.
+// Every request must carry the per-install token from the settings as
+// `Authorization: Bearer <token>` and a JSON body; requests with a browser
+// Origin other than a localhost page are refused, so web pages can't reach
+// the server even with a simple (preflight-free) POST.
+
+use crate::embeddings_server::{read_request, write_response};
+use crate::memory_policy::{AccessScope, MemoryPolicy};
+use crate::memory_store::{MemoryItem, MemoryStore};
+use crate::metadata_filter::MetadataFilter;
+use crate::session::LOCAL_USER_ID;
+use crate::settings::{self, AppSettings};
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Port used when the settings don't pick one
+pub const DEFAULT_PORT: u16 = 8767;
+
+/// Agent id of memories added through MCP
+const MCP_AGENT_ID: &str = "mcp";
+
+/// Newest protocol revision spoken; older clients get theirs echoed back
+const PROTOCOL_VERSION: &str = "2025-03-26";
+const SUPPORTED_VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", "2025-06-18"];
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+const MAX_SEARCH_LIMIT: usize = 50;
+/// Memories listed by `resources/list`
+const LISTED_RESOURCES: usize = 50;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Offer `add_memory`
+    pub allow_writes: bool,
+    /// Bearer token clients must send; generated on first load
+    pub token: String,
+}
+
+impl Default for McpServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            allow_writes: false,
+            token: String::new(),
+        }
+    }
+}
+
+impl McpServerSettings {
+    /// Saved settings, with a token generated (and saved) if there is none
+    pub fn load() -> Self {
+        let mut settings = AppSettings::load().mcp_server;
+        if settings.token.is_empty() {
+            settings.token = new_token();
+            if let Err(e) = settings.save() {
+                warn!("MCP server token not saved: {:#}", e);
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> Result<()> {
+        settings::update(|settings| settings.mcp_server = self.clone()).map(|_| ())
+    }
+}
+
+/// A random token for a new install
+fn new_token() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+/// Serve MCP on 127.0.0.1:`settings.port` in the background
+pub fn spawn(memory_store: Arc<Mutex<MemoryStore>>, settings: McpServerSettings) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", settings.port))
+        .with_context(|| format!("Failed to bind MCP server to port {}", settings.port))?;
+    info!("MCP server: http://127.0.0.1:{}/mcp", settings.port);
+
+    let settings = Arc::new(settings);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let memory_store = memory_store.clone();
+            let settings = settings.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, &memory_store, &settings) {
+                    warn!("MCP request failed: {:#}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Whether a browser `Origin` may talk to the server (only local pages;
+/// not "null", which sandboxed frames and file:// pages send)
+fn origin_allowed(origin: &str) -> bool {
+    let authority = origin.split("://").nth(1).unwrap_or(origin);
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => authority,
+    };
+    origin.contains("://") && matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Whether `Authorization` carries `token`, compared in constant time
+fn authorized(authorization: Option<&String>, token: &str) -> bool {
+    let Some(presented) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let (presented, token) = (presented.trim().as_bytes(), token.as_bytes());
+    !token.is_empty()
+        && presented.len() == token.len()
+        && presented.iter().zip(token).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Whether the body is declared as JSON (rules out simple cross-site POSTs)
+fn is_json(content_type: Option<&String>) -> bool {
+    content_type
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/json"))
+}
+
+fn handle_connection(mut stream: TcpStream, memory_store: &Mutex<MemoryStore>, settings: &McpServerSettings) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err(e) => return write_response(&mut stream, 400, "text/plain", e.to_string().as_bytes()),
+    };
+    if let Some(origin) = request.headers.get("origin").filter(|origin| !origin_allowed(origin)) {
+        info!("MCP request from {} refused", origin);
+        return write_response(&mut stream, 403, "text/plain", b"Origin not allowed");
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/mcp") => {}
+        // No server-initiated messages, so no event stream to open
+        (_, "/mcp") => return write_response(&mut stream, 405, "text/plain", b"Use POST"),
+        _ => return write_response(&mut stream, 404, "text/plain", b"Not found"),
+    }
+    if !authorized(request.headers.get("authorization"), &settings.token) {
+        info!("MCP request without a valid token refused");
+        return write_response(&mut stream, 401, "text/plain", b"Missing or wrong bearer token");
+    }
+    if !is_json(request.headers.get("content-type")) {
+        return write_response(&mut stream, 415, "text/plain", b"Send application/json");
+    }
+
+    let response = match serde_json::from_slice::<Value>(&request.body) {
+        Ok(message) => handle_message(memory_store, &message, settings.allow_writes),
+        Err(e) => Some(error(Value::Null, PARSE_ERROR, &format!("Invalid JSON: {}", e))),
+    };
+    match response {
+        Some(response) => write_response(&mut stream, 200, "application/json", &serde_json::to_vec(&response)?),
+        // Notifications and responses get no reply
+        None => write_response(&mut stream, 202, "text/plain", b""),
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The reply to one JSON-RPC message, if it needs one
+pub fn handle_message(memory_store: &Mutex<MemoryStore>, message: &Value, allow_writes: bool) -> Option<Value> {
+    let Some(method) = message["method"].as_str() else {
+        // A response to us (we send no requests) or garbage
+        return message
+            .get("id")
+            .filter(|_| message.get("result").is_none() && message.get("error").is_none())
+            .map(|id| error(id.clone(), INVALID_REQUEST, "Missing method"));
+    };
+    let id = message.get("id")?.clone();
+    let params = &message["params"];
+    let result = match method {
+        "initialize" => Ok(initialize(params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_list(allow_writes) })),
+        "tools/call" => call_tool(memory_store, params, allow_writes),
+        "resources/list" => Ok(list_resources(&memory_store.lock())),
+        "resources/templates/list" => Ok(json!({
+            "resourceTemplates": [{
+                "uriTemplate": "memory://{id}",
+                "name": "Memory",
+                "description": "One AuraNexus memory by id",
+                "mimeType": "application/json"
+            }]
+        })),
+        "resources/read" => read_resource(&memory_store.lock(), params),
+        _ => return Some(error(id, METHOD_NOT_FOUND, &format!("Unknown method {}", method))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error(id, INVALID_PARAMS, &format!("{:#}", e)),
+    })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params["protocolVersion"].as_str().unwrap_or_default();
+    let version = if SUPPORTED_VERSIONS.contains(&requested) { requested } else { PROTOCOL_VERSION };
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {}, "resources": {} },
+        "serverInfo": { "name": "auranexus-memory", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Long-term memory of the user's AuraNexus companion. Search it before asking the user for something they may have said before."
+    })
+}
+
+fn tool_list(allow_writes: bool) -> Vec<Value> {
+    let mut tools = vec![
+        json!({
+            "name": "search_memory",
+            "description": "Search AuraNexus memories by meaning and keywords, best match first",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_LIMIT },
+                    "filters": {
+                        "type": "object",
+                        "description": "mem0-style metadata filter, e.g. {\"AND\": [{\"kind\": \"message\"}, {\"created_at\": {\"gte\": \"2024-07-01\"}}]}"
+                    }
+                },
+                "required": ["query"]
+            }
+        }),
+        json!({
+            "name": "get_memory",
+            "description": "Get one AuraNexus memory by id",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"]
+            }
+        }),
+    ];
+    if allow_writes {
+        tools.push(json!({
+            "name": "add_memory",
+            "description": "Store a new AuraNexus memory, e.g. a fact about the user",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string" },
+                    "metadata": { "type": "object" }
+                },
+                "required": ["content"]
+            }
+        }));
+    }
+    tools
+}
+
+/// What outside apps may see: everything but private documents
+fn scope() -> AccessScope {
+    AccessScope {
+        agent_id: MCP_AGENT_ID.to_string(),
+        policy: MemoryPolicy::ShareAll,
+        private_unlocked: false,
+    }
+}
+
+/// A memory as sent to clients (no embedding)
+fn memory_json(memory: &MemoryItem) -> Value {
+    let secs = |time: std::time::SystemTime| time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    json!({
+        "id": memory.id,
+        "content": memory.content,
+        "agent_id": memory.agent_id,
+        "metadata": memory.metadata,
+        "created_at": secs(memory.created_at),
+        "updated_at": secs(memory.updated_at),
+    })
+}
+
+fn visible(store: &MemoryStore, id: &str) -> Option<MemoryItem> {
+    store.get(id).filter(|memory| scope().allows(memory))
+}
+
+/// `tools/call`; tool failures are results with `isError`, per the spec
+fn call_tool(memory_store: &Mutex<MemoryStore>, params: &Value, allow_writes: bool) -> Result<Value> {
+    let name = params["name"].as_str().ok_or_else(|| anyhow!("Missing tool name"))?;
+    let arguments = &params["arguments"];
+    let outcome: Result<Value> = match name {
+        "search_memory" => {
+            let query = arguments["query"].as_str().ok_or_else(|| anyhow!("search_memory needs a query"))?;
+            let limit = arguments["limit"]
+                .as_u64()
+                .map_or(DEFAULT_SEARCH_LIMIT, |limit| (limit as usize).clamp(1, MAX_SEARCH_LIMIT));
+            let mut filters = scope().filters();
+            if !arguments["filters"].is_null() {
+                filters.filter = Some(MetadataFilter::parse(&arguments["filters"])?);
+            }
+            let results: Vec<Value> = memory_store
+                .lock()
+                .search_scored(query, Some(&filters), limit)
+                .iter()
+                .map(|(memory, score)| {
+                    let mut memory = memory_json(memory);
+                    memory["score"] = json!(score);
+                    memory
+                })
+                .collect();
+            Ok(json!(results))
+        }
+        "get_memory" => {
+            let id = arguments["id"].as_str().ok_or_else(|| anyhow!("get_memory needs an id"))?;
+            visible(&memory_store.lock(), id)
+                .map(|memory| memory_json(&memory))
+                .ok_or_else(|| anyhow!("No memory {}", id))
+        }
+        "add_memory" if allow_writes => {
+            let content = arguments["content"]
+                .as_str()
+                .map(str::trim)
+                .filter(|content| !content.is_empty())
+                .ok_or_else(|| anyhow!("add_memory needs content"))?;
+            let mut metadata: HashMap<String, Value> = match &arguments["metadata"] {
+                Value::Object(map) => map.clone().into_iter().collect(),
+                _ => HashMap::new(),
+            };
+            metadata.entry("source".to_string()).or_insert_with(|| json!("mcp"));
+            memory_store
+                .lock()
+                .add(
+                    content,
+                    Some(LOCAL_USER_ID.to_string()),
+                    Some(MCP_AGENT_ID.to_string()),
+                    None,
+                    metadata,
+                )
+                .map(|id| {
+                    info!("MCP client added memory {}", id);
+                    json!({ "id": id })
+                })
+                .map_err(|e| anyhow!("{}", e))
+        }
+        _ => return Err(anyhow!("Unknown tool {}", name)),
+    };
+    Ok(match outcome {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": serde_json::to_string_pretty(&value)? }],
+            "structuredContent": { "result": value },
+            "isError": false
+        }),
+        Err(e) => json!({ "content": [{ "type": "text", "text": format!("{:#}", e) }], "isError": true }),
+    })
+}
+
+/// Title of a memory in listings: its first line, shortened
+fn title(memory: &MemoryItem) -> String {
+    let line = memory.content.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > 80 {
+        format!("{}…", line.chars().take(79).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn list_resources(store: &MemoryStore) -> Value {
+    let resources: Vec<Value> = store
+        .get_all(&scope().filters(), LISTED_RESOURCES)
+        .iter()
+        .map(|memory| {
+            json!({
+                "uri": format!("memory://{}", memory.id),
+                "name": title(memory),
+                "mimeType": "application/json"
+            })
+        })
+        .collect();
+    json!({ "resources": resources })
+}
+
+fn read_resource(store: &MemoryStore, params: &Value) -> Result<Value> {
+    let uri = params["uri"].as_str().ok_or_else(|| anyhow!("Missing uri"))?;
+    let id = uri.strip_prefix("memory://").ok_or_else(|| anyhow!("Unknown resource {}", uri))?;
+    let memory = visible(store, id).ok_or_else(|| anyhow!("No memory {}", id))?;
+    Ok(json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": "application/json",
+            "text": memory_json(&memory).to_string()
+        }]
+    }))
+}
+
+/// Current MCP server settings
+#[tauri::command]
+pub async fn get_mcp_server_settings() -> Result<McpServerSettings, String> {
+    Ok(McpServerSettings::load())
+}
+
+/// Save MCP server settings; they apply from the next start
+#[tauri::command]
+pub async fn set_mcp_server_settings(mut settings: McpServerSettings) -> Result<(), String> {
+    if settings.port == 0 {
+        return Err("Pick a port for the MCP server".to_string());
+    }
+    // The token is only ever generated here, never chosen by the caller
+    settings.token = McpServerSettings::load().token;
+    settings.save().map_err(|e| e.to_string())?;
+    info!(
+        "MCP server {} on port {} after restart",
+        if settings.enabled { "on" } else { "off" },
+        settings.port
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(store: &Mutex<MemoryStore>, method: &str, params: Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        handle_message(store, &message, true).unwrap()
+    }
+
+    #[test]
+    fn test_memory_over_mcp() {
+        let store = Mutex::new(MemoryStore::new());
+        let mut private = HashMap::new();
+        private.insert("sensitivity".to_string(), json!("private"));
+        let hidden = store.lock().add("Diary: tomato blight", None, None, None, private).unwrap();
+
+        let init = call(&store, "initialize", json!({ "protocolVersion": "2024-11-05" }));
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert_eq!(handle_message(&store, &notification, true), None);
+
+        let tools = call(&store, "tools/list", json!({}));
+        assert_eq!(tools["result"]["tools"].as_array().unwrap().len(), 3);
+        let read_only = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        assert_eq!(handle_message(&store, &read_only, false).unwrap()["result"]["tools"].as_array().unwrap().len(), 2);
+
+        let added = call(&store, "tools/call", json!({ "name": "add_memory", "arguments": { "content": "Grows tomatoes" } }));
+        assert_eq!(added["result"]["isError"], false);
+        let id = added["result"]["structuredContent"]["result"]["id"].as_str().unwrap().to_string();
+        assert_eq!(store.lock().get(&id).unwrap().agent_id.as_deref(), Some("mcp"));
+
+        let found = call(&store, "tools/call", json!({ "name": "search_memory", "arguments": { "query": "tomato" } }));
+        let results = found["result"]["structuredContent"]["result"].as_array().unwrap().clone();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["id"], id.as_str());
+
+        let read = call(&store, "resources/read", json!({ "uri": format!("memory://{}", id) }));
+        assert!(read["result"]["contents"][0]["text"].as_str().unwrap().contains("Grows tomatoes"));
+        // Private documents stay in the app
+        let missing = call(&store, "tools/call", json!({ "name": "get_memory", "arguments": { "id": hidden } }));
+        assert_eq!(missing["result"]["isError"], true);
+        assert_eq!(call(&store, "resources/list", json!({}))["result"]["resources"].as_array().unwrap().len(), 1);
+
+        assert_eq!(call(&store, "nope", json!({}))["error"]["code"], METHOD_NOT_FOUND);
+        assert!(origin_allowed("http://localhost:5173") && origin_allowed("http://[::1]"));
+        assert!(!origin_allowed("https://example.com") && !origin_allowed("http://localhost.evil.com"));
+        assert!(!origin_allowed("null"));
+
+        let token = "secret-token".to_string();
+        assert!(authorized(Some(&"Bearer secret-token".to_string()), &token));
+        assert!(!authorized(Some(&"Bearer secret-tokem".to_string()), &token));
+        assert!(!authorized(None, &token) && !authorized(Some(&"Bearer ".to_string()), ""));
+        assert!(is_json(Some(&"application/json; charset=utf-8".to_string())));
+        assert!(!is_json(Some(&"text/plain".to_string())) && !is_json(None));
+        assert!(!McpServerSettings::default().allow_writes);
+    }
+}
@@ -26,7 +26,7 @@ impl MemoryManager {
         let message = Message {
             role: role.to_string(),
             content: content.to_string(),
-            timestamp: Utc::now(),
+            timestamp: crate::clock::now(),
         };
         
         self.messages.push_back(message);
@@ -0,0 +1,165 @@
+// Memory History Module - Versions of each memory, as in mem0's history
+//
+// `MemoryStore` records a version every time a memory is updated, deleted or
+// restored: the memory as it was before the update or delete (or as it came
+// back, for a restore), the kind of change and when. Versions are numbered
+// per memory from 1 and kept by the backend (the `memory_history` table for
+// SQLite). `get_memory_history` lists them and `restore_memory_version` puts
+// a memory - even a deleted one - back the way a version recorded it. The
+// per-user data wipe clears the history along with the memories.
+
+use crate::clock::{dated, Dated, SystemClock};
+use crate::memory_policy::current_scope;
+use crate::memory_store::MemoryItem;
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use tracing::info;
+
+/// Kind of change a version records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum MemoryEvent {
+    Update,
+    Delete,
+    Restore,
+}
+
+impl MemoryEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MemoryEvent::Update => "UPDATE",
+            MemoryEvent::Delete => "DELETE",
+            MemoryEvent::Restore => "RESTORE",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "UPDATE" => Some(MemoryEvent::Update),
+            "DELETE" => Some(MemoryEvent::Delete),
+            "RESTORE" => Some(MemoryEvent::Restore),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded change to a memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryVersion {
+    /// Assigned by the backend, from 1 per memory
+    pub version: usize,
+    pub event: MemoryEvent,
+    /// The memory before an update or delete, or as restored (no embedding)
+    pub memory: MemoryItem,
+    pub recorded_at: SystemTime,
+}
+
+impl MemoryVersion {
+    /// A not-yet-numbered version of `memory`, recorded now
+    pub fn new(event: MemoryEvent, memory: MemoryItem) -> Self {
+        Self {
+            version: 0,
+            event,
+            memory: MemoryItem { embedding: None, ..memory },
+            recorded_at: SystemTime::from(crate::clock::now()),
+        }
+    }
+}
+
+fn recorded_at(version: &MemoryVersion) -> Option<DateTime<Utc>> {
+    Some(version.recorded_at.into())
+}
+
+/// Recorded versions of a memory, oldest first
+#[tauri::command]
+pub async fn get_memory_history(
+    memory_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Dated<MemoryVersion>>, String> {
+    let scope = current_scope(&state);
+    let versions: Vec<MemoryVersion> = state
+        .memory_store
+        .lock()
+        .history(&memory_id)
+        .into_iter()
+        .filter(|version| scope.allows(&version.memory))
+        .collect();
+    Ok(dated(versions, &SystemClock, recorded_at))
+}
+
+/// Put a memory back the way `version` recorded it; returns the memory
+#[tauri::command]
+pub async fn restore_memory_version(
+    memory_id: String,
+    version: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<MemoryItem, String> {
+    let scope = current_scope(&state);
+    let mut store = state.memory_store.lock();
+    let recorded = store
+        .history(&memory_id)
+        .into_iter()
+        .find(|recorded| recorded.version == version)
+        .filter(|recorded| scope.allows(&recorded.memory))
+        .ok_or_else(|| format!("Memory {} has no version {}", memory_id, version))?;
+    if store.get(&memory_id).is_some_and(|current| !scope.allows(&current)) {
+        return Err(format!("Memory {} has no version {}", memory_id, version));
+    }
+    let current = store.get(&memory_id);
+    if !scope.can_modify(&recorded.memory) || current.is_some_and(|current| !scope.can_modify(&current)) {
+        return Err(format!("The {} persona can't change the user profile", scope.agent_id));
+    }
+    let memory = store.restore_version(recorded);
+    info!("Restored memory {} to version {}", memory_id, version);
+    Ok(memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_store::{MemoryFilters, MemoryStore};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_versions_and_restore() {
+        let dir = std::env::temp_dir().join(format!("auranexus-history-{}", uuid::Uuid::new_v4()));
+        for mut store in [MemoryStore::open_sqlite(dir.join("memories.db")).unwrap(), MemoryStore::new()] {
+            let metadata = HashMap::from([("mood".to_string(), serde_json::json!("calm"))]);
+            let id = store.add("Likes tea", Some("u".to_string()), None, None, metadata).unwrap();
+            assert!(store.history(&id).is_empty());
+
+            store.update(&id, Some("Likes coffee".to_string()), None).unwrap();
+            assert!(store.delete(&id));
+            let history = store.history(&id);
+            let events: Vec<(usize, MemoryEvent, &str)> = history
+                .iter()
+                .map(|version| (version.version, version.event, version.memory.content.as_str()))
+                .collect();
+            assert_eq!(
+                events,
+                [(1, MemoryEvent::Update, "Likes tea"), (2, MemoryEvent::Delete, "Likes coffee")]
+            );
+
+            // Deleted memories come back with their id and metadata
+            let restored = store.restore_version(history[0].clone());
+            assert_eq!(restored.content, "Likes tea");
+            assert_eq!(store.get(&id).unwrap().metadata["mood"], "calm");
+            assert_eq!(store.history(&id)[2].event, MemoryEvent::Restore);
+
+            store.restore_version(history[1].clone());
+            assert_eq!(store.get(&id).unwrap().content, "Likes coffee");
+            assert_eq!(store.history(&id).len(), 4);
+
+            let filters = MemoryFilters {
+                user_id: Some("u".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(store.delete_all(&filters), 1);
+            assert_eq!(store.delete_history(&filters), 5);
+            assert!(store.history(&id).is_empty());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
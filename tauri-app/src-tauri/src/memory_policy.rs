@@ -0,0 +1,308 @@
+// Memory Policy Module - Per-persona memory isolation
+//
+// Each persona (agent id) has a policy deciding which memories retrieval may
+// return while it is active. Policies are enforced in `MemoryStore` itself via
+// `MemoryFilters::access`, so every search path honours them. The same scope
+// hides documents ingested as private unless the user unlocked the session.
+// Profile memories (kind "profile", or tagged "profile" like extracted facts)
+// can only be changed by personas that share everything.
+
+use crate::memory_store::{MemoryFilters, MemoryItem};
+use crate::modes::BUILTIN_MODES;
+use crate::AppState;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+/// Metadata `kind` (or tag) of memories that describe the user themselves
+pub const PROFILE_KIND: &str = "profile";
+
+/// Metadata key holding a memory's list of tags
+pub const TAGS_KEY: &str = "tags";
+
+/// Metadata key holding an ingested document's `Sensitivity`
+pub const SENSITIVITY_KEY: &str = "sensitivity";
+
+/// Who may retrieve an ingested document
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensitivity {
+    #[default]
+    Normal,
+    /// Only retrievable in sessions the user has explicitly unlocked
+    Private,
+}
+
+/// What a persona may retrieve
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryPolicy {
+    /// Everything the user has stored
+    ShareAll,
+    /// Only memories this persona wrote
+    Isolated,
+    /// Its own memories plus the user profile, which it may not modify
+    ProfileReadOnly,
+}
+
+impl MemoryPolicy {
+    /// Policy for personas without an explicit setting
+    pub fn default_for(agent_id: &str) -> Self {
+        match agent_id {
+            // Roleplay shouldn't surface private Companion-mode facts mid-story
+            "youniverse" => MemoryPolicy::ProfileReadOnly,
+            // Custom modes carry their own
+            _ => crate::modes::ModeRegistry::load()
+                .get(agent_id)
+                .map(|mode| mode.memory_policy)
+                .unwrap_or(MemoryPolicy::ShareAll),
+        }
+    }
+}
+
+/// The active persona and its policy, attached to retrieval filters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessScope {
+    pub agent_id: String,
+    pub policy: MemoryPolicy,
+    /// Private documents are visible (the session was unlocked)
+    pub private_unlocked: bool,
+}
+
+impl AccessScope {
+    /// Whether `memory` is visible to this persona
+    pub fn allows(&self, memory: &MemoryItem) -> bool {
+        if is_private(memory) && !self.private_unlocked {
+            return false;
+        }
+        let own = memory.agent_id.as_deref() == Some(self.agent_id.as_str());
+        match self.policy {
+            MemoryPolicy::ShareAll => true,
+            MemoryPolicy::Isolated => own,
+            MemoryPolicy::ProfileReadOnly => own || is_profile(memory),
+        }
+    }
+
+    /// Whether this persona may add to or edit the user profile
+    pub fn can_write_profile(&self) -> bool {
+        self.policy == MemoryPolicy::ShareAll
+    }
+
+    /// Whether this persona may update or delete `memory`
+    pub fn can_modify(&self, memory: &MemoryItem) -> bool {
+        self.allows(memory) && (self.can_write_profile() || !is_profile(memory))
+    }
+
+    /// Filters restricted to this scope
+    pub fn filters(&self) -> MemoryFilters {
+        MemoryFilters {
+            access: Some(self.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+fn is_profile(memory: &MemoryItem) -> bool {
+    let tagged = memory
+        .metadata
+        .get(TAGS_KEY)
+        .and_then(|v| v.as_array())
+        .is_some_and(|tags| tags.iter().any(|tag| tag.as_str() == Some(PROFILE_KIND)));
+    tagged || memory.metadata.get("kind").and_then(|v| v.as_str()) == Some(PROFILE_KIND)
+}
+
+/// Add the profile tag to `metadata`, keeping any other tags
+pub fn tag_profile(metadata: &mut HashMap<String, serde_json::Value>) {
+    let tags = metadata.entry(TAGS_KEY.to_string()).or_insert_with(|| serde_json::json!([]));
+    match tags.as_array_mut() {
+        Some(tags) if tags.iter().any(|tag| tag.as_str() == Some(PROFILE_KIND)) => {}
+        Some(tags) => tags.push(serde_json::json!(PROFILE_KIND)),
+        None => *tags = serde_json::json!([PROFILE_KIND]),
+    }
+}
+
+fn is_private(memory: &MemoryItem) -> bool {
+    memory
+        .metadata
+        .get(SENSITIVITY_KEY)
+        .and_then(|v| serde_json::from_value::<Sensitivity>(v.clone()).ok())
+        == Some(Sensitivity::Private)
+}
+
+/// Policies chosen by the user, keyed by agent id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersonaPolicies {
+    #[serde(default)]
+    pub policies: HashMap<String, MemoryPolicy>,
+}
+
+impl PersonaPolicies {
+    const FILE: &'static str = "memory_policies.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    pub fn policy_for(&self, agent_id: &str) -> MemoryPolicy {
+        self.policies
+            .get(agent_id)
+            .copied()
+            .unwrap_or_else(|| MemoryPolicy::default_for(agent_id))
+    }
+
+    pub fn scope_for(&self, agent_id: &str) -> AccessScope {
+        AccessScope {
+            agent_id: agent_id.to_string(),
+            policy: self.policy_for(agent_id),
+            private_unlocked: false,
+        }
+    }
+}
+
+/// Access scope of the persona in the current session
+pub fn current_scope(state: &AppState) -> AccessScope {
+    let (agent_id, private_unlocked) = {
+        let session = state.session.lock();
+        (session.agent_id.clone(), session.private_unlocked)
+    };
+    AccessScope {
+        private_unlocked,
+        ..PersonaPolicies::load().scope_for(&agent_id)
+    }
+}
+
+/// Effective policy for every known persona
+#[tauri::command]
+pub async fn get_memory_policies() -> Result<HashMap<String, MemoryPolicy>, String> {
+    let policies = PersonaPolicies::load();
+    let custom = crate::modes::ModeRegistry::load().modes.into_iter().map(|mode| mode.id);
+    let mut effective: HashMap<String, MemoryPolicy> = BUILTIN_MODES
+        .iter()
+        .map(|agent| agent.to_string())
+        .chain(custom)
+        .map(|agent| {
+            let policy = policies.policy_for(&agent);
+            (agent, policy)
+        })
+        .collect();
+    effective.extend(policies.policies);
+    Ok(effective)
+}
+
+#[tauri::command]
+pub async fn set_memory_policy(agent_id: String, policy: MemoryPolicy) -> Result<(), String> {
+    let mut policies = PersonaPolicies::load();
+    policies.policies.insert(agent_id, policy);
+    policies.save().map_err(|e| e.to_string())
+}
+
+/// Let retrieval in the current session return private documents
+///
+/// The unlock ends with the session (mode switch, conversation switch or
+/// restart).
+#[tauri::command]
+pub async fn unlock_private_documents(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let mut session = state.session.lock();
+    session.private_unlocked = true;
+    info!("Private documents unlocked for session {}", session.run_id);
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn lock_private_documents(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let mut session = state.session.lock();
+    session.private_unlocked = false;
+    info!("Private documents locked");
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_store::MemoryStore;
+
+    fn store() -> MemoryStore {
+        let mut store = MemoryStore::new();
+        let mut profile = HashMap::new();
+        profile.insert("kind".to_string(), serde_json::json!(PROFILE_KIND));
+
+        store.add("Companion secret", None, Some("companion".to_string()), None, HashMap::new()).unwrap();
+        store.add("Story event", None, Some("youniverse".to_string()), None, HashMap::new()).unwrap();
+        store.add("User likes tea", None, None, None, profile).unwrap();
+        store
+    }
+
+    fn visible(store: &MemoryStore, policy: MemoryPolicy) -> Vec<String> {
+        let scope = AccessScope {
+            agent_id: "youniverse".to_string(),
+            policy,
+            private_unlocked: false,
+        };
+        let mut contents: Vec<String> = store
+            .get_all(&scope.filters(), 10)
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        contents.sort();
+        contents
+    }
+
+    #[test]
+    fn test_policies_limit_retrieval() {
+        let store = store();
+
+        assert_eq!(visible(&store, MemoryPolicy::ShareAll).len(), 3);
+        assert_eq!(visible(&store, MemoryPolicy::Isolated), vec!["Story event"]);
+        assert_eq!(
+            visible(&store, MemoryPolicy::ProfileReadOnly),
+            vec!["Story event", "User likes tea"]
+        );
+    }
+
+    #[test]
+    fn test_search_respects_scope() {
+        let store = store();
+        let scope = PersonaPolicies::default().scope_for("youniverse");
+
+        assert!(store.search("secret", Some(&scope.filters()), 10).is_empty());
+        assert!(!scope.can_write_profile());
+    }
+
+    #[test]
+    fn test_profile_is_read_only() {
+        let mut store = store();
+        let mut fact = HashMap::from([("kind".to_string(), serde_json::json!("fact"))]);
+        tag_profile(&mut fact);
+        tag_profile(&mut fact);
+        assert_eq!(fact[TAGS_KEY], serde_json::json!([PROFILE_KIND]));
+        let id = store.add("User's dog is named Rex", None, None, None, fact).unwrap();
+        let fact = store.get(&id).unwrap();
+        let story = store.search("story event", None, 1).remove(0);
+
+        let roleplay = PersonaPolicies::default().scope_for("youniverse");
+        assert!(roleplay.allows(&fact));
+        assert!(!roleplay.can_modify(&fact));
+        assert!(roleplay.can_modify(&story));
+        assert!(PersonaPolicies::default().scope_for("companion").can_modify(&fact));
+    }
+
+    #[test]
+    fn test_private_documents_need_unlock() {
+        let mut store = store();
+        let mut private = HashMap::new();
+        private.insert(SENSITIVITY_KEY.to_string(), serde_json::json!(Sensitivity::Private));
+        store.add("Lab results", None, None, None, private).unwrap();
+
+        let mut scope = PersonaPolicies::default().scope_for("companion");
+        assert!(store.search("lab results", Some(&scope.filters()), 10).is_empty());
+        assert_eq!(store.get_all(&scope.filters(), 10).len(), 3);
+
+        scope.private_unlocked = true;
+        assert_eq!(store.search("lab results", Some(&scope.filters()), 10).len(), 1);
+    }
+}
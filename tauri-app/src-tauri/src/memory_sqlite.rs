@@ -0,0 +1,797 @@
+// Memory SQLite Module - Persistent MemoryBackend
+//
+// Memories live in a single `memories` table. Session ids are real indexed
+// columns so the common filters (user/agent/run), ordering and paging are
+// answered by SQLite; metadata, filter expression and persona access filters
+// are applied in Rust on the streamed rows. With encryption at rest on, content and
+// metadata are stored sealed (see `encryption`) and text search moves to
+// Rust too. Each memory's earlier versions are kept in `memory_history`
+// as sealed JSON snapshots. Expiry times are an indexed column so pruning
+// finds expired memories in SQL. The schema is versioned with `PRAGMA user_version` and upgraded
+// by the MIGRATIONS list on open.
+
+use crate::encryption;
+use crate::memory_history::{MemoryEvent, MemoryVersion};
+use crate::memory_store::{
+    apply_update, matches_filters, MemoryBackend, MemoryCursor, MemoryFilters, MemoryItem, MemorySort, MemoryUpdate,
+    SortField,
+};
+use anyhow::{Context, Result};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+/// Schema migrations, applied in order; index + 1 is the schema version
+const MIGRATIONS: &[&str] = &[
+    // v1: initial schema
+    "CREATE TABLE memories (
+        id TEXT PRIMARY KEY,
+        content TEXT NOT NULL,
+        user_id TEXT,
+        agent_id TEXT,
+        run_id TEXT,
+        metadata TEXT NOT NULL DEFAULT '{}',
+        embedding BLOB,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_memories_user ON memories(user_id);
+    CREATE INDEX idx_memories_agent ON memories(agent_id);
+    CREATE INDEX idx_memories_run ON memories(run_id);
+    CREATE INDEX idx_memories_created ON memories(created_at);",
+    // v2: version history of updated, deleted and restored memories
+    "CREATE TABLE memory_history (
+        memory_id TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        event TEXT NOT NULL,
+        memory TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL,
+        PRIMARY KEY (memory_id, version)
+    );",
+    // v3: optional expiry, for pruning
+    "ALTER TABLE memories ADD COLUMN expires_at INTEGER;
+    CREATE INDEX idx_memories_expires ON memories(expires_at);",
+];
+
+const COLUMNS: &str =
+    "id, content, user_id, agent_id, run_id, metadata, embedding, created_at, updated_at, expires_at";
+
+/// Ordering and window for `SqliteBackend::select`
+#[derive(Clone, Copy)]
+struct Page<'a> {
+    sort: MemorySort,
+    after: Option<&'a MemoryCursor>,
+    offset: usize,
+    limit: usize,
+}
+
+impl Page<'_> {
+    /// The newest `limit` rows
+    fn first(limit: usize) -> Self {
+        Self {
+            sort: MemorySort::default(),
+            after: None,
+            offset: 0,
+            limit,
+        }
+    }
+}
+
+/// MemoryBackend stored in a SQLite database file
+pub struct SqliteBackend {
+    conn: Connection,
+}
+
+impl SqliteBackend {
+    /// Open (or create) the database at `path` and bring its schema up to date
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open memory database {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let mut backend = Self { conn };
+        backend.migrate()?;
+        Ok(backend)
+    }
+
+    /// Current schema version
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: i64 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let current = self.schema_version()?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)
+                .with_context(|| format!("Memory database migration v{} failed", version))?;
+            tx.pragma_update(None, "user_version", version as i64)?;
+            tx.commit()?;
+            info!("Memory database migrated to v{}", version);
+        }
+        Ok(())
+    }
+
+    /// Rows matching `filters` (and `query`, if any) in `page` order
+    fn select(&self, query: Option<&str>, filters: &MemoryFilters, page: Page) -> Result<Vec<MemoryItem>> {
+        // Encrypted content can't be matched by SQL; compare it here instead
+        let text_query = query
+            .filter(|_| encryption::is_enabled())
+            .map(str::to_lowercase);
+        let (mut clause, args) = where_clause(query.filter(|_| text_query.is_none()), filters);
+        let column = match page.sort.field {
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+        };
+        let (direction, comparison) = if page.sort.descending { ("DESC", "<") } else { ("ASC", ">") };
+        if let Some(cursor) = page.after {
+            // Nanoseconds are inlined; only the id needs binding
+            let condition = format!(
+                "({}, id) {} ({}, ?{})",
+                column,
+                comparison,
+                to_nanos(cursor.time),
+                args.len() + 1
+            );
+            clause = if clause.is_empty() {
+                format!(" WHERE {}", condition)
+            } else {
+                format!("{} AND {}", clause, condition)
+            };
+        }
+        let args: Vec<&str> = args
+            .iter()
+            .map(String::as_str)
+            .chain(page.after.map(|cursor| cursor.id.as_str()))
+            .collect();
+
+        // Metadata, expression, access and encrypted text filters are checked
+        // here, so SQL can only apply the limit and offset when there are none
+        let post_filter = needs_post_filter(filters) || text_query.is_some();
+        let order = format!(" ORDER BY {} {}, id {}", column, direction, direction);
+        let sql = if post_filter {
+            format!("SELECT {} FROM memories{}{}", COLUMNS, clause, order)
+        } else {
+            format!(
+                "SELECT {} FROM memories{}{} LIMIT {} OFFSET {}",
+                COLUMNS,
+                clause,
+                order,
+                page.limit.min(i64::MAX as usize),
+                page.offset.min(i64::MAX as usize)
+            )
+        };
+
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let mut rows = stmt.query(params_from_iter(args.iter()))?;
+        let mut results = Vec::new();
+        let mut skipped = 0;
+        while results.len() < page.limit {
+            let Some(row) = rows.next()? else { break };
+            let memory = read_row(row)?;
+            let text_matches = text_query
+                .as_ref()
+                .map_or(true, |query| memory.content.to_lowercase().contains(query.as_str()));
+            if post_filter && !(text_matches && matches_filters(&memory, filters)) {
+                continue;
+            }
+            if post_filter && skipped < page.offset {
+                skipped += 1;
+                continue;
+            }
+            results.push(memory);
+        }
+        Ok(results)
+    }
+
+    fn matching_ids(&self, filters: &MemoryFilters) -> Result<Vec<String>> {
+        Ok(self
+            .select(None, filters, Page::first(usize::MAX))?
+            .into_iter()
+            .map(|memory| memory.id)
+            .collect())
+    }
+
+    fn write(&self, memory: &MemoryItem) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO memories ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                COLUMNS
+            ),
+            params![
+                memory.id,
+                encryption::seal_text(&memory.content)?,
+                memory.user_id,
+                memory.agent_id,
+                memory.run_id,
+                encryption::seal_text(&serde_json::to_string(&memory.metadata)?)?,
+                memory.embedding.as_deref().map(encode_embedding),
+                to_nanos(memory.created_at),
+                to_nanos(memory.updated_at),
+                memory.expires_at.map(to_nanos),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn write_version(&self, version: &MemoryVersion) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO memory_history (memory_id, version, event, memory, recorded_at)
+             SELECT ?1, COALESCE(MAX(version), 0) + 1, ?2, ?3, ?4 FROM memory_history WHERE memory_id = ?1",
+            params![
+                version.memory.id,
+                version.event.as_str(),
+                encryption::seal_text(&serde_json::to_string(&version.memory)?)?,
+                to_nanos(version.recorded_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Versions of `memory_id`, or of every memory when `None`
+    fn load_history(&self, memory_id: Option<&str>) -> Result<Vec<MemoryVersion>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT version, event, memory, recorded_at FROM memory_history
+             WHERE ?1 IS NULL OR memory_id = ?1 ORDER BY memory_id, version",
+        )?;
+        let mut rows = stmt.query([memory_id])?;
+        let mut versions = Vec::new();
+        while let Some(row) = rows.next()? {
+            let event: String = row.get(1)?;
+            let memory = encryption::open_text(row.get(2)?)?;
+            versions.push(MemoryVersion {
+                version: row.get::<_, i64>(0)? as usize,
+                event: MemoryEvent::parse(&event).with_context(|| format!("Unknown memory event {}", event))?,
+                memory: serde_json::from_str(&memory).context("Corrupt memory version")?,
+                recorded_at: from_nanos(row.get(3)?),
+            });
+        }
+        Ok(versions)
+    }
+
+    fn load(&self, memory_id: &str) -> Result<Option<MemoryItem>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("SELECT {} FROM memories WHERE id = ?1", COLUMNS))?;
+        let row = stmt.query_row([memory_id], |row| Ok(read_row(row))).optional()?;
+        row.transpose()
+    }
+}
+
+/// Whether some of `filters` can only be checked in Rust
+fn needs_post_filter(filters: &MemoryFilters) -> bool {
+    !filters.metadata.is_empty() || filters.filter.is_some() || filters.access.is_some()
+}
+
+/// SQL WHERE clause and its arguments for the indexed filters
+fn where_clause(query: Option<&str>, filters: &MemoryFilters) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut args = Vec::new();
+
+    for (column, value) in [
+        ("user_id", &filters.user_id),
+        ("agent_id", &filters.agent_id),
+        ("run_id", &filters.run_id),
+    ] {
+        if let Some(value) = value {
+            args.push(value.clone());
+            conditions.push(format!("{} = ?{}", column, args.len()));
+        }
+    }
+
+    if !filters.include_expired {
+        // Inlined like page cursors, as it changes with every query
+        conditions.push(format!(
+            "(expires_at IS NULL OR expires_at > {})",
+            to_nanos(SystemTime::from(crate::clock::now()))
+        ));
+    }
+
+    if let Some(query) = query {
+        // LIKE is case-insensitive for ASCII; lower() both sides for the rest
+        let escaped = query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        args.push(format!("%{}%", escaped));
+        conditions.push(format!("lower(content) LIKE ?{} ESCAPE '\\'", args.len()));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), args)
+    } else {
+        (format!(" WHERE {}", conditions.join(" AND ")), args)
+    }
+}
+
+fn read_row(row: &Row) -> Result<MemoryItem> {
+    let metadata = encryption::open_text(row.get(5)?)?;
+    let embedding: Option<Vec<u8>> = row.get(6)?;
+    Ok(MemoryItem {
+        id: row.get(0)?,
+        content: encryption::open_text(row.get(1)?)?,
+        user_id: row.get(2)?,
+        agent_id: row.get(3)?,
+        run_id: row.get(4)?,
+        metadata: serde_json::from_str::<HashMap<String, serde_json::Value>>(&metadata)
+            .context("Corrupt memory metadata")?,
+        embedding: embedding.map(|bytes| decode_embedding(&bytes)),
+        created_at: from_nanos(row.get(7)?),
+        updated_at: from_nanos(row.get(8)?),
+        expires_at: row.get::<_, Option<i64>>(9)?.map(from_nanos),
+    })
+}
+
+/// Embeddings are stored as little-endian f32s
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+fn to_nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos().min(i64::MAX as u128) as i64)
+        .unwrap_or(0)
+}
+
+fn from_nanos(nanos: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos.max(0) as u64)
+}
+
+// The trait is infallible like the in-memory store; database errors are
+// logged and reported as "nothing found / nothing changed".
+fn logged<T>(operation: &str, result: Result<T>, fallback: T) -> T {
+    result.unwrap_or_else(|e| {
+        error!("Memory database {} failed: {}", operation, e);
+        fallback
+    })
+}
+
+impl MemoryBackend for SqliteBackend {
+    fn insert(&mut self, memory: MemoryItem) {
+        logged("insert", self.write(&memory), ());
+    }
+
+    fn insert_batch(&mut self, memories: Vec<MemoryItem>) {
+        let result = self.conn.unchecked_transaction().map_err(anyhow::Error::from).and_then(|tx| {
+            for memory in &memories {
+                self.write(memory)?;
+            }
+            tx.commit()?;
+            Ok(())
+        });
+        logged("insert_batch", result, ());
+    }
+
+    fn update_batch(&mut self, updates: Vec<(MemoryUpdate, Option<Vec<f32>>)>) -> Vec<bool> {
+        let count = updates.len();
+        let result = self.conn.unchecked_transaction().map_err(anyhow::Error::from).and_then(|tx| {
+            let mut updated = Vec::with_capacity(updates.len());
+            for (update, embedding) in updates {
+                let Some(mut memory) = self.load(&update.memory_id)? else {
+                    updated.push(false);
+                    continue;
+                };
+                apply_update(&mut memory, update.content, update.metadata);
+                if embedding.is_some() {
+                    memory.embedding = embedding;
+                }
+                self.write(&memory)?;
+                updated.push(true);
+            }
+            tx.commit()?;
+            Ok(updated)
+        });
+        logged("update_batch", result, vec![false; count])
+    }
+
+    fn delete_batch(&mut self, memory_ids: &[String]) -> Vec<bool> {
+        let result = self.conn.unchecked_transaction().map_err(anyhow::Error::from).and_then(|tx| {
+            let deleted = {
+                let mut stmt = tx.prepare_cached("DELETE FROM memories WHERE id = ?1")?;
+                memory_ids
+                    .iter()
+                    .map(|id| stmt.execute([id]).map(|changed| changed > 0))
+                    .collect::<rusqlite::Result<Vec<bool>>>()?
+            };
+            tx.commit()?;
+            Ok(deleted)
+        });
+        logged("delete_batch", result, vec![false; memory_ids.len()])
+    }
+
+    fn get(&self, memory_id: &str) -> Option<MemoryItem> {
+        logged("get", self.load(memory_id), None)
+    }
+
+    fn get_all(&self, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem> {
+        logged("get_all", self.select(None, filters, Page::first(limit)), Vec::new())
+    }
+
+    fn search(&self, query: &str, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem> {
+        logged("search", self.select(Some(query), filters, Page::first(limit)), Vec::new())
+    }
+
+    fn get_page(
+        &self,
+        filters: &MemoryFilters,
+        sort: MemorySort,
+        after: Option<&MemoryCursor>,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<MemoryItem> {
+        let page = Page {
+            sort,
+            after,
+            offset: if after.is_some() { 0 } else { offset },
+            limit,
+        };
+        logged("get_page", self.select(None, filters, page), Vec::new())
+    }
+
+    fn update(
+        &mut self,
+        memory_id: &str,
+        content: Option<String>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> bool {
+        let result = self.load(memory_id).and_then(|memory| match memory {
+            Some(mut memory) => {
+                apply_update(&mut memory, content, metadata);
+                self.write(&memory).map(|_| true)
+            }
+            None => Ok(false),
+        });
+        logged("update", result, false)
+    }
+
+    fn set_embedding(&mut self, memory_id: &str, embedding: Vec<f32>) -> bool {
+        let result = self
+            .conn
+            .execute(
+                "UPDATE memories SET embedding = ?1 WHERE id = ?2",
+                params![encode_embedding(&embedding), memory_id],
+            )
+            .map(|changed| changed > 0)
+            .map_err(Into::into);
+        logged("set_embedding", result, false)
+    }
+
+    fn delete(&mut self, memory_id: &str) -> bool {
+        let result = self
+            .conn
+            .execute("DELETE FROM memories WHERE id = ?1", [memory_id])
+            .map(|changed| changed > 0)
+            .map_err(Into::into);
+        logged("delete", result, false)
+    }
+
+    fn delete_all(&mut self, filters: &MemoryFilters) -> usize {
+        let result = self.matching_ids(filters).and_then(|ids| {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached("DELETE FROM memories WHERE id = ?1")?;
+                for id in &ids {
+                    stmt.execute([id])?;
+                }
+            }
+            tx.commit()?;
+            Ok(ids.len())
+        });
+        logged("delete_all", result, 0)
+    }
+
+    fn count(&self) -> usize {
+        let result = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM memories", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(Into::into);
+        logged("count", result, 0)
+    }
+
+    fn expired_ids(&self, now: SystemTime) -> Vec<String> {
+        let result = self
+            .conn
+            .prepare_cached("SELECT id FROM memories WHERE expires_at <= ?1")
+            .and_then(|mut stmt| {
+                stmt.query_map([to_nanos(now)], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(Into::into);
+        logged("expired_ids", result, Vec::new())
+    }
+
+    fn embedded_ids(&self) -> Vec<String> {
+        let result = self
+            .conn
+            .prepare_cached("SELECT id FROM memories WHERE embedding IS NOT NULL")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(Into::into);
+        logged("embedded_ids", result, Vec::new())
+    }
+
+    fn count_filtered(&self, filters: &MemoryFilters) -> usize {
+        if needs_post_filter(filters) {
+            return logged("count", self.matching_ids(filters).map(|ids| ids.len()), 0);
+        }
+        let (clause, args) = where_clause(None, filters);
+        let result = self
+            .conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM memories{}", clause),
+                params_from_iter(args.iter()),
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .map_err(Into::into);
+        logged("count", result, 0)
+    }
+
+    fn record_history(&mut self, versions: Vec<MemoryVersion>) {
+        let result = self.conn.unchecked_transaction().map_err(anyhow::Error::from).and_then(|tx| {
+            for version in &versions {
+                self.write_version(version)?;
+            }
+            tx.commit()?;
+            Ok(())
+        });
+        logged("record_history", result, ());
+    }
+
+    fn history(&self, memory_id: &str) -> Vec<MemoryVersion> {
+        logged("history", self.load_history(Some(memory_id)), Vec::new())
+    }
+
+    fn delete_history(&mut self, filters: &MemoryFilters) -> usize {
+        let result = self.load_history(None).and_then(|versions| {
+            let tx = self.conn.transaction()?;
+            let mut removed = 0;
+            {
+                let mut stmt = tx.prepare_cached("DELETE FROM memory_history WHERE memory_id = ?1 AND version = ?2")?;
+                for version in versions.iter().filter(|version| matches_filters(&version.memory, filters)) {
+                    removed += stmt.execute(params![version.memory.id, version.version as i64])?;
+                }
+            }
+            tx.commit()?;
+            Ok(removed)
+        });
+        logged("delete_history", result, 0)
+    }
+
+    fn rewrite_all(&mut self) -> usize {
+        let everything = MemoryFilters::default().including_expired();
+        let result = self.select(None, &everything, Page::first(usize::MAX)).and_then(|memories| {
+            let versions = self.load_history(None)?;
+            let tx = self.conn.unchecked_transaction()?;
+            for memory in &memories {
+                self.write(memory)?;
+            }
+            for version in &versions {
+                tx.execute(
+                    "UPDATE memory_history SET memory = ?3 WHERE memory_id = ?1 AND version = ?2",
+                    params![
+                        version.memory.id,
+                        version.version as i64,
+                        encryption::seal_text(&serde_json::to_string(&version.memory)?)?,
+                    ],
+                )?;
+            }
+            tx.commit()?;
+            Ok(memories.len())
+        });
+        logged("rewrite_all", result, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::{Embedder, HashingEmbedder};
+    use crate::memory_store::{MemoryStore, MemoryUpdate, NewMemory};
+    use std::sync::Arc;
+
+    fn temp_db(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("auranexus_sqlite_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("memories.db")
+    }
+
+    #[test]
+    fn test_memories_survive_reopen() {
+        let path = temp_db("reopen");
+        let id = {
+            let mut store = MemoryStore::open_sqlite(&path).unwrap();
+            let mut metadata = HashMap::new();
+            metadata.insert("source".to_string(), serde_json::json!("chat"));
+            let id = store.add("Likes green tea", Some("user_1".to_string()), None, None, metadata).unwrap();
+            store.add("Has a cat named Miso", Some("user_2".to_string()), None, None, HashMap::new()).unwrap();
+            assert!(store.set_embedding(&id, vec![0.25, -1.5]));
+            id
+        };
+
+        let store = MemoryStore::open_sqlite(&path).unwrap();
+        assert_eq!(store.count(), 2);
+        let memory = store.get(&id).unwrap();
+        assert_eq!(memory.content, "Likes green tea");
+        assert_eq!(memory.metadata["source"], "chat");
+        assert_eq!(memory.embedding, Some(vec![0.25, -1.5]));
+
+        let filters = MemoryFilters {
+            user_id: Some("user_2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.count_filtered(&filters), 1);
+        assert_eq!(store.search("MISO", None, 10).len(), 1);
+        assert!(store.search("100%", None, 10).is_empty());
+    }
+
+    #[test]
+    fn test_update_delete_and_ordering() {
+        let path = temp_db("update");
+        let mut store = MemoryStore::open_sqlite(&path).unwrap();
+        let first = store.add("First", Some("u".to_string()), None, None, HashMap::new()).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        let second = store.add("Second", Some("u".to_string()), None, None, HashMap::new()).unwrap();
+
+        let all = store.get_all(&MemoryFilters::default(), 10);
+        assert_eq!(all[0].id, second);
+        assert_eq!(store.get_all(&MemoryFilters::default(), 1).len(), 1);
+
+        store.set_embedding(&first, vec![1.0]);
+        assert!(store.update(&first, Some("First, edited".to_string()), None).unwrap());
+        let edited = store.get(&first).unwrap();
+        assert_eq!(edited.content, "First, edited");
+        assert!(edited.embedding.is_none());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("user_id".to_string(), serde_json::json!("u"));
+        let filters = MemoryFilters { metadata, ..Default::default() };
+        assert_eq!(store.delete_all(&filters), 2);
+        assert_eq!(store.count(), 0);
+    }
+
+    #[test]
+    fn test_vector_index_follows_database() {
+        let path = temp_db("index");
+        let index_path = path.with_file_name("memories.hnsw");
+        let embedder: Arc<dyn Embedder> = Arc::new(HashingEmbedder::default());
+        let open = || {
+            MemoryStore::open_sqlite(&path)
+                .unwrap()
+                .with_index_file(&index_path)
+                .with_embedder(embedder.clone())
+        };
+
+        let (kept, removed) = {
+            let mut store = open();
+            let kept = store.add("Grandma's lasagna recipe", None, None, None, HashMap::new()).unwrap();
+            let removed = store.add("Lasagna needs ricotta", None, None, None, HashMap::new()).unwrap();
+            store.save_index();
+            // Deleted after the last save: the reopened index must drop it
+            store.delete(&removed);
+            (kept, removed)
+        };
+
+        let store = open();
+        let results = store.search("lasagna", None, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, kept);
+        assert!(store.get(&removed).is_none());
+    }
+
+    #[test]
+    fn test_batch_operations() {
+        let embedder: Arc<dyn Embedder> = Arc::new(HashingEmbedder::default());
+        let mut store = MemoryStore::open_sqlite(temp_db("batch")).unwrap().with_embedder(embedder);
+        let memory = |content: &str| NewMemory {
+            content: content.to_string(),
+            user_id: Some("u".to_string()),
+            ..Default::default()
+        };
+        let mut refused = memory("Bad metadata");
+        refused.metadata.insert(String::new(), serde_json::json!(1));
+
+        let added = store.add_batch(vec![memory("Walks the dog daily"), refused, memory("Allergic to penicillin")]);
+        assert!(added[1].is_err());
+        let ids = [added[0].clone().unwrap(), added[2].clone().unwrap()];
+        assert_eq!(store.count(), 2);
+        assert!(store.get(&ids[0]).unwrap().embedding.is_some());
+        assert_eq!(store.search("penicillin", None, 1)[0].id, ids[1]);
+
+        let updated = store.update_batch(vec![
+            MemoryUpdate {
+                memory_id: ids[0].clone(),
+                content: Some("Walks the dog twice a day".to_string()),
+                ..Default::default()
+            },
+            MemoryUpdate {
+                memory_id: "missing".to_string(),
+                content: Some("Nothing".to_string()),
+                ..Default::default()
+            },
+        ]);
+        assert_eq!(updated.into_iter().map(Result::unwrap).collect::<Vec<_>>(), [true, false]);
+        let edited = store.get(&ids[0]).unwrap();
+        assert_eq!(edited.content, "Walks the dog twice a day");
+        assert!(edited.embedding.is_some());
+
+        assert_eq!(store.delete_batch(&[ids[1].clone(), "missing".to_string()]), [true, false]);
+        assert_eq!(store.count(), 1);
+    }
+
+    #[test]
+    fn test_paging_and_sorting() {
+        use crate::memory_store::{MemorySort, PageRequest, SortField};
+
+        let path = temp_db("paging");
+        // The database pages in SQL; the in-memory store uses the default
+        for mut store in [MemoryStore::open_sqlite(&path).unwrap(), MemoryStore::new()] {
+            let mut ids = Vec::new();
+            for i in 0..5 {
+                ids.push(store.add(&format!("Memory {}", i), Some("u".to_string()), None, None, HashMap::new()).unwrap());
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            store.add("Someone else's", Some("v".to_string()), None, None, HashMap::new()).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+            store.update(&ids[1], Some("Memory 1, edited".to_string()), None).unwrap();
+            let filters = MemoryFilters {
+                user_id: Some("u".to_string()),
+                ..Default::default()
+            };
+
+            let mut request = PageRequest { limit: 2, ..Default::default() };
+            let mut seen = Vec::new();
+            loop {
+                let page = store.get_page(&filters, &request).unwrap();
+                assert_eq!(page.total, 5);
+                seen.extend(page.items.into_iter().map(|memory| memory.id));
+                match page.next_cursor {
+                    Some(cursor) => request.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            let newest_first: Vec<String> = ids.iter().rev().cloned().collect();
+            assert_eq!(seen, newest_first);
+
+            let by_update = PageRequest {
+                sort: MemorySort { field: SortField::UpdatedAt, descending: false },
+                offset: 3,
+                limit: 10,
+                cursor: None,
+            };
+            let page = store.get_page(&filters, &by_update).unwrap();
+            let page_ids: Vec<&str> = page.items.iter().map(|memory| memory.id.as_str()).collect();
+            assert_eq!(page_ids, [ids[4].as_str(), ids[1].as_str()]);
+            assert!(page.next_cursor.is_none());
+
+            request.cursor = Some("not a cursor".to_string());
+            assert!(store.get_page(&filters, &request).is_err());
+        }
+    }
+
+    #[test]
+    fn test_schema_is_versioned() {
+        let backend = SqliteBackend::open(temp_db("schema")).unwrap();
+        assert_eq!(backend.schema_version().unwrap(), MIGRATIONS.len());
+    }
+}
@@ -2,9 +2,20 @@
 // Original: https://github.com/mem0ai/mem0
 // License: Apache 2.0
 
+use crate::embeddings::{cosine_similarity, Embedder};
+use crate::memory_history::{MemoryEvent, MemoryVersion};
+use crate::memory_policy::AccessScope;
+use crate::memory_sqlite::SqliteBackend;
+use crate::metadata_filter::MetadataFilter;
+use crate::vector_index::VectorIndex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::SystemTime;
+use parking_lot::Mutex;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 use uuid::Uuid;
 
 /// Memory item stored in the database
@@ -16,8 +27,108 @@ pub struct MemoryItem {
     pub agent_id: Option<String>,
     pub run_id: Option<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Vector for similarity search, if one has been computed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
     pub created_at: SystemTime,
     pub updated_at: SystemTime,
+    /// When the memory stops being returned and is pruned; never if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<SystemTime>,
+}
+
+impl MemoryItem {
+    /// Whether the memory's expiry is at or before `now`
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// How search scores fade as memories age
+///
+/// A memory keeps `1 - weight` of its score however old it is; the rest
+/// halves every `half_life_days` since it was last updated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecencyDecay {
+    pub weight: f32,
+    pub half_life_days: f32,
+}
+
+impl RecencyDecay {
+    /// Multiplier for the score of `memory` at `now`
+    pub fn factor(&self, memory: &MemoryItem, now: SystemTime) -> f32 {
+        let age_days = now
+            .duration_since(memory.updated_at)
+            .unwrap_or_default()
+            .as_secs_f32()
+            / 86_400.0;
+        let recency = 0.5f32.powf(age_days / self.half_life_days.max(f32::EPSILON));
+        1.0 - self.weight + self.weight * recency
+    }
+}
+
+/// Serialized metadata larger than this is refused
+pub const MAX_METADATA_BYTES: usize = 64 * 1024;
+
+/// Longest metadata key
+const MAX_METADATA_KEY_CHARS: usize = 128;
+
+/// Deepest nesting of arrays/objects in a metadata value
+const MAX_METADATA_DEPTH: usize = 4;
+
+/// Metadata keys set from a memory's ids; callers can't change them
+pub const RESERVED_METADATA_KEYS: [&str; 3] = ["user_id", "agent_id", "run_id"];
+
+/// Why metadata was refused
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Metadata key '{0}' is reserved for the memory's ids")]
+    ReservedKey(String),
+    #[error("Metadata key '{0}' is empty or too long")]
+    InvalidKey(String),
+    #[error("Metadata value for '{0}' is nested too deeply")]
+    TooDeep(String),
+    #[error("Metadata is {size} bytes (limit {limit})")]
+    TooLarge { size: usize, limit: usize },
+}
+
+fn depth(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        serde_json::Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Check keys, nesting and total size of `metadata`
+///
+/// Reserved keys may appear only with the value the memory's own id gives
+/// them (`ids` in `RESERVED_METADATA_KEYS` order).
+pub fn validate_metadata(
+    metadata: &HashMap<String, serde_json::Value>,
+    ids: [Option<&str>; 3],
+) -> Result<(), MetadataError> {
+    for (key, value) in metadata {
+        if key.is_empty() || key.chars().count() > MAX_METADATA_KEY_CHARS {
+            return Err(MetadataError::InvalidKey(key.chars().take(MAX_METADATA_KEY_CHARS).collect()));
+        }
+        if let Some(i) = RESERVED_METADATA_KEYS.iter().position(|reserved| reserved == key) {
+            if value.as_str() != ids[i] {
+                return Err(MetadataError::ReservedKey(key.clone()));
+            }
+        }
+        if depth(value) > MAX_METADATA_DEPTH {
+            return Err(MetadataError::TooDeep(key.clone()));
+        }
+    }
+    let size = serde_json::to_vec(metadata).map(|json| json.len()).unwrap_or(usize::MAX);
+    if size > MAX_METADATA_BYTES {
+        return Err(MetadataError::TooLarge {
+            size,
+            limit: MAX_METADATA_BYTES,
+        });
+    }
+    Ok(())
 }
 
 /// Filter criteria for querying memories
@@ -27,24 +138,437 @@ pub struct MemoryFilters {
     pub agent_id: Option<String>,
     pub run_id: Option<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Ranges, `in`/`not in`, existence and AND/OR/NOT over metadata
+    pub filter: Option<MetadataFilter>,
+    /// Persona access policy; memories outside the scope are never returned
+    pub access: Option<AccessScope>,
+    /// Match memories past their expiry too; only deletes and maintenance,
+    /// which must reach every stored memory, set this
+    pub include_expired: bool,
+}
+
+impl MemoryFilters {
+    /// These filters, matching expired memories as well
+    pub fn including_expired(&self) -> Self {
+        Self {
+            include_expired: true,
+            ..self.clone()
+        }
+    }
+}
+
+/// Timestamp a page of memories is ordered by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Order of a page of memories; ties are broken by id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemorySort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for MemorySort {
+    /// Newest first, like `get_all`
+    fn default() -> Self {
+        Self {
+            field: SortField::CreatedAt,
+            descending: true,
+        }
+    }
+}
+
+impl MemorySort {
+    fn key<'a>(&self, memory: &'a MemoryItem) -> (SystemTime, &'a str) {
+        match self.field {
+            SortField::CreatedAt => (memory.created_at, &memory.id),
+            SortField::UpdatedAt => (memory.updated_at, &memory.id),
+        }
+    }
+
+    fn order(&self, a: (SystemTime, &str), b: (SystemTime, &str)) -> Ordering {
+        if self.descending {
+            b.cmp(&a)
+        } else {
+            a.cmp(&b)
+        }
+    }
+
+    /// Ordering of `a` relative to `b` in this sort
+    pub fn compare(&self, a: &MemoryItem, b: &MemoryItem) -> Ordering {
+        self.order(self.key(a), self.key(b))
+    }
+
+    /// Whether `memory` comes after `cursor` in this sort
+    pub fn follows(&self, memory: &MemoryItem, cursor: &MemoryCursor) -> bool {
+        self.order(self.key(memory), (cursor.time, &cursor.id)) == Ordering::Greater
+    }
+}
+
+/// Position of the last memory on a page; the next page starts after it
+///
+/// Unlike an offset, it stays put when memories are added or deleted while
+/// the user is paging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryCursor {
+    /// The sort field's value
+    pub time: SystemTime,
+    pub id: String,
+}
+
+impl MemoryCursor {
+    pub fn after(memory: &MemoryItem, sort: MemorySort) -> Self {
+        let (time, id) = sort.key(memory);
+        Self {
+            time,
+            id: id.to_string(),
+        }
+    }
+
+    /// Opaque form handed to the frontend: `<nanoseconds>:<id>`
+    pub fn encode(&self) -> String {
+        let nanos = self.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        format!("{}:{}", nanos, self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let (nanos, id) = cursor.split_once(':')?;
+        let nanos: u64 = nanos.parse().ok()?;
+        (!id.is_empty()).then(|| Self {
+            time: UNIX_EPOCH + Duration::from_nanos(nanos),
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Which page of memories to return
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageRequest {
+    pub sort: MemorySort,
+    /// Memories to skip; ignored when `cursor` is set
+    pub offset: usize,
+    pub limit: usize,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            sort: MemorySort::default(),
+            offset: 0,
+            limit: 50,
+            cursor: None,
+        }
+    }
+}
+
+/// One page of memories and the total matching the filters
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryPage<T = MemoryItem> {
+    pub items: Vec<T>,
+    pub total: usize,
+    /// Pass back as `PageRequest::cursor` for the next page; `None` on the
+    /// last one
+    pub next_cursor: Option<String>,
+}
+
+/// Storage behind a `MemoryStore`
+///
+/// Backends own persistence and querying; `MemoryStore` builds new items and
+/// forwards everything else. Results are returned newest first.
+pub trait MemoryBackend: Send {
+    fn insert(&mut self, memory: MemoryItem);
+    fn get(&self, memory_id: &str) -> Option<MemoryItem>;
+    fn get_all(&self, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem>;
+    /// Case-insensitive substring search
+    fn search(&self, query: &str, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem>;
+    fn update(
+        &mut self,
+        memory_id: &str,
+        content: Option<String>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> bool;
+    fn set_embedding(&mut self, memory_id: &str, embedding: Vec<f32>) -> bool;
+    fn delete(&mut self, memory_id: &str) -> bool;
+    fn delete_all(&mut self, filters: &MemoryFilters) -> usize;
+    fn count(&self) -> usize;
+    fn count_filtered(&self, filters: &MemoryFilters) -> usize;
+    /// Ids of memories whose expiry is at or before `now`
+    fn expired_ids(&self, now: SystemTime) -> Vec<String> {
+        self.get_all(&MemoryFilters::default().including_expired(), usize::MAX)
+            .into_iter()
+            .filter(|memory| memory.is_expired(now))
+            .map(|memory| memory.id)
+            .collect()
+    }
+    /// Append versions to the memories' histories, numbering each from 1
+    fn record_history(&mut self, versions: Vec<MemoryVersion>);
+    /// Recorded versions of a memory, oldest first
+    fn history(&self, memory_id: &str) -> Vec<MemoryVersion>;
+    /// Drop the versions whose memory matches `filters`; returns how many
+    fn delete_history(&mut self, filters: &MemoryFilters) -> usize;
+
+    /// Write every memory again in the current storage format (e.g. after
+    /// encryption at rest is switched); returns how many
+    fn rewrite_all(&mut self) -> usize {
+        let memories = self.get_all(&MemoryFilters::default().including_expired(), usize::MAX);
+        let count = memories.len();
+        for memory in memories {
+            self.insert(memory);
+        }
+        count
+    }
+
+    /// Memories ranked by cosine similarity to `query`, best first
+    ///
+    /// Memories without a vector of the same dimensionality are skipped.
+    fn search_similar(
+        &self,
+        query: &[f32],
+        filters: &MemoryFilters,
+        limit: usize,
+    ) -> Vec<(MemoryItem, f32)> {
+        let mut scored: Vec<(MemoryItem, f32)> = self
+            .get_all(filters, usize::MAX)
+            .into_iter()
+            .filter_map(|memory| {
+                let embedding = memory.embedding.as_deref()?;
+                if embedding.len() != query.len() {
+                    return None;
+                }
+                let score = cosine_similarity(query, embedding);
+                (score > 0.0).then_some((memory, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+        scored
+    }
+
+    /// Up to `limit` memories in `sort` order, starting after `after` (or
+    /// else skipping `offset`); override to page in the database
+    fn get_page(
+        &self,
+        filters: &MemoryFilters,
+        sort: MemorySort,
+        after: Option<&MemoryCursor>,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<MemoryItem> {
+        let mut memories = self.get_all(filters, usize::MAX);
+        memories.sort_by(|a, b| sort.compare(a, b));
+        let start = match after {
+            Some(cursor) => memories.partition_point(|memory| !sort.follows(memory, cursor)),
+            None => offset,
+        };
+        memories.into_iter().skip(start).take(limit).collect()
+    }
+
+    /// Insert several memories (override to store them together)
+    fn insert_batch(&mut self, memories: Vec<MemoryItem>) {
+        for memory in memories {
+            self.insert(memory);
+        }
+    }
+
+    /// Apply several updates, each with its new vector if the content
+    /// changed; `false` for memories that weren't found
+    fn update_batch(&mut self, updates: Vec<(MemoryUpdate, Option<Vec<f32>>)>) -> Vec<bool> {
+        updates
+            .into_iter()
+            .map(|(update, embedding)| {
+                let updated = self.update(&update.memory_id, update.content, update.metadata);
+                if let (true, Some(embedding)) = (updated, embedding) {
+                    self.set_embedding(&update.memory_id, embedding);
+                }
+                updated
+            })
+            .collect()
+    }
+
+    /// Delete several memories; `false` for ids that weren't found
+    fn delete_batch(&mut self, memory_ids: &[String]) -> Vec<bool> {
+        memory_ids.iter().map(|id| self.delete(id)).collect()
+    }
+
+    /// Ids of memories that have an embedding
+    fn embedded_ids(&self) -> Vec<String> {
+        self.get_all(&MemoryFilters::default().including_expired(), usize::MAX)
+            .into_iter()
+            .filter(|memory| memory.embedding.is_some())
+            .map(|memory| memory.id)
+            .collect()
+    }
+}
+
+/// Index changes between automatic saves of the vector index file
+const INDEX_SAVE_INTERVAL: usize = 256;
+
+/// A memory to add with `MemoryStore::add_batch`
+#[derive(Debug, Clone, Default)]
+pub struct NewMemory {
+    pub content: String,
+    pub user_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub run_id: Option<String>,
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Vector computed by the caller; embedded with the batch when `None`
+    pub embedding: Option<Vec<f32>>,
+    pub expires_at: Option<SystemTime>,
+}
+
+/// A change for `MemoryStore::update_batch`; `None` leaves a field as it is
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUpdate {
+    pub memory_id: String,
+    pub content: Option<String>,
+    /// Merged into the existing metadata
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
 /// Memory store for managing conversation memories
 /// 
 /// Translated from mem0's Python implementation to pure Rust.
 /// Provides session-scoped memory storage with flexible filtering.
+/// Storage is pluggable: in-memory by default, or SQLite via `open_sqlite`.
+/// With an embedder attached, new memories are embedded and `search` ranks by
+/// cosine similarity using an HNSW vector index. Expired memories are left
+/// out of searches, listings and counts until `prune_expired` deletes them,
+/// and with a recency decay set older memories rank below fresher ones of
+/// similar relevance.
 pub struct MemoryStore {
-    memories: HashMap<String, MemoryItem>,
+    backend: Box<dyn MemoryBackend>,
+    embedder: Option<Arc<dyn Embedder>>,
+    index: Option<VectorIndex>,
+    /// Where the vector index is persisted, if anywhere
+    index_path: Option<PathBuf>,
+    unsaved_index_changes: usize,
+    recency: Option<RecencyDecay>,
 }
 
 impl MemoryStore {
-    /// Create a new memory store
+    /// Create a new (in-memory) memory store
     pub fn new() -> Self {
+        Self::with_backend(InMemoryBackend::default())
+    }
+
+    /// Create a store on top of `backend`
+    pub fn with_backend(backend: impl MemoryBackend + 'static) -> Self {
         Self {
-            memories: HashMap::new(),
+            backend: Box::new(backend),
+            embedder: None,
+            index: None,
+            index_path: None,
+            unsaved_index_changes: 0,
+            recency: None,
         }
     }
 
+    /// Persist the vector index at `path` (call before `with_embedder`)
+    pub fn with_index_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.index_path = Some(path.into());
+        self
+    }
+
+    /// Embed memories with `embedder` and search them semantically
+    ///
+    /// Loads the saved vector index if there is one and brings it in line
+    /// with the stored embeddings.
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        let dimensions = embedder.dimensions();
+        let saved = self
+            .index_path
+            .as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| match VectorIndex::load(path) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    warn!("Rebuilding vector index: {:#}", e);
+                    None
+                }
+            })
+            .filter(|index| index.dimensions() == dimensions);
+
+        self.index = Some(saved.unwrap_or_else(|| VectorIndex::new(dimensions)));
+        self.embedder = Some(embedder);
+        self.sync_index();
+        self
+    }
+
+    /// Add stored embeddings missing from the index and drop stale entries
+    fn sync_index(&mut self) {
+        let Some(index) = self.index.as_mut() else {
+            return;
+        };
+        let embedded: HashSet<String> = self.backend.embedded_ids().into_iter().collect();
+        let stale: Vec<String> = index
+            .ids()
+            .filter(|id| !embedded.contains(*id))
+            .map(str::to_string)
+            .collect();
+
+        let mut changes = stale.len();
+        for id in &stale {
+            index.remove(id);
+        }
+        for id in embedded.iter().filter(|id| !index.contains(id)) {
+            let embedding = self.backend.get(id).and_then(|memory| memory.embedding);
+            if let Some(embedding) = embedding {
+                changes += index.insert(id, &embedding) as usize;
+            }
+        }
+
+        if changes > 0 {
+            info!("Vector index synced ({} changes, {} vectors)", changes, index.len());
+            self.unsaved_index_changes += changes;
+            self.save_index();
+        }
+    }
+
+    /// Write the vector index to its file, if it has one
+    pub fn save_index(&mut self) {
+        if let (Some(index), Some(path)) = (&self.index, &self.index_path) {
+            match index.save(path) {
+                Ok(()) => self.unsaved_index_changes = 0,
+                Err(e) => warn!("Failed to save vector index: {:#}", e),
+            }
+        }
+    }
+
+    fn index_changed(&mut self) {
+        self.index_changes(1);
+    }
+
+    fn index_changes(&mut self, count: usize) {
+        self.unsaved_index_changes += count;
+        if self.unsaved_index_changes >= INDEX_SAVE_INTERVAL {
+            self.save_index();
+        }
+    }
+
+    /// Blend recency into search scores (or stop, with `None`)
+    pub fn set_recency_decay(&mut self, recency: Option<RecencyDecay>) {
+        self.recency = recency;
+    }
+
+    /// Embedder used for new memories and queries, if any
+    pub fn embedder(&self) -> Option<Arc<dyn Embedder>> {
+        self.embedder.clone()
+    }
+
+    /// Open (creating and migrating if needed) a SQLite-backed store
+    pub fn open_sqlite(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Ok(Self::with_backend(SqliteBackend::open(path)?))
+    }
+
     /// Add a new memory with session identifiers
     /// 
     /// # Arguments
@@ -55,7 +579,7 @@ impl MemoryStore {
     /// * `metadata` - Additional metadata to attach to the memory
     /// 
     /// # Returns
-    /// The ID of the created memory
+    /// The ID of the created memory, or why its metadata was refused
     /// 
     /// # Example
     /// ```rust
@@ -66,7 +590,7 @@ impl MemoryStore {
     ///     None,
     ///     None,
     ///     HashMap::new()
-    /// );
+    /// )?;
     /// ```
     pub fn add(
         &mut self,
@@ -74,37 +598,204 @@ impl MemoryStore {
         user_id: Option<String>,
         agent_id: Option<String>,
         run_id: Option<String>,
-        mut metadata: HashMap<String, serde_json::Value>,
-    ) -> String {
-        let id = Uuid::new_v4().to_string();
-        let now = SystemTime::now();
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<String, MetadataError> {
+        validate_metadata(&metadata, [user_id.as_deref(), agent_id.as_deref(), run_id.as_deref()])?;
+        let content = content.into();
+        let embedding = self.embedder.as_ref().map(|embedder| embedder.embed(&content));
+        Ok(self.insert_new(content, user_id, agent_id, run_id, metadata, embedding))
+    }
 
-        // Add session identifiers to metadata
-        if let Some(uid) = &user_id {
-            metadata.insert("user_id".to_string(), serde_json::json!(uid));
-        }
-        if let Some(aid) = &agent_id {
-            metadata.insert("agent_id".to_string(), serde_json::json!(aid));
-        }
-        if let Some(rid) = &run_id {
-            metadata.insert("run_id".to_string(), serde_json::json!(rid));
-        }
+    /// Add a memory whose embedding was already computed (e.g. in a batch)
+    pub fn add_with_embedding(
+        &mut self,
+        content: impl Into<String>,
+        user_id: Option<String>,
+        agent_id: Option<String>,
+        run_id: Option<String>,
+        metadata: HashMap<String, serde_json::Value>,
+        embedding: Vec<f32>,
+    ) -> Result<String, MetadataError> {
+        validate_metadata(&metadata, [user_id.as_deref(), agent_id.as_deref(), run_id.as_deref()])?;
+        Ok(self.insert_new(content.into(), user_id, agent_id, run_id, metadata, Some(embedding)))
+    }
 
-        let memory = MemoryItem {
-            id: id.clone(),
-            content: content.into(),
+    fn insert_new(
+        &mut self,
+        content: String,
+        user_id: Option<String>,
+        agent_id: Option<String>,
+        run_id: Option<String>,
+        metadata: HashMap<String, serde_json::Value>,
+        embedding: Option<Vec<f32>>,
+    ) -> String {
+        let memory = new_item(NewMemory {
+            content,
             user_id,
             agent_id,
             run_id,
             metadata,
-            created_at: now,
-            updated_at: now,
-        };
-
-        self.memories.insert(id.clone(), memory);
+            embedding,
+            expires_at: None,
+        });
+        let id = memory.id.clone();
+        if let (Some(index), Some(embedding)) = (self.index.as_mut(), memory.embedding.as_deref()) {
+            index.insert(&id, embedding);
+            self.index_changed();
+        }
+        self.backend.insert(memory);
         id
     }
 
+    /// Add several memories at once, returning each one's id or why its
+    /// metadata was refused
+    ///
+    /// Missing vectors are computed with one `embed_batch` call, the backend
+    /// stores the batch together (one transaction for SQLite) and the vector
+    /// index is saved at most once.
+    pub fn add_batch(&mut self, memories: Vec<NewMemory>) -> Vec<Result<String, MetadataError>> {
+        let mut results = Vec::with_capacity(memories.len());
+        let mut accepted = Vec::new();
+        for memory in memories {
+            let ids = [memory.user_id.as_deref(), memory.agent_id.as_deref(), memory.run_id.as_deref()];
+            match validate_metadata(&memory.metadata, ids) {
+                Ok(()) => {
+                    accepted.push((results.len(), memory));
+                    results.push(Ok(String::new()));
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+
+        if let Some(embedder) = &self.embedder {
+            let missing: Vec<usize> = (0..accepted.len()).filter(|&i| accepted[i].1.embedding.is_none()).collect();
+            let texts: Vec<&str> = missing.iter().map(|&i| accepted[i].1.content.as_str()).collect();
+            let vectors = embedder.embed_batch(&texts);
+            for (i, vector) in missing.into_iter().zip(vectors) {
+                accepted[i].1.embedding = Some(vector);
+            }
+        }
+
+        let mut items = Vec::with_capacity(accepted.len());
+        for (slot, memory) in accepted {
+            let memory = new_item(memory);
+            results[slot] = Ok(memory.id.clone());
+            items.push(memory);
+        }
+        if let Some(index) = self.index.as_mut() {
+            let mut changes = 0;
+            for memory in &items {
+                if let Some(embedding) = memory.embedding.as_deref() {
+                    changes += index.insert(&memory.id, embedding) as usize;
+                }
+            }
+            self.index_changes(changes);
+        }
+        self.backend.insert_batch(items);
+        results
+    }
+
+    /// Apply several updates at once, with `update`'s result for each
+    ///
+    /// New contents are embedded with one `embed_batch` call and the backend
+    /// writes the batch together.
+    pub fn update_batch(&mut self, updates: Vec<MemoryUpdate>) -> Vec<Result<bool, MetadataError>> {
+        let mut results = Vec::with_capacity(updates.len());
+        let mut accepted = Vec::new();
+        for update in updates {
+            if let Some(new_metadata) = &update.metadata {
+                let Some(memory) = self.backend.get(&update.memory_id) else {
+                    results.push(Ok(false));
+                    continue;
+                };
+                let ids = [memory.user_id.as_deref(), memory.agent_id.as_deref(), memory.run_id.as_deref()];
+                let mut merged = memory.metadata.clone();
+                merged.extend(new_metadata.clone());
+                if let Err(e) = validate_metadata(&merged, ids) {
+                    results.push(Err(e));
+                    continue;
+                }
+            }
+            accepted.push((results.len(), update));
+            results.push(Ok(false));
+        }
+
+        let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; accepted.len()];
+        if let Some(embedder) = &self.embedder {
+            let changed: Vec<usize> = (0..accepted.len()).filter(|&i| accepted[i].1.content.is_some()).collect();
+            let texts: Vec<&str> = changed
+                .iter()
+                .filter_map(|&i| accepted[i].1.content.as_deref())
+                .collect();
+            let vectors = embedder.embed_batch(&texts);
+            for (i, vector) in changed.into_iter().zip(vectors) {
+                embeddings[i] = Some(vector);
+            }
+        }
+
+        let (slots, batch): (Vec<usize>, Vec<(MemoryUpdate, Option<Vec<f32>>)>) = accepted
+            .into_iter()
+            .zip(embeddings)
+            .map(|((slot, update), embedding)| (slot, (update, embedding)))
+            .unzip();
+        let indexed: Vec<(String, Option<Vec<f32>>)> = batch
+            .iter()
+            .map(|(update, embedding)| (update.memory_id.clone(), embedding.clone()))
+            .collect();
+        let previous: Vec<Option<MemoryItem>> = batch
+            .iter()
+            .map(|(update, _)| self.backend.get(&update.memory_id))
+            .collect();
+        let updated = self.backend.update_batch(batch);
+        let changed = previous
+            .into_iter()
+            .zip(&updated)
+            .filter_map(|(memory, updated)| memory.filter(|_| *updated))
+            .collect();
+        self.record(MemoryEvent::Update, changed);
+
+        let mut changes = 0;
+        for ((slot, (id, embedding)), updated) in slots.into_iter().zip(indexed).zip(updated) {
+            results[slot] = Ok(updated);
+            if let (true, Some(index), Some(embedding)) = (updated, self.index.as_mut(), embedding) {
+                changes += index.insert(&id, &embedding) as usize;
+            }
+        }
+        if changes > 0 {
+            self.index_changes(changes);
+        }
+        results
+    }
+
+    /// Delete several memories at once; `false` for ids that weren't found
+    pub fn delete_batch(&mut self, memory_ids: &[String]) -> Vec<bool> {
+        let previous: Vec<Option<MemoryItem>> = memory_ids.iter().map(|id| self.backend.get(id)).collect();
+        if let Some(index) = self.index.as_mut() {
+            let removed = memory_ids.iter().filter(|id| index.remove(id)).count();
+            if removed > 0 {
+                self.index_changes(removed);
+            }
+        }
+        let deleted = self.backend.delete_batch(memory_ids);
+        let removed = previous
+            .into_iter()
+            .zip(&deleted)
+            .filter_map(|(memory, deleted)| memory.filter(|_| *deleted))
+            .collect();
+        self.record(MemoryEvent::Delete, removed);
+        deleted
+    }
+
+    /// Put back a memory that was deleted, with its id, timestamps and vector
+    pub fn restore(&mut self, memory: MemoryItem) {
+        if let (Some(index), Some(embedding)) = (self.index.as_mut(), memory.embedding.as_deref()) {
+            index.insert(&memory.id, embedding);
+            self.index_changed();
+        }
+        self.backend.insert(memory.clone());
+        self.record(MemoryEvent::Restore, vec![memory]);
+    }
+
     /// Retrieve a specific memory by ID
     /// 
     /// # Arguments
@@ -112,8 +803,8 @@ impl MemoryStore {
     /// 
     /// # Returns
     /// The memory if found, None otherwise
-    pub fn get(&self, memory_id: &str) -> Option<&MemoryItem> {
-        self.memories.get(memory_id)
+    pub fn get(&self, memory_id: &str) -> Option<MemoryItem> {
+        self.backend.get(memory_id)
     }
 
     /// Get all memories matching the given filters
@@ -124,23 +815,36 @@ impl MemoryStore {
     /// 
     /// # Returns
     /// Vector of memories matching the filters, sorted by creation time (newest first)
-    pub fn get_all(&self, filters: &MemoryFilters, limit: usize) -> Vec<&MemoryItem> {
-        let mut results: Vec<&MemoryItem> = self
-            .memories
-            .values()
-            .filter(|memory| self.matches_filters(memory, filters))
-            .collect();
-
-        // Sort by created_at descending (newest first)
-        results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    pub fn get_all(&self, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem> {
+        self.backend.get_all(filters, limit)
+    }
 
-        // Apply limit
-        results.into_iter().take(limit).collect()
+    /// One page of the memories matching `filters`, for browsing large stores
+    ///
+    /// Fails only for a malformed `page.cursor`.
+    pub fn get_page(&self, filters: &MemoryFilters, page: &PageRequest) -> anyhow::Result<MemoryPage> {
+        let after = match page.cursor.as_deref() {
+            Some(cursor) => Some(MemoryCursor::decode(cursor).ok_or_else(|| anyhow::anyhow!("Invalid page cursor"))?),
+            None => None,
+        };
+        let items = self
+            .backend
+            .get_page(filters, page.sort, after.as_ref(), page.offset, page.limit);
+        let next_cursor = items
+            .last()
+            .filter(|_| items.len() == page.limit)
+            .map(|last| MemoryCursor::after(last, page.sort).encode());
+        Ok(MemoryPage {
+            items,
+            total: self.backend.count_filtered(filters),
+            next_cursor,
+        })
     }
 
-    /// Search memories by content (simple substring search for now)
+    /// Search memories by content
     /// 
-    /// TODO: Implement semantic search with embeddings
+    /// Ranked by cosine similarity when an embedder is attached, otherwise
+    /// (or for a query with no usable features) a substring search.
     /// 
     /// # Arguments
     /// * `query` - The search query
@@ -148,30 +852,104 @@ impl MemoryStore {
     /// * `limit` - Maximum number of results
     /// 
     /// # Returns
-    /// Vector of memories matching the query
+    /// Vector of memories matching the query, best match first
     pub fn search(
         &self,
         query: &str,
         filters: Option<&MemoryFilters>,
         limit: usize,
-    ) -> Vec<&MemoryItem> {
-        let query_lower = query.to_lowercase();
+    ) -> Vec<MemoryItem> {
+        self.search_scored(query, filters, limit)
+            .into_iter()
+            .map(|(memory, _)| memory)
+            .collect()
+    }
+
+    /// Like `search`, with each memory's similarity score (1.0 for substring hits)
+    pub fn search_scored(
+        &self,
+        query: &str,
+        filters: Option<&MemoryFilters>,
+        limit: usize,
+    ) -> Vec<(MemoryItem, f32)> {
+        let vector = self
+            .embedder
+            .as_ref()
+            .map(|embedder| embedder.embed(query))
+            .unwrap_or_default();
+        self.search_vector(query, &vector, filters, limit)
+    }
+
+    /// Like `search_scored` with `query` already embedded as `vector` (an
+    /// empty or all-zero vector falls back to text search)
+    pub fn search_vector(
+        &self,
+        query: &str,
+        vector: &[f32],
+        filters: Option<&MemoryFilters>,
+        limit: usize,
+    ) -> Vec<(MemoryItem, f32)> {
         let default_filters = MemoryFilters::default();
         let filters = filters.unwrap_or(&default_filters);
+        let now = SystemTime::from(crate::clock::now());
+        // Extra candidates leave room for decay to reorder and expiry to drop
+        let candidates = limit.saturating_mul(2);
 
-        let mut results: Vec<&MemoryItem> = self
-            .memories
-            .values()
-            .filter(|memory| {
-                self.matches_filters(memory, filters)
-                    && memory.content.to_lowercase().contains(&query_lower)
+        let hits = if vector.iter().any(|v| *v != 0.0) {
+            match &self.index {
+                Some(index) => self.search_index(index, vector, filters, candidates),
+                None => self.backend.search_similar(vector, filters, candidates),
+            }
+        } else {
+            self.backend
+                .search(query, filters, candidates)
+                .into_iter()
+                .map(|memory| (memory, 1.0))
+                .collect()
+        };
+        let mut results: Vec<(MemoryItem, f32)> = hits
+            .into_iter()
+            .filter(|(memory, _)| !memory.is_expired(now))
+            .map(|(memory, score)| match &self.recency {
+                Some(recency) => {
+                    let score = score * recency.factor(&memory, now);
+                    (memory, score)
+                }
+                None => (memory, score),
             })
             .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.truncate(limit);
+        results
+    }
 
-        // Sort by relevance (for now, just by created_at)
-        results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-        results.into_iter().take(limit).collect()
+    /// Nearest neighbours from the index that pass `filters`
+    ///
+    /// Filters are applied after the lookup, so the candidate count grows
+    /// until enough pass or the index is exhausted.
+    fn search_index(
+        &self,
+        index: &VectorIndex,
+        query: &[f32],
+        filters: &MemoryFilters,
+        limit: usize,
+    ) -> Vec<(MemoryItem, f32)> {
+        let mut candidates = limit.max(1) * 4;
+        loop {
+            let hits = index.search(query, candidates);
+            let exhausted = hits.len() < candidates || candidates >= index.len();
+            let results: Vec<(MemoryItem, f32)> = hits
+                .into_iter()
+                .filter(|(_, score)| *score > 0.0)
+                .filter_map(|(id, score)| Some((self.backend.get(&id)?, score)))
+                .filter(|(memory, _)| matches_filters(memory, filters))
+                .take(limit)
+                .collect();
+            if results.len() >= limit || exhausted {
+                return results;
+            }
+            candidates *= 4;
+        }
     }
 
     /// Update an existing memory
@@ -182,25 +960,87 @@ impl MemoryStore {
     /// * `metadata` - Optional metadata to merge with existing
     /// 
     /// # Returns
-    /// true if memory was updated, false if not found
+    /// true if memory was updated, false if not found; an error if the
+    /// merged metadata would be refused
     pub fn update(
         &mut self,
         memory_id: &str,
         content: Option<String>,
         metadata: Option<HashMap<String, serde_json::Value>>,
-    ) -> bool {
-        if let Some(memory) = self.memories.get_mut(memory_id) {
-            if let Some(new_content) = content {
-                memory.content = new_content;
-            }
-            if let Some(new_metadata) = metadata {
-                memory.metadata.extend(new_metadata);
+    ) -> Result<bool, MetadataError> {
+        let Some(previous) = self.backend.get(memory_id) else {
+            return Ok(false);
+        };
+        if let Some(new_metadata) = &metadata {
+            let ids = [previous.user_id.as_deref(), previous.agent_id.as_deref(), previous.run_id.as_deref()];
+            let mut merged = previous.metadata.clone();
+            merged.extend(new_metadata.clone());
+            validate_metadata(&merged, ids)?;
+        }
+
+        let embedding = match (&self.embedder, &content) {
+            (Some(embedder), Some(content)) => Some(embedder.embed(content)),
+            _ => None,
+        };
+        if !self.backend.update(memory_id, content, metadata) {
+            return Ok(false);
+        }
+        self.record(MemoryEvent::Update, vec![previous]);
+        if let Some(embedding) = embedding {
+            self.set_embedding(memory_id, embedding);
+        }
+        Ok(true)
+    }
+
+    /// Set or clear when a memory expires; false if it isn't found
+    pub fn set_expiry(&mut self, memory_id: &str, expires_at: Option<SystemTime>) -> bool {
+        let Some(previous) = self.backend.get(memory_id) else {
+            return false;
+        };
+        self.backend.insert(MemoryItem {
+            expires_at,
+            ..previous.clone()
+        });
+        self.record(MemoryEvent::Update, vec![previous]);
+        true
+    }
+
+    /// Delete memories that expired at or before `now`; returns how many
+    pub fn prune_expired(&mut self, now: SystemTime) -> usize {
+        let expired = self.backend.expired_ids(now);
+        if expired.is_empty() {
+            return 0;
+        }
+        self.delete_batch(&expired).into_iter().filter(|deleted| *deleted).count()
+    }
+
+    /// Ids and contents of memories without a vector from the current embedder
+    pub fn missing_embeddings(&self) -> Vec<(String, String)> {
+        let Some(embedder) = &self.embedder else {
+            return Vec::new();
+        };
+        let dimensions = embedder.dimensions();
+        self.backend
+            .get_all(&MemoryFilters::default(), usize::MAX)
+            .into_iter()
+            .filter(|memory| memory.embedding.as_ref().map(|e| e.len()) != Some(dimensions))
+            .map(|memory| (memory.id, memory.content))
+            .collect()
+    }
+
+    /// Attach an embedding to an existing memory
+    /// 
+    /// # Returns
+    /// true if the memory exists, false if not found
+    pub fn set_embedding(&mut self, memory_id: &str, embedding: Vec<f32>) -> bool {
+        if let Some(index) = self.index.as_mut() {
+            if self.backend.get(memory_id).is_none() {
+                return false;
             }
-            memory.updated_at = SystemTime::now();
-            true
-        } else {
-            false
+            index.insert(memory_id, &embedding);
+            self.index_changed();
         }
+        self.backend.set_embedding(memory_id, embedding)
     }
 
     /// Delete a memory by ID
@@ -211,7 +1051,19 @@ impl MemoryStore {
     /// # Returns
     /// true if memory was deleted, false if not found
     pub fn delete(&mut self, memory_id: &str) -> bool {
-        self.memories.remove(memory_id).is_some()
+        let Some(previous) = self.backend.get(memory_id) else {
+            return false;
+        };
+        if let Some(index) = self.index.as_mut() {
+            if index.remove(memory_id) {
+                self.index_changed();
+            }
+        }
+        let deleted = self.backend.delete(memory_id);
+        if deleted {
+            self.record(MemoryEvent::Delete, vec![previous]);
+        }
+        deleted
     }
 
     /// Delete all memories matching the given filters
@@ -220,78 +1072,380 @@ impl MemoryStore {
     /// * `filters` - Filter criteria for memories to delete
     /// 
     /// # Returns
-    /// Number of memories deleted
+    /// Number of memories deleted (expired ones included)
     pub fn delete_all(&mut self, filters: &MemoryFilters) -> usize {
-        let ids_to_delete: Vec<String> = self
-            .memories
-            .values()
-            .filter(|memory| self.matches_filters(memory, filters))
-            .map(|memory| memory.id.clone())
-            .collect();
+        let filters = &filters.including_expired();
+        let memories = self.backend.get_all(filters, usize::MAX);
+        if let Some(index) = self.index.as_mut() {
+            for memory in &memories {
+                index.remove(&memory.id);
+            }
+            self.unsaved_index_changes += 1;
+            self.save_index();
+        }
+        let deleted = self.backend.delete_all(filters);
+        self.record(MemoryEvent::Delete, memories);
+        deleted
+    }
 
-        let count = ids_to_delete.len();
-        for id in ids_to_delete {
-            self.memories.remove(&id);
+    /// Recorded versions of a memory, oldest first
+    pub fn history(&self, memory_id: &str) -> Vec<MemoryVersion> {
+        self.backend.history(memory_id)
+    }
+
+    /// Put a memory back the way `version` recorded it, re-adding it if it
+    /// has been deleted; the change is recorded too
+    pub fn restore_version(&mut self, version: MemoryVersion) -> MemoryItem {
+        let current = self.backend.get(&version.memory.id);
+        let mut memory = MemoryItem {
+            updated_at: SystemTime::from(crate::clock::now()),
+            ..version.memory
+        };
+        memory.embedding = self.embedder.as_ref().map(|embedder| embedder.embed(&memory.content));
+        if let Some(index) = self.index.as_mut() {
+            let changed = match memory.embedding.as_deref() {
+                Some(embedding) => index.insert(&memory.id, embedding),
+                None => index.remove(&memory.id),
+            };
+            if changed {
+                self.index_changed();
+            }
         }
-        count
+        self.backend.insert(memory.clone());
+        match current {
+            Some(previous) => self.record(MemoryEvent::Update, vec![previous]),
+            None => self.record(MemoryEvent::Restore, vec![memory.clone()]),
+        }
+        memory
     }
 
-    /// Check if a memory matches the given filters
-    fn matches_filters(&self, memory: &MemoryItem, filters: &MemoryFilters) -> bool {
-        // Check user_id
-        if let Some(ref user_id) = filters.user_id {
-            if memory.user_id.as_ref() != Some(user_id) {
-                return false;
+    /// Drop the history of memories matching `filters` (e.g. a user's, when
+    /// their data is wiped); returns the versions removed
+    pub fn delete_history(&mut self, filters: &MemoryFilters) -> usize {
+        self.backend.delete_history(&filters.including_expired())
+    }
+
+    /// Write every memory again in the current storage format
+    pub fn rewrite_all(&mut self) -> usize {
+        self.backend.rewrite_all()
+    }
+
+    /// Record a version of each of `memories`
+    fn record(&mut self, event: MemoryEvent, memories: Vec<MemoryItem>) {
+        if memories.is_empty() {
+            return;
+        }
+        let versions = memories
+            .into_iter()
+            .map(|memory| MemoryVersion::new(event, memory))
+            .collect();
+        self.backend.record_history(versions);
+    }
+
+    /// Get total count of memories
+    pub fn count(&self) -> usize {
+        self.backend.count()
+    }
+
+    /// Get count of memories matching filters
+    pub fn count_filtered(&self, filters: &MemoryFilters) -> usize {
+        self.backend.count_filtered(filters)
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Memories embedded per batch during a backfill
+const BACKFILL_BATCH: usize = 64;
+
+/// Embed memories stored before the current embedder, in the background
+///
+/// The store is only locked to write each batch, not while embedding.
+pub fn spawn_embedding_backfill(store: Arc<Mutex<MemoryStore>>) {
+    std::thread::spawn(move || {
+        let (embedder, pending) = {
+            let store = store.lock();
+            match store.embedder() {
+                Some(embedder) => (embedder, store.missing_embeddings()),
+                None => return,
             }
+        };
+        if pending.is_empty() {
+            return;
         }
 
-        // Check agent_id
-        if let Some(ref agent_id) = filters.agent_id {
-            if memory.agent_id.as_ref() != Some(agent_id) {
-                return false;
+        info!("Embedding {} stored memories", pending.len());
+        for batch in pending.chunks(BACKFILL_BATCH) {
+            let texts: Vec<&str> = batch.iter().map(|(_, content)| content.as_str()).collect();
+            let vectors = embedder.embed_batch(&texts);
+            let mut store = store.lock();
+            for ((id, _), vector) in batch.iter().zip(vectors) {
+                store.set_embedding(id, vector);
             }
         }
+        store.lock().save_index();
+        info!("Memory embeddings up to date");
+    });
+}
 
-        // Check run_id
-        if let Some(ref run_id) = filters.run_id {
-            if memory.run_id.as_ref() != Some(run_id) {
-                return false;
+/// Time between passes of `spawn_expiry_pruning`
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Delete expired memories at startup and then every few minutes
+pub fn spawn_expiry_pruning(store: Arc<Mutex<MemoryStore>>) {
+    std::thread::spawn(move || loop {
+        let pruned = store.lock().prune_expired(SystemTime::from(crate::clock::now()));
+        if pruned > 0 {
+            info!("Pruned {} expired memories", pruned);
+        }
+        std::thread::sleep(PRUNE_INTERVAL);
+    });
+}
+
+/// Check if a memory matches the given filters
+pub fn matches_filters(memory: &MemoryItem, filters: &MemoryFilters) -> bool {
+    // Expired memories are gone for everything but deletes
+    if !filters.include_expired && memory.is_expired(SystemTime::from(crate::clock::now())) {
+        return false;
+    }
+
+    // Check user_id
+    if let Some(ref user_id) = filters.user_id {
+        if memory.user_id.as_ref() != Some(user_id) {
+            return false;
+        }
+    }
+
+    // Check agent_id
+    if let Some(ref agent_id) = filters.agent_id {
+        if memory.agent_id.as_ref() != Some(agent_id) {
+            return false;
+        }
+    }
+
+    // Check run_id
+    if let Some(ref run_id) = filters.run_id {
+        if memory.run_id.as_ref() != Some(run_id) {
+            return false;
+        }
+    }
+
+    // Check metadata filters
+    for (key, value) in &filters.metadata {
+        if memory.metadata.get(key) != Some(value) {
+            return false;
+        }
+    }
+
+    // Check the filter expression
+    if let Some(ref filter) = filters.filter {
+        if !filter.matches(memory) {
+            return false;
+        }
+    }
+
+    // Check persona access policy
+    if let Some(ref access) = filters.access {
+        if !access.allows(memory) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Non-persistent backend (tests, and fallback when no database can be opened)
+#[derive(Default)]
+pub struct InMemoryBackend {
+    memories: HashMap<String, MemoryItem>,
+    history: HashMap<String, Vec<MemoryVersion>>,
+}
+
+impl InMemoryBackend {
+    /// Matching memories, newest first
+    fn matching<'a>(
+        &'a self,
+        filters: &'a MemoryFilters,
+        predicate: impl Fn(&MemoryItem) -> bool + 'a,
+    ) -> Vec<&'a MemoryItem> {
+        let mut results: Vec<&MemoryItem> = self
+            .memories
+            .values()
+            .filter(|memory| matches_filters(memory, filters) && predicate(memory))
+            .collect();
+        results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        results
+    }
+}
+
+impl MemoryBackend for InMemoryBackend {
+    fn insert(&mut self, memory: MemoryItem) {
+        self.memories.insert(memory.id.clone(), memory);
+    }
+
+    fn get(&self, memory_id: &str) -> Option<MemoryItem> {
+        self.memories.get(memory_id).cloned()
+    }
+
+    fn get_all(&self, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem> {
+        self.matching(filters, |_| true)
+            .into_iter()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn search(&self, query: &str, filters: &MemoryFilters, limit: usize) -> Vec<MemoryItem> {
+        let query_lower = query.to_lowercase();
+        self.matching(filters, move |memory| memory.content.to_lowercase().contains(&query_lower))
+            .into_iter()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn update(
+        &mut self,
+        memory_id: &str,
+        content: Option<String>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> bool {
+        match self.memories.get_mut(memory_id) {
+            Some(memory) => {
+                apply_update(memory, content, metadata);
+                true
             }
+            None => false,
         }
+    }
 
-        // Check metadata filters
-        for (key, value) in &filters.metadata {
-            if memory.metadata.get(key) != Some(value) {
-                return false;
+    fn set_embedding(&mut self, memory_id: &str, embedding: Vec<f32>) -> bool {
+        match self.memories.get_mut(memory_id) {
+            Some(memory) => {
+                memory.embedding = Some(embedding);
+                true
             }
+            None => false,
         }
+    }
 
-        true
+    fn delete(&mut self, memory_id: &str) -> bool {
+        self.memories.remove(memory_id).is_some()
     }
 
-    /// Get total count of memories
-    pub fn count(&self) -> usize {
+    fn delete_all(&mut self, filters: &MemoryFilters) -> usize {
+        let ids_to_delete: Vec<String> = self
+            .memories
+            .values()
+            .filter(|memory| matches_filters(memory, filters))
+            .map(|memory| memory.id.clone())
+            .collect();
+
+        for id in &ids_to_delete {
+            self.memories.remove(id);
+        }
+        ids_to_delete.len()
+    }
+
+    fn count(&self) -> usize {
         self.memories.len()
     }
 
-    /// Get count of memories matching filters
-    pub fn count_filtered(&self, filters: &MemoryFilters) -> usize {
+    fn count_filtered(&self, filters: &MemoryFilters) -> usize {
         self.memories
             .values()
-            .filter(|memory| self.matches_filters(memory, filters))
+            .filter(|memory| matches_filters(memory, filters))
             .count()
     }
+
+    fn record_history(&mut self, versions: Vec<MemoryVersion>) {
+        for mut version in versions {
+            let history = self.history.entry(version.memory.id.clone()).or_default();
+            version.version = history.len() + 1;
+            history.push(version);
+        }
+    }
+
+    fn history(&self, memory_id: &str) -> Vec<MemoryVersion> {
+        self.history.get(memory_id).cloned().unwrap_or_default()
+    }
+
+    fn delete_history(&mut self, filters: &MemoryFilters) -> usize {
+        let mut removed = 0;
+        self.history.retain(|_, versions| {
+            let before = versions.len();
+            versions.retain(|version| !matches_filters(&version.memory, filters));
+            removed += before - versions.len();
+            !versions.is_empty()
+        });
+        removed
+    }
 }
 
-impl Default for MemoryStore {
-    fn default() -> Self {
-        Self::new()
+/// Apply an update to a memory in place
+/// A stored item for `memory`: a new id, the current time, and the session
+/// ids copied into its metadata
+fn new_item(memory: NewMemory) -> MemoryItem {
+    let NewMemory {
+        content,
+        user_id,
+        agent_id,
+        run_id,
+        mut metadata,
+        embedding,
+        expires_at,
+    } = memory;
+    let now = SystemTime::from(crate::clock::now());
+
+    // Add session identifiers to metadata
+    if let Some(uid) = &user_id {
+        metadata.insert("user_id".to_string(), serde_json::json!(uid));
+    }
+    if let Some(aid) = &agent_id {
+        metadata.insert("agent_id".to_string(), serde_json::json!(aid));
+    }
+    if let Some(rid) = &run_id {
+        metadata.insert("run_id".to_string(), serde_json::json!(rid));
+    }
+
+    MemoryItem {
+        id: Uuid::new_v4().to_string(),
+        content,
+        user_id,
+        agent_id,
+        run_id,
+        metadata,
+        embedding,
+        created_at: now,
+        updated_at: now,
+        expires_at,
+    }
+}
+
+pub(crate) fn apply_update(
+    memory: &mut MemoryItem,
+    content: Option<String>,
+    metadata: Option<HashMap<String, serde_json::Value>>,
+) {
+    if let Some(new_content) = content {
+        memory.content = new_content;
+        // The old vector no longer describes the content
+        memory.embedding = None;
     }
+    if let Some(new_metadata) = metadata {
+        memory.metadata.extend(new_metadata);
+    }
+    memory.updated_at = SystemTime::from(crate::clock::now());
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::embeddings::HashingEmbedder;
 
     #[test]
     fn test_add_and_get() {
@@ -302,7 +1456,7 @@ mod tests {
             None,
             None,
             HashMap::new(),
-        );
+        ).unwrap();
 
         let memory = store.get(&id).unwrap();
         assert_eq!(memory.content, "Test memory");
@@ -313,9 +1467,9 @@ mod tests {
     fn test_get_all_with_filters() {
         let mut store = MemoryStore::new();
         
-        store.add("Memory 1", Some("user_1".to_string()), None, None, HashMap::new());
-        store.add("Memory 2", Some("user_1".to_string()), None, None, HashMap::new());
-        store.add("Memory 3", Some("user_2".to_string()), None, None, HashMap::new());
+        store.add("Memory 1", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
+        store.add("Memory 2", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
+        store.add("Memory 3", Some("user_2".to_string()), None, None, HashMap::new()).unwrap();
 
         let filters = MemoryFilters {
             user_id: Some("user_1".to_string()),
@@ -330,20 +1484,47 @@ mod tests {
     fn test_search() {
         let mut store = MemoryStore::new();
         
-        store.add("Patient has diabetes", Some("user_1".to_string()), None, None, HashMap::new());
-        store.add("Patient has hypertension", Some("user_1".to_string()), None, None, HashMap::new());
+        store.add("Patient has diabetes", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
+        store.add("Patient has hypertension", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
 
         let results = store.search("diabetes", None, 10);
         assert_eq!(results.len(), 1);
         assert!(results[0].content.contains("diabetes"));
     }
 
+    #[test]
+    fn test_semantic_search_ranks_by_similarity() {
+        let mut store = MemoryStore::new().with_embedder(Arc::new(HashingEmbedder::default()));
+        store.add("Planted tomatoes and basil in the garden", None, None, None, HashMap::new()).unwrap();
+        store.add("The garden needs more tomatoes", None, None, None, HashMap::new()).unwrap();
+        store.add("Filed quarterly taxes", None, None, None, HashMap::new()).unwrap();
+
+        let results = store.search_scored("tomatoes garden basil", None, 10);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].0.content.contains("basil"));
+        assert!(results[0].1 >= results[1].1);
+
+        // Content edits are re-embedded
+        let id = store.search("taxes", None, 1)[0].id.clone();
+        store.update(&id, Some("Watered the basil".to_string()), None).unwrap();
+        assert!(store.search("taxes", None, 10).is_empty());
+        assert_eq!(store.search("basil", None, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_missing_embeddings() {
+        let mut store = MemoryStore::new();
+        let id = store.add("Stored before embeddings", None, None, None, HashMap::new()).unwrap();
+        let store = store.with_embedder(Arc::new(HashingEmbedder::default()));
+        assert_eq!(store.missing_embeddings(), vec![(id, "Stored before embeddings".to_string())]);
+    }
+
     #[test]
     fn test_update() {
         let mut store = MemoryStore::new();
-        let id = store.add("Original content", Some("user_1".to_string()), None, None, HashMap::new());
+        let id = store.add("Original content", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
 
-        store.update(&id, Some("Updated content".to_string()), None);
+        assert!(store.update(&id, Some("Updated content".to_string()), None).unwrap());
 
         let memory = store.get(&id).unwrap();
         assert_eq!(memory.content, "Updated content");
@@ -352,9 +1533,84 @@ mod tests {
     #[test]
     fn test_delete() {
         let mut store = MemoryStore::new();
-        let id = store.add("Test memory", Some("user_1".to_string()), None, None, HashMap::new());
+        let id = store.add("Test memory", Some("user_1".to_string()), None, None, HashMap::new()).unwrap();
 
         assert!(store.delete(&id));
         assert!(store.get(&id).is_none());
     }
+
+    #[test]
+    fn test_metadata_validation() {
+        let mut store = MemoryStore::new();
+        let user = Some("user_1".to_string());
+
+        // A reserved key may only repeat the memory's own id
+        let same = HashMap::from([("user_id".to_string(), serde_json::json!("user_1"))]);
+        let id = store.add("Mine", user.clone(), None, None, same).unwrap();
+        let other = HashMap::from([("user_id".to_string(), serde_json::json!("user_2"))]);
+        assert_eq!(
+            store.add("Theirs", user.clone(), None, None, other.clone()),
+            Err(MetadataError::ReservedKey("user_id".to_string()))
+        );
+        assert!(store.update(&id, None, Some(other)).is_err());
+        assert_eq!(store.get(&id).unwrap().user_id.as_deref(), Some("user_1"));
+
+        let huge = HashMap::from([("notes".to_string(), serde_json::json!("x".repeat(MAX_METADATA_BYTES)))]);
+        assert!(matches!(
+            store.add("Big", user.clone(), None, None, huge),
+            Err(MetadataError::TooLarge { .. })
+        ));
+        let deep = HashMap::from([("tree".to_string(), serde_json::json!([[[[[1]]]]]))]);
+        assert_eq!(
+            store.add("Deep", user.clone(), None, None, deep),
+            Err(MetadataError::TooDeep("tree".to_string()))
+        );
+        let blank = HashMap::from([(String::new(), serde_json::json!(1))]);
+        assert!(store.add("Blank", user, None, None, blank).is_err());
+        assert_eq!(store.count(), 1);
+    }
+
+    #[test]
+    fn test_expiry_and_recency_decay() {
+        let dir = std::env::temp_dir().join(format!("auranexus-expiry-{}", Uuid::new_v4()));
+        for mut store in [MemoryStore::open_sqlite(dir.join("memories.db")).unwrap(), MemoryStore::new()] {
+            let now = SystemTime::from(crate::clock::now());
+            let user = Some("u".to_string());
+            let fresh = store.add("Likes tea", user.clone(), None, None, HashMap::new()).unwrap();
+            let stale = store.add("Likes tea a lot", user.clone(), None, None, HashMap::new()).unwrap();
+            let old = MemoryItem {
+                updated_at: now - Duration::from_secs(90 * 86_400),
+                ..store.get(&stale).unwrap()
+            };
+            store.restore(old);
+
+            // Equal text matches; the memory untouched for 90 days ranks last
+            store.set_recency_decay(Some(RecencyDecay {
+                weight: 0.5,
+                half_life_days: 30.0,
+            }));
+            let results = store.search_scored("likes tea", None, 10);
+            assert_eq!(results[0].0.id, fresh);
+            assert_eq!(results[1].0.id, stale);
+            assert!((results[1].1 - (0.5 + 0.5 * 0.125)).abs() < 0.01);
+
+            // Expired memories drop out of search, then get pruned
+            assert!(store.set_expiry(&stale, Some(now - Duration::from_secs(1))));
+            assert!(store.set_expiry(&fresh, Some(now + Duration::from_secs(3600))));
+            assert_eq!(store.search("likes tea", None, 10).len(), 1);
+            let filters = MemoryFilters {
+                user_id: user.clone(),
+                ..Default::default()
+            };
+            assert_eq!(store.get_all(&filters, 10).len(), 1);
+            assert_eq!(store.count_filtered(&filters), 1);
+            assert_eq!(store.get_page(&filters, &PageRequest::default()).unwrap().items.len(), 1);
+            assert_eq!(store.prune_expired(now), 1);
+            assert!(store.get(&stale).is_none());
+            assert!(store.get(&fresh).unwrap().expires_at.is_some());
+            assert_eq!(store.prune_expired(now + Duration::from_secs(7200)), 1);
+            assert_eq!(store.count(), 0);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,342 @@
+// Metadata Filter Module - mem0-style filter expressions over memories
+//
+// `MemoryFilters::metadata` only matches exact values. A `MetadataFilter` is
+// the richer form mem0 accepts, written the same way in JSON:
+//
+//   {"AND": [{"kind": "document"}, {"score": {"gte": 0.5}}]}
+//   {"OR": [{"tags": {"contains": "health"}}, {"source.app": {"in": ["notes", "mail"]}}]}
+//   {"NOT": [{"private": true}]}
+//   {"created_at": {"gte": "2024-07-01"}, "reviewed": "*"}
+//
+// A bare value means equality and "*" means the key exists. Operators are
+// eq, ne, gt, gte, lt, lte, in, nin, contains, icontains and exists; several
+// keys (or operators) in one object must all match. Dotted keys reach into
+// nested objects, and `created_at` / `updated_at` compare the memory's own
+// timestamps. Ranges compare numbers numerically and dates (RFC3339 or
+// YYYY-MM-DD) chronologically; other strings compare as text.
+
+use crate::memory_store::MemoryItem;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// Comparison applied to one key
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOp {
+    Eq(Value),
+    Ne(Value),
+    Gt(Value),
+    Gte(Value),
+    Lt(Value),
+    Lte(Value),
+    In(Vec<Value>),
+    NotIn(Vec<Value>),
+    /// Substring of a string, or element of an array
+    Contains(Value),
+    /// Case-insensitive substring
+    IContains(String),
+    Exists(bool),
+}
+
+/// A filter expression; see the module comment for the JSON form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Value", into = "Value")]
+pub enum MetadataFilter {
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    /// Matches when none of the filters do
+    Not(Vec<MetadataFilter>),
+    Field { key: String, op: FilterOp },
+}
+
+impl MetadataFilter {
+    pub fn field(key: impl Into<String>, op: FilterOp) -> Self {
+        MetadataFilter::Field { key: key.into(), op }
+    }
+
+    /// Parse the mem0 JSON form
+    pub fn parse(value: &Value) -> Result<Self> {
+        let Value::Object(object) = value else {
+            bail!("A filter must be an object, not {}", value);
+        };
+        let mut filters = object
+            .iter()
+            .map(|(key, value)| match key.as_str() {
+                "AND" => Ok(MetadataFilter::And(parse_list(key, value)?)),
+                "OR" => Ok(MetadataFilter::Or(parse_list(key, value)?)),
+                "NOT" => Ok(MetadataFilter::Not(match value {
+                    Value::Object(_) => vec![Self::parse(value)?],
+                    _ => parse_list(key, value)?,
+                })),
+                _ => parse_field(key, value),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            MetadataFilter::And(filters)
+        })
+    }
+
+    /// The mem0 JSON form
+    pub fn to_value(&self) -> Value {
+        match self {
+            MetadataFilter::And(filters) => serde_json::json!({ "AND": list_value(filters) }),
+            MetadataFilter::Or(filters) => serde_json::json!({ "OR": list_value(filters) }),
+            MetadataFilter::Not(filters) => serde_json::json!({ "NOT": list_value(filters) }),
+            MetadataFilter::Field { key, op } => {
+                let (name, operand) = match op {
+                    FilterOp::Eq(value) => ("eq", value.clone()),
+                    FilterOp::Ne(value) => ("ne", value.clone()),
+                    FilterOp::Gt(value) => ("gt", value.clone()),
+                    FilterOp::Gte(value) => ("gte", value.clone()),
+                    FilterOp::Lt(value) => ("lt", value.clone()),
+                    FilterOp::Lte(value) => ("lte", value.clone()),
+                    FilterOp::In(values) => ("in", Value::Array(values.clone())),
+                    FilterOp::NotIn(values) => ("nin", Value::Array(values.clone())),
+                    FilterOp::Contains(value) => ("contains", value.clone()),
+                    FilterOp::IContains(text) => ("icontains", Value::String(text.clone())),
+                    FilterOp::Exists(exists) => ("exists", Value::Bool(*exists)),
+                };
+                let mut operation = Map::new();
+                operation.insert(name.to_string(), operand);
+                let mut field = Map::new();
+                field.insert(key.clone(), Value::Object(operation));
+                Value::Object(field)
+            }
+        }
+    }
+
+    /// Whether `memory` satisfies the expression
+    pub fn matches(&self, memory: &MemoryItem) -> bool {
+        match self {
+            MetadataFilter::And(filters) => filters.iter().all(|filter| filter.matches(memory)),
+            MetadataFilter::Or(filters) => filters.iter().any(|filter| filter.matches(memory)),
+            MetadataFilter::Not(filters) => !filters.iter().any(|filter| filter.matches(memory)),
+            MetadataFilter::Field { key, op } => {
+                let value = lookup(memory, key);
+                match (op, value.as_ref()) {
+                    (FilterOp::Exists(exists), value) => value.is_some() == *exists,
+                    // A missing key is never equal to anything
+                    (FilterOp::Ne(expected), value) => !value.is_some_and(|value| equals(value, expected)),
+                    (FilterOp::NotIn(values), value) => {
+                        !value.is_some_and(|value| values.iter().any(|expected| equals(value, expected)))
+                    }
+                    (_, None) => false,
+                    (FilterOp::Eq(expected), Some(value)) => equals(value, expected),
+                    (FilterOp::Gt(bound), Some(value)) => compare(value, bound) == Some(Ordering::Greater),
+                    (FilterOp::Gte(bound), Some(value)) => {
+                        matches!(compare(value, bound), Some(Ordering::Greater | Ordering::Equal))
+                    }
+                    (FilterOp::Lt(bound), Some(value)) => compare(value, bound) == Some(Ordering::Less),
+                    (FilterOp::Lte(bound), Some(value)) => {
+                        matches!(compare(value, bound), Some(Ordering::Less | Ordering::Equal))
+                    }
+                    (FilterOp::In(values), Some(value)) => values.iter().any(|expected| equals(value, expected)),
+                    (FilterOp::Contains(needle), Some(value)) => match (value, needle) {
+                        (Value::String(text), Value::String(needle)) => text.contains(needle.as_str()),
+                        (Value::Array(items), needle) => items.iter().any(|item| equals(item, needle)),
+                        _ => false,
+                    },
+                    (FilterOp::IContains(needle), Some(value)) => match value {
+                        Value::String(text) => text.to_lowercase().contains(&needle.to_lowercase()),
+                        Value::Array(items) => items.iter().any(|item| {
+                            item.as_str()
+                                .is_some_and(|item| item.to_lowercase().contains(&needle.to_lowercase()))
+                        }),
+                        _ => false,
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<Value> for MetadataFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<MetadataFilter> for Value {
+    fn from(filter: MetadataFilter) -> Self {
+        filter.to_value()
+    }
+}
+
+fn list_value(filters: &[MetadataFilter]) -> Value {
+    Value::Array(filters.iter().map(MetadataFilter::to_value).collect())
+}
+
+fn parse_list(operator: &str, value: &Value) -> Result<Vec<MetadataFilter>> {
+    let Value::Array(items) = value else {
+        bail!("{} takes a list of filters", operator);
+    };
+    items.iter().map(MetadataFilter::parse).collect()
+}
+
+/// `{"key": value}`, `{"key": "*"}` or `{"key": {"op": operand, ...}}`
+fn parse_field(key: &str, value: &Value) -> Result<MetadataFilter> {
+    if key.is_empty() {
+        bail!("Filter keys can't be empty");
+    }
+    let operations = match value {
+        Value::String(wildcard) if wildcard == "*" => return Ok(MetadataFilter::field(key, FilterOp::Exists(true))),
+        Value::Object(operations) => operations,
+        _ => return Ok(MetadataFilter::field(key, FilterOp::Eq(value.clone()))),
+    };
+    let mut filters = operations
+        .iter()
+        .map(|(name, operand)| {
+            let op = match name.as_str() {
+                "eq" => FilterOp::Eq(operand.clone()),
+                "ne" => FilterOp::Ne(operand.clone()),
+                "gt" => FilterOp::Gt(operand.clone()),
+                "gte" => FilterOp::Gte(operand.clone()),
+                "lt" => FilterOp::Lt(operand.clone()),
+                "lte" => FilterOp::Lte(operand.clone()),
+                "in" | "nin" => {
+                    let values = operand
+                        .as_array()
+                        .cloned()
+                        .ok_or_else(|| anyhow!("'{}' on '{}' takes a list", name, key))?;
+                    if name == "in" {
+                        FilterOp::In(values)
+                    } else {
+                        FilterOp::NotIn(values)
+                    }
+                }
+                "contains" => FilterOp::Contains(operand.clone()),
+                "icontains" => FilterOp::IContains(
+                    operand
+                        .as_str()
+                        .ok_or_else(|| anyhow!("'icontains' on '{}' takes a string", key))?
+                        .to_string(),
+                ),
+                "exists" => FilterOp::Exists(
+                    operand
+                        .as_bool()
+                        .ok_or_else(|| anyhow!("'exists' on '{}' takes true or false", key))?,
+                ),
+                other => bail!("Unknown filter operator '{}' on '{}'", other, key),
+            };
+            Ok(MetadataFilter::field(key, op))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match filters.len() {
+        0 => bail!("No operator given for '{}'", key),
+        1 => Ok(filters.remove(0)),
+        _ => Ok(MetadataFilter::And(filters)),
+    }
+}
+
+/// The value `key` refers to: a timestamp, a metadata key, or a dotted path
+/// into nested metadata
+fn lookup(memory: &MemoryItem, key: &str) -> Option<Value> {
+    match key {
+        "created_at" => return Some(Value::String(DateTime::<Utc>::from(memory.created_at).to_rfc3339())),
+        "updated_at" => return Some(Value::String(DateTime::<Utc>::from(memory.updated_at).to_rfc3339())),
+        _ => {}
+    }
+    if let Some(value) = memory.metadata.get(key) {
+        return Some(value.clone());
+    }
+    let mut parts = key.split('.');
+    let mut value = memory.metadata.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Value::Object(object) => object.get(part)?,
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value.clone())
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    crate::clock::parse(text).or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|time| time.and_utc())
+    })
+}
+
+/// Order of two values, if they are comparable
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => match (parse_date(a), parse_date(b)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => Some(a.cmp(b)),
+        },
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Equal values, treating 5 and 5.0 (and one instant in two date formats)
+/// as the same
+fn equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => compare(a, b) == Some(Ordering::Equal),
+        (Value::String(x), Value::String(y)) => {
+            x == y || matches!((parse_date(x), parse_date(y)), (Some(x), Some(y)) if x == y)
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_parse_and_match() {
+        let created_at = UNIX_EPOCH + Duration::from_secs(1_720_000_000); // 2024-07-03
+        let memory = MemoryItem {
+            id: "m1".to_string(),
+            content: "Took a walk".to_string(),
+            user_id: Some("u".to_string()),
+            agent_id: None,
+            run_id: None,
+            metadata: HashMap::from([
+                ("kind".to_string(), json!("message")),
+                ("score".to_string(), json!(4)),
+                ("tags".to_string(), json!(["Health", "walk"])),
+                ("source".to_string(), json!({"app": "notes", "page": 2})),
+            ]),
+            embedding: None,
+            created_at,
+            updated_at: created_at,
+            expires_at: None,
+        };
+        let matches = |filter: Value| MetadataFilter::parse(&filter).unwrap().matches(&memory);
+
+        assert!(matches(json!({"kind": "message", "score": 4.0})));
+        assert!(matches(json!({"score": {"gte": 3, "lt": 5}})));
+        assert!(!matches(json!({"score": {"gt": 4}})));
+        assert!(matches(json!({"source.app": {"in": ["notes", "mail"]}, "source.page": {"nin": [1]}})));
+        assert!(matches(json!({"tags": {"contains": "walk"}, "kind": {"icontains": "MESS"}})));
+        assert!(matches(json!({"tags": "*", "missing": {"exists": false}, "other": {"ne": 1}})));
+        assert!(matches(json!({"created_at": {"gte": "2024-07-01", "lt": "2024-07-04T00:00:00Z"}})));
+        assert!(matches(json!({"OR": [{"kind": "document"}, {"NOT": [{"score": {"lt": 2}}]}]})));
+        assert!(!matches(json!({"AND": [{"kind": "message"}, {"source.app": "mail"}]})));
+        assert!(!matches(json!({"NOT": {"kind": "message"}})));
+
+        let filter = MetadataFilter::parse(&json!({"OR": [{"score": {"lte": 2}}, {"tags": "*"}]})).unwrap();
+        let round_trip: MetadataFilter = serde_json::from_value(serde_json::to_value(&filter).unwrap()).unwrap();
+        assert_eq!(round_trip, filter);
+
+        for invalid in [json!({"score": {"between": [1, 2]}}), json!({"AND": {"kind": "x"}}), json!([1]), json!({"tags": {}})] {
+            assert!(MetadataFilter::parse(&invalid).is_err());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// Migration Module - One-time move of data from older versions' locations
+//
+// Data now lives in Tauri's app data directory for the bundle identifier.
+// On first start after the change, every legacy directory (see
+// `paths::legacy_data_dirs`) is copied in, file by file through a temp file,
+// and then renamed to `<name>.backup-<time>` beside where it was, so nothing
+// is deleted. Files the new directory already has are left alone. Large files
+// (downloaded models) are moved instead of copied so they don't take up the
+// disk twice. What was done is written to `migration.json`, which also stops
+// the migration from running again; `rollback_data_migration` puts the old
+// directories back for an older version to use.
+
+use crate::{clock, history_store, paths};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const MARKER_FILE: &str = "migration.json";
+
+/// Files at least this big are moved rather than copied
+const MOVE_THRESHOLD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// One legacy directory brought into the app data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MigratedDir {
+    pub from: PathBuf,
+    /// Where the legacy directory was renamed to (unset if the rename failed
+    /// and it was left in place)
+    pub backup: Option<PathBuf>,
+    /// Copied files, relative to both directories
+    pub copied: Vec<PathBuf>,
+    /// Large files moved out of the legacy directory
+    pub moved: Vec<PathBuf>,
+    /// Files the app data directory already had
+    pub skipped: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MigrationRecord {
+    pub migrated_at: String,
+    pub target: PathBuf,
+    pub sources: Vec<MigratedDir>,
+    pub rolled_back: bool,
+}
+
+impl MigrationRecord {
+    fn path(target: &Path) -> PathBuf {
+        target.join(MARKER_FILE)
+    }
+
+    pub fn load(target: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(Self::path(target)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.target)?;
+        history_store::write_atomic(&Self::path(&self.target), &serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Copy (or, for large files, move) everything under `from` into `to`
+fn copy_tree(from: &Path, to: &Path, relative: &Path, migrated: &mut MigratedDir) -> Result<()> {
+    let source_dir = from.join(relative);
+    for entry in std::fs::read_dir(&source_dir).with_context(|| format!("Failed to read {}", source_dir.display()))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let relative = relative.join(entry.file_name());
+        let (source, target) = (from.join(&relative), to.join(&relative));
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            copy_tree(from, to, &relative, migrated)?;
+        } else if file_type.is_file() {
+            if target.exists() {
+                migrated.skipped.push(relative);
+                continue;
+            }
+            let size = entry.metadata()?.len();
+            if size >= MOVE_THRESHOLD_BYTES && std::fs::rename(&source, &target).is_ok() {
+                migrated.moved.push(relative);
+                continue;
+            }
+            let tmp = target.with_file_name(format!("{}.migrating", entry.file_name().to_string_lossy()));
+            std::fs::copy(&source, &tmp).with_context(|| format!("Failed to copy {}", source.display()))?;
+            if std::fs::metadata(&tmp)?.len() != size {
+                let _ = std::fs::remove_file(&tmp);
+                return Err(anyhow!("Copy of {} is incomplete", source.display()));
+            }
+            std::fs::rename(&tmp, &target)?;
+            migrated.copied.push(relative);
+        }
+        // Symlinks are left behind; they may point anywhere
+    }
+    Ok(())
+}
+
+/// `dir` renamed to a backup name beside it
+fn backup_name(dir: &Path) -> PathBuf {
+    let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    dir.with_file_name(format!("{}.backup-{}", name, clock::now().format("%Y%m%d%H%M%S")))
+}
+
+/// Bring every existing legacy directory into `target`, unless done before
+pub fn migrate(legacy: &[PathBuf], target: &Path) -> Result<Option<MigrationRecord>> {
+    if MigrationRecord::load(target).is_some() {
+        return Ok(None);
+    }
+    let mut record = MigrationRecord {
+        migrated_at: clock::timestamp(),
+        target: target.to_path_buf(),
+        ..Default::default()
+    };
+    for from in legacy.iter().filter(|dir| dir.is_dir() && dir.as_path() != target) {
+        info!("Migrating data from {}", from.display());
+        std::fs::create_dir_all(target)?;
+        let mut migrated = MigratedDir {
+            from: from.clone(),
+            ..Default::default()
+        };
+        if let Err(e) = copy_tree(from, target, Path::new(""), &mut migrated) {
+            // Take back this directory's files; the migration is retried on
+            // the next start
+            if let Err(undo_error) = undo(&migrated, target) {
+                warn!("Failed to undo partial migration: {:#}", undo_error);
+            }
+            return Err(e.context(format!("Failed to migrate {}", from.display())));
+        }
+        let backup = backup_name(from);
+        match std::fs::rename(from, &backup) {
+            Ok(()) => migrated.backup = Some(backup),
+            Err(e) => warn!("Left {} in place: {}", from.display(), e),
+        }
+        info!(
+            "Migrated {} file(s), moved {}, kept {} newer",
+            migrated.copied.len(),
+            migrated.moved.len(),
+            migrated.skipped.len()
+        );
+        record.sources.push(migrated);
+    }
+    // Written even when there was nothing to migrate, so the search isn't
+    // repeated on every start
+    record.save()?;
+    Ok(Some(record))
+}
+
+/// Move the moved files back to `migrated.from` and delete the copies
+fn undo(migrated: &MigratedDir, target: &Path) -> Result<()> {
+    for relative in &migrated.moved {
+        let source = migrated.from.join(relative);
+        if let Some(parent) = source.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(target.join(relative), &source)
+            .with_context(|| format!("Failed to move back {}", source.display()))?;
+    }
+    for relative in &migrated.copied {
+        let _ = std::fs::remove_file(target.join(relative));
+    }
+    Ok(())
+}
+
+/// Put the legacy directories back and remove what was copied from them
+pub fn rollback(target: &Path) -> Result<MigrationRecord> {
+    let mut record = MigrationRecord::load(target).ok_or_else(|| anyhow!("No data migration to roll back"))?;
+    if record.rolled_back {
+        return Err(anyhow!("The data migration was already rolled back"));
+    }
+    for migrated in &record.sources {
+        if let Some(backup) = &migrated.backup {
+            if migrated.from.exists() {
+                return Err(anyhow!("{} exists again; move it aside first", migrated.from.display()));
+            }
+            std::fs::rename(backup, &migrated.from)
+                .with_context(|| format!("Failed to restore {}", migrated.from.display()))?;
+        }
+        undo(migrated, target)?;
+    }
+    record.rolled_back = true;
+    record.save()?;
+    Ok(record)
+}
+
+/// Run the migration into the app data directory; failures are logged and
+/// the app starts with whatever is there
+pub fn run() {
+    let target = paths::app_data_dir();
+    match migrate(&paths::legacy_data_dirs(), &target) {
+        Ok(Some(record)) if !record.sources.is_empty() => {
+            info!("Data now lives in {}", target.display());
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Data migration failed: {:#}", e),
+    }
+}
+
+/// What the one-time data migration did, if it ran
+#[tauri::command]
+pub async fn get_data_migration() -> Result<Option<MigrationRecord>, String> {
+    Ok(MigrationRecord::load(&paths::app_data_dir()))
+}
+
+/// Undo the data migration so an older version finds its data; restart the
+/// app afterwards
+#[tauri::command]
+pub async fn rollback_data_migration() -> Result<MigrationRecord, String> {
+    let record = rollback(&paths::app_data_dir()).map_err(|e| format!("{:#}", e))?;
+    info!("Data migration rolled back");
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_and_roll_back() {
+        let root = std::env::temp_dir().join(format!("auranexus_migration_{}", uuid::Uuid::new_v4()));
+        let legacy = root.join("AuraNexus");
+        let target = root.join("com.auranexus.app");
+        std::fs::create_dir_all(legacy.join("history")).unwrap();
+        std::fs::write(legacy.join("settings.toml"), "backend = \"native\"").unwrap();
+        std::fs::write(legacy.join("history").join("history.json"), "[]").unwrap();
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("settings.toml"), "newer").unwrap();
+
+        let record = migrate(&[legacy.clone(), root.join("missing")], &target).unwrap().unwrap();
+        assert_eq!(record.sources.len(), 1);
+        let migrated = &record.sources[0];
+        assert_eq!(migrated.copied, vec![PathBuf::from("history/history.json")]);
+        assert_eq!(migrated.skipped, vec![PathBuf::from("settings.toml")]);
+        assert_eq!(std::fs::read_to_string(target.join("history/history.json")).unwrap(), "[]");
+        assert_eq!(std::fs::read_to_string(target.join("settings.toml")).unwrap(), "newer");
+        // The original is kept as a backup, and the migration only runs once
+        assert!(!legacy.exists());
+        assert!(migrated.backup.as_ref().unwrap().join("settings.toml").exists());
+        assert!(migrate(&[legacy.clone()], &target).unwrap().is_none());
+
+        let rolled_back = rollback(&target).unwrap();
+        assert!(rolled_back.rolled_back);
+        assert!(legacy.join("history/history.json").exists());
+        assert!(!target.join("history/history.json").exists());
+        assert!(rollback(&target).is_err());
+        std::fs::remove_dir_all(root).ok();
+    }
+}
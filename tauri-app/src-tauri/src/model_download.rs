@@ -0,0 +1,190 @@
+// Model Download Module - Checksum-verified model downloads
+//
+// `download_model(url, sha256)` fetches a GGUF file into the models directory
+// with `downloader` (resuming an interrupted `.part`, verifying the digest and
+// renaming into place). Expected digests are kept in `models/manifest.json`,
+// so a download resumed later is checked against the same checksum even if
+// the caller doesn't pass it again.
+
+use crate::downloader::{self, DownloadProgress};
+use crate::paths;
+use crate::setup_wizard::ModelDownloadProgress;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// What is known about one model file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub url: String,
+    /// Expected SHA-256, lowercase hex
+    pub sha256: String,
+    /// Set once the file on disk has matched `sha256`
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub downloaded_at: Option<String>,
+}
+
+/// Expected checksums of downloaded models, keyed by file name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelManifest {
+    pub models: BTreeMap<String, ManifestEntry>,
+}
+
+impl ModelManifest {
+    fn path() -> PathBuf {
+        paths::models_dir().join("manifest.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to save model manifest to {}", path.display()))
+    }
+}
+
+/// File name a model URL is saved under (last path segment, no query)
+pub fn filename_from_url(url: &str) -> Result<String> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\\') {
+        return Err(anyhow!("Can't tell the model file name from {}", url));
+    }
+    Ok(name.to_string())
+}
+
+fn normalize_sha256(sha256: &str) -> Result<String> {
+    let sha256 = sha256.trim().to_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid SHA-256 checksum: {}", sha256));
+    }
+    Ok(sha256)
+}
+
+/// Download `url` into `dir` and verify it
+///
+/// Without `sha256` the checksum recorded in the manifest for the same file
+/// is used; a download with no known checksum is refused. An existing file
+/// that already matches is kept.
+pub fn download(
+    url: &str,
+    sha256: Option<&str>,
+    dir: &Path,
+    on_progress: impl FnMut(&DownloadProgress),
+) -> Result<PathBuf> {
+    let filename = filename_from_url(url)?;
+    let dest = dir.join(&filename);
+
+    let mut manifest = ModelManifest::load();
+    let sha256 = match sha256 {
+        Some(sha256) => normalize_sha256(sha256)?,
+        None => manifest
+            .models
+            .get(&filename)
+            .map(|entry| entry.sha256.clone())
+            .ok_or_else(|| anyhow!("No checksum known for {}", filename))?,
+    };
+
+    if dest.is_file() {
+        if downloader::sha256_file(&dest)? == sha256 {
+            info!("{} already downloaded and verified", filename);
+            return Ok(dest);
+        }
+        return Err(anyhow!(
+            "{} already exists with a different checksum",
+            dest.display()
+        ));
+    }
+
+    // Recorded first so an interrupted download resumes against the same digest
+    manifest.models.insert(
+        filename.clone(),
+        ManifestEntry {
+            url: url.to_string(),
+            sha256: sha256.clone(),
+            verified: false,
+            size_bytes: None,
+            downloaded_at: None,
+        },
+    );
+    manifest.save()?;
+
+    let size = downloader::download_file(url, &dest, Some(&sha256), on_progress)?;
+
+    let mut manifest = ModelManifest::load();
+    if let Some(entry) = manifest.models.get_mut(&filename) {
+        entry.verified = true;
+        entry.size_bytes = Some(size);
+        entry.downloaded_at = Some(crate::clock::timestamp());
+    }
+    manifest.save()?;
+
+    Ok(dest)
+}
+
+/// Download a model into the models directory, verifying its SHA-256
+///
+/// Emits `model-download-progress` events; an interrupted download resumes
+/// where it stopped when called again. Returns the model's path.
+#[tauri::command]
+pub async fn download_model(
+    url: String,
+    sha256: Option<String>,
+    window: tauri::Window,
+) -> Result<String, String> {
+    let model_id = filename_from_url(&url).map_err(|e| e.to_string())?;
+    info!("Downloading {}", model_id);
+
+    let path = tauri::async_runtime::spawn_blocking(move || {
+        download(&url, sha256.as_deref(), &paths::models_dir(), |progress: &DownloadProgress| {
+            let _ = window.emit(
+                "model-download-progress",
+                ModelDownloadProgress {
+                    model_id: model_id.clone(),
+                    progress: progress.clone(),
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    info!("Model downloaded to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_and_checksum_validation() {
+        assert_eq!(
+            filename_from_url("https://huggingface.co/x/y/resolve/main/model-q4.gguf?download=true")
+                .unwrap(),
+            "model-q4.gguf"
+        );
+        assert!(filename_from_url("https://example.com/models/").is_err());
+
+        let sha = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert_eq!(normalize_sha256(sha).unwrap(), sha.to_lowercase());
+        assert!(normalize_sha256("abc").is_err());
+    }
+}
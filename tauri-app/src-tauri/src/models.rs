@@ -1,5 +1,7 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
@@ -83,7 +85,13 @@ pub fn scan_all_model_locations() -> Result<Vec<ModelInfo>> {
         }),
         // User's home directory models folder
         dirs::home_dir().map(|p| p.join("models")),
+        // Models downloaded by the app (setup wizard)
+        Some(crate::paths::models_dir()),
     ];
+    // Folders added in settings.toml
+    let search_paths = search_paths
+        .into_iter()
+        .chain(crate::settings::AppSettings::load().models.extra_dirs.into_iter().map(Some));
 
     for path_option in search_paths {
         if let Some(path) = path_option {
@@ -156,6 +164,286 @@ pub async fn get_model_info(model_path: String) -> Result<ModelInfo, String> {
     })
 }
 
+/// Capabilities read from a GGUF header
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GgufMetadata {
+    pub gguf_version: u32,
+    /// e.g. "llama", "qwen2", "gemma3"
+    pub architecture: Option<String>,
+    /// `general.name`
+    pub name: Option<String>,
+    /// Total weights across all tensors
+    pub parameter_count: Option<u64>,
+    /// e.g. "Q4_K_M" (from `general.file_type`)
+    pub quantization: Option<String>,
+    /// Context length the model was trained for
+    pub context_length: Option<u64>,
+    pub chat_template: Option<String>,
+    pub vocab_size: Option<u64>,
+    /// Transformer layers
+    pub block_count: Option<u64>,
+    pub embedding_length: Option<u64>,
+    /// Attention heads, and key/value heads (fewer with grouped-query attention)
+    pub head_count: Option<u64>,
+    pub head_count_kv: Option<u64>,
+    /// How token vectors are pooled into one; set on embedding models
+    pub pooling_type: Option<u64>,
+    /// False for bidirectional (encoder-only) models
+    pub causal_attention: Option<bool>,
+}
+
+/// Strings longer than this in a header mean a corrupt file
+const MAX_GGUF_STRING_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A header value, as far as the metadata reader cares
+#[derive(Debug, Clone, PartialEq)]
+enum GgufValue {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    /// Array contents are skipped; only the length is kept
+    Array(u64),
+}
+
+impl GgufValue {
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            GgufValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn into_string(self) -> Option<String> {
+        match self {
+            GgufValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn skip(reader: &mut impl Read, bytes: u64) -> Result<()> {
+    let skipped = std::io::copy(&mut reader.by_ref().take(bytes), &mut std::io::sink())?;
+    if skipped < bytes {
+        return Err(anyhow!("GGUF header is truncated"));
+    }
+    Ok(())
+}
+
+fn read_string_len(reader: &mut impl Read) -> Result<u64> {
+    let len = read_u64(reader)?;
+    if len > MAX_GGUF_STRING_BYTES {
+        return Err(anyhow!("GGUF string of {} bytes; file is corrupt", len));
+    }
+    Ok(len)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_string_len(reader)?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Size of a fixed-size GGUF value type, `None` for strings and arrays
+fn scalar_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_value(reader: &mut impl Read, value_type: u32) -> Result<GgufValue> {
+    let mut bytes = [0u8; 8];
+    if let Some(size) = scalar_size(value_type) {
+        reader.read_exact(&mut bytes[..size as usize])?;
+    }
+    Ok(match value_type {
+        0 => GgufValue::Int(bytes[0] as i128),
+        1 => GgufValue::Int(bytes[0] as i8 as i128),
+        2 => GgufValue::Int(u16::from_le_bytes([bytes[0], bytes[1]]) as i128),
+        3 => GgufValue::Int(i16::from_le_bytes([bytes[0], bytes[1]]) as i128),
+        4 => GgufValue::Int(u32::from_le_bytes(bytes[..4].try_into()?) as i128),
+        5 => GgufValue::Int(i32::from_le_bytes(bytes[..4].try_into()?) as i128),
+        6 => GgufValue::Float(f32::from_le_bytes(bytes[..4].try_into()?) as f64),
+        7 => GgufValue::Bool(bytes[0] != 0),
+        8 => GgufValue::Str(read_string(reader)?),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            match scalar_size(item_type) {
+                Some(size) => skip(reader, size.saturating_mul(len))?,
+                None => {
+                    for _ in 0..len {
+                        match item_type {
+                            8 => {
+                                let len = read_string_len(reader)?;
+                                skip(reader, len)?;
+                            }
+                            _ => {
+                                read_value(reader, item_type)?;
+                            }
+                        }
+                    }
+                }
+            }
+            GgufValue::Array(len)
+        }
+        10 => GgufValue::Int(u64::from_le_bytes(bytes) as i128),
+        11 => GgufValue::Int(i64::from_le_bytes(bytes) as i128),
+        12 => GgufValue::Float(f64::from_le_bytes(bytes)),
+        other => return Err(anyhow!("Unknown GGUF value type {}", other)),
+    })
+}
+
+/// Name of a llama.cpp `general.file_type`
+fn file_type_name(file_type: u64) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        _ => return None,
+    })
+}
+
+/// Read the metadata and tensor list of a GGUF stream (no weights)
+pub fn parse_gguf(reader: &mut impl Read) -> Result<GgufMetadata> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).context("File is too short")?;
+    if &magic != b"GGUF" {
+        return Err(anyhow!("Not a GGUF file"));
+    }
+    let version = read_u32(reader)?;
+    if version < 2 {
+        return Err(anyhow!("GGUF version {} is too old", version));
+    }
+    let tensor_count = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+
+    let mut values: HashMap<String, GgufValue> = HashMap::new();
+    for _ in 0..kv_count {
+        let key = read_string(reader)?;
+        let value_type = read_u32(reader)?;
+        values.insert(key, read_value(reader, value_type)?);
+    }
+
+    let mut parameter_count: u64 = 0;
+    for _ in 0..tensor_count {
+        let name_len = read_string_len(reader)?;
+        skip(reader, name_len)?;
+        let n_dims = read_u32(reader)?;
+        let mut elements: u64 = 1;
+        for _ in 0..n_dims {
+            elements = elements.saturating_mul(read_u64(reader)?);
+        }
+        // Tensor type and data offset
+        skip(reader, 4 + 8)?;
+        parameter_count = parameter_count.saturating_add(elements);
+    }
+
+    let architecture = values
+        .remove("general.architecture")
+        .and_then(GgufValue::into_string);
+    let arch_key = |suffix: &str| format!("{}.{}", architecture.as_deref().unwrap_or_default(), suffix);
+
+    Ok(GgufMetadata {
+        gguf_version: version,
+        name: values.remove("general.name").and_then(GgufValue::into_string),
+        parameter_count: (tensor_count > 0).then_some(parameter_count),
+        quantization: values
+            .get("general.file_type")
+            .and_then(GgufValue::as_u64)
+            .and_then(file_type_name)
+            .map(str::to_string),
+        context_length: values.get(&arch_key("context_length")).and_then(GgufValue::as_u64),
+        chat_template: values
+            .remove("tokenizer.chat_template")
+            .and_then(GgufValue::into_string),
+        vocab_size: values
+            .get(&arch_key("vocab_size"))
+            .and_then(GgufValue::as_u64)
+            .or_else(|| match values.get("tokenizer.ggml.tokens") {
+                Some(GgufValue::Array(len)) => Some(*len),
+                _ => None,
+            }),
+        block_count: values.get(&arch_key("block_count")).and_then(GgufValue::as_u64),
+        embedding_length: values.get(&arch_key("embedding_length")).and_then(GgufValue::as_u64),
+        head_count: values.get(&arch_key("attention.head_count")).and_then(GgufValue::as_u64),
+        head_count_kv: values
+            .get(&arch_key("attention.head_count_kv"))
+            .and_then(GgufValue::as_u64),
+        pooling_type: values.get(&arch_key("pooling_type")).and_then(GgufValue::as_u64),
+        causal_attention: values.get(&arch_key("attention.causal")).and_then(GgufValue::as_bool),
+        architecture,
+    })
+}
+
+/// Read a model file's GGUF header without loading the model
+pub fn read_gguf_metadata(path: &Path) -> Result<GgufMetadata> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    parse_gguf(&mut BufReader::new(file))
+        .with_context(|| format!("Failed to read GGUF header of {}", path.display()))
+}
+
+/// Architecture, size, quantization, context and template of a model file
+#[tauri::command]
+pub async fn get_gguf_metadata(model_path: String) -> Result<GgufMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || read_gguf_metadata(Path::new(&model_path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,4 +457,68 @@ mod tests {
         assert_eq!(format_size(1048576), "1.00 MB");
         assert_eq!(format_size(1073741824), "1.00 GB");
     }
+
+    fn gguf_string(out: &mut Vec<u8>, s: &str) {
+        out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    #[test]
+    fn test_parse_gguf_header() {
+        let mut file = b"GGUF".to_vec();
+        file.extend_from_slice(&3u32.to_le_bytes());
+        file.extend_from_slice(&2u64.to_le_bytes()); // tensors
+        file.extend_from_slice(&6u64.to_le_bytes()); // key/values
+
+        gguf_string(&mut file, "general.architecture");
+        file.extend_from_slice(&8u32.to_le_bytes());
+        gguf_string(&mut file, "llama");
+
+        gguf_string(&mut file, "llama.context_length");
+        file.extend_from_slice(&4u32.to_le_bytes());
+        file.extend_from_slice(&8192u32.to_le_bytes());
+
+        gguf_string(&mut file, "llama.block_count");
+        file.extend_from_slice(&4u32.to_le_bytes());
+        file.extend_from_slice(&32u32.to_le_bytes());
+
+        gguf_string(&mut file, "general.file_type");
+        file.extend_from_slice(&4u32.to_le_bytes());
+        file.extend_from_slice(&15u32.to_le_bytes());
+
+        gguf_string(&mut file, "tokenizer.ggml.tokens");
+        file.extend_from_slice(&9u32.to_le_bytes());
+        file.extend_from_slice(&8u32.to_le_bytes());
+        file.extend_from_slice(&3u64.to_le_bytes());
+        for token in ["<s>", "hello", "world"] {
+            gguf_string(&mut file, token);
+        }
+
+        gguf_string(&mut file, "tokenizer.chat_template");
+        file.extend_from_slice(&8u32.to_le_bytes());
+        gguf_string(&mut file, "{{ '<|start_header_id|>' }}");
+
+        for (name, dims) in [("token_embd.weight", [4u64, 3]), ("output.weight", [4, 2])] {
+            gguf_string(&mut file, name);
+            file.extend_from_slice(&2u32.to_le_bytes());
+            for dim in dims {
+                file.extend_from_slice(&dim.to_le_bytes());
+            }
+            file.extend_from_slice(&0u32.to_le_bytes());
+            file.extend_from_slice(&0u64.to_le_bytes());
+        }
+
+        let metadata = parse_gguf(&mut file.as_slice()).unwrap();
+        assert_eq!(metadata.gguf_version, 3);
+        assert_eq!(metadata.architecture.as_deref(), Some("llama"));
+        assert_eq!(metadata.context_length, Some(8192));
+        assert_eq!(metadata.quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(metadata.vocab_size, Some(3));
+        assert_eq!(metadata.parameter_count, Some(20));
+        assert_eq!(metadata.block_count, Some(32));
+        assert_eq!(metadata.head_count_kv, None);
+        assert!(metadata.chat_template.unwrap().contains("start_header_id"));
+
+        assert!(parse_gguf(&mut &b"GGML\0\0\0\0"[..]).is_err());
+    }
 }
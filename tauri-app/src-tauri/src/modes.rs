@@ -0,0 +1,379 @@
+// Modes Module - User-defined modes beside Companion and Youniverse
+//
+// A custom mode is a named persona: its own system prompt, sampling and
+// memory policy, saved as `[[modes]]` in settings.toml. Its id doubles as the agent id its
+// memories are stored under, so switching to it scopes memory like the
+// built-in modes. Conversations left in a mode that was since deleted fall
+// back to Companion.
+
+use crate::memory_policy::{MemoryPolicy, PersonaPolicies};
+use crate::settings::{self, AppSettings};
+use crate::{AppMode, AppState, LlmConfig};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Ids of the modes that are always there
+pub const BUILTIN_MODES: [&str; 2] = ["companion", "youniverse"];
+
+const MAX_NAME_CHARS: usize = 64;
+pub const MAX_PROMPT_CHARS: usize = 16_000;
+
+/// A mode created by the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomMode {
+    /// Derived from the name when created; never changes
+    pub id: String,
+    pub name: String,
+    pub system_prompt: String,
+    #[serde(default)]
+    pub sampling: LlmConfig,
+    #[serde(default = "default_policy")]
+    pub memory_policy: MemoryPolicy,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn default_policy() -> MemoryPolicy {
+    MemoryPolicy::ShareAll
+}
+
+/// Built-in and custom modes, as listed to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct ModeInfo {
+    pub id: String,
+    pub name: String,
+    pub builtin: bool,
+    pub system_prompt: String,
+    pub sampling: LlmConfig,
+    pub memory_policy: MemoryPolicy,
+}
+
+/// A mode's system prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemPrompt {
+    pub mode: String,
+    pub text: String,
+    /// The prompt the app ships with (built-in modes only)
+    pub default_text: Option<String>,
+    /// Differs from the shipped prompt
+    pub customized: bool,
+}
+
+impl SystemPrompt {
+    fn of(mode: &AppMode) -> Self {
+        let text = mode.system_prompt();
+        let default_text = (!matches!(mode, AppMode::Custom(_))).then(|| mode.default_system_prompt());
+        Self {
+            mode: mode.to_string(),
+            customized: default_text.as_ref().is_some_and(|default| *default != text),
+            default_text,
+            text,
+        }
+    }
+}
+
+/// Fields to change in `update_mode` (absent ones are kept)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ModeChanges {
+    pub name: Option<String>,
+    pub system_prompt: Option<String>,
+    pub sampling: Option<LlmConfig>,
+    pub memory_policy: Option<MemoryPolicy>,
+}
+
+/// Custom modes, `[[modes]]` in settings.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ModeRegistry {
+    pub modes: Vec<CustomMode>,
+}
+
+impl ModeRegistry {
+    pub fn load() -> Self {
+        AppSettings::load().modes
+    }
+
+    pub fn save(&self) -> Result<()> {
+        settings::update(|settings| settings.modes = self.clone()).map(|_| ())
+    }
+
+    pub fn list(&self) -> &[CustomMode] {
+        &self.modes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CustomMode> {
+        self.modes.iter().find(|mode| mode.id == id)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut CustomMode> {
+        self.modes
+            .iter_mut()
+            .find(|mode| mode.id == id)
+            .ok_or_else(|| anyhow!("No custom mode {}", id))
+    }
+
+    /// Unused id for a mode called `name`
+    fn new_id(&self, name: &str) -> String {
+        unique_slug(name, "mode", |id| BUILTIN_MODES.contains(&id) || self.get(id).is_some())
+    }
+
+    /// Add a mode; returns it with its new id
+    pub fn create(
+        &mut self,
+        name: &str,
+        system_prompt: &str,
+        sampling: LlmConfig,
+        memory_policy: MemoryPolicy,
+    ) -> Result<CustomMode> {
+        validate(name, system_prompt)?;
+        let now = crate::clock::timestamp();
+        let mode = CustomMode {
+            id: self.new_id(name),
+            name: name.trim().to_string(),
+            system_prompt: system_prompt.trim().to_string(),
+            sampling,
+            memory_policy,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        self.modes.push(mode.clone());
+        Ok(mode)
+    }
+
+    pub fn update(&mut self, id: &str, changes: ModeChanges) -> Result<CustomMode> {
+        let mode = self.get_mut(id)?;
+        let name = changes.name.unwrap_or_else(|| mode.name.clone());
+        let system_prompt = changes.system_prompt.unwrap_or_else(|| mode.system_prompt.clone());
+        validate(&name, &system_prompt)?;
+
+        mode.name = name.trim().to_string();
+        mode.system_prompt = system_prompt.trim().to_string();
+        if let Some(sampling) = changes.sampling {
+            mode.sampling = sampling;
+        }
+        if let Some(policy) = changes.memory_policy {
+            mode.memory_policy = policy;
+        }
+        mode.updated_at = crate::clock::timestamp();
+        Ok(mode.clone())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<CustomMode> {
+        let index = self
+            .modes
+            .iter()
+            .position(|mode| mode.id == id)
+            .ok_or_else(|| anyhow!("No custom mode {}", id))?;
+        Ok(self.modes.remove(index))
+    }
+}
+
+/// Lowercase-and-dashes id for `name` that `taken` doesn't reject, numbered
+/// if needed ("story", "story-2", ...)
+pub fn unique_slug(name: &str, fallback: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut slug = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = match slug.trim_end_matches('-') {
+        "" => fallback.to_string(),
+        slug => slug.to_string(),
+    };
+    if !taken(&slug) {
+        return slug;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", slug, n))
+        .find(|id| !taken(id))
+        .expect("unbounded range")
+}
+
+fn validate(name: &str, system_prompt: &str) -> Result<()> {
+    let name_chars = name.trim().chars().count();
+    if name_chars == 0 || name_chars > MAX_NAME_CHARS {
+        return Err(anyhow!("Mode names must be 1-{} characters", MAX_NAME_CHARS));
+    }
+    if system_prompt.trim().is_empty() {
+        return Err(anyhow!("A mode needs a system prompt"));
+    }
+    if system_prompt.chars().count() > MAX_PROMPT_CHARS {
+        return Err(anyhow!("System prompts are limited to {} characters", MAX_PROMPT_CHARS));
+    }
+    Ok(())
+}
+
+impl From<&CustomMode> for ModeInfo {
+    fn from(mode: &CustomMode) -> Self {
+        Self {
+            id: mode.id.clone(),
+            name: mode.name.clone(),
+            builtin: false,
+            system_prompt: mode.system_prompt.clone(),
+            sampling: mode.sampling.clone(),
+            memory_policy: mode.memory_policy,
+        }
+    }
+}
+
+/// Built-in modes, then custom ones in the order they were created
+#[tauri::command]
+pub async fn list_modes() -> Result<Vec<ModeInfo>, String> {
+    let policies = PersonaPolicies::load();
+    let builtin = [(AppMode::Companion, "Companion"), (AppMode::Youniverse, "Youniverse")]
+        .into_iter()
+        .map(|(mode, name)| ModeInfo {
+            id: mode.to_string(),
+            name: name.to_string(),
+            builtin: true,
+            system_prompt: mode.system_prompt(),
+            sampling: mode.sampling_config(),
+            memory_policy: policies.policy_for(&mode.to_string()),
+        });
+    let registry = ModeRegistry::load();
+    Ok(builtin.chain(registry.modes.iter().map(ModeInfo::from)).collect())
+}
+
+/// Create a custom mode; sampling defaults to Companion's
+#[tauri::command]
+pub async fn create_mode(
+    name: String,
+    system_prompt: String,
+    sampling: Option<LlmConfig>,
+    memory_policy: Option<MemoryPolicy>,
+) -> Result<CustomMode, String> {
+    let mut registry = ModeRegistry::load();
+    let mode = registry
+        .create(
+            &name,
+            &system_prompt,
+            sampling.unwrap_or_else(|| AppMode::Companion.sampling_config()),
+            memory_policy.unwrap_or(MemoryPolicy::ShareAll),
+        )
+        .map_err(|e| e.to_string())?;
+    registry.save().map_err(|e| e.to_string())?;
+    info!("Created mode {} ({})", mode.name, mode.id);
+    Ok(mode)
+}
+
+/// Change a custom mode; it applies from the next message
+#[tauri::command]
+pub async fn update_mode(id: String, changes: ModeChanges) -> Result<CustomMode, String> {
+    let policy_changed = changes.memory_policy.is_some();
+    let mut registry = ModeRegistry::load();
+    let mode = registry.update(&id, changes).map_err(|e| e.to_string())?;
+    registry.save().map_err(|e| e.to_string())?;
+
+    // The mode's own policy replaces one set earlier with set_memory_policy
+    if policy_changed {
+        let mut policies = PersonaPolicies::load();
+        if policies.policies.remove(&id).is_some() {
+            policies.save().map_err(|e| e.to_string())?;
+        }
+    }
+    info!("Updated mode {} ({})", mode.name, mode.id);
+    Ok(mode)
+}
+
+/// Delete a custom mode (not while it is the current one); its memories stay
+#[tauri::command]
+pub async fn delete_mode(id: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if state.current_mode.lock().to_string() == id {
+        return Err("Switch to another mode before deleting this one".to_string());
+    }
+    let mut registry = ModeRegistry::load();
+    let mode = registry.remove(&id).map_err(|e| e.to_string())?;
+    registry.save().map_err(|e| e.to_string())?;
+    info!("Deleted mode {} ({})", mode.name, mode.id);
+    Ok(())
+}
+
+fn named_mode(name: &str) -> Result<AppMode, String> {
+    AppMode::from_name(name).ok_or_else(|| format!("Unknown mode: {}", name))
+}
+
+/// The system prompt a mode currently uses
+#[tauri::command]
+pub async fn get_system_prompt(mode: String) -> Result<SystemPrompt, String> {
+    Ok(SystemPrompt::of(&named_mode(&mode)?))
+}
+
+/// Replace a mode's system prompt; for a built-in mode an empty `text`
+/// restores the shipped prompt. Applies from the next message.
+#[tauri::command]
+pub async fn set_system_prompt(mode: String, text: String) -> Result<SystemPrompt, String> {
+    let mode = named_mode(&mode)?;
+    match &mode {
+        AppMode::Custom(id) => {
+            let mut registry = ModeRegistry::load();
+            let changes = ModeChanges {
+                system_prompt: Some(text),
+                ..Default::default()
+            };
+            registry.update(id, changes).map_err(|e| e.to_string())?;
+            registry.save().map_err(|e| e.to_string())?;
+        }
+        builtin => {
+            let text = text.trim();
+            if text.chars().count() > MAX_PROMPT_CHARS {
+                return Err(format!("System prompts are limited to {} characters", MAX_PROMPT_CHARS));
+            }
+            // Saving the shipped prompt (or nothing) tracks it across updates
+            let custom = Some(text.to_string())
+                .filter(|text| !text.is_empty() && *text != builtin.default_system_prompt());
+            crate::settings::update(|settings| {
+                if let Some(slot) = settings.prompts.for_mode_mut(builtin) {
+                    *slot = custom;
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    info!("Updated the {} system prompt", mode.to_string());
+    Ok(SystemPrompt::of(&mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_avoid_builtins_and_duplicates() {
+        let mut registry = ModeRegistry::default();
+        let tutor = registry
+            .create("Math Tutor!", "You teach maths.", LlmConfig::default(), MemoryPolicy::Isolated)
+            .unwrap();
+        assert_eq!(tutor.id, "math-tutor");
+        let again = registry
+            .create("Math  tutor", "You teach maths too.", LlmConfig::default(), MemoryPolicy::ShareAll)
+            .unwrap();
+        assert_eq!(again.id, "math-tutor-2");
+        let companion = registry
+            .create("Companion", "Another companion.", LlmConfig::default(), MemoryPolicy::ShareAll)
+            .unwrap();
+        assert_eq!(companion.id, "companion-2");
+        assert!(registry.create(" ", "Prompt", LlmConfig::default(), MemoryPolicy::ShareAll).is_err());
+
+        let updated = registry
+            .update(
+                "math-tutor",
+                ModeChanges {
+                    name: Some("Maths Tutor".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.id, "math-tutor");
+        assert_eq!(updated.system_prompt, "You teach maths.");
+        assert_eq!(updated.memory_policy, MemoryPolicy::Isolated);
+
+        registry.remove("math-tutor-2").unwrap();
+        assert!(registry.get("math-tutor-2").is_none());
+        assert!(registry.remove("math-tutor-2").is_err());
+    }
+}
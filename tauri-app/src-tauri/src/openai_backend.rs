@@ -0,0 +1,407 @@
+// OpenAI Backend Module - Client for OpenAI-compatible chat APIs
+//
+// Works with anything that serves `POST {base_url}/chat/completions` with
+// server-sent events: llama-server, LM Studio, Ollama (`/v1`), OpenRouter or
+// OpenAI itself. The base URL, API key and model come from `RemoteSettings`;
+// without a model the first one listed by `GET {base_url}/models` is used.
+//
+// Only standard OpenAI sampling fields are sent, since hosted APIs reject
+// unknown ones. Streams are bounded by the same `BackendTimeouts` as the
+// llm_server.py client.
+
+use crate::backend::{GenerationRequest, LlmBackend};
+use crate::generation::CancellationToken;
+use crate::http_backend::{BackendError, BackendTimeouts, Completion, GenerationStats};
+use crate::logit_bias;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// How often a stalled stream re-checks for cancellation
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where to reach a remote OpenAI-compatible API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    /// e.g. `http://localhost:8080/v1` or `https://openrouter.ai/api/v1`
+    pub base_url: String,
+    /// Sent as `Authorization: Bearer <key>`; local servers usually need none
+    pub api_key: Option<String>,
+    /// Model id; if unset the server's first listed model is used
+    pub model: Option<String>,
+}
+
+/// Blocking client for an OpenAI-compatible API
+pub struct OpenAiBackend {
+    settings: RemoteSettings,
+    client: reqwest::blocking::Client,
+    timeouts: BackendTimeouts,
+    /// Model in use, resolved on first generation when not configured
+    model: Option<String>,
+}
+
+impl OpenAiBackend {
+    pub fn new(settings: RemoteSettings, timeouts: BackendTimeouts) -> Result<Self> {
+        let base_url = settings.base_url.trim().trim_end_matches('/').to_string();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(anyhow!("Remote backend needs an http(s) base URL, got '{}'", base_url));
+        }
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(None::<Duration>)
+            .connect_timeout(Duration::from_secs(timeouts.connect_secs.max(1)))
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self {
+            model: settings.model.clone().filter(|model| !model.trim().is_empty()),
+            settings: RemoteSettings { base_url, ..settings },
+            client,
+            timeouts,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.settings.base_url, path)
+    }
+
+    fn authorize(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        match self.settings.api_key.as_deref().filter(|key| !key.is_empty()) {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Model ids the server offers
+    pub fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .authorize(self.client.get(self.url("models")))
+            .timeout(Duration::from_secs(self.timeouts.health_secs.max(1)))
+            .send()
+            .with_context(|| format!("Failed to reach {}", self.settings.base_url))?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Listing models failed: server returned {}", response.status()));
+        }
+        let body: serde_json::Value = response.json().context("Failed to parse model list")?;
+        Ok(body["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|model| model["id"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// The configured model, or the first the server lists
+    fn model(&mut self) -> Result<String> {
+        if let Some(model) = &self.model {
+            return Ok(model.clone());
+        }
+        let model = self
+            .list_models()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("{} lists no models; set one in the backend settings", self.settings.base_url))?;
+        info!("Using remote model {}", model);
+        self.model = Some(model.clone());
+        Ok(model)
+    }
+
+    /// Stream a chat completion, stopping early when `is_cancelled` or
+    /// `on_token` says so
+    fn stream_chat(
+        &self,
+        body: &serde_json::Value,
+        is_cancelled: impl Fn() -> bool,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        let total_limit = Duration::from_secs(self.timeouts.generate_secs.max(1));
+        let started = Instant::now();
+        let response = self
+            .authorize(self.client.post(self.url("chat/completions")))
+            .json(body)
+            .timeout(total_limit)
+            .send()
+            .with_context(|| format!("Failed to connect to {}", self.settings.base_url))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let detail = response
+                .json::<serde_json::Value>()
+                .ok()
+                .and_then(|body| body["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            return Err(anyhow!("Remote backend returned {} {}", status, detail));
+        }
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(response).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let first_token_limit = Duration::from_secs(self.timeouts.first_token_secs.max(1));
+        let idle_limit = Duration::from_secs(self.timeouts.idle_secs.max(1));
+        let mut text = String::new();
+        let mut stats = GenerationStats {
+            backend: "openai".to_string(),
+            ..Default::default()
+        };
+        let mut streamed_tokens = 0u32;
+        let mut first_token = None;
+        let mut waiting_since = Instant::now();
+        loop {
+            let (operation, wait_limit) = match first_token {
+                Some(_) => ("next token", idle_limit),
+                None => ("first token", first_token_limit),
+            };
+            let deadline = (waiting_since + wait_limit).min(started + total_limit);
+            let now = Instant::now();
+            if now >= deadline {
+                let (operation, limit) = if now >= started + total_limit {
+                    ("generation", total_limit)
+                } else {
+                    (operation, wait_limit)
+                };
+                return Err(BackendError::Timeout { operation, limit }.into());
+            }
+
+            let line = match lines.recv_timeout((deadline - now).min(CANCEL_POLL_INTERVAL)) {
+                Ok(line) => line.context("Failed to read remote stream")?,
+                Err(RecvTimeoutError::Timeout) if is_cancelled() => break,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+
+            let event: serde_json::Value = serde_json::from_str(data)
+                .with_context(|| format!("Malformed stream event: {}", data))?;
+            if let Some(message) = event["error"]["message"].as_str() {
+                return Err(anyhow!("Remote backend error: {}", message));
+            }
+            apply_event_metadata(&mut stats, &event);
+
+            let token = event["choices"][0]["delta"]["content"].as_str().unwrap_or_default();
+            if !token.is_empty() {
+                waiting_since = Instant::now();
+                first_token.get_or_insert(waiting_since);
+                streamed_tokens += 1;
+                text.push_str(token);
+                if !on_token(token) {
+                    break;
+                }
+            }
+        }
+
+        stats.completion_tokens.get_or_insert(streamed_tokens);
+        stats.finish(started, first_token);
+        Ok(Completion {
+            text,
+            stats,
+            tool_calls: Vec::new(),
+        })
+    }
+}
+
+/// Chat Completions request body for `request`
+pub fn request_body(request: &GenerationRequest, model: &str) -> serde_json::Value {
+    let mut messages = vec![serde_json::json!({
+        "role": "system",
+        "content": request.system_prompt,
+    })];
+    messages.extend(request.history.iter().map(|entry| {
+        serde_json::json!({
+            "role": entry.role,
+            "content": entry.content,
+        })
+    }));
+    messages.push(serde_json::json!({
+        "role": "user",
+        "content": request.prompt,
+    }));
+
+    let config = request.config;
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "stream": true,
+        "stream_options": { "include_usage": true },
+        "temperature": config.temperature,
+        "top_p": config.top_p,
+        "max_tokens": config.max_tokens,
+    });
+    if let Some(penalty) = config.frequency_penalty {
+        body["frequency_penalty"] = serde_json::json!(penalty);
+    }
+    if let Some(penalty) = config.presence_penalty {
+        body["presence_penalty"] = serde_json::json!(penalty);
+    }
+    if !config.stop.is_empty() {
+        body["stop"] = serde_json::json!(config.stop);
+    }
+    if let Some(seed) = config.seed {
+        body["seed"] = serde_json::json!(seed);
+    }
+    let logit_bias = logit_bias::token_ids(&config.logit_bias);
+    if !logit_bias.is_empty() {
+        body["logit_bias"] = serde_json::json!(logit_bias);
+    }
+    if let Some(schema) = &config.json_schema {
+        body["response_format"] = serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": "reply", "schema": schema },
+        });
+    }
+    body
+}
+
+/// Model, finish reason and usage from a streamed chunk
+fn apply_event_metadata(stats: &mut GenerationStats, event: &serde_json::Value) {
+    if let Some(model) = event["model"].as_str() {
+        stats.model = Some(model.to_string());
+    }
+    if let Some(reason) = event["choices"][0]["finish_reason"].as_str() {
+        stats.finish_reason = Some(reason.to_string());
+        stats.truncated = reason == "length";
+    }
+    let usage = &event["usage"];
+    if let Some(tokens) = usage["prompt_tokens"].as_u64() {
+        stats.prompt_tokens = Some(tokens as u32);
+    }
+    if let Some(tokens) = usage["completion_tokens"].as_u64() {
+        stats.completion_tokens = Some(tokens as u32);
+    }
+}
+
+impl LlmBackend for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn generate(
+        &mut self,
+        request: &GenerationRequest,
+        cancel: &CancellationToken,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Completion> {
+        let body = request_body(request, &self.model()?);
+        self.stream_chat(&body, || cancel.is_cancelled(), on_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConversationEntry, EntryStatus, LlmConfig};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve one server-sent event response
+    fn serve(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 8192];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}",
+                body
+            );
+        });
+        format!("http://{}/v1", addr)
+    }
+
+    #[test]
+    fn test_streams_chat_completion() {
+        let url = serve(concat!(
+            "data: {\"model\":\"qwen\",\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" there\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2}}\n\n",
+            "data: [DONE]\n\n",
+        ));
+        let mut backend = OpenAiBackend::new(
+            RemoteSettings {
+                base_url: url,
+                api_key: Some("sk-test".to_string()),
+                model: Some("qwen".to_string()),
+            },
+            BackendTimeouts::default(),
+        )
+        .unwrap();
+
+        let history = vec![ConversationEntry {
+            role: "assistant".to_string(),
+            content: "Hello!".to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }];
+        let config = LlmConfig::default();
+        let request = GenerationRequest {
+            prompt: "How are you?",
+            system_prompt: "Be kind.",
+            history: &history,
+            config: &config,
+        };
+
+        let body = request_body(&request, "qwen");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(body["messages"][2]["content"], "How are you?");
+        assert!(body.get("top_k").is_none());
+        assert!(body.get("seed").is_none());
+        assert!(body.get("response_format").is_none());
+        let seeded = LlmConfig {
+            seed: Some(42),
+            json_schema: Some(serde_json::json!({ "type": "object" })),
+            ..LlmConfig::default()
+        };
+        let replay = GenerationRequest {
+            config: &seeded,
+            ..request
+        };
+        let replay_body = request_body(&replay, "qwen");
+        assert_eq!(replay_body["seed"], 42);
+        assert_eq!(replay_body["response_format"]["json_schema"]["schema"]["type"], "object");
+
+        let mut tokens = Vec::new();
+        let completion = backend
+            .generate(&request, &CancellationToken::new(), &mut |token| {
+                tokens.push(token.to_string());
+                true
+            })
+            .unwrap();
+        assert_eq!(completion.text, "Hi there");
+        assert_eq!(tokens, vec!["Hi", " there"]);
+        assert_eq!(completion.stats.model.as_deref(), Some("qwen"));
+        assert_eq!(completion.stats.prompt_tokens, Some(9));
+        assert_eq!(completion.stats.finish_reason.as_deref(), Some("stop"));
+    }
+
+    #[test]
+    fn test_rejects_bad_base_url() {
+        let settings = RemoteSettings {
+            base_url: "localhost:8080".to_string(),
+            ..Default::default()
+        };
+        assert!(OpenAiBackend::new(settings, BackendTimeouts::default()).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+// Application data paths
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Bundle identifier from tauri.conf.json; names the data directory
+const APP_IDENTIFIER: &str = "com.auranexus.app";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Resolve the data directory the way Tauri does for this app; call once at
+/// startup before anything is read or written
+pub fn init(config: &tauri::Config) {
+    if let Some(dir) = tauri::api::path::app_data_dir(config) {
+        let _ = DATA_DIR.set(dir);
+    }
+}
+
+/// Root directory for AuraNexus data (history, settings, caches)
+///
+/// e.g. `%APPDATA%\com.auranexus.app` on Windows,
+/// `~/.local/share/com.auranexus.app` on Linux.
+pub fn app_data_dir() -> PathBuf {
+    DATA_DIR.get().cloned().unwrap_or_else(|| {
+        dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(APP_IDENTIFIER)
+    })
+}
+
+/// Files attached to conversations, one directory per run id
+pub fn attachments_dir() -> PathBuf {
+    app_data_dir().join("attachments")
+}
+
+/// `path` resolved, if it is a file inside `attachments_dir()`
+///
+/// Links and `..` are followed first, so neither can lead outside it.
+pub fn resolve_attachment(path: &Path) -> Option<PathBuf> {
+    let root = attachments_dir().canonicalize().ok()?;
+    let resolved = path.canonicalize().ok()?;
+    (resolved.starts_with(&root) && resolved.is_file()).then_some(resolved)
+}
+
+/// Where earlier versions kept their data: `AuraNexus` in the user data
+/// directory, and in the temp directory when that was unavailable
+pub fn legacy_data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::data_dir().into_iter().map(|dir| dir.join("AuraNexus")).collect();
+    dirs.push(std::env::temp_dir().join("AuraNexus"));
+    dirs
+}
+
+/// Directory where models downloaded by the app are stored
+/// (`models.download_dir` in settings.toml, else `models` in the app data)
+pub fn models_dir() -> PathBuf {
+    crate::settings::AppSettings::load()
+        .models
+        .download_dir
+        .unwrap_or_else(|| app_data_dir().join("models"))
+}
@@ -0,0 +1,276 @@
+// Persona Stats Module - How well each persona's conversations go
+//
+// `get_persona_report` goes through every conversation and groups them by
+// the persona (mode) they use: average rating of its replies (1-5, set with
+// `rate_response`), how often a reply was regenerated, average reply length,
+// and how many conversations were abandoned - the user never wrote back
+// after the first reply. Regenerations leave no trace in the history, so
+// they are counted per persona in `persona_stats.json`. The active
+// conversation is still going, so it never counts as abandoned.
+
+use crate::modes::ModeRegistry;
+use crate::{clock, incognito, AppState, ConversationEntry};
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+const MIN_RATING: f32 = 1.0;
+const MAX_RATING: f32 = 5.0;
+
+/// Serializes read-modify-write of the counts file
+static LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+/// Regenerations per persona id
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegenerationCounts {
+    regenerations: HashMap<String, u64>,
+}
+
+impl RegenerationCounts {
+    const FILE: &'static str = "persona_stats.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    pub fn get(&self, persona: &str) -> u64 {
+        self.regenerations.get(persona).copied().unwrap_or(0)
+    }
+}
+
+/// Count a regenerated reply against `persona`
+pub fn record_regeneration(persona: &str) {
+    let _guard = LOCK.lock();
+    let mut counts = RegenerationCounts::load();
+    *counts.regenerations.entry(persona.to_string()).or_insert(0) += 1;
+    if let Err(e) = counts.save() {
+        warn!("Failed to record regeneration: {}", e);
+    }
+}
+
+/// One persona's numbers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PersonaStats {
+    /// Mode id, e.g. "companion" or a custom mode's id
+    pub persona: String,
+    pub name: String,
+    pub conversations: usize,
+    pub replies: usize,
+    pub rated_replies: usize,
+    pub average_rating: Option<f32>,
+    pub regenerations: u64,
+    /// Regenerations per reply
+    pub regeneration_rate: f32,
+    pub average_reply_words: f32,
+    pub abandoned_conversations: usize,
+    /// Abandoned share of the finished (non-active) conversations
+    pub abandonment_rate: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PersonaReport {
+    pub generated_at: String,
+    /// Busiest persona first
+    pub personas: Vec<PersonaStats>,
+}
+
+/// One conversation as the report sees it
+pub struct ConversationSample<'a> {
+    pub persona: &'a str,
+    pub entries: &'a [ConversationEntry],
+    pub active: bool,
+}
+
+fn ratio(part: f32, whole: usize) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part / whole as f32
+    }
+}
+
+/// Per-persona statistics over `conversations`
+pub fn aggregate<'a>(
+    conversations: impl IntoIterator<Item = ConversationSample<'a>>,
+    regenerations: &RegenerationCounts,
+    name_of: impl Fn(&str) -> String,
+) -> Vec<PersonaStats> {
+    struct Totals {
+        stats: PersonaStats,
+        rating_sum: f32,
+        words: usize,
+        finished: usize,
+    }
+    let mut totals: HashMap<String, Totals> = HashMap::new();
+    for conversation in conversations {
+        let totals = totals.entry(conversation.persona.to_string()).or_insert_with(|| Totals {
+            stats: PersonaStats {
+                persona: conversation.persona.to_string(),
+                ..Default::default()
+            },
+            rating_sum: 0.0,
+            words: 0,
+            finished: 0,
+        });
+        totals.stats.conversations += 1;
+        for reply in conversation.entries.iter().filter(|entry| entry.role == "assistant") {
+            totals.stats.replies += 1;
+            totals.words += reply.content.split_whitespace().count();
+            if let Some(rating) = reply.quality_score {
+                totals.stats.rated_replies += 1;
+                totals.rating_sum += rating;
+            }
+        }
+        if !conversation.active {
+            totals.finished += 1;
+            let user_messages = conversation.entries.iter().filter(|entry| entry.role == "user").count();
+            let replied = conversation.entries.iter().any(|entry| entry.role == "assistant");
+            if user_messages == 1 && replied {
+                totals.stats.abandoned_conversations += 1;
+            }
+        }
+    }
+
+    let mut personas: Vec<PersonaStats> = totals
+        .into_values()
+        .map(|totals| {
+            let mut stats = totals.stats;
+            stats.name = name_of(&stats.persona);
+            stats.average_rating =
+                (stats.rated_replies > 0).then(|| totals.rating_sum / stats.rated_replies as f32);
+            stats.regenerations = regenerations.get(&stats.persona);
+            stats.regeneration_rate = ratio(stats.regenerations as f32, stats.replies);
+            stats.average_reply_words = ratio(totals.words as f32, stats.replies);
+            stats.abandonment_rate = ratio(stats.abandoned_conversations as f32, totals.finished);
+            stats
+        })
+        .collect();
+    personas.sort_by(|a, b| b.replies.cmp(&a.replies).then_with(|| a.persona.cmp(&b.persona)));
+    personas
+}
+
+/// Display name of a persona id
+fn persona_name(persona: &str) -> String {
+    match persona {
+        "companion" => "Companion".to_string(),
+        "youniverse" => "Youniverse".to_string(),
+        id => ModeRegistry::load()
+            .get(id)
+            .map(|mode| mode.name.clone())
+            .unwrap_or_else(|| format!("{} (deleted)", id)),
+    }
+}
+
+/// Rating, regeneration, reply length and abandonment figures per persona
+#[tauri::command]
+pub async fn get_persona_report(state: tauri::State<'_, AppState>) -> Result<PersonaReport, String> {
+    let active_history = state.conversation_history.lock().clone();
+    let manager = state.conversations.lock();
+    let mut stored = Vec::new();
+    for info in manager.list() {
+        let entries = if info.active {
+            active_history.clone()
+        } else {
+            match manager.load(&info.meta.id) {
+                Ok(conversation) => conversation.entries,
+                Err(e) => {
+                    warn!("Skipping conversation {} in the report: {}", info.meta.id, e);
+                    continue;
+                }
+            }
+        };
+        stored.push((info.meta.mode, entries, info.active));
+    }
+    drop(manager);
+
+    let samples = stored.iter().map(|(persona, entries, active)| ConversationSample {
+        persona,
+        entries,
+        active: *active,
+    });
+    Ok(PersonaReport {
+        generated_at: clock::timestamp(),
+        personas: aggregate(samples, &RegenerationCounts::load(), persona_name),
+    })
+}
+
+/// Rate the reply at `index` of the active conversation from 1 to 5
+#[tauri::command]
+pub async fn rate_response(index: usize, rating: f32, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+        return Err(format!("Ratings are from {} to {}", MIN_RATING, MAX_RATING));
+    }
+    let history = {
+        let mut history = state.conversation_history.lock();
+        let entry = history.get_mut(index).ok_or_else(|| format!("No message at {}", index))?;
+        if entry.role != "assistant" {
+            return Err("Only replies can be rated".to_string());
+        }
+        entry.quality_score = Some(rating);
+        history.clone()
+    };
+    if !incognito::is_active(&state) {
+        state.history_store.save(&history).map_err(|e| e.to_string())?;
+    }
+    info!("Rated reply {} at {}", index, rating);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+
+    fn entry(role: &str, content: &str, rating: Option<f32>) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: clock::timestamp(),
+            quality_score: rating,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_per_persona() {
+        let long = vec![
+            entry("user", "Hi", None),
+            entry("assistant", "Hello there friend", Some(5.0)),
+            entry("user", "How are you?", None),
+            entry("assistant", "Fine", Some(3.0)),
+        ];
+        let dropped = vec![entry("user", "Hey", None), entry("assistant", "Hi, who are you", None)];
+        let samples = vec![
+            ConversationSample { persona: "companion", entries: &long, active: false },
+            ConversationSample { persona: "companion", entries: &dropped, active: false },
+            // Still going, so not abandoned
+            ConversationSample { persona: "pirate", entries: &dropped, active: true },
+        ];
+        let mut counts = RegenerationCounts::default();
+        counts.regenerations.insert("companion".to_string(), 3);
+
+        let report = aggregate(samples, &counts, |id| id.to_uppercase());
+        assert_eq!(report.len(), 2);
+        let companion = &report[0];
+        assert_eq!(companion.name, "COMPANION");
+        assert_eq!((companion.conversations, companion.replies), (2, 3));
+        assert_eq!(companion.average_rating, Some(4.0));
+        assert_eq!(companion.regeneration_rate, 1.0);
+        assert_eq!(companion.average_reply_words, 8.0 / 3.0);
+        assert_eq!(companion.abandoned_conversations, 1);
+        assert_eq!(companion.abandonment_rate, 0.5);
+
+        let pirate = &report[1];
+        assert_eq!(pirate.average_rating, None);
+        assert_eq!((pirate.abandoned_conversations, pirate.abandonment_rate), (0, 0.0));
+    }
+}
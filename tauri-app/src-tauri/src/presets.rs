@@ -0,0 +1,372 @@
+// Persona Preset Module - Shareable persona + sampling bundles
+//
+// A preset is a single JSON document that carries everything needed to
+// reproduce a persona on another install: the persona prompt, the sampling
+// configuration, and references to the lorebooks it expects. Presets are
+// versioned so the format can evolve without breaking older files.
+
+use crate::logit_bias;
+use crate::stop_sequences::{MAX_STOP_CHARS, MAX_STOP_SEQUENCES};
+use crate::{AppState, LlmConfig};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+use tracing::info;
+
+/// Magic string identifying an AuraNexus preset file
+pub const PRESET_FORMAT: &str = "auranexus-preset";
+
+/// Current preset format version
+pub const PRESET_FORMAT_VERSION: u32 = 1;
+
+/// Largest preset file we are willing to read (presets are small text files)
+const MAX_PRESET_FILE_BYTES: u64 = 1024 * 1024;
+
+const MAX_NAME_CHARS: usize = 64;
+const MAX_DESCRIPTION_CHARS: usize = 2_000;
+const MAX_PROMPT_CHARS: usize = 16_000;
+const MAX_LOREBOOKS: usize = 32;
+
+/// Errors produced while validating or loading a preset
+#[derive(Debug, Error)]
+pub enum PresetError {
+    #[error("Not an AuraNexus preset (format: {0:?})")]
+    UnknownFormat(String),
+    #[error("Preset format version {0} is newer than this version of AuraNexus supports")]
+    UnsupportedVersion(u32),
+    #[error("Invalid preset field `{field}`: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+    #[error("Preset file is too large ({0} bytes)")]
+    TooLarge(u64),
+    #[error("Failed to read preset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse preset: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Persona definition carried by a preset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaSpec {
+    /// Display name of the persona (e.g., "Aura")
+    pub name: String,
+    /// System prompt that defines the persona
+    pub system_prompt: String,
+    /// Optional opening message shown when a conversation starts
+    #[serde(default)]
+    pub greeting: Option<String>,
+    /// Built-in mode the persona was designed for ("companion" / "youniverse")
+    #[serde(default)]
+    pub base_mode: Option<String>,
+}
+
+/// Reference to a lorebook the preset expects to be available
+///
+/// Lorebooks are not embedded; the reference names a file relative to the
+/// user's lorebook directory so importing a preset never touches other paths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LorebookRef {
+    pub name: String,
+    /// Relative file name inside the lorebook directory
+    pub file: String,
+    /// Optional SHA-256 of the lorebook file for integrity checks
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Shareable sampling/persona preset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaPreset {
+    pub format: String,
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub persona: PersonaSpec,
+    pub sampling: LlmConfig,
+    #[serde(default)]
+    pub lorebooks: Vec<LorebookRef>,
+}
+
+impl PersonaPreset {
+    /// Create a preset in the current format version
+    pub fn new(name: impl Into<String>, persona: PersonaSpec, sampling: LlmConfig) -> Self {
+        Self {
+            format: PRESET_FORMAT.to_string(),
+            format_version: PRESET_FORMAT_VERSION,
+            name: name.into(),
+            description: String::new(),
+            author: None,
+            persona,
+            sampling,
+            lorebooks: Vec::new(),
+        }
+    }
+
+    /// Parse and validate a preset from JSON text
+    pub fn from_json(json: &str) -> Result<Self, PresetError> {
+        let preset: PersonaPreset = serde_json::from_str(json)?;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    /// Serialize the preset as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, PresetError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Load a preset from disk, rejecting oversized or invalid files
+    pub fn load(path: &Path) -> Result<Self, PresetError> {
+        let size = std::fs::metadata(path)?.len();
+        if size > MAX_PRESET_FILE_BYTES {
+            return Err(PresetError::TooLarge(size));
+        }
+
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// Validate and write the preset to disk
+    pub fn save(&self, path: &Path) -> Result<(), PresetError> {
+        self.validate()?;
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Check that the preset is well-formed and its values are in safe ranges
+    pub fn validate(&self) -> Result<(), PresetError> {
+        if self.format != PRESET_FORMAT {
+            return Err(PresetError::UnknownFormat(self.format.clone()));
+        }
+        if self.format_version == 0 {
+            return Err(invalid("format_version", "must be at least 1"));
+        }
+        if self.format_version > PRESET_FORMAT_VERSION {
+            return Err(PresetError::UnsupportedVersion(self.format_version));
+        }
+
+        check_text("name", &self.name, 1, MAX_NAME_CHARS)?;
+        check_text("description", &self.description, 0, MAX_DESCRIPTION_CHARS)?;
+        check_text("persona.name", &self.persona.name, 1, MAX_NAME_CHARS)?;
+        check_text("persona.system_prompt", &self.persona.system_prompt, 1, MAX_PROMPT_CHARS)?;
+        if let Some(greeting) = &self.persona.greeting {
+            check_text("persona.greeting", greeting, 0, MAX_PROMPT_CHARS)?;
+        }
+
+        validate_sampling(&self.sampling)?;
+
+        if self.lorebooks.len() > MAX_LOREBOOKS {
+            return Err(invalid("lorebooks", format!("at most {} references allowed", MAX_LOREBOOKS)));
+        }
+        for lorebook in &self.lorebooks {
+            check_text("lorebooks.name", &lorebook.name, 1, MAX_NAME_CHARS)?;
+            validate_lorebook_file(&lorebook.file)?;
+            if let Some(hash) = &lorebook.sha256 {
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(invalid("lorebooks.sha256", "must be a 64-character hex digest"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that sampling parameters are within the ranges the backends accept
+pub fn validate_sampling(config: &LlmConfig) -> Result<(), PresetError> {
+    check_range("sampling.temperature", config.temperature, 0.0, 2.0)?;
+    check_range("sampling.top_p", config.top_p, 0.0, 1.0)?;
+    if !(0..=1000).contains(&config.top_k) {
+        return Err(invalid("sampling.top_k", "must be between 0 and 1000"));
+    }
+    if !(1..=32768).contains(&config.max_tokens) {
+        return Err(invalid("sampling.max_tokens", "must be between 1 and 32768"));
+    }
+    if config.stop.len() > MAX_STOP_SEQUENCES {
+        return Err(invalid("sampling.stop", format!("at most {} stop sequences", MAX_STOP_SEQUENCES)));
+    }
+    if config.stop.iter().any(|stop| stop.is_empty() || stop.chars().count() > MAX_STOP_CHARS) {
+        return Err(invalid("sampling.stop", format!("stop sequences are 1 to {} characters", MAX_STOP_CHARS)));
+    }
+    logit_bias::validate(&config.logit_bias).map_err(|e| invalid("sampling.logit_bias", e.to_string()))?;
+
+    let optional = [
+        ("sampling.min_p", config.min_p, 0.0, 1.0),
+        ("sampling.frequency_penalty", config.frequency_penalty, -2.0, 2.0),
+        ("sampling.presence_penalty", config.presence_penalty, -2.0, 2.0),
+        ("sampling.dry_multiplier", config.dry_multiplier, 0.0, 5.0),
+        ("sampling.xtc_probability", config.xtc_probability, 0.0, 1.0),
+        ("sampling.dynatemp_range", config.dynatemp_range, 0.0, 2.0),
+    ];
+    for (field, value, min, max) in optional {
+        if let Some(value) = value {
+            check_range(field, value, min, max)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> PresetError {
+    PresetError::InvalidField {
+        field,
+        reason: reason.into(),
+    }
+}
+
+fn check_text(field: &'static str, value: &str, min: usize, max: usize) -> Result<(), PresetError> {
+    let chars = value.trim().chars().count();
+    if chars < min {
+        return Err(invalid(field, "must not be empty"));
+    }
+    if chars > max {
+        return Err(invalid(field, format!("must be at most {} characters", max)));
+    }
+    Ok(())
+}
+
+fn check_range(field: &'static str, value: f32, min: f32, max: f32) -> Result<(), PresetError> {
+    if !value.is_finite() || value < min || value > max {
+        return Err(invalid(field, format!("must be between {} and {}", min, max)));
+    }
+    Ok(())
+}
+
+/// Lorebook references must stay inside the lorebook directory
+fn validate_lorebook_file(file: &str) -> Result<(), PresetError> {
+    let path = Path::new(file);
+    let escapes = path.is_absolute()
+        || file.starts_with('/')
+        || file.starts_with('\\')
+        || file.contains(':')
+        || path
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)));
+
+    if file.is_empty() || escapes {
+        return Err(invalid("lorebooks.file", format!("must be a relative file name, got {:?}", file)));
+    }
+    Ok(())
+}
+
+/// Export the current mode's persona and sampling config as a preset file
+#[tauri::command]
+pub async fn export_preset(
+    path: String,
+    name: String,
+    description: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PersonaPreset, String> {
+    let mode = state.current_mode.lock().clone();
+
+    let persona = PersonaSpec {
+        name: "Aura".to_string(),
+        system_prompt: mode.system_prompt(),
+        greeting: None,
+        base_mode: Some(mode.to_string()),
+    };
+    let mut preset = PersonaPreset::new(name, persona, mode.sampling_config());
+    preset.description = description.unwrap_or_default();
+
+    preset.save(Path::new(&path)).map_err(|e| e.to_string())?;
+
+    info!("Exported preset '{}' to {}", preset.name, path);
+    Ok(preset)
+}
+
+/// Load and validate a preset file shared by another user
+#[tauri::command]
+pub async fn import_preset(path: String) -> Result<PersonaPreset, String> {
+    let preset = PersonaPreset::load(Path::new(&path)).map_err(|e| e.to_string())?;
+
+    info!("Imported preset '{}' (format v{})", preset.name, preset.format_version);
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_preset() -> PersonaPreset {
+        PersonaPreset::new(
+            "Noir Detective",
+            PersonaSpec {
+                name: "Sam".to_string(),
+                system_prompt: "You are a hard-boiled detective narrating a case.".to_string(),
+                greeting: Some("The rain hadn't stopped for three days.".to_string()),
+                base_mode: Some("youniverse".to_string()),
+            },
+            LlmConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut preset = sample_preset();
+        preset.lorebooks.push(LorebookRef {
+            name: "City".to_string(),
+            file: "city_lore.json".to_string(),
+            sha256: None,
+        });
+
+        let json = preset.to_json().unwrap();
+        let loaded = PersonaPreset::from_json(&json).unwrap();
+
+        assert_eq!(loaded.name, "Noir Detective");
+        assert_eq!(loaded.persona.name, "Sam");
+        assert_eq!(loaded.lorebooks.len(), 1);
+        assert_eq!(loaded.sampling.top_k, preset.sampling.top_k);
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let mut preset = sample_preset();
+        preset.format_version = PRESET_FORMAT_VERSION + 1;
+
+        let json = serde_json::to_string(&preset).unwrap();
+        assert!(matches!(
+            PersonaPreset::from_json(&json),
+            Err(PresetError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_sampling() {
+        let mut preset = sample_preset();
+        preset.sampling.temperature = 9.0;
+        assert!(preset.validate().is_err());
+
+        let mut preset = sample_preset();
+        preset.sampling.min_p = Some(-0.5);
+        assert!(preset.validate().is_err());
+    }
+
+    #[test]
+    fn test_rejects_lorebook_path_traversal() {
+        for file in ["../secrets.json", "/etc/passwd", "C:\\lore.json", "lore/../../x.json"] {
+            let mut preset = sample_preset();
+            preset.lorebooks.push(LorebookRef {
+                name: "Bad".to_string(),
+                file: file.to_string(),
+                sha256: None,
+            });
+            assert!(preset.validate().is_err(), "should reject {}", file);
+        }
+    }
+
+    #[test]
+    fn test_missing_sampling_fields_use_defaults() {
+        let json = r#"{
+            "format": "auranexus-preset",
+            "format_version": 1,
+            "name": "Minimal",
+            "persona": { "name": "Aura", "system_prompt": "Be kind." },
+            "sampling": { "temperature": 0.5 }
+        }"#;
+
+        let preset = PersonaPreset::from_json(json).unwrap();
+        assert_eq!(preset.sampling.temperature, 0.5);
+        assert_eq!(preset.sampling.max_tokens, LlmConfig::default().max_tokens);
+    }
+}
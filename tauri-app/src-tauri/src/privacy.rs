@@ -0,0 +1,68 @@
+// Privacy Module - Conversations kept out of memory and the Nexus Core log
+//
+// Privacy mode is lighter than incognito: the conversation is still saved
+// and can be reopened, but its turns never reach the memory store (so no
+// memory extraction, compaction summaries or stored web results), the entity
+// index or the Nexus Core conversation log. It is set per conversation
+// (`ConversationMeta::private`, carried into branches) or for everything with
+// `privacy.private_mode` in settings.toml. Incognito sessions count as
+// private too. Each `ChatResponse` says whether its turn was private.
+
+use crate::settings::{self, AppSettings};
+use crate::{incognito, AppState};
+use serde::Serialize;
+use tauri::Manager;
+use tracing::info;
+
+/// Whether turns in the current conversation stay out of memory
+pub fn is_private(state: &AppState) -> bool {
+    incognito::is_active(state) || AppSettings::load().privacy.private_mode || state.conversations.lock().active_private()
+}
+
+/// Payload of `privacy-changed` events
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivacyMode {
+    /// The active conversation's own toggle
+    pub conversation: bool,
+    /// `privacy.private_mode` in settings.toml
+    pub global: bool,
+    /// Whether the next turn is private, whichever of the above (or
+    /// incognito) makes it so
+    pub effective: bool,
+}
+
+fn current(state: &AppState) -> PrivacyMode {
+    PrivacyMode {
+        conversation: state.conversations.lock().active_private(),
+        global: AppSettings::load().privacy.private_mode,
+        effective: is_private(state),
+    }
+}
+
+#[tauri::command]
+pub async fn get_privacy_mode(state: tauri::State<'_, AppState>) -> Result<PrivacyMode, String> {
+    Ok(current(&state))
+}
+
+/// Turn privacy mode on or off for the active conversation, or with
+/// `global` for every conversation; emits `privacy-changed`
+#[tauri::command]
+pub async fn set_privacy_mode(
+    enabled: bool,
+    global: Option<bool>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<PrivacyMode, String> {
+    if global.unwrap_or(false) {
+        settings::update(|settings| settings.privacy.private_mode = enabled).map_err(|e| e.to_string())?;
+        info!("Global privacy mode {}", if enabled { "on" } else { "off" });
+    } else {
+        let mut manager = state.conversations.lock();
+        let active = manager.active_id().to_string();
+        manager.set_private(&active, enabled).map_err(|e| e.to_string())?;
+        info!("Privacy mode {} for conversation {}", if enabled { "on" } else { "off" }, active);
+    }
+    let mode = current(&state);
+    let _ = app.emit_all("privacy-changed", &mode);
+    Ok(mode)
+}
@@ -0,0 +1,124 @@
+// Prompt Budget Module - History trimmed by token count
+//
+// Every backend gets the conversation history that fits in the context
+// window alongside the system prompt, the new message and room for the
+// reply (`max_tokens`), counted with the chat model's tokenizer (estimated
+// until it has loaded). The oldest turns go first.
+
+use crate::context_window::{self, TURN_OVERHEAD};
+use crate::tokenizer::count_tokens;
+use crate::{ConversationEntry, LlmConfig};
+use tracing::info;
+
+/// Context window used until one is set in the inference settings
+pub const DEFAULT_CONTEXT_TOKENS: usize = 4096;
+
+/// Token budget for one prompt
+#[derive(Debug, Clone, Copy)]
+pub struct PromptBudget {
+    pub context_tokens: usize,
+    /// Kept free for the reply
+    pub reserved_for_reply: usize,
+}
+
+impl PromptBudget {
+    pub fn new(context_tokens: usize, config: &LlmConfig) -> Self {
+        Self {
+            context_tokens,
+            reserved_for_reply: config.max_tokens.max(0) as usize,
+        }
+    }
+
+    /// Tokens left for the system prompt, new message and history
+    pub fn prompt_tokens(&self) -> usize {
+        self.context_tokens.saturating_sub(self.reserved_for_reply)
+    }
+
+    /// The most recent part of `history` that fits next to `system_prompt`
+    /// and `message`
+    pub fn fit_history<'a>(
+        &self,
+        history: &'a [ConversationEntry],
+        system_prompt: &str,
+        message: &str,
+    ) -> &'a [ConversationEntry] {
+        self.fit_history_with(history, system_prompt, message, count_tokens)
+    }
+
+    fn fit_history_with<'a>(
+        &self,
+        history: &'a [ConversationEntry],
+        system_prompt: &str,
+        message: &str,
+        count: impl Fn(&str) -> usize,
+    ) -> &'a [ConversationEntry] {
+        let fixed = count(system_prompt) + count(message) + 2 * TURN_OVERHEAD;
+        let turn_tokens: Vec<usize> = history
+            .iter()
+            .map(|entry| count(&entry.content) + TURN_OVERHEAD)
+            .collect();
+        let start = context_window::first_kept_turn(fixed, &turn_tokens, self.prompt_tokens());
+        if start > 0 {
+            info!(
+                "Keeping the last {} of {} history entries ({}-token budget)",
+                history.len() - start,
+                history.len(),
+                self.prompt_tokens()
+            );
+        }
+        &history[start..]
+    }
+}
+
+/// Prompt tokens for `system_prompt`, `history` and `message` as one chat,
+/// turn markup included
+pub fn request_tokens(system_prompt: &str, history: &[ConversationEntry], message: &str) -> usize {
+    count_tokens(system_prompt)
+        + count_tokens(message)
+        + 2 * TURN_OVERHEAD
+        + history
+            .iter()
+            .map(|entry| count_tokens(&entry.content) + TURN_OVERHEAD)
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+
+    fn entry(content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_history_trimmed_by_tokens_not_count() {
+        let config = LlmConfig {
+            max_tokens: 100,
+            ..Default::default()
+        };
+        let budget = PromptBudget::new(400, &config);
+        let words = |text: &str| text.split_whitespace().count();
+
+        // One long old message, many short recent ones
+        let long = ["word"; 200].join(" ");
+        let mut history = vec![entry(&long)];
+        history.extend((0..30).map(|i| entry(&format!("short {}", i))));
+
+        // 300 prompt tokens: 19 fixed (system + message + overhead), 10 per short entry
+        let kept = budget.fit_history_with(&history, "Be kind.", "Hi", words);
+        assert_eq!(kept.len(), 28);
+        assert_eq!(kept.last().unwrap().content, "short 29");
+
+        // A short conversation is sent whole
+        assert_eq!(budget.fit_history_with(&history[25..], "Be kind.", "Hi", words).len(), 6);
+    }
+}
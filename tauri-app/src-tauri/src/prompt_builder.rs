@@ -0,0 +1,279 @@
+// Prompt Builder Module - Chat-template formatting for the native backend
+//
+// Instruct models follow instructions best in the format they were trained
+// on. The family is recognised from the GGUF's `tokenizer.chat_template`
+// (a Jinja template; its marker tokens give it away) unless
+// `chat_templates.json` overrides it for that model file. Unrecognised
+// templates fall back to ChatML, which most recent fine-tunes understand.
+//
+// The BOS token is never written here; tokenization adds it.
+
+use crate::backend::GenerationRequest;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+/// Prompt formats the native backend can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTemplate {
+    /// `<|im_start|>role ... <|im_end|>` (Qwen, Hermes, many fine-tunes)
+    ChatMl,
+    /// `<|start_header_id|>role<|end_header_id|>` (Llama 3.x)
+    Llama3,
+    /// `[INST] <<SYS>> ... [/INST]` (Llama 2 chat)
+    Llama2,
+    /// `[INST] ... [/INST]` without a system block (Mistral, Mixtral)
+    Mistral,
+    /// `<start_of_turn>user|model` (Gemma; no system role)
+    Gemma,
+    /// `<|user|> ... <|end|>` (Phi-3)
+    Phi3,
+}
+
+/// One message in the order it goes into the prompt
+struct Turn<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+impl ChatTemplate {
+    /// Recognise a template family from a GGUF Jinja chat template
+    pub fn detect(jinja: &str) -> Option<Self> {
+        if jinja.contains("<|im_start|>") {
+            Some(Self::ChatMl)
+        } else if jinja.contains("<|start_header_id|>") {
+            Some(Self::Llama3)
+        } else if jinja.contains("<start_of_turn>") {
+            Some(Self::Gemma)
+        } else if jinja.contains("<|assistant|>") && jinja.contains("<|end|>") {
+            Some(Self::Phi3)
+        } else if jinja.contains("[INST]") && jinja.contains("<<SYS>>") {
+            Some(Self::Llama2)
+        } else if jinja.contains("[INST]") {
+            Some(Self::Mistral)
+        } else {
+            None
+        }
+    }
+
+    /// Format the system prompt, history and new message, ending where the
+    /// assistant's reply should start
+    pub fn render(self, request: &GenerationRequest) -> String {
+        let mut turns: Vec<Turn> = request
+            .history
+            .iter()
+            .map(|entry| Turn {
+                role: if entry.role == "assistant" { "assistant" } else { "user" },
+                content: &entry.content,
+            })
+            .collect();
+        turns.push(Turn {
+            role: "user",
+            content: request.prompt,
+        });
+        let system = request.system_prompt.trim();
+
+        match self {
+            Self::ChatMl => render_chatml(system, &turns),
+            Self::Llama3 => render_llama3(system, &turns),
+            Self::Llama2 | Self::Mistral => render_inst(self, system, &turns),
+            Self::Gemma => render_gemma(system, &turns),
+            Self::Phi3 => render_phi3(system, &turns),
+        }
+    }
+}
+
+fn render_chatml(system: &str, turns: &[Turn]) -> String {
+    let mut prompt = format!("<|im_start|>system\n{}<|im_end|>\n", system);
+    for turn in turns {
+        prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", turn.role, turn.content));
+    }
+    prompt.push_str("<|im_start|>assistant\n");
+    prompt
+}
+
+fn render_llama3(system: &str, turns: &[Turn]) -> String {
+    let mut prompt = String::new();
+    let system = (!system.is_empty()).then_some(Turn {
+        role: "system",
+        content: system,
+    });
+    for turn in system.iter().chain(turns) {
+        prompt.push_str(&format!(
+            "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+            turn.role, turn.content
+        ));
+    }
+    prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+    prompt
+}
+
+/// Llama 2 and Mistral: user turns wrapped in `[INST]`, the system prompt
+/// folded into the first one
+fn render_inst(template: ChatTemplate, system: &str, turns: &[Turn]) -> String {
+    let mut prompt = String::new();
+    let mut system = (!system.is_empty()).then_some(system);
+    for turn in turns {
+        if turn.role == "assistant" {
+            prompt.push_str(&format!(" {}</s>", turn.content));
+            continue;
+        }
+        let content = match system.take() {
+            Some(system) if template == ChatTemplate::Llama2 => {
+                format!("<<SYS>>\n{}\n<</SYS>>\n\n{}", system, turn.content)
+            }
+            Some(system) => format!("{}\n\n{}", system, turn.content),
+            None => turn.content.to_string(),
+        };
+        // Every turn after the first starts a new sequence
+        if !prompt.is_empty() {
+            prompt.push_str("<s>");
+        }
+        prompt.push_str(&format!("[INST] {} [/INST]", content));
+    }
+    prompt
+}
+
+/// Gemma has no system role; the system prompt leads the first user turn
+fn render_gemma(system: &str, turns: &[Turn]) -> String {
+    let mut prompt = String::new();
+    let mut system = (!system.is_empty()).then_some(system);
+    for turn in turns {
+        let (role, content) = if turn.role == "assistant" {
+            ("model", turn.content.to_string())
+        } else {
+            match system.take() {
+                Some(system) => ("user", format!("{}\n\n{}", system, turn.content)),
+                None => ("user", turn.content.to_string()),
+            }
+        };
+        prompt.push_str(&format!("<start_of_turn>{}\n{}<end_of_turn>\n", role, content));
+    }
+    prompt.push_str("<start_of_turn>model\n");
+    prompt
+}
+
+fn render_phi3(system: &str, turns: &[Turn]) -> String {
+    let mut prompt = String::new();
+    if !system.is_empty() {
+        prompt.push_str(&format!("<|system|>\n{}<|end|>\n", system));
+    }
+    for turn in turns {
+        prompt.push_str(&format!("<|{}|>\n{}<|end|>\n", turn.role, turn.content));
+    }
+    prompt.push_str("<|assistant|>\n");
+    prompt
+}
+
+/// Per-model template choices, keyed by model file name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatTemplateOverrides {
+    pub models: HashMap<String, ChatTemplate>,
+}
+
+impl ChatTemplateOverrides {
+    const FILE: &'static str = "chat_templates.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+}
+
+/// Template for `model` (file name): the override, else the one its GGUF
+/// metadata describes, else ChatML
+pub fn resolve(model: &str, metadata_template: Option<&str>) -> ChatTemplate {
+    ChatTemplateOverrides::load()
+        .models
+        .get(model)
+        .copied()
+        .or_else(|| metadata_template.and_then(ChatTemplate::detect))
+        .unwrap_or(ChatTemplate::ChatMl)
+}
+
+/// Force a chat template for a model file, or clear the override with `None`
+#[tauri::command]
+pub async fn set_chat_template(model: String, template: Option<ChatTemplate>) -> Result<(), String> {
+    let mut overrides = ChatTemplateOverrides::load();
+    match template {
+        Some(template) => {
+            info!("Using {:?} chat template for {}", template, model);
+            overrides.models.insert(model, template);
+        }
+        None => {
+            overrides.models.remove(&model);
+        }
+    }
+    overrides.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConversationEntry, EntryStatus, LlmConfig};
+
+    fn entry(role: &str, content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_detect_template_family() {
+        let llama3 = "{% set content = '<|start_header_id|>' + message['role'] + '<|end_header_id|>\n\n' %}";
+        assert_eq!(ChatTemplate::detect(llama3), Some(ChatTemplate::Llama3));
+        assert_eq!(
+            ChatTemplate::detect("{{ '<|im_start|>' + message['role'] }}"),
+            Some(ChatTemplate::ChatMl)
+        );
+        assert_eq!(
+            ChatTemplate::detect("{{ '<start_of_turn>' + role }}"),
+            Some(ChatTemplate::Gemma)
+        );
+        assert_eq!(
+            ChatTemplate::detect("{{ '[INST] ' + message['content'] + ' [/INST]' }}"),
+            Some(ChatTemplate::Mistral)
+        );
+        assert_eq!(ChatTemplate::detect("{{ messages }}"), None);
+    }
+
+    #[test]
+    fn test_render_native_formats() {
+        let history = vec![entry("user", "Hi"), entry("assistant", "Hello!")];
+        let config = LlmConfig::default();
+        let request = GenerationRequest {
+            prompt: "How are you?",
+            system_prompt: "Be kind.",
+            history: &history,
+            config: &config,
+        };
+
+        let llama3 = ChatTemplate::Llama3.render(&request);
+        assert!(llama3.starts_with("<|start_header_id|>system<|end_header_id|>\n\nBe kind.<|eot_id|>"));
+        assert!(llama3.contains("<|start_header_id|>assistant<|end_header_id|>\n\nHello!<|eot_id|>"));
+        assert!(llama3.ends_with("How are you?<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n"));
+
+        let gemma = ChatTemplate::Gemma.render(&request);
+        assert!(gemma.starts_with("<start_of_turn>user\nBe kind.\n\nHi<end_of_turn>\n"));
+        assert!(gemma.contains("<start_of_turn>model\nHello!<end_of_turn>\n"));
+        assert!(gemma.ends_with("<start_of_turn>model\n"));
+
+        let llama2 = ChatTemplate::Llama2.render(&request);
+        assert_eq!(
+            llama2,
+            "[INST] <<SYS>>\nBe kind.\n<</SYS>>\n\nHi [/INST] Hello!</s><s>[INST] How are you? [/INST]"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+// Prompt Trace Module - What went into the last prompt, and why
+//
+// Before each reply the most relevant memories and document chunks (above
+// `MIN_SCORE`, within the persona's access scope, found by the message or
+// one of its reworded variants) are added to the system prompt. The
+// assembled prompt is recorded block by block - system prompt, running
+// summary, retrieved memories and chunks with their similarity scores,
+// history turns (sent or trimmed) and the message - with token counts, so
+// the UI can show a heatmap of where the context went and why an irrelevant
+// chunk made it in.
+
+use crate::memory_policy::current_scope;
+use crate::memory_store::{MemoryFilters, MemoryItem};
+use crate::query_fanout;
+use crate::tokenizer::count_tokens;
+use crate::{AppState, ConversationEntry};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// Retrieved items added to a prompt at most
+const RETRIEVAL_LIMIT: usize = 4;
+
+/// Similarity a memory needs to be added to the prompt
+const MIN_SCORE: f32 = 0.35;
+
+/// Characters of each block shown in the trace
+const PREVIEW_CHARS: usize = 120;
+
+/// The last prompt sent
+static LAST: Mutex<Option<PromptTrace>> = parking_lot::const_mutex(None);
+
+/// Where a block of the prompt came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    SystemPrompt,
+    Summary,
+    Memory,
+    DocumentChunk,
+    HistoryTurn,
+    Message,
+}
+
+/// One block of a prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptBlock {
+    pub kind: BlockKind,
+    /// Memory id, or role and timestamp of a history turn
+    pub source: String,
+    pub preview: String,
+    pub tokens: usize,
+    /// Similarity to the message, for retrieved blocks
+    pub score: Option<f32>,
+    /// False for history trimmed to fit the budget
+    pub included: bool,
+}
+
+impl PromptBlock {
+    fn new(kind: BlockKind, source: impl Into<String>, text: &str) -> Self {
+        Self {
+            kind,
+            source: source.into(),
+            preview: text.chars().take(PREVIEW_CHARS).collect(),
+            tokens: count_tokens(text),
+            score: None,
+            included: true,
+        }
+    }
+}
+
+/// The blocks of one prompt, in prompt order
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTrace {
+    /// The reply the prompt was for (set when recorded)
+    pub generation_id: String,
+    pub created_at: String,
+    /// Tokens across the included blocks
+    pub total_tokens: usize,
+    pub blocks: Vec<PromptBlock>,
+}
+
+/// A memory retrieved for the prompt
+#[derive(Debug, Clone)]
+pub struct Retrieved {
+    pub memory: MemoryItem,
+    pub score: f32,
+}
+
+impl Retrieved {
+    fn kind(&self) -> BlockKind {
+        match self.memory.metadata.get("kind").and_then(|v| v.as_str()) {
+            Some("document_chunk") => BlockKind::DocumentChunk,
+            _ => BlockKind::Memory,
+        }
+    }
+}
+
+/// Memories and document chunks relevant to the message, best first
+///
+/// `queries` are the message and its reworded variants (see
+/// `query_fanout`); their results are fused. Conversation messages are left
+/// out; recent ones are already in the history.
+pub fn retrieve(state: &AppState, queries: &[String]) -> Vec<Retrieved> {
+    let filters = MemoryFilters {
+        user_id: Some(state.session.lock().user_id.clone()),
+        access: Some(current_scope(state)),
+        ..Default::default()
+    };
+    // Embedding is the slow part; do it outside the store lock
+    let embedder = state.memory_store.lock().embedder();
+    let vectors = query_fanout::embed_all(embedder.as_deref(), queries);
+    let lists = {
+        let store = state.memory_store.lock();
+        queries
+            .iter()
+            .zip(&vectors)
+            .map(|(query, vector)| store.search_vector(query, vector, Some(&filters), RETRIEVAL_LIMIT * 4))
+            .collect()
+    };
+    query_fanout::fuse(lists)
+        .into_iter()
+        .filter(|(memory, score)| {
+            *score >= MIN_SCORE
+                && memory.metadata.get("kind").and_then(|v| v.as_str()) != Some("message")
+        })
+        .take(RETRIEVAL_LIMIT)
+        .map(|(memory, score)| Retrieved { memory, score })
+        .collect()
+}
+
+/// `system_prompt` with the retrieved items appended
+pub fn with_retrieved(system_prompt: &str, retrieved: &[Retrieved]) -> String {
+    if retrieved.is_empty() {
+        return system_prompt.to_string();
+    }
+    let items = retrieved
+        .iter()
+        .map(|item| format!("- {}", item.memory.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\nRelevant things you remember:\n{}", system_prompt, items)
+}
+
+/// Everything that went into one prompt
+pub struct PromptParts<'a> {
+    pub system_prompt: &'a str,
+    pub summary: Option<&'a str>,
+    pub retrieved: &'a [Retrieved],
+    /// History considered for the prompt
+    pub history: &'a [ConversationEntry],
+    /// How many of the most recent `history` entries were sent
+    pub history_sent: usize,
+    pub message: &'a str,
+}
+
+impl PromptTrace {
+    pub fn build(parts: &PromptParts) -> Self {
+        let mut blocks = vec![PromptBlock::new(BlockKind::SystemPrompt, "system", parts.system_prompt)];
+        if let Some(summary) = parts.summary {
+            blocks.push(PromptBlock::new(BlockKind::Summary, "rolling_summary", summary));
+        }
+        blocks.extend(parts.retrieved.iter().map(|item| PromptBlock {
+            score: Some(item.score),
+            ..PromptBlock::new(item.kind(), item.memory.id.clone(), &item.memory.content)
+        }));
+
+        let first_sent = parts.history.len().saturating_sub(parts.history_sent);
+        blocks.extend(parts.history.iter().enumerate().map(|(i, entry)| PromptBlock {
+            included: i >= first_sent,
+            ..PromptBlock::new(
+                BlockKind::HistoryTurn,
+                format!("{} {}", entry.role, entry.timestamp),
+                &entry.content,
+            )
+        }));
+        blocks.push(PromptBlock::new(BlockKind::Message, "user", parts.message));
+
+        Self {
+            generation_id: String::new(),
+            created_at: crate::clock::timestamp(),
+            total_tokens: blocks.iter().filter(|b| b.included).map(|b| b.tokens).sum(),
+            blocks,
+        }
+    }
+}
+
+/// Keep `trace` as the prompt of reply `generation_id`, the last one sent
+pub fn record(generation_id: &str, mut trace: PromptTrace) {
+    trace.generation_id = generation_id.to_string();
+    *LAST.lock() = Some(trace);
+}
+
+/// Blocks of the last prompt with their token counts and retrieval scores
+#[tauri::command]
+pub async fn get_prompt_trace() -> Result<Option<PromptTrace>, String> {
+    Ok(LAST.lock().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    fn memory(content: &str, kind: &str) -> MemoryItem {
+        MemoryItem {
+            id: format!("mem-{}", kind),
+            content: content.to_string(),
+            user_id: None,
+            agent_id: None,
+            run_id: None,
+            metadata: HashMap::from([("kind".to_string(), serde_json::json!(kind))]),
+            embedding: None,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+            expires_at: None,
+        }
+    }
+
+    fn entry(content: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_trace_marks_sources_and_trimmed_history() {
+        let retrieved = vec![
+            Retrieved { memory: memory("Allergic to peanuts", "fact"), score: 0.8 },
+            Retrieved { memory: memory("Chapter 3 of the manual", "document_chunk"), score: 0.4 },
+        ];
+        let history = vec![entry("old turn"), entry("recent turn")];
+        let trace = PromptTrace::build(&PromptParts {
+            system_prompt: "Be kind.",
+            summary: None,
+            retrieved: &retrieved,
+            history: &history,
+            history_sent: 1,
+            message: "What should I cook?",
+        });
+
+        let kinds: Vec<BlockKind> = trace.blocks.iter().map(|b| b.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                BlockKind::SystemPrompt,
+                BlockKind::Memory,
+                BlockKind::DocumentChunk,
+                BlockKind::HistoryTurn,
+                BlockKind::HistoryTurn,
+                BlockKind::Message,
+            ]
+        );
+        assert_eq!(trace.blocks[2].score, Some(0.4));
+        assert!(!trace.blocks[3].included);
+        assert!(trace.blocks[4].included);
+        let dropped = trace.blocks[3].tokens;
+        let all: usize = trace.blocks.iter().map(|b| b.tokens).sum();
+        assert_eq!(trace.total_tokens, all - dropped);
+
+        let prompt = with_retrieved("Be kind.", &retrieved);
+        assert!(prompt.ends_with("- Allergic to peanuts\n- Chapter 3 of the manual"));
+    }
+}
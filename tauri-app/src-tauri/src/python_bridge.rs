@@ -1,55 +1,349 @@
-use anyhow::{Context, Result};
-use pyo3::prelude::*;
-use pyo3::types::PyDict;
+// Python Bridge Module - The Python backend in a supervised child process
+//
+// The backend runs in its own Python process (`python/bridge_worker.py`,
+// compiled in) that speaks JSON-RPC over stdin/stdout, so a crash or a hung
+// call in Python can't take the app down or block the async runtime's GIL.
+// Requests go one at a time; generation streams its tokens back as
+// notifications and stops when cancelled. Before a call the worker is
+// pinged if it has been quiet for a while; a worker that died or stopped
+// answering is restarted, and the model it had loaded is loaded again. Calls
+// are held to the limits in `backend_timeouts.json` (first token, gap between
+// tokens, total); a worker that runs past one is killed and restarted like a
+// crashed one. After MAX_RESTARTS restarts in a row without a successful call
+// the bridge gives up and reports `failed`. Dropping the bridge asks the worker to shut down
+// and kills it if it doesn't; when the app exits, the worker sees stdin close
+// and exits on its own.
+
+use crate::bridge_manifest::{BridgeManifest, Operation, MANIFEST_FILE};
+use crate::downloader::{self, DownloadProgress};
+use crate::generation::CancellationToken;
+use crate::http_backend::{self, BackendError, BackendTimeouts};
+use crate::logit_bias;
+use crate::python_env;
+use crate::setup_wizard::{self, starter_models};
+use crate::{paths, ConversationEntry, EntryStatus, LlmConfig};
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
-use std::sync::Arc;
-use parking_lot::Mutex;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// The worker script, run with `python -u -c`
+const WORKER_SCRIPT: &str = include_str!("../python/bridge_worker.py");
+
+/// Overrides the Python interpreter found on the PATH
+const PYTHON_ENV_VAR: &str = "AURANEXUS_PYTHON";
+
+/// Importing the backend (torch, llama_cpp...) can take a while
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(120);
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+/// A worker quiet for longer than this is pinged before the next call
+const HEALTH_CHECK_AFTER: Duration = Duration::from_secs(30);
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+/// How often a waiting call checks for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Restarts in a row before the bridge gives up
+const MAX_RESTARTS: u32 = 3;
+
+/// Where the Python bridge is in its lazy start
+///
+/// Nothing Python-related runs until a Python-dependent call needs it; the
+/// changes are sent to the frontend as `python-bridge-status` events.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum BridgeStatus {
+    NotStarted,
+    Initializing,
+    Ready,
+    Failed { error: String },
+}
+
+static STATUS: Mutex<BridgeStatus> = parking_lot::const_mutex(BridgeStatus::NotStarted);
+
+/// Current state of the Python bridge
+pub fn status() -> BridgeStatus {
+    STATUS.lock().clone()
+}
+
+fn set_status(status: BridgeStatus, on_status: &dyn Fn(&BridgeStatus)) {
+    *STATUS.lock() = status.clone();
+    on_status(&status);
+}
+
+/// Create and initialize the bridge, reporting progress through `on_status`
+///
+/// Called on first need rather than at startup.
+pub fn start(on_status: &dyn Fn(&BridgeStatus)) -> Result<PythonBridge> {
+    set_status(BridgeStatus::Initializing, on_status);
+    let result = PythonBridge::new().and_then(|mut bridge| bridge.initialize().map(|_| bridge));
+    match &result {
+        Ok(_) => set_status(BridgeStatus::Ready, on_status),
+        Err(e) => set_status(BridgeStatus::Failed { error: format!("{:#}", e) }, on_status),
+    }
+    result
+}
+
+/// State of the Python bridge (`not_started` until something needs it)
+#[tauri::command]
+pub async fn get_python_status() -> Result<BridgeStatus, String> {
+    Ok(status())
+}
+
+/// Python interpreter to run the worker with: AURANEXUS_PYTHON, else the
+/// app's own environment (see `python_env`), else the first on the PATH
+fn python_executable() -> Result<String> {
+    if let Ok(python) = std::env::var(PYTHON_ENV_VAR) {
+        return Ok(python);
+    }
+    if let Some(python) = python_env::ready_python() {
+        return Ok(python);
+    }
+    setup_wizard::find_python()
+        .map(|(executable, _)| executable)
+        .ok_or_else(|| anyhow!("No Python interpreter found (set {} to pick one)", PYTHON_ENV_VAR))
+}
+
+/// How long a worker request may run; unset limits don't apply
+#[derive(Debug, Clone, Copy, Default)]
+struct Limits {
+    /// Until the first token (or the answer)
+    first: Option<Duration>,
+    /// Between two tokens
+    idle: Option<Duration>,
+    total: Option<Duration>,
+}
+
+impl Limits {
+    fn total(limit: Duration) -> Self {
+        Self { total: Some(limit), ..Self::default() }
+    }
+
+    /// The limits an HTTP generation gets
+    fn generation(timeouts: &BackendTimeouts) -> Self {
+        Self {
+            first: Some(BackendTimeouts::secs(timeouts.first_token_secs)),
+            idle: Some(BackendTimeouts::secs(timeouts.idle_secs)),
+            total: Some(BackendTimeouts::secs(timeouts.generate_secs)),
+        }
+    }
+}
+
+/// The worker process and its end of the pipes
+struct Worker {
+    /// `None` for a worker simulated in tests
+    child: Option<Child>,
+    stdin: Box<dyn Write + Send>,
+    /// Messages from its stdout, in order; closed when it exits
+    messages: Receiver<Value>,
+    next_id: u64,
+    last_seen: Instant,
+}
+
+impl Worker {
+    fn spawn(python: &str) -> Result<Self> {
+        let mut child = Command::new(python)
+            .args(["-u", "-c", WORKER_SCRIPT])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start {}", python))?;
+        info!("Python worker started (pid {})", child.id());
+
+        let stdin = child.stdin.take().context("Worker stdin unavailable")?;
+        let stdout = child.stdout.take().context("Worker stdout unavailable")?;
+        let stderr = child.stderr.take().context("Worker stderr unavailable")?;
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => info!(target: "python_worker", "{}", line),
+                }
+            }
+        });
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(|line| line.ok()) {
+                info!(target: "python_worker", "{}", line);
+            }
+        });
+
+        Ok(Self {
+            child: Some(child),
+            stdin: Box::new(stdin),
+            messages,
+            next_id: 1,
+            last_seen: Instant::now(),
+        })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        self.child.as_mut().map_or(true, |child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    /// Stop the process at once, without asking
+    fn kill(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn send(&mut self, message: &Value) -> Result<()> {
+        writeln!(self.stdin, "{}", message)
+            .and_then(|_| self.stdin.flush())
+            .context("Python worker is not accepting requests")
+    }
+
+    /// Send a request and wait for its result
+    ///
+    /// Notifications for it go to `on_notification`, which returns `false` to
+    /// cancel; so does `cancel`. Either way the worker still answers, with
+    /// what it had so far. Running past one of `limits` is a
+    /// `BackendError::Timeout`; the worker is then still busy with it.
+    fn request(
+        &mut self,
+        method: &str,
+        params: Value,
+        limits: Limits,
+        cancel: Option<&CancellationToken>,
+        on_notification: &mut dyn FnMut(&Value) -> bool,
+    ) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let started = Instant::now();
+        let mut last_token: Option<Instant> = None;
+        let mut cancel_sent = false;
+        loop {
+            let stop = cancel.is_some_and(|cancel| cancel.is_cancelled());
+            if stop && !cancel_sent {
+                self.send(&json!({ "jsonrpc": "2.0", "method": "cancel", "params": { "id": id } }))?;
+                cancel_sent = true;
+            }
+            let (operation, since, limit) = match last_token {
+                None => ("first token", started, limits.first),
+                Some(at) => ("next token", at, limits.idle),
+            };
+            let mut wait = POLL_INTERVAL;
+            for (operation, since, limit) in [(operation, since, limit), ("Python worker", started, limits.total)] {
+                let Some(limit) = limit else { continue };
+                let deadline = since + limit;
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(BackendError::Timeout { operation, limit }.into());
+                }
+                wait = wait.min(deadline - now);
+            }
+            let message = match self.messages.recv_timeout(wait) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(anyhow!("Python worker exited during {}", method)),
+            };
+            self.last_seen = Instant::now();
+            if message["id"] != json!(id) {
+                if message["params"]["id"] == json!(id) {
+                    last_token = Some(self.last_seen);
+                    if !on_notification(&message) && !cancel_sent {
+                        self.send(&json!({ "jsonrpc": "2.0", "method": "cancel", "params": { "id": id } }))?;
+                        cancel_sent = true;
+                    }
+                }
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error["message"].as_str().unwrap_or("unknown error");
+                return Err(anyhow!("{}", text));
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.request("ping", json!({}), Limits::total(PING_TIMEOUT), None, &mut |_| true)
+            .map(|_| ())
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if self.child.is_none() {
+            return;
+        }
+        let id = self.next_id;
+        let _ = self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": "shutdown" }));
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            if !self.is_alive() {
+                info!("Python worker stopped");
+                return;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        warn!("Python worker didn't stop, killing it");
+        self.kill();
+    }
+}
 
 /// Python backend bridge - connects Tauri to existing Python LLM infrastructure
 /// This enables use of advanced sampling, The Nexus Core, and all Phase 1 features
+///
+/// Module/function names come from the backend's `bridge.toml` manifest.
 pub struct PythonBridge {
     backend_path: PathBuf,
+    manifest: BridgeManifest,
+    python: String,
+    /// Operations that resolved and passed signature checks at init
+    available: HashSet<Operation>,
     initialized: bool,
+    worker: Mutex<Option<Worker>>,
+    /// Model to load again after a restart
+    loaded_model: Mutex<Option<Value>>,
+    /// Restarts since the last successful call
+    restarts: AtomicU32,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct LlmConfig {
-    pub temperature: f32,
-    pub top_p: f32,
-    pub top_k: i32,
-    pub min_p: Option<f32>,
-    pub frequency_penalty: Option<f32>,
-    pub presence_penalty: Option<f32>,
-    pub dry_multiplier: Option<f32>,
-    pub xtc_probability: Option<f32>,
-    pub dynatemp_range: Option<f32>,
-    pub max_tokens: i32,
+/// History entry as handed to Python (borrowed, no per-call copies)
+#[derive(Serialize)]
+struct HistoryMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+    timestamp: &'a str,
 }
 
-impl Default for LlmConfig {
-    fn default() -> Self {
-        Self {
-            temperature: 0.7,
-            top_p: 0.95,
-            top_k: 40,
-            min_p: Some(0.05),
-            frequency_penalty: Some(0.2),
-            presence_penalty: Some(0.1),
-            dry_multiplier: Some(0.7),
-            xtc_probability: None,
-            dynatemp_range: None,
-            max_tokens: 512,
-        }
-    }
+/// History as the JSON list of {role, content, timestamp} Python expects
+fn encode_history(history: &[ConversationEntry]) -> Value {
+    let messages: Vec<HistoryMessage> = history
+        .iter()
+        .map(|entry| HistoryMessage {
+            role: &entry.role,
+            content: &entry.content,
+            timestamp: &entry.timestamp,
+        })
+        .collect();
+    json!(messages)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConversationEntry {
-    pub role: String,
-    pub content: String,
-    pub timestamp: String,
-    pub quality_score: Option<f32>,
+/// History entry as returned by the backend
+#[derive(Deserialize)]
+struct HistoryRecord {
+    role: String,
+    content: String,
+    timestamp: String,
+    #[serde(default)]
+    quality_score: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,24 +355,33 @@ pub struct SearchResult {
 }
 
 impl PythonBridge {
-    /// Create new Python bridge instance
-    pub fn new() -> Result<Arc<Mutex<Self>>> {
+    /// Create new Python bridge instance (call `initialize` before use)
+    pub fn new() -> Result<Self> {
         // Find backend path (relative to executable or development location)
         let backend_path = Self::find_backend_path()
             .context("Failed to find Python backend directory")?;
         
-        println!("🐍 Python backend path: {}", backend_path.display());
+        info!("Python backend path: {}", backend_path.display());
+        
+        let manifest = BridgeManifest::load(&backend_path)?;
+        let python = python_executable()?;
         
-        Ok(Arc::new(Mutex::new(Self {
+        Ok(Self {
             backend_path,
+            manifest,
+            python,
+            available: HashSet::new(),
             initialized: false,
-        })))
+            worker: Mutex::new(None),
+            loaded_model: Mutex::new(None),
+            restarts: AtomicU32::new(0),
+        })
     }
     
     /// Find the Python backend directory
-    fn find_backend_path() -> Option<PathBuf> {
+    pub fn find_backend_path() -> Option<PathBuf> {
         let current_dir = std::env::current_dir().ok()?;
-        println!("🔍 Current directory: {}", current_dir.display());
+        debug!("Current directory: {}", current_dir.display());
         
         // Try multiple locations
         let search_paths = vec![
@@ -93,193 +396,301 @@ impl PythonBridge {
         ];
         
         for path in &search_paths {
-            println!("🔍 Checking path: {}", path.display());
-            if path.exists() && path.join("llm_manager.py").exists() {
-                println!("✅ Found backend at: {}", path.display());
+            debug!("Checking path: {}", path.display());
+            if path.join(MANIFEST_FILE).exists() || path.join("llm_manager.py").exists() {
+                info!("Found backend at: {}", path.display());
                 return Some(path.clone());
             }
         }
         
-        println!("❌ Could not find Python backend in any search path");
+        error!("Could not find Python backend in any search path");
         
         None
     }
     
-    /// Initialize Python interpreter and import backend modules
+    /// Start the worker and import backend modules
     pub fn initialize(&mut self) -> Result<()> {
         if self.initialized {
             return Ok(());
         }
         
-        println!("🐍 Initializing Python interpreter...");
-        println!("🐍 Backend path: {}", self.backend_path.display());
+        info!("Starting Python worker ({})...", self.python);
+        info!("Backend path: {}", self.backend_path.display());
         
-        let result = Python::with_gil(|py| {
-            // Add backend path to sys.path
-            println!("🐍 Adding backend to sys.path...");
-            let sys = py.import("sys")?;
-            let path = sys.getattr("path")?;
-            path.call_method1("insert", (0, self.backend_path.to_str().unwrap()))?;
-            
-            // Also add parent directory (for nexus_core_* modules)
-            if let Some(backend_parent) = self.backend_path.parent() {
-                if let Some(workspace_root) = backend_parent.parent() {
-                    println!("🐍 Adding workspace root to sys.path: {}", workspace_root.display());
-                    path.call_method1("insert", (0, workspace_root.to_str().unwrap()))?;
-                }
+        let mut worker = Worker::spawn(&self.python)?;
+        let available = self.initialize_worker(&mut worker)?;
+        self.available = available;
+        *self.worker.get_mut() = Some(worker);
+        self.initialized = true;
+        info!("Python bridge initialization complete");
+        Ok(())
+    }
+    
+    /// Resolve every manifest operation in a fresh worker
+    fn initialize_worker(&self, worker: &mut Worker) -> Result<HashSet<Operation>> {
+        // The backend, plus the workspace root for nexus_core_* modules
+        let mut paths = vec![self.backend_path.to_string_lossy().into_owned()];
+        if let Some(workspace_root) = self.backend_path.parent().and_then(|parent| parent.parent()) {
+            paths.push(workspace_root.to_string_lossy().into_owned());
+        }
+        let operations: Vec<Value> = Operation::ALL
+            .into_iter()
+            .map(|operation| {
+                let spec = self.manifest.get(operation);
+                debug!("Resolving {} -> {}", operation.name(), spec.target);
+                json!({ "name": operation.name(), "target": spec.target, "params": spec.params })
+            })
+            .collect();
+        let result = worker
+            .request(
+                "initialize",
+                json!({ "paths": paths, "operations": operations }),
+                Limits::total(INITIALIZE_TIMEOUT),
+                None,
+                &mut |_| true,
+            )
+            .context("Failed to initialize Python backend")?;
+        
+        let mut failures = Vec::new();
+        for failure in result["failures"].as_array().into_iter().flatten() {
+            let name = failure["operation"].as_str().unwrap_or_default();
+            let reason = failure["error"].as_str().unwrap_or_default();
+            let Some(operation) = Operation::ALL.into_iter().find(|op| op.name() == name) else {
+                continue;
+            };
+            let spec = self.manifest.get(operation);
+            if spec.required {
+                error!("{} ({}): {}", name, spec.target, reason);
+                failures.push(format!("{} ({}): {}", name, spec.target, reason));
+            } else {
+                warn!("Optional operation {} unavailable: {}", name, reason);
             }
-            
-            // Import backend modules to verify they load
-            println!("🐍 Importing llm_manager...");
-            let _llm_manager = py.import("llm_manager").map_err(|e| {
-                println!("❌ Failed to import llm_manager: {}", e);
-                e
-            })?;
-            
-            println!("🐍 Importing hierarchical_memory...");
-            let _memory_manager = py.import("hierarchical_memory").map_err(|e| {
-                println!("❌ Failed to import hierarchical_memory: {}", e);
-                e
-            })?;
-            
-            println!("✅ Python backend modules loaded successfully");
-            Ok::<(), PyErr>(())
-        });
+        }
+        if !failures.is_empty() {
+            error!("Python initialization failed");
+            return Err(anyhow!("Failed to initialize Python backend: {}", failures.join("; ")));
+        }
+        
+        info!("Python backend modules loaded successfully");
+        Ok(result["available"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|name| Operation::ALL.into_iter().find(|op| Some(op.name()) == name.as_str()))
+            .collect())
+    }
+    
+    /// A running, responsive worker in `slot`, restarting it if needed
+    fn ensure_worker(&self, slot: &mut Option<Worker>) -> Result<()> {
+        if let Some(worker) = slot.as_mut() {
+            let healthy = worker.is_alive()
+                && (worker.last_seen.elapsed() < HEALTH_CHECK_AFTER || worker.ping().is_ok());
+            if healthy {
+                return Ok(());
+            }
+            warn!("Python worker stopped responding");
+            *slot = None;
+        }
         
-        match result {
-            Ok(_) => {
-                self.initialized = true;
-                println!("✅ Python bridge initialization complete");
-                Ok(())
+        let restarts = self.restarts.fetch_add(1, Ordering::SeqCst) + 1;
+        if restarts > MAX_RESTARTS {
+            let error = format!("Python worker failed {} times in a row; not restarting", MAX_RESTARTS);
+            set_status(BridgeStatus::Failed { error: error.clone() }, &|_| {});
+            return Err(anyhow!(error));
+        }
+        info!("Restarting Python worker (attempt {} of {})", restarts, MAX_RESTARTS);
+        set_status(BridgeStatus::Initializing, &|_| {});
+        let mut worker = Worker::spawn(&self.python)?;
+        self.initialize_worker(&mut worker)?;
+        if let Some(model) = self.loaded_model.lock().clone() {
+            info!("Reloading model {}", model);
+            let limit = BackendTimeouts::secs(BackendTimeouts::load().generate_secs);
+            worker.request("call", load_params(model), Limits::total(limit), None, &mut |_| true)?;
+        }
+        set_status(BridgeStatus::Ready, &|_| {});
+        *slot = Some(worker);
+        Ok(())
+    }
+    
+    /// Call `operation` in the worker
+    ///
+    /// Streamed tokens go to `on_token`. A worker that dies during the call
+    /// is restarted on the next one; one that runs past the backend timeouts
+    /// is killed first.
+    fn call(
+        &self,
+        operation: Operation,
+        args: Value,
+        kwargs: Value,
+        cancel: Option<&CancellationToken>,
+        on_token: Option<&mut dyn FnMut(&str) -> bool>,
+    ) -> Result<Value> {
+        if !self.available.contains(&operation) {
+            return Err(anyhow!("Bridge operation '{}' is not available", operation.name()));
+        }
+        // Loaded per call, so edits apply without a restart
+        let timeouts = BackendTimeouts::load();
+        let limits = match operation {
+            Operation::Generate => Limits::generation(&timeouts),
+            _ => Limits::total(BackendTimeouts::secs(timeouts.generate_secs)),
+        };
+        let mut slot = self.worker.lock();
+        self.ensure_worker(&mut slot)?;
+        let worker = slot.as_mut().expect("worker just started");
+        let stream = on_token.is_some();
+        let mut on_token = on_token;
+        let result = worker.request(
+            "call",
+            json!({ "operation": operation.name(), "args": args, "kwargs": kwargs, "stream": stream }),
+            limits,
+            cancel,
+            &mut |message| match on_token.as_mut() {
+                Some(on_token) => on_token(message["params"]["token"].as_str().unwrap_or_default()),
+                None => true,
+            },
+        );
+        match &result {
+            Ok(_) => self.restarts.store(0, Ordering::SeqCst),
+            Err(e) if http_backend::is_timeout(e) => {
+                warn!("Python worker hung during {}, killing it: {}", operation.name(), e);
+                worker.kill();
+                *slot = None;
             }
-            Err(e) => {
-                println!("❌ Python initialization failed: {}", e);
-                Err(anyhow::anyhow!("Failed to initialize Python backend: {}", e))
+            Err(_) if !worker.is_alive() => {
+                warn!("Python worker crashed during {}", operation.name());
+                *slot = None;
             }
+            Err(_) => {}
         }
+        result
     }
     
     /// Check if a model exists or needs to be downloaded
     pub fn check_model_exists(&self) -> Result<bool> {
-        let result = Python::with_gil(|py| {
-            let llm_manager = py.import("llm_manager")?;
-            let find_model = llm_manager.getattr("find_available_model")?;
-            let result = find_model.call0()?;
-            
-            // Returns model path or None
-            Ok::<bool, PyErr>(!result.is_none())
-        });
-        
-        result.context("Failed to check model existence")
+        // Returns model path or None
+        let path = self
+            .call(Operation::FindModel, json!([]), json!({}), None, None)
+            .context("Failed to check model existence")?;
+        Ok(!path.is_null())
     }
     
-    /// Download starter model (Qwen2.5-0.5B-Instruct)
-    pub fn download_starter_model(&self, _progress_callback: impl Fn(f32) + Send + 'static) -> Result<PathBuf> {
-        let result = Python::with_gil(|py| {
-            let llm_manager = py.import("llm_manager")?;
-            let download_fn = llm_manager.getattr("download_starter_model")?;
-            
-            // Call download function (blocking)
-            let model_path = download_fn.call0()?;
-            let path_str: String = model_path.extract()?;
-            
-            Ok::<PathBuf, PyErr>(PathBuf::from(path_str))
-        });
-        
-        result.context("Failed to download starter model")
+    /// Download starter model (Qwen2.5-0.5B-Instruct) into the models directory
+    ///
+    /// Uses the Rust downloader rather than the backend's
+    /// `download_starter_model`, which can't report progress. An existing
+    /// download is reused.
+    pub fn download_starter_model(&self, on_progress: impl FnMut(&DownloadProgress)) -> Result<PathBuf> {
+        let model = starter_models()
+            .into_iter()
+            .next()
+            .context("No starter model defined")?;
+        let dest = paths::models_dir().join(&model.filename);
+        if !dest.is_file() {
+            downloader::download_file(&model.url, &dest, None, on_progress)
+                .context("Failed to download starter model")?;
+        }
+        Ok(dest)
     }
     
     /// Load model into memory
     pub fn load_model(&self, model_path: Option<PathBuf>) -> Result<()> {
-        let result = Python::with_gil(|py| {
-            let llm_manager = py.import("llm_manager")?;
-            let load_fn = llm_manager.getattr("load_model")?;
-            
-            if let Some(path) = model_path {
-                load_fn.call1((path.to_str().unwrap(),))?;
-            } else {
+        let path = match model_path {
+            Some(path) => json!(path.to_string_lossy()),
+            None => {
                 // Auto-find model
-                let find_model = llm_manager.getattr("find_available_model")?;
-                let model_path_py = find_model.call0()?;
-                
-                if model_path_py.is_none() {
-                    return Err(PyErr::new::<pyo3::exceptions::PyFileNotFoundError, _>("No model found"));
+                let found = self
+                    .call(Operation::FindModel, json!([]), json!({}), None, None)
+                    .context("Failed to load model")?;
+                if found.is_null() {
+                    return Err(anyhow!("No model found")).context("Failed to load model");
                 }
-                
-                load_fn.call1((model_path_py,))?;
+                found
             }
-            
-            Ok::<(), PyErr>(())
-        });
-        
-        result.context("Failed to load model")
+        };
+        self.call(Operation::LoadModel, json!([path]), json!({}), None, None)
+            .context("Failed to load model")?;
+        *self.loaded_model.lock() = Some(path);
+        Ok(())
     }
     
     /// Generate response using Python LLM with advanced sampling
+    ///
+    /// Cancelling `cancel` stops at the next streamed token and returns the
+    /// text so far.
     pub fn generate(
         &self,
         prompt: String,
         system_prompt: Option<String>,
-        conversation_history: Vec<ConversationEntry>,
+        conversation_history: &[ConversationEntry],
         config: LlmConfig,
+        cancel: &CancellationToken,
     ) -> Result<String> {
-        let result = Python::with_gil(|py| {
-            let llm_manager = py.import("llm_manager")?;
-            let generate_fn = llm_manager.getattr("generate_with_context")?;
-            
-            // Build kwargs dict with sampling parameters
-            let kwargs = PyDict::new(py);
-            kwargs.set_item("prompt", prompt)?;
-            kwargs.set_item("temperature", config.temperature)?;
-            kwargs.set_item("top_p", config.top_p)?;
-            kwargs.set_item("top_k", config.top_k)?;
-            kwargs.set_item("max_tokens", config.max_tokens)?;
-            
-            if let Some(min_p) = config.min_p {
-                kwargs.set_item("min_p", min_p)?;
-            }
-            if let Some(freq) = config.frequency_penalty {
-                kwargs.set_item("frequency_penalty", freq)?;
-            }
-            if let Some(pres) = config.presence_penalty {
-                kwargs.set_item("presence_penalty", pres)?;
-            }
-            if let Some(dry) = config.dry_multiplier {
-                kwargs.set_item("dry_multiplier", dry)?;
-            }
-            if let Some(xtc) = config.xtc_probability {
-                kwargs.set_item("xtc_probability", xtc)?;
-            }
-            if let Some(dynatemp) = config.dynatemp_range {
-                kwargs.set_item("dynatemp_range", dynatemp)?;
-            }
-            
-            if let Some(sys_prompt) = system_prompt {
-                kwargs.set_item("system_prompt", sys_prompt)?;
-            }
-            
-            // Convert conversation history to Python list of dicts
-            let history_list = pyo3::types::PyList::empty(py);
-            for entry in conversation_history {
-                let entry_dict = PyDict::new(py);
-                entry_dict.set_item("role", entry.role)?;
-                entry_dict.set_item("content", entry.content)?;
-                entry_dict.set_item("timestamp", entry.timestamp)?;
-                history_list.append(entry_dict)?;
-            }
-            kwargs.set_item("conversation_history", history_list)?;
-            
-            // Call generate function
-            let response = generate_fn.call((), Some(kwargs))?;
-            let response_text: String = response.extract()?;
-            
-            Ok::<String, PyErr>(response_text)
+        self.generate_streaming(prompt, system_prompt, conversation_history, config, cancel, |_| true)
+    }
+    
+    /// Generate with `stream=True`, handing each token to `on_token`
+    ///
+    /// The Python function may return an iterator of strings (streamed) or a
+    /// plain string (delivered as one token). `on_token` returns `false` to
+    /// stop early; the text so far is returned.
+    pub fn generate_streaming(
+        &self,
+        prompt: String,
+        system_prompt: Option<String>,
+        conversation_history: &[ConversationEntry],
+        config: LlmConfig,
+        cancel: &CancellationToken,
+        mut on_token: impl FnMut(&str) -> bool,
+    ) -> Result<String> {
+        // Sampling parameters the backend understands; unset ones are left out
+        let mut kwargs = json!({
+            "prompt": prompt,
+            "temperature": config.temperature,
+            "top_p": config.top_p,
+            "top_k": config.top_k,
+            "max_tokens": config.max_tokens,
+            "conversation_history": encode_history(conversation_history),
+            "stream": true,
         });
+        let optional = [
+            ("min_p", config.min_p.map(|v| json!(v))),
+            ("repetition_penalty", config.repeat_penalty.map(|v| json!(v))),
+            ("frequency_penalty", config.frequency_penalty.map(|v| json!(v))),
+            ("presence_penalty", config.presence_penalty.map(|v| json!(v))),
+            ("dry_multiplier", config.dry_multiplier.map(|v| json!(v))),
+            ("xtc_probability", config.xtc_probability.map(|v| json!(v))),
+            ("dynatemp_range", config.dynatemp_range.map(|v| json!(v))),
+            ("stop", Some(json!(config.stop)).filter(|_| !config.stop.is_empty())),
+            ("seed", config.seed.map(|v| json!(v))),
+            ("logit_bias", Some(logit_bias::token_ids(&config.logit_bias)).filter(|bias| !bias.is_empty()).map(|bias| json!(bias))),
+            ("system_prompt", system_prompt.map(|v| json!(v))),
+            ("json_schema", config.json_schema.clone()),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                kwargs[key] = value;
+            }
+        }
         
-        result.context("Failed to generate response")
+        let mut streamed = false;
+        let mut forward = |token: &str| {
+            streamed = true;
+            on_token(token)
+        };
+        let result = self
+            .call(Operation::Generate, json!([]), kwargs, Some(cancel), Some(&mut forward))
+            .context("Failed to generate response")?;
+        let text = result
+            .as_str()
+            .ok_or_else(|| anyhow!("The backend returned {} instead of text", result))?
+            .to_string();
+        // A plain string reply arrives in one piece
+        if !streamed {
+            on_token(&text);
+        }
+        Ok(text)
     }
     
-    /// Log conversation turn to hierarchical storage
     /// Log conversation turn to hierarchical storage
     pub fn log_conversation(
         &self,
@@ -287,73 +698,173 @@ impl PythonBridge {
         assistant_response: String,
         mode: String,
     ) -> Result<()> {
-        let result = Python::with_gil(|py| {
-            let nexus_engine = py.import("nexus_core_engine")?;
-            let log_fn = nexus_engine.getattr("log_conversation_turn")?;
-            
-            log_fn.call1((user_message, assistant_response, mode))?;
-            
-            Ok::<(), PyErr>(())
-        });
-        
-        result.context("Failed to log conversation")
+        self.call(
+            Operation::LogConversation,
+            json!([user_message, assistant_response, mode]),
+            json!({}),
+            None,
+            None,
+        )
+        .context("Failed to log conversation")?;
+        Ok(())
     }
     
     /// Search past conversations using The Nexus Core
     pub fn search_memory(&self, query: String, top_k: usize) -> Result<Vec<SearchResult>> {
-        let result = Python::with_gil(|py| {
-            let nexus_indexing = py.import("nexus_core_indexing")?;
-            let search_fn = nexus_indexing.getattr("intelligent_search")?;
-            
-            let results_py = search_fn.call1((query, top_k))?;
-            
-            // Convert Python list to Rust Vec
-            let results_list: &pyo3::types::PyList = results_py.downcast()?;
-            let mut results = Vec::new();
-            
-            for item in results_list.iter() {
-                let dict: &PyDict = item.downcast()?;
-                let result = SearchResult {
-                    content: dict.get_item("content")?.unwrap().extract()?,
-                    timestamp: dict.get_item("timestamp")?.unwrap().extract()?,
-                    relevance_score: dict.get_item("relevance_score")?.unwrap().extract()?,
-                    citations: dict.get_item("citations")?.unwrap().extract()?,
-                };
-                results.push(result);
-            }
-            
-            Ok::<Vec<SearchResult>, PyErr>(results)
-        });
-        
-        result.context("Failed to search memory")
+        let results = self
+            .call(Operation::SearchMemory, json!([query, top_k]), json!({}), None, None)
+            .context("Failed to search memory")?;
+        serde_json::from_value(results).context("Failed to search memory")
+    }
+    
+    /// Delete what The Nexus Core logged for `user_id`, returning how many
+    /// records went; `None` if the backend has no such operation
+    pub fn delete_user_data(&self, user_id: &str) -> Result<Option<usize>> {
+        if !self.available.contains(&Operation::DeleteUserData) {
+            return Ok(None);
+        }
+        let removed = self
+            .call(Operation::DeleteUserData, json!([user_id]), json!({}), None, None)
+            .context("Failed to delete Nexus Core data")?;
+        Ok(Some(removed.as_u64().unwrap_or(0) as usize))
     }
     
     /// Get recent conversation history
     pub fn get_conversation_history(&self, limit: usize) -> Result<Vec<ConversationEntry>> {
-        let result = Python::with_gil(|py| {
-            let memory_manager = py.import("hierarchical_memory")?;
-            let get_history_fn = memory_manager.getattr("get_recent_history")?;
-            
-            let history_py = get_history_fn.call1((limit,))?;
-            
-            // Convert Python list to Rust Vec
-            let history_list: &pyo3::types::PyList = history_py.downcast()?;
-            let mut history = Vec::new();
-            
-            for item in history_list.iter() {
-                let dict: &PyDict = item.downcast()?;
-                let entry = ConversationEntry {
-                    role: dict.get_item("role")?.unwrap().extract()?,
-                    content: dict.get_item("content")?.unwrap().extract()?,
-                    timestamp: dict.get_item("timestamp")?.unwrap().extract()?,
-                    quality_score: dict.get_item("quality_score")?.and_then(|v| v.extract().ok()),
+        let records = self
+            .call(Operation::RecentHistory, json!([limit]), json!({}), None, None)
+            .context("Failed to get conversation history")?;
+        let records: Vec<HistoryRecord> =
+            serde_json::from_value(records).context("Failed to get conversation history")?;
+        Ok(records
+            .into_iter()
+            .map(|record| ConversationEntry {
+                role: record.role,
+                content: record.content,
+                timestamp: record.timestamp,
+                quality_score: record.quality_score,
+                status: EntryStatus::Complete,
+                tool_calls: Vec::new(),
+                parts: Vec::new(),
+            })
+            .collect())
+    }
+}
+
+/// `call` params loading the model at `path`
+fn load_params(path: Value) -> Value {
+    json!({ "operation": Operation::LoadModel.name(), "args": [path], "kwargs": {}, "stream": false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Requests written to a simulated worker, one per line
+    struct Pipe {
+        buffer: Vec<u8>,
+        requests: mpsc::Sender<Value>,
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.buffer.extend_from_slice(bytes);
+            while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let request = serde_json::from_slice(&line).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                let _ = self.requests.send(request);
+            }
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A worker answering like `bridge_worker.py` with `generate` bound to
+    /// `itertools.repeat`; `stall` sends one token and `hang` nothing
+    fn simulated_worker() -> Worker {
+        let (requests, incoming) = mpsc::channel::<Value>();
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for request in incoming {
+                let id = request["id"].clone();
+                let token = |token: &str| json!({ "jsonrpc": "2.0", "method": "token", "params": { "id": id, "token": token } });
+                let params = &request["params"];
+                let _ = match (request["method"].as_str(), params["operation"].as_str()) {
+                    (Some("ping"), _) => sender.send(json!({ "jsonrpc": "2.0", "id": id, "result": {} })),
+                    (Some("call"), Some("generate")) => {
+                        let text = params["args"][0].as_str().unwrap_or_default();
+                        let count = params["args"][1].as_u64().unwrap_or(1) as usize;
+                        for _ in 0..count {
+                            let _ = sender.send(token(text));
+                        }
+                        sender.send(json!({ "jsonrpc": "2.0", "id": id, "result": text.repeat(count) }))
+                    }
+                    (Some("call"), Some("stall")) => sender.send(token("x")),
+                    (Some("call"), Some("hang")) => Ok(()),
+                    (Some("call"), _) => sender.send(json!({ "jsonrpc": "2.0", "id": id, "error": { "message": "Unknown operation" } })),
+                    _ => Ok(()),
                 };
-                history.push(entry);
             }
-            
-            Ok::<Vec<ConversationEntry>, PyErr>(history)
         });
-        
-        result.context("Failed to get conversation history")
+        Worker {
+            child: None,
+            stdin: Box::new(Pipe { buffer: Vec::new(), requests }),
+            messages,
+            next_id: 1,
+            last_seen: Instant::now(),
+        }
+    }
+
+    fn call_params(operation: &str, args: Value) -> Value {
+        json!({ "operation": operation, "args": args, "kwargs": {}, "stream": true })
+    }
+
+    #[test]
+    fn test_worker_protocol() {
+        let mut worker = simulated_worker();
+        let mut tokens = Vec::new();
+        let text = worker
+            .request("call", call_params("generate", json!(["ab", 3])), Limits::default(), None, &mut |message| {
+                tokens.push(message["params"]["token"].as_str().unwrap().to_string());
+                true
+            })
+            .unwrap();
+        assert_eq!(text, "ababab");
+        assert_eq!(tokens, ["ab", "ab", "ab"]);
+
+        let missing = call_params("search_memory", json!([]));
+        let error = worker.request("call", missing, Limits::default(), None, &mut |_| true).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown operation");
+        worker.ping().unwrap();
+    }
+
+    #[test]
+    fn test_worker_timeouts() {
+        let mut worker = simulated_worker();
+        let limit = Duration::from_millis(200);
+        let cases = [
+            ("hang", Limits { first: Some(limit), ..Limits::default() }, "first token"),
+            ("stall", Limits { idle: Some(limit), ..Limits::default() }, "next token"),
+            ("stall", Limits::total(limit), "Python worker"),
+        ];
+        for (operation, limits, expected) in cases {
+            let error = worker
+                .request("call", call_params(operation, json!([])), limits, None, &mut |_| true)
+                .unwrap_err();
+            assert!(http_backend::is_timeout(&error), "{}: {:#}", operation, error);
+            match error.downcast_ref::<BackendError>() {
+                Some(BackendError::Timeout { operation, .. }) => assert_eq!(*operation, expected),
+                _ => panic!("not a timeout: {:#}", error),
+            }
+        }
+
+        // A worker that keeps streaming is not idle
+        let text = worker
+            .request("call", call_params("generate", json!(["a", 5])), Limits::generation(&BackendTimeouts::default()), None, &mut |_| true)
+            .unwrap();
+        assert_eq!(text, "aaaaa");
     }
 }
@@ -0,0 +1,319 @@
+// Python Environment Module - A private venv for the Python backend
+//
+// The Python backend needs llama-cpp-python and friends, which most users
+// don't have installed. `setup_python_env` finds the system Python, creates
+// a venv in `python-env` under the app data dir and pip-installs the
+// backend's requirements.txt into it, emitting `python-env-progress` events
+// as it goes. A marker file records the hash of the requirements it was
+// built from; once it matches, `ready_python` hands the venv's interpreter
+// to the Python bridge. AURANEXUS_PYTHON still overrides it.
+
+use crate::python_bridge::PythonBridge;
+use crate::{paths, setup_wizard};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+const MARKER_FILE: &str = "auranexus-env.json";
+/// Oldest Python the backend runs on
+const MIN_PYTHON: (u32, u32) = (3, 9);
+/// pip output kept for the error when an install fails
+const ERROR_TAIL_LINES: usize = 20;
+
+/// Guards against two setups writing the same venv
+static SETTING_UP: AtomicBool = AtomicBool::new(false);
+
+pub fn env_dir() -> PathBuf {
+    paths::app_data_dir().join("python-env")
+}
+
+/// The interpreter inside the venv at `dir`
+fn venv_python(dir: &Path) -> PathBuf {
+    if cfg!(windows) {
+        dir.join("Scripts").join("python.exe")
+    } else {
+        dir.join("bin").join("python")
+    }
+}
+
+/// What the venv was built from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EnvMarker {
+    requirements_sha256: String,
+    python_version: String,
+    installed_at: String,
+}
+
+impl EnvMarker {
+    fn load(dir: &Path) -> Option<Self> {
+        std::fs::read_to_string(dir.join(MARKER_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::write(dir.join(MARKER_FILE), serde_json::to_string_pretty(self)?)
+            .context("Failed to write the Python environment marker")
+    }
+}
+
+fn requirements_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// The backend's requirements.txt, if the backend can be found
+fn requirements_file() -> Option<PathBuf> {
+    PythonBridge::find_backend_path()
+        .map(|backend| backend.join("requirements.txt"))
+        .filter(|path| path.is_file())
+}
+
+/// Normalized package names in a requirements file
+fn requirement_names(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .filter_map(package_name)
+        .collect()
+}
+
+/// `Flask_Cors[extra]>=1.0` -> `flask-cors`
+fn package_name(spec: &str) -> Option<String> {
+    let name = spec
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .next()
+        .filter(|name| !name.is_empty())?;
+    Some(name.to_lowercase().replace('_', "-"))
+}
+
+/// The package a line of pip output starts working on
+fn pip_package(line: &str) -> Option<String> {
+    let spec = line
+        .strip_prefix("Collecting ")
+        .or_else(|| line.strip_prefix("Requirement already satisfied: "))?;
+    package_name(spec)
+}
+
+/// `(3, 11)` from "Python 3.11.4"
+fn parse_version(text: &str) -> Option<(u32, u32)> {
+    let mut parts = text.trim().strip_prefix("Python ")?.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.trim().parse().ok()?))
+}
+
+/// Where the environment is
+#[derive(Debug, Clone, Serialize)]
+pub struct PythonEnvStatus {
+    pub dir: String,
+    /// Venv built from the current requirements
+    pub ready: bool,
+    /// Venv exists but was built from other requirements (or never finished)
+    pub outdated: bool,
+    pub python_version: Option<String>,
+    /// System Python a venv would be created from
+    pub system_python: Option<String>,
+    pub requirements_found: bool,
+}
+
+pub fn status() -> PythonEnvStatus {
+    let dir = env_dir();
+    let exists = venv_python(&dir).is_file();
+    let marker = EnvMarker::load(&dir);
+    let current = requirements_file()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| requirements_hash(&text));
+    let ready = exists
+        && match (&marker, &current) {
+            (Some(marker), Some(current)) => &marker.requirements_sha256 == current,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+    PythonEnvStatus {
+        dir: dir.to_string_lossy().into_owned(),
+        ready,
+        outdated: exists && !ready,
+        python_version: marker.map(|marker| marker.python_version),
+        system_python: setup_wizard::find_python().map(|(_, version)| version),
+        requirements_found: current.is_some(),
+    }
+}
+
+/// The venv's interpreter, once it has been set up
+pub fn ready_python() -> Option<String> {
+    let dir = env_dir();
+    let python = venv_python(&dir);
+    (python.is_file() && EnvMarker::load(&dir).is_some()).then(|| python.to_string_lossy().into_owned())
+}
+
+/// Payload of `python-env-progress` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum EnvProgress {
+    CreatingVenv { python: String },
+    Installing { package: Option<String>, done: usize, total: usize },
+    Ready { python: String },
+    Failed { error: String },
+}
+
+/// Run `command`, passing each stdout line to `on_line`; the error carries
+/// the end of stderr
+fn run_streaming(command: &mut Command, on_line: &mut dyn FnMut(&str)) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to start Python")?;
+    let mut stderr = child.stderr.take().context("Python stderr unavailable")?;
+    let errors = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let stdout = child.stdout.take().context("Python stdout unavailable")?;
+    for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+        on_line(&line);
+    }
+    let status = child.wait()?;
+    let errors = errors.join().unwrap_or_default();
+    if status.success() {
+        return Ok(());
+    }
+    let lines: Vec<&str> = errors.lines().collect();
+    let tail = lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n");
+    Err(anyhow!("{} ({})", tail.trim(), status))
+}
+
+/// Create (or update) the venv and install `requirements` into it
+pub fn bootstrap(requirements: &Path, on_progress: &dyn Fn(&EnvProgress)) -> Result<String> {
+    let dir = env_dir();
+    let text = std::fs::read_to_string(requirements)
+        .with_context(|| format!("Can't read {}", requirements.display()))?;
+    let python = venv_python(&dir);
+
+    if !python.is_file() {
+        let (system, version) = setup_wizard::find_python().context("No Python interpreter found on the PATH")?;
+        match parse_version(&version) {
+            Some(found) if found >= MIN_PYTHON => {}
+            _ => {
+                return Err(anyhow!(
+                    "{} is too old; Python {}.{} or newer is needed",
+                    version,
+                    MIN_PYTHON.0,
+                    MIN_PYTHON.1
+                ))
+            }
+        }
+        on_progress(&EnvProgress::CreatingVenv { python: version.clone() });
+        info!("Creating Python environment in {} ({})", dir.display(), version);
+        std::fs::create_dir_all(&dir)?;
+        run_streaming(Command::new(&system).arg("-m").arg("venv").arg(&dir), &mut |_| {})
+            .context("Failed to create the Python environment")?;
+    }
+    // A new requirements file must not be mistaken for the old one if pip fails
+    let _ = std::fs::remove_file(dir.join(MARKER_FILE));
+
+    let names = requirement_names(&text);
+    let total = names.len();
+    let wanted: HashSet<String> = names.into_iter().collect();
+    let mut seen = HashSet::new();
+    on_progress(&EnvProgress::Installing { package: None, done: 0, total });
+    info!("Installing {} Python requirement(s) from {}", total, requirements.display());
+    let mut pip = Command::new(&python);
+    pip.args(["-m", "pip", "install", "--disable-pip-version-check", "--progress-bar", "off", "-r"])
+        .arg(requirements);
+    run_streaming(&mut pip, &mut |line| {
+        if let Some(package) = pip_package(line).filter(|package| wanted.contains(package)) {
+            if seen.insert(package.clone()) {
+                on_progress(&EnvProgress::Installing {
+                    package: Some(package),
+                    done: seen.len(),
+                    total,
+                });
+            }
+        }
+    })
+    .context("Failed to install the Python requirements")?;
+
+    let version = Command::new(&python)
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    EnvMarker {
+        requirements_sha256: requirements_hash(&text),
+        python_version: version,
+        installed_at: crate::clock::timestamp(),
+    }
+    .save(&dir)?;
+    let python = python.to_string_lossy().into_owned();
+    info!("Python environment ready: {}", python);
+    Ok(python)
+}
+
+/// State of the app's Python environment
+#[tauri::command]
+pub async fn get_python_env_status() -> Result<PythonEnvStatus, String> {
+    tauri::async_runtime::spawn_blocking(status)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Create the Python environment and install the backend's requirements,
+/// emitting `python-env-progress` events
+///
+/// The Python backend uses it the next time it starts (e.g. through
+/// `retry_backend_init`).
+#[tauri::command]
+pub async fn setup_python_env(window: tauri::Window) -> Result<PythonEnvStatus, String> {
+    let requirements = requirements_file().ok_or("The Python backend's requirements.txt wasn't found")?;
+    if SETTING_UP.swap(true, Ordering::SeqCst) {
+        return Err("The Python environment is already being set up".to_string());
+    }
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let emit = |progress: &EnvProgress| {
+            let _ = window.emit("python-env-progress", progress);
+        };
+        let result = bootstrap(&requirements, &emit);
+        match &result {
+            Ok(python) => emit(&EnvProgress::Ready { python: python.clone() }),
+            Err(e) => {
+                warn!("Python environment setup failed: {:#}", e);
+                emit(&EnvProgress::Failed { error: format!("{:#}", e) });
+            }
+        }
+        result
+    })
+    .await;
+    SETTING_UP.store(false, Ordering::SeqCst);
+    result.map_err(|e| e.to_string())?.map_err(|e| format!("{:#}", e))?;
+    get_python_env_status().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirements_and_pip_output() {
+        let text = "# Backend\nfastapi==0.109.0\nllama_cpp_python>=0.2  # inference\n\n-e ./local\nuvicorn[standard]\n";
+        assert_eq!(requirement_names(text), ["fastapi", "llama-cpp-python", "uvicorn"]);
+        assert_eq!(
+            pip_package("Collecting llama-cpp-python==0.2.27 (from -r requirements.txt (line 4))").as_deref(),
+            Some("llama-cpp-python")
+        );
+        assert_eq!(
+            pip_package("Requirement already satisfied: FastAPI==0.109.0 in ./lib/site-packages").as_deref(),
+            Some("fastapi")
+        );
+        assert_eq!(pip_package("Installing collected packages: fastapi"), None);
+        assert_eq!(parse_version("Python 3.11.4"), Some((3, 11)));
+        assert!(parse_version("Python 3.8.10").unwrap() < MIN_PYTHON);
+        assert_ne!(requirements_hash(text), requirements_hash("fastapi"));
+    }
+}
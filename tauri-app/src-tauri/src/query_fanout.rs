@@ -0,0 +1,147 @@
+// Query Fan-out Module - Retrieval with several phrasings of the message
+//
+// Before retrieval the model rewrites the message as a few differently worded
+// search queries. Every phrasing is embedded in parallel and searched, and the
+// ranked lists are merged with reciprocal rank fusion: a memory several
+// phrasings find ranks above one that only matched the literal wording. The
+// fused list then goes through the usual score threshold and limit. The
+// variants come from the chat backend with the query expansion preset; short
+// messages, or no model loaded, search the message alone.
+
+use crate::backend::SharedBackend;
+use crate::embeddings::Embedder;
+use crate::memory_store::MemoryItem;
+use crate::task_presets::{self, Task};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Rank damping in reciprocal rank fusion; the usual value from the
+/// literature, so a few top ranks don't dominate
+const RRF_K: f32 = 60.0;
+
+/// Messages with fewer words are searched as they are
+const MIN_WORDS: usize = 4;
+
+/// The message plus up to `count` reworded queries from the model
+pub fn queries(llm: &SharedBackend, message: &str, count: usize) -> Vec<String> {
+    let mut queries = vec![message.to_string()];
+    if count == 0 || message.split_whitespace().count() < MIN_WORDS {
+        return queries;
+    }
+    let prompt = format!(
+        "Write {} different search queries that would find notes relevant to the \
+        message below. Vary the wording and the angle. One query per line, nothing else.\n\n{}",
+        count, message
+    );
+    let generated = llm.blocking_lock().as_mut().and_then(|backend| {
+        task_presets::generate(backend.as_mut(), Task::QueryExpansion, "You write search queries.", &prompt).ok()
+    });
+    if let Some(text) = generated {
+        queries.extend(parse_variants(&text, message, count));
+    }
+    queries
+}
+
+/// Queries from the model's reply: list markers and quotes removed,
+/// duplicates (and repeats of the message) dropped
+pub fn parse_variants(text: &str, message: &str, count: usize) -> Vec<String> {
+    let mut seen = vec![message.trim().to_lowercase()];
+    let mut variants = Vec::new();
+    for line in text.lines() {
+        let query = strip_marker(line);
+        if query.is_empty() || seen.contains(&query.to_lowercase()) {
+            continue;
+        }
+        seen.push(query.to_lowercase());
+        variants.push(query.to_string());
+        if variants.len() == count {
+            break;
+        }
+    }
+    variants
+}
+
+/// `line` without a leading "1." / "2)" / "-" / "*" / "•" and quotes
+fn strip_marker(line: &str) -> &str {
+    let line = line.trim();
+    let unnumbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = if unnumbered.len() < line.len() {
+        // Digits only count as a marker when followed by "." or ")"
+        unnumbered.strip_prefix(|c: char| c == '.' || c == ')').unwrap_or(line)
+    } else {
+        line.strip_prefix(|c: char| matches!(c, '-' | '*' | '•')).unwrap_or(line)
+    };
+    rest.trim().trim_matches('"').trim()
+}
+
+/// Embed every query in parallel (empty vectors without an embedder, which
+/// makes the store fall back to text search)
+pub fn embed_all(embedder: Option<&dyn Embedder>, queries: &[String]) -> Vec<Vec<f32>> {
+    match embedder {
+        Some(embedder) => queries.par_iter().map(|query| embedder.embed(query)).collect(),
+        None => vec![Vec::new(); queries.len()],
+    }
+}
+
+/// Merge ranked result lists by reciprocal rank fusion, best first
+///
+/// Each memory keeps the best similarity it got from any query.
+pub fn fuse(lists: Vec<Vec<(MemoryItem, f32)>>) -> Vec<(MemoryItem, f32)> {
+    let mut fused: HashMap<String, (MemoryItem, f32, f32)> = HashMap::new();
+    for list in lists {
+        for (rank, (memory, score)) in list.into_iter().enumerate() {
+            let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+            let entry = fused.entry(memory.id.clone()).or_insert((memory, score, 0.0));
+            entry.1 = entry.1.max(score);
+            entry.2 += contribution;
+        }
+    }
+    let mut fused: Vec<(MemoryItem, f32, f32)> = fused.into_values().collect();
+    fused.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.total_cmp(&a.1)));
+    fused.into_iter().map(|(memory, score, _)| (memory, score)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn memory(id: &str) -> MemoryItem {
+        MemoryItem {
+            id: id.to_string(),
+            content: id.to_string(),
+            user_id: None,
+            agent_id: None,
+            run_id: None,
+            metadata: HashMap::new(),
+            embedding: None,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_variants_and_fusion() {
+        let reply = "1. Where does my sister live?\n- \"sister's home town\"\n\nwhere does my sister live?\n2024 Anna address";
+        let variants = parse_variants(reply, "Where does Anna live?", 3);
+        assert_eq!(
+            variants,
+            vec!["Where does my sister live?", "sister's home town", "2024 Anna address"]
+        );
+        assert!(parse_variants("Where does Anna live?", "where does anna live?", 3).is_empty());
+
+        // "b" is second for the message but found by both phrasings
+        let fused = fuse(vec![
+            vec![(memory("a"), 0.6), (memory("b"), 0.5)],
+            vec![(memory("b"), 0.7), (memory("c"), 0.4)],
+        ]);
+        let ids: Vec<&str> = fused.iter().map(|(m, _)| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert_eq!(fused[0].1, 0.7);
+
+        // One list keeps its order
+        let single = fuse(vec![vec![(memory("x"), 0.9), (memory("y"), 0.8)]]);
+        assert_eq!(single[0].0.id, "x");
+    }
+}
@@ -4,6 +4,7 @@
 use crate::memory_store::{MemoryStore, MemoryFilters};
 use crate::text_chunker::{TextChunker, ChunkingConfig};
 use std::collections::HashMap;
+use tracing::info;
 
 /// Example: Document ingestion with memory storage
 /// 
@@ -26,7 +27,7 @@ pub fn ingest_document(
     
     let chunks = chunker.chunk_with_metadata(document);
     let chunk_count = chunks.len();
-    println!("📄 Split document into {} chunks", chunk_count);
+    info!("Split document into {} chunks", chunk_count);
     
     // Step 2: Store each chunk with metadata
     let mut chunk_ids = Vec::new();
@@ -36,23 +37,20 @@ pub fn ingest_document(
         metadata.insert("chunk_index".to_string(), serde_json::json!(idx));
         metadata.insert("chunk_count".to_string(), serde_json::json!(chunk_count));
         
-        let chunk_id = store.add(
-            chunk_text,
-            Some(user_id.to_string()),
-            None,
-            None,
-            metadata,
-        );
+        let chunk_id = store
+            .add(chunk_text, Some(user_id.to_string()), None, None, metadata)
+            .expect("chunk metadata is valid");
         chunk_ids.push(chunk_id);
     }
     
-    println!("💾 Stored {} chunks in memory", chunk_ids.len());
+    info!("Stored {} chunks in memory", chunk_ids.len());
     chunk_ids
 }
 
 /// Example: Retrieve relevant document chunks
 /// 
-/// Demonstrates simple keyword search (TODO: upgrade to semantic search)
+/// Ranked through the store's HNSW vector index when it has an embedder,
+/// keyword search otherwise
 pub fn retrieve_chunks(
     query: &str,
     user_id: &str,
@@ -67,7 +65,7 @@ pub fn retrieve_chunks(
     // Search for relevant chunks
     let results = store.search(query, Some(&filters), top_k);
     
-    println!("🔍 Found {} relevant chunks for query: {}", results.len(), query);
+    info!("Found {} relevant chunks for query: {}", results.len(), query);
     
     results.iter().map(|mem| mem.content.clone()).collect()
 }
@@ -117,7 +115,7 @@ pub fn medical_conversation_example() {
         Some(agent_id.to_string()),
         Some("session_2024_001".to_string()),
         HashMap::new(),
-    );
+    ).expect("example metadata is valid");
     
     store.add(
         "Patient has history of type 2 diabetes",
@@ -125,7 +123,7 @@ pub fn medical_conversation_example() {
         Some(agent_id.to_string()),
         Some("session_2024_001".to_string()),
         HashMap::new(),
-    );
+    ).expect("example metadata is valid");
     
     // Retrieve all memories for this patient+agent
     let filters = MemoryFilters {
@@ -135,10 +133,10 @@ pub fn medical_conversation_example() {
     };
     
     let memories = store.get_all(&filters, 100);
-    println!("📋 Retrieved {} memories for patient", memories.len());
+    info!("Retrieved {} memories for patient", memories.len());
     
     for memory in memories {
-        println!("  - {}", memory.content);
+        info!("  - {}", memory.content);
     }
 }
 
@@ -163,17 +161,17 @@ pub fn document_qa_example() {
     "#;
     
     // Ingest document
-    println!("\n=== Document Ingestion ===");
+    info!("=== Document Ingestion ===");
     let _chunk_ids = ingest_document(document, "diabetes_guide", user_id, &mut store);
     
     // Ask questions
-    println!("\n=== Question 1: What are the symptoms? ===");
+    info!("=== Question 1: What are the symptoms? ===");
     answer_question("What are the symptoms of type 2 diabetes?", user_id, &store);
     
-    println!("\n=== Question 2: How is it treated? ===");
+    info!("=== Question 2: How is it treated? ===");
     answer_question("How is type 2 diabetes treated?", user_id, &store);
     
-    println!("\n=== Question 3: What are complications? ===");
+    info!("=== Question 3: What are complications? ===");
     answer_question("What complications can occur?", user_id, &store);
 }
 
@@ -0,0 +1,209 @@
+// Redaction Module - Scrub personal details before messages are remembered
+//
+// Messages are written to the memory store (and, when it is enabled, the
+// Nexus Core log) as they were typed, which for a health companion can mean
+// emails, phone numbers, home addresses or card numbers sitting in
+// memories.db for good. `scrub` replaces them with placeholders such as
+// `[EMAIL]` first; the working history and the conversation files keep the
+// original text. Regexes handle each category, toggled under `[redaction]`
+// in settings.toml. With `llm` on, the chat model also gets a pass to catch
+// what the patterns miss - only when it is idle, since it runs inline.
+
+use crate::backend::{GenerationRequest, LlmBackend, SharedBackend};
+use crate::generation::CancellationToken;
+use crate::settings::AppSettings;
+use crate::task_presets::{self, Task};
+use crate::{AppState, LlmConfig};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+const REDACTION_PROMPT: &str = "Rewrite the user's text exactly, replacing personal details with placeholders: \
+[NAME] for people's full names, [EMAIL], [PHONE], [ADDRESS] for street addresses, [CARD] for payment card \
+numbers and [ID] for account, insurance or ID numbers. Change nothing else. Reply with the rewritten text only.";
+
+/// Which kinds of personal detail are replaced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub emails: bool,
+    pub phone_numbers: bool,
+    pub addresses: bool,
+    pub credit_cards: bool,
+    /// Also have the chat model rewrite the message (slower)
+    pub llm: bool,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            emails: true,
+            phone_numbers: true,
+            addresses: true,
+            credit_cards: true,
+            llm: false,
+        }
+    }
+}
+
+fn email_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b").unwrap())
+}
+
+/// 13 to 19 digits, optionally grouped with spaces or dashes
+fn card_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap())
+}
+
+/// North American numbers, and international ones written with a `+`
+fn phone_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?:\+1[\s.-]?)?(?:\(\d{3}\)\s?|\b\d{3}[\s.-])\d{3}[\s.-]\d{4}\b|\+\d{1,3}(?:[\s.-]?\d{2,4}){2,4}\b")
+            .unwrap()
+    })
+}
+
+/// A house number, up to three words and a street type
+fn address_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"\b\d{1,5}\s+(?:[A-Z][A-Za-z]*\.?\s+){1,3}(?i:street|st|avenue|ave|road|rd|boulevard|blvd|lane|ln|drive|dr|court|ct|way|place|pl|terrace|circle|highway|hwy)\b",
+        )
+        .unwrap()
+    })
+}
+
+/// Whether `digits` pass the Luhn checksum every card number carries
+fn luhn_valid(digits: &str) -> bool {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| match (i % 2 == 1, digit * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => digit,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// `text` with the enabled categories replaced by placeholders
+pub fn redact(text: &str, settings: &RedactionSettings) -> String {
+    let mut text = text.to_string();
+    if settings.emails {
+        text = email_pattern().replace_all(&text, "[EMAIL]").into_owned();
+    }
+    // Before phone numbers, which would match part of a card number
+    if settings.credit_cards {
+        text = card_pattern()
+            .replace_all(&text, |captures: &regex::Captures| {
+                let digits: String = captures[0].chars().filter(char::is_ascii_digit).collect();
+                if luhn_valid(&digits) {
+                    "[CARD]".to_string()
+                } else {
+                    captures[0].to_string()
+                }
+            })
+            .into_owned();
+    }
+    if settings.phone_numbers {
+        text = phone_pattern().replace_all(&text, "[PHONE]").into_owned();
+    }
+    if settings.addresses {
+        text = address_pattern().replace_all(&text, "[ADDRESS]").into_owned();
+    }
+    text
+}
+
+/// Have `backend` rewrite `text` with personal details replaced
+pub fn redact_with_llm(backend: &mut dyn LlmBackend, text: &str) -> Result<String> {
+    let config = LlmConfig {
+        // Room for the whole message back
+        max_tokens: (text.len() / 3 + 32) as i32,
+        ..task_presets::config_for(Task::Extraction)
+    };
+    let request = GenerationRequest {
+        prompt: text,
+        system_prompt: REDACTION_PROMPT,
+        history: &[],
+        config: &config,
+    };
+    let rewritten = backend.generate(&request, &CancellationToken::new(), &mut |_| true)?.text;
+    let rewritten = rewritten.trim();
+    // Placeholders shorten the text a little; losing most of it means the
+    // model answered instead of rewriting
+    if rewritten.is_empty() || rewritten.len() < text.len() / 2 {
+        return Err(anyhow!("The model didn't return the rewritten message"));
+    }
+    Ok(rewritten.to_string())
+}
+
+/// `text` as it may be remembered, per the redaction settings
+pub fn scrub(state: &AppState, text: &str) -> String {
+    scrub_with(&state.llm, text)
+}
+
+/// `scrub` for background work that holds the shared backend, not the state
+pub fn scrub_with(llm: &SharedBackend, text: &str) -> String {
+    let settings = AppSettings::load().redaction;
+    let redacted = redact(text, &settings);
+    if redacted != text {
+        info!("Redacted personal details from a message");
+    }
+    if !settings.llm {
+        return redacted;
+    }
+    // Never wait for a reply that is still generating
+    let Ok(mut llm) = llm.try_lock() else {
+        return redacted;
+    };
+    let Some(backend) = llm.as_mut() else {
+        return redacted;
+    };
+    match tokio::task::block_in_place(|| redact_with_llm(backend.as_mut(), &redacted)) {
+        Ok(rewritten) => rewritten,
+        Err(e) => {
+            warn!("Model redaction skipped: {:#}", e);
+            redacted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_categories() {
+        let settings = RedactionSettings::default();
+        let text = "Email jane.doe@example.com or call (555) 123-4567 / +44 20 7946 0958. \
+                    I live at 221 Baker Street. Card 4111 1111 1111 1111, order 1234 5678 9012 3456.";
+        let redacted = redact(text, &settings);
+        assert_eq!(
+            redacted,
+            "Email [EMAIL] or call [PHONE] / [PHONE]. I live at [ADDRESS]. Card [CARD], order 1234 5678 9012 3456."
+        );
+        // Dates, doses and plain numbers are left alone
+        let medical = "Started 50 mg metformin on 2024-03-15, A1C was 7.2";
+        assert_eq!(redact(medical, &settings), medical);
+
+        let emails_only = RedactionSettings {
+            phone_numbers: false,
+            addresses: false,
+            credit_cards: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            redact("me@example.org, 555-123-4567", &emails_only),
+            "[EMAIL], 555-123-4567"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+// Rolling Summary Module - Running summary of a conversation's older turns
+//
+// Once the turns not yet summarized pass `TRIGGER_TOKENS`, all but the most
+// recent `KEEP_RECENT_TOKENS` of them are folded into the session's running
+// summary (by the chat backend with the summarization preset, else
+// extractively). Prompts then carry the summary in the system prompt and only
+// the turns after it, while the visible history stays untouched.
+//
+// The summary remembers the last entry it covers; if that entry is no longer
+// in the history (trimmed, edited), the summary is ignored until the next
+// pass rebuilds it.
+
+use crate::backend::SharedBackend;
+use crate::compaction::extractive_summary;
+use crate::embeddings::{fnv1a, HashingEmbedder};
+use crate::generation::GenerationTracker;
+use crate::task_presets::{self, Task};
+use crate::tokenizer::count_tokens;
+use crate::{context_window, encryption, paths, AppState, ConversationEntry};
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Unsummarized history size that triggers a pass
+pub const TRIGGER_TOKENS: usize = 2048;
+
+/// Most recent history always sent verbatim
+pub const KEEP_RECENT_TOKENS: usize = 1024;
+
+/// Guards against overlapping passes
+static SUMMARIZING: AtomicBool = AtomicBool::new(false);
+
+/// Identifies a history entry without storing its text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryKey {
+    pub role: String,
+    pub timestamp: String,
+    pub content_hash: u64,
+}
+
+impl EntryKey {
+    pub fn of(entry: &ConversationEntry) -> Self {
+        Self {
+            role: entry.role.clone(),
+            timestamp: entry.timestamp.clone(),
+            content_hash: fnv1a(entry.content.as_bytes()),
+        }
+    }
+}
+
+/// A session's running summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingSummary {
+    pub run_id: String,
+    pub text: String,
+    /// Messages folded in so far
+    pub covered_messages: usize,
+    /// Last entry the summary covers
+    pub covered_through: EntryKey,
+    pub updated_at: String,
+}
+
+impl RollingSummary {
+    /// Index of the first history entry after the summary, if it still
+    /// lines up with `history`
+    pub fn uncovered_start(&self, history: &[ConversationEntry]) -> Option<usize> {
+        history
+            .iter()
+            .rposition(|entry| EntryKey::of(entry) == self.covered_through)
+            .map(|i| i + 1)
+    }
+}
+
+/// Running summaries by run id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SummaryFile {
+    sessions: HashMap<String, RollingSummary>,
+}
+
+impl SummaryFile {
+    fn path() -> PathBuf {
+        paths::app_data_dir().join("rolling_summaries.json")
+    }
+
+    fn load() -> Self {
+        encryption::read_to_string(&Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        encryption::write(&path, serde_json::to_string_pretty(self)?.as_bytes())
+            .with_context(|| format!("Failed to save summaries to {}", path.display()))
+    }
+}
+
+/// The running summary of session `run_id`, if one has been written
+pub fn load(run_id: &str) -> Option<RollingSummary> {
+    SummaryFile::load().sessions.remove(run_id)
+}
+
+/// Drop the running summaries of `run_ids`; returns how many there were
+pub fn forget(run_ids: &[String]) -> Result<usize> {
+    let mut file = SummaryFile::load();
+    let before = file.sessions.len();
+    file.sessions.retain(|run_id, _| !run_ids.contains(run_id));
+    let removed = before - file.sessions.len();
+    if removed > 0 {
+        file.save()?;
+    }
+    Ok(removed)
+}
+
+/// What to send for `history`: the summary text (if it still applies) and
+/// the entries after it
+pub fn apply<'a>(
+    summary: Option<&'a RollingSummary>,
+    history: &'a [ConversationEntry],
+) -> (Option<&'a str>, &'a [ConversationEntry]) {
+    match summary.and_then(|summary| Some((summary, summary.uncovered_start(history)?))) {
+        Some((summary, start)) => (Some(summary.text.as_str()), &history[start..]),
+        None => (None, history),
+    }
+}
+
+/// `system_prompt` with the running summary appended
+pub fn with_summary(system_prompt: &str, summary: Option<&str>) -> String {
+    match summary {
+        Some(summary) => format!(
+            "{}\n\nSummary of the conversation so far:\n{}",
+            system_prompt, summary
+        ),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// How many of the unsummarized entries (given their token counts) to fold
+/// into the summary, once they have grown past the trigger
+pub fn summarize_count(token_counts: &[usize]) -> Option<usize> {
+    if token_counts.iter().sum::<usize>() <= TRIGGER_TOKENS {
+        return None;
+    }
+    let keep_from = context_window::first_kept_turn(0, token_counts, KEEP_RECENT_TOKENS);
+    (keep_from > 0).then_some(keep_from)
+}
+
+/// Fold `entries` into `previous`; extractively while a reply is generating
+fn summarize(
+    llm: &SharedBackend,
+    generation: &GenerationTracker,
+    previous: Option<&str>,
+    entries: &[ConversationEntry],
+) -> String {
+    let transcript = entries
+        .iter()
+        .map(|entry| format!("{}: {}", entry.role, entry.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Here is the summary of a conversation so far:\n{}\n\n\
+        Update it to also cover the messages below. Keep names, facts, decisions \
+        and open questions; at most eight sentences.\n\n{}",
+        previous.unwrap_or("(nothing yet)"),
+        transcript
+    );
+
+    let generated = if generation.is_active() {
+        None
+    } else {
+        let mut backend = llm.blocking_lock();
+        backend.as_mut().and_then(|backend| {
+            task_presets::generate(
+                backend.as_mut(),
+                Task::Summarization,
+                "You write concise, factual summaries.",
+                &prompt,
+            )
+            .ok()
+        })
+    };
+    let generated = generated
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty());
+    generated.unwrap_or_else(|| {
+        let extractive = extractive_summary(&entries.iter().collect::<Vec<_>>(), &HashingEmbedder::default());
+        match previous {
+            Some(previous) => format!("{} {}", previous, extractive),
+            None => extractive,
+        }
+    })
+}
+
+/// Update the running summary in the background if the unsummarized part
+/// of the history has grown past the trigger
+pub fn spawn_update(
+    history: Arc<Mutex<Vec<ConversationEntry>>>,
+    llm: SharedBackend,
+    generation: Arc<GenerationTracker>,
+    run_id: String,
+) {
+    let existing = load(&run_id);
+    let (previous, to_summarize) = {
+        let history = history.lock();
+        let (summary_text, uncovered) = apply(existing.as_ref(), &history);
+        // A summary that no longer lines up is rebuilt from the start
+        let previous = summary_text.and(existing.clone());
+        let counts: Vec<usize> = uncovered
+            .iter()
+            .map(|entry| count_tokens(&entry.content) + context_window::TURN_OVERHEAD)
+            .collect();
+        match summarize_count(&counts) {
+            Some(count) => (previous, uncovered[..count].to_vec()),
+            None => return,
+        }
+    };
+
+    if SUMMARIZING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        info!("Summarizing {} older messages...", to_summarize.len());
+        let text = summarize(&llm, &generation, previous.as_ref().map(|s| s.text.as_str()), &to_summarize);
+        let summary = RollingSummary {
+            run_id: run_id.clone(),
+            text,
+            covered_messages: previous.map(|s| s.covered_messages).unwrap_or(0) + to_summarize.len(),
+            covered_through: EntryKey::of(&to_summarize[to_summarize.len() - 1]),
+            updated_at: crate::clock::timestamp(),
+        };
+
+        let mut file = SummaryFile::load();
+        file.sessions.insert(run_id, summary);
+        if let Err(e) = file.save() {
+            warn!("Failed to persist conversation summary: {}", e);
+        }
+        SUMMARIZING.store(false, Ordering::SeqCst);
+    });
+}
+
+/// The current session's running summary, if one has been written
+#[tauri::command]
+pub async fn get_conversation_summary(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<RollingSummary>, String> {
+    let run_id = state.session.lock().run_id.clone();
+    Ok(load(&run_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStatus;
+
+    fn entry(role: &str, content: &str, timestamp: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: timestamp.to_string(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_summary_covers_older_turns() {
+        // Under the trigger: nothing to do
+        assert_eq!(summarize_count(&[500, 500, 500]), None);
+        // Over it: everything but the last ~1024 tokens
+        assert_eq!(summarize_count(&[900, 900, 600, 300, 300]), Some(3));
+
+        let history = vec![
+            entry("user", "Hi", "t1"),
+            entry("assistant", "Hello", "t1"),
+            entry("user", "Plan a trip", "t2"),
+        ];
+        let summary = RollingSummary {
+            run_id: "run".to_string(),
+            text: "Greetings".to_string(),
+            covered_messages: 2,
+            covered_through: EntryKey::of(&history[1]),
+            updated_at: String::new(),
+        };
+        assert_eq!(summary.uncovered_start(&history), Some(2));
+        // The covered entry is gone (e.g. trimmed): the summary no longer applies
+        assert_eq!(summary.uncovered_start(&history[2..]), None);
+    }
+}
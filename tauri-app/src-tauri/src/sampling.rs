@@ -0,0 +1,187 @@
+// Sampling Module - llama.cpp sampler chain built from `LlmConfig`
+//
+// Stages run in llama.cpp's usual order: penalties and DRY adjust the logits,
+// top-k/top-p/min-p/XTC trim the candidates, then temperature (dynamic when
+// `dynatemp_range` is set) and a seeded random pick. A temperature of zero
+// or below samples greedily. Unset options leave their stage out. A JSON
+// Schema in the config becomes a grammar stage ahead of all of them, so only
+// tokens that keep the reply valid JSON for it can be picked.
+
+use crate::{structured, LlmConfig};
+use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::sampling::LlamaSampler;
+use tracing::warn;
+
+/// Tokens looked back over by the repetition penalties
+const PENALTY_LAST_N: i32 = 64;
+
+/// DRY defaults (as in llama.cpp and the Python backend)
+const DRY_BASE: f32 = 1.75;
+const DRY_ALLOWED_LENGTH: i32 = 2;
+const DRY_SEQUENCE_BREAKERS: [&str; 4] = ["\n", ":", "\"", "*"];
+
+/// Probability a token must exceed to count as "top" for XTC
+const XTC_THRESHOLD: f32 = 0.1;
+
+/// One step of the sampler chain
+#[derive(Debug, Clone, PartialEq)]
+pub enum SamplerStage {
+    /// GBNF grammar the output must follow
+    Grammar(String),
+    Penalties {
+        repeat: f32,
+        frequency: f32,
+        presence: f32,
+    },
+    Dry {
+        multiplier: f32,
+    },
+    TopK(i32),
+    TopP(f32),
+    MinP(f32),
+    Xtc {
+        probability: f32,
+    },
+    Temperature {
+        temperature: f32,
+        /// Dynamic temperature range (0 for a fixed temperature)
+        range: f32,
+    },
+    /// Random pick from what's left
+    Distribution,
+    Greedy,
+}
+
+/// Stages `config` asks for, in order
+pub fn plan(config: &LlmConfig) -> Vec<SamplerStage> {
+    let mut stages = Vec::new();
+
+    if let Some(schema) = &config.json_schema {
+        match structured::to_gbnf(schema) {
+            Ok(grammar) => stages.push(SamplerStage::Grammar(grammar)),
+            Err(e) => warn!("Sampling without the JSON Schema: {}", e),
+        }
+    }
+    let repeat = config.repeat_penalty.unwrap_or(1.0);
+    let frequency = config.frequency_penalty.unwrap_or(0.0);
+    let presence = config.presence_penalty.unwrap_or(0.0);
+    if repeat != 1.0 || frequency != 0.0 || presence != 0.0 {
+        stages.push(SamplerStage::Penalties {
+            repeat,
+            frequency,
+            presence,
+        });
+    }
+    if let Some(multiplier) = config.dry_multiplier.filter(|m| *m > 0.0) {
+        stages.push(SamplerStage::Dry { multiplier });
+    }
+
+    if config.temperature <= 0.0 {
+        stages.push(SamplerStage::Greedy);
+        return stages;
+    }
+
+    if config.top_k > 0 {
+        stages.push(SamplerStage::TopK(config.top_k));
+    }
+    if config.top_p > 0.0 && config.top_p < 1.0 {
+        stages.push(SamplerStage::TopP(config.top_p));
+    }
+    if let Some(min_p) = config.min_p.filter(|p| *p > 0.0) {
+        stages.push(SamplerStage::MinP(min_p));
+    }
+    if let Some(probability) = config.xtc_probability.filter(|p| *p > 0.0) {
+        stages.push(SamplerStage::Xtc { probability });
+    }
+    stages.push(SamplerStage::Temperature {
+        temperature: config.temperature,
+        range: config.dynatemp_range.unwrap_or(0.0).max(0.0),
+    });
+    stages.push(SamplerStage::Distribution);
+    stages
+}
+
+/// A fresh seed for a request that didn't ask for one
+pub fn random_seed() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default()
+}
+
+/// Build the llama.cpp sampler chain for `config`
+pub fn build_sampler(config: &LlmConfig, model: &LlamaModel, seed: u32) -> LlamaSampler {
+    let samplers = plan(config).into_iter().map(|stage| match stage {
+        SamplerStage::Grammar(grammar) => LlamaSampler::grammar(model, &grammar, "root"),
+        SamplerStage::Penalties {
+            repeat,
+            frequency,
+            presence,
+        } => LlamaSampler::penalties(PENALTY_LAST_N, repeat, frequency, presence),
+        SamplerStage::Dry { multiplier } => LlamaSampler::dry(
+            model,
+            multiplier,
+            DRY_BASE,
+            DRY_ALLOWED_LENGTH,
+            -1, // whole context
+            DRY_SEQUENCE_BREAKERS,
+        ),
+        SamplerStage::TopK(k) => LlamaSampler::top_k(k),
+        SamplerStage::TopP(p) => LlamaSampler::top_p(p, 1),
+        SamplerStage::MinP(p) => LlamaSampler::min_p(p, 1),
+        SamplerStage::Xtc { probability } => LlamaSampler::xtc(probability, XTC_THRESHOLD, 1, seed),
+        SamplerStage::Temperature { temperature, range } if range > 0.0 => {
+            LlamaSampler::temp_ext(temperature, range, 1.0)
+        }
+        SamplerStage::Temperature { temperature, .. } => LlamaSampler::temp(temperature),
+        SamplerStage::Distribution => LlamaSampler::dist(seed),
+        SamplerStage::Greedy => LlamaSampler::greedy(),
+    });
+    LlamaSampler::chain_simple(samplers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_follows_config() {
+        let config = LlmConfig {
+            xtc_probability: Some(0.5),
+            dynatemp_range: Some(0.3),
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(&config),
+            vec![
+                SamplerStage::Penalties {
+                    repeat: 1.0,
+                    frequency: 0.2,
+                    presence: 0.1
+                },
+                SamplerStage::Dry { multiplier: 0.7 },
+                SamplerStage::TopK(40),
+                SamplerStage::TopP(0.95),
+                SamplerStage::MinP(0.05),
+                SamplerStage::Xtc { probability: 0.5 },
+                SamplerStage::Temperature {
+                    temperature: 0.7,
+                    range: 0.3
+                },
+                SamplerStage::Distribution,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_temperature_is_greedy() {
+        let config = LlmConfig {
+            temperature: 0.0,
+            frequency_penalty: None,
+            presence_penalty: None,
+            dry_multiplier: None,
+            ..Default::default()
+        };
+        assert_eq!(plan(&config), vec![SamplerStage::Greedy]);
+    }
+}
@@ -0,0 +1,256 @@
+// Sampling Presets Module - Named sampling settings to pick per conversation
+//
+// Four presets ship with the app (Precise, Balanced, Creative, Storyteller);
+// the user's own are saved in `sampling_presets.json`. A conversation that
+// selects a preset refers to it by id, so editing a saved preset changes
+// every conversation using it. If the preset is deleted those conversations
+// go back to their mode's sampling.
+
+use crate::conversations::{self, ConversationInfo};
+use crate::modes::unique_slug;
+use crate::presets::validate_sampling;
+use crate::{AppState, LlmConfig};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+const MAX_NAME_CHARS: usize = 64;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// Named sampling settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingPreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub config: LlmConfig,
+    /// Shipped with the app; can't be changed or deleted
+    #[serde(skip_deserializing)]
+    pub builtin: bool,
+}
+
+/// The presets that ship with the app
+pub fn builtin_presets() -> Vec<SamplingPreset> {
+    let preset = |id: &str, name: &str, description: &str, config: LlmConfig| SamplingPreset {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        config,
+        builtin: true,
+    };
+    vec![
+        preset(
+            "precise",
+            "Precise",
+            "Focused, factual answers with little variation",
+            LlmConfig {
+                temperature: 0.3,
+                top_p: 0.9,
+                top_k: 40,
+                min_p: Some(0.1),
+                frequency_penalty: Some(0.0),
+                presence_penalty: Some(0.0),
+                dry_multiplier: None,
+                max_tokens: 512,
+                ..Default::default()
+            },
+        ),
+        preset("balanced", "Balanced", "Natural conversation", LlmConfig::default()),
+        preset(
+            "creative",
+            "Creative",
+            "Varied wording and unexpected ideas",
+            LlmConfig {
+                temperature: 1.0,
+                top_k: 80,
+                frequency_penalty: Some(0.3),
+                presence_penalty: Some(0.3),
+                dry_multiplier: Some(0.8),
+                xtc_probability: Some(0.1),
+                ..Default::default()
+            },
+        ),
+        preset(
+            "storyteller",
+            "Storyteller",
+            "Long, vivid narration for stories and roleplay",
+            LlmConfig {
+                temperature: 0.9,
+                top_k: 100,
+                min_p: Some(0.03),
+                presence_penalty: Some(0.2),
+                dry_multiplier: Some(0.8),
+                dynatemp_range: Some(0.3),
+                max_tokens: 768,
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+/// The user's saved presets
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetLibrary {
+    presets: Vec<SamplingPreset>,
+}
+
+impl PresetLibrary {
+    const FILE: &'static str = "sampling_presets.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// Built-in presets, then the user's
+    pub fn all(&self) -> Vec<SamplingPreset> {
+        builtin_presets().into_iter().chain(self.presets.iter().cloned()).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<SamplingPreset> {
+        self.all().into_iter().find(|preset| preset.id == id)
+    }
+
+    /// Save a preset under `name`, replacing the user's preset of that name
+    pub fn put(&mut self, name: &str, description: &str, config: LlmConfig) -> Result<SamplingPreset> {
+        let name = name.trim();
+        let name_chars = name.chars().count();
+        if name_chars == 0 || name_chars > MAX_NAME_CHARS {
+            return Err(anyhow!("Preset names are 1 to {} characters", MAX_NAME_CHARS));
+        }
+        if description.chars().count() > MAX_DESCRIPTION_CHARS {
+            return Err(anyhow!("Preset descriptions are at most {} characters", MAX_DESCRIPTION_CHARS));
+        }
+        if builtin_presets().iter().any(|preset| preset.name.eq_ignore_ascii_case(name)) {
+            return Err(anyhow!("\"{}\" is a built-in preset; save under another name", name));
+        }
+        validate_sampling(&config)?;
+
+        if let Some(existing) = self.presets.iter_mut().find(|preset| preset.name.eq_ignore_ascii_case(name)) {
+            existing.description = description.trim().to_string();
+            existing.config = config;
+            return Ok(existing.clone());
+        }
+        let all = self.all();
+        let preset = SamplingPreset {
+            id: unique_slug(name, "preset", |id| all.iter().any(|preset| preset.id == id)),
+            name: name.to_string(),
+            description: description.trim().to_string(),
+            config,
+            builtin: false,
+        };
+        self.presets.push(preset.clone());
+        Ok(preset)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<SamplingPreset> {
+        if builtin_presets().iter().any(|preset| preset.id == id) {
+            return Err(anyhow!("Built-in presets can't be deleted"));
+        }
+        let index = self
+            .presets
+            .iter()
+            .position(|preset| preset.id == id)
+            .ok_or_else(|| anyhow!("No sampling preset {}", id))?;
+        Ok(self.presets.remove(index))
+    }
+}
+
+/// Sampling of the preset `id`, if it exists
+pub fn config(id: &str) -> Option<LlmConfig> {
+    PresetLibrary::load().get(id).map(|preset| preset.config)
+}
+
+/// Built-in and saved sampling presets
+#[tauri::command]
+pub async fn list_sampling_presets() -> Result<Vec<SamplingPreset>, String> {
+    Ok(PresetLibrary::load().all())
+}
+
+/// Save sampling settings as a named preset (replacing a saved preset with
+/// the same name)
+#[tauri::command]
+pub async fn save_sampling_preset(
+    name: String,
+    config: LlmConfig,
+    description: Option<String>,
+) -> Result<SamplingPreset, String> {
+    let mut library = PresetLibrary::load();
+    let preset = library
+        .put(&name, description.as_deref().unwrap_or_default(), config)
+        .map_err(|e| e.to_string())?;
+    library.save().map_err(|e| e.to_string())?;
+    info!("Saved sampling preset {} ({})", preset.name, preset.id);
+    Ok(preset)
+}
+
+/// Delete a saved preset; conversations using it go back to their mode's
+/// sampling
+#[tauri::command]
+pub async fn delete_sampling_preset(id: String) -> Result<(), String> {
+    let mut library = PresetLibrary::load();
+    let preset = library.remove(&id).map_err(|e| e.to_string())?;
+    library.save().map_err(|e| e.to_string())?;
+    info!("Deleted sampling preset {}", preset.name);
+    Ok(())
+}
+
+/// Use a preset's sampling in a conversation (the active one unless
+/// `conversation_id` is given); `None` goes back to the mode's sampling.
+/// Replaces any sampling the conversation was created with.
+#[tauri::command]
+pub async fn set_sampling_preset(
+    preset_id: Option<String>,
+    conversation_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    if let Some(id) = &preset_id {
+        PresetLibrary::load()
+            .get(id)
+            .ok_or_else(|| format!("No sampling preset {}", id))?;
+    }
+    let mut manager = state.conversations.lock();
+    let id = conversation_id.unwrap_or_else(|| manager.active_id().to_string());
+    manager.set_preset(&id, preset_id.clone()).map_err(|e| e.to_string())?;
+    info!("Conversation {} uses sampling preset {:?}", id, preset_id);
+    conversations::info(&manager, &id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_and_saved_presets() {
+        for preset in builtin_presets() {
+            validate_sampling(&preset.config).unwrap();
+        }
+
+        let mut library = PresetLibrary::default();
+        let config = LlmConfig {
+            temperature: 0.5,
+            ..Default::default()
+        };
+        let saved = library.put("Late Night", "", config.clone()).unwrap();
+        assert_eq!(saved.id, "late-night");
+        assert!(!saved.builtin);
+        assert_eq!(library.all().len(), 5);
+
+        // Same name replaces, a built-in name is refused
+        let replaced = library.put("late night", "Calmer", LlmConfig::default()).unwrap();
+        assert_eq!(replaced.id, "late-night");
+        assert_eq!(library.get("late-night").unwrap().config.temperature, 0.7);
+        assert!(library.put("Creative", "", config.clone()).is_err());
+        assert!(library.put("Too hot", "", LlmConfig { temperature: 5.0, ..config }).is_err());
+
+        assert!(library.remove("balanced").is_err());
+        library.remove("late-night").unwrap();
+        assert!(library.get("late-night").is_none());
+        assert!(library.get("storyteller").unwrap().builtin);
+    }
+}
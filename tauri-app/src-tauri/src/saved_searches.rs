@@ -0,0 +1,314 @@
+// Saved Searches Module - Named memory searches that can be re-run or watched
+//
+// A saved search is a query plus filters, stored in `saved_searches.json`.
+// Running one returns the current matches, flagging those added since it was
+// last run. Watched searches are re-evaluated in the background and the user
+// is notified (desktop notification + `saved-search-matches` event) when new
+// memories match.
+
+use crate::memory_policy::{current_scope, AccessScope};
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use crate::metadata_filter::MetadataFilter;
+use crate::AppState;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::Manager;
+use tracing::{info, warn};
+
+/// How often watched searches are re-evaluated
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Matches returned when the caller doesn't give a limit
+const DEFAULT_LIMIT: usize = 20;
+
+/// Filters of a saved search (all optional)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub agent_id: Option<String>,
+    pub run_id: Option<String>,
+    /// Memory kind, e.g. "message" or "document"
+    pub kind: Option<String>,
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// mem0-style expression, e.g. `{"score": {"gte": 3}}`
+    pub filter: Option<MetadataFilter>,
+}
+
+impl SearchFilters {
+    fn to_memory_filters(&self, access: Option<AccessScope>) -> MemoryFilters {
+        let mut metadata = self.metadata.clone();
+        if let Some(kind) = &self.kind {
+            metadata.insert("kind".to_string(), serde_json::json!(kind));
+        }
+        MemoryFilters {
+            agent_id: self.agent_id.clone(),
+            run_id: self.run_id.clone(),
+            metadata,
+            filter: self.filter.clone(),
+            access,
+            ..Default::default()
+        }
+    }
+}
+
+/// A named query over the memory store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub filters: SearchFilters,
+    /// Matches scoring below this similarity are ignored
+    #[serde(default = "default_min_score")]
+    pub min_score: f32,
+    /// Re-evaluate periodically and notify about new matches
+    #[serde(default)]
+    pub watch: bool,
+    pub created_at: DateTime<Utc>,
+    /// When the search was last run (manually or by the watcher)
+    #[serde(default)]
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+fn default_min_score() -> f32 {
+    0.3
+}
+
+/// One memory matching a saved search
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub memory: MemoryItem,
+    pub score: f32,
+    /// Added since the search was last run
+    pub new: bool,
+    /// When the memory was added, e.g. "3 days ago"
+    pub relative_time: String,
+}
+
+/// Result of running a saved search
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSearchResult {
+    pub search: SavedSearch,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// All saved searches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSearches {
+    pub searches: Vec<SavedSearch>,
+}
+
+impl SavedSearches {
+    const FILE: &'static str = "saved_searches.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut SavedSearch> {
+        self.searches
+            .iter_mut()
+            .find(|search| search.id == id)
+            .ok_or_else(|| anyhow!("No saved search {}", id))
+    }
+}
+
+/// Current matches of `search`, best first; `new` marks memories created
+/// after `last_checked`
+pub fn evaluate(
+    store: &MemoryStore,
+    search: &SavedSearch,
+    access: Option<AccessScope>,
+    limit: usize,
+) -> Vec<SearchMatch> {
+    let filters = search.filters.to_memory_filters(access);
+    let now = crate::clock::now();
+    store
+        .search_scored(&search.query, Some(&filters), limit)
+        .into_iter()
+        .filter(|(_, score)| *score >= search.min_score)
+        .map(|(memory, score)| {
+            let created = DateTime::<Utc>::from(memory.created_at);
+            SearchMatch {
+                new: search.last_checked.map(|checked| created > checked).unwrap_or(true),
+                relative_time: crate::clock::relative(created, now),
+                memory,
+                score,
+            }
+        })
+        .collect()
+}
+
+/// Run a saved search and record the run
+fn run(state: &AppState, id: &str, limit: usize) -> Result<SavedSearchResult> {
+    let mut saved = SavedSearches::load();
+    let search = saved.get_mut(id)?;
+    let access = current_scope(state);
+    let matches = evaluate(&state.memory_store.lock(), search, Some(access), limit);
+    search.last_checked = Some(crate::clock::now());
+    let search = search.clone();
+    saved.save()?;
+
+    Ok(SavedSearchResult { search, matches })
+}
+
+/// Re-run watched searches and announce any new matches
+fn check_watched(app: &tauri::AppHandle) -> Result<()> {
+    let state = app.state::<AppState>();
+    let ids: Vec<String> = SavedSearches::load()
+        .searches
+        .into_iter()
+        .filter(|search| search.watch)
+        .map(|search| search.id)
+        .collect();
+
+    for id in ids {
+        let mut result = run(&state, &id, DEFAULT_LIMIT)?;
+        result.matches.retain(|m| m.new);
+        if !result.matches.is_empty() {
+            info!("{} new match(es) for \"{}\"", result.matches.len(), result.search.name);
+            notify(app, &result);
+        }
+    }
+    Ok(())
+}
+
+fn notify(app: &tauri::AppHandle, result: &SavedSearchResult) {
+    let identifier = app.config().tauri.bundle.identifier.clone();
+    if let Err(e) = tauri::api::notification::Notification::new(identifier)
+        .title(format!("New matches for \"{}\"", result.search.name))
+        .body(format!("{} new memories match this search", result.matches.len()))
+        .show()
+    {
+        warn!("Failed to show saved search notification: {}", e);
+    }
+    if let Err(e) = app.emit_all("saved-search-matches", result) {
+        warn!("Failed to emit saved search event: {}", e);
+    }
+}
+
+/// Check watched searches every few minutes
+pub fn spawn_watcher(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        if let Err(e) = check_watched(&app) {
+            warn!("Saved search check failed: {}", e);
+        }
+    });
+}
+
+/// Save a named search; saving under an existing name replaces that search
+#[tauri::command]
+pub async fn save_search(
+    name: String,
+    query: String,
+    filters: Option<SearchFilters>,
+    watch: Option<bool>,
+    min_score: Option<f32>,
+) -> Result<SavedSearch, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() || query.trim().is_empty() {
+        return Err("A saved search needs a name and a query".to_string());
+    }
+
+    let mut saved = SavedSearches::load();
+    let existing = saved.searches.iter().position(|search| search.name == name);
+    let search = SavedSearch {
+        id: existing
+            .map(|i| saved.searches[i].id.clone())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name,
+        query,
+        filters: filters.unwrap_or_default(),
+        min_score: min_score.unwrap_or_else(default_min_score),
+        watch: watch.unwrap_or(false),
+        created_at: crate::clock::now(),
+        // Only memories added from now on count as new
+        last_checked: Some(crate::clock::now()),
+    };
+    match existing {
+        Some(i) => saved.searches[i] = search.clone(),
+        None => saved.searches.push(search.clone()),
+    }
+    saved.save().map_err(|e| e.to_string())?;
+
+    Ok(search)
+}
+
+#[tauri::command]
+pub async fn list_saved_searches() -> Result<Vec<SavedSearch>, String> {
+    Ok(SavedSearches::load().searches)
+}
+
+/// Current matches of a saved search, with those added since its last run
+/// marked `new`
+#[tauri::command]
+pub async fn run_saved_search(
+    id: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<SavedSearchResult, String> {
+    run(&state, &id, limit.unwrap_or(DEFAULT_LIMIT)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(id: String) -> Result<(), String> {
+    let mut saved = SavedSearches::load();
+    let before = saved.searches.len();
+    saved.searches.retain(|search| search.id != id);
+    if saved.searches.len() == before {
+        return Err(format!("No saved search {}", id));
+    }
+    saved.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_flags_new_matches() {
+        let mut store = MemoryStore::new();
+        let mut metadata = HashMap::new();
+        metadata.insert("kind".to_string(), serde_json::json!("message"));
+        store.add("Planning the trip to Kyoto", None, Some("companion".to_string()), None, metadata.clone()).unwrap();
+        store.add("Kyoto notes from the guidebook", None, None, None, HashMap::new()).unwrap();
+
+        let mut search = SavedSearch {
+            id: "s1".to_string(),
+            name: "Kyoto".to_string(),
+            query: "kyoto".to_string(),
+            filters: SearchFilters {
+                kind: Some("message".to_string()),
+                ..Default::default()
+            },
+            min_score: default_min_score(),
+            watch: true,
+            created_at: crate::clock::now(),
+            last_checked: None,
+        };
+
+        let matches = evaluate(&store, &search, None, 10);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].memory.content, "Planning the trip to Kyoto");
+        assert!(matches[0].new);
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        search.last_checked = Some(crate::clock::now());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(!evaluate(&store, &search, None, 10)[0].new);
+
+        store.add("Kyoto hotel booked", None, None, None, metadata).unwrap();
+        let matches = evaluate(&store, &search, None, 10);
+        assert_eq!(matches.iter().filter(|m| m.new).count(), 1);
+    }
+}
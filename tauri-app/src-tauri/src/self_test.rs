@@ -0,0 +1,278 @@
+// Self Test Module - End-to-end checks of each subsystem with toy data
+//
+// `run_self_test` runs every check in turn and reports pass/fail per
+// component, so a broken update shows which part broke. The checks use
+// scratch stores in a temporary directory and never touch the user's data;
+// only the embedder and the chat backend are the app's own. The generation
+// check is skipped while a reply is streaming. A check that panics fails
+// instead of taking the rest down.
+
+use crate::backend::{self, GenerationRequest, LlmBackend};
+use crate::embeddings::{cosine_similarity, Embedder, HashingEmbedder};
+use crate::generation::CancellationToken;
+use crate::history_store::HistoryStore;
+use crate::memory_store::MemoryStore;
+use crate::settings::AppSettings;
+use crate::text_chunker::TextChunker;
+use crate::vector_index::VectorIndex;
+use crate::{clock, tokenizer, AppState, ConversationEntry, EntryStatus, LlmConfig};
+use anyhow::{anyhow, ensure, Result};
+use tokio::sync::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::info;
+
+/// Toy document for the chunk → embed → index → retrieve check
+const SAMPLE_DOCUMENT: &str = "The lighthouse keeper rows out every Tuesday to fetch supplies. \
+    Her garden grows tomatoes, basil and a stubborn fig tree. \
+    In winter the storms cut the island off for weeks at a time. \
+    She keeps a logbook of every ship that passes the point.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Couldn't run right now (e.g. a reply is being generated)
+    Skipped,
+}
+
+/// Outcome of one component's check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub component: &'static str,
+    pub status: CheckStatus,
+    /// What was verified, or what went wrong
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// No check failed (skipped ones don't count)
+    pub passed: bool,
+    pub ran_at: String,
+    pub results: Vec<CheckResult>,
+}
+
+fn run(component: &'static str, check: impl FnOnce() -> Result<String>) -> CheckResult {
+    let started = Instant::now();
+    let (status, detail) = match catch_unwind(AssertUnwindSafe(check)) {
+        Ok(Ok(detail)) => (CheckStatus::Pass, detail),
+        Ok(Err(e)) => (CheckStatus::Fail, format!("{:#}", e)),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            (CheckStatus::Fail, format!("panicked: {}", message))
+        }
+    };
+    CheckResult {
+        component,
+        status,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn chunking() -> Result<String> {
+    let chunks = TextChunker::new().chunk_text(SAMPLE_DOCUMENT);
+    ensure!(!chunks.is_empty(), "no chunks produced");
+    ensure!(
+        chunks.iter().any(|chunk| chunk.contains("fig tree")),
+        "chunks lost part of the text"
+    );
+    Ok(format!("{} chunk(s)", chunks.len()))
+}
+
+fn embeddings(embedder: &dyn Embedder) -> Result<String> {
+    let vectors = embedder.embed_batch(&["tomatoes in the garden", "a garden of tomatoes", "quarterly tax filing"]);
+    ensure!(
+        vectors.iter().all(|vector| vector.len() == embedder.dimensions()),
+        "vectors don't have {} dimensions",
+        embedder.dimensions()
+    );
+    ensure!(vectors.iter().flatten().all(|x| x.is_finite()), "vector has NaN or infinite values");
+    let similar = cosine_similarity(&vectors[0], &vectors[1]);
+    let unrelated = cosine_similarity(&vectors[0], &vectors[2]);
+    ensure!(similar > unrelated, "similar texts aren't closer ({:.2} vs {:.2})", similar, unrelated);
+    Ok(format!("{} ({} dimensions)", embedder.name(), embedder.dimensions()))
+}
+
+/// Chunk, embed and store the sample, then find the right chunk
+fn retrieval(embedder: Arc<dyn Embedder>) -> Result<String> {
+    let chunker = TextChunker::new();
+    let mut store = MemoryStore::new().with_embedder(embedder);
+    for chunk in chunker.chunk_text(SAMPLE_DOCUMENT) {
+        store.add(chunk, None, None, None, HashMap::new())?;
+    }
+    let results = store.search_scored("what grows in her garden", None, 1);
+    let (top, score) = results.first().ok_or_else(|| anyhow!("nothing retrieved"))?;
+    ensure!(top.content.contains("tomatoes"), "retrieved the wrong chunk: {:?}", top.content);
+    Ok(format!("top match scored {:.2}", score))
+}
+
+fn vector_index() -> Result<String> {
+    let embedder = HashingEmbedder::default();
+    let mut index = VectorIndex::new(embedder.dimensions());
+    let texts = ["red apples", "blue ocean waves", "mountain hiking trail", "apple pie recipe"];
+    for (i, text) in texts.iter().enumerate() {
+        ensure!(index.insert(&i.to_string(), &embedder.embed(text)), "insert {} was refused", i);
+    }
+    let hits = index.search(&embedder.embed("blue ocean waves"), 2);
+    ensure!(hits.first().is_some_and(|(id, _)| id == "1"), "nearest neighbour isn't the query itself");
+    Ok(format!("{} vectors", texts.len()))
+}
+
+fn database(dir: &Path) -> Result<String> {
+    let path = dir.join("memories.db");
+    let id = {
+        let mut store = MemoryStore::open_sqlite(&path)?;
+        store.add("Self test memory", None, None, None, HashMap::new())?
+    };
+    let reopened = MemoryStore::open_sqlite(&path)?;
+    let memory = reopened.get(&id).ok_or_else(|| anyhow!("memory missing after reopening"))?;
+    ensure!(memory.content == "Self test memory", "memory came back changed");
+    Ok("write, reopen and read".to_string())
+}
+
+fn history(dir: &Path) -> Result<String> {
+    let store = HistoryStore::open(dir.join("history"))?;
+    let entry = ConversationEntry {
+        role: "user".to_string(),
+        content: "Self test message".to_string(),
+        timestamp: clock::timestamp(),
+        quality_score: None,
+        status: EntryStatus::Complete,
+        tool_calls: Vec::new(),
+        parts: Vec::new(),
+    };
+    store.save(&[entry])?;
+    let loaded = store.load()?;
+    ensure!(
+        loaded.len() == 1 && loaded[0].content == "Self test message",
+        "history didn't round-trip"
+    );
+    Ok("save and load".to_string())
+}
+
+fn tokens() -> Result<String> {
+    let count = tokenizer::count_tokens("The quick brown fox jumps over the lazy dog.");
+    ensure!((2..=40).contains(&count), "implausible token count {}", count);
+    let source = if tokenizer::default_tokenizer().is_some() { "model vocabulary" } else { "estimate" };
+    Ok(format!("{} tokens ({})", count, source))
+}
+
+fn settings() -> Result<String> {
+    AppSettings::load().validate()?;
+    Ok("settings.toml is valid".to_string())
+}
+
+/// A few tokens from the chat backend
+fn generation(llm: &mut Option<Box<dyn LlmBackend>>) -> Result<String> {
+    let active = llm.get_or_insert_with(|| backend::select_backend(&|_| {}));
+    let config = LlmConfig {
+        temperature: 0.0,
+        max_tokens: 8,
+        ..Default::default()
+    };
+    let request = GenerationRequest {
+        prompt: "Reply with the single word OK.",
+        system_prompt: "You are a test harness.",
+        history: &[],
+        config: &config,
+    };
+    let completion = active.generate(&request, &CancellationToken::new(), &mut |_| true)?;
+    ensure!(!completion.text.trim().is_empty(), "{} returned no text", active.name());
+    Ok(format!("{} replied {:?}", active.name(), completion.text.trim()))
+}
+
+fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("auranexus_selftest_{}", uuid::Uuid::new_v4()))
+}
+
+/// Run every check with the app's embedder and chat backend
+pub fn run_all(embedder: Arc<dyn Embedder>, llm: &Mutex<Option<Box<dyn LlmBackend>>>) -> SelfTestReport {
+    let dir = scratch_dir();
+    let mut results = vec![
+        run("settings", settings),
+        run("chunking", chunking),
+        run("embeddings", || embeddings(embedder.as_ref())),
+        run("vector_index", vector_index),
+        run("retrieval", || retrieval(embedder.clone())),
+        run("tokenizer", tokens),
+        run("database", || {
+            std::fs::create_dir_all(&dir)?;
+            database(&dir)
+        }),
+        run("history", || history(&dir)),
+    ];
+    results.push(match llm.try_lock() {
+        Ok(mut llm) => run("generation", || generation(&mut llm)),
+        Err(_) => CheckResult {
+            component: "generation",
+            status: CheckStatus::Skipped,
+            detail: "a reply is being generated".to_string(),
+            duration_ms: 0,
+        },
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+
+    SelfTestReport {
+        passed: results.iter().all(|result| result.status != CheckStatus::Fail),
+        ran_at: clock::timestamp(),
+        results,
+    }
+}
+
+/// Check each subsystem end to end; see the report for which passed
+#[tauri::command]
+pub async fn run_self_test(state: tauri::State<'_, AppState>) -> Result<SelfTestReport, String> {
+    info!("Running self test");
+    let embedder = state.memory_store.lock().embedder();
+    let embedder = embedder.unwrap_or_else(|| Arc::new(HashingEmbedder::default()));
+    let llm = state.llm.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || run_all(embedder, &llm))
+        .await
+        .map_err(|e| e.to_string())?;
+    for result in &report.results {
+        let icon = match result.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Fail => "❌",
+            CheckStatus::Skipped => "⏭️",
+        };
+        info!("{} {}: {}", icon, result.component, result.detail);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_checks_pass() {
+        let dir = scratch_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let embedder: Arc<dyn Embedder> = Arc::new(HashingEmbedder::default());
+        chunking().unwrap();
+        embeddings(embedder.as_ref()).unwrap();
+        vector_index().unwrap();
+        retrieval(embedder).unwrap();
+        database(&dir).unwrap();
+        history(&dir).unwrap();
+        std::fs::remove_dir_all(dir).ok();
+
+        let failed = run("boom", || panic!("kaboom"));
+        assert_eq!(failed.status, CheckStatus::Fail);
+        assert!(failed.detail.contains("kaboom"));
+        let failed = run("broken", || Err(anyhow!("disk on fire")));
+        assert_eq!(failed.detail, "disk on fire");
+    }
+}
@@ -0,0 +1,186 @@
+// Sentence Segmenter Module - Sentence boundaries for text chunking
+//
+// Unicode mode uses UAX #29 sentence boundaries (which handle CJK and other
+// non-Latin terminators), then re-joins breaks that follow a known
+// abbreviation ("Dr.", "e.g.", "z.B.") or an initial ("J. Smith").
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Language whose abbreviation rules are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    /// Only the rules shared by all languages
+    Other,
+}
+
+/// Abbreviations recognised in every language (lowercase, with the final dot)
+const COMMON_ABBREVIATIONS: &[&str] = &[
+    "dr.", "prof.", "vs.", "e.g.", "i.e.", "cf.", "fig.", "no.", "nr.", "approx.", "ca.",
+];
+
+impl Language {
+    fn abbreviations(self) -> &'static [&'static str] {
+        match self {
+            Language::English => &[
+                "mr.", "mrs.", "ms.", "jr.", "sr.", "st.", "mt.", "inc.", "ltd.", "co.", "corp.",
+                "dept.", "est.", "jan.", "feb.", "mar.", "apr.", "jun.", "jul.", "aug.", "sep.",
+                "sept.", "oct.", "nov.", "dec.", "a.m.", "p.m.", "u.s.", "u.k.",
+            ],
+            Language::German => &[
+                "z.b.", "bzw.", "usw.", "vgl.", "d.h.", "u.a.", "hr.", "fr.", "str.", "evtl.",
+                "ggf.", "inkl.", "max.", "min.", "s.",
+            ],
+            Language::French => &[
+                "m.", "mme.", "mlle.", "mm.", "p.ex.", "env.", "av.", "bd.", "chap.", "p.",
+            ],
+            Language::Spanish => &[
+                "sr.", "sra.", "srta.", "ud.", "uds.", "p.ej.", "pág.", "dña.", "avda.",
+            ],
+            Language::Other => &[],
+        }
+    }
+}
+
+/// How sentence boundaries are found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segmentation {
+    /// Split after every `.!?。？！` (fast, but breaks on abbreviations)
+    Regex,
+    /// UAX #29 boundaries plus abbreviation rules for the language
+    Unicode(Language),
+}
+
+impl Default for Segmentation {
+    fn default() -> Self {
+        Segmentation::Unicode(Language::default())
+    }
+}
+
+/// Splits text into sentences
+pub struct SentenceSegmenter {
+    segmentation: Segmentation,
+    sentence_regex: Regex,
+}
+
+impl SentenceSegmenter {
+    pub fn new(segmentation: Segmentation) -> Self {
+        // Regex for sentence detection (supports multiple languages)
+        let sentence_regex = Regex::new(r"[^.!?。？！]+[.!?。？！]?").unwrap();
+
+        Self {
+            segmentation,
+            sentence_regex,
+        }
+    }
+
+    /// Split `text` into sentences, keeping their surrounding whitespace
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match self.segmentation {
+            Segmentation::Regex => self.sentence_regex.find_iter(text).map(|m| m.as_str()).collect(),
+            Segmentation::Unicode(language) => split_unicode(text, language),
+        }
+    }
+}
+
+fn split_unicode(text: &str, language: Language) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+
+    for piece in text.split_sentence_bounds() {
+        end += piece.len();
+        if end < text.len() && ends_with_abbreviation(&text[start..end], language) {
+            continue;
+        }
+        sentences.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+
+    sentences
+}
+
+/// Whether `sentence` ends in an abbreviation or initial rather than a full stop
+fn ends_with_abbreviation(sentence: &str, language: Language) -> bool {
+    let Some(word) = sentence.split_whitespace().last() else {
+        return false;
+    };
+    // Ignore opening brackets/quotes: "(e.g." or "«Dr."
+    let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+    if !word.ends_with('.') {
+        return false;
+    }
+
+    // A single capital letter is an initial ("J. R. R. Tolkien")
+    let mut letters = word.trim_end_matches('.').chars();
+    if let (Some(letter), None) = (letters.next(), letters.next()) {
+        if letter.is_uppercase() {
+            return true;
+        }
+    }
+
+    let word = word.to_lowercase();
+    COMMON_ABBREVIATIONS
+        .iter()
+        .chain(language.abbreviations())
+        .any(|abbreviation| *abbreviation == word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str, segmentation: Segmentation) -> Vec<String> {
+        SentenceSegmenter::new(segmentation)
+            .split(text)
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_abbreviations_do_not_end_sentences() {
+        let text = "Dr. Smith arrived late. He brought snacks, e.g. Crackers and cheese. Then he left.";
+        assert_eq!(
+            sentences(text, Segmentation::Unicode(Language::English)),
+            vec![
+                "Dr. Smith arrived late.",
+                "He brought snacks, e.g. Crackers and cheese.",
+                "Then he left.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_initials_and_language_rules() {
+        let english = "The book is by J. R. R. Tolkien. It is long.";
+        assert_eq!(
+            sentences(english, Segmentation::Unicode(Language::English)),
+            vec!["The book is by J. R. R. Tolkien.", "It is long."]
+        );
+
+        let german = "Wir brauchen Obst, z.B. Äpfel. Das reicht.";
+        assert_eq!(
+            sentences(german, Segmentation::Unicode(Language::German)),
+            vec!["Wir brauchen Obst, z.B. Äpfel.", "Das reicht."]
+        );
+    }
+
+    #[test]
+    fn test_cjk_terminators() {
+        let text = "今日は晴れです。明日は雨でしょう！本当ですか？";
+        assert_eq!(
+            sentences(text, Segmentation::Unicode(Language::Other)),
+            vec!["今日は晴れです。", "明日は雨でしょう！", "本当ですか？"]
+        );
+    }
+}
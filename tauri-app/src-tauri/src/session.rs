@@ -0,0 +1,375 @@
+// Session Module - mem0-style session identifiers
+//
+// Every write to `MemoryStore` is tagged with who it belongs to (`user_id`),
+// which persona produced it (`agent_id`), and which conversation run it came
+// from (`run_id`), so memories can be scoped per session.
+
+use crate::clock::{self, dated, Dated, SystemClock};
+use crate::history_store::{ArchivedSession, ArchivedSessionSummary};
+use crate::memory_policy::current_scope;
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryPage, PageRequest, RESERVED_METADATA_KEYS};
+use crate::metadata_filter::MetadataFilter;
+use crate::{AppState, ConversationEntry};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::SystemTime;
+use uuid::Uuid;
+use tracing::info;
+
+/// The single local user of a desktop install
+pub const LOCAL_USER_ID: &str = "local_user";
+
+/// Identifiers attached to everything written during a session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionIds {
+    pub user_id: String,
+    pub agent_id: String,
+    pub run_id: String,
+    /// Private documents are retrievable; never persisted, so a new session
+    /// or a restart starts locked
+    #[serde(skip)]
+    pub private_unlocked: bool,
+    /// Nothing from this session is written anywhere (see `incognito`)
+    #[serde(skip)]
+    pub incognito: bool,
+}
+
+impl SessionIds {
+    /// Start a new run for `agent_id` (e.g. the current mode)
+    pub fn new(agent_id: impl Into<String>) -> Self {
+        Self {
+            user_id: LOCAL_USER_ID.to_string(),
+            agent_id: agent_id.into(),
+            run_id: Uuid::new_v4().to_string(),
+            private_unlocked: false,
+            incognito: false,
+        }
+    }
+
+    /// Arguments for `MemoryStore::add`
+    pub fn memory_ids(&self) -> (Option<String>, Option<String>, Option<String>) {
+        (
+            Some(self.user_id.clone()),
+            Some(self.agent_id.clone()),
+            Some(self.run_id.clone()),
+        )
+    }
+
+    /// Filters matching only this run
+    pub fn run_filters(&self) -> MemoryFilters {
+        MemoryFilters {
+            user_id: Some(self.user_id.clone()),
+            run_id: Some(self.run_id.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Identifiers of the current session
+#[tauri::command]
+pub async fn get_session_info(state: tauri::State<'_, AppState>) -> Result<SessionIds, String> {
+    Ok(state.session.lock().clone())
+}
+
+fn created_at(memory: &MemoryItem) -> Option<DateTime<Utc>> {
+    Some(memory.created_at.into())
+}
+
+/// Memories written during a session (defaults to the current one), newest first
+#[tauri::command]
+pub async fn get_session_memories(
+    run_id: Option<String>,
+    limit: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Dated<MemoryItem>>, String> {
+    let mut filters = state.session.lock().run_filters();
+    if let Some(run_id) = run_id {
+        filters.run_id = Some(run_id);
+    }
+    filters.access = Some(current_scope(&state));
+
+    let memories = state.memory_store.lock().get_all(&filters, limit);
+    Ok(dated(memories, &SystemClock, created_at))
+}
+
+/// Search memories within a session (defaults to the current one)
+#[tauri::command]
+pub async fn search_session_memories(
+    query: String,
+    run_id: Option<String>,
+    limit: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Dated<MemoryItem>>, String> {
+    let mut filters = state.session.lock().run_filters();
+    if let Some(run_id) = run_id {
+        filters.run_id = Some(run_id);
+    }
+    filters.access = Some(current_scope(&state));
+
+    let memories = state.memory_store.lock().search(&query, Some(&filters), limit);
+    Ok(dated(memories, &SystemClock, created_at))
+}
+
+/// A page of memories for the memory viewer, with the total matching
+///
+/// Defaults to the current session; `all_sessions` browses every memory of
+/// the user. `filter` narrows it with a mem0-style expression.
+#[tauri::command]
+pub async fn browse_memories(
+    run_id: Option<String>,
+    all_sessions: Option<bool>,
+    filter: Option<MetadataFilter>,
+    page: Option<PageRequest>,
+    state: tauri::State<'_, AppState>,
+) -> Result<MemoryPage<Dated<MemoryItem>>, String> {
+    let mut filters = state.session.lock().run_filters();
+    if all_sessions.unwrap_or(false) {
+        filters.run_id = None;
+    } else if let Some(run_id) = run_id {
+        filters.run_id = Some(run_id);
+    }
+    filters.filter = filter;
+    filters.access = Some(current_scope(&state));
+
+    let page = state
+        .memory_store
+        .lock()
+        .get_page(&filters, &page.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    Ok(MemoryPage {
+        items: dated(page.items, &SystemClock, created_at),
+        total: page.total,
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// Set when a memory expires and is pruned, or keep it forever with `None`
+#[tauri::command]
+pub async fn set_memory_expiry(
+    memory_id: String,
+    expires_at: Option<DateTime<Utc>>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let scope = current_scope(&state);
+    let mut store = state.memory_store.lock();
+    if !store.get(&memory_id).is_some_and(|memory| scope.allows(&memory)) {
+        return Err(format!("Memory {} not found", memory_id));
+    }
+    store.set_expiry(&memory_id, expires_at.map(SystemTime::from));
+    info!("Memory {} expires {:?}", memory_id, expires_at);
+    Ok(())
+}
+
+/// Sessions archived by mode switches, newest first (cold ones on request)
+#[tauri::command]
+pub async fn list_archived_sessions(
+    include_cold: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Dated<ArchivedSessionSummary>>, String> {
+    let sessions = state.history_store.list_archived(include_cold.unwrap_or(false));
+    Ok(dated(sessions, &SystemClock, |session| clock::parse(&session.archived_at)))
+}
+
+/// Full transcript of an archived session
+#[tauri::command]
+pub async fn get_archived_session(
+    run_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ArchivedSession, String> {
+    state.history_store.load_archived(&run_id).map_err(|e| e.to_string())
+}
+
+/// Move a session out of cold storage back into the archive listing
+#[tauri::command]
+pub async fn unarchive_session(
+    run_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ArchivedSession, String> {
+    info!("Unarchiving session {}", run_id);
+    state.history_store.unarchive(&run_id).map_err(|e| e.to_string())
+}
+
+/// Interleave the entries of several sessions by timestamp
+///
+/// Each session keeps its own order; entries whose timestamp can't be read
+/// stay right after the entry before them.
+fn interleave(sessions: Vec<Vec<ConversationEntry>>) -> Vec<ConversationEntry> {
+    let time = |entry: &ConversationEntry| {
+        DateTime::parse_from_rfc3339(&entry.timestamp)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    };
+
+    let total = sessions.iter().map(Vec::len).sum();
+    let mut queues: Vec<_> = sessions.into_iter().map(|s| s.into_iter().peekable()).collect();
+    let mut merged = Vec::with_capacity(total);
+    while merged.len() < total {
+        let mut next: Option<(usize, Option<DateTime<Utc>>)> = None;
+        for (i, queue) in queues.iter_mut().enumerate() {
+            let Some(entry) = queue.peek() else { continue };
+            let t = time(entry);
+            let earlier = match (&next, t) {
+                (None, _) => true,
+                // Undated entries go out as soon as they're at the front
+                (Some(_), None) => true,
+                (Some((_, None)), _) => false,
+                (Some((_, Some(best))), Some(t)) => t < *best,
+            };
+            if earlier {
+                next = Some((i, t));
+                if t.is_none() {
+                    break;
+                }
+            }
+        }
+        let Some((i, _)) = next else { break };
+        merged.extend(queues[i].next());
+    }
+    merged
+}
+
+/// Merge sessions into a new archived session, interleaved by timestamp
+///
+/// Sources are left as they were. Their recorded messages are copied into the
+/// new run (with `merged_from`, keeping their embeddings) so the merged
+/// session can be searched and exported, and tagged with `merged_into`.
+pub fn merge(state: &AppState, ids: &[String]) -> Result<ArchivedSession> {
+    let mut unique: Vec<&String> = Vec::new();
+    for id in ids {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    if unique.len() < 2 {
+        return Err(anyhow!("Select at least two sessions to merge"));
+    }
+
+    let mut agent_id = None;
+    let mut sessions = Vec::new();
+    for id in &unique {
+        let (agent, entries) = match state.history_store.load_archived(id) {
+            Ok(archived) => (archived.session.agent_id, archived.entries),
+            Err(_) => {
+                let transcript = crate::html_export::load_transcript(state, id)?;
+                (transcript.mode, transcript.entries)
+            }
+        };
+        agent_id.get_or_insert(agent);
+        sessions.push(entries);
+    }
+
+    let merged = SessionIds::new(agent_id.unwrap_or_default());
+    let entries = interleave(sessions);
+    state.history_store.archive(&merged, &entries)?;
+
+    let mut store = state.memory_store.lock();
+    let mut copied = 0;
+    for id in &unique {
+        let mut filters = MemoryFilters {
+            run_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        filters
+            .metadata
+            .insert("kind".to_string(), serde_json::json!("message"));
+
+        for item in store.get_all(&filters, usize::MAX) {
+            let mut metadata = item.metadata.clone();
+            // The copy takes the merged run's ids
+            metadata.retain(|key, _| !RESERVED_METADATA_KEYS.contains(&key.as_str()));
+            metadata.insert("merged_from".to_string(), serde_json::json!(id));
+            let (user, agent, run) = merged.memory_ids();
+            match item.embedding {
+                Some(embedding) => {
+                    store.add_with_embedding(item.content, user, agent, run, metadata, embedding)?
+                }
+                None => store.add(item.content, user, agent, run, metadata)?,
+            };
+
+            let tag = HashMap::from([("merged_into".to_string(), serde_json::json!(merged.run_id))]);
+            store.update(&item.id, None, Some(tag))?;
+            copied += 1;
+        }
+    }
+    drop(store);
+
+    info!(
+        "Merged {} sessions into {} ({} entries, {} memories)",
+        unique.len(),
+        merged.run_id,
+        entries.len(),
+        copied
+    );
+    state.history_store.load_archived(&merged.run_id)
+}
+
+/// Merge sessions (archived or current) into a new archived session
+#[tauri::command]
+pub async fn merge_sessions(
+    ids: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ArchivedSession, String> {
+    merge(&state, &ids).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_store::MemoryStore;
+    use crate::EntryStatus;
+
+    #[test]
+    fn test_run_filters_scope_to_session() {
+        let mut store = MemoryStore::new();
+        let first = SessionIds::new("companion");
+        let second = SessionIds::new("companion");
+        assert_ne!(first.run_id, second.run_id);
+
+        let (user, agent, run) = first.memory_ids();
+        store.add("From the first run", user, agent, run, HashMap::new()).unwrap();
+        let (user, agent, run) = second.memory_ids();
+        store.add("From the second run", user, agent, run, HashMap::new()).unwrap();
+
+        let results = store.get_all(&first.run_filters(), 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].content, "From the first run");
+        assert_eq!(results[0].agent_id.as_deref(), Some("companion"));
+    }
+
+    fn entry(content: &str, timestamp: &str) -> ConversationEntry {
+        ConversationEntry {
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: timestamp.to_string(),
+            quality_score: None,
+            status: EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_interleave_by_timestamp() {
+        let main = vec![
+            entry("main 1", "2024-05-01T10:00:00Z"),
+            entry("main 2", "2024-05-01T10:05:00Z"),
+            entry("main 3", "2024-05-01T10:20:00Z"),
+        ];
+        let quick = vec![
+            entry("quick 1", "2024-05-01T12:10:00+02:00"),
+            entry("quick note", ""),
+            entry("quick 2", "2024-05-01T10:15:00Z"),
+        ];
+
+        let merged: Vec<String> = interleave(vec![main, quick])
+            .into_iter()
+            .map(|entry| entry.content)
+            .collect();
+        assert_eq!(
+            merged,
+            vec!["main 1", "main 2", "quick 1", "quick note", "quick 2", "main 3"]
+        );
+    }
+}
@@ -0,0 +1,502 @@
+// Settings Module - App-wide settings in settings.toml
+//
+// System prompts and sampling per mode, custom modes, how much history is
+// kept, where models are looked for and downloaded to, which backend answers
+// chat and how, global privacy mode, what is redacted from stored messages,
+// the tools, the MCP server, encryption at rest and the timezone times are
+// shown in. `settings.toml` in the app data directory can
+// be edited by hand; it is read when a setting is needed, so edits apply
+// without a restart. Missing keys use the built-in defaults, and a section
+// missing from the file is taken from the JSON file it used to live in
+// (`LEGACY_FILES`). Changes made through `update_settings` are announced
+// with a `settings-changed` event; secrets are never sent to the frontend.
+// Settings that belong to one feature and are edited through its own
+// commands keep a JSON file of their own, loaded and saved with
+// `load_json`/`save_json`.
+
+use crate::backend::BackendSettings;
+use crate::clock::UserTimezone;
+use crate::consolidation::ConsolidationSettings;
+use crate::encryption::EncryptionSettings;
+use crate::fact_extraction::ExtractionSettings;
+use crate::history_store::write_atomic;
+use crate::mcp_server::McpServerSettings;
+use crate::memory_store::RecencyDecay;
+use crate::modes::{ModeRegistry, MAX_PROMPT_CHARS};
+use crate::redaction::RedactionSettings;
+use crate::tools::ToolSettings;
+use crate::{paths, AppMode, AppState, LlmConfig};
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use tracing::{info, warn};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Sections of settings.toml that used to be JSON files of their own, with
+/// the key holding the section if it wasn't the whole file
+const LEGACY_FILES: &[(&str, &str, Option<&str>)] = &[
+    ("backend", "backend.json", None),
+    ("encryption", "encryption.json", None),
+    ("mcp_server", "mcp_server.json", None),
+    ("modes", "modes.json", Some("modes")),
+    ("tools", "tools.json", None),
+];
+
+/// Smallest working history that still holds a few exchanges
+const MIN_HISTORY_ENTRIES: usize = 10;
+
+/// Each variant is another search, and the model writes them all before the
+/// reply starts
+const MAX_QUERY_VARIANTS: usize = 6;
+
+/// Sampling for each mode's chat replies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingSettings {
+    pub companion: LlmConfig,
+    pub youniverse: LlmConfig,
+}
+
+impl Default for SamplingSettings {
+    fn default() -> Self {
+        Self {
+            companion: AppMode::Companion.default_sampling(),
+            youniverse: AppMode::Youniverse.default_sampling(),
+        }
+    }
+}
+
+impl SamplingSettings {
+    pub fn for_mode(&self, mode: &AppMode) -> &LlmConfig {
+        match mode {
+            // Custom modes keep their own sampling; a deleted one uses Companion's
+            AppMode::Companion | AppMode::Custom(_) => &self.companion,
+            AppMode::Youniverse => &self.youniverse,
+        }
+    }
+}
+
+/// System prompts replacing the built-in modes' own (unset keeps theirs)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptSettings {
+    pub companion: Option<String>,
+    pub youniverse: Option<String>,
+}
+
+impl PromptSettings {
+    /// The slot holding `mode`'s prompt; custom modes keep theirs in `modes`
+    pub fn for_mode_mut(&mut self, mode: &AppMode) -> Option<&mut Option<String>> {
+        match mode {
+            AppMode::Companion => Some(&mut self.companion),
+            AppMode::Youniverse => Some(&mut self.youniverse),
+            AppMode::Custom(_) => None,
+        }
+    }
+
+    pub fn for_mode(&self, mode: &AppMode) -> Option<&str> {
+        match mode {
+            AppMode::Companion => self.companion.as_deref(),
+            AppMode::Youniverse => self.youniverse.as_deref(),
+            AppMode::Custom(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Hard upper bound on entries kept in the working history
+    pub max_entries: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { max_entries: 200 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeSettings {
+    /// IANA timezone times are shown in, e.g. "Europe/Berlin" (the
+    /// system's when unset)
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSettings {
+    /// Where downloaded models go (the app data `models` folder when unset)
+    pub download_dir: Option<PathBuf>,
+    /// More folders searched for GGUF files
+    pub extra_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Every conversation runs in privacy mode (see `privacy`)
+    pub private_mode: bool,
+}
+
+/// Query fan-out before retrieval (see `query_fanout`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrievalSettings {
+    pub query_fanout: bool,
+    /// Reworded queries searched beside the message
+    pub query_variants: usize,
+    /// Share of a memory's search score that fades with age (0 turns decay off)
+    pub recency_weight: f32,
+    /// Days after its last update for a memory's recency to halve
+    pub recency_half_life_days: f32,
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self {
+            query_fanout: true,
+            query_variants: 3,
+            recency_weight: 0.2,
+            recency_half_life_days: 30.0,
+        }
+    }
+}
+
+impl RetrievalSettings {
+    /// Reworded queries to ask the model for (none with fan-out off)
+    pub fn variant_count(&self) -> usize {
+        if self.query_fanout {
+            self.query_variants
+        } else {
+            0
+        }
+    }
+
+    /// How search scores fade with age, if they do
+    pub fn recency_decay(&self) -> Option<RecencyDecay> {
+        (self.recency_weight > 0.0).then(|| RecencyDecay {
+            weight: self.recency_weight,
+            half_life_days: self.recency_half_life_days,
+        })
+    }
+}
+
+/// Everything in `settings.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    #[serde(deserialize_with = "crate::backend::deserialize_settings")]
+    pub backend: BackendSettings,
+    pub consolidation: ConsolidationSettings,
+    /// Changed only by `enable_encryption`/`disable_encryption`
+    pub encryption: EncryptionSettings,
+    pub extraction: ExtractionSettings,
+    pub history: HistorySettings,
+    pub mcp_server: McpServerSettings,
+    /// Custom modes
+    pub modes: ModeRegistry,
+    pub models: ModelSettings,
+    pub prompts: PromptSettings,
+    pub privacy: PrivacySettings,
+    pub redaction: RedactionSettings,
+    pub retrieval: RetrievalSettings,
+    pub sampling: SamplingSettings,
+    pub time: TimeSettings,
+    pub tools: ToolSettings,
+}
+
+impl AppSettings {
+    fn path() -> PathBuf {
+        paths::app_data_dir().join(SETTINGS_FILE)
+    }
+
+    /// Saved settings; an unreadable file falls back to the defaults
+    pub fn load() -> Self {
+        let text = std::fs::read_to_string(Self::path()).unwrap_or_default();
+        Self::from_toml_in(&text, &paths::app_data_dir()).unwrap_or_else(|e| {
+            warn!("Ignoring {}: {:#}", SETTINGS_FILE, e);
+            Self::default()
+        })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).context("Failed to parse settings TOML")
+    }
+
+    /// `from_toml`, with missing sections taken from the legacy files in `dir`
+    fn from_toml_in(text: &str, dir: &Path) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text).context("Failed to parse settings TOML")?;
+        merge_legacy_files(&mut table, dir);
+        toml::Value::Table(table).try_into().context("Failed to parse settings TOML")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).context("Failed to serialize settings")?;
+        write_atomic(&path, text.as_bytes())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        UserTimezone::parse(self.time.timezone.as_deref())?;
+        if self.retrieval.query_variants > MAX_QUERY_VARIANTS {
+            return Err(anyhow!("At most {} query variants", MAX_QUERY_VARIANTS));
+        }
+        if !(0.0..=1.0).contains(&self.retrieval.recency_weight) {
+            return Err(anyhow!("Recency weight must be between 0 and 1"));
+        }
+        if self.retrieval.recency_half_life_days <= 0.0 {
+            return Err(anyhow!("Recency half-life must be more than 0 days"));
+        }
+        if self.history.max_entries < MIN_HISTORY_ENTRIES {
+            return Err(anyhow!("History must keep at least {} entries", MIN_HISTORY_ENTRIES));
+        }
+        if self.mcp_server.port == 0 {
+            return Err(anyhow!("Pick a port for the MCP server"));
+        }
+        self.tools.web_search.validate()?;
+        self.tools.file_read.validate()?;
+        for prompt in [&self.prompts.companion, &self.prompts.youniverse].into_iter().flatten() {
+            if prompt.chars().count() > MAX_PROMPT_CHARS {
+                return Err(anyhow!("System prompts are limited to {} characters", MAX_PROMPT_CHARS));
+            }
+        }
+        for (mode, config) in [("companion", &self.sampling.companion), ("youniverse", &self.sampling.youniverse)] {
+            if !(0.0..=2.0).contains(&config.temperature) {
+                return Err(anyhow!("{} temperature must be between 0 and 2", mode));
+            }
+            if config.max_tokens < 1 {
+                return Err(anyhow!("{} max_tokens must be at least 1", mode));
+            }
+        }
+        Ok(())
+    }
+
+    /// These settings with the fields present in `patch` replaced
+    pub fn patched(&self, mut patch: serde_json::Value) -> Result<Self> {
+        // A bare backend kind changes only the kind
+        if let Some(kind) = patch.get("backend").filter(|backend| backend.is_string()).cloned() {
+            patch["backend"] = serde_json::json!({ "kind": kind });
+        }
+        let mut value = serde_json::to_value(self)?;
+        merge(&mut value, patch);
+        serde_json::from_value(value).context("Invalid settings")
+    }
+
+    /// These settings as the frontend sees them, without secrets
+    pub fn public(&self) -> Self {
+        let mut settings = self.clone();
+        settings.backend.remote.api_key = None;
+        settings.mcp_server.token.clear();
+        settings.tools.web_search.brave_api_key = None;
+        settings
+    }
+
+    /// These settings with the secrets and encryption state of `current`,
+    /// which only their own commands change
+    fn with_secrets_of(mut self, current: &Self) -> Self {
+        self.encryption = current.encryption.clone();
+        self.backend.remote.api_key = current.backend.remote.api_key.clone();
+        self.mcp_server.token = current.mcp_server.token.clone();
+        self.tools.web_search.brave_api_key = current.tools.web_search.brave_api_key.clone();
+        self
+    }
+}
+
+/// Add the sections missing from `table` from the `LEGACY_FILES` in `dir`
+///
+/// A backend given only as its kind (settings.toml before the rest of the
+/// backend moved in) is completed from `backend.json` too.
+fn merge_legacy_files(table: &mut toml::Table, dir: &Path) {
+    for (section, file, key) in LEGACY_FILES {
+        if table.get(*section).is_some_and(|value| !value.is_str()) {
+            continue;
+        }
+        let legacy = read_legacy(&dir.join(file));
+        let legacy = match key {
+            Some(key) => legacy.and_then(|mut legacy| legacy.as_table_mut()?.remove(*key)),
+            None => legacy,
+        };
+        let Some(mut legacy) = legacy else {
+            continue;
+        };
+        if let (Some(kind), Some(fields)) = (table.remove(*section), legacy.as_table_mut()) {
+            fields.insert("kind".to_string(), kind);
+        }
+        table.insert(section.to_string(), legacy);
+    }
+}
+
+/// A legacy JSON settings file as TOML, which has no nulls
+fn read_legacy(path: &Path) -> Option<toml::Value> {
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    toml::Value::try_from(without_nulls(json)).ok()
+}
+
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, without_nulls(value)))
+            .collect(),
+        serde_json::Value::Array(items) => items.into_iter().filter(|item| !item.is_null()).map(without_nulls).collect(),
+        other => other,
+    }
+}
+
+/// Settings kept in a JSON file of their own in the app data directory; a
+/// missing or unreadable file gives the defaults
+pub fn load_json<T: DeserializeOwned + Default>(file: &str) -> T {
+    std::fs::read_to_string(paths::app_data_dir().join(file))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Save settings loaded with `load_json`
+pub fn save_json<T: Serialize>(file: &str, value: &T) -> Result<()> {
+    let path = paths::app_data_dir().join(file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("Failed to save {}", path.display()))
+}
+
+/// Merge `patch` into `target`; objects merge key by key, anything else
+/// replaces
+fn merge(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Load, change and save the settings, returning the result
+pub fn update(change: impl FnOnce(&mut AppSettings)) -> Result<AppSettings> {
+    let mut settings = AppSettings::load();
+    change(&mut settings);
+    settings.save()?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_settings() -> Result<AppSettings, String> {
+    Ok(AppSettings::load().public())
+}
+
+/// Change some settings; `patch` holds only the fields to change, e.g.
+/// `{"history": {"max_entries": 300}}`. Emits `settings-changed` with the
+/// result.
+#[tauri::command]
+pub async fn update_settings(
+    patch: serde_json::Value,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<AppSettings, String> {
+    let current = AppSettings::load();
+    let settings = current
+        .patched(patch)
+        .map_err(|e| format!("{:#}", e))?
+        .with_secrets_of(&current);
+    settings.validate().map_err(|e| e.to_string())?;
+
+    let backend_changed = settings.backend != current.backend;
+    if backend_changed && state.generation.is_active() {
+        return Err("Can't change the backend while a reply is being generated".to_string());
+    }
+    settings.save().map_err(|e| e.to_string())?;
+    state
+        .memory_store
+        .lock()
+        .set_recency_decay(settings.retrieval.recency_decay());
+    if backend_changed {
+        *state.llm.lock().await = None;
+        info!("Backend set to {:?}", settings.backend.kind);
+    }
+
+    info!("Settings updated");
+    let settings = settings.public();
+    if let Err(e) = app.emit_all("settings-changed", &settings) {
+        warn!("Failed to announce settings change: {}", e);
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+
+    #[test]
+    fn test_partial_toml_and_patch() {
+        let settings = AppSettings::from_toml(
+            "backend = \"native\"\n\n[history]\nmax_entries = 50\n\n[sampling.youniverse]\ntemperature = 1.1\n",
+        )
+        .unwrap();
+        assert_eq!(settings.backend.kind, BackendKind::Native);
+        assert_eq!(settings.history.max_entries, 50);
+        assert_eq!(settings.sampling.youniverse.temperature, 1.1);
+        // Untouched sections keep their defaults
+        assert_eq!(settings.sampling.companion.max_tokens, 256);
+        assert!(settings.models.extra_dirs.is_empty());
+
+        // Round-trips through TOML
+        let text = toml::to_string_pretty(&settings).unwrap();
+        let reloaded = AppSettings::from_toml(&text).unwrap();
+        assert_eq!(reloaded.sampling.companion.top_k, 50);
+        assert_eq!(reloaded.sampling.youniverse.temperature, 1.1);
+
+        let patched = settings
+            .patched(serde_json::json!({"history": {"max_entries": 5}, "sampling": {"companion": {"top_k": 20}}}))
+            .unwrap();
+        assert_eq!(patched.sampling.companion.top_k, 20);
+        assert_eq!(patched.sampling.companion.max_tokens, 256);
+        assert_eq!(patched.backend.kind, BackendKind::Native);
+        assert!(patched.validate().is_err());
+        assert!(settings.patched(serde_json::json!({"backend": "carrier_pigeon"})).is_err());
+    }
+
+    #[test]
+    fn test_legacy_files_fill_missing_sections() {
+        let dir = std::env::temp_dir().join(format!("auranexus-settings-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("modes.json"), r#"{"modes": [{"id": "chef", "name": "Chef", "system_prompt": "Cook.", "created_at": "", "updated_at": ""}]}"#).unwrap();
+        std::fs::write(
+            dir.join("backend.json"),
+            r#"{"remote": {"base_url": "http://localhost:8080/v1", "api_key": "sk-1", "model": null}, "model_path": null}"#,
+        )
+        .unwrap();
+
+        // Only the kind in settings.toml: the rest comes from backend.json
+        let settings = AppSettings::from_toml_in("backend = \"remote\"\n", &dir).unwrap();
+        assert_eq!(settings.backend.kind, BackendKind::Remote);
+        assert_eq!(settings.backend.remote.base_url, "http://localhost:8080/v1");
+        assert_eq!(settings.backend.remote.api_key.as_deref(), Some("sk-1"));
+        assert_eq!(settings.public().backend.remote.api_key, None);
+        assert_eq!(settings.modes.get("chef").unwrap().name, "Chef");
+        assert_eq!(AppSettings::from_toml_in("", &dir).unwrap().backend.kind, BackendKind::Auto);
+
+        // A saved section wins over the old file
+        let text = toml::to_string_pretty(&AppSettings::default()).unwrap();
+        assert_eq!(AppSettings::from_toml_in(&text, &dir).unwrap().backend.remote.base_url, "");
+
+        // Secrets only change through their own commands
+        let patched = settings.patched(serde_json::json!({"backend": {"remote": {"api_key": "sk-2"}}})).unwrap();
+        assert_eq!(patched.with_secrets_of(&settings).backend.remote.api_key.as_deref(), Some("sk-1"));
+        assert_eq!(settings.patched(serde_json::json!({"backend": "native"})).unwrap().backend.remote.base_url, "http://localhost:8080/v1");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
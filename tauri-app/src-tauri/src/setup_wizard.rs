@@ -0,0 +1,383 @@
+// Setup Wizard Module - First-run onboarding flow
+//
+// Walks a new user through: hardware scan → model recommendation → model
+// download (with progress events) → optional Python backend detection →
+// persona selection. The model picked or downloaded becomes the chat model
+// (`model_path` in the backend settings). Progress is persisted so the wizard
+// resumes where the user left off and is skipped entirely once completed.
+
+use crate::backend::BackendSettings;
+use crate::downloader::{self, DownloadProgress};
+use crate::http_backend::HttpBackend;
+use crate::models::{self, ModelInfo};
+use crate::{apply_mode_switch, paths, AppMode, AppState};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+
+/// Payload of `model-download-progress` events
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDownloadProgress {
+    pub model_id: String,
+    #[serde(flatten)]
+    pub progress: DownloadProgress,
+}
+
+/// Wizard steps, in order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStep {
+    #[default]
+    Welcome,
+    Hardware,
+    Model,
+    Backend,
+    Persona,
+    Complete,
+}
+
+/// Hardware summary used for model recommendation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareInfo {
+    pub os: String,
+    pub cpu_threads: usize,
+    pub total_ram_bytes: u64,
+    pub available_ram_bytes: u64,
+}
+
+/// A curated starter model the wizard can download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarterModel {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub filename: String,
+    /// Approximate download size
+    pub size_bytes: u64,
+    /// Minimum system RAM for comfortable CPU inference
+    pub min_ram_bytes: u64,
+}
+
+/// Result of looking for the optional Python backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonBackendStatus {
+    /// llm_server.py answered its health check
+    pub server_reachable: bool,
+    /// Python interpreter found on PATH, if any
+    pub python_executable: Option<String>,
+    pub python_version: Option<String>,
+}
+
+/// Persisted wizard progress
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SetupState {
+    pub step: SetupStep,
+    pub completed: bool,
+    pub hardware: Option<HardwareInfo>,
+    pub recommended_model: Option<StarterModel>,
+    /// Models already present on disk (an existing model skips the download)
+    #[serde(default)]
+    pub existing_models: Vec<ModelInfo>,
+    /// Path of the model the user chose or downloaded
+    pub selected_model: Option<String>,
+    pub python_backend: Option<PythonBackendStatus>,
+    pub persona: Option<String>,
+}
+
+impl SetupState {
+    const FILE: &'static str = "setup.json";
+
+    /// Load saved progress (fresh state on first run)
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// Move forward to `step` (never backwards)
+    fn advance(&mut self, step: SetupStep) {
+        if step_index(step) > step_index(self.step) {
+            self.step = step;
+        }
+    }
+}
+
+fn step_index(step: SetupStep) -> usize {
+    match step {
+        SetupStep::Welcome => 0,
+        SetupStep::Hardware => 1,
+        SetupStep::Model => 2,
+        SetupStep::Backend => 3,
+        SetupStep::Persona => 4,
+        SetupStep::Complete => 5,
+    }
+}
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// Starter models, smallest first
+pub fn starter_models() -> Vec<StarterModel> {
+    vec![
+        StarterModel {
+            id: "qwen2.5-0.5b".to_string(),
+            name: "Qwen2.5 0.5B Instruct (Q4_K_M)".to_string(),
+            url: "https://huggingface.co/Qwen/Qwen2.5-0.5B-Instruct-GGUF/resolve/main/qwen2.5-0.5b-instruct-q4_k_m.gguf".to_string(),
+            filename: "qwen2.5-0.5b-instruct-q4_k_m.gguf".to_string(),
+            size_bytes: 491 * 1024 * 1024,
+            min_ram_bytes: 2 * GB,
+        },
+        StarterModel {
+            id: "qwen2.5-1.5b".to_string(),
+            name: "Qwen2.5 1.5B Instruct (Q4_K_M)".to_string(),
+            url: "https://huggingface.co/Qwen/Qwen2.5-1.5B-Instruct-GGUF/resolve/main/qwen2.5-1.5b-instruct-q4_k_m.gguf".to_string(),
+            filename: "qwen2.5-1.5b-instruct-q4_k_m.gguf".to_string(),
+            size_bytes: 1117 * 1024 * 1024,
+            min_ram_bytes: 6 * GB,
+        },
+        StarterModel {
+            id: "qwen2.5-3b".to_string(),
+            name: "Qwen2.5 3B Instruct (Q4_K_M)".to_string(),
+            url: "https://huggingface.co/Qwen/Qwen2.5-3B-Instruct-GGUF/resolve/main/qwen2.5-3b-instruct-q4_k_m.gguf".to_string(),
+            filename: "qwen2.5-3b-instruct-q4_k_m.gguf".to_string(),
+            size_bytes: 2104 * 1024 * 1024,
+            min_ram_bytes: 10 * GB,
+        },
+        StarterModel {
+            id: "qwen2.5-7b".to_string(),
+            name: "Qwen2.5 7B Instruct (Q4_K_M)".to_string(),
+            url: "https://huggingface.co/bartowski/Qwen2.5-7B-Instruct-GGUF/resolve/main/Qwen2.5-7B-Instruct-Q4_K_M.gguf".to_string(),
+            filename: "Qwen2.5-7B-Instruct-Q4_K_M.gguf".to_string(),
+            size_bytes: 4683 * 1024 * 1024,
+            min_ram_bytes: 16 * GB,
+        },
+    ]
+}
+
+/// Pick the largest starter model the machine can comfortably run
+pub fn recommend_model(hardware: &HardwareInfo) -> StarterModel {
+    let models = starter_models();
+    models
+        .iter()
+        .rev()
+        .find(|model| hardware.total_ram_bytes >= model.min_ram_bytes)
+        .unwrap_or(&models[0])
+        .clone()
+}
+
+/// Inspect CPU and memory
+pub fn scan_hardware() -> HardwareInfo {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+
+    HardwareInfo {
+        os: sysinfo::System::long_os_version().unwrap_or_else(|| std::env::consts::OS.to_string()),
+        cpu_threads: std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        total_ram_bytes: system.total_memory(),
+        available_ram_bytes: system.available_memory(),
+    }
+}
+
+/// Look for llm_server.py and a Python interpreter
+pub fn detect_python_backend() -> PythonBackendStatus {
+    let server_reachable = HttpBackend::local()
+        .map(|backend| backend.health())
+        .unwrap_or(false);
+
+    let mut status = PythonBackendStatus {
+        server_reachable,
+        python_executable: None,
+        python_version: None,
+    };
+
+    if let Some((executable, version)) = find_python() {
+        status.python_executable = Some(executable);
+        status.python_version = Some(version);
+    }
+
+    status
+}
+
+/// First Python interpreter on the PATH, with its version
+pub fn find_python() -> Option<(String, String)> {
+    ["python3", "python", "py"].into_iter().find_map(|candidate| {
+        let output = std::process::Command::new(candidate).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Older Pythons print the version to stderr
+        let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+        Some((candidate.to_string(), String::from_utf8_lossy(&text).trim().to_string()))
+    })
+}
+
+/// Current wizard progress
+#[tauri::command]
+pub async fn get_setup_state() -> Result<SetupState, String> {
+    Ok(SetupState::load())
+}
+
+/// Scan hardware and recommend a starter model
+#[tauri::command]
+pub async fn run_hardware_scan() -> Result<SetupState, String> {
+    let (hardware, existing_models) = tauri::async_runtime::spawn_blocking(|| {
+        (scan_hardware(), models::scan_all_model_locations().unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut state = SetupState::load();
+    state.recommended_model = Some(recommend_model(&hardware));
+    state.hardware = Some(hardware);
+    state.existing_models = existing_models;
+    state.advance(SetupStep::Model);
+    state.save().map_err(|e| e.to_string())?;
+
+    info!("Hardware scanned, recommended model: {:?}",
+        state.recommended_model.as_ref().map(|m| &m.name));
+    Ok(state)
+}
+
+/// Save `path` as the chat model, so the backends load it from now on
+fn use_model(path: &Path) -> Result<()> {
+    let mut settings = BackendSettings::load();
+    settings.model_path = Some(path.to_path_buf());
+    settings.save()
+}
+
+/// Use a model that is already on disk instead of downloading one
+#[tauri::command]
+pub async fn select_setup_model(model_path: String) -> Result<SetupState, String> {
+    if !Path::new(&model_path).is_file() {
+        return Err(format!("Model file not found: {}", model_path));
+    }
+    use_model(Path::new(&model_path)).map_err(|e| e.to_string())?;
+
+    let mut state = SetupState::load();
+    state.selected_model = Some(model_path);
+    state.advance(SetupStep::Backend);
+    state.save().map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Download a starter model, emitting `model-download-progress` events
+/// (bytes downloaded, total, speed and ETA)
+#[tauri::command]
+pub async fn download_setup_model(
+    model_id: String,
+    window: tauri::Window,
+) -> Result<SetupState, String> {
+    let model = starter_models()
+        .into_iter()
+        .find(|m| m.id == model_id)
+        .ok_or_else(|| format!("Unknown starter model: {}", model_id))?;
+
+    let dest = paths::models_dir().join(&model.filename);
+    info!("Downloading {} to {}", model.name, dest.display());
+
+    let download_dest = dest.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        downloader::download_file(&model.url, &download_dest, None, |progress: &DownloadProgress| {
+            let _ = window.emit("model-download-progress", ModelDownloadProgress {
+                model_id: model.id.clone(),
+                progress: progress.clone(),
+            });
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    use_model(&dest).map_err(|e| e.to_string())?;
+
+    let mut state = SetupState::load();
+    state.selected_model = Some(dest.to_string_lossy().to_string());
+    state.advance(SetupStep::Backend);
+    state.save().map_err(|e| e.to_string())?;
+
+    info!("Starter model downloaded");
+    Ok(state)
+}
+
+/// Check for the optional Python backend
+#[tauri::command]
+pub async fn detect_setup_backend() -> Result<SetupState, String> {
+    let status = tauri::async_runtime::spawn_blocking(detect_python_backend)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut state = SetupState::load();
+    state.python_backend = Some(status);
+    state.advance(SetupStep::Persona);
+    state.save().map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Choose the starting persona (companion or youniverse); it is switched to
+/// like any mode change, so the session and conversation follow it
+#[tauri::command]
+pub async fn select_setup_persona(
+    persona: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<SetupState, String> {
+    let mode = match persona.as_str() {
+        "companion" => AppMode::Companion,
+        "youniverse" => AppMode::Youniverse,
+        _ => return Err(format!("Unknown persona: {}", persona)),
+    };
+    let current = state.current_mode.lock().to_string();
+    if current != mode.to_string() {
+        apply_mode_switch(&state, mode, false);
+    }
+
+    let mut setup = SetupState::load();
+    setup.persona = Some(persona);
+    setup.advance(SetupStep::Complete);
+    setup.save().map_err(|e| e.to_string())?;
+    Ok(setup)
+}
+
+/// Finish onboarding so the wizard is not shown again
+#[tauri::command]
+pub async fn complete_setup() -> Result<SetupState, String> {
+    let mut state = SetupState::load();
+    state.completed = true;
+    state.step = SetupStep::Complete;
+    state.save().map_err(|e| e.to_string())?;
+
+    info!("Setup complete");
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hardware(ram_gb: u64) -> HardwareInfo {
+        HardwareInfo {
+            os: "test".to_string(),
+            cpu_threads: 8,
+            total_ram_bytes: ram_gb * GB,
+            available_ram_bytes: ram_gb * GB / 2,
+        }
+    }
+
+    #[test]
+    fn test_recommend_model_scales_with_ram() {
+        assert_eq!(recommend_model(&hardware(1)).id, "qwen2.5-0.5b");
+        assert_eq!(recommend_model(&hardware(8)).id, "qwen2.5-1.5b");
+        assert_eq!(recommend_model(&hardware(12)).id, "qwen2.5-3b");
+        assert_eq!(recommend_model(&hardware(64)).id, "qwen2.5-7b");
+    }
+
+    #[test]
+    fn test_advance_never_goes_backwards() {
+        let mut state = SetupState::default();
+        state.advance(SetupStep::Backend);
+        state.advance(SetupStep::Hardware);
+        assert_eq!(state.step, SetupStep::Backend);
+    }
+}
@@ -0,0 +1,347 @@
+// Share Module - Passphrase-encrypted conversation files (.aurachat)
+//
+// A shared conversation is one file another AuraNexus install can import:
+// the entries exactly as stored (Markdown, links/citations, typed parts) plus
+// the bytes of every attached image or file. The JSON payload is
+// zstd-compressed and sealed with ChaCha20-Poly1305 under a key derived from
+// the passphrase with Argon2id, so the file is useless without it. Only files
+// in the app's attachments directory are ever read into a share, and on
+// import parts that point anywhere but the imported copies are dropped.
+// Imported messages are added to memory redacted like live turns, and not at
+// all while `privacy.private_mode` is on.
+//
+// Layout: "AURACHAT" | version (1 byte) | salt (16) | nonce (12) | ciphertext
+
+use crate::content::ContentPart;
+use crate::html_export::load_transcript;
+use crate::history_store::ArchivedSession;
+use crate::redaction;
+use crate::session::SessionIds;
+use crate::settings::AppSettings;
+use crate::{paths, AppState, ConversationEntry};
+use anyhow::{anyhow, Context, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const MAGIC: &[u8; 8] = b"AURACHAT";
+const FILE_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Attachments larger than this are left out of the file
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// An attached file carried inside the share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedAttachment {
+    /// Path the parts referred to on the sharing install
+    pub original_path: String,
+    pub name: String,
+    /// File contents, base64
+    pub data: String,
+}
+
+/// Decrypted contents of a `.aurachat` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedConversation {
+    /// Mode/persona the conversation ran in
+    pub mode: String,
+    pub shared_at: String,
+    pub entries: Vec<ConversationEntry>,
+    #[serde(default)]
+    pub attachments: Vec<SharedAttachment>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext` under `passphrase` into the file layout
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(anyhow!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.push(FILE_VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt a sealed file; fails on a wrong passphrase or a damaged file
+pub fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if sealed.len() < HEADER_LEN || &sealed[..MAGIC.len()] != MAGIC {
+        return Err(anyhow!("Not an AuraNexus shared conversation"));
+    }
+    let version = sealed[MAGIC.len()];
+    if version != FILE_VERSION {
+        return Err(anyhow!("Unsupported shared conversation version {}", version));
+    }
+
+    let salt = &sealed[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &sealed[MAGIC.len() + 1 + SALT_LEN..HEADER_LEN];
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), &sealed[HEADER_LEN..])
+        .map_err(|_| anyhow!("Wrong passphrase or damaged file"))
+}
+
+/// Paths of the images and files attached to `entries`
+fn attachment_paths(entries: &[ConversationEntry]) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for part in entries.iter().flat_map(|entry| &entry.parts) {
+        let path = match part {
+            ContentPart::Image { path, .. } | ContentPart::File { path, .. } => path,
+            _ => continue,
+        };
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+fn read_attachment(path: &str) -> Option<SharedAttachment> {
+    let Some(file) = paths::resolve_attachment(Path::new(path)) else {
+        warn!("Leaving {} out of the share (not an app attachment)", path);
+        return None;
+    };
+    let size = std::fs::metadata(&file).ok()?.len();
+    if size > MAX_ATTACHMENT_BYTES {
+        warn!("Leaving {} out of the share ({} bytes)", path, size);
+        return None;
+    }
+    let bytes = std::fs::read(&file).ok()?;
+    Some(SharedAttachment {
+        original_path: path.to_string(),
+        name: file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".to_string()),
+        data: BASE64.encode(bytes),
+    })
+}
+
+/// Write session `session_id` to an encrypted `.aurachat` file at `path`
+pub fn share(state: &AppState, session_id: &str, passphrase: &str, path: &Path) -> Result<()> {
+    let transcript = load_transcript(state, session_id)?;
+    let attachments = attachment_paths(&transcript.entries)
+        .iter()
+        .filter_map(|path| read_attachment(path))
+        .collect();
+    let shared = SharedConversation {
+        mode: transcript.mode,
+        shared_at: crate::clock::timestamp(),
+        entries: transcript.entries,
+        attachments,
+    };
+
+    let json = serde_json::to_vec(&shared)?;
+    let compressed = zstd::encode_all(json.as_slice(), 3).context("Failed to compress conversation")?;
+    let sealed = seal(&compressed, passphrase)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, sealed).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Decrypt a `.aurachat` file
+pub fn read_shared(path: &Path, passphrase: &str) -> Result<SharedConversation> {
+    let sealed = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let compressed = open(&sealed, passphrase)?;
+    let json = zstd::decode_all(compressed.as_slice()).context("Failed to decompress conversation")?;
+    serde_json::from_slice(&json).context("Failed to parse shared conversation")
+}
+
+/// Point image and file parts at the imported copies in `moved`
+///
+/// Parts whose file wasn't carried are dropped: their paths are the
+/// sharer's, and could name any file on this machine. Returns how many.
+fn localize_parts(entries: &mut [ConversationEntry], moved: &HashMap<String, String>) -> usize {
+    let mut dropped = 0;
+    for entry in entries.iter_mut() {
+        entry.parts.retain_mut(|part| match part {
+            ContentPart::Image { path, .. } | ContentPart::File { path, .. } => match moved.get(path) {
+                Some(local) => {
+                    *path = local.clone();
+                    true
+                }
+                None => {
+                    dropped += 1;
+                    false
+                }
+            },
+            _ => true,
+        });
+    }
+    dropped
+}
+
+/// Import a shared conversation as a new archived session
+///
+/// Attachments are saved under `attachments/<run_id>/` and the entries'
+/// parts pointed at the copies; messages are recorded in the memory store
+/// so the conversation is searchable like any other.
+pub fn import(state: &AppState, shared: SharedConversation) -> Result<ArchivedSession> {
+    let session = SessionIds::new(shared.mode.clone());
+    let dir = paths::attachments_dir().join(&session.run_id);
+
+    let mut moved: HashMap<String, String> = HashMap::new();
+    for attachment in &shared.attachments {
+        let bytes = BASE64
+            .decode(&attachment.data)
+            .with_context(|| format!("Attachment {} is damaged", attachment.name))?;
+        // Never trust a name from the file as a path
+        let name = Path::new(&attachment.name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".to_string());
+        let target = dir.join(format!("{}-{}", moved.len() + 1, name));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&target, bytes).with_context(|| format!("Failed to write {}", target.display()))?;
+        moved.insert(attachment.original_path.clone(), target.to_string_lossy().into_owned());
+    }
+
+    let mut entries = shared.entries;
+    let dropped = localize_parts(&mut entries, &moved);
+    if dropped > 0 {
+        warn!("Dropped {} attachment(s) the shared file didn't carry", dropped);
+    }
+
+    state.history_store.archive(&session, &entries)?;
+    if AppSettings::load().privacy.private_mode {
+        info!("Privacy mode is on; shared messages stay out of memory");
+    } else {
+        // Redacted before the store is locked; the model pass can take a while
+        let stored: Vec<String> = entries
+            .iter()
+            .map(|entry| redaction::scrub(state, &entry.content))
+            .collect();
+        let mut store = state.memory_store.lock();
+        for (entry, content) in entries.iter().zip(stored) {
+            let mut metadata = HashMap::new();
+            metadata.insert("kind".to_string(), serde_json::json!("message"));
+            metadata.insert("role".to_string(), serde_json::json!(entry.role));
+            metadata.insert("timestamp".to_string(), serde_json::json!(entry.timestamp));
+            metadata.insert("status".to_string(), serde_json::json!(entry.status));
+            metadata.insert("shared_at".to_string(), serde_json::json!(shared.shared_at));
+            let (user_id, agent_id, run_id) = session.memory_ids();
+            store.add(content, user_id, agent_id, run_id, metadata)?;
+        }
+    }
+
+    info!(
+        "Imported shared conversation as {} ({} entries, {} attachments)",
+        session.run_id,
+        entries.len(),
+        moved.len()
+    );
+    state.history_store.load_archived(&session.run_id)
+}
+
+fn default_share_path(session_id: &str) -> PathBuf {
+    let short_id: String = session_id.chars().take(8).collect();
+    paths::app_data_dir()
+        .join("exports")
+        .join(format!("conversation-{}.aurachat", short_id))
+}
+
+/// Write a session to an encrypted `.aurachat` file; returns the file path
+#[tauri::command]
+pub async fn share_conversation(
+    session_id: String,
+    passphrase: String,
+    path: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_share_path(&session_id));
+    share(&state, &session_id, &passphrase, &path).map_err(|e| e.to_string())?;
+
+    info!("Shared conversation to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Import a `.aurachat` file as a new archived session
+#[tauri::command]
+pub async fn import_shared_conversation(
+    path: String,
+    passphrase: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ArchivedSession, String> {
+    let shared = read_shared(Path::new(&path), &passphrase).map_err(|e| e.to_string())?;
+    import(&state, shared).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let sealed = seal(b"[link](https://example.com)", "correct horse").unwrap();
+        assert_eq!(&sealed[..8], MAGIC);
+        assert!(!sealed.windows(4).any(|w| w == b"link"));
+
+        assert_eq!(open(&sealed, "correct horse").unwrap(), b"[link](https://example.com)");
+        assert!(open(&sealed, "wrong horse!").is_err());
+        assert!(open(b"not a share", "correct horse").is_err());
+        assert!(seal(b"x", "short").is_err());
+    }
+
+    #[test]
+    fn test_import_keeps_only_carried_files() {
+        let image = |path: &str| ContentPart::Image {
+            path: path.to_string(),
+            mime_type: None,
+            alt: None,
+        };
+        let mut entries = vec![ConversationEntry {
+            role: "user".to_string(),
+            content: "Look".to_string(),
+            timestamp: String::new(),
+            quality_score: None,
+            status: crate::EntryStatus::Complete,
+            tool_calls: Vec::new(),
+            parts: vec![ContentPart::text("Look"), image("/home/a/cat.png"), image("/etc/passwd")],
+        }];
+        let moved = HashMap::from([("/home/a/cat.png".to_string(), "/data/attachments/r/1-cat.png".to_string())]);
+
+        assert_eq!(localize_parts(&mut entries, &moved), 1);
+        assert_eq!(entries[0].parts, [ContentPart::text("Look"), image("/data/attachments/r/1-cat.png")]);
+        assert!(read_attachment("/etc/passwd").is_none());
+    }
+}
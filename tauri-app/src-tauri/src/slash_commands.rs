@@ -0,0 +1,449 @@
+// Slash Commands Module - `/mode`, `/model`, `/forget`, `/roll`, `/ingest` typed in chat
+//
+// A user message starting with `/name` is looked up in the command registry
+// before anything is generated. A command either answers by itself (the
+// answer is shown, nothing is generated and nothing enters the history) or
+// hands back a message to generate a reply to in place of the typed one
+// (`/roll 2d6 I attack the troll` becomes the text plus the roll). Only
+// `/word` followed by a space or the end counts, so paths like `/usr/bin`
+// reach the model untouched, and `//` escapes a literal leading slash.
+// New commands are registered in `CommandRegistry::builtin`.
+
+use crate::memory_policy;
+use crate::ingest::{DocumentSource, IngestJob};
+use crate::{apply_mode_switch, backend, models, modes, trash, AppMode, AppState};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// Most memories one `/forget` may delete; more means the phrase is too vague
+const MAX_FORGET: usize = 20;
+
+/// Dice limits for `/roll`
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u32 = 1000;
+const MAX_MODIFIER: i64 = 1000;
+
+static REGISTRY: OnceLock<CommandRegistry> = OnceLock::new();
+
+/// A `/name args` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation<'a> {
+    pub name: &'a str,
+    /// The rest of the message, trimmed
+    pub args: &'a str,
+}
+
+/// Parse a slash command; `None` for an ordinary message
+pub fn parse(message: &str) -> Option<Invocation<'_>> {
+    let rest = message.trim_start().strip_prefix('/')?;
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(rest.len());
+    let (name, tail) = rest.split_at(end);
+    let starts_with_letter = name.starts_with(|c: char| c.is_ascii_alphabetic());
+    if !starts_with_letter || !(tail.is_empty() || tail.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some(Invocation { name, args: tail.trim() })
+}
+
+/// What a command did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Shown to the user as the answer; nothing is generated
+    Reply(String),
+    /// Generate a reply to this message instead of the typed one
+    Generate(String),
+}
+
+/// What a handler gets to work with
+pub struct Context<'a> {
+    pub state: &'a AppState,
+    pub registry: &'a CommandRegistry,
+}
+
+pub type Handler = fn(&Context, &str) -> Result<Outcome>;
+
+#[derive(Clone, Serialize)]
+pub struct CommandSpec {
+    /// Typed after the slash, lowercase
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    #[serde(skip)]
+    pub handler: Handler,
+}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+}
+
+impl CommandRegistry {
+    /// The commands that ship with the app
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        let builtins = [
+            CommandSpec {
+                name: "help",
+                usage: "/help",
+                description: "List the commands",
+                handler: help,
+            },
+            CommandSpec {
+                name: "mode",
+                usage: "/mode [name]",
+                description: "Show the modes, or switch to one",
+                handler: mode,
+            },
+            CommandSpec {
+                name: "model",
+                usage: "/model [name]",
+                description: "Show the models, or switch to the one whose name contains `name`",
+                handler: model,
+            },
+            CommandSpec {
+                name: "forget",
+                usage: "/forget <phrase>",
+                description: "Move memories mentioning the phrase to the trash",
+                handler: forget,
+            },
+            CommandSpec {
+                name: "roll",
+                usage: "/roll [NdM+K] [message]",
+                description: "Roll dice (d20 by default); with a message, the model sees the result",
+                handler: roll,
+            },
+            CommandSpec {
+                name: "ingest",
+                usage: "/ingest <path>",
+                description: "Add a file to the searchable documents",
+                handler: ingest,
+            },
+        ];
+        for spec in builtins {
+            registry.register(spec).expect("built-in command names are unique");
+        }
+        registry
+    }
+
+    pub fn register(&mut self, spec: CommandSpec) -> Result<()> {
+        if self.get(spec.name).is_some() {
+            return Err(anyhow!("/{} is already a command", spec.name));
+        }
+        self.commands.push(spec);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.iter().find(|spec| spec.name == name)
+    }
+
+    pub fn commands(&self) -> &[CommandSpec] {
+        &self.commands
+    }
+
+    /// Run the command in `message`; `None` if it isn't one
+    pub fn dispatch(&self, state: &AppState, message: &str) -> Option<Result<Outcome>> {
+        let invocation = parse(message)?;
+        let name = invocation.name.to_lowercase();
+        let result = match self.get(&name) {
+            Some(spec) => {
+                info!("/{}", spec.name);
+                (spec.handler)(&Context { state, registry: self }, invocation.args)
+            }
+            None => Err(anyhow!("Unknown command /{}; /help lists them", name)),
+        };
+        Some(result)
+    }
+}
+
+/// Run the slash command in `message` against the app's commands
+pub fn dispatch(state: &AppState, message: &str) -> Option<Result<Outcome>> {
+    REGISTRY.get_or_init(CommandRegistry::builtin).dispatch(state, message)
+}
+
+/// A message with a leading `//` escaped to a literal `/`
+pub fn unescape(message: &str) -> &str {
+    message
+        .trim_start()
+        .strip_prefix('/')
+        .filter(|rest| rest.starts_with('/'))
+        .unwrap_or(message)
+}
+
+fn help(context: &Context, _args: &str) -> Result<Outcome> {
+    let lines: Vec<String> = context
+        .registry
+        .commands()
+        .iter()
+        .map(|spec| format!("{} - {}", spec.usage, spec.description))
+        .collect();
+    Ok(Outcome::Reply(format!(
+        "{}\nStart a message with // to send a literal /.",
+        lines.join("\n")
+    )))
+}
+
+fn mode(context: &Context, args: &str) -> Result<Outcome> {
+    let registry = modes::ModeRegistry::load();
+    if args.is_empty() {
+        let mut names: Vec<String> = modes::BUILTIN_MODES.iter().map(|id| id.to_string()).collect();
+        names.extend(registry.list().iter().map(|mode| mode.id.clone()));
+        let current = context.state.current_mode.lock().to_string();
+        return Ok(Outcome::Reply(format!("Mode: {}\nAvailable: {}", current, names.join(", "))));
+    }
+    let wanted = args.to_lowercase();
+    let target = AppMode::from_name(&wanted)
+        .or_else(|| {
+            registry
+                .list()
+                .iter()
+                .find(|mode| mode.name.to_lowercase() == wanted)
+                .map(|mode| AppMode::Custom(mode.id.clone()))
+        })
+        .ok_or_else(|| anyhow!("Unknown mode: {}", args))?;
+    apply_mode_switch(context.state, target.clone(), false);
+    Ok(Outcome::Reply(format!("Switched to {} mode", target.to_string())))
+}
+
+fn model(context: &Context, args: &str) -> Result<Outcome> {
+    let available = models::scan_all_model_locations()?;
+    let current = backend::BackendSettings::load().model_path;
+    if args.is_empty() {
+        let lines: Vec<String> = available
+            .iter()
+            .map(|model| {
+                let marker = if current.as_deref() == Some(model.path.as_path()) { "▶" } else { " " };
+                format!("{} {} ({})", marker, model.name, model.size_human)
+            })
+            .collect();
+        if lines.is_empty() {
+            return Ok(Outcome::Reply("No models found".to_string()));
+        }
+        return Ok(Outcome::Reply(lines.join("\n")));
+    }
+
+    let wanted = args.to_lowercase();
+    let matches: Vec<_> = match available.iter().find(|model| model.name.to_lowercase() == wanted) {
+        Some(exact) => vec![exact],
+        None => available
+            .iter()
+            .filter(|model| model.name.to_lowercase().contains(&wanted))
+            .collect(),
+    };
+    let chosen = match matches.as_slice() {
+        [] => return Err(anyhow!("No model matches \"{}\"", args)),
+        [one] => *one,
+        several => {
+            let names: Vec<&str> = several.iter().map(|model| model.name.as_str()).collect();
+            return Err(anyhow!("\"{}\" matches several models: {}", args, names.join(", ")));
+        }
+    };
+    if context.state.generation.is_active() {
+        return Err(anyhow!("Can't switch models while a reply is being generated"));
+    }
+    // Commands run on the async runtime; loading blocks
+    let backend = tokio::task::block_in_place(|| backend::select_model(&context.state.llm, &chosen.path, &|_| {}))?;
+    Ok(Outcome::Reply(format!("Now using {} ({} backend)", chosen.name, backend)))
+}
+
+fn forget(context: &Context, args: &str) -> Result<Outcome> {
+    if args.chars().count() < 3 {
+        return Err(anyhow!("Usage: /forget <phrase> (at least 3 characters)"));
+    }
+    let phrase = args.to_lowercase();
+    let filters = memory_policy::current_scope(context.state).filters();
+    let ids: Vec<String> = context
+        .state
+        .memory_store
+        .lock()
+        .get_all(&filters, usize::MAX)
+        .into_iter()
+        .filter(|memory| {
+            memory.metadata.get("kind").and_then(|kind| kind.as_str()) != Some("document_chunk")
+        })
+        .filter(|memory| memory.content.to_lowercase().contains(&phrase))
+        .map(|memory| memory.id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(Outcome::Reply(format!("Nothing to forget about \"{}\"", args)));
+    }
+    if ids.len() > MAX_FORGET {
+        return Err(anyhow!(
+            "{} memories mention \"{}\"; use a more specific phrase (at most {})",
+            ids.len(),
+            args,
+            MAX_FORGET
+        ));
+    }
+    for id in &ids {
+        trash::trash_memory(context.state, id)?;
+    }
+    Ok(Outcome::Reply(format!(
+        "Forgot {} memor{} about \"{}\" (restore from the trash if that was a mistake)",
+        ids.len(),
+        if ids.len() == 1 { "y" } else { "ies" },
+        args
+    )))
+}
+
+/// `NdM+K` dice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i64,
+}
+
+impl Dice {
+    /// Parse `2d6`, `d20`, `3d8+2` or `1d6-1`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || anyhow!("\"{}\" isn't a dice roll like 2d6 or d20+3", spec);
+        let spec = spec.to_lowercase();
+        let (count, rest) = spec.split_once('d').ok_or_else(invalid)?;
+        let count = if count.is_empty() { 1 } else { count.parse().map_err(|_| invalid())? };
+        let (sides, modifier) = match rest.find(|c| c == '+' || c == '-') {
+            Some(at) => {
+                let (sides, modifier) = rest.split_at(at);
+                let modifier: i64 = modifier.parse().map_err(|_| invalid())?;
+                (sides, modifier)
+            }
+            None => (rest, 0),
+        };
+        let sides: u32 = sides.parse().map_err(|_| invalid())?;
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(anyhow!("Roll between 1 and {} dice", MAX_DICE));
+        }
+        if !(2..=MAX_SIDES).contains(&sides) {
+            return Err(anyhow!("Dice have between 2 and {} sides", MAX_SIDES));
+        }
+        if modifier.abs() > MAX_MODIFIER {
+            return Err(anyhow!("The modifier is at most {}", MAX_MODIFIER));
+        }
+        Ok(Self { count, sides, modifier })
+    }
+
+    /// Roll, with `die(sides)` giving 1..=sides
+    pub fn roll(&self, mut die: impl FnMut(u32) -> u32) -> Roll {
+        let rolls: Vec<u32> = (0..self.count).map(|_| die(self.sides)).collect();
+        let total = rolls.iter().map(|&roll| roll as i64).sum::<i64>() + self.modifier;
+        Roll { dice: *self, rolls, total }
+    }
+}
+
+impl std::fmt::Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            m if m > 0 => write!(f, "+{}", m),
+            m => write!(f, "{}", m),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roll {
+    pub dice: Dice,
+    pub rolls: Vec<u32>,
+    pub total: i64,
+}
+
+impl std::fmt::Display for Roll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rolls: Vec<String> = self.rolls.iter().map(u32::to_string).collect();
+        write!(f, "{}: [{}]", self.dice, rolls.join(", "))?;
+        if self.dice.modifier != 0 {
+            write!(f, " {:+}", self.dice.modifier)?;
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+/// A fair die roll from 1 to `sides`
+fn random_die(sides: u32) -> u32 {
+    (uuid::Uuid::new_v4().as_u128() % sides as u128) as u32 + 1
+}
+
+fn roll(_context: &Context, args: &str) -> Result<Outcome> {
+    let (spec, message) = match args.split_once(char::is_whitespace) {
+        Some((spec, message)) => (spec, message.trim()),
+        None => (args, ""),
+    };
+    let (dice, message) = match spec {
+        "" => (Dice::parse("d20")?, message),
+        spec if spec.contains(|c: char| c.is_ascii_digit()) => (Dice::parse(spec)?, message),
+        // No dice given: the whole argument is the message
+        _ => (Dice::parse("d20")?, args),
+    };
+    let result = dice.roll(random_die);
+    if message.is_empty() {
+        Ok(Outcome::Reply(format!("🎲 {}", result)))
+    } else {
+        Ok(Outcome::Generate(format!("{}\n\n(Dice roll {})", message, result)))
+    }
+}
+
+fn ingest(context: &Context, args: &str) -> Result<Outcome> {
+    let path = PathBuf::from(args.trim_matches('"'));
+    if args.is_empty() {
+        return Err(anyhow!("Usage: /ingest <path>"));
+    }
+    if !path.is_file() {
+        return Err(anyhow!("{} is not a file", path.display()));
+    }
+    let doc_id = path.to_string_lossy().into_owned();
+    context.state.ingest.enqueue(IngestJob {
+        doc_id: doc_id.clone(),
+        source: DocumentSource::File(path),
+        sensitivity: Default::default(),
+        replace: false,
+    })?;
+    Ok(Outcome::Reply(format!("Ingesting {}; it becomes searchable once indexed", doc_id)))
+}
+
+/// Commands that can be typed in chat, for autocomplete
+#[tauri::command]
+pub async fn list_slash_commands() -> Result<Vec<CommandSpec>, String> {
+    Ok(REGISTRY.get_or_init(CommandRegistry::builtin).commands().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_roll() {
+        assert_eq!(parse("/mode youniverse"), Some(Invocation { name: "mode", args: "youniverse" }));
+        assert_eq!(parse("  /help"), Some(Invocation { name: "help", args: "" }));
+        assert_eq!(parse("/usr/bin is a directory"), None);
+        assert_eq!(parse("//mode is literal"), None);
+        assert_eq!(parse("/ nothing"), None);
+        assert_eq!(parse("plain /mode"), None);
+        assert_eq!(unescape("//mode is literal"), "/mode is literal");
+        let mut registry = CommandRegistry::builtin();
+        let duplicate = registry.get("roll").unwrap().clone();
+        assert!(registry.register(duplicate).is_err());
+
+        let dice = Dice::parse("3d8+2").unwrap();
+        assert_eq!(dice, Dice { count: 3, sides: 8, modifier: 2 });
+        assert_eq!(Dice::parse("D20").unwrap(), Dice { count: 1, sides: 20, modifier: 0 });
+        assert_eq!(Dice::parse("1d6-1").unwrap().modifier, -1);
+        assert!(Dice::parse("0d6").is_err());
+        assert!(Dice::parse("2d1").is_err());
+        assert!(Dice::parse("2x6").is_err());
+
+        let mut faces = [4, 8, 1].into_iter();
+        let result = dice.roll(|sides| {
+            assert_eq!(sides, 8);
+            faces.next().unwrap()
+        });
+        assert_eq!(result.total, 15);
+        assert_eq!(result.to_string(), "3d8+2: [4, 8, 1] +2 = 15");
+        assert!((0..100).all(|_| (1..=6).contains(&random_die(6))));
+    }
+}
@@ -0,0 +1,119 @@
+// Stop Sequences Module - End a streamed reply where a stop string appears
+//
+// Tokens rarely line up with a stop string ("\nUser:" may arrive as "\n",
+// "User" and ":"), so `StopFilter` holds back any tail of the stream that
+// could still become one and only passes on text that can't. When a stop
+// string completes, everything from it on is dropped and generation should
+// end. Used by the native sampler loop; the Python bridge and llm_server.py
+// get the list in their request instead.
+
+/// Most stop strings per request (the OpenAI API's limit)
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
+/// Longest stop string, in characters
+pub const MAX_STOP_CHARS: usize = 64;
+
+/// Filters streamed text against a set of stop strings
+pub struct StopFilter<'a> {
+    stops: &'a [String],
+    /// Text that may be the start of a stop string
+    pending: String,
+    stopped: bool,
+}
+
+impl<'a> StopFilter<'a> {
+    pub fn new(stops: &'a [String]) -> Self {
+        Self {
+            stops,
+            pending: String::new(),
+            stopped: false,
+        }
+    }
+
+    /// A stop string was reached; nothing more should be generated
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Add a streamed piece, returning the text that is safe to pass on
+    pub fn push(&mut self, piece: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.pending.push_str(piece);
+        let found = self
+            .stops
+            .iter()
+            .filter(|stop| !stop.is_empty())
+            .filter_map(|stop| self.pending.find(stop.as_str()))
+            .min();
+        if let Some(at) = found {
+            self.stopped = true;
+            self.pending.truncate(at);
+            return std::mem::take(&mut self.pending);
+        }
+        let held = self.partial_match_len();
+        let emit_to = self.pending.len() - held;
+        let rest = self.pending.split_off(emit_to);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// Text still held back once the stream ends without a stop
+    pub fn finish(self) -> String {
+        if self.stopped {
+            String::new()
+        } else {
+            self.pending
+        }
+    }
+
+    /// Byte length of the longest tail of `pending` that starts a stop string
+    fn partial_match_len(&self) -> usize {
+        self.pending
+            .char_indices()
+            .map(|(i, _)| &self.pending[i..])
+            .find(|tail| self.stops.iter().any(|stop| stop.starts_with(tail)))
+            .map_or(0, str::len)
+    }
+}
+
+/// `text` up to the first stop string in it
+pub fn truncate<'t>(text: &'t str, stops: &[String]) -> &'t str {
+    let end = stops
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| text.find(stop.as_str()))
+        .min()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_split_across_pieces() {
+        let stops = vec!["\nUser:".to_string(), "<|end|>".to_string()];
+        let mut filter = StopFilter::new(&stops);
+        let mut out = String::new();
+        for piece in ["Sure", ", here", " it is.", "\n", "Us", "er", ": and me"] {
+            out.push_str(&filter.push(piece));
+            if filter.is_stopped() {
+                break;
+            }
+        }
+        assert!(filter.is_stopped());
+        assert_eq!(out, "Sure, here it is.");
+
+        // A near miss is released once it can't be a stop any more
+        let mut filter = StopFilter::new(&stops);
+        assert_eq!(filter.push("Hi\nUs"), "Hi");
+        assert_eq!(filter.push("ually"), "\nUsually");
+        assert_eq!(filter.push(" <|en"), " ");
+        assert_eq!(filter.finish(), "<|en");
+
+        assert_eq!(truncate("one<|end|>two\nUser: x", &stops), "one");
+        assert_eq!(truncate("no stops", &[]), "no stops");
+    }
+}
@@ -0,0 +1,415 @@
+// Structured Module - Replies that must be JSON matching a schema
+//
+// `generate_structured` asks the chat backend for a JSON value matching a
+// JSON Schema. The schema travels in `LlmConfig::json_schema`: the native
+// sampler turns it into a GBNF grammar (`to_gbnf`), llm_server.py and the
+// Python backend into a llama-cpp-python grammar, and OpenAI
+// compatible servers get it as `response_format`. Whatever comes back is
+// parsed with serde_json and checked against the schema (`validate`); a reply
+// that fails either is asked for again with the problem added to the prompt.
+//
+// Only the common part of JSON Schema is understood: type, properties,
+// required, items, enum, const and anyOf/oneOf, plus length and range bounds
+// when validating. The grammar writes every listed property in order, with
+// `null` allowed for optional ones.
+
+use crate::backend::{self, GenerationRequest, LlmBackend};
+use crate::generation::CancellationToken;
+use crate::http_backend::GenerationStats;
+use crate::task_presets::{self, Task};
+use crate::{AppState, LlmConfig};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Tries before giving up on a reply that won't parse or validate
+const MAX_ATTEMPTS: usize = 3;
+
+/// Rules the schema's rule builds on
+const BASE_RULES: &str = r#"ws ::= [ \t\n]{0,20}
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F]{4} ) )* "\""
+number ::= "-"? ( [0-9] | [1-9] [0-9]{0,15} ) ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+integer ::= "-"? ( [0-9] | [1-9] [0-9]{0,15} )
+boolean ::= "true" | "false"
+null ::= "null"
+value ::= object | array | string | number | boolean | null
+object ::= "{" ws ( string ws ":" ws value ws ( "," ws string ws ":" ws value ws )* )? "}"
+array ::= "[" ws ( value ws ( "," ws value ws )* )? "]"
+"#;
+
+/// GBNF grammar accepting JSON that matches `schema`
+pub fn to_gbnf(schema: &Value) -> Result<String> {
+    Ok(format!("root ::= ws {} ws\n{}", rule(schema)?, BASE_RULES))
+}
+
+/// `text` as a GBNF string literal
+fn literal(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+fn alternatives(options: Vec<String>) -> String {
+    format!("( {} )", options.join(" | "))
+}
+
+/// Grammar expression for one schema
+fn rule(schema: &Value) -> Result<String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok("value".to_string()),
+        Value::Object(schema) => schema,
+        _ => return Err(anyhow!("Unsupported schema: {}", schema)),
+    };
+    if schema.contains_key("$ref") {
+        return Err(anyhow!("$ref isn't supported; inline the referenced schema"));
+    }
+    if let Some(constant) = schema.get("const") {
+        return Ok(literal(&constant.to_string()));
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        return Ok(alternatives(options.iter().map(|option| literal(&option.to_string())).collect()));
+    }
+    if let Some(options) = schema.get("anyOf").or_else(|| schema.get("oneOf")).and_then(Value::as_array) {
+        return Ok(alternatives(options.iter().map(rule).collect::<Result<_>>()?));
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ if schema.contains_key("properties") => vec!["object"],
+        _ if schema.contains_key("items") => vec!["array"],
+        _ => return Ok("value".to_string()),
+    };
+    let rules = types
+        .into_iter()
+        .map(|name| match name {
+            "object" => object_rule(schema),
+            "array" => array_rule(schema),
+            "string" | "number" | "integer" | "boolean" | "null" => Ok(name.to_string()),
+            other => Err(anyhow!("Unknown type \"{}\"", other)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(match rules.len() {
+        1 => rules.into_iter().next().unwrap_or_default(),
+        _ => alternatives(rules),
+    })
+}
+
+fn object_rule(schema: &serde_json::Map<String, Value>) -> Result<String> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object).filter(|p| !p.is_empty()) else {
+        return Ok("object".to_string());
+    };
+    let required = required(schema);
+    let fields = properties
+        .iter()
+        .map(|(name, property)| {
+            let value = rule(property)?;
+            let value = if required.contains(&name.as_str()) {
+                value
+            } else {
+                format!("( {} | null )", value)
+            };
+            Ok(format!("{} ws \":\" ws {} ws", literal(&Value::from(name.as_str()).to_string()), value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("\"{{\" ws {} \"}}\"", fields.join(" \",\" ws ")))
+}
+
+fn array_rule(schema: &serde_json::Map<String, Value>) -> Result<String> {
+    let item = match schema.get("items") {
+        Some(items) => rule(items)?,
+        None => "value".to_string(),
+    };
+    let min_items = schema.get("minItems").and_then(Value::as_u64).unwrap_or(0);
+    let elements = format!("{} ws ( \",\" ws {} ws )*", item, item);
+    Ok(if min_items > 0 {
+        format!("\"[\" ws {} \"]\"", elements)
+    } else {
+        format!("\"[\" ws ( {} )? \"]\"", elements)
+    })
+}
+
+fn required(schema: &serde_json::Map<String, Value>) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// The JSON value in a reply, allowing for a code fence or words around it
+pub fn parse_reply(text: &str) -> Result<Value> {
+    let text = text.trim();
+    let unfenced = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(text)
+        .trim();
+    serde_json::from_str(unfenced)
+        .or_else(|error| {
+            let start = unfenced.find(|c| c == '{' || c == '[');
+            let end = unfenced.rfind(|c| c == '}' || c == ']');
+            match (start, end) {
+                (Some(start), Some(end)) if start < end => {
+                    serde_json::from_str(&unfenced[start..=end]).map_err(|_| error)
+                }
+                _ => Err(error),
+            }
+        })
+        .map_err(|e| anyhow!("not valid JSON ({})", e))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+/// Check `value` against `schema`
+pub fn validate(value: &Value, schema: &Value) -> Result<()> {
+    check(value, schema, "$")
+}
+
+fn check(value: &Value, schema: &Value, path: &str) -> Result<()> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+    if let Some(constant) = schema.get("const") {
+        if value != constant {
+            return Err(anyhow!("{} should be {}", path, constant));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(anyhow!("{} should be one of {}", path, Value::from(options.clone())));
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            if !options.iter().any(|option| check(value, option, path).is_ok()) {
+                return Err(anyhow!("{} matches none of the {} options", path, key));
+            }
+        }
+    }
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+        return Err(anyhow!("{} should be {}, not {}", path, types.join(" or "), type_name(value)));
+    }
+
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    match value {
+        Value::Object(fields) => {
+            let required = required(schema);
+            if let Some(missing) = required.iter().find(|name| !fields.contains_key(**name)) {
+                return Err(anyhow!("{} is missing \"{}\"", path, missing));
+            }
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (name, property) in properties {
+                    match fields.get(name) {
+                        // The grammar writes null for optional fields left out
+                        Some(Value::Null) if !required.contains(&name.as_str()) => {}
+                        Some(field) => check(field, property, &format!("{}.{}", path, name))?,
+                        None => {}
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            let count = items.len() as f64;
+            if bound("minItems").is_some_and(|min| count < min) || bound("maxItems").is_some_and(|max| count > max) {
+                return Err(anyhow!("{} has the wrong number of items ({})", path, items.len()));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(item, item_schema, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as f64;
+            if bound("minLength").is_some_and(|min| length < min) || bound("maxLength").is_some_and(|max| length > max) {
+                return Err(anyhow!("{} has the wrong length ({} characters)", path, text.chars().count()));
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if bound("minimum").is_some_and(|min| number < min) || bound("maximum").is_some_and(|max| number > max) {
+                return Err(anyhow!("{} is out of range ({})", path, number));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A reply that parsed and matched the schema
+#[derive(Debug, Clone, Serialize)]
+pub struct Structured {
+    pub value: Value,
+    /// Replies it took, counting this one
+    pub attempts: usize,
+    /// Stats of the accepted reply
+    pub stats: GenerationStats,
+}
+
+/// Generate until a reply matches `schema`, at most `MAX_ATTEMPTS` times
+pub fn generate(
+    backend: &mut dyn LlmBackend,
+    prompt: &str,
+    system_prompt: &str,
+    schema: &Value,
+    config: &LlmConfig,
+) -> Result<Structured> {
+    // Refuse schemas the native sampler couldn't follow
+    to_gbnf(schema)?;
+    let config = LlmConfig {
+        json_schema: Some(schema.clone()),
+        ..config.clone()
+    };
+    let system_prompt = format!(
+        "{}\n\nReply with only a JSON value matching this JSON Schema:\n{}",
+        system_prompt, schema
+    );
+
+    let mut problem = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let prompt = match attempt {
+            1 => prompt.to_string(),
+            _ => format!(
+                "{}\n\nYour last reply was rejected: {}. Reply again with only the JSON.",
+                prompt, problem
+            ),
+        };
+        let request = GenerationRequest {
+            prompt: &prompt,
+            system_prompt: &system_prompt,
+            history: &[],
+            config: &config,
+        };
+        let completion = backend.generate(&request, &CancellationToken::new(), &mut |_| true)?;
+        match parse_reply(&completion.text).and_then(|value| validate(&value, schema).map(|()| value)) {
+            Ok(value) => {
+                return Ok(Structured {
+                    value,
+                    attempts: attempt,
+                    stats: completion.stats,
+                })
+            }
+            Err(e) => {
+                warn!("Structured reply {} of {} rejected: {}", attempt, MAX_ATTEMPTS, e);
+                problem = e.to_string();
+            }
+        }
+    }
+    Err(anyhow!("No valid reply after {} tries; the last was {}", MAX_ATTEMPTS, problem))
+}
+
+/// Generate JSON matching `schema` for `prompt`
+///
+/// Uses the extraction task's sampling unless `config` is given. With
+/// `memory_id`, the fields of the resulting object are merged into that
+/// memory's metadata.
+#[tauri::command]
+pub async fn generate_structured(
+    prompt: String,
+    schema: Value,
+    system_prompt: Option<String>,
+    config: Option<LlmConfig>,
+    memory_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Structured, String> {
+    let config = config.unwrap_or_else(|| task_presets::config_for(Task::Extraction));
+    let system_prompt = system_prompt.unwrap_or_else(|| "You extract structured data from text.".to_string());
+    let llm = state.llm.clone();
+    let structured = tauri::async_runtime::spawn_blocking(move || {
+        let mut llm = llm.blocking_lock();
+        let active = llm.get_or_insert_with(|| backend::select_backend(&|_| {}));
+        generate(active.as_mut(), &prompt, &system_prompt, &schema, &config)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))?;
+
+    if let Some(memory_id) = memory_id {
+        let fields = structured
+            .value
+            .as_object()
+            .ok_or("Only a JSON object can be stored as memory metadata")?;
+        let metadata: HashMap<String, Value> = fields.clone().into_iter().collect();
+        let updated = state
+            .memory_store
+            .lock()
+            .update(&memory_id, None, Some(metadata))
+            .map_err(|e| e.to_string())?;
+        if !updated {
+            return Err(format!("No memory {}", memory_id));
+        }
+    }
+    info!("Structured reply accepted after {} attempt(s)", structured.attempts);
+    Ok(structured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_grammar_and_validation() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 },
+                "mood": { "enum": ["happy", "sad"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["name", "mood"]
+        });
+        let grammar = to_gbnf(&schema).unwrap();
+        assert!(grammar.starts_with("root ::= ws \"{\" ws "));
+        assert!(grammar.contains("\"\\\"name\\\"\" ws \":\" ws string ws"));
+        assert!(grammar.contains("( integer | null )"));
+        assert!(grammar.contains("( \"\\\"happy\\\"\" | \"\\\"sad\\\"\" )"));
+        assert!(to_gbnf(&json!({ "$ref": "#/defs/x" })).is_err());
+
+        let good = parse_reply("Sure:\n```json\n{\"name\": \"Ada\", \"age\": null, \"mood\": \"happy\", \"tags\": [\"x\"]}\n```").unwrap();
+        assert!(validate(&good, &schema).is_ok());
+        let prose = parse_reply("Here it is: {\"name\": \"Bo\", \"mood\": \"sad\"} hope that helps").unwrap();
+        assert!(validate(&prose, &schema).is_ok());
+
+        let error = validate(&json!({ "name": "Ada" }), &schema).unwrap_err().to_string();
+        assert!(error.contains("missing \"mood\""));
+        let error = validate(&json!({ "name": "Ada", "mood": "happy", "age": 3.5 }), &schema).unwrap_err().to_string();
+        assert!(error.contains("$.age should be integer"));
+        let error = validate(&json!({ "name": "", "mood": "sad" }), &schema).unwrap_err().to_string();
+        assert!(error.contains("$.name has the wrong length"));
+        assert!(validate(&json!({ "name": "A", "mood": "happy", "tags": [1] }), &schema).is_err());
+        assert!(parse_reply("no json here").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+// System Probe Module - Memory available to the model, and GPU offload
+//
+// RAM comes from sysinfo; VRAM from `nvidia-smi` where an NVIDIA driver is
+// installed, and Apple Silicon GPUs share system RAM. How many layers to
+// offload is estimated from the GGUF header: each layer's share of the file
+// plus its slice of the KV cache at the context size, fitted into free VRAM
+// less some headroom. No usable GPU means everything stays on the CPU.
+
+use crate::models::{read_gguf_metadata, GgufMetadata};
+use crate::setup_wizard::{scan_hardware, HardwareInfo};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use tracing::info;
+
+/// VRAM kept free for the compute buffers and other programs
+const MIN_HEADROOM_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Fraction of free VRAM kept free as headroom, if more than the minimum
+const HEADROOM_FRACTION: f64 = 0.1;
+
+/// Bytes per KV cache element (f16)
+const KV_ELEMENT_BYTES: u64 = 2;
+
+/// A detected GPU
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuInfo {
+    pub name: String,
+    pub total_vram_bytes: u64,
+    pub free_vram_bytes: u64,
+    /// Shares system RAM (Apple Silicon)
+    pub unified_memory: bool,
+}
+
+/// Hardware the model runs on
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemInfo {
+    #[serde(flatten)]
+    pub hardware: HardwareInfo,
+    pub gpus: Vec<GpuInfo>,
+}
+
+impl SystemInfo {
+    /// The GPU with the most free memory
+    pub fn best_gpu(&self) -> Option<&GpuInfo> {
+        self.gpus.iter().max_by_key(|gpu| gpu.free_vram_bytes)
+    }
+}
+
+/// Parse `nvidia-smi --query-gpu=name,memory.total,memory.free
+/// --format=csv,noheader,nounits` output (MiB)
+fn parse_nvidia_smi(output: &str) -> Vec<GpuInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [name, total, free] = fields.as_slice() else {
+                return None;
+            };
+            Some(GpuInfo {
+                name: name.to_string(),
+                total_vram_bytes: total.parse::<u64>().ok()? * 1024 * 1024,
+                free_vram_bytes: free.parse::<u64>().ok()? * 1024 * 1024,
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+fn nvidia_gpus() -> Vec<GpuInfo> {
+    Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=name,memory.total,memory.free",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Detect RAM and GPUs
+pub fn probe() -> SystemInfo {
+    let hardware = scan_hardware();
+    let mut gpus = nvidia_gpus();
+    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        gpus.push(GpuInfo {
+            name: "Apple GPU".to_string(),
+            total_vram_bytes: hardware.total_ram_bytes,
+            free_vram_bytes: hardware.available_ram_bytes,
+            unified_memory: true,
+        });
+    }
+    SystemInfo { hardware, gpus }
+}
+
+/// Estimated memory of one layer: its share of the weights plus its KV cache
+pub fn layer_bytes(metadata: &GgufMetadata, file_size: u64, n_ctx: u64) -> Option<u64> {
+    let layers = metadata.block_count.filter(|&n| n > 0)?;
+    // Embeddings and output weights count as roughly one more layer
+    let weights = file_size / (layers + 1);
+    let kv = match (metadata.embedding_length, metadata.head_count) {
+        (Some(embedding), Some(heads)) if heads > 0 => {
+            let kv_heads = metadata.head_count_kv.unwrap_or(heads);
+            // Keys and values, scaled down for grouped-query attention
+            2 * n_ctx * embedding * kv_heads / heads * KV_ELEMENT_BYTES
+        }
+        _ => 0,
+    };
+    Some(weights + kv)
+}
+
+/// Layers to offload given `free_vram` bytes; more than the layer count
+/// offloads the output layer too
+pub fn choose_gpu_layers(metadata: &GgufMetadata, file_size: u64, n_ctx: u64, free_vram: u64) -> u32 {
+    let headroom = MIN_HEADROOM_BYTES.max((free_vram as f64 * HEADROOM_FRACTION) as u64);
+    let usable = free_vram.saturating_sub(headroom);
+    let (Some(layers), Some(per_layer)) = (metadata.block_count, layer_bytes(metadata, file_size, n_ctx)) else {
+        // Unknown layout: offload everything only if the whole file fits
+        return if file_size <= usable { 999 } else { 0 };
+    };
+    let fit = usable / per_layer.max(1);
+    if fit > layers {
+        (layers + 1) as u32
+    } else {
+        fit as u32
+    }
+}
+
+/// Layers of the model at `path` to offload on this machine
+pub fn gpu_layers_for(path: &Path, n_ctx: u32) -> u32 {
+    let system = probe();
+    let Some(gpu) = system.best_gpu() else {
+        info!("No GPU detected, running on the CPU");
+        return 0;
+    };
+    let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let metadata = read_gguf_metadata(path).unwrap_or_default();
+    let layers = choose_gpu_layers(&metadata, file_size, n_ctx as u64, gpu.free_vram_bytes);
+    info!(
+        "Offloading {} of {} layers to {} ({} MiB free)",
+        layers,
+        metadata
+            .block_count
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "?".to_string()),
+        gpu.name,
+        gpu.free_vram_bytes / (1024 * 1024)
+    );
+    layers
+}
+
+/// Detected RAM, CPU threads and GPUs with their free memory
+#[tauri::command]
+pub async fn get_system_info() -> Result<SystemInfo, String> {
+    tauri::async_runtime::spawn_blocking(probe)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_layers_fit_free_vram() {
+        let gpus = parse_nvidia_smi("NVIDIA GeForce RTX 3060, 12288, 10240\n");
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].free_vram_bytes, 10 * GIB);
+
+        // 8B-style model: 32 layers, 4.6 GiB file, GQA 32/8 heads
+        let metadata = GgufMetadata {
+            block_count: Some(32),
+            embedding_length: Some(4096),
+            head_count: Some(32),
+            head_count_kv: Some(8),
+            ..Default::default()
+        };
+        let file_size = 4_620_000_000;
+        // Fits entirely (output layer too)
+        assert_eq!(choose_gpu_layers(&metadata, file_size, 4096, 10 * GIB), 33);
+        // About half fits in 3 GiB
+        let partial = choose_gpu_layers(&metadata, file_size, 4096, 3 * GIB);
+        assert!(partial > 10 && partial < 20, "{}", partial);
+        // Nothing fits under the headroom
+        assert_eq!(choose_gpu_layers(&metadata, file_size, 4096, 256 * 1024 * 1024), 0);
+    }
+}
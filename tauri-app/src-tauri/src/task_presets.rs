@@ -0,0 +1,184 @@
+// Task Presets Module - Sampling settings for internal generation
+//
+// Chat replies use the conversation's (or mode's) `LlmConfig`. Everything the
+// app asks the model for itself - titles, summaries, memory extraction,
+// quality scores, search queries - uses the preset for that task instead:
+// low temperature where the answer should be factual, a handful of tokens
+// where only a few words are wanted. Presets can be changed in `task_presets.json`; tasks
+// missing from it use the built-in defaults below.
+
+use crate::backend::{GenerationRequest, LlmBackend};
+use crate::generation::CancellationToken;
+use crate::LlmConfig;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+/// Kinds of internal generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+    /// Short conversation titles
+    Title,
+    /// Summaries of history (compaction, digests)
+    Summarization,
+    /// Facts pulled out of conversation for memory
+    Extraction,
+    /// Rating a reply
+    QualityScoring,
+    /// Rewording a message as search queries for retrieval
+    QueryExpansion,
+    /// Deciding whether to call a tool before replying
+    ToolUse,
+}
+
+impl Task {
+    pub const ALL: [Task; 6] = [
+        Task::Title,
+        Task::Summarization,
+        Task::Extraction,
+        Task::QualityScoring,
+        Task::QueryExpansion,
+        Task::ToolUse,
+    ];
+
+    /// Built-in settings for the task
+    pub fn default_config(self) -> LlmConfig {
+        let factual = LlmConfig {
+            min_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            dry_multiplier: None,
+            ..Default::default()
+        };
+        match self {
+            Task::Title => LlmConfig {
+                temperature: 0.3,
+                max_tokens: 16,
+                ..factual
+            },
+            Task::Summarization => LlmConfig {
+                temperature: 0.2,
+                top_p: 0.9,
+                top_k: 40,
+                max_tokens: 160,
+                ..factual
+            },
+            Task::Extraction => LlmConfig {
+                temperature: 0.1,
+                max_tokens: 256,
+                ..factual
+            },
+            Task::QualityScoring => LlmConfig {
+                temperature: 0.0,
+                max_tokens: 8,
+                ..factual
+            },
+            // Some variety, so the phrasings differ
+            Task::QueryExpansion => LlmConfig {
+                temperature: 0.7,
+                max_tokens: 128,
+                ..factual
+            },
+            Task::ToolUse => LlmConfig {
+                temperature: 0.0,
+                max_tokens: 200,
+                ..factual
+            },
+        }
+    }
+}
+
+/// Saved per-task overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskPresets {
+    pub tasks: HashMap<Task, LlmConfig>,
+}
+
+impl TaskPresets {
+    const FILE: &'static str = "task_presets.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+
+    /// Settings for `task`: the saved override, else the built-in default
+    pub fn config(&self, task: Task) -> LlmConfig {
+        self.tasks
+            .get(&task)
+            .cloned()
+            .unwrap_or_else(|| task.default_config())
+    }
+}
+
+/// Settings to use for `task` right now
+pub fn config_for(task: Task) -> LlmConfig {
+    TaskPresets::load().config(task)
+}
+
+/// Run an internal generation on `backend` with the task's preset
+pub fn generate(backend: &mut dyn LlmBackend, task: Task, system_prompt: &str, prompt: &str) -> Result<String> {
+    let config = config_for(task);
+    let request = GenerationRequest {
+        prompt,
+        system_prompt,
+        history: &[],
+        config: &config,
+    };
+    backend
+        .generate(&request, &CancellationToken::new(), &mut |_| true)
+        .map(|completion| completion.text)
+}
+
+/// Current settings for every task
+#[tauri::command]
+pub async fn get_task_presets() -> Result<HashMap<Task, LlmConfig>, String> {
+    let presets = TaskPresets::load();
+    Ok(Task::ALL
+        .into_iter()
+        .map(|task| (task, presets.config(task)))
+        .collect())
+}
+
+/// Override a task's settings, or restore the default with `None`
+#[tauri::command]
+pub async fn set_task_preset(task: Task, config: Option<LlmConfig>) -> Result<(), String> {
+    let mut presets = TaskPresets::load();
+    match config {
+        Some(config) => {
+            info!("Updated {:?} preset (temperature {})", task, config.temperature);
+            presets.tasks.insert(task, config);
+        }
+        None => {
+            presets.tasks.remove(&task);
+        }
+    }
+    presets.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_preset_overrides_default() {
+        let json = r#"{"tasks": {"title": {"temperature": 0.5, "max_tokens": 24}}}"#;
+        let presets: TaskPresets = serde_json::from_str(json).unwrap();
+
+        let title = presets.config(Task::Title);
+        assert_eq!(title.temperature, 0.5);
+        assert_eq!(title.max_tokens, 24);
+        // Unset fields take the chat defaults, not the task's
+        assert_eq!(title.top_k, LlmConfig::default().top_k);
+
+        let extraction = presets.config(Task::Extraction);
+        assert!(extraction.temperature <= 0.2);
+        assert_eq!(extraction.dry_multiplier, None);
+    }
+}
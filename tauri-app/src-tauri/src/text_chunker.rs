@@ -2,14 +2,41 @@
 // Original: https://github.com/run-llama/llama_index
 // License: MIT
 
-use regex::Regex;
+use crate::sentence_segmenter::{Segmentation, SentenceSegmenter};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+
+/// Unit in which chunk sizes and overlap are measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeUnit {
+    /// Unicode characters
+    #[default]
+    Chars,
+    /// Whitespace-separated words (a cheap approximation of tokens)
+    Words,
+    /// Tokens of the chat model's tokenizer (estimated until it has loaded)
+    Tokens,
+}
+
+impl SizeUnit {
+    /// Size of `text` in this unit
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            SizeUnit::Chars => text.chars().count(),
+            SizeUnit::Words => text.split_whitespace().count(),
+            SizeUnit::Tokens => crate::tokenizer::count_tokens(text),
+        }
+    }
+}
 
 /// Configuration for text chunking
 #[derive(Debug, Clone)]
 pub struct ChunkingConfig {
-    /// Target size for each chunk in tokens/characters
+    /// Maximum size of each chunk, in `size_unit`
     pub chunk_size: usize,
-    /// Overlap between chunks to maintain context
+    /// Maximum overlap between consecutive chunks, in `size_unit`
     pub chunk_overlap: usize,
     /// Separator for splitting paragraphs
     pub paragraph_separator: String,
@@ -17,6 +44,10 @@ pub struct ChunkingConfig {
     pub sentence_separator: String,
     /// Minimum chunk size (chunks smaller than this will be merged)
     pub min_chunk_size: usize,
+    /// Unit for `chunk_size`, `chunk_overlap` and `min_chunk_size`
+    pub size_unit: SizeUnit,
+    /// How sentences are found inside paragraphs that exceed `chunk_size`
+    pub segmentation: Segmentation,
 }
 
 impl Default for ChunkingConfig {
@@ -27,6 +58,8 @@ impl Default for ChunkingConfig {
             paragraph_separator: "\n\n".to_string(),
             sentence_separator: ". ".to_string(),
             min_chunk_size: 100,
+            size_unit: SizeUnit::Chars,
+            segmentation: Segmentation::default(),
         }
     }
 }
@@ -37,7 +70,7 @@ impl Default for ChunkingConfig {
 /// Tries to keep sentences and paragraphs together for better semantic coherence.
 pub struct TextChunker {
     config: ChunkingConfig,
-    sentence_regex: Regex,
+    segmenter: SentenceSegmenter,
 }
 
 impl TextChunker {
@@ -48,13 +81,9 @@ impl TextChunker {
 
     /// Create a new text chunker with custom configuration
     pub fn with_config(config: ChunkingConfig) -> Self {
-        // Regex for sentence detection (supports multiple languages)
-        let sentence_regex = Regex::new(r"[^.!?。？！]+[.!?。？！]?").unwrap();
+        let segmenter = SentenceSegmenter::new(config.segmentation);
 
-        Self {
-            config,
-            sentence_regex,
-        }
+        Self { config, segmenter }
     }
 
     /// Split text into chunks with overlap
@@ -74,116 +103,211 @@ impl TextChunker {
         // First, try to split by paragraphs
         let paragraphs: Vec<&str> = text
             .split(&self.config.paragraph_separator)
-            .filter(|p| !p.trim().is_empty())
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
             .collect();
 
-        if paragraphs.len() == 1 {
-            // No paragraph breaks, split by sentences
-            self.chunk_by_sentences(text)
+        let separator = if paragraphs.len() == 1 {
+            " "
         } else {
-            // Split by paragraphs, then refine
-            self.chunk_by_paragraphs(&paragraphs)
-        }
+            self.config.paragraph_separator.as_str()
+        };
+
+        let segments = self.segments(&paragraphs);
+        let chunks = self.pack_segments(&segments);
+        self.merge_small_chunks(chunks, separator)
     }
 
-    /// Split text by paragraph boundaries
-    fn chunk_by_paragraphs(&self, paragraphs: &[&str]) -> Vec<String> {
-        let mut chunks = Vec::new();
-        let mut current_chunk = String::new();
-        let mut current_size = 0;
+    /// Measure `text` in the configured size unit
+    pub fn measure(&self, text: &str) -> usize {
+        self.config.size_unit.measure(text)
+    }
 
-        for paragraph in paragraphs {
-            let para_size = paragraph.len();
+    /// Break paragraphs into segments that each fit within `chunk_size`
+    ///
+    /// Paragraphs that fit are kept whole; larger ones fall back to sentences,
+    /// and sentences that are still too large are split between words.
+    fn segments<'a>(&'a self, paragraphs: &[&'a str]) -> Vec<Segment<'a>> {
+        let mut segments = Vec::new();
 
-            // If adding this paragraph exceeds chunk size
-            if current_size + para_size > self.config.chunk_size && !current_chunk.is_empty() {
-                // Save current chunk
-                if !current_chunk.trim().is_empty() {
-                    chunks.push(current_chunk.trim().to_string());
-                }
+        for paragraph in paragraphs {
+            let mut joiner = self.config.paragraph_separator.as_str();
 
-                // Start new chunk with overlap
-                current_chunk = self.get_overlap_text(&current_chunk);
+            let size = self.measure(paragraph);
+            if size <= self.config.chunk_size {
+                segments.push(Segment { text: paragraph, joiner, size });
+                continue;
             }
 
-            // Add paragraph to current chunk
-            if !current_chunk.is_empty() {
-                current_chunk.push_str(&self.config.paragraph_separator);
+            let sentences = self
+                .segmenter
+                .split(paragraph)
+                .into_iter()
+                .map(str::trim)
+                .filter(|s| !s.is_empty());
+
+            for sentence in sentences {
+                for piece in self.split_oversized(sentence) {
+                    segments.push(Segment {
+                        text: piece,
+                        joiner,
+                        size: self.measure(piece),
+                    });
+                    joiner = " ";
+                }
             }
-            current_chunk.push_str(paragraph);
-            current_size = current_chunk.len();
         }
 
-        // Add final chunk
-        if !current_chunk.trim().is_empty() {
-            chunks.push(current_chunk.trim().to_string());
+        segments
+    }
+
+    /// Split a sentence larger than `chunk_size` between words
+    fn split_oversized<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let limit = self.config.chunk_size.max(1);
+        if self.measure(text) <= limit {
+            return vec![text];
         }
 
-        chunks
-    }
+        let mut pieces = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut end = 0;
 
-    /// Split text by sentence boundaries
-    fn chunk_by_sentences(&self, text: &str) -> Vec<String> {
-        let sentences: Vec<&str> = self
-            .sentence_regex
-            .find_iter(text)
-            .map(|m| m.as_str())
-            .collect();
+        for word in text.split_whitespace() {
+            let word_start = word.as_ptr() as usize - text.as_ptr() as usize;
+            let word_end = word_start + word.len();
+
+            // A single word longer than a chunk (only possible in chars)
+            if self.measure(word) > limit {
+                if let Some(piece_start) = start.take() {
+                    pieces.push(&text[piece_start..end]);
+                }
+                pieces.extend(split_chars(word, limit));
+                continue;
+            }
 
-        if sentences.is_empty() {
-            return vec![text.to_string()];
+            match start {
+                Some(piece_start) if self.measure(&text[piece_start..word_end]) > limit => {
+                    pieces.push(&text[piece_start..end]);
+                    start = Some(word_start);
+                }
+                Some(_) => {}
+                None => start = Some(word_start),
+            }
+            end = word_end;
         }
 
-        let mut chunks = Vec::new();
-        let mut current_chunk = String::new();
-        let mut current_size = 0;
+        if let Some(piece_start) = start {
+            pieces.push(&text[piece_start..end]);
+        }
 
-        for sentence in sentences {
-            let sentence_size = sentence.len();
+        pieces
+    }
 
-            // If adding this sentence exceeds chunk size
-            if current_size + sentence_size > self.config.chunk_size && !current_chunk.is_empty() {
-                // Save current chunk
-                if !current_chunk.trim().is_empty() {
-                    chunks.push(current_chunk.trim().to_string());
-                }
+    /// Greedily pack segments into chunks of at most `chunk_size`
+    ///
+    /// Each new chunk starts with the trailing segments of the previous chunk
+    /// that fit within `chunk_overlap`, so overlap is always whole segments and
+    /// never exceeds the configured amount.
+    fn pack_segments(&self, segments: &[Segment]) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut window: Vec<&Segment> = Vec::new();
 
-                // Start new chunk with overlap
-                current_chunk = self.get_overlap_text(&current_chunk);
+        for segment in segments {
+            window.push(segment);
+            if window.len() == 1 || self.window_size(&window) <= self.config.chunk_size {
+                continue;
             }
 
-            // Add sentence to current chunk
-            if !current_chunk.is_empty() && !current_chunk.ends_with(' ') {
-                current_chunk.push(' ');
+            // Full: emit everything before this segment
+            window.pop();
+            chunks.push(render_segments(&window));
+
+            // Carry the overlap tail, dropping from the front until the new segment fits
+            let overlap_start = (1..window.len())
+                .find(|&i| self.window_size(&window[i..]) <= self.config.chunk_overlap)
+                .unwrap_or(window.len());
+            window.drain(..overlap_start);
+            window.push(segment);
+            while window.len() > 1 && self.window_size(&window) > self.config.chunk_size {
+                window.remove(0);
             }
-            current_chunk.push_str(sentence);
-            current_size = current_chunk.len();
         }
 
-        // Add final chunk
-        if !current_chunk.trim().is_empty() {
-            chunks.push(current_chunk.trim().to_string());
+        if !window.is_empty() {
+            chunks.push(render_segments(&window));
         }
 
         chunks
     }
 
-    /// Get overlap text from the end of a chunk
-    fn get_overlap_text(&self, text: &str) -> String {
-        if text.len() <= self.config.chunk_overlap {
-            return text.to_string();
+    /// Size of the chunk `window` would render to
+    fn window_size(&self, window: &[&Segment]) -> usize {
+        window
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let joiner = if i == 0 { 0 } else { self.measure(segment.joiner) };
+                joiner + segment.size
+            })
+            .sum()
+    }
+
+    /// Merge chunks smaller than `min_chunk_size` into their neighbours
+    ///
+    /// Undersized chunks are carried forward into the next chunk; an undersized
+    /// final chunk is merged backward into the previous one. A merged chunk can
+    /// exceed `chunk_size` by less than `min_chunk_size`.
+    fn merge_small_chunks(&self, chunks: Vec<String>, separator: &str) -> Vec<String> {
+        if self.config.min_chunk_size == 0 || chunks.len() < 2 {
+            return chunks;
         }
 
-        // Get last N characters for overlap
-        let start_pos = text.len() - self.config.chunk_overlap;
-        
-        // Try to start at a sentence boundary
-        if let Some(sentence_start) = text[start_pos..].find(". ") {
-            return text[start_pos + sentence_start + 2..].to_string();
+        let mut merged: Vec<String> = Vec::with_capacity(chunks.len());
+        let mut carry: Option<String> = None;
+
+        for chunk in chunks {
+            let chunk = match carry.take() {
+                Some(small) => self.join_chunks(&small, &chunk, separator),
+                None => chunk,
+            };
+
+            if self.measure(&chunk) < self.config.min_chunk_size {
+                carry = Some(chunk);
+            } else {
+                merged.push(chunk);
+            }
         }
 
-        // Otherwise just use character position
-        text[start_pos..].to_string()
+        if let Some(small) = carry {
+            match merged.pop() {
+                Some(previous) => merged.push(self.join_chunks(&previous, &small, separator)),
+                None => merged.push(small),
+            }
+        }
+
+        merged
+    }
+
+    /// Join two adjacent chunks, dropping the overlap the second one repeats
+    fn join_chunks(&self, first: &str, second: &str, separator: &str) -> String {
+        // Shorter matches are too likely to be coincidental
+        const MIN_OVERLAP_MATCH: usize = 8;
+
+        let max_overlap = first.len().min(second.len());
+        let overlap = (MIN_OVERLAP_MATCH..=max_overlap)
+            .rev()
+            .filter(|&k| second.is_char_boundary(k))
+            .find(|&k| {
+                let rest = &second[k..];
+                first.ends_with(&second[..k])
+                    && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+                    && self.measure(&second[..k]) <= self.config.chunk_overlap
+            });
+
+        match overlap {
+            Some(k) => format!("{}{}", first, &second[k..]),
+            None => format!("{}{}{}", first, separator, second),
+        }
     }
 
     /// Chunk text and return with metadata
@@ -200,14 +324,17 @@ impl TextChunker {
 
     /// Estimate number of chunks for a given text
     pub fn estimate_chunks(&self, text: &str) -> usize {
-        let text_len = text.len();
-        let effective_chunk_size = self.config.chunk_size - self.config.chunk_overlap;
+        let text_size = self.measure(text);
+        let effective_chunk_size = self
+            .config
+            .chunk_size
+            .saturating_sub(self.config.chunk_overlap);
         
         if effective_chunk_size == 0 {
             return 1;
         }
 
-        (text_len + effective_chunk_size - 1) / effective_chunk_size
+        (text_size + effective_chunk_size - 1) / effective_chunk_size
     }
 }
 
@@ -217,6 +344,154 @@ impl Default for TextChunker {
     }
 }
 
+/// A piece of text that is never split across chunks
+struct Segment<'a> {
+    text: &'a str,
+    /// Separator placed before this segment when it follows another one
+    joiner: &'a str,
+    size: usize,
+}
+
+/// Join segments into chunk text
+fn render_segments(window: &[&Segment]) -> String {
+    let mut chunk = String::new();
+    for (i, segment) in window.iter().enumerate() {
+        if i > 0 {
+            chunk.push_str(segment.joiner);
+        }
+        chunk.push_str(segment.text);
+    }
+    chunk
+}
+
+/// Split `text` into pieces of at most `max_chars` characters
+fn split_chars(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+
+    for (count, (index, _)) in text.char_indices().enumerate() {
+        if count > 0 && count % max_chars == 0 {
+            pieces.push(&text[start..index]);
+            start = index;
+        }
+    }
+    pieces.push(&text[start..]);
+
+    pieces
+}
+
+/// Chunker that reads from a `BufRead` and yields chunks lazily
+///
+/// Text is read in bounded windows. Each window is chunked with
+/// `TextChunker`; every chunk except the last is emitted, and the last one
+/// (which may end mid-sentence) is carried into the next window. Memory stays
+/// around one window plus one chunk regardless of the input size.
+pub struct StreamingChunker<R> {
+    chunker: TextChunker,
+    reader: R,
+    /// Bytes to read before chunking
+    window: usize,
+    /// Text read but not yet emitted
+    buffer: String,
+    /// Incomplete UTF-8 sequence left over from the last read
+    partial_char: Vec<u8>,
+    ready: VecDeque<String>,
+    finished: bool,
+}
+
+impl<R: BufRead> StreamingChunker<R> {
+    /// Default bytes read per window
+    pub const DEFAULT_WINDOW: usize = 256 * 1024;
+
+    pub fn new(reader: R, config: ChunkingConfig) -> Self {
+        // Keep many chunks per window so the carried tail is a small fraction
+        let window = Self::DEFAULT_WINDOW.max(config.chunk_size.saturating_mul(32));
+        Self {
+            chunker: TextChunker::with_config(config),
+            reader,
+            window,
+            buffer: String::new(),
+            partial_char: Vec::new(),
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Override how many bytes are read per window
+    pub fn with_window(mut self, bytes: usize) -> Self {
+        self.window = bytes.max(1);
+        self
+    }
+
+    /// Read the next window and chunk it
+    fn fill(&mut self) -> io::Result<()> {
+        let mut eof = false;
+        let mut read_total = 0;
+
+        // Always read a full window of new text, even if the carried tail is large
+        while read_total < self.window {
+            let mut bytes = std::mem::take(&mut self.partial_char);
+            let limit = (self.window - read_total) as u64;
+            let read = (&mut self.reader).take(limit).read_until(b'\n', &mut bytes)?;
+            if read == 0 {
+                // A dangling partial character at EOF is invalid; keep it lossily
+                self.buffer.push_str(&String::from_utf8_lossy(&bytes));
+                eof = true;
+                break;
+            }
+            read_total += read;
+            self.push_bytes(bytes);
+        }
+
+        let mut chunks = self.chunker.chunk_text(&self.buffer);
+        if eof {
+            self.buffer.clear();
+            self.finished = true;
+        } else {
+            // Keep the trailing whitespace so a paragraph break at the window edge survives
+            let trailing = self.buffer[self.buffer.trim_end().len()..].to_string();
+            self.buffer = chunks.pop().unwrap_or_default() + &trailing;
+        }
+        self.ready.extend(chunks);
+
+        Ok(())
+    }
+
+    fn push_bytes(&mut self, mut bytes: Vec<u8>) {
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            // Read stopped inside a multi-byte character
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => {
+                self.buffer.push_str(&String::from_utf8_lossy(&bytes));
+                return;
+            }
+        };
+
+        self.partial_char = bytes.split_off(valid);
+        self.buffer.push_str(&String::from_utf8_lossy(&bytes));
+    }
+}
+
+impl<R: BufRead> Iterator for StreamingChunker<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = self.ready.pop_front() {
+                return Some(Ok(chunk));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
 /// Simple character-based text splitter (fallback for non-semantic chunking)
 pub struct SimpleTextSplitter {
     chunk_size: usize,
@@ -322,6 +597,262 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_single_sentence_tail_is_merged() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 70,
+            chunk_overlap: 0,
+            min_chunk_size: 20,
+            ..Default::default()
+        });
+
+        let text = "Alpha beta gamma delta epsilon. Zeta eta theta iota kappa lambda. Mu.";
+        let chunks = chunker.chunk_text(text);
+
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].ends_with("Mu."));
+    }
+
+    #[test]
+    fn test_merged_tail_does_not_repeat_overlap() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 70,
+            chunk_overlap: 10,
+            min_chunk_size: 20,
+            ..Default::default()
+        });
+
+        let text = "Alpha beta gamma delta epsilon. Zeta eta theta iota kappa lambda. Mu.";
+        let chunks = chunker.chunk_text(text);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].matches("lambda").count(), 1);
+        assert!(chunks[0].ends_with("Mu."));
+    }
+
+    #[test]
+    fn test_small_middle_chunk_merges_forward() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 40,
+            chunk_overlap: 0,
+            min_chunk_size: 15,
+            ..Default::default()
+        });
+
+        let chunks = vec![
+            "The first chunk is long enough.".to_string(),
+            "Tiny.".to_string(),
+            "The third chunk is also long enough.".to_string(),
+        ];
+        let merged = chunker.merge_small_chunks(chunks, " ");
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[1], "Tiny. The third chunk is also long enough.");
+    }
+
+    #[test]
+    fn test_consecutive_small_chunks_accumulate() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 40,
+            chunk_overlap: 0,
+            min_chunk_size: 12,
+            ..Default::default()
+        });
+
+        let chunks = vec!["One.".to_string(), "Two.".to_string(), "Three.".to_string()];
+        let merged = chunker.merge_small_chunks(chunks, " ");
+
+        assert_eq!(merged, vec!["One. Two. Three.".to_string()]);
+    }
+
+    #[test]
+    fn test_min_chunk_size_zero_disables_merging() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            min_chunk_size: 0,
+            ..Default::default()
+        });
+
+        let chunks = vec!["A.".to_string(), "B.".to_string()];
+        assert_eq!(chunker.merge_small_chunks(chunks.clone(), " "), chunks);
+    }
+
+    #[test]
+    fn test_every_chunk_meets_min_size() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 120,
+            chunk_overlap: 20,
+            min_chunk_size: 40,
+            ..Default::default()
+        });
+
+        let text = "Short one. ".repeat(30) + "End.";
+        let chunks = chunker.chunk_text(&text);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.len() >= 40, "chunk too small: {:?}", chunk);
+        }
+        assert!(chunks.last().unwrap().ends_with("End."));
+    }
+
+    /// Longest suffix of `first` that `second` starts with, in chars
+    fn shared_overlap(first: &str, second: &str) -> usize {
+        (1..=first.len().min(second.len()))
+            .rev()
+            .filter(|&k| first.is_char_boundary(first.len() - k))
+            .find(|&k| second.starts_with(&first[first.len() - k..]))
+            .map(|k| first[first.len() - k..].chars().count())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_paragraph_overlap_stays_within_limit() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 200,
+            chunk_overlap: 50,
+            min_chunk_size: 0,
+            ..Default::default()
+        });
+
+        let text = (0..12)
+            .map(|i| format!("Paragraph {} talks about topic number {} at length.", i, i))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let chunks = chunker.chunk_text(&text);
+
+        assert!(chunks.len() > 2);
+        for pair in chunks.windows(2) {
+            assert!(shared_overlap(&pair[0], &pair[1]) <= 50, "overlap too large: {:?}", pair);
+            assert!(pair[0].chars().count() <= 200);
+        }
+    }
+
+    #[test]
+    fn test_oversized_paragraph_is_split() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 60,
+            chunk_overlap: 0,
+            min_chunk_size: 0,
+            ..Default::default()
+        });
+
+        let long = "This sentence is part of a very long paragraph. ".repeat(6);
+        let text = format!("Intro.\n\n{}\n\nOutro.", long.trim());
+        let chunks = chunker.chunk_text(&text);
+
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 60, "chunk too large: {:?}", chunk);
+        }
+        assert!(chunks.len() >= 4);
+        assert!(chunks.first().unwrap().starts_with("Intro."));
+        assert!(chunks.last().unwrap().ends_with("Outro."));
+    }
+
+    #[test]
+    fn test_multibyte_text_overlap() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 20,
+            chunk_overlap: 8,
+            min_chunk_size: 0,
+            ..Default::default()
+        });
+
+        let text = "今日は良い天気です。明日は雨が降るでしょう。週末は晴れるといいですね。";
+        let chunks = chunker.chunk_text(text);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 20);
+        }
+    }
+
+    #[test]
+    fn test_word_size_unit() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 8,
+            chunk_overlap: 4,
+            min_chunk_size: 0,
+            size_unit: SizeUnit::Words,
+            ..Default::default()
+        });
+
+        let text = "One two three. Four five six. Seven eight nine. Ten eleven twelve.";
+        let chunks = chunker.chunk_text(text);
+
+        assert_eq!(
+            chunks,
+            vec![
+                "One two three. Four five six.".to_string(),
+                "Four five six. Seven eight nine.".to_string(),
+                "Seven eight nine. Ten eleven twelve.".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_streaming_matches_in_memory_for_small_input() {
+        let config = ChunkingConfig {
+            chunk_size: 80,
+            chunk_overlap: 20,
+            min_chunk_size: 0,
+            ..Default::default()
+        };
+        let text = "First paragraph. It has two sentences.\n\nSecond paragraph is here. ".repeat(5);
+
+        let expected = TextChunker::with_config(config.clone()).chunk_text(&text);
+        let streamed: Vec<String> = StreamingChunker::new(text.as_bytes(), config)
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn test_streaming_small_windows_keep_all_text() {
+        let config = ChunkingConfig {
+            chunk_size: 50,
+            chunk_overlap: 10,
+            min_chunk_size: 0,
+            ..Default::default()
+        };
+        let words: Vec<String> = (0..400).map(|i| format!("wörd{}", i)).collect();
+        let text = words
+            .chunks(7)
+            .map(|sentence| sentence.join(" ") + ".")
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let chunks: Vec<String> = StreamingChunker::new(text.as_bytes(), config)
+            .with_window(37)
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert!(chunks.len() > 10);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 50, "chunk too large: {:?}", chunk);
+            assert!(!chunk.contains('\u{FFFD}'), "split character in {:?}", chunk);
+        }
+        let joined = chunks.join(" ");
+        for word in &words {
+            assert!(joined.contains(word.as_str()), "lost {}", word);
+        }
+    }
+
+    #[test]
+    fn test_abbreviations_stay_in_sentence() {
+        let chunker = TextChunker::with_config(ChunkingConfig {
+            chunk_size: 45,
+            chunk_overlap: 0,
+            min_chunk_size: 0,
+            ..Default::default()
+        });
+
+        let text = "Yesterday I met Dr. Watson at the station. We talked for an hour about cases.";
+        let chunks = chunker.chunk_text(text);
+
+        assert!(chunks[0].contains("Dr. Watson"), "split at abbreviation: {:?}", chunks);
+    }
+
     #[test]
     fn test_estimate_chunks() {
         let chunker = TextChunker::with_config(ChunkingConfig {
@@ -340,3 +871,84 @@ mod tests {
         println!("Estimated: {}, Actual: {}", estimated, actual);
     }
 }
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Build a document of unique words so any shared text between chunks is real overlap
+    fn document(sentences: &[(usize, bool)]) -> (String, Vec<String>) {
+        let mut text = String::new();
+        let mut words = Vec::new();
+
+        for (i, (len, paragraph_break)) in sentences.iter().enumerate() {
+            let sentence: Vec<String> = (0..*len)
+                .map(|_| {
+                    let word = format!("w{}", words.len());
+                    words.push(word.clone());
+                    word
+                })
+                .collect();
+            text.push_str(&sentence.join(" "));
+            text.push('.');
+            if i + 1 < sentences.len() {
+                text.push_str(if *paragraph_break { "\n\n" } else { " " });
+            }
+        }
+
+        (text, words)
+    }
+
+    fn overlap_of(first: &str, second: &str, unit: SizeUnit) -> usize {
+        (1..=first.len().min(second.len()))
+            .rev()
+            .filter(|&k| first.is_char_boundary(first.len() - k))
+            .find(|&k| second.starts_with(&first[first.len() - k..]))
+            .map(|k| unit.measure(&first[first.len() - k..]))
+            .unwrap_or(0)
+    }
+
+    proptest! {
+        #[test]
+        fn chunks_respect_size_and_overlap(
+            sentences in prop::collection::vec((1usize..30, any::<bool>()), 1..60),
+            chunk_size in 20usize..400,
+            overlap_percent in 0usize..60,
+            count_words in any::<bool>(),
+        ) {
+            let size_unit = if count_words { SizeUnit::Words } else { SizeUnit::Chars };
+            let chunk_size = if count_words { chunk_size / 10 + 1 } else { chunk_size };
+            let chunk_overlap = chunk_size * overlap_percent / 100;
+            let chunker = TextChunker::with_config(ChunkingConfig {
+                chunk_size,
+                chunk_overlap,
+                min_chunk_size: 0,
+                size_unit,
+                ..Default::default()
+            });
+
+            let (text, words) = document(&sentences);
+            let chunks = chunker.chunk_text(&text);
+
+            for chunk in &chunks {
+                prop_assert!(size_unit.measure(chunk) <= chunk_size, "chunk too large: {:?}", chunk);
+            }
+            for pair in chunks.windows(2) {
+                prop_assert!(
+                    overlap_of(&pair[0], &pair[1], size_unit) <= chunk_overlap,
+                    "overlap too large: {:?}",
+                    pair
+                );
+            }
+
+            let seen: std::collections::HashSet<&str> = chunks
+                .iter()
+                .flat_map(|chunk| chunk.split(|c: char| c.is_whitespace() || c == '.'))
+                .collect();
+            for word in &words {
+                prop_assert!(seen.contains(word.as_str()), "lost word {}", word);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Tokenizer Module - Vocabulary-only GGUF loading for token counting
+//
+// Loading a GGUF with `vocab_only` reads just the tokenizer (no weights, no
+// context), which takes a fraction of a second even for large models. The
+// chat model's vocabulary is loaded this way at startup so token counts,
+// context budgets and token-sized chunks are exact long before the full
+// model is ready. Until then, counts fall back to a characters/4 estimate.
+
+use anyhow::{Context, Result};
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaModel};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Tokenizer of the chat model, once loaded
+static DEFAULT: Mutex<Option<Arc<Tokenizer>>> = parking_lot::const_mutex(None);
+
+/// A model's vocabulary without its weights
+pub struct Tokenizer {
+    model: LlamaModel,
+}
+
+impl Tokenizer {
+    /// Load only the tokenizer from a GGUF file
+    pub fn load(path: &Path) -> Result<Self> {
+        let backend = crate::llm::llama_backend()?;
+        let params = LlamaModelParams::default().with_vocab_only(true);
+        let model = LlamaModel::load_from_file(backend, path, &params)
+            .with_context(|| format!("Failed to load tokenizer from {}", path.display()))?;
+        Ok(Self { model })
+    }
+
+    /// Exact token count of `text` (without BOS)
+    pub fn count(&self, text: &str) -> Result<usize> {
+        let tokens = self
+            .model
+            .str_to_token(text, AddBos::Never)
+            .context("Failed to tokenize text")?;
+        Ok(tokens.len())
+    }
+}
+
+/// Rough token count for when no tokenizer is loaded (~4 chars per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The chat model's tokenizer, if it has been loaded
+pub fn default_tokenizer() -> Option<Arc<Tokenizer>> {
+    DEFAULT.lock().clone()
+}
+
+/// Tokens in `text` by the chat model's tokenizer, else estimated
+pub fn count_tokens(text: &str) -> usize {
+    default_tokenizer()
+        .and_then(|tokenizer| tokenizer.count(text).ok())
+        .unwrap_or_else(|| estimate_tokens(text))
+}
+
+/// Load the chat model's tokenizer in the background
+pub fn preload_default() {
+    if let Some(path) = crate::llm::LlmManager::default_model() {
+        preload(path);
+    }
+}
+
+/// Load the tokenizer of `path` in the background and count with it from
+/// then on
+pub fn preload(path: PathBuf) {
+    std::thread::spawn(move || match Tokenizer::load(&path) {
+        Ok(tokenizer) => {
+            info!("Tokenizer loaded from {}", path.display());
+            *DEFAULT.lock() = Some(Arc::new(tokenizer));
+        }
+        Err(e) => warn!("Token counts will be estimated ({:#})", e),
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenCount {
+    pub tokens: usize,
+    /// Counted by the model's tokenizer rather than estimated
+    pub exact: bool,
+}
+
+/// Count the tokens in `text` for the chat model (or a given GGUF)
+#[tauri::command]
+pub async fn get_token_count(text: String, model_path: Option<String>) -> Result<TokenCount, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let tokenizer = match model_path {
+            Some(path) => {
+                let tokenizer = Tokenizer::load(Path::new(&path)).map_err(|e| e.to_string())?;
+                Some(Arc::new(tokenizer))
+            }
+            None => default_tokenizer(),
+        };
+        Ok(match tokenizer.and_then(|tokenizer| tokenizer.count(&text).ok()) {
+            Some(tokens) => TokenCount { tokens, exact: true },
+            None => TokenCount {
+                tokens: estimate_tokens(&text),
+                exact: false,
+            },
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_without_tokenizer() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        // Counted in characters, not bytes
+        assert_eq!(estimate_tokens("héllo wörld"), 3);
+    }
+}
@@ -0,0 +1,156 @@
+// Tool Calls Module - Records of the tools the assistant ran for a reply
+//
+// Each invocation is kept with its arguments, timing and (truncated) output
+// so the persisted history, `ChatResponse` and exported transcripts show what
+// the assistant actually did. Tools run by llm_server.py are reported in its
+// stream; tools run in-process go through `invoke`.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Output kept per invocation; the rest is cut (the model saw all of it)
+pub const MAX_TOOL_OUTPUT_CHARS: usize = 2000;
+
+/// One tool call made while generating a reply
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct ToolInvocation {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    /// RFC 3339 start time
+    pub started_at: String,
+    pub duration_ms: u64,
+    /// Output as returned to the model, cut to `MAX_TOOL_OUTPUT_CHARS`
+    pub output: String,
+    /// Length of the full output, in characters
+    pub output_chars: usize,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ToolInvocation {
+    fn new(
+        tool: &str,
+        arguments: serde_json::Value,
+        started_at: String,
+        duration_ms: u64,
+        result: Result<&str, String>,
+    ) -> Self {
+        let (output, error) = match result {
+            Ok(output) => (output, None),
+            Err(error) => ("", Some(error)),
+        };
+        let (kept, truncated) = truncate_output(output, MAX_TOOL_OUTPUT_CHARS);
+        Self {
+            tool: tool.to_string(),
+            arguments,
+            started_at,
+            duration_ms,
+            output: kept,
+            output_chars: output.chars().count(),
+            truncated,
+            error,
+        }
+    }
+
+    /// Parse a tool call reported by llm_server.py
+    ///
+    /// Accepts `{"tool"|"name", "arguments", "duration_ms", "output", "error"}`;
+    /// `arguments` may be an object or a JSON-encoded string.
+    pub fn from_server(event: &serde_json::Value) -> Option<Self> {
+        let tool = event["tool"].as_str().or_else(|| event["name"].as_str())?;
+        let arguments = match &event["arguments"] {
+            serde_json::Value::String(raw) => {
+                serde_json::from_str(raw).unwrap_or_else(|_| serde_json::json!(raw))
+            }
+            other => other.clone(),
+        };
+        let result = match event["error"].as_str() {
+            Some(error) => Err(error.to_string()),
+            None => Ok(event["output"].as_str().unwrap_or_default()),
+        };
+        let started_at = event["started_at"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(crate::clock::timestamp);
+        Some(Self::new(
+            tool,
+            arguments,
+            started_at,
+            event["duration_ms"].as_u64().unwrap_or(0),
+            result,
+        ))
+    }
+}
+
+/// Run an in-process tool, returning its record and full result
+pub fn invoke(
+    tool: &str,
+    arguments: serde_json::Value,
+    run: impl FnOnce(&serde_json::Value) -> anyhow::Result<String>,
+) -> (ToolInvocation, anyhow::Result<String>) {
+    let started_at = crate::clock::timestamp();
+    let started = Instant::now();
+    let result = run(&arguments);
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let record = ToolInvocation::new(
+        tool,
+        arguments,
+        started_at,
+        duration_ms,
+        result.as_deref().map_err(|e| format!("{:#}", e)),
+    );
+    (record, result)
+}
+
+/// First `max_chars` characters of `output`, and whether anything was cut
+pub fn truncate_output(output: &str, max_chars: usize) -> (String, bool) {
+    match output.char_indices().nth(max_chars) {
+        Some((cut, _)) => (format!("{}…", &output[..cut]), true),
+        None => (output.to_string(), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoke_records_and_truncates() {
+        let long = "é".repeat(MAX_TOOL_OUTPUT_CHARS + 10);
+        let (record, result) = invoke("read_file", serde_json::json!({"path": "notes.txt"}), |_| {
+            Ok(long.clone())
+        });
+        assert_eq!(result.unwrap(), long);
+        assert_eq!(record.tool, "read_file");
+        assert_eq!(record.arguments["path"], "notes.txt");
+        assert!(record.truncated);
+        assert_eq!(record.output_chars, MAX_TOOL_OUTPUT_CHARS + 10);
+        assert_eq!(record.output.chars().count(), MAX_TOOL_OUTPUT_CHARS + 1);
+
+        let (failed, _) = invoke("web_search", serde_json::Value::Null, |_| {
+            Err(anyhow::anyhow!("offline"))
+        });
+        assert_eq!(failed.error.as_deref(), Some("offline"));
+        assert!(!failed.truncated);
+    }
+
+    #[test]
+    fn test_from_server() {
+        let record = ToolInvocation::from_server(&serde_json::json!({
+            "name": "calculator",
+            "arguments": "{\"expression\": \"2+2\"}",
+            "duration_ms": 3,
+            "output": "4"
+        }))
+        .unwrap();
+        assert_eq!(record.tool, "calculator");
+        assert_eq!(record.arguments["expression"], "2+2");
+        assert_eq!(record.output, "4");
+        assert_eq!(record.duration_ms, 3);
+
+        assert!(ToolInvocation::from_server(&serde_json::json!({"output": "x"})).is_none());
+    }
+}
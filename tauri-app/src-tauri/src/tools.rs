@@ -0,0 +1,336 @@
+// Tools Module - Tools the model can call before it replies
+//
+// When tools are on for the current mode, the model is first shown the
+// message with the list of tools and either answers NONE or writes one call,
+// in the format its chat template knows (`capabilities::ToolCallFormat`; a
+// bare JSON object when the format is unknown). The tool runs and its output
+// joins the reply's system prompt as numbered sources, which the reply cites
+// as [1], [2]... Each run is recorded as a `ToolInvocation` on the reply.
+// The decision isn't streamed, so call syntax never shows up in the chat.
+// Models whose template has no tool format are not asked. Which tools are on,
+// and for which modes, is kept in the `[tools]` section of settings.toml.
+// Tools that touch the user's machine ask for confirmation in the chat window
+// first.
+
+use crate::backend::{self, GenerationRequest};
+use crate::capabilities::{self, Feature, ToolCallFormat};
+use crate::file_read::{FileRead, FileReadSettings};
+use crate::generation::CancellationToken;
+use crate::memory_store::MemoryStore;
+use crate::privacy;
+use crate::session::SessionIds;
+use crate::settings::{self, AppSettings};
+use crate::structured;
+use crate::task_presets::{self, Task};
+use crate::tool_calls::{self, ToolInvocation};
+use crate::web_search::{WebSearch, WebSearchSettings};
+use crate::{AppMode, AppState, ConversationEntry};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// History entries the model sees when deciding, so follow-ups make sense
+const DECISION_HISTORY: usize = 4;
+
+/// What a tool can reach while it runs
+pub struct ToolContext {
+    pub memory_store: Arc<Mutex<MemoryStore>>,
+    pub session: SessionIds,
+    /// Window to ask the user for confirmation in
+    pub window: Option<tauri::Window>,
+    /// Privacy mode: nothing may be written to the memory store
+    pub private: bool,
+}
+
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// One line telling the model when to use it
+    fn description(&self) -> &'static str;
+
+    /// JSON Schema of the arguments
+    fn parameters(&self) -> Value;
+
+    /// Run with the model's arguments, returning text for the reply's prompt
+    fn run(&self, arguments: &Value, context: &ToolContext) -> Result<String>;
+}
+
+/// Which tools are on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolSettings {
+    /// Modes (ids) whose replies may use tools
+    pub modes: Vec<String>,
+    pub web_search: WebSearchSettings,
+    pub file_read: FileReadSettings,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            modes: vec!["companion".to_string()],
+            web_search: WebSearchSettings::default(),
+            file_read: FileReadSettings::default(),
+        }
+    }
+}
+
+impl ToolSettings {
+    pub fn load() -> Self {
+        AppSettings::load().tools
+    }
+
+    pub fn save(&self) -> Result<()> {
+        settings::update(|settings| settings.tools = self.clone()).map(|_| ())
+    }
+
+    /// Tools that are on for `mode`
+    pub fn tools_for(&self, mode: &AppMode) -> Vec<Box<dyn Tool>> {
+        if !self.modes.contains(&mode.to_string()) {
+            return Vec::new();
+        }
+        let mut tools: Vec<Box<dyn Tool>> = Vec::new();
+        if self.web_search.enabled {
+            tools.push(Box::new(WebSearch::new(self.web_search.clone())));
+        }
+        if self.file_read.enabled && !self.file_read.allowed_paths.is_empty() {
+            tools.push(Box::new(FileRead::new(self.file_read.clone())));
+        }
+        tools
+    }
+}
+
+/// A call the model asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// How the model should write a call
+fn call_syntax(format: ToolCallFormat) -> &'static str {
+    match format {
+        ToolCallFormat::Hermes => r#"<tool_call>{"name": "<tool>", "arguments": {...}}</tool_call>"#,
+        ToolCallFormat::Llama3 => r#"<|python_tag|>{"name": "<tool>", "parameters": {...}}"#,
+        ToolCallFormat::Mistral => r#"[TOOL_CALLS][{"name": "<tool>", "arguments": {...}}]"#,
+        ToolCallFormat::Generic => r#"{"name": "<tool>", "arguments": {...}}"#,
+    }
+}
+
+/// System prompt asking the model whether to call one of `tools`
+pub fn decision_prompt(tools: &[Box<dyn Tool>], format: ToolCallFormat) -> String {
+    let listed: Vec<String> = tools
+        .iter()
+        .map(|tool| format!("- {}: {}\n  Arguments: {}", tool.name(), tool.description(), tool.parameters()))
+        .collect();
+    format!(
+        "Before the assistant answers the user's last message, decide whether one of these tools is needed:\n\
+        {}\n\n\
+        If a tool would help, reply with exactly one call and nothing else:\n{}\n\
+        Otherwise reply NONE. Use a tool only for information you don't have, such as recent events.",
+        listed.join("\n"),
+        call_syntax(format)
+    )
+}
+
+/// The tool call in the model's reply, in any of the known formats
+pub fn parse_call(text: &str) -> Option<ToolCall> {
+    let text = text.trim();
+    if text.is_empty() || text.to_uppercase().starts_with("NONE") {
+        return None;
+    }
+    let body = match text.find("<tool_call>") {
+        Some(start) => {
+            let rest = &text[start + "<tool_call>".len()..];
+            rest.split("</tool_call>").next().unwrap_or(rest)
+        }
+        None => text
+            .split_once("<|python_tag|>")
+            .or_else(|| text.split_once("[TOOL_CALLS]"))
+            .map_or(text, |(_, rest)| rest),
+    };
+    let value = structured::parse_reply(body).ok()?;
+    let call = match value {
+        Value::Array(calls) => calls.into_iter().next()?,
+        call => call,
+    };
+    let name = call["name"].as_str()?.to_string();
+    let arguments = match call.get("arguments").or_else(|| call.get("parameters")) {
+        // Some templates send the arguments JSON-encoded
+        Some(Value::String(raw)) => serde_json::from_str(raw).ok()?,
+        Some(arguments) => arguments.clone(),
+        None => Value::Object(Default::default()),
+    };
+    Some(ToolCall { name, arguments })
+}
+
+/// A tool that ran for a message
+#[derive(Debug, Clone)]
+pub struct ToolRun {
+    pub invocation: ToolInvocation,
+    /// Full output, unless the tool failed
+    pub output: Option<String>,
+}
+
+/// Ask the model whether `message` needs a tool, and run it if so
+pub fn decide_and_run(
+    backend: &mut dyn backend::LlmBackend,
+    tools: &[Box<dyn Tool>],
+    message: &str,
+    history: &[ConversationEntry],
+    context: &ToolContext,
+) -> Result<Option<ToolRun>> {
+    let format = capabilities::current()
+        .and_then(|capabilities| capabilities.tool_calls)
+        .unwrap_or(ToolCallFormat::Generic);
+    let system_prompt = decision_prompt(tools, format);
+    let config = task_presets::config_for(Task::ToolUse);
+    let request = GenerationRequest {
+        prompt: message,
+        system_prompt: &system_prompt,
+        history,
+        config: &config,
+    };
+    let decision = backend.generate(&request, &CancellationToken::new(), &mut |_| true)?;
+    let Some(call) = parse_call(&decision.text) else {
+        return Ok(None);
+    };
+    let tool = tools
+        .iter()
+        .find(|tool| tool.name() == call.name)
+        .ok_or_else(|| anyhow!("The model asked for an unknown tool: {}", call.name))?;
+    info!("Running {} {}", call.name, call.arguments);
+    let (invocation, result) = tool_calls::invoke(tool.name(), call.arguments, |arguments| {
+        tool.run(arguments, context)
+    });
+    if let Err(e) = &result {
+        warn!("{} failed: {:#}", tool.name(), e);
+    }
+    Ok(Some(ToolRun {
+        invocation,
+        output: result.ok(),
+    }))
+}
+
+/// Run any tool the model wants for `message` before the reply (nothing if
+/// tools are off for `mode`, or the model can't call them)
+pub async fn run_before_reply(
+    state: &AppState,
+    window: &tauri::Window,
+    mode: &AppMode,
+    message: &str,
+) -> Option<ToolRun> {
+    let tools = ToolSettings::load().tools_for(mode);
+    if tools.is_empty() {
+        return None;
+    }
+    if let Err(e) = capabilities::require_current(Feature::ToolCalls) {
+        warn!("Tools skipped: {}", e);
+        return None;
+    }
+    let history = {
+        let history = state.conversation_history.lock();
+        history[history.len().saturating_sub(DECISION_HISTORY)..].to_vec()
+    };
+    let context = ToolContext {
+        memory_store: state.memory_store.clone(),
+        session: state.session.lock().clone(),
+        window: Some(window.clone()),
+        private: privacy::is_private(state),
+    };
+    let llm = state.llm.clone();
+    let message = message.to_string();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut llm = llm.blocking_lock();
+        let active = llm.get_or_insert_with(|| backend::select_backend(&|_| {}));
+        decide_and_run(active.as_mut(), &tools, &message, &history, &context)
+    })
+    .await;
+    match result {
+        Ok(Ok(run)) => run,
+        Ok(Err(e)) => {
+            warn!("Tool step failed: {:#}", e);
+            None
+        }
+        Err(e) => {
+            warn!("Tool step failed: {}", e);
+            None
+        }
+    }
+}
+
+/// `system_prompt` with a tool's output added as sources to cite
+pub fn with_output(system_prompt: &str, run: Option<&ToolRun>) -> String {
+    match run {
+        Some(ToolRun {
+            invocation,
+            output: Some(output),
+        }) => format!(
+            "{}\n\nOutput of {} for the user's last message. Use it where it helps, and cite \
+            numbered sources you use as [n]:\n{}",
+            system_prompt, invocation.tool, output
+        ),
+        Some(ToolRun { invocation, output: None }) => format!(
+            "{}\n\n{} failed for the user's last message; say so if the answer depended on it.",
+            system_prompt, invocation.tool
+        ),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// Current tool settings (the Brave API key is never sent back)
+#[tauri::command]
+pub async fn get_tool_settings() -> Result<ToolSettings, String> {
+    let mut settings = ToolSettings::load();
+    settings.web_search.brave_api_key = None;
+    Ok(settings)
+}
+
+/// Replace the tool settings; an unset Brave API key keeps the saved one
+#[tauri::command]
+pub async fn set_tool_settings(mut settings: ToolSettings) -> Result<ToolSettings, String> {
+    settings.web_search.validate().map_err(|e| e.to_string())?;
+    settings.file_read.validate().map_err(|e| e.to_string())?;
+    if settings.web_search.brave_api_key.is_none() {
+        settings.web_search.brave_api_key = ToolSettings::load().web_search.brave_api_key;
+    }
+    settings.save().map_err(|e| e.to_string())?;
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+    info!(
+        "Tool settings updated (web search {}, file read {} in {} folder(s))",
+        on_off(settings.web_search.enabled),
+        on_off(settings.file_read.enabled),
+        settings.file_read.allowed_paths.len()
+    );
+    settings.web_search.brave_api_key = None;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_call_formats() {
+        let expected = ToolCall {
+            name: "web_search".to_string(),
+            arguments: json!({ "query": "eclipse date" }),
+        };
+        let replies = [
+            r#"<tool_call>{"name": "web_search", "arguments": {"query": "eclipse date"}}</tool_call>"#,
+            r#"<|python_tag|>{"name": "web_search", "parameters": {"query": "eclipse date"}}"#,
+            r#"[TOOL_CALLS][{"name": "web_search", "arguments": {"query": "eclipse date"}}]"#,
+            r#"Sure: {"name": "web_search", "arguments": "{\"query\": \"eclipse date\"}"}"#,
+        ];
+        for reply in replies {
+            assert_eq!(parse_call(reply), Some(expected.clone()), "{}", reply);
+        }
+        assert_eq!(parse_call("NONE"), None);
+        assert_eq!(parse_call("None needed."), None);
+        assert_eq!(parse_call("The eclipse is on Tuesday."), None);
+    }
+}
@@ -0,0 +1,347 @@
+// Trash Module - Soft deletes for memories, documents and sessions
+//
+// Deleting moves the memories (with their vectors) and, for a session, its
+// archived transcript into `trash/<id>.json` (sealed like the history when
+// encryption at rest is on). Nothing in the trash is
+// searched or listed, but it can be restored exactly as it was until it is
+// purged, `purge_after_days` after deletion.
+
+use crate::history_store::ArchivedSession;
+use crate::ingest::document_filters;
+use crate::memory_policy::current_scope;
+use crate::memory_store::{MemoryFilters, MemoryItem, MemoryStore};
+use crate::{encryption, paths, AppState};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::info;
+
+/// Characters of a deleted memory shown in the trash listing
+const LABEL_CHARS: usize = 80;
+
+/// How long deleted items are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// Days before deleted items are purged for good (0 = never)
+    pub purge_after_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { purge_after_days: 30 }
+    }
+}
+
+impl TrashSettings {
+    const FILE: &'static str = "trash.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    /// When an item deleted at `deleted_at` is purged, if ever
+    pub fn purge_at(&self, deleted_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (self.purge_after_days > 0).then(|| deleted_at + chrono::Duration::days(self.purge_after_days as i64))
+    }
+}
+
+/// What was deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Memory,
+    Document,
+    Session,
+}
+
+/// A deleted item, everything needed to restore it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub kind: TrashKind,
+    /// Memory preview, document id or session run id
+    pub label: String,
+    pub deleted_at: DateTime<Utc>,
+    pub memories: Vec<MemoryItem>,
+    #[serde(default)]
+    pub session: Option<ArchivedSession>,
+}
+
+/// Trash listing entry
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashSummary {
+    pub id: String,
+    pub kind: TrashKind,
+    pub label: String,
+    pub deleted_at: DateTime<Utc>,
+    pub memory_count: usize,
+    pub purge_at: Option<DateTime<Utc>>,
+}
+
+impl TrashItem {
+    fn new(kind: TrashKind, label: String, memories: Vec<MemoryItem>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            label,
+            deleted_at: crate::clock::now(),
+            memories,
+            session: None,
+        }
+    }
+
+    fn summary(&self, settings: &TrashSettings) -> TrashSummary {
+        TrashSummary {
+            id: self.id.clone(),
+            kind: self.kind,
+            label: self.label.clone(),
+            deleted_at: self.deleted_at,
+            memory_count: self.memories.len(),
+            purge_at: settings.purge_at(self.deleted_at),
+        }
+    }
+}
+
+fn trash_dir() -> PathBuf {
+    paths::app_data_dir().join("trash")
+}
+
+fn item_path(id: &str) -> Result<PathBuf> {
+    // Trash ids are UUIDs; anything else could escape the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("Invalid trash id: {}", id));
+    }
+    Ok(trash_dir().join(format!("{}.json", id)))
+}
+
+fn save(item: &TrashItem) -> Result<()> {
+    std::fs::create_dir_all(trash_dir())?;
+    encryption::write(&item_path(&item.id)?, serde_json::to_string(item)?.as_bytes())
+}
+
+fn load(id: &str) -> Result<TrashItem> {
+    let path = item_path(id)?;
+    let json = encryption::read_to_string(&path).with_context(|| format!("Nothing in the trash with id {}", id))?;
+    serde_json::from_str(&json).context("Failed to parse trash item")
+}
+
+/// Every item in the trash (unreadable files are skipped)
+fn list() -> Vec<TrashItem> {
+    std::fs::read_dir(trash_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| encryption::read_to_string(&entry.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect()
+}
+
+/// Take the memories matching `filters` out of the store
+pub fn take_memories(store: &mut MemoryStore, filters: &MemoryFilters) -> Vec<MemoryItem> {
+    let memories = store.get_all(filters, usize::MAX);
+    for memory in &memories {
+        store.delete(&memory.id);
+    }
+    memories
+}
+
+/// Move one memory to the trash
+///
+/// The active persona must be allowed to modify it (see `AccessScope`).
+pub fn trash_memory(state: &AppState, memory_id: &str) -> Result<TrashSummary> {
+    let scope = current_scope(state);
+    let memory = {
+        let mut store = state.memory_store.lock();
+        let memory = store
+            .get(memory_id)
+            .filter(|memory| scope.allows(memory))
+            .ok_or_else(|| anyhow!("No memory {}", memory_id))?;
+        if !scope.can_modify(&memory) {
+            return Err(anyhow!("The {} persona can't change the user profile", scope.agent_id));
+        }
+        store.delete(memory_id);
+        memory
+    };
+    let label = memory.content.chars().take(LABEL_CHARS).collect();
+    let item = TrashItem::new(TrashKind::Memory, label, vec![memory]);
+    save(&item)?;
+    Ok(item.summary(&TrashSettings::load()))
+}
+
+/// Move an ingested document's chunks to the trash
+pub fn trash_document(state: &AppState, doc_id: &str) -> Result<TrashSummary> {
+    let chunks = take_memories(&mut state.memory_store.lock(), &document_filters(doc_id));
+    if chunks.is_empty() {
+        return Err(anyhow!("No ingested document {}", doc_id));
+    }
+    let item = TrashItem::new(TrashKind::Document, doc_id.to_string(), chunks);
+    save(&item)?;
+    Ok(item.summary(&TrashSettings::load()))
+}
+
+/// Move an archived session and its memories to the trash
+pub fn trash_session(state: &AppState, run_id: &str) -> Result<TrashSummary> {
+    if state.session.lock().run_id == run_id {
+        return Err(anyhow!("The current session can't be deleted"));
+    }
+    let archived = state.history_store.remove_archived(run_id)?;
+    let filters = MemoryFilters {
+        run_id: Some(run_id.to_string()),
+        ..Default::default()
+    };
+    let memories = take_memories(&mut state.memory_store.lock(), &filters);
+
+    let mut item = TrashItem::new(TrashKind::Session, run_id.to_string(), memories);
+    item.session = Some(archived);
+    if let Err(e) = save(&item) {
+        // Don't lose the session if the trash can't be written
+        restore_item(state, &item)?;
+        return Err(e);
+    }
+    Ok(item.summary(&TrashSettings::load()))
+}
+
+fn restore_item(state: &AppState, item: &TrashItem) -> Result<()> {
+    if let Some(session) = &item.session {
+        state.history_store.restore_archived(session)?;
+    }
+    let mut store = state.memory_store.lock();
+    for memory in &item.memories {
+        store.restore(memory.clone());
+    }
+    Ok(())
+}
+
+/// Put a trashed item back where it was
+pub fn restore(state: &AppState, id: &str) -> Result<TrashItem> {
+    let item = load(id)?;
+    restore_item(state, &item)?;
+    std::fs::remove_file(item_path(id)?).context("Failed to remove restored trash item")?;
+    Ok(item)
+}
+
+/// Permanently delete trashed items holding `user_id`'s memories or
+/// sessions; returns how many
+pub fn purge_user(user_id: &str) -> Result<usize> {
+    let mut removed = 0;
+    for item in list() {
+        let owned = item.memories.iter().any(|memory| memory.user_id.as_deref() == Some(user_id))
+            || item.session.as_ref().is_some_and(|archived| archived.session.user_id == user_id);
+        if owned {
+            std::fs::remove_file(item_path(&item.id)?).context("Failed to delete trash item")?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Delete items whose purge date has passed; returns how many
+pub fn purge_expired(now: DateTime<Utc>) -> usize {
+    let settings = TrashSettings::load();
+    list()
+        .into_iter()
+        .filter(|item| settings.purge_at(item.deleted_at).is_some_and(|at| at <= now))
+        .filter(|item| item_path(&item.id).is_ok_and(|path| std::fs::remove_file(path).is_ok()))
+        .count()
+}
+
+/// Purge expired trash in the background
+pub fn spawn_purge() {
+    std::thread::spawn(|| match purge_expired(crate::clock::now()) {
+        0 => {}
+        purged => info!("Purged {} expired item(s) from the trash", purged),
+    });
+}
+
+/// Move a memory to the trash
+#[tauri::command]
+pub async fn delete_memory(memory_id: String, state: tauri::State<'_, AppState>) -> Result<TrashSummary, String> {
+    let summary = trash_memory(&state, &memory_id).map_err(|e| e.to_string())?;
+    info!("Moved memory {} to the trash", memory_id);
+    Ok(summary)
+}
+
+/// Move an ingested document (all its chunks) to the trash
+#[tauri::command]
+pub async fn delete_document(doc_id: String, state: tauri::State<'_, AppState>) -> Result<TrashSummary, String> {
+    let summary = trash_document(&state, &doc_id).map_err(|e| e.to_string())?;
+    info!("Moved document {} ({} chunks) to the trash", doc_id, summary.memory_count);
+    Ok(summary)
+}
+
+/// Move an archived session and its memories to the trash
+#[tauri::command]
+pub async fn delete_session(run_id: String, state: tauri::State<'_, AppState>) -> Result<TrashSummary, String> {
+    let summary = trash_session(&state, &run_id).map_err(|e| e.to_string())?;
+    info!("Moved session {} to the trash", run_id);
+    Ok(summary)
+}
+
+/// Items in the trash, most recently deleted first
+#[tauri::command]
+pub async fn list_trash() -> Result<Vec<TrashSummary>, String> {
+    let settings = TrashSettings::load();
+    let mut items: Vec<TrashSummary> = list().iter().map(|item| item.summary(&settings)).collect();
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+/// Restore an item from the trash
+#[tauri::command]
+pub async fn restore_from_trash(id: String, state: tauri::State<'_, AppState>) -> Result<TrashSummary, String> {
+    let item = restore(&state, &id).map_err(|e| e.to_string())?;
+    info!("Restored {:?} {} from the trash", item.kind, item.label);
+    Ok(item.summary(&TrashSettings::load()))
+}
+
+/// Permanently delete everything in the trash; returns how many items
+#[tauri::command]
+pub async fn empty_trash() -> Result<usize, String> {
+    let mut removed = 0;
+    for item in list() {
+        let path = item_path(&item.id).map_err(|e| e.to_string())?;
+        std::fs::remove_file(path).map_err(|e| e.to_string())?;
+        removed += 1;
+    }
+    info!("Emptied the trash ({} items)", removed);
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::HashingEmbedder;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trashed_memories_restore_intact() {
+        let mut store = MemoryStore::new().with_embedder(Arc::new(HashingEmbedder::default()));
+        let run = Some("run-1".to_string());
+        store.add("Tomatoes in the garden", None, None, None, HashMap::new()).unwrap();
+        store.add("Basil in the garden", None, None, run.clone(), HashMap::new()).unwrap();
+
+        let filters = MemoryFilters {
+            run_id: run,
+            ..Default::default()
+        };
+        let taken = take_memories(&mut store, &filters);
+        assert_eq!(taken.len(), 1);
+        let id = taken[0].id.clone();
+        assert!(store.get(&id).is_none());
+        assert!(store.search("basil", None, 10).is_empty());
+
+        // Same id, and searchable again
+        store.restore(taken[0].clone());
+        assert_eq!(store.count(), 2);
+        assert_eq!(store.get(&id).unwrap().content, "Basil in the garden");
+        assert_eq!(store.search("basil", None, 1)[0].id, id);
+
+        let settings = TrashSettings { purge_after_days: 30 };
+        let deleted = Utc::now();
+        assert_eq!(settings.purge_at(deleted), Some(deleted + chrono::Duration::days(30)));
+        assert_eq!(TrashSettings { purge_after_days: 0 }.purge_at(deleted), None);
+    }
+}
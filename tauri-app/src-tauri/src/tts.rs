@@ -0,0 +1,313 @@
+// TTS Module - Read-aloud of responses while they are still generating
+//
+// `SentenceScheduler` turns the token stream into sentences as soon as the
+// segmenter sees a boundary followed by more text, so the first sentence is
+// queued while later tokens are still arriving. `TtsQueue` synthesizes queued
+// sentences in order on a worker thread through the Piper server (tts/piper)
+// and emits each clip as a `tts-audio` event for the UI to play back in
+// `index` order. Starting a new generation or stopping drops anything still
+// queued from the previous one.
+
+use crate::sentence_segmenter::{Segmentation, SentenceSegmenter};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// Where tts/piper/piper_serve.py listens by default
+pub const DEFAULT_TTS_URL: &str = "http://127.0.0.1:59125";
+
+/// Read-aloud preferences
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TtsSettings {
+    pub enabled: bool,
+    pub server_url: String,
+    /// Piper voice name; the server default if unset
+    pub voice: Option<String>,
+    /// Short sentences ("Sure!") are joined with the next one up to this length
+    pub min_sentence_chars: usize,
+}
+
+impl Default for TtsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server_url: DEFAULT_TTS_URL.to_string(),
+            voice: None,
+            min_sentence_chars: 24,
+        }
+    }
+}
+
+impl TtsSettings {
+    const FILE: &'static str = "tts.json";
+
+    pub fn load() -> Self {
+        crate::settings::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        crate::settings::save_json(Self::FILE, self)
+    }
+}
+
+/// Splits streamed text into speakable sentences as they complete
+pub struct SentenceScheduler {
+    segmenter: SentenceSegmenter,
+    buffer: String,
+    min_chars: usize,
+}
+
+impl SentenceScheduler {
+    pub fn new(min_chars: usize) -> Self {
+        Self {
+            segmenter: SentenceSegmenter::new(Segmentation::default()),
+            buffer: String::new(),
+            min_chars,
+        }
+    }
+
+    /// Add a token; returns the sentences it completed
+    ///
+    /// The last sentence in the buffer is held back because later tokens may
+    /// still extend it (or turn its final "Dr." into an abbreviation).
+    pub fn push(&mut self, token: &str) -> Vec<String> {
+        self.buffer.push_str(token);
+        let sentences = self.segmenter.split(&self.buffer);
+        let Some((_, complete)) = sentences.split_last() else {
+            return Vec::new();
+        };
+
+        let mut ready = Vec::new();
+        let mut consumed = 0;
+        let mut pending = 0;
+        for sentence in complete {
+            pending += sentence.len();
+            let text = speakable(&self.buffer[consumed..consumed + pending]);
+            if text.chars().count() >= self.min_chars {
+                ready.push(text);
+                consumed += pending;
+                pending = 0;
+            }
+        }
+        self.buffer.drain(..consumed);
+        ready
+    }
+
+    /// Whatever is left once generation has finished
+    pub fn finish(&mut self) -> Option<String> {
+        let text = speakable(&std::mem::take(&mut self.buffer));
+        Some(text).filter(|text| !text.is_empty())
+    }
+}
+
+/// Strip markdown markers that would otherwise be read out
+fn speakable(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !matches!(c, '*' | '_' | '`' | '#'))
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A synthesized sentence, emitted as `tts-audio`
+#[derive(Debug, Clone, Serialize)]
+pub struct TtsClip {
+    pub generation_id: String,
+    /// Playback order within the generation
+    pub index: usize,
+    pub text: String,
+    /// WAV audio, base64 encoded
+    pub audio: String,
+}
+
+struct TtsJob {
+    epoch: u64,
+    window: tauri::Window,
+    settings: TtsSettings,
+    clip: TtsClip,
+}
+
+/// Sentences waiting to be synthesized, in order
+pub struct TtsQueue {
+    sender: Sender<TtsJob>,
+    /// Bumped to drop every job queued before it
+    epoch: Arc<AtomicU64>,
+}
+
+impl TtsQueue {
+    /// Spawn the synthesis worker
+    pub fn start() -> Arc<Self> {
+        let (sender, receiver) = mpsc::channel::<TtsJob>();
+        let epoch = Arc::new(AtomicU64::new(0));
+
+        let current = epoch.clone();
+        std::thread::spawn(move || {
+            let client = match reqwest::blocking::Client::builder()
+                .connect_timeout(Duration::from_secs(2))
+                .timeout(Duration::from_secs(60))
+                .build()
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("TTS worker failed to start: {}", e);
+                    return;
+                }
+            };
+
+            for mut job in receiver {
+                if job.epoch != current.load(Ordering::SeqCst) {
+                    continue;
+                }
+                match synthesize(&client, &job.settings, &job.clip.text) {
+                    // The generation may have been stopped while synthesizing
+                    Ok(_) if job.epoch != current.load(Ordering::SeqCst) => {}
+                    Ok(audio) => {
+                        job.clip.audio = BASE64.encode(&audio);
+                        let _ = job.window.emit("tts-audio", &job.clip);
+                    }
+                    Err(e) => warn!("TTS failed for sentence {}: {}", job.clip.index, e),
+                }
+            }
+        });
+
+        Arc::new(Self { sender, epoch })
+    }
+
+    /// Start reading a new generation aloud, dropping anything still queued
+    ///
+    /// Returns `None` when read-aloud is disabled.
+    pub fn begin(&self, window: tauri::Window, generation_id: &str) -> Option<TtsSpeaker> {
+        let settings = TtsSettings::load();
+        if !settings.enabled {
+            return None;
+        }
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst) + 1;
+        Some(TtsSpeaker {
+            scheduler: SentenceScheduler::new(settings.min_sentence_chars),
+            sender: self.sender.clone(),
+            epoch,
+            window,
+            settings,
+            generation_id: generation_id.to_string(),
+            next_index: 0,
+        })
+    }
+
+    /// Drop every queued sentence
+    pub fn stop(&self) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Feeds one generation's tokens into the queue
+pub struct TtsSpeaker {
+    scheduler: SentenceScheduler,
+    sender: Sender<TtsJob>,
+    epoch: u64,
+    window: tauri::Window,
+    settings: TtsSettings,
+    generation_id: String,
+    next_index: usize,
+}
+
+impl TtsSpeaker {
+    pub fn push_token(&mut self, token: &str) {
+        for sentence in self.scheduler.push(token) {
+            self.enqueue(sentence);
+        }
+    }
+
+    /// Queue the final sentence
+    pub fn finish(mut self) {
+        if let Some(sentence) = self.scheduler.finish() {
+            self.enqueue(sentence);
+        }
+    }
+
+    fn enqueue(&mut self, text: String) {
+        let job = TtsJob {
+            epoch: self.epoch,
+            window: self.window.clone(),
+            settings: self.settings.clone(),
+            clip: TtsClip {
+                generation_id: self.generation_id.clone(),
+                index: self.next_index,
+                text,
+                audio: String::new(),
+            },
+        };
+        self.next_index += 1;
+        let _ = self.sender.send(job);
+    }
+}
+
+/// WAV audio for `text` from the Piper HTTP server
+fn synthesize(client: &reqwest::blocking::Client, settings: &TtsSettings, text: &str) -> Result<Vec<u8>> {
+    let mut query = vec![("text", text)];
+    if let Some(voice) = &settings.voice {
+        query.push(("voice", voice.as_str()));
+    }
+    let response = client
+        .get(settings.server_url.trim_end_matches('/'))
+        .query(&query)
+        .send()
+        .context("TTS server not reachable")?;
+    if !response.status().is_success() {
+        return Err(anyhow!("TTS server returned {}", response.status()));
+    }
+    Ok(response.bytes()?.to_vec())
+}
+
+#[tauri::command]
+pub async fn get_tts_settings() -> Result<TtsSettings, String> {
+    Ok(TtsSettings::load())
+}
+
+#[tauri::command]
+pub async fn set_tts_settings(settings: TtsSettings) -> Result<(), String> {
+    settings.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(scheduler: &mut SentenceScheduler, text: &str) -> Vec<String> {
+        // Stream a few characters at a time, like an LLM would
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .chunks(3)
+            .flat_map(|chunk| scheduler.push(&chunk.iter().collect::<String>()))
+            .collect()
+    }
+
+    #[test]
+    fn test_first_sentence_ready_before_the_end() {
+        let mut scheduler = SentenceScheduler::new(10);
+        let ready = feed(&mut scheduler, "The dragon woke up slowly. It was hungry. And then");
+        assert_eq!(ready, vec!["The dragon woke up slowly.", "It was hungry."]);
+        assert_eq!(scheduler.finish().as_deref(), Some("And then"));
+        assert_eq!(scheduler.finish(), None);
+    }
+
+    #[test]
+    fn test_short_sentences_are_joined() {
+        let mut scheduler = SentenceScheduler::new(20);
+        let ready = feed(&mut scheduler, "Sure! I can **help** with that today. Next");
+        assert_eq!(ready, vec!["Sure! I can help with that today."]);
+    }
+
+    #[test]
+    fn test_abbreviation_is_not_a_sentence_end() {
+        let mut scheduler = SentenceScheduler::new(1);
+        let ready = feed(&mut scheduler, "Ask Dr. Smith tomorrow. Bye");
+        assert_eq!(ready, vec!["Ask Dr. Smith tomorrow."]);
+    }
+}
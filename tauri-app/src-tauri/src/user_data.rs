@@ -0,0 +1,411 @@
+// User Data Module - Export everything, or wipe a user's data
+//
+// `export_all_data` writes one zip holding the conversation files (history,
+// archives and parked conversations), every memory as JSON and the settings
+// files, so users can take their data elsewhere. Encrypted files are written
+// to the zip decrypted; embeddings are left out, and so are secrets: the
+// encryption settings are skipped and credentials in settings files (a remote
+// backend's API key, the MCP token) are blanked. `delete_all_user_data` is the
+// GDPR-style erase: it purges a user's memories (and their recorded versions)
+// from the `MemoryStore`, their conversations (current, parked and archived)
+// with the running and topic summaries and attachments that belong to them, whatever
+// of theirs is in the trash, asks the backend to drop what The Nexus Core
+// logged, and removes log lines that mention the user. The entity index is
+// rebuilt from the transcripts left, and digest notes (which summarize the
+// local user's week) are deleted with the local user's data. It returns
+// counts of what was removed.
+
+use crate::entities::{self, EntityIndex};
+use crate::memory_store::{MemoryFilters, MemoryItem};
+use crate::{compaction, digest, encryption, logging, paths, rolling_summary, trash, AppState};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Directories (under the app data dir) exported as conversations
+const CONVERSATION_DIRS: &[&str] = &["history", "conversations"];
+/// Top-level settings files left out of the export
+const EXCLUDED_SETTINGS: &[&str] = &["encryption.json"];
+/// settings.toml sections left out of the export
+const EXCLUDED_SECTIONS: &[&str] = &["encryption"];
+/// Settings keys whose values are blanked in the export
+const SECRET_KEYS: &[&str] = &["api_key", "brave_api_key", "token"];
+
+/// What went into an export
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub conversation_files: usize,
+    pub memories: usize,
+    pub settings_files: usize,
+}
+
+/// What `delete_all_user_data` removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeletedData {
+    pub memories: usize,
+    /// Earlier versions of the memories (see `memory_history`)
+    pub memory_versions: usize,
+    /// Transcripts: archived sessions, parked conversations and the current
+    /// history
+    pub conversations: usize,
+    pub rolling_summaries: usize,
+    /// Sessions' topic summaries (see `compaction`)
+    pub compactions: usize,
+    /// Attachment files
+    pub attachments: usize,
+    pub trash_items: usize,
+    /// Names dropped from the entity index
+    pub entities: usize,
+    pub digest_notes: usize,
+    /// Nexus Core records; `None` if the active backend keeps none (or no
+    /// backend is running)
+    pub nexus_core: Option<usize>,
+    pub log_entries: usize,
+}
+
+/// Files directly in `dir` that hold settings
+fn settings_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("json" | "toml")))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            !EXCLUDED_SETTINGS.contains(&name)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn blank_json_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = serde_json::Value::String(String::new());
+                } else {
+                    blank_json_secrets(value);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(blank_json_secrets),
+        _ => {}
+    }
+}
+
+fn blank_toml_secrets(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    *value = toml::Value::String(String::new());
+                } else {
+                    blank_toml_secrets(value);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(blank_toml_secrets),
+        _ => {}
+    }
+}
+
+/// A settings file as exported: with `SECRET_KEYS` blanked (a file that
+/// can't be parsed is left out rather than risk copying a secret)
+fn export_settings(path: &Path) -> Result<Option<Vec<u8>>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let scrubbed = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str::<serde_json::Value>(&text).ok().map(|mut value| {
+            blank_json_secrets(&mut value);
+            serde_json::to_vec_pretty(&value)
+        }),
+        _ => text.parse::<toml::Value>().ok().map(|mut value| {
+            if let Some(table) = value.as_table_mut() {
+                for section in EXCLUDED_SECTIONS {
+                    table.remove(*section);
+                }
+            }
+            blank_toml_secrets(&mut value);
+            Ok(toml::to_string_pretty(&value).unwrap_or_default().into_bytes())
+        }),
+    };
+    Ok(scrubbed.transpose()?)
+}
+
+/// Name of `path` inside the zip, relative to `root` with `/` separators
+fn zip_name(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Write the zip for the data in `root` (and `memories`) to `path`
+pub fn export_to(root: &Path, memories: &[MemoryItem], path: &Path) -> Result<ExportSummary> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut summary = ExportSummary {
+        path: path.to_string_lossy().into_owned(),
+        conversation_files: 0,
+        memories: memories.len(),
+        settings_files: 0,
+    };
+
+    for dir in CONVERSATION_DIRS {
+        let files = WalkDir::new(root.join(dir))
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) != Some("tmp"));
+        for entry in files {
+            let contents = encryption::read(entry.path())?;
+            zip.start_file(zip_name(root, entry.path()), options)?;
+            zip.write_all(&contents)?;
+            summary.conversation_files += 1;
+        }
+    }
+
+    let memories: Vec<MemoryItem> = memories
+        .iter()
+        .cloned()
+        .map(|memory| MemoryItem { embedding: None, ..memory })
+        .collect();
+    zip.start_file("memories.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&memories)?)?;
+
+    for settings in settings_files(root) {
+        let Some(contents) = export_settings(&settings)? else {
+            warn!("Left {} out of the export: unreadable settings", settings.display());
+            continue;
+        };
+        zip.start_file(format!("settings/{}", zip_name(root, &settings)), options)?;
+        zip.write_all(&contents)?;
+        summary.settings_files += 1;
+    }
+
+    zip.finish().context("Failed to finish the export")?;
+    Ok(summary)
+}
+
+/// Remove lines mentioning `user_id` from the log files in `dir`
+///
+/// Files are rewritten in place, since the current one is still open for
+/// appending.
+pub fn purge_logs(dir: &Path, user_id: &str) -> Result<usize> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if !path.is_file() {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let kept: Vec<&str> = text.lines().filter(|line| !line.contains(user_id)).collect();
+        let dropped = text.lines().count() - kept.len();
+        if dropped == 0 {
+            continue;
+        }
+        let mut contents = kept.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        std::fs::write(&path, contents).with_context(|| format!("Failed to rewrite {}", path.display()))?;
+        removed += dropped;
+    }
+    Ok(removed)
+}
+
+/// Delete the attachments of session `run_id`; returns how many files
+fn remove_attachments(run_id: &str) -> Result<usize> {
+    // Run ids are UUIDs; anything else could escape the directory
+    if run_id.is_empty() || !run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Ok(0);
+    }
+    let dir = paths::attachments_dir().join(run_id);
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let files = WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .count();
+    std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))?;
+    Ok(files)
+}
+
+/// Remove `user_id`'s conversations and what is kept about them
+fn purge_conversations(state: &AppState, user_id: &str, deleted: &mut DeletedData) -> Result<()> {
+    let mut run_ids = state.history_store.remove_user_archives(user_id)?;
+    run_ids.extend(state.conversations.lock().remove_user(user_id)?);
+    deleted.conversations = run_ids.len();
+
+    let session = state.session.lock().clone();
+    let local = session.user_id == user_id;
+    if local {
+        {
+            let mut history = state.conversation_history.lock();
+            deleted.conversations += !history.is_empty() as usize;
+            history.clear();
+            state.history_store.save(&history)?;
+        }
+        // Journals of interrupted responses hold transcript text too
+        state.history_store.recover_inflight();
+        state.conversations.lock().clear_active()?;
+        run_ids.push(session.run_id.clone());
+    }
+
+    deleted.rolling_summaries = rolling_summary::forget(&run_ids)?;
+    deleted.compactions = compaction::forget(&run_ids)?;
+    for run_id in &run_ids {
+        deleted.attachments += remove_attachments(run_id)?;
+    }
+    deleted.trash_items = trash::purge_user(user_id)?;
+
+    // Derived from every transcript, so rebuilt from the ones left
+    let indexed = EntityIndex::load().count();
+    let history = state.conversation_history.lock().clone();
+    let remaining = entities::rebuild(&state.history_store, &history, &session.run_id)?;
+    deleted.entities = indexed.saturating_sub(remaining);
+    if local {
+        deleted.digest_notes = digest::delete_notes()?;
+    }
+    Ok(())
+}
+
+/// Write a zip of all conversations, memories and settings to `path`
+#[tauri::command]
+pub async fn export_all_data(path: String, state: tauri::State<'_, AppState>) -> Result<ExportSummary, String> {
+    let memories = state.memory_store.lock().get_all(&MemoryFilters::default(), usize::MAX);
+    let path = PathBuf::from(path);
+    let summary = tauri::async_runtime::spawn_blocking(move || export_to(&paths::app_data_dir(), &memories, &path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+    info!(
+        "Exported {} conversation file(s), {} memories and {} settings file(s) to {}",
+        summary.conversation_files, summary.memories, summary.settings_files, summary.path
+    );
+    Ok(summary)
+}
+
+/// Erase everything stored for `user_id` in memory, conversations, the
+/// trash, The Nexus Core and the local logs
+#[tauri::command]
+pub async fn delete_all_user_data(user_id: String, state: tauri::State<'_, AppState>) -> Result<DeletedData, String> {
+    if user_id.trim().is_empty() {
+        return Err("A user id is required".to_string());
+    }
+    if state.generation.is_active() {
+        return Err("Wait for the current response to finish".to_string());
+    }
+
+    let filters = MemoryFilters {
+        user_id: Some(user_id.clone()),
+        ..Default::default()
+    };
+    let mut deleted = {
+        let mut store = state.memory_store.lock();
+        let memories = store.delete_all(&filters);
+        DeletedData {
+            memories,
+            // After the delete, so the versions it recorded go too
+            memory_versions: store.delete_history(&filters),
+            ..Default::default()
+        }
+    };
+    purge_conversations(&state, &user_id, &mut deleted).map_err(|e| format!("{:#}", e))?;
+
+    if let Some(backend) = state.llm.lock().await.as_mut() {
+        deleted.nexus_core = tokio::task::block_in_place(|| backend.delete_user_data(&user_id))
+            .map_err(|e| format!("{:#}", e))?;
+    }
+
+    deleted.log_entries = purge_logs(&logging::log_dir(), &user_id).unwrap_or_else(|e| {
+        warn!("Failed to purge the logs: {:#}", e);
+        0
+    });
+
+    info!(
+        "Deleted data for a user: {} memories ({} earlier versions), {} conversations, {} trash items, {} attachments, {} Nexus Core records, {} log entries",
+        deleted.memories,
+        deleted.memory_versions,
+        deleted.conversations,
+        deleted.trash_items,
+        deleted.attachments,
+        deleted.nexus_core.unwrap_or(0),
+        deleted.log_entries
+    );
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_export_and_purge_logs() {
+        let root = std::env::temp_dir().join(format!("auranexus-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("history/archive")).unwrap();
+        std::fs::write(root.join("history/history.json"), "[]").unwrap();
+        std::fs::write(root.join("history/archive/run.json"), "{}").unwrap();
+        std::fs::write(root.join("settings.toml"), "[history]\n[encryption]\nsalt = \"c2FsdA==\"").unwrap();
+        std::fs::write(root.join("encryption.json"), "{}").unwrap();
+        std::fs::write(root.join("backend.json"), r#"{"remote": {"api_key": "sk-secret", "model": "m"}}"#).unwrap();
+
+        let path = root.join("exports/all.zip");
+        let summary = export_to(&root, &[], &path).unwrap();
+        assert_eq!((summary.conversation_files, summary.memories, summary.settings_files), (2, 0, 2));
+        let mut zip = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<String> = zip.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "history/archive/run.json",
+                "history/history.json",
+                "memories.json",
+                "settings/backend.json",
+                "settings/settings.toml"
+            ]
+        );
+        let mut backend = String::new();
+        zip.by_name("settings/backend.json").unwrap().read_to_string(&mut backend).unwrap();
+        assert!(!backend.contains("sk-secret") && backend.contains("\"model\": \"m\""));
+        let mut settings = String::new();
+        zip.by_name("settings/settings.toml").unwrap().read_to_string(&mut settings).unwrap();
+        assert!(settings.contains("[history]") && !settings.contains("salt"));
+        let mut memories = String::new();
+        zip.by_name("memories.json").unwrap().read_to_string(&mut memories).unwrap();
+        assert_eq!(memories, "[]");
+
+        let logs = root.join("logs");
+        std::fs::create_dir_all(&logs).unwrap();
+        std::fs::write(logs.join("auranexus.log.2026-01-01"), "a user_1\nb\nc user_1\n").unwrap();
+        assert_eq!(purge_logs(&logs, "user_1").unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(logs.join("auranexus.log.2026-01-01")).unwrap(), "b\n");
+        assert_eq!(purge_logs(&root.join("missing"), "user_1").unwrap(), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
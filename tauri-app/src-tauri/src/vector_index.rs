@@ -0,0 +1,547 @@
+// Vector Index Module - HNSW approximate nearest-neighbour search
+//
+// A Hierarchical Navigable Small World graph over unit-length vectors, keyed
+// by memory id. Inserts are incremental; deletes leave a tombstone that is
+// still used for navigation but never returned, and the graph is rebuilt once
+// tombstones outnumber live entries. The index saves to a compact
+// little-endian file so it doesn't have to be rebuilt on every start.
+
+use crate::embeddings::normalize;
+use anyhow::{anyhow, Context, Result};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"ANVI";
+const FORMAT_VERSION: u32 = 1;
+
+/// Tuning knobs for the graph
+#[derive(Debug, Clone, Copy)]
+pub struct HnswParams {
+    /// Links per node on upper layers (twice this on layer 0)
+    pub m: usize,
+    /// Candidate list size while inserting
+    pub ef_construction: usize,
+    /// Minimum candidate list size while searching
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+struct Node {
+    id: String,
+    vector: Vec<f32>,
+    /// Neighbour slots per layer, layer 0 first
+    links: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+/// A node and its distance to the query
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    slot: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.slot.cmp(&other.slot))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// HNSW index from memory id to embedding
+pub struct VectorIndex {
+    dimensions: usize,
+    params: HnswParams,
+    nodes: Vec<Node>,
+    /// Live id -> slot in `nodes`
+    slots: HashMap<String, usize>,
+    entry_point: Option<usize>,
+    deleted: usize,
+    /// xorshift state for level assignment
+    rng: u64,
+}
+
+impl VectorIndex {
+    pub fn new(dimensions: usize) -> Self {
+        Self::with_params(dimensions, HnswParams::default())
+    }
+
+    pub fn with_params(dimensions: usize, params: HnswParams) -> Self {
+        Self {
+            dimensions,
+            params,
+            nodes: Vec::new(),
+            slots: HashMap::new(),
+            entry_point: None,
+            deleted: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Number of live (non-deleted) vectors
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    /// Ids of all live vectors
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.slots.keys().map(String::as_str)
+    }
+
+    /// Insert or replace the vector for `id`
+    ///
+    /// Returns false (and ignores the vector) if its length doesn't match.
+    pub fn insert(&mut self, id: &str, vector: &[f32]) -> bool {
+        if vector.len() != self.dimensions {
+            return false;
+        }
+        self.remove(id);
+
+        let mut vector = vector.to_vec();
+        normalize(&mut vector);
+        let level = self.random_level();
+        let slot = self.nodes.len();
+        self.nodes.push(Node {
+            id: id.to_string(),
+            vector,
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.slots.insert(id.to_string(), slot);
+
+        let Some(entry) = self.entry_point else {
+            self.entry_point = Some(slot);
+            return true;
+        };
+
+        let query = self.nodes[slot].vector.clone();
+        let top = self.nodes[entry].links.len() - 1;
+        let mut entry_points = vec![self.candidate(&query, entry)];
+
+        // Greedy descent through the layers above the new node
+        for layer in (level + 1..=top).rev() {
+            entry_points = self.search_layer(&query, &entry_points, 1, layer);
+        }
+
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, self.params.ef_construction, layer);
+            let max_links = self.max_links(layer);
+            let neighbours: Vec<u32> = found
+                .iter()
+                .filter(|candidate| candidate.slot != slot)
+                .take(max_links)
+                .map(|candidate| candidate.slot as u32)
+                .collect();
+
+            for &neighbour in &neighbours {
+                let neighbour = neighbour as usize;
+                self.nodes[neighbour].links[layer].push(slot as u32);
+                if self.nodes[neighbour].links[layer].len() > max_links {
+                    self.prune(neighbour, layer, max_links);
+                }
+            }
+            self.nodes[slot].links[layer] = neighbours;
+            entry_points = found;
+        }
+
+        if level > top {
+            self.entry_point = Some(slot);
+        }
+        true
+    }
+
+    /// Remove `id`; returns false if it wasn't indexed
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(slot) = self.slots.remove(id) else {
+            return false;
+        };
+        self.nodes[slot].deleted = true;
+        self.deleted += 1;
+
+        if self.slots.is_empty() {
+            *self = Self::with_params(self.dimensions, self.params);
+        } else if self.deleted > self.slots.len() {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// The `k` nearest live vectors to `query` as (id, cosine similarity)
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        let Some(entry) = self.entry_point else {
+            return Vec::new();
+        };
+        if query.len() != self.dimensions || k == 0 {
+            return Vec::new();
+        }
+
+        let mut query = query.to_vec();
+        normalize(&mut query);
+        let mut entry_points = vec![self.candidate(&query, entry)];
+        for layer in (1..self.nodes[entry].links.len()).rev() {
+            entry_points = self.search_layer(&query, &entry_points, 1, layer);
+        }
+
+        // Tombstones take up room in the candidate list, so widen it
+        let ef = (self.params.ef_search.max(k) + self.deleted).min(self.nodes.len());
+        self.search_layer(&query, &entry_points, ef, 0)
+            .into_iter()
+            .filter(|candidate| !self.nodes[candidate.slot].deleted)
+            .take(k)
+            .map(|candidate| (self.nodes[candidate.slot].id.clone(), 1.0 - candidate.distance))
+            .collect()
+    }
+
+    /// Re-insert the live vectors into a fresh graph, dropping tombstones
+    pub fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        let rng = self.rng;
+        *self = Self::with_params(self.dimensions, self.params);
+        self.rng = rng;
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(&node.id, &node.vector);
+        }
+    }
+
+    fn candidate(&self, query: &[f32], slot: usize) -> Candidate {
+        Candidate {
+            distance: distance(query, &self.nodes[slot].vector),
+            slot,
+        }
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.params.m * 2
+        } else {
+            self.params.m
+        }
+    }
+
+    /// Keep only the `max_links` closest neighbours of `slot` on `layer`
+    fn prune(&mut self, slot: usize, layer: usize, max_links: usize) {
+        let vector = &self.nodes[slot].vector;
+        let mut neighbours: Vec<Candidate> = self.nodes[slot].links[layer]
+            .iter()
+            .map(|&neighbour| Candidate {
+                distance: distance(vector, &self.nodes[neighbour as usize].vector),
+                slot: neighbour as usize,
+            })
+            .collect();
+        neighbours.sort();
+        neighbours.truncate(max_links);
+        self.nodes[slot].links[layer] = neighbours.into_iter().map(|c| c.slot as u32).collect();
+    }
+
+    /// Best-first search of one layer; returns up to `ef` nodes, closest first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[Candidate],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().map(|c| c.slot).collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> =
+            entry_points.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Candidate> = entry_points.iter().copied().collect();
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(closest)) = candidates.pop() {
+            let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+            if closest.distance > furthest && found.len() >= ef {
+                break;
+            }
+
+            let Some(links) = self.nodes[closest.slot].links.get(layer) else {
+                continue;
+            };
+            for &neighbour in links {
+                let neighbour = neighbour as usize;
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = self.candidate(query, neighbour);
+                let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+                if found.len() < ef || candidate.distance < furthest {
+                    candidates.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Level for a new node: P(level >= l) = m^-l
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let uniform = ((self.rng >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.params.m.max(2) as f64).ln();
+        ((-uniform.ln() * scale) as usize).min(16)
+    }
+
+    /// Write the index to `path` (atomically, via a temp file)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(std::fs::File::create(&tmp)?);
+            out.write_all(MAGIC)?;
+            for value in [
+                FORMAT_VERSION,
+                self.dimensions as u32,
+                self.params.m as u32,
+                self.params.ef_construction as u32,
+                self.params.ef_search as u32,
+            ] {
+                write_u32(&mut out, value)?;
+            }
+            out.write_all(&self.rng.to_le_bytes())?;
+            write_u32(&mut out, self.entry_point.map_or(u32::MAX, |slot| slot as u32))?;
+            write_u32(&mut out, self.nodes.len() as u32)?;
+
+            for node in &self.nodes {
+                write_u32(&mut out, node.id.len() as u32)?;
+                out.write_all(node.id.as_bytes())?;
+                out.write_all(&[node.deleted as u8])?;
+                for value in &node.vector {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+                write_u32(&mut out, node.links.len() as u32)?;
+                for links in &node.links {
+                    write_u32(&mut out, links.len() as u32)?;
+                    for &link in links {
+                        write_u32(&mut out, link)?;
+                    }
+                }
+            }
+            out.flush()?;
+        }
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to save vector index to {}", path.display()))
+    }
+
+    /// Read an index written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open vector index {}", path.display()))?;
+        let mut input = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != FORMAT_VERSION {
+            return Err(anyhow!("{} is not a vector index", path.display()));
+        }
+        let dimensions = read_u32(&mut input)? as usize;
+        let params = HnswParams {
+            m: read_u32(&mut input)? as usize,
+            ef_construction: read_u32(&mut input)? as usize,
+            ef_search: read_u32(&mut input)? as usize,
+        };
+        let mut index = Self::with_params(dimensions, params);
+        let mut rng = [0u8; 8];
+        input.read_exact(&mut rng)?;
+        index.rng = u64::from_le_bytes(rng);
+        let entry_point = read_u32(&mut input)?;
+        let count = read_u32(&mut input)? as usize;
+
+        for slot in 0..count {
+            let mut id = vec![0u8; read_u32(&mut input)? as usize];
+            input.read_exact(&mut id)?;
+            let id = String::from_utf8(id).context("Corrupt vector index id")?;
+            let mut deleted = [0u8; 1];
+            input.read_exact(&mut deleted)?;
+
+            let mut vector = Vec::with_capacity(dimensions);
+            for _ in 0..dimensions {
+                let mut bytes = [0u8; 4];
+                input.read_exact(&mut bytes)?;
+                vector.push(f32::from_le_bytes(bytes));
+            }
+
+            let layers = read_u32(&mut input)? as usize;
+            let mut links = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let len = read_u32(&mut input)? as usize;
+                let mut layer = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let link = read_u32(&mut input)?;
+                    if link as usize >= count {
+                        return Err(anyhow!("Corrupt vector index link"));
+                    }
+                    layer.push(link);
+                }
+                links.push(layer);
+            }
+
+            let deleted = deleted[0] != 0;
+            if deleted {
+                index.deleted += 1;
+            } else {
+                index.slots.insert(id.clone(), slot);
+            }
+            index.nodes.push(Node {
+                id,
+                vector,
+                links,
+                deleted,
+            });
+        }
+
+        index.entry_point = match entry_point {
+            u32::MAX => None,
+            slot if (slot as usize) < count => Some(slot as usize),
+            _ => return Err(anyhow!("Corrupt vector index entry point")),
+        };
+        Ok(index)
+    }
+}
+
+/// Cosine distance between unit vectors
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+fn write_u32(out: &mut impl Write, value: u32) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(input: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                (0..dimensions)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn brute_force(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let mut query = query.to_vec();
+        normalize(&mut query);
+        let mut scored: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let mut v = v.clone();
+                normalize(&mut v);
+                (i, distance(&query, &v))
+            })
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+        scored.into_iter().take(k).map(|(i, _)| i.to_string()).collect()
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let vectors = random_vectors(600, 16);
+        let mut index = VectorIndex::new(16);
+        for (i, vector) in vectors.iter().enumerate() {
+            assert!(index.insert(&i.to_string(), vector));
+        }
+        assert_eq!(index.len(), 600);
+
+        let queries = random_vectors(620, 16).split_off(600);
+        let mut hits = 0;
+        for query in &queries {
+            let expected = brute_force(&vectors, query, 10);
+            let found: Vec<String> = index.search(query, 10).into_iter().map(|(id, _)| id).collect();
+            hits += found.iter().filter(|id| expected.contains(id)).count();
+        }
+        assert!(hits as f32 / 200.0 >= 0.9, "recall {}", hits as f32 / 200.0);
+    }
+
+    #[test]
+    fn test_delete_and_replace() {
+        let mut index = VectorIndex::new(2);
+        index.insert("a", &[1.0, 0.0]);
+        index.insert("b", &[0.0, 1.0]);
+        assert_eq!(index.search(&[1.0, 0.1], 1)[0].0, "a");
+
+        assert!(index.remove("a"));
+        assert!(!index.remove("a"));
+        assert_eq!(index.search(&[1.0, 0.1], 2).len(), 1);
+
+        // Replacing moves the vector
+        index.insert("b", &[1.0, 0.0]);
+        let results = index.search(&[1.0, 0.0], 5);
+        assert_eq!(results.len(), 1);
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+        assert!(!index.insert("c", &[1.0]));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let vectors = random_vectors(200, 8);
+        let mut index = VectorIndex::new(8);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector);
+        }
+        index.remove("7");
+
+        let path = std::env::temp_dir().join(format!("auranexus_index_{}.bin", std::process::id()));
+        index.save(&path).unwrap();
+        let loaded = VectorIndex::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.len(), 199);
+        assert!(!loaded.contains("7"));
+        assert_eq!(loaded.search(&vectors[3], 5), index.search(&vectors[3], 5));
+    }
+}
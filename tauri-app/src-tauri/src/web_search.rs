@@ -0,0 +1,340 @@
+// Web Search Module - Look things up on the web for a reply
+//
+// A `Tool` the model can call with a query when a message needs information
+// it can't have, such as current events. Three providers: a SearxNG instance
+// (its JSON API), DuckDuckGo (the plain HTML results page, no key needed) or
+// the Brave Search API (needs a subscription key). Results come back as a
+// numbered list of title, URL and snippet that the reply cites by number.
+// With `store_results` on, each result is also kept in the memory store
+// (kind "web_result") so later conversations can retrieve it - except in
+// incognito sessions and private conversations, where nothing is written.
+
+use crate::tools::{Tool, ToolContext};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const USER_AGENT: &str = concat!("AuraNexus/", env!("CARGO_PKG_VERSION"));
+const DUCKDUCKGO_URL: &str = "https://html.duckduckgo.com/html/";
+const BRAVE_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+
+/// Most results a search may ask for
+const MAX_RESULTS: usize = 10;
+const MAX_QUERY_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchProvider {
+    Searxng,
+    DuckDuckGo,
+    Brave,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSearchSettings {
+    pub enabled: bool,
+    pub provider: SearchProvider,
+    /// Base URL of the SearxNG instance, e.g. "http://localhost:8888"
+    pub searxng_url: String,
+    pub brave_api_key: Option<String>,
+    pub max_results: usize,
+    /// Keep results in the memory store
+    pub store_results: bool,
+}
+
+impl Default for WebSearchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: SearchProvider::DuckDuckGo,
+            searxng_url: "http://localhost:8888".to_string(),
+            brave_api_key: None,
+            max_results: 5,
+            store_results: false,
+        }
+    }
+}
+
+impl WebSearchSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_RESULTS).contains(&self.max_results) {
+            return Err(anyhow!("Web search returns 1 to {} results", MAX_RESULTS));
+        }
+        if self.provider == SearchProvider::Searxng && reqwest::Url::parse(&self.searxng_url).is_err() {
+            return Err(anyhow!("Invalid SearxNG URL: {}", self.searxng_url));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Results as numbered sources for the prompt
+pub fn format_results(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| format!("[{}] {} ({})\n{}", i + 1, result.title, result.url, result.snippet))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Results from a SearxNG `format=json` response
+pub fn parse_searxng(body: &Value) -> Vec<SearchResult> {
+    body["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            Some(SearchResult {
+                title: result["title"].as_str()?.trim().to_string(),
+                url: result["url"].as_str()?.to_string(),
+                snippet: result["content"].as_str().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Results from a Brave Search API response
+pub fn parse_brave(body: &Value) -> Vec<SearchResult> {
+    body["web"]["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            Some(SearchResult {
+                title: strip_html(result["title"].as_str()?),
+                url: result["url"].as_str()?.to_string(),
+                snippet: strip_html(result["description"].as_str().unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+/// Results from DuckDuckGo's HTML results page
+pub fn parse_duckduckgo(html: &str) -> Vec<SearchResult> {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static SNIPPET: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| {
+        Regex::new(r#"(?s)<a[^>]*class="result__a"[^>]*href="([^"]*)"[^>]*>(.*?)</a>"#).unwrap()
+    });
+    let snippet = SNIPPET.get_or_init(|| Regex::new(r#"(?s)class="result__snippet"[^>]*>(.*?)</a>"#).unwrap());
+
+    let links: Vec<_> = link.captures_iter(html).collect();
+    let mut results = Vec::new();
+    for (i, captures) in links.iter().enumerate() {
+        // The snippet sits between this link and the next one
+        let start = captures.get(0).map_or(0, |m| m.end());
+        let end = links.get(i + 1).and_then(|next| next.get(0)).map_or(html.len(), |m| m.start());
+        let text = snippet
+            .captures(&html[start..end])
+            .map(|found| strip_html(&found[1]))
+            .unwrap_or_default();
+        let url = duckduckgo_target(&decode_entities(&captures[1]));
+        // Ads link through DuckDuckGo's own click tracker
+        if url.contains("duckduckgo.com/y.js") {
+            continue;
+        }
+        results.push(SearchResult {
+            title: strip_html(&captures[2]),
+            url,
+            snippet: text,
+        });
+    }
+    results
+}
+
+/// The real URL behind a DuckDuckGo redirect ("//duckduckgo.com/l/?uddg=...")
+fn duckduckgo_target(href: &str) -> String {
+    let absolute = if href.starts_with("//") { format!("https:{}", href) } else { href.to_string() };
+    reqwest::Url::parse(&absolute)
+        .ok()
+        .and_then(|url| url.query_pairs().find(|(key, _)| key == "uddg").map(|(_, target)| target.into_owned()))
+        .unwrap_or(absolute)
+}
+
+fn strip_html(text: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    let text = decode_entities(&tag.replace_all(text, ""));
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// The `web_search` tool
+pub struct WebSearch {
+    settings: WebSearchSettings,
+}
+
+impl WebSearch {
+    pub fn new(settings: WebSearchSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Search with the configured provider
+    pub fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .context("Failed to build HTTP client")?;
+        let results = match self.settings.provider {
+            SearchProvider::Searxng => {
+                let url = format!("{}/search", self.settings.searxng_url.trim_end_matches('/'));
+                let body: Value = client
+                    .get(url)
+                    .query(&[("q", query), ("format", "json")])
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .context("SearxNG search failed")?
+                    .json()?;
+                parse_searxng(&body)
+            }
+            SearchProvider::DuckDuckGo => {
+                let html = client
+                    .post(DUCKDUCKGO_URL)
+                    .form(&[("q", query)])
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .context("DuckDuckGo search failed")?
+                    .text()?;
+                parse_duckduckgo(&html)
+            }
+            SearchProvider::Brave => {
+                let key = self
+                    .settings
+                    .brave_api_key
+                    .as_deref()
+                    .filter(|key| !key.is_empty())
+                    .ok_or_else(|| anyhow!("Brave search needs an API key"))?;
+                let count = self.settings.max_results.to_string();
+                let body: Value = client
+                    .get(BRAVE_URL)
+                    .header("X-Subscription-Token", key)
+                    .header("Accept", "application/json")
+                    .query(&[("q", query), ("count", count.as_str())])
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .context("Brave search failed")?
+                    .json()?;
+                parse_brave(&body)
+            }
+        };
+        Ok(results.into_iter().take(self.settings.max_results).collect())
+    }
+
+    /// Keep `results` as memories of this session
+    fn store(&self, query: &str, results: &[SearchResult], context: &ToolContext) {
+        if context.session.incognito || context.private {
+            return;
+        }
+        let (user_id, agent_id, run_id) = context.session.memory_ids();
+        let mut store = context.memory_store.lock();
+        for result in results {
+            let mut metadata = HashMap::new();
+            metadata.insert("kind".to_string(), json!("web_result"));
+            metadata.insert("url".to_string(), json!(result.url));
+            metadata.insert("title".to_string(), json!(result.title));
+            metadata.insert("query".to_string(), json!(query));
+            let content = format!("{}\n{}", result.title, result.snippet);
+            if let Err(e) = store.add(content, user_id.clone(), agent_id.clone(), run_id.clone(), metadata) {
+                warn!("Failed to store web result {}: {}", result.url, e);
+            }
+        }
+    }
+}
+
+impl Tool for WebSearch {
+    fn name(&self) -> &'static str {
+        "web_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search the web for current events, news or facts that may have changed recently"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "query": { "type": "string", "description": "What to search for" } },
+            "required": ["query"]
+        })
+    }
+
+    fn run(&self, arguments: &Value, context: &ToolContext) -> Result<String> {
+        let query = arguments["query"]
+            .as_str()
+            .map(str::trim)
+            .filter(|query| !query.is_empty())
+            .ok_or_else(|| anyhow!("web_search needs a query"))?;
+        let query: String = query.chars().take(MAX_QUERY_CHARS).collect();
+        let results = self.search(&query)?;
+        info!("{} result(s) for {:?}", results.len(), query);
+        if results.is_empty() {
+            return Ok(format!("No results for {:?}.", query));
+        }
+        if self.settings.store_results {
+            self.store(&query, &results, context);
+        }
+        Ok(format_results(&results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_responses() {
+        let html = r#"
+            <div class="result"><a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fexample.com%2Fnews&amp;rut=abc">Big <b>News</b></a>
+            <a class="result__snippet" href="x">It happened &amp; then <b>more</b>.</a></div>
+            <div class="result"><a class="result__a" href="https://duckduckgo.com/y.js?ad=1">Ad</a></div>
+            <div class="result"><a class="result__a" href="https://plain.org/">Plain</a></div>"#;
+        let results = parse_duckduckgo(html);
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    title: "Big News".to_string(),
+                    url: "https://example.com/news".to_string(),
+                    snippet: "It happened & then more.".to_string(),
+                },
+                SearchResult {
+                    title: "Plain".to_string(),
+                    url: "https://plain.org/".to_string(),
+                    snippet: String::new(),
+                },
+            ]
+        );
+        assert_eq!(format_results(&results[..1]), "[1] Big News (https://example.com/news)\nIt happened & then more.");
+
+        let searxng = json!({ "results": [{ "title": "A", "url": "https://a.io", "content": " about a " }, { "url": "no title" }] });
+        assert_eq!(parse_searxng(&searxng).len(), 1);
+        assert_eq!(parse_searxng(&searxng)[0].snippet, "about a");
+
+        let brave = json!({ "web": { "results": [{ "title": "<strong>B</strong>", "url": "https://b.io", "description": "b" }] } });
+        assert_eq!(parse_brave(&brave)[0].title, "B");
+    }
+}
@@ -266,10 +266,18 @@ async function sendMessage() {
     } catch (error) {
         console.error('Send message failed:', error);
         removeMessage(loadingId);
-        addMessage('❌ ' + error, 'error', 'System');
+        addMessage('❌ ' + describeError(error), 'error', 'System');
     }
 }
 
+// Commands reject with {code, message, hint}; older ones with a plain string
+function describeError(error) {
+    if (error && typeof error === 'object' && error.message) {
+        return error.hint ? `${error.message}\n💡 ${error.hint}` : error.message;
+    }
+    return String(error);
+}
+
 // Add message to UI
 function addMessage(text, type, sender) {
     const container = document.getElementById('messagesContainer');